@@ -0,0 +1,51 @@
+//! With the `bundled` feature, list every file of the frontend build as
+//! `include_bytes!` in `$OUT_DIR/bundled.rs`, see `routes::bundled`
+
+use std::{
+    env,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+fn main() {
+    if env::var_os("CARGO_FEATURE_BUNDLED").is_none() {
+        println!("cargo:rerun-if-changed=build.rs");
+        return;
+    }
+
+    let root = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("../frontend/build");
+    println!("cargo:rerun-if-changed={}", root.display());
+    let root = root.canonicalize().unwrap_or_else(|_| {
+        panic!(
+            "`bundled` embeds {}, build the frontend first",
+            root.display()
+        )
+    });
+
+    let mut files = Vec::new();
+    walk(&root, &mut files);
+    files.sort();
+
+    let mut out = String::from("static FILES: &[(&str, &[u8])] = &[\n");
+    for file in files {
+        let path = file.strip_prefix(&root).unwrap().to_string_lossy();
+        let path = path.replace('\\', "/");
+        writeln!(out, "    ({:?}, include_bytes!({:?})),", path, file).unwrap();
+    }
+    out.push_str("];\n");
+
+    let dest = Path::new(&env::var("OUT_DIR").unwrap()).join("bundled.rs");
+    fs::write(dest, out).unwrap();
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            walk(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
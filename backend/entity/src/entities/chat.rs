@@ -1,8 +1,9 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.14
 
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "chat")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -11,10 +12,61 @@ pub struct Model {
     pub model_id: i32,
     #[sea_orm(nullable)]
     pub title: Option<String>,
+    pub reproducible: bool,
+    #[sea_orm(nullable)]
+    pub head_id: Option<i32>,
+    #[sea_orm(nullable)]
+    pub topic: Option<String>,
+    #[sea_orm(nullable)]
+    pub sentiment: Option<crate::ChatSentiment>,
+    #[sea_orm(nullable)]
+    pub task: Option<crate::ChatTask>,
+    #[sea_orm(nullable)]
+    pub tagged_at: Option<i64>,
+    #[sea_orm(nullable)]
+    pub retention_notice_at: Option<i64>,
+    #[sea_orm(nullable, unique)]
+    pub share_token: Option<String>,
+    #[sea_orm(nullable)]
+    pub folder_id: Option<i32>,
+    pub pinned: bool,
+    #[sea_orm(nullable)]
+    pub archived_at: Option<i64>,
+    /// Prompt experiment the chat was sampled into, see `prompt_variant`
+    #[sea_orm(nullable)]
+    pub prompt_variant_id: Option<i32>,
+    /// Instructions of the user for this chat, a template like the built-in prompt
+    #[sea_orm(column_type = "Text", nullable)]
+    pub system_prompt: Option<String>,
+    /// `system_prompt` stands in for the built-in prompt rather than following it
+    pub system_prompt_replace: bool,
+    /// Stands in for the messages of the branch up to `summary_until` once
+    /// the chat grows too long for the model
+    #[sea_orm(column_type = "Text", nullable)]
+    pub summary: Option<String>,
+    #[sea_orm(nullable)]
+    pub summary_until: Option<i32>,
+    /// Persona of the owner the chat talks as, see `persona`
+    #[sea_orm(nullable)]
+    pub persona_id: Option<i32>,
+    /// Only members of the workspace reach the chat, see `utils::workspace`
+    pub workspace_id: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::chat_collection::Entity")]
+    ChatCollection,
+    #[sea_orm(has_many = "super::chat_draft::Entity")]
+    ChatDraft,
+    #[sea_orm(has_many = "super::chat_label::Entity")]
+    ChatLabel,
+    #[sea_orm(has_many = "super::chat_member::Entity")]
+    ChatMember,
+    #[sea_orm(has_many = "super::chat_variable::Entity")]
+    ChatVariable,
+    #[sea_orm(has_one = "super::context_stat::Entity")]
+    ContextStat,
     #[sea_orm(has_many = "super::message::Entity")]
     Message,
     #[sea_orm(
@@ -25,6 +77,8 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     Model,
+    #[sea_orm(has_many = "super::schedule::Entity")]
+    Schedule,
     #[sea_orm(
         belongs_to = "super::user::Entity",
         from = "Column::OwnerId",
@@ -35,6 +89,42 @@ pub enum Relation {
     User,
 }
 
+impl Related<super::chat_collection::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChatCollection.def()
+    }
+}
+
+impl Related<super::chat_draft::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChatDraft.def()
+    }
+}
+
+impl Related<super::chat_label::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChatLabel.def()
+    }
+}
+
+impl Related<super::chat_member::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChatMember.def()
+    }
+}
+
+impl Related<super::chat_variable::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChatVariable.def()
+    }
+}
+
+impl Related<super::context_stat::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ContextStat.def()
+    }
+}
+
 impl Related<super::message::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Message.def()
@@ -47,6 +137,12 @@ impl Related<super::model::Entity> for Entity {
     }
 }
 
+impl Related<super::schedule::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Schedule.def()
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
@@ -7,25 +7,33 @@ use sea_orm::entity::prelude::*;
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
-    pub message_id: i32,
+    pub owner_id: i32,
     pub name: String,
+    /// Sniffed from the content, not the one the client sent
+    pub content_type: String,
+    pub size: i64,
+    #[sea_orm(unique)]
+    pub storage_key: String,
+    pub created_at: i64,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
-    #[sea_orm(
-        belongs_to = "super::message::Entity",
-        from = "Column::MessageId",
-        to = "super::message::Column::Id",
-        on_update = "Cascade",
-        on_delete = "Cascade"
-    )]
-    Message,
+    #[sea_orm(has_many = "super::attachment::Entity")]
+    Attachment,
+    #[sea_orm(has_many = "super::document::Entity")]
+    Document,
 }
 
-impl Related<super::message::Entity> for Entity {
+impl Related<super::attachment::Entity> for Entity {
     fn to() -> RelationDef {
-        Relation::Message.def()
+        Relation::Attachment.def()
+    }
+}
+
+impl Related<super::document::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Document.def()
     }
 }
 
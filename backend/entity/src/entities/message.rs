@@ -1,14 +1,38 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.14
 
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "message")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
     pub chat_id: i32,
     pub kind: crate::MessageKind,
+    #[sea_orm(nullable)]
+    pub generation: Option<String>,
+    pub truncated: bool,
+    pub private: bool,
+    pub created_at: i64,
+    pub tokens: i64,
+    #[sea_orm(nullable)]
+    pub parent_id: Option<i32>,
+    /// Prompt variant the reply was written with
+    #[sea_orm(nullable)]
+    pub prompt_variant_id: Option<i32>,
+    /// Member who sent the message or asked for the reply, None for the owner
+    /// in messages written before chats had members
+    #[sea_orm(nullable)]
+    pub author_id: Option<i32>,
+    /// Set while the reply streams, left set by a crash until the chat is
+    /// opened again
+    pub generating: bool,
+    /// The server stopped while the reply streamed, see `chat/{id}/continue`
+    pub interrupted: bool,
+    /// Rounds of the tool loop, see `message/{id}/trace`
+    #[sea_orm(nullable)]
+    pub trace: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -23,6 +47,10 @@ pub enum Relation {
     Chat,
     #[sea_orm(has_many = "super::chunk::Entity")]
     Chunk,
+    #[sea_orm(has_one = "super::feedback::Entity")]
+    Feedback,
+    #[sea_orm(has_many = "super::link::Entity")]
+    Link,
 }
 
 impl Related<super::chat::Entity> for Entity {
@@ -37,4 +65,16 @@ impl Related<super::chunk::Entity> for Entity {
     }
 }
 
+impl Related<super::feedback::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Feedback.def()
+    }
+}
+
+impl Related<super::link::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Link.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -2,10 +2,61 @@
 
 pub mod prelude;
 
+pub mod api_key;
+pub mod attachment;
+pub mod audit_log;
 pub mod chat;
+pub mod chat_collection;
+pub mod chat_draft;
+pub mod chat_label;
+pub mod chat_member;
+pub mod chat_variable;
 pub mod chunk;
+pub mod collection;
 pub mod config;
+pub mod contact;
+pub mod context_stat;
+pub mod document;
+pub mod document_chunk;
+pub mod email_verification;
+pub mod feedback;
+pub mod file;
+pub mod folder;
+pub mod identity;
+pub mod inbox_message;
+pub mod job;
+pub mod label;
+pub mod link;
+pub mod login_throttle;
+pub mod memory;
 pub mod message;
 pub mod model;
+pub mod password_reset;
+pub mod persona;
+pub mod policy;
+pub mod prompt_template;
+pub mod prompt_template_version;
+pub mod prompt_variant;
+pub mod price;
+pub mod push_subscription;
+pub mod quota;
+pub mod recovery_code;
+pub mod reply_stat;
+pub mod routing_rule;
+pub mod schedule;
+pub mod search_term;
+pub mod session;
+pub mod spend;
+pub mod spend_cap;
+pub mod sync_change;
 pub mod tool;
+pub mod totp;
+pub mod trash;
+pub mod usage;
 pub mod user;
+pub mod user_spend;
+pub mod webhook;
+pub mod workspace;
+pub mod workspace_credential;
+pub mod workspace_member;
+pub mod workspace_model;
@@ -14,6 +14,20 @@ pub struct Model {
 pub enum Relation {
     #[sea_orm(has_many = "super::chat::Entity")]
     Chat,
+    #[sea_orm(has_many = "super::persona::Entity")]
+    Persona,
+    #[sea_orm(has_many = "super::prompt_variant::Entity")]
+    PromptVariant,
+    #[sea_orm(has_many = "super::routing_rule::Entity")]
+    RoutingRule,
+    #[sea_orm(has_many = "super::schedule::Entity")]
+    Schedule,
+    #[sea_orm(has_one = "super::spend_cap::Entity")]
+    SpendCap,
+    #[sea_orm(has_many = "super::user_spend::Entity")]
+    UserSpend,
+    #[sea_orm(has_many = "super::workspace_model::Entity")]
+    WorkspaceModel,
 }
 
 impl Related<super::chat::Entity> for Entity {
@@ -22,4 +36,46 @@ impl Related<super::chat::Entity> for Entity {
     }
 }
 
+impl Related<super::persona::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Persona.def()
+    }
+}
+
+impl Related<super::prompt_variant::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PromptVariant.def()
+    }
+}
+
+impl Related<super::routing_rule::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RoutingRule.def()
+    }
+}
+
+impl Related<super::schedule::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Schedule.def()
+    }
+}
+
+impl Related<super::spend_cap::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SpendCap.def()
+    }
+}
+
+impl Related<super::user_spend::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserSpend.def()
+    }
+}
+
+impl Related<super::workspace_model::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::WorkspaceModel.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
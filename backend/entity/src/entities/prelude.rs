@@ -1,9 +1,60 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.14
 
+pub use super::api_key::Entity as ApiKey;
+pub use super::attachment::Entity as Attachment;
+pub use super::audit_log::Entity as AuditLog;
 pub use super::chat::Entity as Chat;
+pub use super::chat_collection::Entity as ChatCollection;
+pub use super::chat_draft::Entity as ChatDraft;
+pub use super::chat_label::Entity as ChatLabel;
+pub use super::chat_member::Entity as ChatMember;
+pub use super::chat_variable::Entity as ChatVariable;
 pub use super::chunk::Entity as Chunk;
+pub use super::collection::Entity as Collection;
 pub use super::config::Entity as Config;
+pub use super::contact::Entity as Contact;
+pub use super::context_stat::Entity as ContextStat;
+pub use super::document::Entity as Document;
+pub use super::document_chunk::Entity as DocumentChunk;
+pub use super::email_verification::Entity as EmailVerification;
+pub use super::feedback::Entity as Feedback;
+pub use super::file::Entity as File;
+pub use super::folder::Entity as Folder;
+pub use super::identity::Entity as Identity;
+pub use super::inbox_message::Entity as InboxMessage;
+pub use super::job::Entity as Job;
+pub use super::label::Entity as Label;
+pub use super::link::Entity as Link;
+pub use super::login_throttle::Entity as LoginThrottle;
+pub use super::memory::Entity as Memory;
 pub use super::message::Entity as Message;
 pub use super::model::Entity as Model;
+pub use super::password_reset::Entity as PasswordReset;
+pub use super::persona::Entity as Persona;
+pub use super::policy::Entity as Policy;
+pub use super::prompt_template::Entity as PromptTemplate;
+pub use super::prompt_template_version::Entity as PromptTemplateVersion;
+pub use super::prompt_variant::Entity as PromptVariant;
+pub use super::price::Entity as Price;
+pub use super::push_subscription::Entity as PushSubscription;
+pub use super::quota::Entity as Quota;
+pub use super::recovery_code::Entity as RecoveryCode;
+pub use super::reply_stat::Entity as ReplyStat;
+pub use super::routing_rule::Entity as RoutingRule;
+pub use super::schedule::Entity as Schedule;
+pub use super::search_term::Entity as SearchTerm;
+pub use super::session::Entity as Session;
+pub use super::spend::Entity as Spend;
+pub use super::spend_cap::Entity as SpendCap;
+pub use super::sync_change::Entity as SyncChange;
 pub use super::tool::Entity as Tool;
+pub use super::totp::Entity as Totp;
+pub use super::trash::Entity as Trash;
+pub use super::usage::Entity as Usage;
 pub use super::user::Entity as User;
+pub use super::user_spend::Entity as UserSpend;
+pub use super::webhook::Entity as Webhook;
+pub use super::workspace::Entity as Workspace;
+pub use super::workspace_credential::Entity as WorkspaceCredential;
+pub use super::workspace_member::Entity as WorkspaceMember;
+pub use super::workspace_model::Entity as WorkspaceModel;
@@ -10,12 +10,92 @@ pub struct Model {
     pub name: String,
     pub password: String,
     pub preference: crate::UserPreference,
+    pub demo_expires_at: Option<i64>,
+    pub email: Option<String>,
+    pub role: crate::UserRole,
+    pub email_verified: bool,
+    pub purge_at: Option<i64>,
+    /// Raised by every write of `preference`, see `user/preferences`
+    pub preference_version: i32,
+    /// Secret of the feed of the user at `/calendar/{token}`, None until asked for
+    #[sea_orm(nullable, unique)]
+    pub calendar_token: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::api_key::Entity")]
+    ApiKey,
     #[sea_orm(has_many = "super::chat::Entity")]
     Chat,
+    #[sea_orm(has_many = "super::chat_draft::Entity")]
+    ChatDraft,
+    #[sea_orm(has_many = "super::chat_member::Entity")]
+    ChatMember,
+    #[sea_orm(has_many = "super::collection::Entity")]
+    Collection,
+    #[sea_orm(has_many = "super::contact::Entity")]
+    Contact,
+    #[sea_orm(has_many = "super::document::Entity")]
+    Document,
+    #[sea_orm(has_many = "super::email_verification::Entity")]
+    EmailVerification,
+    #[sea_orm(has_many = "super::feedback::Entity")]
+    Feedback,
+    #[sea_orm(has_many = "super::folder::Entity")]
+    Folder,
+    #[sea_orm(has_many = "super::identity::Entity")]
+    Identity,
+    #[sea_orm(has_many = "super::inbox_message::Entity")]
+    InboxMessage,
+    #[sea_orm(has_many = "super::label::Entity")]
+    Label,
+    #[sea_orm(has_many = "super::memory::Entity")]
+    Memory,
+    #[sea_orm(has_many = "super::password_reset::Entity")]
+    PasswordReset,
+    #[sea_orm(has_many = "super::persona::Entity")]
+    Persona,
+    #[sea_orm(has_many = "super::policy::Entity")]
+    Policy,
+    #[sea_orm(has_many = "super::prompt_template::Entity")]
+    PromptTemplate,
+    #[sea_orm(has_many = "super::prompt_template_version::Entity")]
+    PromptTemplateVersion,
+    #[sea_orm(has_many = "super::push_subscription::Entity")]
+    PushSubscription,
+    #[sea_orm(has_one = "super::quota::Entity")]
+    Quota,
+    #[sea_orm(has_many = "super::recovery_code::Entity")]
+    RecoveryCode,
+    #[sea_orm(has_many = "super::routing_rule::Entity")]
+    RoutingRule,
+    #[sea_orm(has_many = "super::schedule::Entity")]
+    Schedule,
+    #[sea_orm(has_many = "super::search_term::Entity")]
+    SearchTerm,
+    #[sea_orm(has_many = "super::session::Entity")]
+    Session,
+    #[sea_orm(has_one = "super::spend_cap::Entity")]
+    SpendCap,
+    #[sea_orm(has_one = "super::totp::Entity")]
+    Totp,
+    #[sea_orm(has_many = "super::trash::Entity")]
+    Trash,
+    #[sea_orm(has_many = "super::usage::Entity")]
+    Usage,
+    #[sea_orm(has_many = "super::user_spend::Entity")]
+    UserSpend,
+    #[sea_orm(has_many = "super::webhook::Entity")]
+    Webhook,
+    #[sea_orm(has_many = "super::workspace_member::Entity")]
+    WorkspaceMember,
+}
+
+impl Related<super::api_key::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ApiKey.def()
+    }
 }
 
 impl Related<super::chat::Entity> for Entity {
@@ -24,4 +104,190 @@ impl Related<super::chat::Entity> for Entity {
     }
 }
 
+impl Related<super::chat_draft::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChatDraft.def()
+    }
+}
+
+impl Related<super::chat_member::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChatMember.def()
+    }
+}
+
+impl Related<super::collection::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Collection.def()
+    }
+}
+
+impl Related<super::contact::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Contact.def()
+    }
+}
+
+impl Related<super::document::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Document.def()
+    }
+}
+
+impl Related<super::email_verification::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::EmailVerification.def()
+    }
+}
+
+impl Related<super::feedback::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Feedback.def()
+    }
+}
+
+impl Related<super::folder::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Folder.def()
+    }
+}
+
+impl Related<super::identity::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Identity.def()
+    }
+}
+
+impl Related<super::inbox_message::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::InboxMessage.def()
+    }
+}
+
+impl Related<super::label::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Label.def()
+    }
+}
+
+impl Related<super::memory::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Memory.def()
+    }
+}
+
+impl Related<super::password_reset::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PasswordReset.def()
+    }
+}
+
+impl Related<super::persona::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Persona.def()
+    }
+}
+
+impl Related<super::policy::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Policy.def()
+    }
+}
+
+impl Related<super::prompt_template::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PromptTemplate.def()
+    }
+}
+
+impl Related<super::prompt_template_version::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PromptTemplateVersion.def()
+    }
+}
+
+impl Related<super::push_subscription::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PushSubscription.def()
+    }
+}
+
+impl Related<super::quota::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Quota.def()
+    }
+}
+
+impl Related<super::recovery_code::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RecoveryCode.def()
+    }
+}
+
+impl Related<super::routing_rule::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RoutingRule.def()
+    }
+}
+
+impl Related<super::schedule::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Schedule.def()
+    }
+}
+
+impl Related<super::search_term::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SearchTerm.def()
+    }
+}
+
+impl Related<super::session::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Session.def()
+    }
+}
+
+impl Related<super::spend_cap::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SpendCap.def()
+    }
+}
+
+impl Related<super::totp::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Totp.def()
+    }
+}
+
+impl Related<super::trash::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Trash.def()
+    }
+}
+
+impl Related<super::usage::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Usage.def()
+    }
+}
+
+impl Related<super::user_spend::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserSpend.def()
+    }
+}
+
+impl Related<super::webhook::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Webhook.def()
+    }
+}
+
+impl Related<super::workspace_member::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::WorkspaceMember.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -1,17 +1,20 @@
 use anyhow::Result;
+use schemars::JsonSchema;
 use sea_orm::{DeriveActiveEnum, FromJsonQueryResult, entity::prelude::*};
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
 #[sea_orm(rs_type = "i32", db_type = "Integer")]
 pub enum MessageKind {
     Hidden = 0,
     User = 1,
     Assistant = 2,
+    /// provenance note, e.g. where a merged chat continue from
+    Marker = 3,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
 #[sea_orm(rs_type = "i32", db_type = "Integer")]
 pub enum ChunkKind {
     Text = 0,
@@ -19,7 +22,260 @@ pub enum ChunkKind {
     ToolCall = 2,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
+/// Kind of the entity a tool result refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "i32", db_type = "Integer")]
+pub enum LinkKind {
+    Mail = 0,
+    MailThread = 1,
+    Place = 2,
+    /// a chunk retrieved from the knowledge base, the id is the document's
+    Document = 3,
+}
+
+/// Where the ingestion of a document is at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "i32", db_type = "Integer")]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentStatus {
+    #[sea_orm(num_value = 0)]
+    Pending,
+    #[sea_orm(num_value = 1)]
+    Ready,
+    #[sea_orm(num_value = 2)]
+    Failed,
+}
+
+/// Security relevant event of `audit_log`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "i32", db_type = "Integer")]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum AuditKind {
+    #[sea_orm(num_value = 0)]
+    Login,
+    /// wrong password or code, the user is the account tried if it exists
+    #[sea_orm(num_value = 1)]
+    LoginFail,
+    /// an access token renewed with a refresh token
+    #[sea_orm(num_value = 2)]
+    Refresh,
+    #[sea_orm(num_value = 3)]
+    ToolCall,
+    /// settings, models, prompts, quotas or tools changed by an admin
+    #[sea_orm(num_value = 4)]
+    Config,
+    /// a user created or deleted by an admin
+    #[sea_orm(num_value = 5)]
+    User,
+    /// a message or reply flagged or blocked, see `moderation`
+    #[sea_orm(num_value = 6)]
+    Moderation,
+}
+
+/// What a row of `job` does, its payload tells on what
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "i32", db_type = "Integer")]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    #[sea_orm(num_value = 0)]
+    Title,
+    /// chunk and embed a document of the knowledge base
+    #[sea_orm(num_value = 1)]
+    Ingest,
+    /// a run of a scheduled task
+    #[sea_orm(num_value = 2)]
+    Schedule,
+    #[sea_orm(num_value = 3)]
+    Mail,
+    #[sea_orm(num_value = 4)]
+    Retention,
+    #[sea_orm(num_value = 5)]
+    TrashPurge,
+    #[sea_orm(num_value = 6)]
+    AccountPurge,
+    /// a delivery to a webhook of a user
+    #[sea_orm(num_value = 7)]
+    Webhook,
+    /// drop the stats of replies older than `STATS_DAYS`
+    #[sea_orm(num_value = 8)]
+    StatsPurge,
+    /// a Web Push message to a browser of a user
+    #[sea_orm(num_value = 9)]
+    Push,
+    /// a zip of everything kept about a user, see `user/takeout`
+    #[sea_orm(num_value = 10)]
+    Takeout,
+}
+
+/// Where a row of `job` is at, done jobs are deleted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "i32", db_type = "Integer")]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// waiting for its time, either new or retried after a failure
+    #[sea_orm(num_value = 0)]
+    Pending,
+    #[sea_orm(num_value = 1)]
+    Running,
+    /// gave up after the last attempt, kept for an admin to retry
+    #[sea_orm(num_value = 2)]
+    Failed,
+}
+
+/// What a row of `sync_change` refers to, written by triggers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "i32", db_type = "Integer")]
+pub enum SyncEntity {
+    Chat = 0,
+    Message = 1,
+    /// id is the user's
+    Preference = 2,
+}
+
+/// How a term of a user change the search, see `search_term`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "i32", db_type = "Integer")]
+pub enum SearchTermKind {
+    /// messages containing it rank higher
+    Boost = 0,
+    /// removed from messages before they are indexed, e.g. a mail signature
+    Ignore = 1,
+}
+
+/// Admins manage models, settings and other users
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[sea_orm(rs_type = "i32", db_type = "Integer")]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum UserRole {
+    #[sea_orm(num_value = 0)]
+    User,
+    #[sea_orm(num_value = 1)]
+    Admin,
+}
+
+/// Mood of the user over a chat, guessed by the tagger
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[sea_orm(rs_type = "i32", db_type = "Integer")]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum ChatSentiment {
+    #[sea_orm(num_value = 0)]
+    Negative,
+    #[sea_orm(num_value = 1)]
+    Neutral,
+    #[sea_orm(num_value = 2)]
+    Positive,
+}
+
+/// Part of a member in a shared chat, the creator of the chat is always an owner
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[sea_orm(rs_type = "i32", db_type = "Integer")]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum ChatMemberRole {
+    /// manage the members and the settings of the chat
+    #[sea_orm(num_value = 0)]
+    Owner,
+    /// read and send messages
+    #[sea_orm(num_value = 1)]
+    Member,
+}
+
+/// Rating of a reply by its user
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[sea_orm(rs_type = "i32", db_type = "Integer")]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum FeedbackRating {
+    Down = -1,
+    Up = 1,
+}
+
+/// What the user wanted from a chat, guessed by the tagger
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[sea_orm(rs_type = "i32", db_type = "Integer")]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum ChatTask {
+    #[sea_orm(num_value = 0)]
+    Other,
+    #[sea_orm(num_value = 1)]
+    Question,
+    #[sea_orm(num_value = 2)]
+    Coding,
+    #[sea_orm(num_value = 3)]
+    Writing,
+    #[sea_orm(num_value = 4)]
+    Analysis,
+    #[sea_orm(num_value = 5)]
+    Planning,
+    #[sea_orm(num_value = 6)]
+    Chitchat,
+}
+
+#[derive(
+    Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult, JsonSchema,
+)]
 #[typeshare]
 pub struct UserPreference {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -28,6 +284,121 @@ pub struct UserPreference {
     pub locale: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub submit_on_enter: Option<String>,
+    /// Voice replies are read aloud with, one of the TTS provider's
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+    /// Speed of the voice, e.g. `1.25`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voice_speed: Option<String>,
+    /// Days idle chats are kept, e.g. `30`, only when sooner than the policy
+    /// of the instance; empty to follow it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retention_days: Option<String>,
+    /// IANA name of the time zone of the user, e.g. `Asia/Taipei`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(length(max = 64))]
+    pub timezone: Option<String>,
+    /// Minutes the time zone is ahead of UTC now, as the browser reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = -720, max = 840))]
+    pub utc_offset: Option<i32>,
+    /// Model new chats start with, before the default of the instance
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_model_id: Option<i32>,
+    /// Push a notification when a reply ends while away, true when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify_replies: Option<bool>,
+    /// Push a notification when a scheduled task runs, true when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify_schedules: Option<bool>,
+}
+
+/// What an API key can do, on top of identifying its user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyScope {
+    /// Read chats, messages and models
+    Read,
+    /// Create and write chats and messages
+    Chat,
+    /// Let the model call tools in messages sent with the key
+    Tools,
+    /// Only counts and usage, never the content of chats, for dashboards
+    Stats,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
+pub struct ApiKeyScopes(pub Vec<ApiKeyScope>);
+
+impl ApiKeyScopes {
+    pub fn has(&self, scope: ApiKeyScope) -> bool {
+        self.0.contains(&scope)
+    }
+}
+
+/// What a webhook is called on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    /// A reply of the assistant ended, in a chat of the user
+    MessageCompleted,
+    /// A scheduled task of the user sent its prompt, or could not
+    ScheduleRun,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
+pub struct WebhookEvents(pub Vec<WebhookEvent>);
+
+impl WebhookEvents {
+    pub fn has(&self, event: WebhookEvent) -> bool {
+        self.0.contains(&event)
+    }
+}
+
+/// Names of tools, e.g. the ones a persona keeps
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
+pub struct ToolNames(pub Vec<String>);
+
+/// Background feature an `inbox_message` comes from, see `notify::inbox`
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[sea_orm(rs_type = "i32", db_type = "Integer")]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum InboxSource {
+    /// a run of a scheduled task
+    #[sea_orm(num_value = 0)]
+    Schedule,
+    /// spending went past a monthly cap or most of it
+    #[sea_orm(num_value = 1)]
+    SpendCap,
+}
+
+/// When a routing rule of a user applies to a message
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult, JsonSchema)]
+#[typeshare]
+#[serde(tag = "t", content = "c", rename_all = "snake_case")]
+pub enum RoutingCondition {
+    /// The message has a fenced code block or lines of code
+    Code,
+    /// The prompt, the history included, is estimated past this many tokens
+    Tokens(i32),
+    /// The last reply of the chat called at least this many tools
+    ToolCalls(i32),
+    /// The message contains this text, ignoring case
+    Keyword(String),
 }
 
 impl crate::entities::model::Model {
@@ -62,9 +433,11 @@ pub struct ModelCapability {
     pub audio: bool,
     #[serde(default)]
     pub ocr: OcrEngine,
+    #[serde(default)]
+    pub reasoning: bool,
 }
 
-#[derive(Debug, Clone, Deserialize, Default, Serialize)]
+#[derive(Debug, Clone, Deserialize, Default, Serialize, JsonSchema)]
 #[typeshare]
 pub struct ModelParameter {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -75,29 +448,32 @@ pub struct ModelParameter {
     pub top_k: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>,
+    /// sampling seed, only honored by some providers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u32>,
 }
 
 impl ModelParameter {
     fn check(&self) -> Result<(), &'static str> {
-        if let Some(temperature) = self.temperature {
-            if temperature < 0.0 || temperature > 1.0 {
-                return Err("Temperature must be between 0.0 and 1.0");
-            }
+        if let Some(temperature) = self.temperature
+            && (!(0.0..=1.0).contains(&temperature))
+        {
+            return Err("Temperature must be between 0.0 and 1.0");
         }
-        if let Some(repeat_penalty) = self.repeat_penalty {
-            if repeat_penalty < 1.0 || repeat_penalty > 2.0 {
-                return Err("Repeat penalty must be between 1.0 and 2.0");
-            }
+        if let Some(repeat_penalty) = self.repeat_penalty
+            && (!(1.0..=2.0).contains(&repeat_penalty))
+        {
+            return Err("Repeat penalty must be between 1.0 and 2.0");
         }
-        if let Some(top_k) = self.top_k {
-            if top_k < 0 || top_k > 100 {
-                return Err("Top K must be between 0 and 100");
-            }
+        if let Some(top_k) = self.top_k
+            && (!(0..=100).contains(&top_k))
+        {
+            return Err("Top K must be between 0 and 100");
         }
-        if let Some(top_p) = self.top_p {
-            if top_p < 0.0 || top_p > 1.0 {
-                return Err("Top P must be between 0.0 and 1.0");
-            }
+        if let Some(top_p) = self.top_p
+            && (!(0.0..=1.0).contains(&top_p))
+        {
+            return Err("Top P must be between 0.0 and 1.0");
         }
         Ok(())
     }
@@ -126,6 +502,86 @@ impl ModelConfig {
     }
 }
 
+/// What produced an assistant message, pinned in reproducible chats
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[typeshare]
+pub struct Generation {
+    pub model_id: String,
+    pub parameter: ModelParameter,
+    pub prompt_version: String,
+    /// Version of the admin template the prompt was built on, see
+    /// `prompt_template_version`, None for the built-in prompt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template_version: Option<i32>,
+    /// unix seconds, 0 for messages generated before it was recorded
+    #[serde(default)]
+    pub created_at: u32,
+}
+
+impl crate::message::Model {
+    pub fn get_generation(&self) -> Option<Generation> {
+        serde_json::from_str(self.generation.as_ref()?).ok()
+    }
+}
+
+/// How an assistant message ran the tool loop, kept for `message/{id}/trace`
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[typeshare]
+pub struct Trace {
+    pub rounds: Vec<TraceRound>,
+    /// Milliseconds from the start of the reply to its end
+    pub duration_ms: u32,
+    /// The limit the loop ran into, the model then answered without tools
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stopped: Option<String>,
+}
+
+/// A completion of the model, then the tools it called
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[typeshare]
+pub struct TraceRound {
+    /// As reported by the provider
+    pub model: String,
+    /// Milliseconds from the start of the reply
+    pub start_ms: u32,
+    pub duration_ms: u32,
+    pub tokens: u32,
+    /// In USD
+    pub cost: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<String>,
+    pub tools: Vec<TraceTool>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[typeshare]
+pub struct TraceTool {
+    pub name: String,
+    /// Milliseconds from the start of the reply
+    pub start_ms: u32,
+    /// Waiting for the user to answer included
+    pub duration_ms: u32,
+    pub status: TraceStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum TraceStatus {
+    Done,
+    Failed,
+    /// Not called, the limits were reached or the tool is unknown
+    Skipped,
+    /// The reply was halted during the call
+    Halted,
+}
+
+impl crate::message::Model {
+    pub fn get_trace(&self) -> Option<Trace> {
+        serde_json::from_str(self.trace.as_ref()?).ok()
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ToolCall {
     pub id: String,
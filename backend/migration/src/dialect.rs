@@ -0,0 +1,85 @@
+//! What SQLite and PostgreSQL write differently, for the raw SQL of migrations
+//!
+//! Triggers keep the SQLite form of `(name, event, statements)`, statements
+//! reading `NEW` and `OLD`; PostgreSQL runs them from a function of the same
+//! name
+
+use sea_orm_migration::{
+    prelude::*,
+    schema,
+    sea_orm::{ConnectionTrait, DbBackend},
+};
+
+pub fn is_postgres(manager: &SchemaManager) -> bool {
+    manager.get_database_backend() == DbBackend::Postgres
+}
+
+/// Row trigger running `statements` on `event`, e.g. `AFTER INSERT ON chat`
+pub async fn create_trigger(
+    manager: &SchemaManager<'_>,
+    name: &str,
+    event: &str,
+    statements: &str,
+) -> Result<(), DbErr> {
+    let conn = manager.get_connection();
+    if is_postgres(manager) {
+        conn.execute_unprepared(&format!(
+            "CREATE OR REPLACE FUNCTION {name}() RETURNS trigger LANGUAGE plpgsql AS $$
+            BEGIN {statements} RETURN NULL; END $$"
+        ))
+        .await?;
+        conn.execute_unprepared(&format!(
+            "CREATE OR REPLACE TRIGGER {name} {event} FOR EACH ROW EXECUTE FUNCTION {name}()"
+        ))
+        .await?;
+    } else {
+        conn.execute_unprepared(&format!(
+            "CREATE TRIGGER IF NOT EXISTS {name} {event} BEGIN {statements} END;"
+        ))
+        .await?;
+    }
+    Ok(())
+}
+
+pub async fn drop_trigger(
+    manager: &SchemaManager<'_>,
+    name: &str,
+    event: &str,
+) -> Result<(), DbErr> {
+    let conn = manager.get_connection();
+    if is_postgres(manager) {
+        let table = event.rsplit(" ON ").next().unwrap_or_default();
+        conn.execute_unprepared(&format!("DROP TRIGGER IF EXISTS {name} ON {table}"))
+            .await?;
+        conn.execute_unprepared(&format!("DROP FUNCTION IF EXISTS {name}()"))
+            .await?;
+    } else {
+        conn.execute_unprepared(&format!("DROP TRIGGER IF EXISTS {name};"))
+            .await?;
+    }
+    Ok(())
+}
+
+/// Text of `key` in the JSON object of the text column `column`
+pub fn json_text(manager: &SchemaManager, column: &str, key: &str) -> String {
+    match is_postgres(manager) {
+        true => format!("({column}::jsonb ->> '{key}')"),
+        false => format!("json_extract({column}, '$.{key}')"),
+    }
+}
+
+/// Column of a JSON entity field, `jsonb` as sea-orm decodes them from on
+/// PostgreSQL and text on SQLite
+pub fn json<T: IntoIden>(manager: &SchemaManager, column: T) -> ColumnDef {
+    match is_postgres(manager) {
+        true => schema::json_binary(column),
+        false => schema::string(column),
+    }
+}
+
+pub fn json_null<T: IntoIden>(manager: &SchemaManager, column: T) -> ColumnDef {
+    match is_postgres(manager) {
+        true => schema::json_binary_null(column),
+        false => schema::string_null(column),
+    }
+}
@@ -1,14 +1,142 @@
 pub use sea_orm_migration::prelude::*;
 use sea_orm_migration::sea_orm::Database;
 
+mod dialect;
+
 mod m20250908_082005_create_table;
+mod m20261014_000001_reproducible;
+mod m20261014_000002_link;
+mod m20261014_000003_truncated;
+mod m20261014_000004_private;
+mod m20261014_000005_policy;
+mod m20261014_000006_demo;
+mod m20261015_000001_price;
+mod m20261015_000002_session;
+mod m20261015_000003_identity;
+mod m20261015_000004_totp;
+mod m20261015_000005_password_reset;
+mod m20261015_000006_api_key;
+mod m20261015_000007_role;
+mod m20261015_000008_device;
+mod m20261015_000009_login_throttle;
+mod m20261015_000010_email_verification;
+mod m20261015_000011_account_purge;
+mod m20261015_000012_chat_variable;
+mod m20261015_000013_sync;
+mod m20261015_000014_message_search;
+mod m20261015_000015_message_created_at;
+mod m20261015_000016_context_stat;
+mod m20261015_000017_branch;
+mod m20261015_000018_chat_tag;
+mod m20261015_000019_search_term;
+mod m20261015_000020_retention;
+mod m20261015_000021_share;
+mod m20261015_000022_folder;
+mod m20261015_000023_message_tokens;
+mod m20261015_000024_pin;
+mod m20261015_000025_spend;
+mod m20261015_000026_trash;
+mod m20261015_000027_attachment;
+mod m20261015_000028_prompt_variant;
+mod m20261015_000029_feedback;
+mod m20261015_000030_system_prompt;
+mod m20261015_000031_chat_member;
+mod m20261015_000032_usage;
+mod m20261015_000033_schedule;
+mod m20261015_000034_compaction;
+mod m20261015_000035_prompt_template;
+mod m20261015_000036_prompt_template_version;
+mod m20261015_000037_persona;
+mod m20261015_000038_document;
+mod m20261015_000039_memory;
+mod m20261015_000040_document_chunk_label;
+mod m20261015_000041_collection;
+mod m20261015_000042_audit_log;
+mod m20261015_000043_workspace;
+mod m20261015_000044_job;
+mod m20261015_000045_webhook;
+mod m20261015_000046_reply_stat;
+mod m20261015_000047_push_subscription;
+mod m20261015_000048_spend_cap;
+mod m20261015_000049_chat_draft;
+mod m20261015_000050_routing_rule;
+mod m20261015_000051_contact;
+mod m20261015_000052_message_generating;
+mod m20261015_000053_inbox_message;
+mod m20261015_000054_message_trace;
+mod m20261015_000055_user_preference_version;
+mod m20261015_000056_calendar_token;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20250908_082005_create_table::Migration)]
+        vec![
+            Box::new(m20250908_082005_create_table::Migration),
+            Box::new(m20261014_000001_reproducible::Migration),
+            Box::new(m20261014_000002_link::Migration),
+            Box::new(m20261014_000003_truncated::Migration),
+            Box::new(m20261014_000004_private::Migration),
+            Box::new(m20261014_000005_policy::Migration),
+            Box::new(m20261014_000006_demo::Migration),
+            Box::new(m20261015_000001_price::Migration),
+            Box::new(m20261015_000002_session::Migration),
+            Box::new(m20261015_000003_identity::Migration),
+            Box::new(m20261015_000004_totp::Migration),
+            Box::new(m20261015_000005_password_reset::Migration),
+            Box::new(m20261015_000006_api_key::Migration),
+            Box::new(m20261015_000007_role::Migration),
+            Box::new(m20261015_000008_device::Migration),
+            Box::new(m20261015_000009_login_throttle::Migration),
+            Box::new(m20261015_000010_email_verification::Migration),
+            Box::new(m20261015_000011_account_purge::Migration),
+            Box::new(m20261015_000012_chat_variable::Migration),
+            Box::new(m20261015_000013_sync::Migration),
+            Box::new(m20261015_000014_message_search::Migration),
+            Box::new(m20261015_000015_message_created_at::Migration),
+            Box::new(m20261015_000016_context_stat::Migration),
+            Box::new(m20261015_000017_branch::Migration),
+            Box::new(m20261015_000018_chat_tag::Migration),
+            Box::new(m20261015_000019_search_term::Migration),
+            Box::new(m20261015_000020_retention::Migration),
+            Box::new(m20261015_000021_share::Migration),
+            Box::new(m20261015_000022_folder::Migration),
+            Box::new(m20261015_000023_message_tokens::Migration),
+            Box::new(m20261015_000024_pin::Migration),
+            Box::new(m20261015_000025_spend::Migration),
+            Box::new(m20261015_000026_trash::Migration),
+            Box::new(m20261015_000027_attachment::Migration),
+            Box::new(m20261015_000028_prompt_variant::Migration),
+            Box::new(m20261015_000029_feedback::Migration),
+            Box::new(m20261015_000030_system_prompt::Migration),
+            Box::new(m20261015_000031_chat_member::Migration),
+            Box::new(m20261015_000032_usage::Migration),
+            Box::new(m20261015_000033_schedule::Migration),
+            Box::new(m20261015_000034_compaction::Migration),
+            Box::new(m20261015_000035_prompt_template::Migration),
+            Box::new(m20261015_000036_prompt_template_version::Migration),
+            Box::new(m20261015_000037_persona::Migration),
+            Box::new(m20261015_000038_document::Migration),
+            Box::new(m20261015_000039_memory::Migration),
+            Box::new(m20261015_000040_document_chunk_label::Migration),
+            Box::new(m20261015_000041_collection::Migration),
+            Box::new(m20261015_000042_audit_log::Migration),
+            Box::new(m20261015_000043_workspace::Migration),
+            Box::new(m20261015_000044_job::Migration),
+            Box::new(m20261015_000045_webhook::Migration),
+            Box::new(m20261015_000046_reply_stat::Migration),
+            Box::new(m20261015_000047_push_subscription::Migration),
+            Box::new(m20261015_000048_spend_cap::Migration),
+            Box::new(m20261015_000049_chat_draft::Migration),
+            Box::new(m20261015_000050_routing_rule::Migration),
+            Box::new(m20261015_000051_contact::Migration),
+            Box::new(m20261015_000052_message_generating::Migration),
+            Box::new(m20261015_000053_inbox_message::Migration),
+            Box::new(m20261015_000054_message_trace::Migration),
+            Box::new(m20261015_000055_user_preference_version::Migration),
+            Box::new(m20261015_000056_calendar_token::Migration),
+        ]
     }
 }
 
@@ -16,14 +16,6 @@ pub enum Model {
     Config,
 }
 
-#[derive(DeriveIden)]
-pub enum File {
-    Table,
-    Id,
-    MessageId,
-    Name,
-}
-
 #[derive(DeriveIden)]
 pub enum Config {
     Table,
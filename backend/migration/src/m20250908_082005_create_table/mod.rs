@@ -30,8 +30,8 @@ impl MigrationTrait for Migration {
         model::Migration.up(manager).await?;
 
         chat::Migration.up(manager).await?;
-        chunk::Migration.up(manager).await?;
         message::Migration.up(manager).await?;
+        chunk::Migration.up(manager).await?;
 
         default::Migration.up(manager).await?;
         Ok(())
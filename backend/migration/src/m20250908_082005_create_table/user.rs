@@ -1,6 +1,7 @@
 use sea_orm_migration::{prelude::*, schema::*};
 
 use super::entity::*;
+use crate::dialect;
 
 #[derive(DeriveMigrationName)]
 pub struct Migration;
@@ -16,7 +17,7 @@ impl MigrationTrait for Migration {
                     .col(pk_auto(User::Id))
                     .col(string(User::Name))
                     .col(string(User::Password))
-                    .col(string(User::Preference).default("{}"))
+                    .col(dialect::json(manager, User::Preference).default("{}"))
                     .to_owned(),
             )
             .await?;
@@ -0,0 +1,56 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(Link::Table)
+                    .col(pk_auto(Link::Id))
+                    .col(integer(Link::MessageId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-link-message_id-message")
+                            .from(Link::Table, Link::MessageId)
+                            .to(Message::Table, Message::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(integer(Link::Kind))
+                    .col(string(Link::EntityId))
+                    .col(string(Link::Label))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Link::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Link {
+    Table,
+    Id,
+    MessageId,
+    Kind,
+    EntityId,
+    Label,
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    Id,
+}
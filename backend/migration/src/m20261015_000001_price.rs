@@ -0,0 +1,53 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(Price::Table)
+                    .col(pk_auto(Price::Id))
+                    .col(string(Price::ModelId))
+                    .col(double(Price::Prompt))
+                    .col(double(Price::Completion))
+                    .col(big_integer(Price::EffectiveAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx-price-model_id-effective_at")
+                    .table(Price::Table)
+                    .col(Price::ModelId)
+                    .col(Price::EffectiveAt)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Price::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Price {
+    Table,
+    Id,
+    ModelId,
+    Prompt,
+    Completion,
+    EffectiveAt,
+}
@@ -0,0 +1,84 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(Totp::Table)
+                    .col(integer(Totp::UserId).primary_key())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-totp-user_id-user")
+                            .from(Totp::Table, Totp::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(string(Totp::Secret))
+                    .col(big_integer_null(Totp::EnabledAt))
+                    .col(big_integer_null(Totp::LastStep))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(RecoveryCode::Table)
+                    .col(pk_auto(RecoveryCode::Id))
+                    .col(integer(RecoveryCode::UserId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-recovery_code-user_id-user")
+                            .from(RecoveryCode::Table, RecoveryCode::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(string(RecoveryCode::CodeHash))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RecoveryCode::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Totp::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Totp {
+    Table,
+    UserId,
+    Secret,
+    EnabledAt,
+    LastStep,
+}
+
+#[derive(DeriveIden)]
+enum RecoveryCode {
+    Table,
+    Id,
+    UserId,
+    CodeHash,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
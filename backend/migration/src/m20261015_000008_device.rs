@@ -0,0 +1,48 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // sqlite only add one column per statement
+        for column in [
+            string_null(Session::Label),
+            string_null(Session::Ip),
+            big_integer_null(Session::LastSeenAt),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Session::Table)
+                        .add_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [Session::Label, Session::Ip, Session::LastSeenAt] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Session::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Session {
+    Table,
+    Label,
+    Ip,
+    LastSeenAt,
+}
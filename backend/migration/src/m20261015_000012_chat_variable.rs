@@ -0,0 +1,56 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(ChatVariable::Table)
+                    .col(integer(ChatVariable::ChatId))
+                    .col(string(ChatVariable::Key))
+                    .col(text(ChatVariable::Value))
+                    .primary_key(
+                        Index::create()
+                            .col(ChatVariable::ChatId)
+                            .col(ChatVariable::Key),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-chat_variable-chat_id-chat")
+                            .from(ChatVariable::Table, ChatVariable::ChatId)
+                            .to(Chat::Table, Chat::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ChatVariable::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum ChatVariable {
+    Table,
+    ChatId,
+    Key,
+    Value,
+}
+
+#[derive(DeriveIden)]
+enum Chat {
+    Table,
+    Id,
+}
@@ -0,0 +1,194 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::dialect;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Entities of `sync_change`, keep in sync with `entity::SyncEntity`
+const CHAT: i32 = 0;
+const MESSAGE: i32 = 1;
+const PREFERENCE: i32 = 2;
+
+/// Statements of a trigger recording a change of `entity` with id `id` for
+/// the user `user`, selected from `from`. An entity keeps only its latest
+/// change, left as is when `from` select nothing
+fn record(entity: i32, id: &str, user: &str, from: &str, deleted: bool) -> String {
+    format!(
+        "DELETE FROM sync_change WHERE entity = {entity} AND entity_id = {id}
+            AND EXISTS (SELECT 1 {from});
+        INSERT INTO sync_change (user_id, entity, entity_id, deleted)
+        SELECT {user}, {entity}, {id}, {deleted} {from};",
+        deleted = match deleted {
+            true => "TRUE",
+            false => "FALSE",
+        },
+    )
+}
+
+/// (name, event, statements)
+fn triggers() -> Vec<(&'static str, &'static str, String)> {
+    let message = |row: &str| format!("FROM chat WHERE chat.id = {}.chat_id", row);
+    let chunk = |row: &str| {
+        format!(
+            "FROM message JOIN chat ON chat.id = message.chat_id WHERE message.id = {}.message_id",
+            row
+        )
+    };
+    vec![
+        (
+            "sync_chat_insert",
+            "AFTER INSERT ON chat",
+            record(CHAT, "NEW.id", "NEW.owner_id", "", false),
+        ),
+        (
+            "sync_chat_update",
+            "AFTER UPDATE ON chat",
+            record(CHAT, "NEW.id", "NEW.owner_id", "", false),
+        ),
+        (
+            "sync_chat_delete",
+            "AFTER DELETE ON chat",
+            record(CHAT, "OLD.id", "OLD.owner_id", "", true),
+        ),
+        (
+            "sync_message_insert",
+            "AFTER INSERT ON message",
+            record(MESSAGE, "NEW.id", "chat.owner_id", &message("NEW"), false),
+        ),
+        (
+            "sync_message_update",
+            "AFTER UPDATE ON message",
+            record(MESSAGE, "NEW.id", "chat.owner_id", &message("NEW"), false),
+        ),
+        // nothing is recorded once the chat is gone, its tombstone covers the messages
+        (
+            "sync_message_delete",
+            "AFTER DELETE ON message",
+            record(MESSAGE, "OLD.id", "chat.owner_id", &message("OLD"), true),
+        ),
+        // the content of a message is in its chunks
+        (
+            "sync_chunk_insert",
+            "AFTER INSERT ON chunk",
+            record(
+                MESSAGE,
+                "NEW.message_id",
+                "chat.owner_id",
+                &chunk("NEW"),
+                false,
+            ),
+        ),
+        (
+            "sync_chunk_update",
+            "AFTER UPDATE ON chunk",
+            record(
+                MESSAGE,
+                "NEW.message_id",
+                "chat.owner_id",
+                &chunk("NEW"),
+                false,
+            ),
+        ),
+        (
+            "sync_chunk_delete",
+            "AFTER DELETE ON chunk",
+            record(
+                MESSAGE,
+                "OLD.message_id",
+                "chat.owner_id",
+                &chunk("OLD"),
+                false,
+            ),
+        ),
+        (
+            "sync_preference_update",
+            "AFTER UPDATE OF preference ON \"user\"",
+            record(PREFERENCE, "NEW.id", "NEW.id", "", false),
+        ),
+    ]
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // no foreign key, deleting a user cascade to chats whose triggers
+        // still record tombstones for them
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(SyncChange::Table)
+                    .col(pk_auto(SyncChange::Id))
+                    .col(integer(SyncChange::UserId))
+                    .col(integer(SyncChange::Entity))
+                    .col(integer(SyncChange::EntityId))
+                    .col(boolean(SyncChange::Deleted))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-sync_change-user_id-id")
+                    .table(SyncChange::Table)
+                    .col(SyncChange::UserId)
+                    .col(SyncChange::Id)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-sync_change-entity-entity_id")
+                    .table(SyncChange::Table)
+                    .col(SyncChange::Entity)
+                    .col(SyncChange::EntityId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // entities from before the triggers are changes since the beginning
+        let conn = manager.get_connection();
+        for select in [
+            format!("SELECT owner_id, {CHAT}, id, FALSE FROM chat"),
+            format!(
+                "SELECT chat.owner_id, {MESSAGE}, message.id, FALSE
+                FROM message JOIN chat ON chat.id = message.chat_id"
+            ),
+            format!("SELECT id, {PREFERENCE}, id, FALSE FROM \"user\""),
+        ] {
+            conn.execute_unprepared(&format!(
+                "INSERT INTO sync_change (user_id, entity, entity_id, deleted) {}",
+                select
+            ))
+            .await?;
+        }
+
+        // triggers catch every write, including cascades and background tasks
+        for (name, event, statements) in triggers() {
+            dialect::create_trigger(manager, name, event, &statements).await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (name, event, _) in triggers() {
+            dialect::drop_trigger(manager, name, event).await?;
+        }
+        manager
+            .drop_table(Table::drop().table(SyncChange::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum SyncChange {
+    Table,
+    Id,
+    UserId,
+    Entity,
+    EntityId,
+    Deleted,
+}
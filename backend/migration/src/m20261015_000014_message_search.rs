@@ -0,0 +1,85 @@
+use sea_orm_migration::prelude::*;
+
+use crate::dialect;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// `ChunkKind::Text`, reasoning and tool calls are not searched
+const TEXT: i32 = 0;
+
+/// (name, event, statements), rows of `message_fts` share the ids of chunks
+fn triggers() -> Vec<(&'static str, &'static str, String)> {
+    let insert = format!(
+        "INSERT INTO message_fts (rowid, content, message_id)
+        SELECT NEW.id, NEW.content, NEW.message_id WHERE NEW.kind = {TEXT};"
+    );
+    let delete = "DELETE FROM message_fts WHERE rowid = OLD.id;";
+    vec![
+        (
+            "message_fts_insert",
+            "AFTER INSERT ON chunk",
+            insert.clone(),
+        ),
+        (
+            "message_fts_update",
+            "AFTER UPDATE ON chunk",
+            format!("{} {}", delete, insert),
+        ),
+        (
+            "message_fts_delete",
+            "AFTER DELETE ON chunk",
+            delete.to_owned(),
+        ),
+    ]
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let conn = manager.get_connection();
+        // trigram match substrings, words of CJK text are not separated by spaces
+        if dialect::is_postgres(manager) {
+            // a plain table under the column names of SQLite, searched with
+            // ILIKE through a trigram index
+            conn.execute_unprepared("CREATE EXTENSION IF NOT EXISTS pg_trgm")
+                .await?;
+            conn.execute_unprepared(
+                "CREATE TABLE IF NOT EXISTS message_fts
+                (rowid integer PRIMARY KEY, content text NOT NULL, message_id integer NOT NULL)",
+            )
+            .await?;
+            conn.execute_unprepared(
+                "CREATE INDEX IF NOT EXISTS \"idx-message_fts-content\"
+                ON message_fts USING gin (content gin_trgm_ops)",
+            )
+            .await?;
+        } else {
+            conn.execute_unprepared(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS message_fts
+                USING fts5(content, message_id UNINDEXED, tokenize = 'trigram')",
+            )
+            .await?;
+        }
+        conn.execute_unprepared(&format!(
+            "INSERT INTO message_fts (rowid, content, message_id)
+            SELECT id, content, message_id FROM chunk WHERE kind = {TEXT}"
+        ))
+        .await?;
+        for (name, event, statements) in triggers() {
+            dialect::create_trigger(manager, name, event, &statements).await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (name, event, _) in triggers() {
+            dialect::drop_trigger(manager, name, event).await?;
+        }
+        manager
+            .get_connection()
+            .execute_unprepared("DROP TABLE IF EXISTS message_fts")
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::dialect;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .add_column(big_integer(Message::CreatedAt).default(0))
+                    .to_owned(),
+            )
+            .await?;
+        // replies recorded when they were generated, other messages stay unknown
+        let created_at = dialect::json_text(manager, "generation", "created_at");
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                "UPDATE message SET created_at = COALESCE(CAST({created_at} AS bigint), 0)
+                WHERE generation IS NOT NULL"
+            ))
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .drop_column(Message::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    CreatedAt,
+}
@@ -0,0 +1,61 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(ContextStat::Table)
+                    .col(integer(ContextStat::ChatId).primary_key())
+                    .col(integer(ContextStat::Completions))
+                    .col(integer(ContextStat::Truncations))
+                    .col(big_integer(ContextStat::LastPromptTokens))
+                    .col(big_integer(ContextStat::MaxPromptTokens))
+                    .col(big_integer_null(ContextStat::ContextLength))
+                    .col(integer(ContextStat::Streak))
+                    .col(big_integer(ContextStat::UpdatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-context_stat-chat_id-chat")
+                            .from(ContextStat::Table, ContextStat::ChatId)
+                            .to(Chat::Table, Chat::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ContextStat::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum ContextStat {
+    Table,
+    ChatId,
+    Completions,
+    Truncations,
+    LastPromptTokens,
+    MaxPromptTokens,
+    ContextLength,
+    Streak,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Chat {
+    Table,
+    Id,
+}
@@ -0,0 +1,64 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // sqlite add one column per statement
+        for col in [
+            string_null(Chat::Topic),
+            integer_null(Chat::Sentiment),
+            integer_null(Chat::Task),
+            big_integer_null(Chat::TaggedAt),
+        ] {
+            manager
+                .alter_table(Table::alter().table(Chat::Table).add_column(col).to_owned())
+                .await?;
+        }
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-chat-owner_id-topic")
+                    .table(Chat::Table)
+                    .col(Chat::OwnerId)
+                    .col(Chat::Topic)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-chat-owner_id-topic")
+                    .table(Chat::Table)
+                    .to_owned(),
+            )
+            .await?;
+        for col in [Chat::Topic, Chat::Sentiment, Chat::Task, Chat::TaggedAt] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Chat::Table)
+                        .drop_column(col)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Chat {
+    Table,
+    OwnerId,
+    Topic,
+    Sentiment,
+    Task,
+    TaggedAt,
+}
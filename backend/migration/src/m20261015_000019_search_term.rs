@@ -0,0 +1,181 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::dialect;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// `ChunkKind::Text` and `ChunkKind::ToolCall`, only the result of a call is
+/// searched
+const TEXT: i32 = 0;
+const TOOL_CALL: i32 = 2;
+/// `SearchTermKind::Ignore`
+const IGNORE: i32 = 1;
+/// Ignored terms of a user applied when indexing, triggers cannot loop over
+/// rows so each one is a nested `replace`
+const MAX_IGNORED: usize = 10;
+
+/// What `message_fts` index for each chunk, with the ignored terms of the owner
+/// removed
+fn document(manager: &SchemaManager) -> String {
+    let result = dialect::json_text(manager, "chunk.content", "content");
+    let mut content = format!("CASE chunk.kind WHEN {TEXT} THEN chunk.content ELSE {result} END");
+    for offset in 0..MAX_IGNORED {
+        content = format!(
+            "replace({content}, COALESCE((SELECT term FROM search_term
+            WHERE search_term.user_id = chat.owner_id AND search_term.kind = {IGNORE}
+            ORDER BY search_term.id LIMIT 1 OFFSET {offset}), ''), '')"
+        );
+    }
+    let create = match dialect::is_postgres(manager) {
+        true => "CREATE OR REPLACE VIEW",
+        false => "CREATE VIEW IF NOT EXISTS",
+    };
+    format!(
+        "{create} search_document AS
+        SELECT chunk.id AS id, chunk.message_id AS message_id, chat.owner_id AS owner_id,
+            {content} AS content
+        FROM chunk
+        JOIN message ON message.id = chunk.message_id
+        JOIN chat ON chat.id = message.chat_id
+        WHERE chunk.kind = {TEXT} OR (chunk.kind = {TOOL_CALL} AND json_valid(chunk.content))"
+    )
+}
+
+/// (name, event, statements), replacing those of `message_search`
+fn triggers() -> Vec<(&'static str, &'static str, String)> {
+    let insert = "INSERT INTO message_fts (rowid, content, message_id)
+        SELECT id, content, message_id FROM search_document WHERE id = NEW.id;";
+    let delete = "DELETE FROM message_fts WHERE rowid = OLD.id;";
+    vec![
+        (
+            "message_fts_insert",
+            "AFTER INSERT ON chunk",
+            insert.to_owned(),
+        ),
+        (
+            "message_fts_update",
+            "AFTER UPDATE ON chunk",
+            format!("{} {}", delete, insert),
+        ),
+    ]
+}
+
+/// Triggers of `message_search`, restored by `down`
+fn previous_triggers() -> Vec<(&'static str, &'static str, String)> {
+    let insert = format!(
+        "INSERT INTO message_fts (rowid, content, message_id)
+        SELECT NEW.id, NEW.content, NEW.message_id WHERE NEW.kind = {TEXT};"
+    );
+    let delete = "DELETE FROM message_fts WHERE rowid = OLD.id;";
+    vec![
+        (
+            "message_fts_insert",
+            "AFTER INSERT ON chunk",
+            insert.clone(),
+        ),
+        (
+            "message_fts_update",
+            "AFTER UPDATE ON chunk",
+            format!("{} {}", delete, insert),
+        ),
+    ]
+}
+
+async fn replace_triggers(
+    manager: &SchemaManager<'_>,
+    triggers: Vec<(&'static str, &'static str, String)>,
+) -> Result<(), DbErr> {
+    for (name, event, statements) in triggers {
+        dialect::drop_trigger(manager, name, event).await?;
+        dialect::create_trigger(manager, name, event, &statements).await?;
+    }
+    Ok(())
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(SearchTerm::Table)
+                    .col(pk_auto(SearchTerm::Id))
+                    .col(integer(SearchTerm::UserId))
+                    .col(integer(SearchTerm::Kind))
+                    .col(string(SearchTerm::Term))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-search_term-user_id-user")
+                            .from(SearchTerm::Table, SearchTerm::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-search_term-user_id")
+                    .table(SearchTerm::Table)
+                    .col(SearchTerm::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        let conn = manager.get_connection();
+        if dialect::is_postgres(manager) {
+            // built into SQLite
+            conn.execute_unprepared(
+                "CREATE OR REPLACE FUNCTION json_valid(doc text) RETURNS boolean
+                LANGUAGE plpgsql IMMUTABLE AS $$
+                BEGIN PERFORM doc::jsonb; RETURN TRUE;
+                EXCEPTION WHEN others THEN RETURN FALSE; END $$",
+            )
+            .await?;
+        }
+        conn.execute_unprepared(&document(manager)).await?;
+        replace_triggers(manager, triggers()).await?;
+        // tool results were not indexed before
+        conn.execute_unprepared("DELETE FROM message_fts").await?;
+        conn.execute_unprepared(
+            "INSERT INTO message_fts (rowid, content, message_id)
+            SELECT id, content, message_id FROM search_document",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let conn = manager.get_connection();
+        replace_triggers(manager, previous_triggers()).await?;
+        conn.execute_unprepared("DROP VIEW IF EXISTS search_document")
+            .await?;
+        conn.execute_unprepared(&format!(
+            "DELETE FROM message_fts WHERE rowid NOT IN (SELECT id FROM chunk WHERE kind = {TEXT})"
+        ))
+        .await?;
+        manager
+            .drop_table(Table::drop().table(SearchTerm::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum SearchTerm {
+    Table,
+    Id,
+    UserId,
+    Kind,
+    Term,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
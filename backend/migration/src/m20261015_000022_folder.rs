@@ -0,0 +1,197 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(Folder::Table)
+                    .col(pk_auto(Folder::Id))
+                    .col(integer(Folder::OwnerId))
+                    .col(string(Folder::Name))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-folder-owner_id-user")
+                            .from(Folder::Table, Folder::OwnerId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-folder-owner_id-name")
+                    .table(Folder::Table)
+                    .col(Folder::OwnerId)
+                    .col(Folder::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(Label::Table)
+                    .col(pk_auto(Label::Id))
+                    .col(integer(Label::OwnerId))
+                    .col(string(Label::Name))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-label-owner_id-user")
+                            .from(Label::Table, Label::OwnerId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-label-owner_id-name")
+                    .table(Label::Table)
+                    .col(Label::OwnerId)
+                    .col(Label::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(ChatLabel::Table)
+                    .col(integer(ChatLabel::ChatId))
+                    .col(integer(ChatLabel::LabelId))
+                    .primary_key(
+                        Index::create()
+                            .col(ChatLabel::ChatId)
+                            .col(ChatLabel::LabelId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-chat_label-chat_id-chat")
+                            .from(ChatLabel::Table, ChatLabel::ChatId)
+                            .to(Chat::Table, Chat::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-chat_label-label_id-label")
+                            .from(ChatLabel::Table, ChatLabel::LabelId)
+                            .to(Label::Table, Label::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-chat_label-label_id")
+                    .table(ChatLabel::Table)
+                    .col(ChatLabel::LabelId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // no foreign key, deleting a folder move its chats out of it
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Chat::Table)
+                    .add_column(integer_null(Chat::FolderId))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-chat-folder_id")
+                    .table(Chat::Table)
+                    .col(Chat::FolderId)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-chat-folder_id")
+                    .table(Chat::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Chat::Table)
+                    .drop_column(Chat::FolderId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(ChatLabel::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Label::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Folder::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Folder {
+    Table,
+    Id,
+    OwnerId,
+    Name,
+}
+
+#[derive(DeriveIden)]
+enum Label {
+    Table,
+    Id,
+    OwnerId,
+    Name,
+}
+
+#[derive(DeriveIden)]
+enum ChatLabel {
+    Table,
+    ChatId,
+    LabelId,
+}
+
+#[derive(DeriveIden)]
+enum Chat {
+    Table,
+    Id,
+    FolderId,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
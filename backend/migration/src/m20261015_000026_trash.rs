@@ -0,0 +1,78 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // no foreign key to the chat, it is deleted while its rows are here
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(Trash::Table)
+                    .col(pk_auto(Trash::Id))
+                    .col(integer(Trash::OwnerId))
+                    .col(integer(Trash::ChatId))
+                    .col(integer_null(Trash::MessageId))
+                    .col(string_null(Trash::Title))
+                    .col(big_integer(Trash::DeletedAt))
+                    .col(text(Trash::Snapshot))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-trash-owner_id-user")
+                            .from(Trash::Table, Trash::OwnerId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-trash-owner_id")
+                    .table(Trash::Table)
+                    .col(Trash::OwnerId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-trash-deleted_at")
+                    .table(Trash::Table)
+                    .col(Trash::DeletedAt)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Trash::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Trash {
+    Table,
+    Id,
+    OwnerId,
+    ChatId,
+    MessageId,
+    Title,
+    DeletedAt,
+    Snapshot,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
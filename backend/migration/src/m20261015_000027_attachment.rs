@@ -0,0 +1,102 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // no foreign key to the owner, the sweep remove the stored objects of
+        // purged accounts with their rows
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(File::Table)
+                    .col(pk_auto(File::Id))
+                    .col(integer(File::OwnerId))
+                    .col(string(File::Name))
+                    .col(string(File::ContentType))
+                    .col(big_integer(File::Size))
+                    .col(string_uniq(File::StorageKey))
+                    .col(big_integer(File::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-file-owner_id")
+                    .table(File::Table)
+                    .col(File::OwnerId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // no foreign key to the message, messages in the trash keep their files
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(Attachment::Table)
+                    .col(integer(Attachment::MessageId))
+                    .col(integer(Attachment::FileId))
+                    .col(integer(Attachment::ChatId))
+                    .primary_key(
+                        Index::create()
+                            .col(Attachment::MessageId)
+                            .col(Attachment::FileId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-attachment-file_id-file")
+                            .from(Attachment::Table, Attachment::FileId)
+                            .to(File::Table, File::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-attachment-file_id")
+                    .table(Attachment::Table)
+                    .col(Attachment::FileId)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Attachment::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(File::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum File {
+    Table,
+    Id,
+    OwnerId,
+    Name,
+    ContentType,
+    Size,
+    StorageKey,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Attachment {
+    Table,
+    MessageId,
+    FileId,
+    ChatId,
+}
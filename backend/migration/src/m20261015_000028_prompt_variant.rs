@@ -0,0 +1,130 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // a null template is the built-in prompt, the control of an experiment
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(PromptVariant::Table)
+                    .col(pk_auto(PromptVariant::Id))
+                    .col(integer(PromptVariant::ModelId))
+                    .col(string(PromptVariant::Name))
+                    .col(text_null(PromptVariant::Template))
+                    .col(integer(PromptVariant::Weight).default(1))
+                    .col(big_integer(PromptVariant::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-prompt_variant-model_id-model")
+                            .from(PromptVariant::Table, PromptVariant::ModelId)
+                            .to(Model::Table, Model::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-prompt_variant-model_id")
+                    .table(PromptVariant::Table)
+                    .col(PromptVariant::ModelId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // sampled once per chat, replies keep the variant they were written with
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Chat::Table)
+                    .add_column(integer_null(Chat::PromptVariantId))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .add_column(integer_null(Message::PromptVariantId))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-message-prompt_variant_id")
+                    .table(Message::Table)
+                    .col(Message::PromptVariantId)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-message-prompt_variant_id")
+                    .table(Message::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .drop_column(Message::PromptVariantId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Chat::Table)
+                    .drop_column(Chat::PromptVariantId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(PromptVariant::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum PromptVariant {
+    Table,
+    Id,
+    ModelId,
+    Name,
+    Template,
+    Weight,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Model {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Chat {
+    Table,
+    PromptVariantId,
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    PromptVariantId,
+}
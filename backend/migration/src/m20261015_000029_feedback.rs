@@ -0,0 +1,79 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // one rating per reply, rating it again replace it
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(Feedback::Table)
+                    .col(integer(Feedback::MessageId).primary_key())
+                    .col(integer(Feedback::UserId))
+                    .col(integer(Feedback::Rating))
+                    .col(text_null(Feedback::Comment))
+                    .col(big_integer(Feedback::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-feedback-message_id-message")
+                            .from(Feedback::Table, Feedback::MessageId)
+                            .to(Message::Table, Message::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-feedback-user_id-user")
+                            .from(Feedback::Table, Feedback::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-feedback-created_at")
+                    .table(Feedback::Table)
+                    .col(Feedback::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Feedback::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Feedback {
+    Table,
+    MessageId,
+    UserId,
+    Rating,
+    Comment,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
@@ -0,0 +1,105 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // the owner of the chat is not listed, `chat.owner_id` stays the creator
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(ChatMember::Table)
+                    .col(integer(ChatMember::ChatId))
+                    .col(integer(ChatMember::UserId))
+                    .col(integer(ChatMember::Role))
+                    .col(big_integer(ChatMember::JoinedAt))
+                    .primary_key(
+                        Index::create()
+                            .col(ChatMember::ChatId)
+                            .col(ChatMember::UserId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-chat_member-chat_id-chat")
+                            .from(ChatMember::Table, ChatMember::ChatId)
+                            .to(Chat::Table, Chat::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-chat_member-user_id-user")
+                            .from(ChatMember::Table, ChatMember::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-chat_member-user_id")
+                    .table(ChatMember::Table)
+                    .col(ChatMember::UserId)
+                    .to_owned(),
+            )
+            .await?;
+        // None for messages written before, by the owner
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .add_column(integer_null(Message::AuthorId))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .drop_column(Message::AuthorId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(ChatMember::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum ChatMember {
+    Table,
+    ChatId,
+    UserId,
+    Role,
+    JoinedAt,
+}
+
+#[derive(DeriveIden)]
+enum Chat {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    AuthorId,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
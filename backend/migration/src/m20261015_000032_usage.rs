@@ -0,0 +1,96 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // counted apart from the messages, deleting a chat give nothing back
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(Usage::Table)
+                    .col(integer(Usage::UserId))
+                    .col(big_integer(Usage::Day))
+                    .col(integer(Usage::Messages).default(0))
+                    .col(big_integer(Usage::Tokens).default(0))
+                    .col(integer(Usage::ToolCalls).default(0))
+                    .primary_key(Index::create().col(Usage::UserId).col(Usage::Day))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-usage-user_id-user")
+                            .from(Usage::Table, Usage::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        // a null limit follow the env
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(Quota::Table)
+                    .col(integer(Quota::UserId).primary_key())
+                    .col(integer_null(Quota::DailyMessages))
+                    .col(big_integer_null(Quota::DailyTokens))
+                    .col(integer_null(Quota::DailyToolCalls))
+                    .col(integer_null(Quota::MonthlyMessages))
+                    .col(big_integer_null(Quota::MonthlyTokens))
+                    .col(integer_null(Quota::MonthlyToolCalls))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-quota-user_id-user")
+                            .from(Quota::Table, Quota::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Quota::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Usage::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Usage {
+    Table,
+    UserId,
+    Day,
+    Messages,
+    Tokens,
+    ToolCalls,
+}
+
+#[derive(DeriveIden)]
+enum Quota {
+    Table,
+    UserId,
+    DailyMessages,
+    DailyTokens,
+    DailyToolCalls,
+    MonthlyMessages,
+    MonthlyTokens,
+    MonthlyToolCalls,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
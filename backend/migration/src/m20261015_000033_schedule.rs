@@ -0,0 +1,109 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(Schedule::Table)
+                    .col(pk_auto(Schedule::Id))
+                    .col(integer(Schedule::OwnerId))
+                    .col(integer(Schedule::ModelId))
+                    // runs post in it, a new one is created once it is deleted
+                    .col(integer_null(Schedule::ChatId))
+                    .col(string(Schedule::Name))
+                    .col(text(Schedule::Prompt))
+                    .col(string(Schedule::Cron))
+                    .col(integer(Schedule::UtcOffset).default(0))
+                    .col(boolean(Schedule::Enabled).default(true))
+                    .col(big_integer_null(Schedule::NextRunAt))
+                    .col(big_integer_null(Schedule::LastRunAt))
+                    .col(text_null(Schedule::LastError))
+                    .col(big_integer(Schedule::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-schedule-owner_id-user")
+                            .from(Schedule::Table, Schedule::OwnerId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-schedule-model_id-model")
+                            .from(Schedule::Table, Schedule::ModelId)
+                            .to(Model::Table, Model::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-schedule-chat_id-chat")
+                            .from(Schedule::Table, Schedule::ChatId)
+                            .to(Chat::Table, Chat::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-schedule-next_run_at")
+                    .table(Schedule::Table)
+                    .col(Schedule::NextRunAt)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Schedule::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Schedule {
+    Table,
+    Id,
+    OwnerId,
+    ModelId,
+    ChatId,
+    Name,
+    Prompt,
+    Cron,
+    UtcOffset,
+    Enabled,
+    NextRunAt,
+    LastRunAt,
+    LastError,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Model {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Chat {
+    Table,
+    Id,
+}
@@ -0,0 +1,68 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(PromptTemplate::Table)
+                    .col(pk_auto(PromptTemplate::Id))
+                    // one of the built-in prompts, see `prompts::NAMES`
+                    .col(string(PromptTemplate::Name))
+                    // null for every locale without a template of its own
+                    .col(string_null(PromptTemplate::Locale))
+                    .col(text(PromptTemplate::Content))
+                    .col(integer_null(PromptTemplate::AuthorId))
+                    .col(big_integer(PromptTemplate::UpdatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-prompt_template-author_id-user")
+                            .from(PromptTemplate::Table, PromptTemplate::AuthorId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-prompt_template-name")
+                    .table(PromptTemplate::Table)
+                    .col(PromptTemplate::Name)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PromptTemplate::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum PromptTemplate {
+    Table,
+    Id,
+    Name,
+    Locale,
+    Content,
+    AuthorId,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
@@ -0,0 +1,114 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(PromptTemplateVersion::Table)
+                    .col(pk_auto(PromptTemplateVersion::Id))
+                    .col(integer(PromptTemplateVersion::TemplateId))
+                    .col(text(PromptTemplateVersion::Content))
+                    .col(integer_null(PromptTemplateVersion::AuthorId))
+                    .col(big_integer(PromptTemplateVersion::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-prompt_template_version-template_id-prompt_template")
+                            .from(
+                                PromptTemplateVersion::Table,
+                                PromptTemplateVersion::TemplateId,
+                            )
+                            .to(PromptTemplate::Table, PromptTemplate::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-prompt_template_version-author_id-user")
+                            .from(
+                                PromptTemplateVersion::Table,
+                                PromptTemplateVersion::AuthorId,
+                            )
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-prompt_template_version-template_id")
+                    .table(PromptTemplateVersion::Table)
+                    .col(PromptTemplateVersion::TemplateId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PromptTemplate::Table)
+                    // version in use, its content is copied to the template
+                    .add_column(integer_null(PromptTemplate::VersionId))
+                    .to_owned(),
+            )
+            .await?;
+        // templates written before get their content as first version
+        let conn = manager.get_connection();
+        conn.execute_unprepared(
+            "INSERT INTO prompt_template_version (template_id, content, author_id, created_at)
+            SELECT id, content, author_id, updated_at FROM prompt_template",
+        )
+        .await?;
+        conn.execute_unprepared(
+            "UPDATE prompt_template SET version_id = (SELECT max(id) FROM prompt_template_version
+            WHERE template_id = prompt_template.id)",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PromptTemplate::Table)
+                    .drop_column(PromptTemplate::VersionId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(PromptTemplateVersion::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum PromptTemplateVersion {
+    Table,
+    Id,
+    TemplateId,
+    Content,
+    AuthorId,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum PromptTemplate {
+    Table,
+    Id,
+    VersionId,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
@@ -0,0 +1,109 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::dialect;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(Persona::Table)
+                    .col(pk_auto(Persona::Id))
+                    .col(integer(Persona::OwnerId))
+                    .col(string(Persona::Name))
+                    .col(text(Persona::Prompt))
+                    // chats created with the persona start on it
+                    .col(integer_null(Persona::ModelId))
+                    // json list of tool names, null for every tool of the mode
+                    .col(dialect::json_null(manager, Persona::Tools))
+                    .col(big_integer(Persona::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-persona-owner_id-user")
+                            .from(Persona::Table, Persona::OwnerId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-persona-model_id-model")
+                            .from(Persona::Table, Persona::ModelId)
+                            .to(Model::Table, Model::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-persona-owner_id")
+                    .table(Persona::Table)
+                    .col(Persona::OwnerId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Chat::Table)
+                    // not a foreign key, cleared when the persona is deleted
+                    .add_column(integer_null(Chat::PersonaId))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Chat::Table)
+                    .drop_column(Chat::PersonaId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Persona::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Persona {
+    Table,
+    Id,
+    OwnerId,
+    Name,
+    Prompt,
+    ModelId,
+    Tools,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Chat {
+    Table,
+    PersonaId,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Model {
+    Table,
+    Id,
+}
@@ -0,0 +1,129 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(Document::Table)
+                    .col(pk_auto(Document::Id))
+                    .col(integer(Document::OwnerId))
+                    // the uploaded content, kept out of the sweep of orphan files
+                    .col(integer_null(Document::FileId))
+                    .col(string(Document::Name))
+                    .col(integer(Document::Status))
+                    .col(string_null(Document::Error))
+                    .col(integer(Document::Chunks).default(0))
+                    .col(big_integer(Document::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-document-owner_id-user")
+                            .from(Document::Table, Document::OwnerId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-document-file_id-file")
+                            .from(Document::Table, Document::FileId)
+                            .to(File::Table, File::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-document-owner_id")
+                    .table(Document::Table)
+                    .col(Document::OwnerId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(DocumentChunk::Table)
+                    .col(pk_auto(DocumentChunk::Id))
+                    .col(integer(DocumentChunk::DocumentId))
+                    .col(integer(DocumentChunk::Ordinal))
+                    .col(text(DocumentChunk::Content))
+                    // little endian f32 of the vector
+                    .col(binary(DocumentChunk::Embedding))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-document_chunk-document_id-document")
+                            .from(DocumentChunk::Table, DocumentChunk::DocumentId)
+                            .to(Document::Table, Document::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-document_chunk-document_id")
+                    .table(DocumentChunk::Table)
+                    .col(DocumentChunk::DocumentId)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DocumentChunk::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Document::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Document {
+    Table,
+    Id,
+    OwnerId,
+    FileId,
+    Name,
+    Status,
+    Error,
+    Chunks,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum DocumentChunk {
+    Table,
+    Id,
+    DocumentId,
+    Ordinal,
+    Content,
+    Embedding,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum File {
+    Table,
+    Id,
+}
@@ -0,0 +1,65 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(Memory::Table)
+                    .col(pk_auto(Memory::Id))
+                    .col(integer(Memory::OwnerId))
+                    .col(text(Memory::Content))
+                    // chat the fact was saved in, not a foreign key
+                    .col(integer_null(Memory::ChatId))
+                    .col(big_integer(Memory::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-memory-owner_id-user")
+                            .from(Memory::Table, Memory::OwnerId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-memory-owner_id")
+                    .table(Memory::Table)
+                    .col(Memory::OwnerId)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Memory::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Memory {
+    Table,
+    Id,
+    OwnerId,
+    Content,
+    ChatId,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
@@ -0,0 +1,160 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(Collection::Table)
+                    .col(pk_auto(Collection::Id))
+                    .col(integer(Collection::OwnerId))
+                    .col(string(Collection::Name))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-collection-owner_id-user")
+                            .from(Collection::Table, Collection::OwnerId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-collection-owner_id-name")
+                    .table(Collection::Table)
+                    .col(Collection::OwnerId)
+                    .col(Collection::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(ChatCollection::Table)
+                    .col(integer(ChatCollection::ChatId))
+                    .col(integer(ChatCollection::CollectionId))
+                    .primary_key(
+                        Index::create()
+                            .col(ChatCollection::ChatId)
+                            .col(ChatCollection::CollectionId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-chat_collection-chat_id-chat")
+                            .from(ChatCollection::Table, ChatCollection::ChatId)
+                            .to(Chat::Table, Chat::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-chat_collection-collection_id-collection")
+                            .from(ChatCollection::Table, ChatCollection::CollectionId)
+                            .to(Collection::Table, Collection::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-chat_collection-collection_id")
+                    .table(ChatCollection::Table)
+                    .col(ChatCollection::CollectionId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // no foreign key, deleting a collection move its documents out of it
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Document::Table)
+                    .add_column(integer_null(Document::CollectionId))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-document-collection_id")
+                    .table(Document::Table)
+                    .col(Document::CollectionId)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-document-collection_id")
+                    .table(Document::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Document::Table)
+                    .drop_column(Document::CollectionId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(ChatCollection::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Collection::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Collection {
+    Table,
+    Id,
+    OwnerId,
+    Name,
+}
+
+#[derive(DeriveIden)]
+enum ChatCollection {
+    Table,
+    ChatId,
+    CollectionId,
+}
+
+#[derive(DeriveIden)]
+enum Document {
+    Table,
+    CollectionId,
+}
+
+#[derive(DeriveIden)]
+enum Chat {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
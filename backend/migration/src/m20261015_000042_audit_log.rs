@@ -0,0 +1,64 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(AuditLog::Table)
+                    .col(pk_auto(AuditLog::Id))
+                    .col(integer(AuditLog::Kind))
+                    // not a foreign key, entries outlive the user
+                    .col(integer_null(AuditLog::UserId))
+                    .col(string_null(AuditLog::Ip))
+                    .col(string_null(AuditLog::RequestId))
+                    .col(text(AuditLog::Detail))
+                    .col(big_integer(AuditLog::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-audit_log-user_id")
+                    .table(AuditLog::Table)
+                    .col(AuditLog::UserId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-audit_log-created_at")
+                    .table(AuditLog::Table)
+                    .col(AuditLog::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditLog::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum AuditLog {
+    Table,
+    Id,
+    Kind,
+    UserId,
+    Ip,
+    RequestId,
+    Detail,
+    CreatedAt,
+}
@@ -0,0 +1,299 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Chats written before workspaces, and users created since, land here
+const DEFAULT_WORKSPACE: i32 = 1;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(Workspace::Table)
+                    .col(pk_auto(Workspace::Id))
+                    .col(string_uniq(Workspace::Name))
+                    .col(big_integer(Workspace::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(WorkspaceMember::Table)
+                    .col(integer(WorkspaceMember::WorkspaceId))
+                    .col(integer(WorkspaceMember::UserId))
+                    .primary_key(
+                        Index::create()
+                            .col(WorkspaceMember::WorkspaceId)
+                            .col(WorkspaceMember::UserId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-workspace_member-workspace_id-workspace")
+                            .from(WorkspaceMember::Table, WorkspaceMember::WorkspaceId)
+                            .to(Workspace::Table, Workspace::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-workspace_member-user_id-user")
+                            .from(WorkspaceMember::Table, WorkspaceMember::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-workspace_member-user_id")
+                    .table(WorkspaceMember::Table)
+                    .col(WorkspaceMember::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // no rows allow every model
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(WorkspaceModel::Table)
+                    .col(integer(WorkspaceModel::WorkspaceId))
+                    .col(integer(WorkspaceModel::ModelId))
+                    .primary_key(
+                        Index::create()
+                            .col(WorkspaceModel::WorkspaceId)
+                            .col(WorkspaceModel::ModelId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-workspace_model-workspace_id-workspace")
+                            .from(WorkspaceModel::Table, WorkspaceModel::WorkspaceId)
+                            .to(Workspace::Table, Workspace::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-workspace_model-model_id-model")
+                            .from(WorkspaceModel::Table, WorkspaceModel::ModelId)
+                            .to(Model::Table, Model::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(WorkspaceCredential::Table)
+                    .col(integer(WorkspaceCredential::WorkspaceId))
+                    .col(string(WorkspaceCredential::Name))
+                    .col(text(WorkspaceCredential::Value))
+                    .primary_key(
+                        Index::create()
+                            .col(WorkspaceCredential::WorkspaceId)
+                            .col(WorkspaceCredential::Name),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-workspace_credential-workspace_id-workspace")
+                            .from(WorkspaceCredential::Table, WorkspaceCredential::WorkspaceId)
+                            .to(Workspace::Table, Workspace::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // no foreign keys, sqlite cannot add them to existing tables
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Chat::Table)
+                    .add_column(integer(Chat::WorkspaceId).default(DEFAULT_WORKSPACE))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-chat-workspace_id")
+                    .table(Chat::Table)
+                    .col(Chat::WorkspaceId)
+                    .to_owned(),
+            )
+            .await?;
+        // the workspace a device selected, None for the first of the user
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Session::Table)
+                    .add_column(integer_null(Session::WorkspaceId))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ApiKey::Table)
+                    .add_column(integer_null(ApiKey::WorkspaceId))
+                    .to_owned(),
+            )
+            .await?;
+        // None for the templates of every workspace
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PromptTemplate::Table)
+                    .add_column(integer_null(PromptTemplate::WorkspaceId))
+                    .to_owned(),
+            )
+            .await?;
+
+        let conn = manager.get_connection();
+        // the first row of the table, so sequences of PostgreSQL count it
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |x| x.as_secs() as i64);
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(Workspace::Table)
+                    .columns([Workspace::Name, Workspace::CreatedAt])
+                    .values_panic(["Default".into(), now.into()])
+                    .to_owned(),
+            )
+            .await?;
+        conn.execute_unprepared(&format!(
+            "INSERT INTO workspace_member (workspace_id, user_id) SELECT {}, id FROM \"user\"",
+            DEFAULT_WORKSPACE
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (table, column) in [
+            (
+                PromptTemplate::Table.into_iden(),
+                PromptTemplate::WorkspaceId.into_iden(),
+            ),
+            (ApiKey::Table.into_iden(), ApiKey::WorkspaceId.into_iden()),
+            (Session::Table.into_iden(), Session::WorkspaceId.into_iden()),
+        ] {
+            manager
+                .alter_table(Table::alter().table(table).drop_column(column).to_owned())
+                .await?;
+        }
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-chat-workspace_id")
+                    .table(Chat::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Chat::Table)
+                    .drop_column(Chat::WorkspaceId)
+                    .to_owned(),
+            )
+            .await?;
+        for table in [
+            WorkspaceCredential::Table.into_iden(),
+            WorkspaceModel::Table.into_iden(),
+            WorkspaceMember::Table.into_iden(),
+            Workspace::Table.into_iden(),
+        ] {
+            manager
+                .drop_table(Table::drop().table(table).to_owned())
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Workspace {
+    Table,
+    Id,
+    Name,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum WorkspaceMember {
+    Table,
+    WorkspaceId,
+    UserId,
+}
+
+#[derive(DeriveIden)]
+enum WorkspaceModel {
+    Table,
+    WorkspaceId,
+    ModelId,
+}
+
+#[derive(DeriveIden)]
+enum WorkspaceCredential {
+    Table,
+    WorkspaceId,
+    Name,
+    Value,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Model {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Chat {
+    Table,
+    WorkspaceId,
+}
+
+#[derive(DeriveIden)]
+enum Session {
+    Table,
+    WorkspaceId,
+}
+
+#[derive(DeriveIden)]
+enum ApiKey {
+    Table,
+    WorkspaceId,
+}
+
+#[derive(DeriveIden)]
+enum PromptTemplate {
+    Table,
+    WorkspaceId,
+}
@@ -0,0 +1,73 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(Job::Table)
+                    .col(pk_auto(Job::Id))
+                    .col(integer(Job::Kind))
+                    // at most one job per key, e.g. the recurring purges
+                    .col(string_null(Job::Key).unique_key())
+                    .col(text(Job::Payload))
+                    .col(integer(Job::Status))
+                    .col(integer(Job::Attempts).default(0))
+                    .col(big_integer(Job::RunAt))
+                    .col(text_null(Job::LastError))
+                    .col(big_integer(Job::CreatedAt))
+                    .col(big_integer(Job::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-job-status-run_at")
+                    .table(Job::Table)
+                    .col(Job::Status)
+                    .col(Job::RunAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // documents left pending by an earlier build were resumed at startup
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "INSERT INTO job (kind, payload, status, attempts, run_at, created_at, updated_at)
+                SELECT 1, '{\"t\":\"ingest\",\"c\":{\"document_id\":' || id || '}}', 0, 0,
+                    created_at, created_at, created_at
+                FROM document WHERE status = 0",
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Job::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Job {
+    Table,
+    Id,
+    Kind,
+    Key,
+    Payload,
+    Status,
+    Attempts,
+    RunAt,
+    LastError,
+    CreatedAt,
+    UpdatedAt,
+}
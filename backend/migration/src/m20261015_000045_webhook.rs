@@ -0,0 +1,63 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::dialect;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(Webhook::Table)
+                    .col(pk_auto(Webhook::Id))
+                    .col(integer(Webhook::UserId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-webhook-user_id-user")
+                            .from(Webhook::Table, Webhook::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(string(Webhook::Url))
+                    .col(string(Webhook::Secret))
+                    .col(dialect::json(manager, Webhook::Events).default("[]"))
+                    .col(big_integer(Webhook::CreatedAt))
+                    .col(big_integer_null(Webhook::LastDeliveryAt))
+                    .col(text_null(Webhook::LastError))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Webhook::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Webhook {
+    Table,
+    Id,
+    UserId,
+    Url,
+    Secret,
+    Events,
+    CreatedAt,
+    LastDeliveryAt,
+    LastError,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
@@ -0,0 +1,59 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::dialect;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // kept apart from the messages like `usage`, deleting a chat keep its replies counted
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(ReplyStat::Table)
+                    .col(pk_auto(ReplyStat::Id))
+                    .col(string(ReplyStat::ModelId))
+                    .col(big_integer(ReplyStat::Tokens))
+                    .col(double(ReplyStat::Cost))
+                    .col(big_integer(ReplyStat::TtftMs))
+                    .col(dialect::json(manager, ReplyStat::Tools).default("[]"))
+                    .col(boolean(ReplyStat::Failed))
+                    .col(big_integer(ReplyStat::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-reply_stat-created_at")
+                    .table(ReplyStat::Table)
+                    .col(ReplyStat::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ReplyStat::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum ReplyStat {
+    Table,
+    Id,
+    ModelId,
+    Tokens,
+    Cost,
+    TtftMs,
+    Tools,
+    Failed,
+    CreatedAt,
+}
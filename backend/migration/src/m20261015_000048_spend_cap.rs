@@ -0,0 +1,115 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // counted apart from `reply_stat`, which does not keep the user
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(UserSpend::Table)
+                    .col(integer(UserSpend::UserId))
+                    .col(integer(UserSpend::ModelId))
+                    .col(big_integer(UserSpend::Month))
+                    .col(double(UserSpend::Cost).default(0.0))
+                    .primary_key(
+                        Index::create()
+                            .col(UserSpend::UserId)
+                            .col(UserSpend::ModelId)
+                            .col(UserSpend::Month),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-user_spend-user_id-user")
+                            .from(UserSpend::Table, UserSpend::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-user_spend-model_id-model")
+                            .from(UserSpend::Table, UserSpend::ModelId)
+                            .to(Model::Table, Model::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        // a cap of a user or of a model, exactly one of the two is set
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(SpendCap::Table)
+                    .col(pk_auto(SpendCap::Id))
+                    .col(integer_null(SpendCap::UserId).unique_key())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-spend_cap-user_id-user")
+                            .from(SpendCap::Table, SpendCap::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(integer_null(SpendCap::ModelId).unique_key())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-spend_cap-model_id-model")
+                            .from(SpendCap::Table, SpendCap::ModelId)
+                            .to(Model::Table, Model::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(double(SpendCap::Monthly))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SpendCap::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(UserSpend::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum UserSpend {
+    Table,
+    UserId,
+    ModelId,
+    Month,
+    Cost,
+}
+
+#[derive(DeriveIden)]
+enum SpendCap {
+    Table,
+    Id,
+    UserId,
+    ModelId,
+    Monthly,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Model {
+    Table,
+    Id,
+}
@@ -0,0 +1,73 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // one per member of the chat, members write their own messages
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(ChatDraft::Table)
+                    .col(integer(ChatDraft::ChatId))
+                    .col(integer(ChatDraft::UserId))
+                    .col(text(ChatDraft::Content))
+                    .col(big_integer(ChatDraft::UpdatedAt))
+                    .primary_key(
+                        Index::create()
+                            .col(ChatDraft::ChatId)
+                            .col(ChatDraft::UserId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-chat_draft-chat_id-chat")
+                            .from(ChatDraft::Table, ChatDraft::ChatId)
+                            .to(Chat::Table, Chat::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-chat_draft-user_id-user")
+                            .from(ChatDraft::Table, ChatDraft::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ChatDraft::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum ChatDraft {
+    Table,
+    ChatId,
+    UserId,
+    Content,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Chat {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
@@ -0,0 +1,82 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::dialect;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(RoutingRule::Table)
+                    .col(pk_auto(RoutingRule::Id))
+                    .col(integer(RoutingRule::UserId))
+                    // rules are tried in order, the first matching one wins
+                    .col(integer(RoutingRule::Position))
+                    // json of `RoutingCondition`
+                    .col(dialect::json(manager, RoutingRule::Condition))
+                    .col(integer(RoutingRule::ModelId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-routing_rule-user_id-user")
+                            .from(RoutingRule::Table, RoutingRule::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-routing_rule-model_id-model")
+                            .from(RoutingRule::Table, RoutingRule::ModelId)
+                            .to(Model::Table, Model::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-routing_rule-user_id")
+                    .table(RoutingRule::Table)
+                    .col(RoutingRule::UserId)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RoutingRule::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum RoutingRule {
+    Table,
+    Id,
+    UserId,
+    Position,
+    Condition,
+    ModelId,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Model {
+    Table,
+    Id,
+}
@@ -0,0 +1,70 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(Contact::Table)
+                    .col(pk_auto(Contact::Id))
+                    .col(integer(Contact::UserId))
+                    .col(string(Contact::Name))
+                    .col(string(Contact::Email))
+                    // how the user refers to them, e.g. `my sister`
+                    .col(string_null(Contact::Note))
+                    .col(big_integer(Contact::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-contact-user_id-user")
+                            .from(Contact::Table, Contact::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        // an address is saved once per user, saving it again renames it
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-contact-user_id-email")
+                    .table(Contact::Table)
+                    .col(Contact::UserId)
+                    .col(Contact::Email)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Contact::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Contact {
+    Table,
+    Id,
+    UserId,
+    Name,
+    Email,
+    Note,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
@@ -0,0 +1,69 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(InboxMessage::Table)
+                    .col(pk_auto(InboxMessage::Id))
+                    .col(integer(InboxMessage::UserId))
+                    .col(integer(InboxMessage::Source))
+                    .col(text(InboxMessage::Content))
+                    // kept when the chat is deleted, the link then goes nowhere
+                    .col(integer_null(InboxMessage::ChatId))
+                    .col(big_integer(InboxMessage::CreatedAt))
+                    .col(big_integer_null(InboxMessage::ReadAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-inbox_message-user_id-user")
+                            .from(InboxMessage::Table, InboxMessage::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-inbox_message-user_id")
+                    .table(InboxMessage::Table)
+                    .col(InboxMessage::UserId)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(InboxMessage::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum InboxMessage {
+    Table,
+    Id,
+    UserId,
+    Source,
+    Content,
+    ChatId,
+    CreatedAt,
+    ReadAt,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
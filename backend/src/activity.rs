@@ -0,0 +1,132 @@
+//! Messages and tokens of a user over time, for a heatmap
+//!
+//! Buckets before the current day no longer change, so they are counted once a
+//! day per user, and only the current day is counted on every request. Deleted
+//! chats still show in the past buckets until the next day
+
+use std::{collections::HashMap, sync::Mutex};
+
+use entity::MessageKind;
+use sea_orm::{ConnectionTrait, DbErr, FromQueryResult};
+
+use crate::utils::sql;
+
+const DAY: i64 = 24 * 3600;
+
+#[derive(Debug, Default)]
+pub struct Activity {
+    map: Mutex<HashMap<Key, Entry>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Key {
+    user_id: i32,
+    width: i64,
+    offset: i64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    /// Start of the day the buckets stop at
+    today: i64,
+    past: Vec<Bucket>,
+}
+
+#[derive(Debug, Clone, FromQueryResult)]
+pub struct Bucket {
+    /// unix seconds
+    pub start: i64,
+    /// Sent by the user
+    pub messages: i64,
+    /// Output tokens of the replies
+    pub tokens: i64,
+}
+
+impl Activity {
+    /// Buckets with any message, from `days` before the current day to now
+    ///
+    /// `width` is the seconds of a bucket and `offset` the seconds of the user
+    /// ahead of UTC, days and hours start at their local time
+    pub async fn buckets(
+        &self,
+        conn: &impl ConnectionTrait,
+        user_id: i32,
+        width: i64,
+        offset: i64,
+        days: i64,
+    ) -> Result<Vec<Bucket>, DbErr> {
+        let now = time::UtcDateTime::now().unix_timestamp();
+        let today = today(offset);
+        let key = Key {
+            user_id,
+            width,
+            offset,
+        };
+
+        let cached = self
+            .map
+            .lock()
+            .unwrap()
+            .get(&key)
+            .filter(|x| x.today == today)
+            .map(|x| x.past.clone());
+        let mut buckets = match cached {
+            Some(past) => past,
+            None => {
+                let past = count(conn, user_id, width, offset, today - days * DAY, today).await?;
+                let mut map = self.map.lock().unwrap();
+                // offsets shift the day by at most 14 hours
+                map.retain(|_, x| x.today > now - 2 * DAY);
+                map.insert(
+                    key,
+                    Entry {
+                        today,
+                        past: past.clone(),
+                    },
+                );
+                past
+            }
+        };
+        buckets.extend(count(conn, user_id, width, offset, today, now + 1).await?);
+        Ok(buckets)
+    }
+}
+
+/// Start of the current day at `offset` seconds ahead of UTC, unix seconds
+pub fn today(offset: i64) -> i64 {
+    let now = time::UtcDateTime::now().unix_timestamp();
+    (now + offset).div_euclid(DAY) * DAY - offset
+}
+
+/// Messages created in `[from, to)`, messages from before times were recorded have none
+async fn count(
+    conn: &impl ConnectionTrait,
+    user_id: i32,
+    width: i64,
+    offset: i64,
+    from: i64,
+    to: i64,
+) -> Result<Vec<Bucket>, DbErr> {
+    Bucket::find_by_statement(sql::statement(
+        conn.get_database_backend(),
+        "SELECT (message.created_at + ?) / ? * ? - ? AS start,
+            COUNT(CASE WHEN message.kind = ? THEN 1 END) AS messages,
+            CAST(SUM(message.tokens) AS bigint) AS tokens
+        FROM message
+        JOIN chat ON chat.id = message.chat_id
+        WHERE chat.owner_id = ? AND message.created_at >= ? AND message.created_at < ?
+        GROUP BY 1 ORDER BY 1",
+        [
+            offset.into(),
+            width.into(),
+            width.into(),
+            offset.into(),
+            (MessageKind::User as i32).into(),
+            user_id.into(),
+            from.max(1).into(),
+            to.into(),
+        ],
+    ))
+    .all(conn)
+    .await
+}
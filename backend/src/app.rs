@@ -0,0 +1,514 @@
+//! Entry points of the two builds
+//!
+//! `headless` (default) only serve the API and the frontend, `desktop` run the
+//! same server in a background thread and put an icon in the system tray,
+//! which shows the status of the server and shuts it down gracefully
+
+use std::{future::IntoFuture, net::SocketAddr, sync::Arc, time::Duration};
+
+use axum::{
+    Router,
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{get, post},
+    serve::ListenerExt,
+};
+use dotenv::var;
+use futures_util::FutureExt;
+use migration::MigratorTrait;
+use tower::ServiceBuilder;
+use tower_http::services::{ServeDir, ServeFile};
+
+use crate::{
+    AppState, audit, bind, bind::Bound, config::SHUTDOWN_TIMEOUT, config::Settings, database, demo,
+    files, imagegen, jobs, middlewares, middlewares::cache_control::CacheControlLayer,
+    middlewares::rate_limit::RateLimiter, moderation, notify, oauth, openrouter::Openrouter,
+    pricing, prompts::PromptEnv, push, quota, retention::Retention, routes, schedule, spend,
+    sse::SseContext, stt, telemetry, tls, tls::TlsListener, tools, tools::ToolStore, tts,
+    undo::Undo, utils, utils::keyring::Keyring, utils::password_hash::Hasher, utils::secrets,
+    utils::secrets::Secrets,
+};
+
+fn bind_addr() -> String {
+    var("BIND_ADDR").unwrap_or("0.0.0.0:8001".to_owned())
+}
+
+pub fn database_url() -> String {
+    var("DATABASE_URL").unwrap_or("sqlite://db.sqlite?mode=rwc".to_owned())
+}
+
+/// Multi-threaded, one worker per core unless `WORKERS` says otherwise
+pub fn runtime() -> tokio::runtime::Runtime {
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    if let Some(workers) = var("WORKERS")
+        .ok()
+        .and_then(|x| x.parse::<usize>().ok())
+        .filter(|x| *x > 0)
+    {
+        builder.worker_threads(workers);
+    }
+    builder
+        .enable_all()
+        .build()
+        .expect("Cannot build tokio runtime")
+}
+
+/// Serve until the listener fails
+#[cfg(not(feature = "desktop"))]
+pub async fn run_server() {
+    serve(std::future::pending(), |_| {}).await
+}
+
+/// Serve until `shutdown` completes, telling `on_bound` the URL of the server
+/// once it listens
+///
+/// Open connections then get [`SHUTDOWN_TIMEOUT`] seconds to finish, streamed
+/// replies keep theirs open for as long as the model writes
+pub async fn serve(
+    shutdown: impl Future<Output = ()> + Send + 'static,
+    on_bound: impl FnOnce(String),
+) {
+    let database_url = database_url();
+
+    migration::migrate(&database_url)
+        .await
+        .expect("Migration failed");
+
+    let conn = database::connect(&database_url)
+        .await
+        .expect("Cannot connect to database");
+
+    migration::Migrator::up(&conn, None)
+        .await
+        .expect("Cannot migrate database");
+
+    let keyring = Keyring::load(conn.clone())
+        .await
+        .expect("Cannot load paseto keys");
+
+    let secrets = Secrets::load(&conn).await.expect("Cannot load secrets key");
+    let sealed = secrets::seal_existing(&conn, &secrets)
+        .await
+        .expect("Cannot seal credentials");
+    if sealed > 0 {
+        tracing::info!("sealed {} workspace credentials", sealed);
+    }
+
+    let instance_id = utils::instance::instance_id(&conn)
+        .await
+        .expect("Cannot load instance id");
+
+    let sse = SseContext::new(conn.clone());
+    sse.spawn_reaper();
+    let prompt = PromptEnv::new(conn.clone());
+    let settings = Settings::load(conn.clone(), secrets.clone())
+        .await
+        .expect("Cannot load settings");
+    let openrouter = Openrouter::new(&settings.current());
+    let pricing = pricing::Pricing::new(conn.clone())
+        .await
+        .expect("Cannot load model prices");
+    let retention = Retention::load(conn.clone())
+        .await
+        .expect("Cannot load retention policy");
+    let rate_limit = RateLimiter::new(settings.current().rate_limit);
+    let moderation = moderation::Moderation::new(settings.current().moderation);
+    let spend = spend::SpendGuard::load(conn.clone())
+        .await
+        .expect("Cannot load spend guard");
+    let push = push::Push::load(&conn)
+        .await
+        .expect("Cannot load VAPID key");
+    let files = files::Files::from_env().expect("Cannot configure file storage");
+    tracing::info!("storing files in {}", files.describe());
+    let mut tools = ToolStore::new(conn.clone(), secrets.clone());
+
+    tools.add_tool::<tools::wttr::Wttr>().unwrap();
+    tools.add_tool::<tools::nearbyplace::NearByPlace>().unwrap();
+    tools.add_tool::<tools::directions::Directions>().unwrap();
+    tools.add_tool::<tools::mail::RecentMail>().unwrap();
+    tools.add_tool::<tools::mail::ReplyMail>().unwrap();
+    tools.add_tool::<tools::mail::SendMail>().unwrap();
+    tools.add_tool::<tools::mail::GetMailContent>().unwrap();
+    tools.add_tool::<tools::contacts::AddContact>().unwrap();
+    tools.add_tool::<tools::contacts::FindContact>().unwrap();
+    tools.add_tool::<tools::contacts::ListContacts>().unwrap();
+    tools.add_tool::<tools::rss::RssSearch>().unwrap();
+    tools.add_tool::<tools::memory::RememberFact>().unwrap();
+    tools.add_tool::<tools::memory::RecallFacts>().unwrap();
+    tools.add_tool::<tools::agent::Delegate>().unwrap();
+    // models are not offered a tool that can only fail
+    let image = imagegen::ImageGen::from_env();
+    if image.is_some() {
+        tools.add_tool::<tools::image::GenerateImage>().unwrap();
+    }
+    let tools_dir = tools::declared::dir();
+    let declared = tools.load_declared(&tools_dir);
+    if declared > 0 {
+        tracing::info!(
+            "loaded {} declared tools from {}",
+            declared,
+            tools_dir.display()
+        );
+    }
+    tools
+        .load_disabled()
+        .await
+        .expect("Cannot load tools kill switch");
+
+    let state = Arc::new(AppState {
+        conn,
+        keyring,
+        secrets,
+        sse,
+        hasher: Hasher::default(),
+        openrouter,
+        prompt,
+        tools,
+        instance_id,
+        demo: demo::Demo::from_env(),
+        pricing,
+        prefetch: Default::default(),
+        oauth: oauth::OAuth::from_env().await,
+        mailer: notify::mailer::Mailer::from_env(),
+        inputs: Default::default(),
+        undo: Undo::from_env(),
+        activity: Default::default(),
+        cache: Default::default(),
+        retention,
+        settings,
+        rate_limit,
+        body_limits: middlewares::body_limit::BodyLimits::from_env(),
+        spend,
+        spend_caps: spend::cap::SpendCaps::from_env(),
+        quotas: quota::Quotas::from_env(),
+        moderation,
+        idempotency: Default::default(),
+        notifier: Default::default(),
+        push,
+        jobs: Default::default(),
+        files,
+        stt: stt::Stt::from_env(),
+        tts: tts::Tts::from_env(),
+        image,
+    });
+    demo::Demo::spawn_purge(state.clone());
+    pricing::Pricing::spawn_sync(state.clone());
+    utils::tagger::spawn(state.clone());
+    Undo::spawn_expire(state.clone());
+    audit::spawn_purge(state.clone());
+    files::Files::spawn_sweep(state.clone());
+    schedule::spawn_runner(state.clone());
+    jobs::Jobs::spawn_workers(state.clone());
+    telemetry::spawn_export();
+    Openrouter::spawn_reload(state.clone());
+    RateLimiter::spawn_reload(state.clone());
+    moderation::Moderation::spawn_reload(state.clone());
+
+    let app = Router::new()
+        .nest(
+            "/api",
+            Router::new()
+                .nest("/chat", routes::chat::routes())
+                .nest("/file", routes::file::routes())
+                .nest("/folder", routes::folder::routes())
+                .nest("/kb", routes::kb::routes())
+                .nest("/label", routes::label::routes())
+                .nest("/user", routes::user::routes())
+                .nest("/memory", routes::memory::routes())
+                .nest("/message", routes::message::routes())
+                .nest("/model", routes::model::routes())
+                .nest("/notifications", routes::notifications::routes())
+                .nest("/persona", routes::persona::routes())
+                .nest("/policy", routes::policy::routes())
+                .nest("/pricing", routes::pricing::routes())
+                .nest("/prompt", routes::prompt::routes())
+                .nest("/schedule", routes::schedule::routes())
+                .nest("/setting", routes::setting::routes())
+                .nest("/sync", routes::sync::routes())
+                .nest("/admin", routes::admin::routes())
+                .nest("/workspace", routes::workspace::routes())
+                .nest("/auth/totp", routes::auth::totp::routes())
+                .route("/undo/{token}", post(routes::undo::route))
+                // the screenshot is in base64, limited like uploads
+                .route("/capture", post(routes::capture::route))
+                .nest("/v1", routes::openai::routes())
+                .layer(middleware::from_extractor_with_state::<
+                    middlewares::auth::Middleware,
+                    _,
+                >(state.clone()))
+                .nest("/auth", routes::auth::routes())
+                .nest("/demo", routes::demo::routes())
+                .nest("/federation", routes::federation::routes())
+                .merge(routes::openapi::routes())
+                // authenticate with the first message, browsers cannot set headers on it
+                .route("/ws", get(routes::ws::route))
+                // bodies are limited by the middleware, see `middlewares::body_limit`
+                .layer(DefaultBodyLimit::disable())
+                .layer(middleware::from_fn(middlewares::compression::middleware))
+                .layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    middlewares::body_limit::middleware,
+                ))
+                .layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    middlewares::rate_limit::middleware,
+                ))
+                // errors of the rate limit and the body limit are localized too
+                .layer(middleware::from_fn(middlewares::locale::middleware))
+                // outermost, so requests refused by the rate limit are counted
+                .layer(middleware::from_fn(middlewares::metrics::middleware))
+                .layer(middleware::from_fn(middlewares::request_id::middleware)),
+        )
+        .route("/share/{token}", get(routes::share::route))
+        .route("/calendar/{token}", get(routes::calendar::route))
+        .route("/metrics", get(routes::metrics::route))
+        .route("/healthz", get(routes::health::healthz))
+        .route("/readyz", get(routes::health::readyz));
+    let app = frontend(app).with_state(state);
+
+    let app = match middlewares::cors::from_env() {
+        Some(cors) => app.layer(cors),
+        #[cfg(feature = "dev")]
+        None => app.layer(middlewares::cors::any()),
+        #[cfg(not(feature = "dev"))]
+        None => app,
+    };
+
+    // the peer address is needed by the demo rate limit
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
+    let listener = bind::bind(&bind_addr())
+        .await
+        .expect("Cannot bind BIND_ADDR");
+    let paths = tls::Paths::from_env();
+    on_bound(listener.url(paths.is_some()));
+
+    let shutdown = shutdown.shared();
+    // axum gives the peer address of tapped listeners, not of others
+    let server = match (listener, paths) {
+        (Bound::Tcp(tcp), Some(paths)) => {
+            let tls = TlsListener::new(tcp, paths).expect("Cannot start TLS");
+            axum::serve(tls.tap_io(|_| {}), app)
+                .with_graceful_shutdown(shutdown.clone())
+                .into_future()
+                .boxed()
+        }
+        (Bound::Tcp(tcp), None) => axum::serve(tcp, app)
+            .with_graceful_shutdown(shutdown.clone())
+            .into_future()
+            .boxed(),
+        #[cfg(unix)]
+        (Bound::Unix(_), Some(_)) => panic!("TLS is only served over TCP, not a unix socket"),
+        #[cfg(unix)]
+        (Bound::Unix(unix), None) => axum::serve(unix.tap_io(|_| {}), app)
+            .with_graceful_shutdown(shutdown.clone())
+            .into_future()
+            .boxed(),
+    };
+
+    let drain = async {
+        shutdown.await;
+        tokio::time::sleep(Duration::from_secs(SHUTDOWN_TIMEOUT)).await;
+    };
+    tokio::select! {
+        res = server => res.unwrap(),
+        _ = drain => tracing::warn!(
+            "connections still open {} seconds after shutdown, closing them",
+            SHUTDOWN_TIMEOUT
+        ),
+    }
+}
+
+/// The frontend build embedded by `bundled` unless `STATIC_DIR` is set, else
+/// that directory
+fn frontend(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    let static_dir = var("STATIC_DIR");
+    #[cfg(feature = "bundled")]
+    if static_dir.is_err() {
+        return router.fallback(routes::bundled::route);
+    }
+    let static_dir = static_dir.unwrap_or("../frontend/build".to_owned());
+    router.fallback_service(
+        ServiceBuilder::new().layer(CacheControlLayer).service(
+            ServeDir::new(&static_dir)
+                .precompressed_gzip()
+                .precompressed_br()
+                .fallback(
+                    ServeFile::new(format!("{}/index.html", static_dir))
+                        .precompressed_br()
+                        .precompressed_gzip(),
+                ),
+        ),
+    )
+}
+
+/// Server in a background thread, tray event loop on the main thread
+///
+/// Some platforms (macOS) only allow the event loop on the main thread. The
+/// tooltip follows the status of the server, Quit shuts it down gracefully and
+/// exits once it stopped
+#[cfg(feature = "desktop")]
+pub fn run_desktop() -> anyhow::Result<()> {
+    use std::panic::AssertUnwindSafe;
+
+    use betrayer::{
+        Icon, Menu, MenuItem, TrayEvent, TrayIcon, TrayIconBuilder, winit::WinitTrayIconBuilderExt,
+    };
+    use tokio::sync::oneshot;
+    use winit::{
+        application::ApplicationHandler,
+        event::WindowEvent,
+        event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+        window::WindowId,
+    };
+
+    #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+    enum Signal {
+        Open,
+        Quit,
+    }
+
+    #[derive(Debug, Clone)]
+    enum Status {
+        Starting,
+        /// The URL it listens on
+        Running(String),
+        Stopping,
+        Failed(String),
+        Stopped,
+    }
+
+    impl Status {
+        fn tooltip(&self) -> String {
+            match self {
+                Status::Starting => "llumen - starting".to_owned(),
+                Status::Running(url) => format!("llumen - running on {}", url),
+                Status::Stopping => "llumen - stopping".to_owned(),
+                Status::Failed(err) => format!("llumen - failed: {}", err),
+                Status::Stopped => "llumen - stopped".to_owned(),
+            }
+        }
+    }
+
+    enum UserEvent {
+        Tray(TrayEvent<Signal>),
+        Server(Status),
+    }
+
+    struct Tray {
+        icon: TrayIcon<Signal>,
+        /// Known once the server listens
+        url: Option<String>,
+        /// Taken by the first Quit
+        shutdown: Option<oneshot::Sender<()>>,
+        /// Quit was chosen after the server failed, nothing is left to stop
+        failed: bool,
+    }
+
+    impl ApplicationHandler<UserEvent> for Tray {
+        fn resumed(&mut self, _event_loop: &ActiveEventLoop) {}
+        fn user_event(&mut self, event_loop: &ActiveEventLoop, event: UserEvent) {
+            match event {
+                UserEvent::Tray(TrayEvent::Menu(Signal::Open)) => match &self.url {
+                    Some(url) => {
+                        if let Err(err) = open_browser(url) {
+                            tracing::warn!("cannot open browser: {}", err);
+                        }
+                    }
+                    None => tracing::warn!("the server does not listen yet"),
+                },
+                UserEvent::Tray(TrayEvent::Menu(Signal::Quit)) => {
+                    if self.failed {
+                        return event_loop.exit();
+                    }
+                    if let Some(shutdown) = self.shutdown.take() {
+                        let _ = shutdown.send(());
+                        self.icon
+                            .set_tooltip::<String>(Some(Status::Stopping.tooltip()));
+                    }
+                }
+                UserEvent::Tray(_) => {}
+                UserEvent::Server(status) => {
+                    self.icon.set_tooltip::<String>(Some(status.tooltip()));
+                    match status {
+                        Status::Running(url) => self.url = Some(url),
+                        Status::Failed(_) => {
+                            self.url = None;
+                            self.failed = true;
+                        }
+                        Status::Stopped => event_loop.exit(),
+                        Status::Starting | Status::Stopping => {}
+                    }
+                }
+            }
+        }
+        fn window_event(
+            &mut self,
+            _event_loop: &ActiveEventLoop,
+            _window_id: WindowId,
+            _event: WindowEvent,
+        ) {
+        }
+    }
+
+    let event_loop = EventLoop::<UserEvent>::with_user_event().build()?;
+    let icon = TrayIconBuilder::new()
+        .with_icon(Icon::from_png_bytes(include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../frontend/static/favicon-96x96.png"
+        )))?)
+        .with_tooltip(Status::Starting.tooltip())
+        .with_menu(Menu::new([
+            MenuItem::button("Open", Signal::Open),
+            MenuItem::button("Quit", Signal::Quit),
+        ]))
+        .build_event_loop(&event_loop, |x| Some(UserEvent::Tray(x)))?;
+
+    let (shutdown, stop) = oneshot::channel::<()>();
+    let proxy = event_loop.create_proxy();
+    std::thread::spawn(move || {
+        let bound = proxy.clone();
+        let res = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            runtime().block_on(serve(
+                async move {
+                    let _ = stop.await;
+                },
+                move |url| {
+                    let _ = bound.send_event(UserEvent::Server(Status::Running(url)));
+                },
+            ))
+        }));
+        let status = match res {
+            Ok(()) => Status::Stopped,
+            Err(panic) => Status::Failed(
+                panic
+                    .downcast_ref::<String>()
+                    .cloned()
+                    .or_else(|| panic.downcast_ref::<&str>().map(|x| x.to_string()))
+                    .unwrap_or_else(|| "see the log".to_owned()),
+            ),
+        };
+        let _ = proxy.send_event(UserEvent::Server(status));
+    });
+
+    event_loop.set_control_flow(ControlFlow::Wait);
+    event_loop.run_app(&mut Tray {
+        icon,
+        url: None,
+        shutdown: Some(shutdown),
+        failed: false,
+    })?;
+    Ok(())
+}
+
+#[cfg(feature = "desktop")]
+fn open_browser(url: &str) -> std::io::Result<std::process::Child> {
+    use std::process::Command;
+
+    #[cfg(target_os = "windows")]
+    return Command::new("cmd").args(["/C", "start", "", url]).spawn();
+    #[cfg(target_os = "macos")]
+    return Command::new("open").arg(url).spawn();
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    return Command::new("xdg-open").arg(url).spawn();
+}
@@ -0,0 +1,80 @@
+//! Security relevant events, kept in `audit_log` for admins (`admin/audit`)
+//!
+//! Logins, token refreshes, tool calls and changes made by admins are written
+//! with the id and IP of the request they came from, see
+//! `middlewares::request_id`, and logged with them as fields. Writing an entry
+//! never fails the action itself. Entries are kept [`AUDIT_DAYS`]
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use entity::{AuditKind, audit_log, prelude::*};
+use sea_orm::{ActiveValue::Set, DbConn, prelude::*};
+
+use crate::{
+    AppState,
+    config::{AUDIT_DAYS, AUDIT_PURGE_INTERVAL},
+    middlewares::request_id,
+    tools::ToolCtx,
+};
+
+/// Write an entry, `detail` is a short description for the admin
+pub async fn record(conn: &DbConn, kind: AuditKind, user_id: Option<i32>, detail: String) {
+    let ctx = request_id::current();
+    let request_id = ctx.as_ref().map(|x| x.id.clone());
+    let ip = ctx.map(|x| x.ip.to_string());
+    // the request id is in the span
+    tracing::info!(kind = ?kind, user_id, ip, "{}", detail);
+
+    let res = AuditLog::insert(audit_log::ActiveModel {
+        kind: Set(kind),
+        user_id: Set(user_id),
+        ip: Set(ip),
+        request_id: Set(request_id),
+        detail: Set(detail),
+        created_at: Set(now()),
+        ..Default::default()
+    })
+    .exec(conn)
+    .await;
+    if let Err(err) = res {
+        tracing::warn!("cannot write the audit log: {}", err);
+    }
+}
+
+/// A tool run for the user of `ctx`
+pub async fn tool_call(ctx: &ToolCtx, name: &str, ok: bool) {
+    let outcome = if ok { "done" } else { "failed" };
+    let detail = format!("{} in chat {} {}", name, ctx.chat_id, outcome);
+    record(
+        &ctx.app.conn,
+        AuditKind::ToolCall,
+        Some(ctx.user_id),
+        detail,
+    )
+    .await;
+}
+
+pub fn spawn_purge(app: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(AUDIT_PURGE_INTERVAL));
+        loop {
+            interval.tick().await;
+            if let Err(err) = purge_due(&app.conn).await {
+                tracing::warn!("cannot purge the audit log: {}", err);
+            }
+        }
+    });
+}
+
+async fn purge_due(conn: &DbConn) -> Result<()> {
+    AuditLog::delete_many()
+        .filter(audit_log::Column::CreatedAt.lt(now() - AUDIT_DAYS * 24 * 3600))
+        .exec(conn)
+        .await?;
+    Ok(())
+}
+
+fn now() -> i64 {
+    time::UtcDateTime::now().unix_timestamp()
+}
@@ -0,0 +1,311 @@
+//! Backups of an SQLite instance, see `admin/backup` and the `backup` command
+//!
+//! A backup is a tar archive of `backup.json`, a snapshot of the database
+//! taken with `VACUUM INTO` while the server runs, and the uploaded files
+//! under `files/` by storage key, fetched from the storage in use. Restoring
+//! one is for a fresh instance: its database is migrated to the schema of
+//! this build in a copy, the files are put in the storage, then every table
+//! is replaced within a transaction and the search index rebuilt; the files
+//! are removed again if that fails. Settings and keys are read at startup, so the
+//! server is restarted afterward. PostgreSQL is backed up with `pg_dump`
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use entity::prelude::*;
+use migration::MigratorTrait;
+use sea_orm::{
+    ConnectionTrait, Database, DbBackend, DbConn, EntityTrait, PaginatorTrait, QueryOrder,
+    sqlx::{self, Executor, Row},
+};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::{
+    files::{self, Files},
+    utils::tar,
+};
+
+/// Bumped when the layout of the archive changes
+const FORMAT: u32 = 1;
+const MANIFEST: &str = "backup.json";
+const DATABASE: &str = "db.sqlite";
+const FILES: &str = "files/";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    format: u32,
+    created_at: i64,
+    /// Latest migration applied to the database
+    migration: String,
+    files: u64,
+}
+
+/// What a restore brought back
+pub struct Restored {
+    pub users: u64,
+    pub chats: u64,
+    pub files: u64,
+}
+
+/// A consistent copy of the database, taken without stopping writers
+pub struct Snapshot {
+    path: PathBuf,
+    manifest: Manifest,
+}
+
+impl Snapshot {
+    pub async fn take(conn: &DbConn) -> Result<Self> {
+        supported(conn)?;
+        let now = time::UtcDateTime::now().unix_timestamp();
+        let path = temp_path("backup")?;
+        conn.execute_unprepared(&format!("VACUUM INTO {}", literal(&path)))
+            .await
+            .context("cannot snapshot the database")?;
+
+        let migration = migration::Migrator::get_applied_migrations(conn)
+            .await?
+            .last()
+            .map(|x| x.name().to_owned())
+            .unwrap_or_default();
+        let files = File::find().count(conn).await?;
+        Ok(Self {
+            path,
+            manifest: Manifest {
+                format: FORMAT,
+                created_at: now,
+                migration,
+                files,
+            },
+        })
+    }
+
+    /// Write the archive of the snapshot and of the files it refers to
+    pub async fn write(
+        self,
+        conn: &DbConn,
+        files: &Files,
+        out: impl AsyncWrite + Unpin,
+    ) -> Result<()> {
+        let res = self.write_archive(conn, files, out).await;
+        tokio::fs::remove_file(&self.path).await.ok();
+        res
+    }
+
+    async fn write_archive(
+        &self,
+        conn: &DbConn,
+        files: &Files,
+        out: impl AsyncWrite + Unpin,
+    ) -> Result<()> {
+        let now = self.manifest.created_at;
+        let mut archive = tar::Writer::new(out);
+        archive
+            .append_bytes(MANIFEST, now, &serde_json::to_vec_pretty(&self.manifest)?)
+            .await?;
+        let db = tokio::fs::File::open(&self.path).await?;
+        let size = db.metadata().await?.len();
+        archive.append(DATABASE, size, now, db).await?;
+
+        // files uploaded since the snapshot are left out with their rows
+        let rows = File::find()
+            .order_by_asc(entity::file::Column::Id)
+            .all(conn)
+            .await?;
+        for row in rows {
+            let data = match files.get(&row.storage_key).await {
+                Ok(data) => data,
+                Err(err) => {
+                    tracing::warn!("file {} is not backed up: {}", row.id, err);
+                    continue;
+                }
+            };
+            let path = format!("{}{}", FILES, row.storage_key);
+            archive.append_bytes(&path, row.created_at, &data).await?;
+        }
+        archive.finish().await?;
+        Ok(())
+    }
+}
+
+/// Replace everything of a fresh instance with the archive read from `input`
+pub async fn restore(
+    conn: &DbConn,
+    files: &Files,
+    input: impl AsyncRead + Unpin,
+) -> Result<Restored> {
+    supported(conn)?;
+    if Chat::find().count(conn).await? > 0
+        || File::find().count(conn).await? > 0
+        || User::find().count(conn).await? > 1
+    {
+        bail!("only a fresh instance can be restored, without chats, files nor other users");
+    }
+
+    let mut archive = tar::Reader::new(input);
+    let manifest: Manifest = match archive.next().await? {
+        Some(entry) if entry.path == MANIFEST => serde_json::from_slice(&archive.bytes().await?)?,
+        _ => bail!("not a backup, {} missing", MANIFEST),
+    };
+    if manifest.format != FORMAT {
+        bail!(
+            "backup of format {}, this build reads {}",
+            manifest.format,
+            FORMAT
+        );
+    }
+    match archive.next().await? {
+        Some(entry) if entry.path == DATABASE => {}
+        _ => bail!("not a backup, {} missing", DATABASE),
+    }
+    let path = temp_path("restore")?;
+    // files go to the storage last, once the database is known to restore,
+    // and are removed again if it does not
+    let mut written = vec![];
+    let res = async {
+        let mut db = tokio::fs::File::create(&path).await?;
+        archive.copy_to(&mut db).await?;
+        drop(db);
+        migrate(&path).await?;
+        restore_files(files, &mut archive, &mut written).await?;
+        replace(conn, &path).await
+    }
+    .await;
+    tokio::fs::remove_file(&path).await.ok();
+    if res.is_err() {
+        for key in &written {
+            files::discard(files, key).await;
+        }
+    }
+    res?;
+
+    Ok(Restored {
+        users: User::find().count(conn).await?,
+        chats: Chat::find().count(conn).await?,
+        files: File::find().count(conn).await?,
+    })
+}
+
+/// Put the files of the archive in the storage, `written` holding the keys
+/// of those put so far
+async fn restore_files(
+    files: &Files,
+    archive: &mut tar::Reader<impl AsyncRead + Unpin>,
+    written: &mut Vec<String>,
+) -> Result<()> {
+    while let Some(entry) = archive.next().await? {
+        let Some(key) = entry.path.strip_prefix(FILES) else {
+            continue;
+        };
+        if key.is_empty()
+            || !key
+                .chars()
+                .all(|x| x.is_ascii_alphanumeric() || x == '-' || x == '_')
+        {
+            bail!("malformed file name {} in the backup", entry.path);
+        }
+        let spool = std::env::temp_dir().join(format!("llumen-restore-{}", key));
+        let mut out = tokio::fs::File::create(&spool).await?;
+        let res = archive.copy_to(&mut out).await;
+        drop(out);
+        if let Err(err) = res {
+            tokio::fs::remove_file(&spool).await.ok();
+            return Err(err);
+        }
+        files.put(key, &spool, entry.size).await?;
+        written.push(key.to_owned());
+    }
+    Ok(())
+}
+
+/// Bring the database of an older build to the schema of this one
+async fn migrate(path: &Path) -> Result<()> {
+    let url = format!("sqlite://{}?mode=rw", path.display());
+    let db = Database::connect(url).await?;
+    migration::Migrator::up(&db, None)
+        .await
+        .context("cannot migrate the backup, is it of a newer build?")?;
+    db.close().await?;
+    Ok(())
+}
+
+/// Copy every table of the database at `path` over the ones of `conn`
+///
+/// `ATTACH` is refused within a transaction, so both run on a connection taken
+/// from the pool rather than through sea-orm
+async fn replace(conn: &DbConn, path: &Path) -> Result<()> {
+    let mut db = conn.get_sqlite_connection_pool().acquire().await?;
+    sqlx::query("ATTACH DATABASE ? AS backup")
+        .bind(path.display().to_string())
+        .execute(&mut *db)
+        .await?;
+    let res = copy_tables(&mut db).await;
+    if res.is_err() {
+        db.execute("ROLLBACK").await.ok();
+    }
+    db.execute("DETACH DATABASE backup").await?;
+    res
+}
+
+async fn copy_tables(db: &mut sqlx::SqliteConnection) -> Result<()> {
+    // the search index is rebuilt and the migrations are the ones of this
+    // build; `sync_change` goes last as triggers write to it on every copy
+    let tables: Vec<String> = sqlx::query(
+        "SELECT name FROM sqlite_master WHERE type = 'table'
+            AND name NOT LIKE 'sqlite_%' AND name NOT LIKE 'message_fts%'
+            AND name != 'seaql_migrations'
+        ORDER BY name = 'sync_change', name",
+    )
+    .fetch_all(&mut *db)
+    .await?
+    .iter()
+    .map(|x| x.get(0))
+    .collect();
+
+    db.execute("BEGIN IMMEDIATE; PRAGMA defer_foreign_keys = ON;")
+        .await?;
+    for table in &tables {
+        db.execute(format!("DELETE FROM main.\"{}\"", table).as_str())
+            .await?;
+    }
+    for table in &tables {
+        let columns: Vec<String> = sqlx::query("SELECT name FROM pragma_table_info(?, 'main')")
+            .bind(table)
+            .fetch_all(&mut *db)
+            .await?
+            .iter()
+            .map(|x| format!("\"{}\"", x.get::<String, _>(0)))
+            .collect();
+        let columns = columns.join(", ");
+        let copy = format!(
+            "DELETE FROM main.\"{table}\";
+            INSERT INTO main.\"{table}\" ({columns}) SELECT {columns} FROM backup.\"{table}\""
+        );
+        db.execute(copy.as_str()).await?;
+    }
+    db.execute(
+        "DELETE FROM message_fts;
+        INSERT INTO message_fts (rowid, content, message_id)
+            SELECT id, content, message_id FROM search_document;
+        COMMIT;",
+    )
+    .await?;
+    Ok(())
+}
+
+fn supported(conn: &DbConn) -> Result<()> {
+    match conn.get_database_backend() {
+        DbBackend::Sqlite => Ok(()),
+        _ => bail!("backups are of SQLite, back up PostgreSQL with pg_dump"),
+    }
+}
+
+fn temp_path(purpose: &str) -> Result<PathBuf> {
+    let key = files::new_key()?;
+    Ok(std::env::temp_dir().join(format!("llumen-{}-{}.sqlite", purpose, key)))
+}
+
+/// `path` as an SQL string literal
+fn literal(path: &Path) -> String {
+    format!("'{}'", path.display().to_string().replace('\'', "''"))
+}
@@ -0,0 +1,155 @@
+//! Where the server listens, from `BIND_ADDR` env
+//!
+//! A TCP address such as `0.0.0.0:8001`, or `unix:/run/llumen/llumen.sock` for
+//! a reverse proxy on the same host. A socket passed by systemd socket
+//! activation is taken instead when there is one, TCP or unix. The peers of a
+//! unix socket are on this host and seen as `127.0.0.1`, so set `TRUST_PROXY`
+//! for the addresses of clients
+
+use anyhow::Result;
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use {
+    anyhow::bail,
+    axum::serve::Listener,
+    dotenv::var,
+    std::{
+        fs::Permissions,
+        io,
+        net::{IpAddr, Ipv4Addr, SocketAddr},
+        os::{
+            fd::{FromRawFd, IntoRawFd},
+            unix::fs::{FileTypeExt, PermissionsExt},
+        },
+        path::Path,
+    },
+    tokio::net::{UnixListener, UnixStream},
+};
+
+#[cfg(unix)]
+use crate::config::UNIX_SOCKET_MODE;
+
+/// Peer address of every connection of a unix socket
+#[cfg(unix)]
+const LOCAL_PEER: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+/// First inherited socket, see sd_listen_fds(3)
+#[cfg(unix)]
+const SD_LISTEN_FDS_START: i32 = 3;
+
+pub enum Bound {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(LocalListener),
+}
+
+impl Bound {
+    /// Where a browser on this host reaches the server, the unspecified
+    /// address of a TCP socket bound on all interfaces taken as `localhost`
+    pub fn url(&self, tls: bool) -> String {
+        let scheme = if tls { "https" } else { "http" };
+        match self {
+            Bound::Tcp(tcp) => match tcp.local_addr() {
+                Ok(addr) if addr.ip().is_unspecified() => {
+                    format!("{}://localhost:{}", scheme, addr.port())
+                }
+                Ok(addr) => format!("{}://{}", scheme, addr),
+                Err(_) => format!("{}://localhost", scheme),
+            },
+            #[cfg(unix)]
+            Bound::Unix(unix) => match unix.0.local_addr() {
+                Ok(addr) => match addr.as_pathname() {
+                    Some(path) => format!("unix:{}", path.display()),
+                    None => "unix:".to_owned(),
+                },
+                Err(_) => "unix:".to_owned(),
+            },
+        }
+    }
+}
+
+pub async fn bind(addr: &str) -> Result<Bound> {
+    #[cfg(unix)]
+    {
+        if let Some(bound) = systemd()? {
+            return Ok(bound);
+        }
+        if let Some(path) = addr.strip_prefix("unix:") {
+            return Ok(Bound::Unix(unix(Path::new(path))?));
+        }
+    }
+    Ok(Bound::Tcp(TcpListener::bind(addr).await?))
+}
+
+/// The first socket systemd passed to this process, if any
+#[cfg(unix)]
+fn systemd() -> Result<Option<Bound>> {
+    let ours = var("LISTEN_PID")
+        .ok()
+        .and_then(|x| x.parse::<u32>().ok())
+        .is_some_and(|x| x == std::process::id());
+    let fds = var("LISTEN_FDS")
+        .ok()
+        .and_then(|x| x.parse::<u32>().ok())
+        .unwrap_or(0);
+    if !ours || fds == 0 {
+        return Ok(None);
+    }
+    if fds > 1 {
+        tracing::warn!("systemd passed {} sockets, only the first is used", fds);
+    }
+
+    // SAFETY: LISTEN_PID names this process, so systemd opened the socket at
+    // SD_LISTEN_FDS_START for it and nothing else in the process owns it
+    let unix = unsafe { std::os::unix::net::UnixListener::from_raw_fd(SD_LISTEN_FDS_START) };
+    // the address of a TCP socket is not one of a unix socket
+    if unix.local_addr().is_ok() {
+        unix.set_nonblocking(true)?;
+        tracing::info!("listening on the unix socket of systemd");
+        return Ok(Some(Bound::Unix(LocalListener(UnixListener::from_std(
+            unix,
+        )?))));
+    }
+    // SAFETY: taken back from the listener above, which no longer owns it
+    let tcp = unsafe { std::net::TcpListener::from_raw_fd(unix.into_raw_fd()) };
+    tcp.set_nonblocking(true)?;
+    tracing::info!("listening on {} of systemd", tcp.local_addr()?);
+    Ok(Some(Bound::Tcp(TcpListener::from_std(tcp)?)))
+}
+
+/// Bind the socket with `UNIX_SOCKET_MODE`, replacing the one of a previous run
+#[cfg(unix)]
+fn unix(path: &Path) -> Result<LocalListener> {
+    if std::fs::symlink_metadata(path).is_ok_and(|x| x.file_type().is_socket()) {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            bail!("{} is in use by another server", path.display());
+        }
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    let mode = var("UNIX_SOCKET_MODE")
+        .ok()
+        .and_then(|x| u32::from_str_radix(&x, 8).ok())
+        .unwrap_or(UNIX_SOCKET_MODE);
+    std::fs::set_permissions(path, Permissions::from_mode(mode))?;
+    tracing::info!("listening on {}", path.display());
+    Ok(LocalListener(listener))
+}
+
+/// Unix socket giving its peers an IP address, which handlers expect
+#[cfg(unix)]
+pub struct LocalListener(UnixListener);
+
+#[cfg(unix)]
+impl Listener for LocalListener {
+    type Io = UnixStream;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        let (io, _) = Listener::accept(&mut self.0).await;
+        (io, LOCAL_PEER)
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(LOCAL_PEER)
+    }
+}
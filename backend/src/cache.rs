@@ -0,0 +1,120 @@
+//! Copies of rows read on most requests, kept [`CACHE_TTL`] seconds
+//!
+//! `auth` alone read the session, the workspace membership and the user twice
+//! per request. Writes through this instance invalidate their entries; those of
+//! other instances sharing the database and of the command line apply once the
+//! entries expire. Chats are not cached, too many places write them
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use entity::{model, user};
+use tokio::time::Instant;
+
+use crate::config::{CACHE_CAPACITY, CACHE_TTL};
+
+const TTL: Duration = Duration::from_secs(CACHE_TTL);
+
+pub struct Cache<K, V> {
+    map: Mutex<HashMap<K, (Instant, V)>>,
+    /// Bumped by every invalidation, so a load that started before one is not
+    /// cached
+    generation: AtomicU64,
+}
+
+impl<K, V> Default for Cache<K, V> {
+    fn default() -> Self {
+        Self {
+            map: Mutex::new(HashMap::new()),
+            generation: AtomicU64::new(0),
+        }
+    }
+}
+
+impl<K: Eq + Hash, V: Clone> Cache<K, V> {
+    pub fn get(&self, key: &K) -> Option<V> {
+        let map = self.map.lock().unwrap();
+        map.get(key)
+            .filter(|(at, _)| at.elapsed() < TTL)
+            .map(|(_, value)| value.clone())
+    }
+
+    /// The cached value of `key`, else the one `load` read, cached if it
+    /// succeed
+    pub async fn get_or_load<E>(
+        &self,
+        key: K,
+        load: impl Future<Output = Result<V, E>>,
+    ) -> Result<V, E> {
+        if let Some(value) = self.get(&key) {
+            return Ok(value);
+        }
+        let generation = self.generation.load(Ordering::Acquire);
+        let value = load.await?;
+        let mut map = self.map.lock().unwrap();
+        if self.generation.load(Ordering::Acquire) == generation {
+            if map.len() >= CACHE_CAPACITY {
+                map.retain(|_, (at, _)| at.elapsed() < TTL);
+            }
+            if map.len() < CACHE_CAPACITY {
+                map.insert(key, (Instant::now(), value.clone()));
+            }
+        }
+        Ok(value)
+    }
+
+    pub fn invalidate(&self, key: &K) {
+        let mut map = self.map.lock().unwrap();
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        map.remove(key);
+    }
+
+    pub fn invalidate_if(&self, f: impl Fn(&K, &V) -> bool) {
+        let mut map = self.map.lock().unwrap();
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        map.retain(|key, (_, value)| !f(key, value));
+    }
+
+    pub fn clear(&self) {
+        self.invalidate_if(|_, _| true);
+    }
+}
+
+#[derive(Default)]
+pub struct Caches {
+    /// Rows of `user` by id, for the role and the preference; None for
+    /// deleted users
+    pub users: Cache<i32, Option<user::Model>>,
+    /// User and expiry of sessions by id, None for unknown ones
+    pub sessions: Cache<i32, Option<(i32, i64)>>,
+    /// Whether a user is a member of a workspace, by workspace and user
+    pub members: Cache<(i32, i32), bool>,
+    /// Every row of `model`
+    pub models: Cache<(), Arc<Vec<model::Model>>>,
+}
+
+impl Caches {
+    /// Drop what is cached of a user, after their row, sessions or
+    /// memberships changed
+    pub fn forget_user(&self, user_id: i32) {
+        self.users.invalidate(&user_id);
+        self.sessions
+            .invalidate_if(|_, x| x.is_some_and(|(id, _)| id == user_id));
+        self.members.invalidate_if(|(_, id), _| *id == user_id);
+    }
+
+    /// Drop everything, after the database was replaced
+    pub fn clear(&self) {
+        self.users.clear();
+        self.sessions.clear();
+        self.members.clear();
+        self.models.clear();
+    }
+}
@@ -0,0 +1,297 @@
+//! Subcommands of the binary, for operators managing an instance from a shell
+//!
+//! Without a subcommand the server starts as `serve` would. The others open
+//! `DATABASE_URL` themselves, so they work with the server stopped or running,
+//! and exit once done; what they change is written to the audit log without a
+//! user
+
+use std::{
+    io::{BufRead, Write},
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result, bail};
+use clap::{Parser, Subcommand};
+use entity::{AuditKind, UserRole, chat, prelude::*, user};
+use migration::MigratorTrait;
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, DbConn, EntityTrait, QueryFilter, QueryOrder, TransactionTrait,
+};
+use serde_json::{Map, Value};
+
+use crate::{
+    app, audit, backup,
+    config::Settings,
+    database,
+    files::Files,
+    utils::{
+        export::Transcript,
+        instance,
+        keyring::Keyring,
+        password_hash::Hasher,
+        secrets::{self, Secrets},
+        session, workspace,
+    },
+};
+
+#[derive(Debug, Parser)]
+#[command(about = "llumen server and administration")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Start the server, the default
+    Serve,
+    /// Create an admin account
+    CreateAdmin {
+        username: String,
+        /// Read from stdin when left out, keeping it out of the shell history
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Set the password of an account and sign it out everywhere
+    ResetPassword {
+        username: String,
+        /// Read from stdin when left out
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Encrypt new tokens with a new key, restart the server to use it
+    ///
+    /// Tokens of the old key are accepted until they expire, see
+    /// `utils::keyring`; `/api/admin/keys/rotate` does the same without a
+    /// restart
+    RotatePasetoKey,
+    /// Apply pending database migrations, without starting the server
+    Migrate,
+    /// Write every chat as JSON, in the format of `/api/user/export`
+    ExportData {
+        /// Only the chats of this user, as an array; otherwise an object of
+        /// the chats of every user by name
+        #[arg(long)]
+        user: Option<String>,
+        /// Write here instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Write a tar archive of the database and the uploaded files, as
+    /// `/api/admin/backup`; the server may keep running
+    Backup {
+        /// Write here instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Restore a fresh instance from the archive of `backup`, then start the
+    /// server
+    Restore { input: PathBuf },
+}
+
+/// Run a subcommand other than `serve`
+pub async fn run(command: Command) -> Result<()> {
+    let database_url = app::database_url();
+    if let Command::Migrate = command {
+        migration::migrate(&database_url).await?;
+        // credentials written before `utils::secrets` are sealed on start too
+        let conn = database::connect(&database_url).await?;
+        let secrets = Secrets::load(&conn).await?;
+        let sealed = secrets::seal_existing(&conn, &secrets).await?;
+        Settings::load(conn, secrets).await?;
+        eprintln!("database is up to date, {} credentials sealed", sealed);
+        return Ok(());
+    }
+
+    let conn = database::connect(&database_url).await?;
+    match command {
+        Command::Serve | Command::Migrate => unreachable!(),
+        Command::CreateAdmin { username, password } => {
+            create_admin(&conn, username, password).await
+        }
+        Command::ResetPassword { username, password } => {
+            reset_password(&conn, username, password).await
+        }
+        Command::RotatePasetoKey => rotate_paseto_key(&conn).await,
+        Command::ExportData { user, output } => export_data(&conn, user, output).await,
+        Command::Backup { output } => backup(&conn, output).await,
+        Command::Restore { input } => restore(&conn, input).await,
+    }
+}
+
+async fn create_admin(conn: &DbConn, username: String, password: Option<String>) -> Result<()> {
+    if find_user(conn, &username).await?.is_some() {
+        bail!("user {} exists, use reset-password", username);
+    }
+    let password = password_or_stdin(password)?;
+    let txn = conn.begin().await?;
+    let user_id = User::insert(user::ActiveModel {
+        name: Set(username.clone()),
+        password: Set(Hasher::default().hash_password(&password).await),
+        role: Set(UserRole::Admin),
+        email_verified: Set(true),
+        ..Default::default()
+    })
+    .exec(&txn)
+    .await?
+    .last_insert_id;
+    workspace::join(&txn, workspace::DEFAULT_WORKSPACE, user_id).await?;
+    txn.commit().await?;
+
+    let detail = format!("user {} created as Admin from the command line", username);
+    audit::record(conn, AuditKind::User, None, detail).await;
+    eprintln!("admin {} created", username);
+    Ok(())
+}
+
+async fn reset_password(conn: &DbConn, username: String, password: Option<String>) -> Result<()> {
+    let user = find_user(conn, &username)
+        .await?
+        .with_context(|| format!("cannot find user {}", username))?;
+    let password = password_or_stdin(password)?;
+
+    let txn = conn.begin().await?;
+    User::update(user::ActiveModel {
+        id: Set(user.id),
+        password: Set(Hasher::default().hash_password(&password).await),
+        ..Default::default()
+    })
+    .exec(&txn)
+    .await?;
+    let revoked = session::revoke_all(&txn, user.id).await?;
+    txn.commit().await?;
+
+    let detail = format!("password of {} reset from the command line", username);
+    audit::record(conn, AuditKind::User, None, detail).await;
+    eprintln!(
+        "password of {} reset, {} sessions revoked",
+        username, revoked
+    );
+    Ok(())
+}
+
+async fn rotate_paseto_key(conn: &DbConn) -> Result<()> {
+    let retired = Keyring::load(conn.clone()).await?.rotate().await?;
+
+    let detail = "paseto key rotated from the command line".to_owned();
+    audit::record(conn, AuditKind::Config, None, detail).await;
+    eprintln!(
+        "paseto key rotated, {} older keys still accepted; restart the server to use it",
+        retired
+    );
+    Ok(())
+}
+
+async fn export_data(conn: &DbConn, user: Option<String>, output: Option<PathBuf>) -> Result<()> {
+    let instance_id = instance::instance_id(conn).await?;
+    let body = match user {
+        Some(username) => {
+            let user = find_user(conn, &username)
+                .await?
+                .with_context(|| format!("cannot find user {}", username))?;
+            transcripts(conn, &instance_id, user.id).await?
+        }
+        None => {
+            let users = User::find()
+                .order_by_asc(user::Column::Id)
+                .all(conn)
+                .await?;
+            let mut all = Map::new();
+            for user in users {
+                all.insert(user.name, transcripts(conn, &instance_id, user.id).await?);
+            }
+            Value::Object(all)
+        }
+    };
+    let body = serde_json::to_vec_pretty(&body)?;
+
+    match output {
+        Some(path) => {
+            std::fs::write(&path, body)?;
+            eprintln!("exported to {}", path.display());
+        }
+        None => std::io::stdout().write_all(&body)?,
+    }
+    Ok(())
+}
+
+async fn backup(conn: &DbConn, output: Option<PathBuf>) -> Result<()> {
+    let files = Files::from_env()?;
+    let snapshot = backup::Snapshot::take(conn).await?;
+    match &output {
+        Some(path) => {
+            let out = tokio::fs::File::create(path).await?;
+            snapshot.write(conn, &files, out).await?;
+            eprintln!("backed up to {}", path.display());
+        }
+        None => snapshot.write(conn, &files, tokio::io::stdout()).await?,
+    }
+    audit::record(
+        conn,
+        AuditKind::Config,
+        None,
+        "backup taken from the command line".to_owned(),
+    )
+    .await;
+    Ok(())
+}
+
+async fn restore(conn: &DbConn, input: PathBuf) -> Result<()> {
+    // a fresh instance may not have been started yet
+    migration::Migrator::up(conn, None).await?;
+    let files = Files::from_env()?;
+    let archive = tokio::fs::File::open(&input)
+        .await
+        .with_context(|| format!("cannot open {}", input.display()))?;
+    let restored = backup::restore(conn, &files, tokio::io::BufReader::new(archive)).await?;
+
+    let detail = format!(
+        "backup restored from the command line, {} users, {} chats and {} files",
+        restored.users, restored.chats, restored.files
+    );
+    audit::record(conn, AuditKind::Config, None, detail).await;
+    eprintln!(
+        "restored {} users, {} chats and {} files",
+        restored.users, restored.chats, restored.files
+    );
+    Ok(())
+}
+
+/// Every chat owned by the user, as `/api/user/export` sends them
+async fn transcripts(conn: &DbConn, instance_id: &str, user_id: i32) -> Result<Value> {
+    let chats = Chat::find()
+        .filter(chat::Column::OwnerId.eq(user_id))
+        .order_by_asc(chat::Column::Id)
+        .all(conn)
+        .await?;
+    let mut transcripts = Vec::with_capacity(chats.len());
+    for chat in &chats {
+        transcripts.push(Transcript::load(conn, instance_id.to_owned(), chat, user_id).await?);
+    }
+    Ok(serde_json::to_value(transcripts)?)
+}
+
+async fn find_user(conn: &DbConn, username: &str) -> Result<Option<user::Model>> {
+    Ok(User::find()
+        .filter(user::Column::Name.eq(username))
+        .one(conn)
+        .await?)
+}
+
+/// The first line of stdin unless given, so it can be piped in
+fn password_or_stdin(password: Option<String>) -> Result<String> {
+    let password = match password {
+        Some(x) => x,
+        None => {
+            eprint!("password: ");
+            std::io::stderr().flush()?;
+            let mut line = String::new();
+            std::io::stdin().lock().read_line(&mut line)?;
+            line.trim_end_matches(['\r', '\n']).to_owned()
+        }
+    };
+    if password.is_empty() {
+        bail!("the password cannot be empty");
+    }
+    Ok(password)
+}
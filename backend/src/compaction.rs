@@ -0,0 +1,184 @@
+//! Summaries standing in for the older messages of long chats
+//!
+//! Before a reply, a chat whose last prompt took [`COMPACT_RATIO`] of the
+//! context of its model has the messages of its branch but the latest
+//! [`COMPACT_KEEP_MESSAGES`] summarized by `COMPACT_MODEL`, `TITLE_MODEL` or
+//! the model of the chat, the first one set. The history sent is then the
+//! summary followed by the later messages, see `message::create`. Messages
+//! themselves are kept, the user still reads and branches from all of them
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use dotenv::var;
+use entity::{MessageKind, chat, message, patch::ChunkKind, prelude::*};
+use sea_orm::{QueryOrder, prelude::*};
+
+use crate::{
+    AppState,
+    config::{COMPACT_KEEP_MESSAGES, COMPACT_MESSAGE_MAX_CHARS, COMPACT_RATIO},
+    openrouter,
+    utils::branch,
+};
+
+const SUMMARIZE_PROMPT: &str = "You condense conversations between a user and an assistant. \
+Write a summary the assistant can continue the conversation from: the goals of the user, facts, \
+names, numbers and decisions established, results of tools, and questions still open. \
+Merge the earlier summary in if one is given. Write in the language of the conversation, \
+as plain prose without preamble.";
+
+/// The summary of the chat and the last message it cover, if it cover the
+/// branch `ids`
+pub fn summary<'a>(chat: &'a chat::Model, ids: &[i32]) -> Option<(&'a str, i32)> {
+    let until = chat.summary_until?;
+    // a summary of another branch is of no use
+    ids.contains(&until)
+        .then_some(())
+        .and(chat.summary.as_deref())
+        .map(|x| (x, until))
+}
+
+/// Sent before the messages after the summary
+pub fn summary_prompt(summary: &str) -> String {
+    format!(
+        "Earlier messages of this conversation were condensed into this summary:\n\n{}",
+        summary
+    )
+}
+
+/// Summarize the chat if its last prompt came close to the context of the
+/// model, true if the history changed
+pub async fn compact_if_due(
+    app: &Arc<AppState>,
+    chat_id: i32,
+    chat_model: &openrouter::Model,
+) -> Result<bool> {
+    let Some(stat) = ContextStat::find_by_id(chat_id).one(&app.conn).await? else {
+        return Ok(false);
+    };
+    let context_length = app
+        .pricing
+        .context_length(&chat_model.id)
+        .or(stat.context_length.map(|x| x as usize));
+    let Some(context_length) = context_length else {
+        return Ok(false);
+    };
+    if (stat.last_prompt_tokens as f64) < context_length as f64 * COMPACT_RATIO {
+        return Ok(false);
+    }
+    compact(app, chat_id, chat_model).await
+}
+
+/// Summarize the branch but its latest messages, false if nothing was left
+/// to summarize
+pub async fn compact(
+    app: &Arc<AppState>,
+    chat_id: i32,
+    chat_model: &openrouter::Model,
+) -> Result<bool> {
+    let chat = Chat::find_by_id(chat_id)
+        .one(&app.conn)
+        .await?
+        .context("Cannot find the chat")?;
+    let ids = branch::active(&app.conn, chat_id).await?;
+    let Some(until) = ids
+        .len()
+        .checked_sub(COMPACT_KEEP_MESSAGES + 1)
+        .map(|x| ids[x])
+    else {
+        return Ok(false);
+    };
+    let previous = summary(&chat, &ids);
+    let from = previous.map(|(_, x)| x);
+    if from.is_some_and(|x| x >= until) {
+        return Ok(false);
+    }
+    let covered: Vec<i32> = ids
+        .into_iter()
+        .filter(|x| *x <= until && from.is_none_or(|from| *x > from))
+        .collect();
+
+    let transcript = transcript(&app.conn, covered).await?;
+    let mut input = String::new();
+    if let Some((previous, _)) = previous {
+        input.push_str(&format!("Earlier summary:\n{}\n\n", previous));
+    }
+    input.push_str(&format!("Conversation:\n{}", transcript));
+    let completion = app
+        .openrouter
+        .complete(
+            vec![
+                openrouter::Message::System(SUMMARIZE_PROMPT.to_owned()),
+                openrouter::Message::User(input),
+            ],
+            model(chat_model),
+        )
+        .await?;
+    app.spend.add(app, completion.price).await?;
+    let summary = completion.response.trim();
+    if summary.is_empty() {
+        anyhow::bail!("the summary is empty");
+    }
+
+    Chat::update_many()
+        .col_expr(chat::Column::Summary, summary.into())
+        .col_expr(chat::Column::SummaryUntil, until.into())
+        .filter(chat::Column::Id.eq(chat_id))
+        .exec(&app.conn)
+        .await?;
+    tracing::info!("chat {} summarized up to message {}", chat_id, until);
+    Ok(true)
+}
+
+/// Text of the messages, tool calls by their result
+///
+/// The summary is shared by every member, private messages stay out of it
+async fn transcript(conn: &DbConn, ids: Vec<i32>) -> Result<String> {
+    let res = Message::find()
+        .filter(message::Column::Id.is_in(ids))
+        .filter(message::Column::Private.eq(false))
+        .order_by_asc(message::Column::Id)
+        .find_with_related(Chunk)
+        .all(conn)
+        .await?;
+    let mut transcript = String::new();
+    for (message, chunks) in res {
+        let role = match message.kind {
+            MessageKind::User => "User",
+            MessageKind::Assistant => "Assistant",
+            MessageKind::Hidden | MessageKind::Marker => continue,
+        };
+        let mut text = String::new();
+        for chunk in chunks {
+            match chunk.kind {
+                ChunkKind::Text => text.push_str(&chunk.content),
+                ChunkKind::Reasoning => continue,
+                ChunkKind::ToolCall => {
+                    let call = chunk.as_tool_call()?;
+                    text.push_str(&format!("\n[tool {}: {}]\n", call.name, call.content));
+                }
+            }
+        }
+        if let Some((at, _)) = text.char_indices().nth(COMPACT_MESSAGE_MAX_CHARS) {
+            text.truncate(at);
+            text.push('…');
+        }
+        transcript.push_str(&format!("{}: {}\n\n", role, text.trim()));
+    }
+    Ok(transcript)
+}
+
+fn model(chat_model: &openrouter::Model) -> openrouter::Model {
+    openrouter::Model {
+        id: var("COMPACT_MODEL")
+            .or_else(|_| var("TITLE_MODEL"))
+            .unwrap_or(chat_model.id.clone()),
+        temperature: Some(0.2),
+        repeat_penalty: None,
+        top_k: None,
+        top_p: None,
+        seed: None,
+        online: false,
+        reasoning: false,
+    }
+}
@@ -1,2 +0,0 @@
-pub const MAX_SSE_BUF: usize = 64;
-pub const MAX_PAGINATE_LIMIT: u32 = 100;
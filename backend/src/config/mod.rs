@@ -0,0 +1,366 @@
+mod settings;
+
+pub use settings::{RuntimeConfig, Settings};
+
+/// Tokens kept per chat for clients resuming with `Last-Event-ID`
+pub const MAX_SSE_REPLAY: usize = 1024;
+/// Tokens queued per connection before it is dropped as lagged
+pub const SSE_QUEUE_SIZE: usize = 256;
+/// Seconds between SSE ping comments, below the usual 60s proxy read timeout
+pub const SSE_KEEP_ALIVE: u64 = 15;
+/// Seconds between sweeps of chat streams nobody listen to
+pub const SSE_REAP_INTERVAL: u64 = 60;
+pub const MAX_PAGINATE_LIMIT: u32 = 100;
+/// Changes returned by a sync, clients call again while there are more
+pub const SYNC_MAX_CHANGES: u64 = 500;
+pub const SEARCH_MAX_RESULTS: u32 = 50;
+/// Characters of a message shown around the first hit of a search
+pub const SEARCH_SNIPPET_CHARS: usize = 160;
+/// Boosted and ignored terms of a user, each, see `SearchTermKind`
+///
+/// Ignored terms past the 10th are not applied by the index triggers
+pub const SEARCH_MAX_TERMS: usize = 10;
+/// An ignored term can be a whole mail signature
+pub const SEARCH_MAX_TERM_CHARS: usize = 1000;
+/// Bytes of a request body, see `middlewares::body_limit` and
+/// `JSON_BODY_MAX_BYTES` env
+pub const JSON_BODY_MAX_BYTES: usize = 2 * 1024 * 1024;
+/// Bytes of the body of an upload or a chat import, exports of every chat are
+/// large, see `UPLOAD_BODY_MAX_BYTES` env
+pub const UPLOAD_BODY_MAX_BYTES: usize = 64 * 1024 * 1024;
+pub const CHAT_IMPORT_MAX_CHATS: usize = 1000;
+/// Characters of a folder or label name
+pub const FOLDER_NAME_MAX_CHARS: usize = 64;
+/// Labels a chat can have
+pub const CHAT_MAX_LABELS: usize = 16;
+/// Days of activity counted per day and per hour
+pub const ACTIVITY_DAYS: i64 = 365;
+pub const ACTIVITY_HOUR_DAYS: i64 = 14;
+/// Default of `UNDO_WINDOW`, seconds a deletion can be undone
+pub const UNDO_WINDOW_SECS: u64 = 15;
+
+/// Tool calls allowed per assistant turn outside of agent mode, unless set by
+/// `agent_loop` of `admin/config` like the limits below
+pub const MAX_TOOL_STEPS: usize = 8;
+/// Autonomy budget for agent mode
+pub const AGENT_MAX_STEPS: usize = 32;
+/// In USD, as reported by openrouter
+pub const AGENT_MAX_COST: f64 = 0.5;
+pub const AGENT_MAX_SECS: u64 = 600;
+/// Rounds of tool calls a reply can make before the model has to answer
+pub const MAX_TOOL_DEPTH: usize = 32;
+/// Tool calls allowed per delegated subtask
+pub const DELEGATE_MAX_STEPS: usize = 8;
+/// Delegated runs kept in the tool state of a chat
+pub const DELEGATE_MAX_RUNS: usize = 8;
+/// Questions a tool call can ask the user before it fails
+pub const TOOL_INPUT_MAX_ROUNDS: usize = 3;
+/// Seconds a tool call wait for the user to answer
+pub const TOOL_INPUT_TIMEOUT: u64 = 600;
+/// Calls with invalid arguments a reply can fix by calling again, later ones
+/// tell the model to answer without the tool
+pub const TOOL_REPAIR_MAX_ROUNDS: usize = 1;
+/// Default upstream timeouts in seconds, see `UPSTREAM_*_TIMEOUT` env
+pub const UPSTREAM_CONNECT_TIMEOUT: u64 = 10;
+/// Max silence between two streamed events
+pub const UPSTREAM_IDLE_TIMEOUT: u64 = 60;
+pub const UPSTREAM_TOTAL_TIMEOUT: u64 = 900;
+/// Demo mode limits, per IP and hour
+pub const DEMO_SESSIONS_PER_HOUR: u32 = 3;
+pub const DEMO_MESSAGES_PER_HOUR: u32 = 20;
+/// In USD, as reported by openrouter
+pub const DEMO_COST_PER_HOUR: f64 = 0.05;
+/// Demo accounts are purged after that, same as the token lifetime
+pub const DEMO_SESSION_SECS: i64 = 3600;
+pub const DEMO_CAPTCHA_SECS: u64 = 300;
+pub const DEMO_PURGE_INTERVAL: u64 = 300;
+/// Lifetime of the token a federated request carry
+pub const FEDERATION_TOKEN_SECS: u64 = 60;
+/// Seconds between syncs of model prices
+pub const PRICE_SYNC_INTERVAL: u64 = 6 * 3600;
+/// Seconds between runs of the chat tagger, see `TAG_MODEL`
+pub const TAG_INTERVAL: u64 = 10 * 60;
+/// Seconds a chat stay without new messages before it is tagged
+pub const TAG_IDLE_SECS: i64 = 30 * 60;
+/// Chats tagged per run, a backlog is worked through over several runs
+pub const TAG_BATCH: u64 = 20;
+/// Characters of the transcript the tagger read, the start tell the topic
+pub const TAG_MAX_CHARS: usize = 12_000;
+/// Most frequent topics listed by the tag counts
+pub const TAG_TOP_TOPICS: u64 = 20;
+/// Lifetime of access tokens, renewed with a refresh token
+pub const ACCESS_TOKEN_SECS: u64 = 15 * 60;
+pub const REFRESH_TOKEN_SECS: i64 = 30 * 24 * 3600;
+/// Seconds a rotated PASETO key still decrypts tokens, the longest a token
+/// lives (demo tokens)
+pub const PASETO_KEY_RETAIN_SECS: i64 = 3600;
+/// Seconds an `Idempotency-Key` of `message/create` is remembered
+pub const IDEMPOTENCY_TTL: u64 = 24 * 3600;
+pub const IDEMPOTENCY_KEY_MAX_LEN: usize = 255;
+/// Seconds a prefetched chat history is kept for the next message
+pub const PREFETCH_TTL: u64 = 120;
+/// Tokens of a streamed chunk between checkpoints of its text
+pub const STREAM_CHECKPOINT_TOKENS: usize = 64;
+/// Milliseconds at most between checkpoints of a streamed chunk
+pub const STREAM_CHECKPOINT_INTERVAL_MS: u64 = 2000;
+/// Connections of the pool of an SQLite database unless `DB_POOL_SIZE` says
+/// otherwise, WAL lets them read while one writes
+pub const SQLITE_POOL_SIZE: u32 = 4;
+/// Milliseconds an SQLite connection waits for a lock, see `database`
+pub const SQLITE_BUSY_TIMEOUT_MS: u64 = 5000;
+/// Retries of a write that still found the database locked
+pub const DB_BUSY_RETRIES: u32 = 5;
+/// Milliseconds before the first retry of a locked write, doubled on each
+pub const DB_BUSY_BACKOFF_MS: u64 = 20;
+/// Seconds rows read on most requests are cached, how long writes of other
+/// instances take to apply, see `cache`
+pub const CACHE_TTL: u64 = 10;
+/// Entries of each cache before the expired ones are dropped
+pub const CACHE_CAPACITY: usize = 10_000;
+/// Seconds a user has to finish a social login
+pub const OAUTH_STATE_SECS: u64 = 600;
+/// Time steps a TOTP code is still accepted before or after its own
+pub const TOTP_SKEW: i64 = 1;
+/// Recovery codes given when enabling TOTP
+pub const TOTP_RECOVERY_CODES: usize = 10;
+/// Share of the cgroup memory limit or of the open files limit that trigger a warning
+pub const SYSTEM_WARN_RATIO: f64 = 0.9;
+/// Lifetime of a mailed password reset link
+pub const PASSWORD_RESET_SECS: i64 = 3600;
+/// Accounts deleted by their owner are kept this long, signing in cancel the deletion
+pub const ACCOUNT_PURGE_GRACE_SECS: i64 = 24 * 3600;
+/// Lifetime of the confirmation token of an account deletion
+pub const ACCOUNT_PURGE_CONFIRM_SECS: u64 = 600;
+/// Seconds between sweeps of accounts past their grace period
+pub const ACCOUNT_PURGE_INTERVAL: u64 = 300;
+/// Seconds between sweeps of the chats past the retention policy
+pub const RETENTION_INTERVAL: u64 = 3600;
+/// Owners are told this long before their idle chats are deleted
+pub const RETENTION_GRACE_SECS: i64 = 7 * 24 * 3600;
+/// Titles listed in the notice mail, the rest are counted
+pub const RETENTION_NOTICE_TITLES: usize = 10;
+/// Days the output of tool calls is kept without `TOOL_LOG_DAYS`
+pub const TOOL_LOG_DAYS: u32 = 30;
+/// Lifetime of a mailed email verification link
+pub const EMAIL_VERIFICATION_SECS: i64 = 24 * 3600;
+/// Seconds between updates of the last use of an API key
+pub const API_KEY_TOUCH_SECS: i64 = 60;
+/// Response bytes a declared tool give to the model, the rest is cut
+pub const DECLARED_TOOL_MAX_BYTES: usize = 16 * 1024;
+pub const DECLARED_TOOL_TIMEOUT: u64 = 30;
+/// Bytes of JSON a chat variable can hold, tools fail past it
+pub const CHAT_VARIABLE_MAX_BYTES: usize = 4 * 1024;
+/// Variables a chat can hold, new keys are refused past it
+pub const CHAT_VARIABLE_MAX_KEYS: u64 = 32;
+/// Share of the context of the model above which a prompt counts as hitting the ceiling
+pub const CONTEXT_CEILING_RATIO: f64 = 0.9;
+/// Completions in a row hitting the ceiling before the user is warned
+pub const CONTEXT_ALERT_STREAK: i32 = 3;
+/// Share of the context of the model above which older messages are summarized
+pub const COMPACT_RATIO: f64 = 0.75;
+/// Latest messages of the branch always sent as they are
+pub const COMPACT_KEEP_MESSAGES: usize = 6;
+/// Characters of a message the summarizer read, the rest is cut
+pub const COMPACT_MESSAGE_MAX_CHARS: usize = 4_000;
+/// Failed logins allowed for an account before it is locked out
+pub const LOGIN_FREE_FAILURES_ACCOUNT: i32 = 5;
+/// Failed logins allowed from an IP, higher since users can share one behind NAT
+pub const LOGIN_FREE_FAILURES_IP: i32 = 20;
+/// First lockout, doubled on every further failure
+pub const LOGIN_LOCKOUT_SECS: i64 = 30;
+pub const LOGIN_LOCKOUT_MAX_SECS: i64 = 3600;
+/// Failures older than this are forgotten
+pub const LOGIN_FAILURE_WINDOW_SECS: i64 = 24 * 3600;
+/// Hours before the current one whose average spend the spend guard compare to
+pub const SPEND_TRAILING_HOURS: i64 = 24;
+/// Default of `SPEND_GUARD_MIN`, in USD, an hour below it never pause generations
+pub const SPEND_GUARD_MIN_COST: f64 = 1.0;
+/// Share of a monthly spending cap past which the user or the admins are warned
+pub const SPEND_CAP_WARN_RATIO: f64 = 0.8;
+/// Days deleted chats and messages stay in the trash before they are purged
+pub const TRASH_DAYS: i64 = 30;
+/// Seconds between sweeps of the trash
+pub const TRASH_PURGE_INTERVAL: u64 = 3600;
+/// Characters of a deleted message listed in the trash
+pub const TRASH_TITLE_CHARS: usize = 80;
+/// Bytes an uploaded file can have
+pub const FILE_MAX_BYTES: u64 = 20 * 1024 * 1024;
+/// Files a message can be sent with
+pub const FILE_MAX_PER_MESSAGE: usize = 8;
+/// Characters kept of the name of an uploaded file
+pub const FILE_NAME_MAX_CHARS: usize = 255;
+/// Files attached to no message are purged once this old
+pub const FILE_ORPHAN_SECS: i64 = 24 * 3600;
+/// Seconds between sweeps of the files
+pub const FILE_SWEEP_INTERVAL: u64 = 3600;
+/// Bytes of a backup written ahead of the client downloading it
+pub const BACKUP_STREAM_BUFFER: usize = 256 * 1024;
+/// Seconds browsers cache a CORS preflight, see `CORS_MAX_AGE` env
+pub const CORS_MAX_AGE: u64 = 600;
+/// Bytes of a recording sent to `chat/{id}/voice`, the limit of the Whisper API
+pub const VOICE_MAX_BYTES: usize = 25 * 1024 * 1024;
+/// Characters of a reply read aloud by `message/{id}/audio`, the limit of the
+/// OpenAI speech API
+pub const TTS_MAX_CHARS: usize = 4096;
+/// Seconds a provider has to generate an image, they commonly take a minute
+pub const IMAGE_TIMEOUT: u64 = 180;
+/// Largest generated image stored, in bytes
+pub const IMAGE_MAX_BYTES: usize = 16 * 1024 * 1024;
+/// Seconds a moderation provider has to answer before the text is let through
+pub const MODERATION_TIMEOUT: u64 = 10;
+/// Tokens of a streamed reply between checks against the moderation rules
+pub const MODERATION_OUTPUT_TOKENS: usize = 32;
+/// Characters kept of the comment of a rating
+pub const FEEDBACK_COMMENT_MAX_CHARS: usize = 2000;
+/// Latest comments listed by `admin/feedback`
+pub const FEEDBACK_COMMENTS: u64 = 50;
+/// Characters of the system prompt of a chat, see `chat/{id}/settings`
+pub const SYSTEM_PROMPT_MAX_CHARS: usize = 20_000;
+/// Characters of the draft of a chat, see `chat/{id}/draft`
+pub const CHAT_DRAFT_MAX_CHARS: usize = 100_000;
+/// Characters kept of the selection sent to `capture`
+pub const CAPTURE_SELECTION_MAX_CHARS: usize = 20_000;
+/// Messages a request of `v1/chat/completions` can carry
+pub const COMPLETIONS_MAX_MESSAGES: usize = 200;
+/// Notifications a slow stream of `user/notifications` can fall behind by
+pub const NOTIFY_CAPACITY: usize = 16;
+/// Seconds between checks for due scheduled tasks
+pub const SCHEDULE_INTERVAL: u64 = 30;
+/// Scheduled tasks a user can have
+pub const SCHEDULE_MAX_PER_USER: u64 = 20;
+/// Characters of the prompt of a scheduled task
+pub const SCHEDULE_PROMPT_MAX_CHARS: usize = 20_000;
+/// Characters of the name of a scheduled task
+pub const SCHEDULE_NAME_MAX_CHARS: usize = 100;
+/// Days ahead the calendar feed lists the runs of scheduled tasks for
+pub const CALENDAR_DAYS: i64 = 30;
+/// Runs of a scheduled task the calendar feed lists at most
+pub const CALENDAR_MAX_RUNS: usize = 50;
+/// Personas a user can have
+pub const PERSONA_MAX_PER_USER: u64 = 50;
+/// Characters of the name of a persona
+pub const PERSONA_NAME_MAX_CHARS: usize = 100;
+/// Characters of the prompt of a persona
+pub const PERSONA_PROMPT_MAX_CHARS: usize = 20_000;
+/// Documents a user can add to their knowledge base
+pub const KB_MAX_DOCUMENTS_PER_USER: u64 = 100;
+/// Characters of a chunk of a document, each is embedded on its own
+pub const KB_CHUNK_CHARS: usize = 1500;
+/// Characters a chunk repeat of the one before, so a passage cut in two is
+/// still found whole
+pub const KB_CHUNK_OVERLAP_CHARS: usize = 200;
+/// Chunks added to the prompt of a reply at most
+pub const KB_TOP_K: usize = 5;
+/// Cosine similarity a chunk need with the text of the user to be added
+pub const KB_MIN_SCORE: f32 = 0.3;
+/// Characters of the text of the user embedded to search the knowledge base
+pub const KB_QUERY_MAX_CHARS: usize = 2000;
+/// Facts kept about a user, see `memory`
+pub const MEMORY_MAX_PER_USER: u64 = 200;
+/// Characters of a fact
+pub const MEMORY_FACT_MAX_CHARS: usize = 500;
+/// Facts added to the system prompt of a reply at most
+pub const MEMORY_PROMPT_FACTS: usize = 20;
+/// Facts `recallfacts` return at most
+pub const MEMORY_RECALL_LIMIT: usize = 20;
+/// Contacts in the address book of a user, see `tools::contacts`
+pub const CONTACT_MAX_PER_USER: usize = 500;
+/// Characters of the name, address or note of a contact
+pub const CONTACT_FIELD_MAX_CHARS: usize = 200;
+/// Contacts `findcontact` and `listcontacts` return at most
+pub const CONTACT_LIST_LIMIT: usize = 50;
+/// Turns `directions` describe at most
+pub const DIRECTIONS_MAX_STEPS: usize = 20;
+/// Seconds of each request of `directions` to the geocoder and the router
+pub const DIRECTIONS_TIMEOUT: u64 = 15;
+/// Bytes a compressed part of a DOCX file, or the streams of a PDF file
+/// together, can inflate to, so a small file cannot fill the memory
+pub const EXTRACT_MAX_INFLATED_BYTES: usize = 64 * 1024 * 1024;
+/// Characters of the text of an attached document sent to the model
+pub const FILE_TEXT_MAX_CHARS: usize = 200_000;
+/// Collections a user can sort their documents in
+pub const KB_MAX_COLLECTIONS_PER_USER: u64 = 50;
+/// Requests a user, or an IP without a token, can make per minute before the
+/// policy is set by an admin, see `middlewares::rate_limit`
+pub const RATE_LIMIT_PER_MINUTE: u32 = 600;
+/// Requests that can be made at once after a pause
+pub const RATE_LIMIT_BURST: u32 = 120;
+/// Buckets kept before the full ones are dropped
+pub const RATE_LIMIT_MAX_KEYS: usize = 10_000;
+/// Upper bounds in seconds of the buckets timing requests, see
+/// `middlewares::metrics`
+pub const METRICS_HTTP_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+/// Upper bounds in seconds of the buckets timing calls to the upstream
+pub const METRICS_UPSTREAM_BUCKETS: &[f64] = &[0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+/// Days entries of the audit log are kept, see `audit`
+pub const AUDIT_DAYS: i64 = 90;
+/// Seconds between purges of old audit entries
+pub const AUDIT_PURGE_INTERVAL: u64 = 3600;
+/// Audit entries `admin/audit` return at most per page
+pub const AUDIT_PAGE: u64 = 50;
+/// Days the stats of replies are kept, the longest range of `admin/stats`
+pub const STATS_DAYS: u32 = 90;
+/// Seconds between purges of the stats of old replies
+pub const STATS_PURGE_INTERVAL: u64 = 3600;
+/// Tools and models `admin/stats` list at most, most used first
+pub const STATS_TOP: usize = 20;
+/// Bytes a JSON response needs to be compressed, smaller ones gain little
+/// over the gzip header, see `middlewares::compression`
+pub const COMPRESS_MIN_BYTES: u64 = 1024;
+/// Seconds between batches of spans sent to the OTLP collector, see `telemetry`
+pub const OTEL_EXPORT_INTERVAL: u64 = 5;
+/// Spans waiting to be sent, later ones are dropped while the collector is down
+pub const OTEL_MAX_QUEUE: usize = 8192;
+/// Logs kept as events of a span, a long stream would log far more
+pub const OTEL_MAX_EVENTS: usize = 128;
+/// Seconds a client has to finish the TLS handshake, see `tls`
+pub const TLS_HANDSHAKE_TIMEOUT: u64 = 10;
+/// Seconds between checks of the certificate files for a renewal
+pub const TLS_RELOAD_INTERVAL: u64 = 60;
+/// Permissions of a unix socket of `BIND_ADDR`, read and write for the group of
+/// the reverse proxy, see `UNIX_SOCKET_MODE` env
+pub const UNIX_SOCKET_MODE: u32 = 0o660;
+/// Workers running the jobs of `jobs`
+pub const JOB_WORKERS: usize = 4;
+/// Seconds an idle worker waits before looking for due jobs again, a new job
+/// wakes one earlier
+pub const JOB_POLL_INTERVAL: u64 = 5;
+/// Seconds a claimed job is left to its worker before another one runs it
+pub const JOB_LEASE_SECS: i64 = 15 * 60;
+/// Attempts of a job before it is given up
+pub const JOB_MAX_ATTEMPTS: i32 = 5;
+/// Seconds before the first retry of a failed job, doubled on each failure
+pub const JOB_BACKOFF_SECS: i64 = 30;
+/// Jobs `admin/job/list` return at most
+pub const JOB_PAGE: u64 = 100;
+/// Webhooks a user registers at most, see `webhook`
+pub const WEBHOOK_MAX_PER_USER: u64 = 10;
+/// Seconds a webhook has to answer a delivery before it is retried
+pub const WEBHOOK_TIMEOUT: u64 = 10;
+/// Browsers a user subscribes to Web Push at most, see `push`
+pub const PUSH_MAX_PER_USER: u64 = 20;
+/// Seconds a push service keeps a message for a browser that is offline
+pub const PUSH_TTL: u64 = 24 * 3600;
+/// Seconds a push service has to accept a message before it is retried
+pub const PUSH_TIMEOUT: u64 = 10;
+/// Characters of a reply shown in its notification
+pub const PUSH_BODY_CHARS: usize = 160;
+/// Seconds open connections get to finish on a graceful shutdown, streamed
+/// replies would hold it forever otherwise
+pub const SHUTDOWN_TIMEOUT: u64 = 5;
+/// Rules of `user/routing` a user keeps at most, see `routing`
+pub const ROUTING_MAX_RULES: usize = 20;
+/// Characters of the text a keyword rule looks for
+pub const ROUTING_KEYWORD_MAX_CHARS: usize = 100;
+/// Lines that look like code before a message without a fenced block counts as
+/// code
+pub const ROUTING_CODE_LINES: usize = 3;
+/// Characters taken as one token when the size of a message is estimated
+pub const ROUTING_CHARS_PER_TOKEN: usize = 4;
+/// Messages of the inbox of a user kept at most, the oldest go first, see
+/// `notify::inbox`
+pub const INBOX_MAX_PER_USER: u64 = 200;
+/// Messages `/api/notifications` return at most
+pub const INBOX_PAGE: u64 = 50;
@@ -0,0 +1,133 @@
+//! Settings admins change at runtime with `admin/config`, kept in `config`
+//!
+//! The current [`RuntimeConfig`] is in a watch channel: what is read on use,
+//! like the default model, is read from [`Settings::current`], and what is
+//! built from it, like the upstream client of `Openrouter` or the buckets of
+//! `RateLimiter`, is rebuilt by a task following [`Settings::subscribe`]. A
+//! field left unset falls back to its env, so an instance never configured
+//! this way runs as before
+//!
+//! The API keys are stored sealed by `utils::secrets`
+
+use anyhow::Result;
+use entity::{config, prelude::*};
+use sea_orm::{ActiveValue::Set, DbConn, EntityTrait, sea_query::OnConflict};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use typeshare::typeshare;
+
+use crate::{
+    middlewares::rate_limit::RateLimitPolicy, moderation::ModerationPolicy,
+    routes::message::budget::AgentLoopPolicy, utils::secrets::Secrets,
+};
+
+const KEY: &str = "runtime_config";
+/// Where the rate limit was kept before the other settings
+const LEGACY_RATE_LIMIT_KEY: &str = "rate_limit";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[typeshare]
+#[serde(default)]
+pub struct RuntimeConfig {
+    /// Model new chats start with until the user picks one
+    pub default_model_id: Option<i32>,
+    /// Requests per user or IP, see `middlewares::rate_limit`
+    pub rate_limit: RateLimitPolicy,
+    /// Override `API_BASE` env
+    pub api_base: Option<String>,
+    /// Override `API_KEY` env, never sent back
+    pub api_key: Option<String>,
+    /// Override `GOOGLE_MAP_API_KEY` env of the nearby place tool, never sent
+    /// back
+    pub google_map_api_key: Option<String>,
+    /// Screening of messages and replies, see `moderation`
+    pub moderation: ModerationPolicy,
+    /// Limits of the tool loop of replies, see `routes::message::budget`
+    pub agent_loop: AgentLoopPolicy,
+    /// Deliver webhooks to addresses of private networks, e.g. Home Assistant;
+    /// loopback and link-local ones never are, see `webhook`
+    pub webhook_private_networks: bool,
+}
+
+pub struct Settings {
+    conn: DbConn,
+    secrets: Secrets,
+    tx: watch::Sender<RuntimeConfig>,
+}
+
+impl RuntimeConfig {
+    /// The API keys, as stored
+    fn keys(&mut self) -> [&mut Option<String>; 2] {
+        [&mut self.api_key, &mut self.google_map_api_key]
+    }
+}
+
+impl Settings {
+    /// Restore the settings saved by [`Settings::set`], sealing keys saved
+    /// before they were
+    pub async fn load(conn: DbConn, secrets: Secrets) -> Result<Self> {
+        let mut config = match Config::find_by_id(KEY).one(&conn).await? {
+            Some(x) => serde_json::from_slice(&x.value)?,
+            None => RuntimeConfig {
+                rate_limit: match Config::find_by_id(LEGACY_RATE_LIMIT_KEY).one(&conn).await? {
+                    Some(x) => serde_json::from_slice(&x.value)?,
+                    None => RateLimitPolicy::default(),
+                },
+                ..Default::default()
+            },
+        };
+        let mut plain = false;
+        for key in config.keys().into_iter().flatten() {
+            plain |= !Secrets::is_sealed(key);
+            *key = secrets.open(key)?;
+        }
+        let settings = Self {
+            conn,
+            secrets,
+            tx: watch::Sender::new(config.clone()),
+        };
+        if plain {
+            settings.save(&config).await?;
+        }
+        Ok(settings)
+    }
+
+    pub fn current(&self) -> RuntimeConfig {
+        self.tx.borrow().clone()
+    }
+
+    /// Receive every change made after the call
+    pub fn subscribe(&self) -> watch::Receiver<RuntimeConfig> {
+        self.tx.subscribe()
+    }
+
+    /// Save and apply at once, subscribers are only woken by a change
+    pub async fn set(&self, config: RuntimeConfig) -> Result<()> {
+        self.save(&config).await?;
+        self.tx.send_if_modified(|x| {
+            let changed = *x != config;
+            *x = config;
+            changed
+        });
+        Ok(())
+    }
+
+    async fn save(&self, config: &RuntimeConfig) -> Result<()> {
+        let mut stored = config.clone();
+        for key in stored.keys().into_iter().flatten() {
+            *key = self.secrets.seal(key)?;
+        }
+        Config::insert(config::ActiveModel {
+            key: Set(KEY.to_owned()),
+            value: Set(serde_json::to_vec(&stored)?),
+        })
+        .on_conflict(
+            OnConflict::column(config::Column::Key)
+                .update_column(config::Column::Value)
+                .to_owned(),
+        )
+        .exec(&self.conn)
+        .await?;
+        Ok(())
+    }
+}
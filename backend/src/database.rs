@@ -0,0 +1,152 @@
+//! Connections to `DATABASE_URL`
+//!
+//! SQLite runs in WAL mode with `synchronous=NORMAL` unless told otherwise, so
+//! reads no longer wait for a write to commit; a connection waits
+//! `SQLITE_BUSY_TIMEOUT` for the lock of another writer. A write can still
+//! find the database locked past that timeout, or when a transaction that
+//! read first turns into a write after another commit, which SQLite refuses
+//! without waiting. [`retry`] runs those again after a short backoff
+
+use std::{str::FromStr, time::Duration};
+
+use anyhow::Result;
+use dotenv::var;
+use sea_orm::{
+    ConnectOptions, Database, DbConn, DbErr, RuntimeErr, SqlxSqliteConnector, TransactionError,
+    sqlx::{
+        self, ConnectOptions as _,
+        sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous},
+    },
+};
+
+use crate::config::{
+    DB_BUSY_BACKOFF_MS, DB_BUSY_RETRIES, SQLITE_BUSY_TIMEOUT_MS, SQLITE_POOL_SIZE,
+};
+
+/// `SQLITE_BUSY` and `SQLITE_LOCKED`, the primary codes of extended ones
+const BUSY_CODES: [i32; 2] = [5, 6];
+
+pub struct DbOptions {
+    /// None is the default of the backend, [`SQLITE_POOL_SIZE`] for SQLite
+    pool_size: Option<u32>,
+    journal_mode: SqliteJournalMode,
+    synchronous: SqliteSynchronous,
+    busy_timeout: Duration,
+}
+
+impl DbOptions {
+    /// `DB_POOL_SIZE`, `SQLITE_JOURNAL_MODE`, `SQLITE_SYNCHRONOUS` and
+    /// `SQLITE_BUSY_TIMEOUT` in milliseconds, unset or unparsable ones are the
+    /// defaults
+    pub fn from_env() -> Self {
+        fn parse<T: FromStr>(name: &str) -> Option<T> {
+            var(name).ok().and_then(|x| x.parse().ok())
+        }
+        Self {
+            pool_size: parse("DB_POOL_SIZE").filter(|x| *x > 0),
+            journal_mode: parse("SQLITE_JOURNAL_MODE").unwrap_or(SqliteJournalMode::Wal),
+            synchronous: parse("SQLITE_SYNCHRONOUS").unwrap_or(SqliteSynchronous::Normal),
+            busy_timeout: Duration::from_millis(
+                parse("SQLITE_BUSY_TIMEOUT").unwrap_or(SQLITE_BUSY_TIMEOUT_MS),
+            ),
+        }
+    }
+}
+
+/// Open the pool of `url` with the options of the env
+pub async fn connect(url: &str) -> Result<DbConn, DbErr> {
+    let options = DbOptions::from_env();
+    if !url.starts_with("sqlite:") {
+        let mut opt = ConnectOptions::new(url);
+        if let Some(size) = options.pool_size {
+            opt.max_connections(size);
+        }
+        return Database::connect(opt).await;
+    }
+
+    let opt = SqliteConnectOptions::from_str(url)
+        .map_err(|err| DbErr::Conn(RuntimeErr::SqlxError(err)))?
+        .journal_mode(options.journal_mode)
+        .synchronous(options.synchronous)
+        .busy_timeout(options.busy_timeout)
+        // the level sea-orm logs at, `telemetry` turns them into spans
+        .log_statements(log::LevelFilter::Info);
+    // every connection to memory is a database of its own
+    let memory = url.contains(":memory:") || url.contains("mode=memory");
+    let size = match memory {
+        true => 1,
+        false => options.pool_size.unwrap_or(SQLITE_POOL_SIZE),
+    };
+    let pool = SqlitePoolOptions::new()
+        .max_connections(size)
+        .connect_with(opt)
+        .await
+        .map_err(|err| DbErr::Conn(RuntimeErr::SqlxError(err)))?;
+    Ok(SqlxSqliteConnector::from_sqlx_sqlite_pool(pool))
+}
+
+/// Whether `err` is SQLite finding the database locked
+pub fn is_busy(err: &anyhow::Error) -> bool {
+    err.chain().any(|err| {
+        if let Some(err) = err.downcast_ref::<sqlx::Error>() {
+            return sqlx_busy(err);
+        }
+        // a transaction does not give its error as a source
+        match err.downcast_ref::<TransactionError<anyhow::Error>>() {
+            Some(TransactionError::Transaction(err)) => is_busy(err),
+            Some(TransactionError::Connection(err)) => db_busy(err),
+            None => match err.downcast_ref::<TransactionError<DbErr>>() {
+                Some(TransactionError::Transaction(err) | TransactionError::Connection(err)) => {
+                    db_busy(err)
+                }
+                None => false,
+            },
+        }
+    })
+}
+
+fn db_busy(err: &DbErr) -> bool {
+    match err {
+        DbErr::Conn(RuntimeErr::SqlxError(err))
+        | DbErr::Exec(RuntimeErr::SqlxError(err))
+        | DbErr::Query(RuntimeErr::SqlxError(err)) => sqlx_busy(err),
+        _ => false,
+    }
+}
+
+fn sqlx_busy(err: &sqlx::Error) -> bool {
+    let sqlx::Error::Database(err) = err else {
+        return false;
+    };
+    err.code()
+        .and_then(|code| code.parse::<i32>().ok())
+        .is_some_and(|code| BUSY_CODES.contains(&(code & 0xff)))
+}
+
+/// Run `op` again while it finds the database locked, up to
+/// [`DB_BUSY_RETRIES`] times; `op` must be safe to repeat, a statement or a
+/// whole transaction
+pub async fn retry<T, E, F, Fut>(mut op: F) -> Result<T>
+where
+    E: Into<anyhow::Error>,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 0;
+    loop {
+        let err = match op().await {
+            Ok(v) => return Ok(v),
+            Err(err) => err.into(),
+        };
+        if attempt == DB_BUSY_RETRIES || !is_busy(&err) {
+            return Err(err);
+        }
+        attempt += 1;
+        let backoff = DB_BUSY_BACKOFF_MS << (attempt - 1);
+        tracing::debug!("database is locked, retry {} in {}ms", attempt, backoff);
+        tokio::time::sleep(Duration::from_millis(
+            backoff + fastrand::u64(..DB_BUSY_BACKOFF_MS),
+        ))
+        .await;
+    }
+}
@@ -0,0 +1,218 @@
+//! Public demo mode, see `DEMO_MODE` env
+//!
+//! Visitors get a throwaway account after solving a captcha, usage is capped
+//! per IP and the accounts are purged once expired
+
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use dotenv::var;
+use entity::{prelude::*, user};
+use sea_orm::{ColumnTrait, DbConn, EntityTrait, QueryFilter};
+use tokio::time::Instant;
+
+use crate::{
+    AppState,
+    config::{
+        DEMO_CAPTCHA_SECS, DEMO_COST_PER_HOUR, DEMO_MESSAGES_PER_HOUR, DEMO_PURGE_INTERVAL,
+        DEMO_SESSIONS_PER_HOUR,
+    },
+    utils::account_purge,
+};
+
+const WINDOW: Duration = Duration::from_secs(3600);
+
+#[derive(Debug)]
+pub struct Demo {
+    /// model every demo chat use, default to the one requested
+    pub model_id: Option<i32>,
+    usage: Mutex<HashMap<IpAddr, Usage>>,
+    /// demo user id to the IP it logged in from
+    sessions: Mutex<HashMap<i32, IpAddr>>,
+    captchas: Mutex<HashMap<u32, Captcha>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Usage {
+    since: Instant,
+    sessions: u32,
+    messages: u32,
+    cost: f64,
+}
+
+impl Usage {
+    fn new() -> Self {
+        Self {
+            since: Instant::now(),
+            sessions: 0,
+            messages: 0,
+            cost: 0.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Captcha {
+    answer: u32,
+    expires: Instant,
+}
+
+/// What an IP can still do in the current hour
+#[derive(Debug, Clone, Copy)]
+pub struct Remaining {
+    pub sessions: u32,
+    pub messages: u32,
+}
+
+impl Demo {
+    /// Return None unless `DEMO_MODE` is set
+    pub fn from_env() -> Option<Self> {
+        let enabled = var("DEMO_MODE").is_ok_and(|x| x == "1" || x == "true");
+        if !enabled {
+            return None;
+        }
+        Some(Self {
+            model_id: var("DEMO_MODEL_ID").ok().and_then(|x| x.parse().ok()),
+            usage: Default::default(),
+            sessions: Default::default(),
+            captchas: Default::default(),
+        })
+    }
+
+    /// A new question, return its id and the text to show
+    pub fn captcha(&self) -> (u32, String) {
+        let (a, b) = (fastrand::u32(1..20), fastrand::u32(1..20));
+        let id = fastrand::u32(..);
+
+        let mut captchas = self.captchas.lock().unwrap();
+        let now = Instant::now();
+        captchas.retain(|_, x| x.expires > now);
+        captchas.insert(
+            id,
+            Captcha {
+                answer: a + b,
+                expires: now + Duration::from_secs(DEMO_CAPTCHA_SECS),
+            },
+        );
+        (id, format!("{} + {} = ?", a, b))
+    }
+
+    /// A captcha can only be tried once
+    pub fn solve(&self, id: u32, answer: u32) -> bool {
+        self.captchas
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .is_some_and(|x| x.answer == answer && x.expires > Instant::now())
+    }
+
+    pub fn remaining(&self, ip: IpAddr) -> Remaining {
+        let usage = self.usage_of(ip);
+        let messages = match usage.cost < DEMO_COST_PER_HOUR {
+            true => DEMO_MESSAGES_PER_HOUR.saturating_sub(usage.messages),
+            false => 0,
+        };
+        Remaining {
+            sessions: DEMO_SESSIONS_PER_HOUR.saturating_sub(usage.sessions),
+            messages,
+        }
+    }
+
+    /// Count a new session of `ip`, false if it has used up its sessions
+    pub fn start_session(&self, ip: IpAddr) -> bool {
+        self.update(ip, |usage| {
+            if usage.sessions >= DEMO_SESSIONS_PER_HOUR {
+                return false;
+            }
+            usage.sessions += 1;
+            true
+        })
+    }
+
+    pub fn bind(&self, user_id: i32, ip: IpAddr) {
+        self.sessions.lock().unwrap().insert(user_id, ip);
+    }
+
+    /// Count a message of a demo user, return the reason if it is refused
+    pub fn allow_message(&self, user_id: i32) -> Result<(), &'static str> {
+        let ip = self
+            .sessions
+            .lock()
+            .unwrap()
+            .get(&user_id)
+            .copied()
+            .ok_or("demo session expired, start a new one")?;
+        self.update(ip, |usage| {
+            if usage.messages >= DEMO_MESSAGES_PER_HOUR {
+                return Err("demo message limit reached, try again later");
+            }
+            if usage.cost >= DEMO_COST_PER_HOUR {
+                return Err("demo cost limit reached, try again later");
+            }
+            usage.messages += 1;
+            Ok(())
+        })
+    }
+
+    pub fn add_cost(&self, user_id: i32, cost: f64) {
+        let ip = self.sessions.lock().unwrap().get(&user_id).copied();
+        if let Some(ip) = ip {
+            self.update(ip, |usage| usage.cost += cost);
+        }
+    }
+
+    /// Periodically delete expired demo users, their chats cascade
+    pub fn spawn_purge(app: Arc<AppState>) {
+        tokio::spawn(async move {
+            let Some(demo) = &app.demo else {
+                return;
+            };
+            let mut interval = tokio::time::interval(Duration::from_secs(DEMO_PURGE_INTERVAL));
+            loop {
+                interval.tick().await;
+                if let Err(err) = demo.purge(&app.conn).await {
+                    tracing::warn!("cannot purge demo users: {}", err);
+                }
+            }
+        });
+    }
+
+    async fn purge(&self, conn: &DbConn) -> anyhow::Result<()> {
+        let now = time::UtcDateTime::now().unix_timestamp();
+        let expired = User::find()
+            .filter(user::Column::DemoExpiresAt.lt(now))
+            .all(conn)
+            .await?;
+        if expired.is_empty() {
+            return Ok(());
+        }
+
+        let ids: Vec<i32> = expired.iter().map(|x| x.id).collect();
+        account_purge::purge(conn, &ids).await?;
+
+        let mut sessions = self.sessions.lock().unwrap();
+        for id in &ids {
+            sessions.remove(id);
+        }
+        tracing::debug!("purged {} demo users", ids.len());
+        Ok(())
+    }
+
+    fn usage_of(&self, ip: IpAddr) -> Usage {
+        let usage = self.usage.lock().unwrap();
+        match usage.get(&ip) {
+            Some(x) if x.since.elapsed() < WINDOW => *x,
+            _ => Usage::new(),
+        }
+    }
+
+    fn update<T>(&self, ip: IpAddr, f: impl FnOnce(&mut Usage) -> T) -> T {
+        let mut usage = self.usage.lock().unwrap();
+        usage.retain(|_, x| x.since.elapsed() < WINDOW);
+        f(usage.entry(ip).or_insert_with(Usage::new))
+    }
+}
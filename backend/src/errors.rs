@@ -1,15 +1,43 @@
 use axum::Json;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-#[derive(Debug, Clone, Serialize)]
-#[typeshare]
+use crate::middlewares::locale::{self, Locale};
+
+/// Sent as a [`LocalizedError`]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(into = "LocalizedError")]
+#[schemars(with = "LocalizedError")]
 pub struct Error {
     pub error: ErrorKind,
     pub reason: String,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// An [`Error`] as answered
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[typeshare]
+pub struct LocalizedError {
+    pub error: ErrorKind,
+    /// What went wrong in detail, in English
+    pub reason: String,
+    /// What `error` means for the user, in the locale of the request, see
+    /// `middlewares::locale`
+    pub message: String,
+}
+
+impl From<Error> for LocalizedError {
+    fn from(value: Error) -> Self {
+        let locale = locale::current().unwrap_or_default();
+        Self {
+            message: value.error.message(locale).to_owned(),
+            error: value.error,
+            reason: value.reason,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[typeshare]
 #[serde(rename_all = "snake_case")]
 pub enum ErrorKind {
@@ -18,9 +46,75 @@ pub enum ErrorKind {
     MalformedRequest,
     Internal,
     LoginFail,
+    /// The password is right, but a TOTP or recovery code is needed
+    TotpRequired,
     ResourceNotFound,
     ApiFail,
     ToolCallFail,
+    /// Generations are paused by the spend guard until an admin resume them
+    Paused,
+    /// A daily or monthly quota of the user is used up, see `user/usage`
+    QuotaExceeded,
+    /// Too many requests in a short time, see `middlewares::rate_limit`
+    RateLimited,
+    /// The body of the request is over its limit, see `middlewares::body_limit`
+    PayloadTooLarge,
+    /// The message or reply was blocked by the moderation policy, see
+    /// `moderation`
+    Moderated,
+    /// A monthly spending cap of the user or of the model is reached, see
+    /// `spend::cap`
+    SpendCapped,
+    /// Arguments written by the model do not match the schema of the tool,
+    /// see `tools::validate`
+    ToolArgsInvalid,
+    /// Changed since the version the request was based on, read it again
+    Conflict,
+}
+
+impl ErrorKind {
+    pub fn message(&self, locale: Locale) -> &'static str {
+        match locale {
+            Locale::En => match self {
+                ErrorKind::Unauthorized => "You are not allowed to do this",
+                ErrorKind::MalformedToken => "Your session is not valid, please log in again",
+                ErrorKind::MalformedRequest => "The request is not valid",
+                ErrorKind::Internal => "Something went wrong on the server",
+                ErrorKind::LoginFail => "Wrong username or password",
+                ErrorKind::TotpRequired => "A verification code is required",
+                ErrorKind::ResourceNotFound => "It does not exist or was deleted",
+                ErrorKind::ApiFail => "The model provider failed to answer",
+                ErrorKind::ToolCallFail => "A tool failed",
+                ErrorKind::Paused => "Generations are paused by an admin",
+                ErrorKind::QuotaExceeded => "Your quota is used up",
+                ErrorKind::RateLimited => "Too many requests, please try again later",
+                ErrorKind::PayloadTooLarge => "The request is too large",
+                ErrorKind::Moderated => "The content was blocked by the moderation policy",
+                ErrorKind::SpendCapped => "The monthly spending cap is reached",
+                ErrorKind::ToolArgsInvalid => "A tool was called with invalid arguments",
+                ErrorKind::Conflict => "It was changed elsewhere, please try again",
+            },
+            Locale::ZhTw => match self {
+                ErrorKind::Unauthorized => "你沒有權限執行此操作",
+                ErrorKind::MalformedToken => "登入狀態無效，請重新登入",
+                ErrorKind::MalformedRequest => "請求無效",
+                ErrorKind::Internal => "伺服器發生錯誤",
+                ErrorKind::LoginFail => "使用者名稱或密碼錯誤",
+                ErrorKind::TotpRequired => "需要驗證碼",
+                ErrorKind::ResourceNotFound => "找不到資源，可能已被刪除",
+                ErrorKind::ApiFail => "模型供應商未能回應",
+                ErrorKind::ToolCallFail => "工具執行失敗",
+                ErrorKind::Paused => "生成已被管理員暫停",
+                ErrorKind::QuotaExceeded => "你的用量額度已用完",
+                ErrorKind::RateLimited => "請求過於頻繁，請稍後再試",
+                ErrorKind::PayloadTooLarge => "請求的內容過大",
+                ErrorKind::Moderated => "內容已被審核政策封鎖",
+                ErrorKind::SpendCapped => "已達每月花費上限",
+                ErrorKind::ToolArgsInvalid => "工具呼叫的參數無效",
+                ErrorKind::Conflict => "已在其他地方變更，請再試一次",
+            },
+        }
+    }
 }
 
 pub type JsonResult<T> = Result<Json<T>, Json<Error>>;
@@ -0,0 +1,85 @@
+//! Experimental federation between instances, see `FEDERATION_*` env
+//!
+//! A home instance forward completions of `peer/` models to a peer instance,
+//! which answer them with its own upstream, e.g. local models on a GPU box.
+//! Requests carry a short-lived PASETO encrypted with a key both sides share
+
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use dotenv::var;
+use pasetors::{
+    Local,
+    claims::{Claims, ClaimsValidationRules},
+    keys::SymmetricKey,
+    local,
+    token::UntrustedToken,
+    version4::V4,
+};
+
+use crate::config::FEDERATION_TOKEN_SECS;
+
+/// Model ids starting with it are answered by the peer
+pub const PEER_PREFIX: &str = "peer/";
+
+#[derive(Clone)]
+pub struct Federation {
+    key: SymmetricKey<V4>,
+    /// Base url of the peer, None if this instance only serve
+    peer: Option<String>,
+    /// Answer completions of peers
+    pub serve: bool,
+}
+
+impl Federation {
+    /// Return None unless `FEDERATION_KEY` is set
+    pub fn from_env() -> Option<Self> {
+        let key = var("FEDERATION_KEY").ok()?;
+        let key = STANDARD
+            .decode(key.trim())
+            .ok()
+            .and_then(|x| SymmetricKey::from(&x).ok());
+        let Some(key) = key else {
+            tracing::warn!("FEDERATION_KEY must be 32 bytes in base64, federation disabled");
+            return None;
+        };
+
+        Some(Self {
+            key,
+            peer: var("FEDERATION_PEER")
+                .ok()
+                .map(|x| x.trim_end_matches('/').to_owned()),
+            serve: var("FEDERATION_SERVE").is_ok_and(|x| x == "1" || x == "true"),
+        })
+    }
+
+    /// Completion endpoint of the peer
+    pub fn peer_endpoint(&self) -> Option<String> {
+        self.peer
+            .as_ref()
+            .map(|x| format!("{}/api/federation/completions", x))
+    }
+
+    /// A token for one request to the peer
+    pub fn sign(&self) -> Result<String> {
+        let mut claims = Claims::new_expires_in(&Duration::from_secs(FEDERATION_TOKEN_SECS))?;
+        // safety:
+        // "fed" is not reserve
+        claims.add_additional("fed", true).unwrap();
+        Ok(local::encrypt(&self.key, &claims, None, None)?)
+    }
+
+    /// Check a token signed by a peer
+    pub fn verify(&self, token: &str) -> Result<()> {
+        let token = UntrustedToken::<Local, V4>::try_from(token)?;
+        let token = local::decrypt(&self.key, &token, &ClaimsValidationRules::new(), None, None)?;
+        token
+            .payload_claims()
+            .and_then(|x| x.get_claim("fed"))
+            .and_then(|x| x.as_bool())
+            .filter(|x| *x)
+            .context("Not a federation token")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+//! Uploaded files, see `FILE_STORAGE` env
+//!
+//! Uploads are spooled to a temporary file while their size is checked, then
+//! moved to the storage under a random key. `attachment` link them to
+//! messages without a foreign key to the message, so messages in the trash
+//! keep their files; files attached to nothing nor added to the knowledge base
+//! for [`FILE_ORPHAN_SECS`] and files of purged accounts are removed by a
+//! periodic sweep
+
+mod s3;
+mod sniff;
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Result, bail};
+use entity::{attachment, file, prelude::*};
+use sea_orm::{ActiveValue::Set, ConnectionTrait, QueryOrder, QuerySelect, Statement, prelude::*};
+
+pub use sniff::{HEAD_BYTES, sniff};
+
+use crate::{
+    AppState,
+    config::{FILE_ORPHAN_SECS, FILE_SWEEP_INTERVAL, FILE_TEXT_MAX_CHARS},
+    openrouter,
+    utils::{
+        extract::{self, Format},
+        sql,
+    },
+};
+
+enum Storage {
+    /// `FILE_DIR`
+    Local(PathBuf),
+    S3(s3::Bucket),
+}
+
+pub struct Files {
+    storage: Storage,
+}
+
+impl Files {
+    /// `FILE_STORAGE` is `local` (default) or `s3`
+    pub fn from_env() -> Result<Self> {
+        let storage = match dotenv::var("FILE_STORAGE").as_deref() {
+            Ok("local") | Err(_) => {
+                Storage::Local(dotenv::var("FILE_DIR").unwrap_or("files".to_owned()).into())
+            }
+            Ok("s3") => Storage::S3(s3::Bucket::from_env()?),
+            Ok(x) => bail!("unknown FILE_STORAGE {}, expect local or s3", x),
+        };
+        Ok(Self { storage })
+    }
+
+    pub fn describe(&self) -> String {
+        match &self.storage {
+            Storage::Local(dir) => dir.display().to_string(),
+            Storage::S3(bucket) => bucket.describe(),
+        }
+    }
+
+    /// Move a spooled upload to the storage, the temporary file is consumed
+    pub async fn put(&self, key: &str, spool: &Path, size: u64) -> Result<()> {
+        match &self.storage {
+            Storage::Local(dir) => {
+                tokio::fs::create_dir_all(dir).await?;
+                let path = dir.join(key);
+                // the temporary directory may be on another device
+                if tokio::fs::rename(spool, &path).await.is_err() {
+                    tokio::fs::copy(spool, &path).await?;
+                    tokio::fs::remove_file(spool).await?;
+                }
+            }
+            Storage::S3(bucket) => {
+                let file = tokio::fs::File::open(spool).await?;
+                let res = bucket.put(key, file.into(), size).await;
+                tokio::fs::remove_file(spool).await?;
+                res?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn put_bytes(&self, key: &str, data: Vec<u8>) -> Result<()> {
+        match &self.storage {
+            Storage::Local(dir) => {
+                tokio::fs::create_dir_all(dir).await?;
+                tokio::fs::write(dir.join(key), data).await?;
+            }
+            Storage::S3(bucket) => {
+                let size = data.len() as u64;
+                bucket.put(key, data.into(), size).await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        match &self.storage {
+            Storage::Local(dir) => Ok(tokio::fs::read(dir.join(key)).await?),
+            Storage::S3(bucket) => bucket.get(key).await,
+        }
+    }
+
+    /// Deleting a missing object succeed
+    pub async fn delete(&self, key: &str) -> Result<()> {
+        match &self.storage {
+            Storage::Local(dir) => match tokio::fs::remove_file(dir.join(key)).await {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+                _ => Ok(()),
+            },
+            Storage::S3(bucket) => bucket.delete(key).await,
+        }
+    }
+
+    /// Periodically purge the files attached to nothing and those of purged accounts
+    pub fn spawn_sweep(app: Arc<AppState>) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(FILE_SWEEP_INTERVAL));
+            loop {
+                interval.tick().await;
+                if let Err(err) = sweep(&app).await {
+                    tracing::warn!("cannot sweep files: {}", err);
+                }
+            }
+        });
+    }
+}
+
+/// Random key of a new object
+pub fn new_key() -> Result<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).map_err(|e| anyhow::anyhow!("Cannot generate key: {}", e))?;
+    Ok(bytes.iter().map(|x| format!("{:02x}", x)).collect())
+}
+
+/// Store a file received whole rather than uploaded, e.g. a screenshot
+pub async fn create(
+    conn: &impl ConnectionTrait,
+    files: &Files,
+    owner_id: i32,
+    name: String,
+    data: Vec<u8>,
+) -> Result<file::Model> {
+    let key = new_key()?;
+    let content_type = sniff(&data[..data.len().min(HEAD_BYTES)]).to_owned();
+    let size = data.len() as i64;
+    files.put_bytes(&key, data).await?;
+    let res = File::insert(file::ActiveModel {
+        owner_id: Set(owner_id),
+        name: Set(name),
+        content_type: Set(content_type),
+        size: Set(size),
+        storage_key: Set(key.clone()),
+        created_at: Set(time::UtcDateTime::now().unix_timestamp()),
+        ..Default::default()
+    })
+    .exec_with_returning(conn)
+    .await;
+    if res.is_err() {
+        discard(files, &key).await;
+    }
+    Ok(res?)
+}
+
+/// Whether every file of `ids` belong to the user
+pub async fn all_owned(
+    conn: &impl ConnectionTrait,
+    user_id: i32,
+    ids: &[i32],
+) -> Result<bool, DbErr> {
+    let count = File::find()
+        .filter(file::Column::Id.is_in(ids.to_vec()))
+        .filter(file::Column::OwnerId.eq(user_id))
+        .count(conn)
+        .await?;
+    Ok(count == ids.len() as u64)
+}
+
+pub async fn attach(
+    conn: &impl ConnectionTrait,
+    chat_id: i32,
+    message_id: i32,
+    file_ids: &[i32],
+) -> Result<(), DbErr> {
+    if file_ids.is_empty() {
+        return Ok(());
+    }
+    Attachment::insert_many(file_ids.iter().map(|&file_id| attachment::ActiveModel {
+        message_id: Set(message_id),
+        file_id: Set(file_id),
+        chat_id: Set(chat_id),
+    }))
+    .exec(conn)
+    .await?;
+    Ok(())
+}
+
+/// Ids of the files attached to a message, an edit of it keep them
+pub async fn attached_ids(conn: &impl ConnectionTrait, message_id: i32) -> Result<Vec<i32>, DbErr> {
+    Attachment::find()
+        .select_only()
+        .column(attachment::Column::FileId)
+        .filter(attachment::Column::MessageId.eq(message_id))
+        .order_by_asc(attachment::Column::FileId)
+        .into_tuple()
+        .all(conn)
+        .await
+}
+
+/// Files attached to the messages with their content, in order of upload
+///
+/// PDF, DOCX and HTML files come with their text, models read few of them
+pub async fn load(
+    conn: &impl ConnectionTrait,
+    files: &Files,
+    message_ids: Vec<i32>,
+) -> Result<HashMap<i32, Vec<openrouter::File>>> {
+    let rows = Attachment::find()
+        .find_also_related(File)
+        .filter(attachment::Column::MessageId.is_in(message_ids))
+        .order_by_asc(attachment::Column::FileId)
+        .all(conn)
+        .await?;
+    let mut map: HashMap<i32, Vec<openrouter::File>> = HashMap::new();
+    for (attachment, file) in rows {
+        let Some(file) = file else {
+            continue;
+        };
+        let data = files.get(&file.storage_key).await?;
+        let text = text(&file, &data).await;
+        map.entry(attachment.message_id)
+            .or_default()
+            .push(openrouter::File {
+                name: file.name,
+                data,
+                text,
+            });
+    }
+    Ok(map)
+}
+
+/// Text of a document, None for other files or when none is found, then the
+/// file itself is sent
+async fn text(file: &file::Model, data: &[u8]) -> Option<String> {
+    let format = extract::format(&file.content_type, &file.name)?;
+    if format == Format::Text {
+        return None;
+    }
+    match extract::extract(format, data.to_vec()).await {
+        Ok(sections) => {
+            let mut text = extract::join(&sections);
+            if let Some((at, _)) = text.char_indices().nth(FILE_TEXT_MAX_CHARS) {
+                text.truncate(at);
+                text.push('…');
+            }
+            Some(text)
+        }
+        Err(err) => {
+            tracing::warn!("cannot read the text of file {}: {}", file.id, err);
+            None
+        }
+    }
+}
+
+async fn sweep(app: &Arc<AppState>) -> Result<()> {
+    let conn = &app.conn;
+    let now = time::UtcDateTime::now().unix_timestamp();
+
+    // messages deleted for good, by the trash purge, the retention policy or
+    // with their account
+    conn.execute(Statement::from_string(
+        conn.get_database_backend(),
+        "DELETE FROM attachment
+        WHERE message_id NOT IN (SELECT id FROM message)
+        AND NOT EXISTS (
+            SELECT 1 FROM trash WHERE trash.message_id = attachment.message_id
+            OR (trash.message_id IS NULL AND trash.chat_id = attachment.chat_id)
+        )",
+    ))
+    .await?;
+
+    let due = File::find()
+        .from_raw_sql(sql::statement(
+            conn.get_database_backend(),
+            "SELECT file.* FROM file
+            WHERE file.owner_id NOT IN (SELECT id FROM \"user\")
+            OR (file.created_at < ? AND file.id NOT IN (SELECT file_id FROM attachment)
+                AND file.id NOT IN (SELECT file_id FROM document WHERE file_id IS NOT NULL))",
+            [(now - FILE_ORPHAN_SECS).into()],
+        ))
+        .all(conn)
+        .await?;
+    let mut purged = vec![];
+    for x in due {
+        match app.files.delete(&x.storage_key).await {
+            Ok(()) => purged.push(x.id),
+            // kept for the next sweep
+            Err(err) => tracing::warn!("cannot delete file {}: {}", x.id, err),
+        }
+    }
+    if !purged.is_empty() {
+        File::delete_many()
+            .filter(file::Column::Id.is_in(purged.clone()))
+            .exec(conn)
+            .await?;
+        tracing::info!("purged {} files", purged.len());
+    }
+    Ok(())
+}
+
+/// Remove a stored object whose row could not be inserted
+pub async fn discard(files: &Files, key: &str) {
+    if let Err(err) = files.delete(key).await {
+        tracing::warn!("cannot delete object {}: {}", key, err);
+    }
+}
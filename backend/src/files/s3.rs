@@ -0,0 +1,156 @@
+//! S3-compatible bucket, requests are signed with AWS signature version 4
+//!
+//! Objects are addressed in path style, `{endpoint}/{bucket}/{key}`, which
+//! MinIO, R2 and AWS all accept
+
+use anyhow::{Context, Result};
+use hmac::{Hmac, Mac};
+use reqwest::{Body, Method, RequestBuilder};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+/// The body is streamed, so its hash is left out of the signature
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+pub struct Bucket {
+    client: reqwest::Client,
+    endpoint: Url,
+    bucket: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+}
+
+impl Bucket {
+    /// `S3_ENDPOINT`, `S3_BUCKET`, `S3_ACCESS_KEY` and `S3_SECRET_KEY` are
+    /// required, `S3_REGION` default to `us-east-1`
+    pub fn from_env() -> Result<Self> {
+        let var = |name: &str| dotenv::var(name).with_context(|| format!("{} is not set", name));
+        Ok(Self {
+            client: reqwest::Client::new(),
+            endpoint: var("S3_ENDPOINT")?.parse()?,
+            bucket: var("S3_BUCKET")?,
+            region: dotenv::var("S3_REGION").unwrap_or("us-east-1".to_owned()),
+            access_key: var("S3_ACCESS_KEY")?,
+            secret_key: var("S3_SECRET_KEY")?,
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{}/{}",
+            self.endpoint.as_str().trim_end_matches('/'),
+            self.bucket
+        )
+    }
+
+    pub async fn put(&self, key: &str, body: Body, size: u64) -> Result<()> {
+        self.request(Method::PUT, key)?
+            .header(reqwest::header::CONTENT_LENGTH, size)
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        let res = self
+            .request(Method::GET, key)?
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(res.bytes().await?.to_vec())
+    }
+
+    /// Deleting a missing object succeed
+    pub async fn delete(&self, key: &str) -> Result<()> {
+        self.request(Method::DELETE, key)?
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    fn request(&self, method: Method, key: &str) -> Result<RequestBuilder> {
+        let path = format!(
+            "{}/{}/{}",
+            self.endpoint.path().trim_end_matches('/'),
+            self.bucket,
+            key
+        );
+        let mut url = self.endpoint.clone();
+        url.set_path(&path);
+        let host = match url.port() {
+            Some(port) => format!(
+                "{}:{}",
+                url.host_str().context("S3_ENDPOINT has no host")?,
+                port
+            ),
+            None => url
+                .host_str()
+                .context("S3_ENDPOINT has no host")?
+                .to_owned(),
+        };
+
+        let now = time::UtcDateTime::now();
+        let date = format!("{:04}{:02}{:02}", now.year(), now.month() as u8, now.day());
+        let amz_date = format!(
+            "{}T{:02}{:02}{:02}Z",
+            date,
+            now.hour(),
+            now.minute(),
+            now.second()
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method.as_str(),
+            url.path(),
+            host,
+            UNSIGNED_PAYLOAD,
+            amz_date,
+            signed_headers,
+            UNSIGNED_PAYLOAD
+        );
+        let to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex(&Sha256::digest(canonical.as_bytes()))
+        );
+
+        let key = hmac(format!("AWS4{}", self.secret_key).as_bytes(), &date);
+        let key = hmac(&key, &self.region);
+        let key = hmac(&key, "s3");
+        let key = hmac(&key, "aws4_request");
+        let signature = hex(&hmac(&key, &to_sign));
+
+        Ok(self
+            .client
+            .request(method, url)
+            .header("x-amz-content-sha256", UNSIGNED_PAYLOAD)
+            .header("x-amz-date", amz_date)
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    self.access_key, scope, signed_headers, signature
+                ),
+            ))
+    }
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    // safety:
+    // HMAC accept keys of any length
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|x| format!("{:02x}", x)).collect()
+}
@@ -0,0 +1,58 @@
+//! Content type from the first bytes of a file, what the client claim is ignored
+
+/// Bytes [`sniff`] need at most
+pub const HEAD_BYTES: usize = 512;
+
+const MAGIC: &[(usize, &[u8], &str)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+    (0, b"\xff\xd8\xff", "image/jpeg"),
+    (0, b"GIF87a", "image/gif"),
+    (0, b"GIF89a", "image/gif"),
+    (0, b"BM", "image/bmp"),
+    (0, b"%PDF-", "application/pdf"),
+    (0, b"PK\x03\x04", "application/zip"),
+    (0, b"ID3", "audio/mpeg"),
+    (0, b"\xff\xfb", "audio/mpeg"),
+    (0, b"\xff\xf3", "audio/mpeg"),
+    (0, b"fLaC", "audio/flac"),
+    (0, b"OggS", "audio/ogg"),
+    (4, b"ftypM4A", "audio/mp4"),
+    (4, b"ftyp", "video/mp4"),
+    (0, b"\x1a\x45\xdf\xa3", "video/webm"),
+];
+
+/// Content type of a file starting with `head`
+pub fn sniff(head: &[u8]) -> &'static str {
+    if head.len() >= 12 && &head[..4] == b"RIFF" {
+        match &head[8..12] {
+            b"WEBP" => return "image/webp",
+            b"WAVE" => return "audio/wav",
+            _ => {}
+        }
+    }
+    for (offset, magic, content_type) in MAGIC {
+        if head.len() >= offset + magic.len() && &head[*offset..offset + magic.len()] == *magic {
+            return content_type;
+        }
+    }
+    if is_text(head) {
+        return "text/plain";
+    }
+    "application/octet-stream"
+}
+
+/// UTF-8 without control characters, the head may end inside a character
+fn is_text(head: &[u8]) -> bool {
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        Err(err) if err.error_len().is_none() => {
+            // safety: checked up to there
+            std::str::from_utf8(&head[..err.valid_up_to()]).unwrap()
+        }
+        Err(_) => return false,
+    };
+    !text.is_empty()
+        && !text
+            .chars()
+            .any(|x| x.is_control() && !matches!(x, '\n' | '\r' | '\t' | '\x0c'))
+}
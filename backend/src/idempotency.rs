@@ -0,0 +1,122 @@
+//! `Idempotency-Key` of `message/create`, so a retried send is answered once
+//!
+//! The first request with a key sends the message in a task of its own, a
+//! dropped connection does not stop it halfway. Retries with the same key wait
+//! for that request and get its message id, the reply streams on the chat as
+//! usual. A failed send free the key for the next retry
+//!
+//! Keys are kept for [`IDEMPOTENCY_TTL`] and do not survive a restart
+
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Mutex,
+    time::Duration,
+};
+
+use axum::{Json, http::HeaderMap};
+use tokio::{sync::watch, time::Instant};
+
+use crate::{
+    config::{IDEMPOTENCY_KEY_MAX_LEN, IDEMPOTENCY_TTL},
+    errors::*,
+};
+
+const TTL: Duration = Duration::from_secs(IDEMPOTENCY_TTL);
+
+type Outcome = Option<Result<i32, Json<Error>>>;
+
+#[derive(Default)]
+pub struct Idempotency {
+    map: Mutex<HashMap<(i32, String), Entry>>,
+}
+
+struct Entry {
+    /// Hash of the request, a key reused for another message is refused
+    fingerprint: u64,
+    rx: watch::Receiver<Outcome>,
+    at: Instant,
+}
+
+pub enum Claim {
+    /// First request with the key, send the message and [`Claim::finish`]
+    New(Pending),
+    /// A retry, wait for the first request
+    Seen(watch::Receiver<Outcome>),
+}
+
+pub struct Pending {
+    key: (i32, String),
+    tx: watch::Sender<Outcome>,
+}
+
+/// The key of a request, None without the header
+pub fn key(headers: &HeaderMap) -> Result<Option<String>, Json<Error>> {
+    let Some(key) = headers.get("idempotency-key") else {
+        return Ok(None);
+    };
+    let key = key
+        .to_str()
+        .ok()
+        .map(str::trim)
+        .filter(|x| !x.is_empty() && x.len() <= IDEMPOTENCY_KEY_MAX_LEN)
+        .ok_or(format!(
+            "Idempotency-Key is 1 to {} visible ASCII characters",
+            IDEMPOTENCY_KEY_MAX_LEN
+        ))
+        .kind(ErrorKind::MalformedRequest)?;
+    Ok(Some(key.to_owned()))
+}
+
+pub fn fingerprint(x: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    x.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Idempotency {
+    pub fn claim(&self, user_id: i32, key: String, fingerprint: u64) -> Result<Claim, Json<Error>> {
+        let mut map = self.map.lock().unwrap();
+        map.retain(|_, x| x.at.elapsed() < TTL);
+        let key = (user_id, key);
+        if let Some(entry) = map.get(&key) {
+            if entry.fingerprint != fingerprint {
+                return Err(Json(Error {
+                    error: ErrorKind::MalformedRequest,
+                    reason: "The Idempotency-Key was used for another message".to_owned(),
+                }));
+            }
+            return Ok(Claim::Seen(entry.rx.clone()));
+        }
+        let (tx, rx) = watch::channel(None);
+        map.insert(
+            key.clone(),
+            Entry {
+                fingerprint,
+                rx,
+                at: Instant::now(),
+            },
+        );
+        Ok(Claim::New(Pending { key, tx }))
+    }
+
+    /// Hand the outcome to the retries waiting
+    pub fn finish(&self, pending: Pending, outcome: Result<i32, Json<Error>>) {
+        if outcome.is_err() {
+            self.map.lock().unwrap().remove(&pending.key);
+        }
+        pending.tx.send_replace(Some(outcome));
+    }
+}
+
+/// Outcome of the first request with the key
+pub async fn wait(mut rx: watch::Receiver<Outcome>) -> Result<i32, Json<Error>> {
+    match rx.wait_for(Option::is_some).await {
+        Ok(outcome) => outcome.clone().unwrap(),
+        // the first request panicked
+        Err(_) => Err(Json(Error {
+            error: ErrorKind::Internal,
+            reason: "The message with this Idempotency-Key was not sent".to_owned(),
+        })),
+    }
+}
@@ -0,0 +1,115 @@
+//! Image generation for the `generateimage` tool, see `IMAGE_PROVIDER` env
+
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use dotenv::var;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{IMAGE_MAX_BYTES, IMAGE_TIMEOUT};
+
+/// `/images/generations` of an OpenAI-compatible provider
+pub struct ImageGen {
+    endpoint: String,
+    api_key: Option<String>,
+    model: String,
+    client: reqwest::Client,
+}
+
+#[derive(Serialize)]
+struct GenerationReq<'a> {
+    model: &'a str,
+    prompt: &'a str,
+    n: u32,
+    size: &'a str,
+}
+
+#[derive(Deserialize)]
+struct GenerationResp {
+    data: Vec<GenerationRespImage>,
+}
+
+/// Models answer with either, `gpt-image-1` always in base64
+#[derive(Deserialize)]
+struct GenerationRespImage {
+    b64_json: Option<String>,
+    url: Option<String>,
+}
+
+impl ImageGen {
+    /// None unless `IMAGE_PROVIDER` is `openai`
+    pub fn from_env() -> Option<Self> {
+        match var("IMAGE_PROVIDER").ok()?.as_str() {
+            "openai" => Some(Self {
+                endpoint: format!(
+                    "{}/images/generations",
+                    var("IMAGE_API_BASE")
+                        .unwrap_or("https://api.openai.com/v1".to_owned())
+                        .trim_end_matches('/')
+                ),
+                api_key: var("IMAGE_API_KEY").ok(),
+                model: var("IMAGE_MODEL").unwrap_or("gpt-image-1".to_owned()),
+                client: reqwest::Client::builder()
+                    .timeout(Duration::from_secs(IMAGE_TIMEOUT))
+                    .build()
+                    .expect("Cannot build http client"),
+            }),
+            x => {
+                tracing::warn!(
+                    "unknown IMAGE_PROVIDER {}, expect openai, image generation disabled",
+                    x
+                );
+                None
+            }
+        }
+    }
+
+    /// Bytes of one image of `prompt`, `size` as the provider takes it, e.g.
+    /// `1024x1024`
+    pub async fn generate(&self, prompt: &str, size: &str) -> Result<Vec<u8>> {
+        let req = self.client.post(&self.endpoint).json(&GenerationReq {
+            model: &self.model,
+            prompt,
+            n: 1,
+            size,
+        });
+        let req = match &self.api_key {
+            Some(api_key) => req.bearer_auth(api_key),
+            None => req,
+        };
+        let res = req.send().await?;
+        if !res.status().is_success() {
+            bail!(
+                "image generation failed with {}: {}",
+                res.status(),
+                res.text().await.unwrap_or_default()
+            );
+        }
+        let image = res
+            .json::<GenerationResp>()
+            .await?
+            .data
+            .into_iter()
+            .next()
+            .context("The provider returned no image")?;
+
+        let data = match (image.b64_json, image.url) {
+            (Some(b64), _) => STANDARD.decode(b64)?,
+            (None, Some(url)) => self
+                .client
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?
+                .to_vec(),
+            (None, None) => bail!("The provider returned no image"),
+        };
+        if data.len() > IMAGE_MAX_BYTES {
+            bail!("The generated image is too large");
+        }
+        Ok(data)
+    }
+}
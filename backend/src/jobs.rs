@@ -0,0 +1,389 @@
+//! Persistent queue of background work, see `admin/job`
+//!
+//! Work that must outlive a restart is written as a row of `job` before it
+//! runs: titles of new chats, ingestion of documents, runs of scheduled tasks,
+//! mails, webhook deliveries, Web Push messages, takeouts and the recurring
+//! purges.
+//! [`JOB_WORKERS`] workers claim due jobs with a conditional update, so
+//! instances sharing a database run each once; a claim is a lease of
+//! [`JOB_LEASE_SECS`], after which the job of a worker that died runs again. A
+//! failure is retried after a backoff doubling from [`JOB_BACKOFF_SECS`] up to
+//! [`JOB_MAX_ATTEMPTS`], then the job is kept failed for an admin to retry.
+//! Recurring jobs are never given up, they wait for their next time instead
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use entity::{JobKind, JobStatus, WebhookEvent, job, prelude::*};
+use sea_orm::{
+    ActiveValue::Set, ConnectionTrait, DbConn, QueryOrder, QuerySelect, prelude::*,
+    sea_query::OnConflict,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+
+use crate::{
+    AppState,
+    config::{
+        ACCOUNT_PURGE_INTERVAL, JOB_BACKOFF_SECS, JOB_LEASE_SECS, JOB_MAX_ATTEMPTS,
+        JOB_POLL_INTERVAL, JOB_WORKERS, RETENTION_INTERVAL, STATS_PURGE_INTERVAL,
+        TRASH_PURGE_INTERVAL,
+    },
+    kb, push, reply_stats, retention,
+    routes::message::create,
+    schedule, takeout, trash,
+    utils::account_purge,
+    webhook,
+};
+
+/// What a job does, stored as the payload of its row
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "t", content = "c", rename_all = "snake_case")]
+pub enum Task {
+    /// Title the chat if it still has none
+    Title {
+        chat_id: i32,
+        /// Who sent the message, the title follows their locale
+        user_id: i32,
+        /// Model the chat was answered with, see `TITLE_MODEL`
+        model_id: String,
+    },
+    Ingest {
+        document_id: i32,
+    },
+    Schedule {
+        schedule_id: i32,
+    },
+    /// Built by `Mailer::task`, dropped if SMTP is not configured anymore
+    Mail {
+        to: String,
+        subject: String,
+        /// Plain text
+        body: String,
+        /// Alternative of `body`, absent from mails queued before it existed
+        #[serde(default)]
+        html: Option<String>,
+    },
+    Retention,
+    TrashPurge,
+    AccountPurge,
+    StatsPurge,
+    /// `body` is the JSON sent, built when the event happened
+    Webhook {
+        webhook_id: i32,
+        event: WebhookEvent,
+        body: String,
+    },
+    /// `body` is the JSON of a `push::Message`, encrypted when sent
+    Push {
+        subscription_id: i32,
+        body: String,
+    },
+    /// Pushed with `push_once`, one per user at a time
+    Takeout {
+        user_id: i32,
+    },
+}
+
+impl Task {
+    pub fn kind(&self) -> JobKind {
+        match self {
+            Task::Title { .. } => JobKind::Title,
+            Task::Ingest { .. } => JobKind::Ingest,
+            Task::Schedule { .. } => JobKind::Schedule,
+            Task::Mail { .. } => JobKind::Mail,
+            Task::Retention => JobKind::Retention,
+            Task::TrashPurge => JobKind::TrashPurge,
+            Task::AccountPurge => JobKind::AccountPurge,
+            Task::StatsPurge => JobKind::StatsPurge,
+            Task::Webhook { .. } => JobKind::Webhook,
+            Task::Push { .. } => JobKind::Push,
+            Task::Takeout { .. } => JobKind::Takeout,
+        }
+    }
+
+    /// What it is about for admins, without the body of mails
+    pub fn describe(&self) -> String {
+        match self {
+            Task::Title { chat_id, .. } => format!("chat {}", chat_id),
+            Task::Ingest { document_id } => format!("document {}", document_id),
+            Task::Schedule { schedule_id } => format!("scheduled task {}", schedule_id),
+            Task::Mail { to, subject, .. } => format!("\"{}\" to {}", subject, to),
+            Task::Webhook { webhook_id, .. } => format!("webhook {}", webhook_id),
+            Task::Push {
+                subscription_id, ..
+            } => format!("push subscription {}", subscription_id),
+            Task::Takeout { user_id } => format!("user {}", user_id),
+            Task::Retention | Task::TrashPurge | Task::AccountPurge | Task::StatsPurge => {
+                String::new()
+            }
+        }
+    }
+
+    /// `last` is true on the final attempt of a job that can be given up
+    async fn run(self, app: &Arc<AppState>, last: bool) -> Result<()> {
+        match self {
+            Task::Title {
+                chat_id,
+                user_id,
+                model_id,
+            } => create::update_title(app, chat_id, user_id, &model_id).await,
+            Task::Ingest { document_id } => kb::ingest_job(app, document_id, last).await,
+            Task::Schedule { schedule_id } => schedule::run_job(app, schedule_id).await,
+            Task::Mail {
+                to,
+                subject,
+                body,
+                html,
+            } => {
+                let Some(mailer) = app.mailer.as_ref() else {
+                    tracing::warn!("mail to {} dropped, SMTP is not configured", to);
+                    return Ok(());
+                };
+                mailer.send(&to, &subject, body, html).await
+            }
+            Task::Retention => retention::sweep(app).await,
+            Task::TrashPurge => trash::purge_due(&app.conn).await,
+            Task::AccountPurge => account_purge::purge_due(&app.conn).await,
+            Task::StatsPurge => reply_stats::purge_due(&app.conn).await,
+            Task::Webhook {
+                webhook_id,
+                event,
+                body,
+            } => webhook::deliver(app, webhook_id, event, body).await,
+            Task::Push {
+                subscription_id,
+                body,
+            } => push::deliver(app, subscription_id, body).await,
+            Task::Takeout { user_id } => takeout::run_job(app, user_id).await,
+        }
+    }
+}
+
+/// Seconds between runs of a recurring job, None for one that runs once
+fn every(kind: JobKind) -> Option<u64> {
+    match kind {
+        JobKind::Retention => Some(RETENTION_INTERVAL),
+        JobKind::TrashPurge => Some(TRASH_PURGE_INTERVAL),
+        JobKind::AccountPurge => Some(ACCOUNT_PURGE_INTERVAL),
+        JobKind::StatsPurge => Some(STATS_PURGE_INTERVAL),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+pub struct Jobs {
+    /// Workers sleep between polls, a new job wakes one
+    wake: Notify,
+}
+
+impl Jobs {
+    /// Run `task` as soon as a worker is free
+    ///
+    /// Within a transaction the job waits for the next poll once committed
+    pub async fn push(&self, conn: &impl ConnectionTrait, task: Task) -> Result<()> {
+        insert(conn, &task, None, now()).await?;
+        self.wake.notify_one();
+        Ok(())
+    }
+
+    /// Like `push`, unless a job of `key` is pending or running, false then
+    ///
+    /// A failed job of `key` is replaced
+    pub async fn push_once(
+        &self,
+        conn: &impl ConnectionTrait,
+        task: Task,
+        key: &str,
+    ) -> Result<bool> {
+        Job::delete_many()
+            .filter(job::Column::Key.eq(key))
+            .filter(job::Column::Status.eq(JobStatus::Failed))
+            .exec(conn)
+            .await?;
+        let inserted = insert(conn, &task, Some(key), now()).await?;
+        if inserted {
+            self.wake.notify_one();
+        }
+        Ok(inserted)
+    }
+
+    /// Make a failed job pending again with a fresh set of attempts, false if
+    /// there is no such job
+    pub async fn retry(&self, conn: &DbConn, id: i32) -> Result<bool> {
+        let res = Job::update_many()
+            .col_expr(job::Column::Status, JobStatus::Pending.into())
+            .col_expr(job::Column::Attempts, 0.into())
+            .col_expr(job::Column::RunAt, now().into())
+            .col_expr(job::Column::UpdatedAt, now().into())
+            .filter(job::Column::Id.eq(id))
+            .filter(job::Column::Status.eq(JobStatus::Failed))
+            .exec(conn)
+            .await?;
+        self.wake.notify_one();
+        Ok(res.rows_affected > 0)
+    }
+
+    /// Schedule the recurring jobs and start the workers
+    pub fn spawn_workers(app: Arc<AppState>) {
+        tokio::spawn(async move {
+            let now = now();
+            for (task, key) in [
+                (Task::Retention, "retention"),
+                (Task::TrashPurge, "trash_purge"),
+                (Task::AccountPurge, "account_purge"),
+                (Task::StatsPurge, "stats_purge"),
+            ] {
+                if let Err(err) = insert(&app.conn, &task, Some(key), now).await {
+                    tracing::warn!("cannot schedule the {} job: {}", key, err);
+                }
+            }
+            for _ in 0..JOB_WORKERS {
+                tokio::spawn(work(app.clone()));
+            }
+        });
+    }
+}
+
+/// Nothing is inserted if a job of `key` exists, false then
+async fn insert(
+    conn: &impl ConnectionTrait,
+    task: &Task,
+    key: Option<&str>,
+    now: i64,
+) -> Result<bool> {
+    let inserted = Job::insert(job::ActiveModel {
+        kind: Set(task.kind()),
+        key: Set(key.map(str::to_owned)),
+        payload: Set(serde_json::to_string(task)?),
+        status: Set(JobStatus::Pending),
+        attempts: Set(0),
+        run_at: Set(now),
+        last_error: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    })
+    .on_conflict(OnConflict::column(job::Column::Key).do_nothing().to_owned())
+    .exec_without_returning(conn)
+    .await?;
+    Ok(inserted > 0)
+}
+
+async fn work(app: Arc<AppState>) {
+    loop {
+        match claim(&app.conn).await {
+            Ok(Some(job)) => {
+                run(&app, job).await;
+                continue;
+            }
+            Ok(None) => {}
+            Err(err) => tracing::warn!("cannot claim a job: {}", err),
+        }
+        tokio::select! {
+            _ = app.jobs.wake.notified() => {}
+            _ = tokio::time::sleep(Duration::from_secs(JOB_POLL_INTERVAL)) => {}
+        }
+    }
+}
+
+/// A due job, or a running one whose lease ended, leased to this worker
+async fn claim(conn: &DbConn) -> Result<Option<job::Model>> {
+    let now = now();
+    let due = Job::find()
+        .filter(job::Column::Status.is_in([JobStatus::Pending, JobStatus::Running]))
+        .filter(job::Column::RunAt.lte(now))
+        .order_by_asc(job::Column::RunAt)
+        .limit(JOB_WORKERS as u64)
+        .all(conn)
+        .await?;
+    for job in due {
+        // another worker may have claimed it since
+        let claimed = Job::update_many()
+            .col_expr(job::Column::Status, JobStatus::Running.into())
+            .col_expr(job::Column::Attempts, (job.attempts + 1).into())
+            .col_expr(job::Column::RunAt, (now + JOB_LEASE_SECS).into())
+            .col_expr(job::Column::UpdatedAt, now.into())
+            .filter(job::Column::Id.eq(job.id))
+            .filter(job::Column::Status.eq(job.status))
+            .filter(job::Column::RunAt.eq(job.run_at))
+            .exec(conn)
+            .await?;
+        if claimed.rows_affected > 0 {
+            return Ok(Some(job::Model {
+                attempts: job.attempts + 1,
+                ..job
+            }));
+        }
+    }
+    Ok(None)
+}
+
+async fn run(app: &Arc<AppState>, job: job::Model) {
+    let every = every(job.kind);
+    let last = every.is_none() && job.attempts >= JOB_MAX_ATTEMPTS;
+    let res = match serde_json::from_str::<Task>(&job.payload) {
+        Ok(task) => task.run(app, last).await,
+        Err(err) => Err(err.into()),
+    };
+    if let Err(err) = &res {
+        tracing::warn!(
+            "job {} of {:?} failed at attempt {}: {}",
+            job.id,
+            job.kind,
+            job.attempts,
+            err
+        );
+    }
+    if let Err(err) = finish(&app.conn, &job, every, res).await {
+        tracing::warn!("cannot update job {}: {}", job.id, err);
+    }
+}
+
+async fn finish(
+    conn: &DbConn,
+    job: &job::Model,
+    every: Option<u64>,
+    res: Result<()>,
+) -> Result<()> {
+    let now = now();
+    let (status, attempts, run_at, error) = match (res, every) {
+        (Ok(()), None) => {
+            Job::delete_by_id(job.id).exec(conn).await?;
+            return Ok(());
+        }
+        (Ok(()), Some(every)) => (JobStatus::Pending, 0, now + every as i64, None),
+        (Err(err), _) if job.attempts < JOB_MAX_ATTEMPTS => (
+            JobStatus::Pending,
+            job.attempts,
+            now + backoff(job.attempts),
+            Some(err.to_string()),
+        ),
+        (Err(err), Some(every)) => (
+            JobStatus::Pending,
+            0,
+            now + every as i64,
+            Some(err.to_string()),
+        ),
+        (Err(err), None) => (JobStatus::Failed, job.attempts, now, Some(err.to_string())),
+    };
+    Job::update_many()
+        .col_expr(job::Column::Status, status.into())
+        .col_expr(job::Column::Attempts, attempts.into())
+        .col_expr(job::Column::RunAt, run_at.into())
+        .col_expr(job::Column::LastError, error.into())
+        .col_expr(job::Column::UpdatedAt, now.into())
+        .filter(job::Column::Id.eq(job.id))
+        .exec(conn)
+        .await?;
+    Ok(())
+}
+
+/// Seconds before the attempt after `attempts` failed ones
+fn backoff(attempts: i32) -> i64 {
+    JOB_BACKOFF_SECS << (attempts - 1).clamp(0, 16)
+}
+
+fn now() -> i64 {
+    time::UtcDateTime::now().unix_timestamp()
+}
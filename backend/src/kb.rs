@@ -0,0 +1,302 @@
+//! Knowledge base of the documents a user adds, see `routes::kb`
+//!
+//! A document is an uploaded text, HTML, PDF or DOCX file, see
+//! `utils::extract`. Each page or heading of it is split into chunks of
+//! [`KB_CHUNK_CHARS`] overlapping by [`KB_CHUNK_OVERLAP_CHARS`], each embedded
+//! by the embedding provider, see `EMBEDDING_MODEL` env. Ingestion runs as a job of `jobs`,
+//! retried on failure and resumed after a restart
+//!
+//! Before a reply, the text of the user is embedded and the [`KB_TOP_K`] chunks
+//! of their documents closest to it are added to the system prompt, numbered
+//! for the model to cite, and linked to the reply. A chat scoped to some
+//! collections of the user only searches the documents in them, see
+//! `routes::kb::assign`. Vectors are compared in full, the documents of a
+//! user stay few enough for it
+
+use anyhow::{Context, Result, bail};
+use entity::{
+    DocumentStatus, LinkKind, chat_collection, chunk, document, document_chunk, patch::ChunkKind,
+    prelude::*,
+};
+use sea_orm::{
+    ActiveValue::Set, JoinType, QueryOrder, QuerySelect, QueryTrait, TransactionTrait, prelude::*,
+};
+
+use crate::{
+    AppState,
+    config::{KB_CHUNK_CHARS, KB_CHUNK_OVERLAP_CHARS, KB_MIN_SCORE, KB_QUERY_MAX_CHARS, KB_TOP_K},
+    tools::EntityLink,
+    utils::extract,
+};
+
+/// A chunk close to the text of the user
+pub struct Passage {
+    pub document_id: i32,
+    pub name: String,
+    pub ordinal: i32,
+    /// Page or heading of the chunk
+    pub label: Option<String>,
+    pub content: String,
+}
+
+impl Passage {
+    /// Name of the document with the page or heading, the chunk number for
+    /// plain text
+    fn citation(&self) -> String {
+        match &self.label {
+            Some(label) => format!("{}, {}", self.name, label),
+            None => format!("{} §{}", self.name, self.ordinal + 1),
+        }
+    }
+}
+
+/// Ingest the document as a job of `jobs`, its status tell when it is done
+///
+/// A failure leaves it pending for the next attempt, the `last` one marks it
+/// failed
+pub async fn ingest_job(app: &AppState, document_id: i32, last: bool) -> Result<()> {
+    let res = ingest(app, document_id).await;
+    let (status, error) = match &res {
+        Ok(chunks) => {
+            tracing::info!("document {} ingested in {} chunks", document_id, chunks);
+            (DocumentStatus::Ready, None)
+        }
+        Err(_) if !last => return res.map(|_| ()),
+        Err(err) => (DocumentStatus::Failed, Some(err.to_string())),
+    };
+    Document::update_many()
+        .col_expr(document::Column::Status, status.into())
+        .col_expr(document::Column::Error, error.into())
+        .filter(document::Column::Id.eq(document_id))
+        .exec(&app.conn)
+        .await?;
+    res.map(|_| ())
+}
+
+/// Chunk and embed the file of the document, replacing its earlier chunks
+async fn ingest(app: &AppState, document_id: i32) -> Result<usize> {
+    let (_, file) = Document::find_by_id(document_id)
+        .find_also_related(File)
+        .one(&app.conn)
+        .await?
+        .context("the document was deleted")?;
+    let file = file.context("the file of the document was deleted")?;
+    let Some(format) = extract::format(&file.content_type, &file.name) else {
+        bail!("cannot read {} files", file.content_type);
+    };
+    let data = app.files.get(&file.storage_key).await?;
+    let chunks: Vec<(Option<String>, String)> = extract::extract(format, data)
+        .await?
+        .into_iter()
+        .flat_map(|x| {
+            let label = x.label;
+            chunk(&x.text).into_iter().map(move |x| (label.clone(), x))
+        })
+        .collect();
+    if chunks.is_empty() {
+        bail!("the document has no text");
+    }
+    let embeddings = app
+        .openrouter
+        .embed(chunks.iter().map(|(_, x)| x.clone()).collect())
+        .await?;
+
+    let txn = app.conn.begin().await?;
+    DocumentChunk::delete_many()
+        .filter(document_chunk::Column::DocumentId.eq(document_id))
+        .exec(&txn)
+        .await?;
+    let count = chunks.len();
+    let rows: Vec<_> = chunks
+        .into_iter()
+        .zip(embeddings)
+        .enumerate()
+        .map(
+            |(i, ((label, content), vector))| document_chunk::ActiveModel {
+                document_id: Set(document_id),
+                ordinal: Set(i as i32),
+                label: Set(label),
+                content: Set(content),
+                embedding: Set(encode(&vector)),
+                ..Default::default()
+            },
+        )
+        .collect();
+    // kept under the limit of variables of a statement
+    for batch in rows.chunks(100) {
+        DocumentChunk::insert_many(batch.iter().cloned())
+            .exec(&txn)
+            .await?;
+    }
+    Document::update_many()
+        .col_expr(document::Column::Chunks, (count as i32).into())
+        .filter(document::Column::Id.eq(document_id))
+        .exec(&txn)
+        .await?;
+    txn.commit().await?;
+    Ok(count)
+}
+
+/// Split the text in chunks of [`KB_CHUNK_CHARS`], broken at a blank where
+/// one is in the second half
+pub fn chunk(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut chunks = vec![];
+    let mut start = 0;
+    while start < chars.len() {
+        let mut end = (start + KB_CHUNK_CHARS).min(chars.len());
+        if end < chars.len()
+            && let Some(x) = (start + KB_CHUNK_CHARS / 2..end)
+                .rev()
+                .find(|x| chars[*x].is_whitespace())
+        {
+            end = x + 1;
+        }
+        let chunk: String = chars[start..end].iter().collect();
+        let chunk = chunk.trim();
+        if !chunk.is_empty() {
+            chunks.push(chunk.to_owned());
+        }
+        if end == chars.len() {
+            break;
+        }
+        start = end - KB_CHUNK_OVERLAP_CHARS.min(end - start - 1);
+    }
+    chunks
+}
+
+/// Chunks of the ready documents of the user closest to the query, best first,
+/// among the collections of the chat if it is scoped to some
+///
+/// Nothing is embedded for users without documents
+pub async fn search(
+    app: &AppState,
+    user_id: i32,
+    chat_id: i32,
+    query: &str,
+) -> Result<Vec<Passage>> {
+    let scope = scope(&app.conn, chat_id).await?;
+    let vectors: Vec<(i32, Vec<u8>)> = DocumentChunk::find()
+        .select_only()
+        .column(document_chunk::Column::Id)
+        .column(document_chunk::Column::Embedding)
+        .join(
+            JoinType::InnerJoin,
+            document_chunk::Relation::Document.def(),
+        )
+        .filter(document::Column::OwnerId.eq(user_id))
+        .filter(document::Column::Status.eq(DocumentStatus::Ready))
+        .apply_if((!scope.is_empty()).then_some(scope), |q, x| {
+            q.filter(document::Column::CollectionId.is_in(x))
+        })
+        .into_tuple()
+        .all(&app.conn)
+        .await?;
+    let query = query.trim();
+    if vectors.is_empty() || query.is_empty() {
+        return Ok(vec![]);
+    }
+    let query: String = query.chars().take(KB_QUERY_MAX_CHARS).collect();
+    let query = app
+        .openrouter
+        .embed(vec![query])
+        .await?
+        .pop()
+        .context("no embedding of the query")?;
+
+    let mut scored: Vec<(f32, i32)> = vectors
+        .into_iter()
+        .filter_map(|(id, x)| {
+            let x = decode(&x);
+            // chunks embedded by another model are not comparable
+            (x.len() == query.len()).then(|| (cosine(&query, &x), id))
+        })
+        .filter(|(score, _)| *score >= KB_MIN_SCORE)
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.truncate(KB_TOP_K);
+    let ids: Vec<i32> = scored.iter().map(|(_, id)| *id).collect();
+
+    let mut rows = DocumentChunk::find()
+        .find_also_related(Document)
+        .filter(document_chunk::Column::Id.is_in(ids.clone()))
+        .all(&app.conn)
+        .await?;
+    rows.sort_by_key(|(x, _)| ids.iter().position(|id| *id == x.id));
+    Ok(rows
+        .into_iter()
+        .filter_map(|(chunk, document)| {
+            Some(Passage {
+                document_id: chunk.document_id,
+                name: document?.name,
+                ordinal: chunk.ordinal,
+                label: chunk.label,
+                content: chunk.content,
+            })
+        })
+        .collect())
+}
+
+/// Collections the chat is scoped to, empty for every document
+pub async fn scope(conn: &DbConn, chat_id: i32) -> Result<Vec<i32>, DbErr> {
+    ChatCollection::find()
+        .select_only()
+        .column(chat_collection::Column::CollectionId)
+        .filter(chat_collection::Column::ChatId.eq(chat_id))
+        .order_by_asc(chat_collection::Column::CollectionId)
+        .into_tuple()
+        .all(conn)
+        .await
+}
+
+/// Text of a user message, what a regenerated reply search with
+pub async fn message_text(conn: &DbConn, message_id: i32) -> Result<String> {
+    let chunks = Chunk::find()
+        .filter(chunk::Column::MessageId.eq(message_id))
+        .filter(chunk::Column::Kind.eq(ChunkKind::Text))
+        .order_by_asc(chunk::Column::Id)
+        .all(conn)
+        .await?;
+    Ok(chunks.into_iter().map(|x| x.content).collect())
+}
+
+/// Appended to the system prompt, passages are cited by their number
+pub fn prompt(passages: &[Passage]) -> String {
+    let mut prompt = "\n\nPassages from the documents of the user that may help with the reply. \
+Where one is used, cite it by its number in brackets, e.g. [1]; ignore those unrelated."
+        .to_owned();
+    for (i, x) in passages.iter().enumerate() {
+        prompt.push_str(&format!("\n\n[{}] {}\n{}", i + 1, x.citation(), x.content));
+    }
+    prompt
+}
+
+/// Sources listed under the reply, in the order they are cited
+pub fn links(passages: &[Passage]) -> Vec<EntityLink> {
+    passages
+        .iter()
+        .map(|x| EntityLink {
+            kind: LinkKind::Document,
+            id: x.document_id.to_string(),
+            label: x.citation(),
+        })
+        .collect()
+}
+
+fn encode(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn decode(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|x| f32::from_le_bytes([x[0], x[1], x[2], x[3]]))
+        .collect()
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |x: &[f32]| x.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm = norm(a) * norm(b);
+    if norm == 0.0 { 0.0 } else { dot / norm }
+}
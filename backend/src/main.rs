@@ -1,212 +1,158 @@
+mod activity;
+mod app;
+mod audit;
+mod backup;
+mod bind;
+mod cache;
+mod cli;
+mod compaction;
 mod config;
+mod database;
+mod demo;
 mod errors;
+mod federation;
+mod files;
+mod idempotency;
+mod imagegen;
+mod jobs;
+mod kb;
+mod memory;
 mod middlewares;
+mod moderation;
+mod notify;
+mod oauth;
 mod openrouter;
+mod prefetch;
+mod pricing;
 mod prompts;
+mod push;
+mod quota;
+mod reply_stats;
+mod retention;
 mod routes;
+mod routing;
+mod schedule;
+mod spend;
 mod sse;
+mod stt;
+mod takeout;
+mod telemetry;
+mod tls;
 mod tools;
+mod trash;
+mod tts;
+mod undo;
 mod utils;
+mod webhook;
 
-use std::sync::Arc;
+#[cfg(all(feature = "headless", feature = "desktop"))]
+compile_error!(
+    "`headless` and `desktop` are exclusive, build with `--no-default-features --features desktop`"
+);
 
 use crate::{openrouter::Openrouter, prompts::PromptEnv, tools::ToolStore};
-use anyhow::Context;
-use axum::{Router, middleware};
-use betrayer::{
-    Icon, Menu, MenuItem, TrayEvent, TrayIcon, TrayIconBuilder, winit::WinitTrayIconBuilderExt,
-};
-use dotenv::var;
-use entity::prelude::*;
-use middlewares::cache_control::CacheControlLayer;
-use migration::MigratorTrait;
-use pasetors::{keys::SymmetricKey, version4::V4};
-use sea_orm::{Database, DbConn, EntityTrait};
+use clap::Parser;
+use sea_orm::DbConn;
 use sse::SseContext;
-use tokio::net::TcpListener;
-use tower::ServiceBuilder;
-use tower_http::services::{ServeDir, ServeFile};
 use tracing::Level;
-use tracing_subscriber::{filter, layer::SubscriberExt, util::SubscriberInitExt};
-use utils::password_hash::Hasher;
-use winit::{
-    application::ApplicationHandler,
-    event::{Event, WindowEvent},
-    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
-    window::WindowId,
+use tracing_subscriber::{
+    Layer, filter, fmt::writer::BoxMakeWriter, layer::SubscriberExt, util::SubscriberInitExt,
 };
-
-#[cfg(feature = "dev")]
-use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
+use utils::password_hash::Hasher;
 
 pub struct AppState {
     pub conn: DbConn,
-    pub key: SymmetricKey<V4>,
+    /// Keys of access tokens, see `utils::keyring`
+    pub keyring: utils::keyring::Keyring,
+    /// Seals the credentials stored in the database, see `utils::secrets`
+    pub secrets: utils::secrets::Secrets,
     pub sse: SseContext,
     pub prompt: PromptEnv,
     pub hasher: Hasher,
     pub openrouter: Openrouter,
     pub tools: ToolStore,
+    /// Stamped into exported documents
+    pub instance_id: String,
+    /// Only in demo mode
+    pub demo: Option<demo::Demo>,
+    pub pricing: pricing::Pricing,
+    pub prefetch: prefetch::Prefetch,
+    pub oauth: oauth::OAuth,
+    /// Only if SMTP is configured
+    pub mailer: Option<notify::mailer::Mailer>,
+    /// Tool calls waiting for the user to answer a question
+    pub inputs: tools::PendingInputs,
+    /// Deletions that can still be undone
+    pub undo: undo::Undo,
+    /// Past buckets of `user/activity`, recounted daily
+    pub activity: activity::Activity,
+    /// Rows read on most requests, see `cache`
+    pub cache: cache::Caches,
+    pub retention: retention::Retention,
+    /// Settings admins change at runtime, see `admin/config`
+    pub settings: config::Settings,
+    pub rate_limit: middlewares::rate_limit::RateLimiter,
+    pub body_limits: middlewares::body_limit::BodyLimits,
+    /// Pause generations on a burst of spending
+    pub spend: spend::SpendGuard,
+    /// Monthly spending caps of users and models
+    pub spend_caps: spend::cap::SpendCaps,
+    pub quotas: quota::Quotas,
+    /// Screening of messages and replies, see `admin/config`
+    pub moderation: moderation::Moderation,
+    /// Keys of `message/create` seen recently
+    pub idempotency: idempotency::Idempotency,
+    /// Notifications outside of chats, see `/api/user/notifications`
+    pub notifier: notify::Notifier,
+    /// Web Push to users without a stream open
+    pub push: push::Push,
+    /// Background work that outlives a restart
+    pub jobs: jobs::Jobs,
+    pub files: files::Files,
+    /// Only if `STT_PROVIDER` is configured
+    pub stt: Option<stt::Stt>,
+    /// Only if `TTS_PROVIDER` is configured
+    pub tts: Option<tts::Tts>,
+    /// Only if `IMAGE_PROVIDER` is configured
+    pub image: Option<imagegen::ImageGen>,
 }
 
-#[tokio::main(flavor = "current_thread")]
-async fn main() {
+fn main() {
     dotenv::dotenv().ok();
 
+    let command = cli::Cli::parse().command.unwrap_or(cli::Command::Serve);
+    let serving = matches!(command, cli::Command::Serve);
+    // stdout of the other subcommands is their output
+    let writer = match serving {
+        true => BoxMakeWriter::new(std::io::stdout),
+        false => BoxMakeWriter::new(std::io::stderr),
+    };
+
     tracing_subscriber::registry()
-        .with(tracing_subscriber::fmt::layer())
-        .with(filter::Targets::new().with_target("backend", Level::TRACE))
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(writer)
+                .with_filter(filter::Targets::new().with_target("backend", Level::TRACE)),
+        )
+        .with(telemetry::layer())
         .init();
 
-    let database_url = var("DATABASE_URL").unwrap_or("sqlite://db.sqlite?mode=rwc".to_owned());
-    let bind_addr = var("BIND_ADDR").unwrap_or("0.0.0.0:8001".to_owned());
-    let static_dir = var("STATIC_DIR").unwrap_or("../frontend/build".to_owned());
-
-    migration::migrate(&database_url)
-        .await
-        .expect("Migration failed");
-
-    let conn = Database::connect(database_url)
-        .await
-        .expect("Cannot connect to database");
-
-    migration::Migrator::up(&conn, None)
-        .await
-        .expect("Cannot migrate database");
-
-    let key = SymmetricKey::from(
-        &Config::find_by_id("paseto_key")
-            .one(&conn)
-            .await
-            .unwrap()
-            .context("Cannot find paseto key")
-            .unwrap()
-            .value,
-    )
-    .expect("Cannot parse paseto key");
-
-    let sse = SseContext::new(conn.clone());
-    let prompt = PromptEnv::new(conn.clone());
-    let openrouter = Openrouter::new();
-    let mut tools = ToolStore::new(conn.clone());
+    let runtime = app::runtime();
 
-    tools.add_tool::<tools::wttr::Wttr>().unwrap();
-    tools.add_tool::<tools::nearbyplace::NearByPlace>().unwrap();
-    tools.add_tool::<tools::mail::RecentMail>().unwrap();
-    tools.add_tool::<tools::mail::ReplyMail>().unwrap();
-    tools.add_tool::<tools::mail::SendMail>().unwrap();
-    tools.add_tool::<tools::mail::GetMailContent>().unwrap();
-    tools.add_tool::<tools::rss::RssSearch>().unwrap();
+    if !serving {
+        if let Err(err) = runtime.block_on(cli::run(command)) {
+            eprintln!("error: {:#}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    let state = Arc::new(AppState {
-        conn,
-        key,
-        sse,
-        hasher: Hasher::default(),
-        openrouter,
-        prompt,
-        tools,
-    });
+    #[cfg(feature = "desktop")]
+    {
+        drop(runtime);
+        app::run_desktop().expect("Cannot start system tray");
+    }
 
-    let var_name = Router::new();
-    let app = var_name
-        .nest(
-            "/api",
-            Router::new()
-                .nest("/chat", routes::chat::routes())
-                .nest("/user", routes::user::routes())
-                .nest("/message", routes::message::routes())
-                .nest("/model", routes::model::routes())
-                .layer(middleware::from_extractor_with_state::<
-                    middlewares::auth::Middleware,
-                    _,
-                >(state.clone()))
-                .nest("/auth", routes::auth::routes()),
-        )
-        .fallback_service(
-            ServiceBuilder::new().layer(CacheControlLayer).service(
-                ServeDir::new(static_dir.to_owned())
-                    .precompressed_gzip()
-                    .precompressed_br()
-                    .fallback(
-                        ServeFile::new(format!("{}/index.html", static_dir))
-                            .precompressed_br()
-                            .precompressed_gzip(),
-                    ),
-            ),
-        )
-        .with_state(state);
-
-    #[cfg(feature = "dev")]
-    let app = app.layer(
-        CorsLayer::new()
-            .allow_methods(AllowMethods::any())
-            .allow_origin(AllowOrigin::any())
-            .allow_headers(AllowHeaders::list([
-                http::header::AUTHORIZATION,
-                http::header::CONTENT_TYPE,
-            ])),
-    );
-
-    let tcp = TcpListener::bind(bind_addr).await.unwrap();
-    tokio::spawn(async {
-        axum::serve(tcp, app).await.unwrap();
-    })
-    .await;
-    // tray().unwrap();
+    #[cfg(not(feature = "desktop"))]
+    runtime.block_on(app::run_server());
 }
-
-// #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-// enum Signal {
-//     Profile(u32),
-//     Open,
-//     Quit,
-// }
-
-// fn tray() -> anyhow::Result<()> {
-//     let event_loop = EventLoop::with_user_event().build()?;
-
-//     let tray = TrayIconBuilder::new()
-//         .with_icon(Icon::from_png_bytes(include_bytes!(concat!(
-//             env!("CARGO_MANIFEST_DIR"),
-//             "/../frontend/static/favicon-96x96.png"
-//         )))?)
-//         .with_tooltip("Demo System Tray")
-//         .with_menu(build_menu())
-//         .build_event_loop(&event_loop, |e| Some(e))?;
-
-//     event_loop.set_control_flow(ControlFlow::Wait);
-//     event_loop.run_app(&mut App { tray })?;
-//     Ok(())
-// }
-
-// struct App {
-//     tray: TrayIcon<Signal>,
-// }
-
-// impl ApplicationHandler<TrayEvent<Signal>> for App {
-//     fn resumed(&mut self, _event_loop: &ActiveEventLoop) {}
-//     fn user_event(&mut self, event_loop: &ActiveEventLoop, event: TrayEvent<Signal>) {
-//         if let TrayEvent::Menu(signal) = event {
-//             match signal {
-//                 Signal::Profile(i) => {
-//                     self.tray.set_tooltip(format!("Active Profile: Hi"));
-//                     self.tray.set_menu(build_menu());
-//                 }
-//                 Signal::Open => {}
-//                 Signal::Quit => event_loop.exit(),
-//             }
-//         }
-//     }
-//     fn window_event(
-//         &mut self,
-//         _event_loop: &ActiveEventLoop,
-//         _window_id: WindowId,
-//         _event: WindowEvent,
-//     ) {
-//     }
-// }
-
-// fn build_menu() -> Menu<Signal> {
-//     Menu::new([MenuItem::button("Quit", Signal::Quit)])
-// }
@@ -0,0 +1,112 @@
+//! Facts about a user kept across their chats
+//!
+//! The model saves them with `rememberfact` and looks them up with
+//! `recallfacts`, see `tools::memory`; the user reads and removes them in
+//! their settings. Every reply gets the [`MEMORY_PROMPT_FACTS`] most relevant
+//! ones in its system prompt, see `PromptEnv::memories`: those sharing the
+//! most words with the text of the user first, then the latest
+
+use anyhow::Result;
+use entity::{memory, prelude::*};
+use sea_orm::{ActiveValue::Set, QueryOrder, prelude::*};
+
+use crate::config::{MEMORY_FACT_MAX_CHARS, MEMORY_MAX_PER_USER};
+
+pub enum Saved {
+    New(i32),
+    /// The same fact was saved before
+    Known,
+    /// The user has [`MEMORY_MAX_PER_USER`] facts
+    Full,
+}
+
+/// Save a fact, trimmed and cut to [`MEMORY_FACT_MAX_CHARS`]
+pub async fn save(
+    conn: &DbConn,
+    user_id: i32,
+    chat_id: Option<i32>,
+    content: &str,
+) -> Result<Saved> {
+    let content: String = content.trim().chars().take(MEMORY_FACT_MAX_CHARS).collect();
+    let facts = all(conn, user_id).await?;
+    if facts
+        .iter()
+        .any(|x| x.content.to_lowercase() == content.to_lowercase())
+    {
+        return Ok(Saved::Known);
+    }
+    if facts.len() as u64 >= MEMORY_MAX_PER_USER {
+        return Ok(Saved::Full);
+    }
+    let id = Memory::insert(memory::ActiveModel {
+        owner_id: Set(user_id),
+        content: Set(content),
+        chat_id: Set(chat_id),
+        created_at: Set(time::UtcDateTime::now().unix_timestamp()),
+        ..Default::default()
+    })
+    .exec(conn)
+    .await?
+    .last_insert_id;
+    Ok(Saved::New(id))
+}
+
+/// Facts of the user, latest first
+pub async fn all(conn: &DbConn, user_id: i32) -> Result<Vec<memory::Model>> {
+    Ok(Memory::find()
+        .filter(memory::Column::OwnerId.eq(user_id))
+        .order_by_desc(memory::Column::Id)
+        .all(conn)
+        .await?)
+}
+
+/// Facts sharing the most words with the text, then the latest, `limit` at most
+pub async fn relevant(
+    conn: &DbConn,
+    user_id: i32,
+    text: &str,
+    limit: usize,
+) -> Result<Vec<memory::Model>> {
+    let terms = terms(text);
+    let mut facts: Vec<_> = all(conn, user_id)
+        .await?
+        .into_iter()
+        .map(|x| (score(&x.content, &terms), x))
+        .collect();
+    // stable, the latest stay first among those of the same score
+    facts.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    Ok(facts.into_iter().take(limit).map(|(_, x)| x).collect())
+}
+
+/// Facts containing a word of the query, latest first
+pub async fn search(
+    conn: &DbConn,
+    user_id: i32,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<memory::Model>> {
+    let terms = terms(query);
+    Ok(all(conn, user_id)
+        .await?
+        .into_iter()
+        .filter(|x| terms.is_empty() || score(&x.content, &terms) > 0)
+        .take(limit)
+        .collect())
+}
+
+/// Lowercase words of two characters or more
+fn terms(text: &str) -> Vec<String> {
+    let mut terms: Vec<String> = text
+        .split(|x: char| !x.is_alphanumeric())
+        .filter(|x| x.chars().count() >= 2)
+        .map(str::to_lowercase)
+        .collect();
+    terms.sort();
+    terms.dedup();
+    terms
+}
+
+fn score(fact: &str, terms: &[String]) -> usize {
+    let fact = fact.to_lowercase();
+    terms.iter().filter(|x| fact.contains(x.as_str())).count()
+}
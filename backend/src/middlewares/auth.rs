@@ -5,18 +5,96 @@ use axum::{
     extract::FromRequestParts,
     http::{header, request::Parts},
 };
-use pasetors::{Local, claims::ClaimsValidationRules, local, token::UntrustedToken, version4::V4};
+use entity::{ApiKeyScope, ApiKeyScopes, UserRole, prelude::*, user};
+use pasetors::claims::ClaimsValidationRules;
+use sea_orm::{DbErr, EntityTrait};
 
-use crate::{AppState, errors::*};
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::locale,
+    utils::{api_key, session, workspace},
+};
 
 #[derive(Debug, Clone, Copy)]
 pub struct UserId(pub i32);
 
+/// Workspace the request works in, see `utils::workspace`
+#[derive(Debug, Clone, Copy)]
+pub struct WorkspaceId(pub i32);
+
+/// Session of the token, absent for demo tokens and API keys
+#[derive(Debug, Clone, Copy)]
+pub struct SessionId(pub i32);
+
+/// Present on requests made with a demo token
+#[derive(Debug, Clone, Copy)]
+pub struct DemoUser;
+
+/// Present on requests made with an API key instead of a token
+#[derive(Debug, Clone)]
+pub struct ApiKeyUser(pub ApiKeyScopes);
+
+/// Reject users without the admin role, use it after [`Middleware`]
+#[derive(Debug, Clone, Copy)]
+pub struct AdminOnly;
+
+/// Routes a demo token can reach, relative to `/api`
+const DEMO_ROUTES: &[&str] = &["/chat/", "/message/", "/model/list", "/undo/"];
+/// Routes an API key with the `read` scope can reach, relative to `/api`,
+/// besides `/chat/{id}/export`
+const READ_ROUTES: &[&str] = &[
+    "/chat/read",
+    "/chat/paginate",
+    "/chat/sse",
+    "/chat/tags",
+    "/chat/trash/list",
+    "/folder/list",
+    "/label/list",
+    "/message/paginate",
+    "/message/search",
+    "/message/stats",
+    "/model/list",
+    "/model/read",
+    "/user/activity",
+    "/user/read",
+    "/user/search_terms/read",
+    "/user/export",
+];
+/// Routes an API key with the `stats` scope can reach, relative to `/api`
+///
+/// Aggregates only, the admin ones still need the owner of the key to be an admin
+const STATS_ROUTES: &[&str] = &[
+    "/chat/tags",
+    "/user/activity",
+    "/user/stats",
+    "/pricing/list",
+    "/pricing/history",
+    "/admin/context",
+    "/admin/spend/read",
+    "/admin/stats",
+    "/admin/system",
+    "/admin/tags",
+];
+/// Routes an API key with the `chat` scope can reach, relative to `/api`
+const CHAT_ROUTES: &[&str] = &[
+    "/capture",
+    "/chat/",
+    "/file/",
+    "/folder/",
+    "/label/",
+    "/message/",
+    "/model/list",
+    "/undo/",
+    "/v1/",
+];
+
 pub struct Middleware;
 
 impl FromRequestParts<Arc<AppState>> for Middleware {
     type Rejection = Json<Error>;
 
+    #[tracing::instrument(name = "auth", skip_all)]
     async fn from_request_parts(
         parts: &mut Parts,
         state: &Arc<AppState>,
@@ -28,22 +106,201 @@ impl FromRequestParts<Arc<AppState>> for Middleware {
             .kind(ErrorKind::Unauthorized)?;
 
         let token = token.to_str().kind(ErrorKind::MalformedToken)?;
-        let token = UntrustedToken::<Local, V4>::try_from(token).kind(ErrorKind::MalformedToken)?;
-        let validation_rules = ClaimsValidationRules::new();
-        let token = local::decrypt(&state.key, &token, &validation_rules, None, None)
-            .kind(ErrorKind::MalformedToken)?;
-
-        let claim = token
-            .payload_claims()
-            .map(|x| x.get_claim("uid").map(|x| x.as_i64()))
-            .flatten()
-            .flatten();
-
-        let user_id = claim
-            .ok_or("Missing claim")
-            .kind(ErrorKind::MalformedToken)? as i32;
-        parts.extensions.insert(UserId(user_id));
+        // OpenAI clients send the API key as a bearer token, see `routes::openai`
+        let token = token.strip_prefix("Bearer ").unwrap_or(token);
+
+        if token.starts_with(api_key::PREFIX) {
+            let (user_id, workspace_id, scopes) = api_key::find(&state.conn, token)
+                .await
+                .kind(ErrorKind::Internal)?
+                .ok_or("unknown or revoked API key")
+                .kind(ErrorKind::Unauthorized)?;
+            let path = parts.uri.path();
+            let path = path.strip_prefix("/api").unwrap_or(path);
+            let allowed = |scope, routes: &[&str]| {
+                scopes.has(scope) && routes.iter().any(|x| path.starts_with(x))
+            };
+            let export = path.starts_with("/chat/") && path.ends_with("/export");
+            let read =
+                allowed(ApiKeyScope::Read, READ_ROUTES) || scopes.has(ApiKeyScope::Read) && export;
+            if !read
+                && !allowed(ApiKeyScope::Chat, CHAT_ROUTES)
+                && !allowed(ApiKeyScope::Stats, STATS_ROUTES)
+            {
+                return Err(Json(Error {
+                    error: ErrorKind::Unauthorized,
+                    reason: "not in the scopes of this API key".to_owned(),
+                }));
+            }
+            let workspace_id = workspace(state, user_id, workspace_id).await?;
+            preferred_locale(state, user_id).await;
+            parts.extensions.insert(ApiKeyUser(scopes));
+            parts.extensions.insert(UserId(user_id));
+            parts.extensions.insert(workspace_id);
+            return Ok(Self);
+        }
+
+        let (user_id, workspace_id, demo, session) = verify(state, token).await?;
+        if let Some(demo) = demo {
+            let path = parts.uri.path();
+            let path = path.strip_prefix("/api").unwrap_or(path);
+            if !DEMO_ROUTES.iter().any(|x| path.starts_with(x)) {
+                return Err(Json(Error {
+                    error: ErrorKind::Unauthorized,
+                    reason: "not available in demo".to_owned(),
+                }));
+            }
+            parts.extensions.insert(demo);
+        }
+        if let Some(session) = session {
+            parts.extensions.insert(session);
+        }
+        preferred_locale(state, user_id.0).await;
+        parts.extensions.insert(user_id);
+        parts.extensions.insert(workspace_id);
 
         Ok(Self)
     }
 }
+
+impl FromRequestParts<Arc<AppState>> for AdminOnly {
+    type Rejection = Json<Error>;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let UserId(user_id) = *parts
+            .extensions
+            .get::<UserId>()
+            .ok_or("cannot find user")
+            .kind(ErrorKind::Unauthorized)?;
+        match is_admin(state, user_id).await? {
+            true => Ok(Self),
+            false => Err(Json(Error {
+                error: ErrorKind::Unauthorized,
+                reason: "only available to admins".to_owned(),
+            })),
+        }
+    }
+}
+
+/// The locale of the user's preference for a request without one, see
+/// `middlewares::locale`
+async fn preferred_locale(state: &AppState, user_id: i32) {
+    if locale::is_set() {
+        return;
+    }
+    match user(state, user_id).await {
+        Ok(Some(user)) => locale::fill(user.preference.locale.as_deref()),
+        Ok(None) => {}
+        Err(err) => tracing::warn!("cannot read the locale of user {}: {}", user_id, err),
+    }
+}
+
+/// The row of a user, cached for `CACHE_TTL`, see `cache`
+pub async fn user(state: &AppState, user_id: i32) -> Result<Option<user::Model>, DbErr> {
+    let load = User::find_by_id(user_id).one(&state.conn);
+    state.cache.users.get_or_load(user_id, load).await
+}
+
+/// Looked up on every request, so a demotion apply to live tokens within
+/// `CACHE_TTL`
+pub async fn is_admin(state: &AppState, user_id: i32) -> Result<bool, Json<Error>> {
+    let user = user(state, user_id)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("cannot find user")
+        .kind(ErrorKind::Unauthorized)?;
+    Ok(user.role == UserRole::Admin)
+}
+
+/// Decrypt a token and return the user it belongs to and their workspace
+///
+/// Tokens of revoked sessions are rejected, tokens issued before sessions had
+/// ids are accepted until they expire
+pub async fn verify(
+    state: &AppState,
+    token: &str,
+) -> Result<(UserId, WorkspaceId, Option<DemoUser>, Option<SessionId>), Json<Error>> {
+    let token = state
+        .keyring
+        .decrypt(token, &ClaimsValidationRules::new())
+        .kind(ErrorKind::MalformedToken)?;
+
+    let claims = token.payload_claims();
+    let claim = claims
+        .and_then(|x| x.get_claim("uid").map(|x| x.as_i64()))
+        .flatten();
+    let demo = claims
+        .and_then(|x| x.get_claim("demo"))
+        .and_then(|x| x.as_bool())
+        .is_some_and(|x| x)
+        .then_some(DemoUser);
+
+    let workspace_id = claims
+        .and_then(|x| x.get_claim("wid"))
+        .and_then(|x| x.as_i64())
+        .map(|x| x as i32);
+    let session = claims
+        .and_then(|x| x.get_claim("sid"))
+        .and_then(|x| x.as_i64())
+        .map(|x| SessionId(x as i32));
+
+    let user_id = claim
+        .ok_or("Missing claim")
+        .kind(ErrorKind::MalformedToken)? as i32;
+
+    if let Some(SessionId(session_id)) = session
+        && !session::alive(
+            state
+                .cache
+                .sessions
+                .get_or_load(session_id, session::expiry(&state.conn, session_id))
+                .await
+                .kind(ErrorKind::Internal)?,
+        )
+    {
+        return Err(Json(Error {
+            error: ErrorKind::Unauthorized,
+            reason: "session is revoked".to_owned(),
+        }));
+    }
+    let workspace_id = workspace(state, user_id, workspace_id).await?;
+    Ok((UserId(user_id), workspace_id, demo, session))
+}
+
+/// The workspace of the token if the user is still a member, otherwise the
+/// first one of the user, for tokens issued before workspaces and API keys
+/// bound to none
+///
+/// Looked up on every request, so removing a member apply to live tokens
+/// within `CACHE_TTL`
+async fn workspace(
+    state: &AppState,
+    user_id: i32,
+    claim: Option<i32>,
+) -> Result<WorkspaceId, Json<Error>> {
+    if let Some(id) = claim {
+        let load = workspace::is_member(&state.conn, id, user_id);
+        return match state
+            .cache
+            .members
+            .get_or_load((id, user_id), load)
+            .await
+            .kind(ErrorKind::Internal)?
+        {
+            true => Ok(WorkspaceId(id)),
+            false => Err(Json(Error {
+                error: ErrorKind::Unauthorized,
+                reason: "not a member of this workspace".to_owned(),
+            })),
+        };
+    }
+    workspace::resolve(&state.conn, user_id, None)
+        .await
+        .kind(ErrorKind::Internal)?
+        .map(WorkspaceId)
+        .ok_or("not a member of any workspace")
+        .kind(ErrorKind::Unauthorized)
+}
@@ -0,0 +1,106 @@
+//! Size limits of `/api` request bodies
+//!
+//! Multipart uploads and the JSON routes carrying a file ([`UPLOAD_ROUTES`])
+//! are limited by `UPLOAD_BODY_MAX_BYTES` env, other requests by
+//! `JSON_BODY_MAX_BYTES`. A larger `Content-Length` is refused before the body
+//! is read, a chunked body once it goes past the limit; both are answered 413
+//! with the `payload_too_large` error rather than the plain text rejection of
+//! axum. Routes still check the size of what they store, e.g. `FILE_MAX_BYTES`.
+//! Restoring a backup ([`UNLIMITED_ROUTES`]) is not limited
+
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    body::Body,
+    extract::{MatchedPath, Request, State},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use dotenv::var;
+use http_body_util::Limited;
+
+use crate::{
+    AppState,
+    config::{JSON_BODY_MAX_BYTES, UPLOAD_BODY_MAX_BYTES},
+    errors::*,
+};
+
+/// JSON routes whose body is a file, limited like uploads
+const UPLOAD_ROUTES: &[&str] = &["/api/capture", "/api/chat/import"];
+/// Admin routes whose body is as large as the instance
+const UNLIMITED_ROUTES: &[&str] = &["/api/admin/backup/restore"];
+
+#[derive(Debug, Clone, Copy)]
+pub struct BodyLimits {
+    pub json: usize,
+    pub upload: usize,
+}
+
+impl BodyLimits {
+    pub fn from_env() -> Self {
+        let bytes = |key: &str, default: usize| {
+            var(key)
+                .ok()
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            json: bytes("JSON_BODY_MAX_BYTES", JSON_BODY_MAX_BYTES),
+            upload: bytes("UPLOAD_BODY_MAX_BYTES", UPLOAD_BODY_MAX_BYTES),
+        }
+    }
+
+    fn of(&self, req: &Request) -> usize {
+        let multipart = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|x| x.to_str().ok())
+            .is_some_and(|x| x.starts_with("multipart/form-data"));
+        let route = req.extensions().get::<MatchedPath>().map(|x| x.as_str());
+        if route.is_some_and(|x| UNLIMITED_ROUTES.contains(&x)) {
+            return usize::MAX;
+        }
+        match multipart || route.is_some_and(|x| UPLOAD_ROUTES.contains(&x)) {
+            true => self.upload,
+            false => self.json,
+        }
+    }
+}
+
+pub async fn middleware(State(app): State<Arc<AppState>>, req: Request, next: Next) -> Response {
+    let limit = app.body_limits.of(&req);
+    let length = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.parse::<u64>().ok());
+    if length.is_some_and(|x| x > limit as u64) {
+        return too_large(limit);
+    }
+
+    let req = req.map(|body| Body::new(Limited::new(body, limit)));
+    let res = next.run(req).await;
+
+    // extractors that read past the limit answer in plain text
+    let json = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|x| x.as_bytes().starts_with(b"application/json"));
+    if res.status() == StatusCode::PAYLOAD_TOO_LARGE && !json {
+        return too_large(limit);
+    }
+    res
+}
+
+fn too_large(limit: usize) -> Response {
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        Json(Error {
+            error: ErrorKind::PayloadTooLarge,
+            reason: format!("The request is larger than {} bytes", limit),
+        }),
+    )
+        .into_response()
+}
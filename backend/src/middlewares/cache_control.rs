@@ -11,7 +11,6 @@ use tower::{Layer, Service};
 
 pub struct ResponseFuture<F> {
     response_future: F,
-    layer: CacheControlLayer,
 }
 
 #[derive(Debug, Default, Clone, Copy)]
@@ -21,20 +20,16 @@ impl<S> Layer<S> for CacheControlLayer {
     type Service = CacheControl<S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        CacheControl {
-            inner,
-            layer: *self,
-        }
+        CacheControl { inner }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct CacheControl<S> {
     inner: S,
-    layer: CacheControlLayer,
 }
 
-impl<'a, S, T, U> Service<Request<T>> for CacheControl<S>
+impl<S, T, U> Service<Request<T>> for CacheControl<S>
 where
     S: Service<Request<T>, Response = Response<U>>,
     U: Default,
@@ -51,10 +46,7 @@ where
     fn call(&mut self, req: Request<T>) -> Self::Future {
         let response_future = self.inner.call(req);
 
-        ResponseFuture {
-            response_future,
-            layer: self.layer,
-        }
+        ResponseFuture { response_future }
     }
 }
 
@@ -69,12 +61,12 @@ where
         let pin_response_future = Pin::new(&mut self.response_future);
         let mut response: Response<B> = ready!(pin_response_future.poll(cx))?;
 
-        if let Some(Ok(content_type)) = response.headers().get(CONTENT_TYPE).map(|x| x.to_str()) {
-            if content_type != "text/html" {
-                response
-                    .headers_mut()
-                    .insert(CACHE_CONTROL, HeaderValue::from_static("max-age=604800"));
-            }
+        if let Some(Ok(content_type)) = response.headers().get(CONTENT_TYPE).map(|x| x.to_str())
+            && content_type != "text/html"
+        {
+            response
+                .headers_mut()
+                .insert(CACHE_CONTROL, HeaderValue::from_static("max-age=604800"));
         }
 
         Poll::Ready(Ok(response))
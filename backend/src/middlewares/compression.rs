@@ -0,0 +1,88 @@
+//! gzip of the JSON responses of `/api`, for clients accepting it
+//!
+//! The streaming routes ([`STREAMING_ROUTES`]) are never compressed: an encoder
+//! holds events back until its buffer fills, so SSE would arrive in bursts.
+//! Other responses are compressed only when their body is JSON of a known size
+//! of at least `COMPRESS_MIN_BYTES`. Static files are precompressed by the
+//! build and served as is
+
+use axum::{
+    body::{Body, HttpBody},
+    extract::{MatchedPath, Request},
+    http::{
+        HeaderValue, StatusCode,
+        header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, VARY},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{config::COMPRESS_MIN_BYTES, utils::gzip::gzip};
+
+/// Routes sending events or bytes as they come
+const STREAMING_ROUTES: &[&str] = &[
+    "/api/chat/sse",
+    "/api/user/notifications",
+    "/api/ws",
+    "/api/federation/completions",
+    "/api/v1/chat/completions",
+    "/api/message/{id}/audio",
+];
+
+pub async fn middleware(req: Request, next: Next) -> Response {
+    let accepted = req
+        .headers()
+        .get(ACCEPT_ENCODING)
+        .and_then(|x| x.to_str().ok())
+        .is_some_and(accepts_gzip);
+    let streaming = req
+        .extensions()
+        .get::<MatchedPath>()
+        .is_some_and(|x| STREAMING_ROUTES.contains(&x.as_str()));
+
+    let res = next.run(req).await;
+
+    match accepted && !streaming {
+        true => compress(res).await,
+        false => res,
+    }
+}
+
+async fn compress(res: Response) -> Response {
+    let json = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .is_some_and(|x| x.as_bytes().starts_with(b"application/json"));
+    let encoded = res.headers().contains_key(CONTENT_ENCODING);
+    let size = res.body().size_hint().exact();
+    if !json || encoded || size.is_none_or(|x| x < COMPRESS_MIN_BYTES) {
+        return res;
+    }
+
+    let (mut parts, body) = res.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    parts.headers.remove(CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+    parts
+        .headers
+        .append(VARY, HeaderValue::from_static("accept-encoding"));
+    Response::from_parts(parts, Body::from(gzip(&bytes)))
+}
+
+/// `gzip` or `*` in `Accept-Encoding` without `q=0`
+fn accepts_gzip(header: &str) -> bool {
+    header.split(',').any(|coding| {
+        let mut params = coding.split(';').map(str::trim);
+        let name = params.next().unwrap_or_default();
+        let refused = params.any(|x| {
+            x.strip_prefix("q=")
+                .and_then(|q| q.parse::<f32>().ok())
+                .is_some_and(|q| q == 0.0)
+        });
+        (name.eq_ignore_ascii_case("gzip") || name == "*") && !refused
+    })
+}
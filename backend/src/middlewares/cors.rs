@@ -0,0 +1,90 @@
+//! CORS for web clients and browser extensions on other origins, see
+//! `CORS_ORIGINS` env
+//!
+//! Without it the API only answer the origin it is served from, builds with
+//! the `dev` feature allow any origin instead, see [`any`]
+
+use std::time::Duration;
+
+use dotenv::var;
+use http::{HeaderName, HeaderValue, Method, header, request::Parts};
+#[cfg(feature = "dev")]
+use tower_http::cors::{AllowHeaders, AllowMethods};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::{config::CORS_MAX_AGE, middlewares::request_id};
+
+/// `CORS_ORIGINS` is a comma separated list of origins, where `*` stand for
+/// one or more labels of a host, e.g. `https://*.example.com`, or `*` alone
+/// for any origin
+pub fn from_env() -> Option<CorsLayer> {
+    let origins: Vec<String> = var("CORS_ORIGINS")
+        .ok()?
+        .split(',')
+        .map(|x| x.trim().trim_end_matches('/').to_owned())
+        .filter(|x| !x.is_empty())
+        .collect();
+    if origins.is_empty() {
+        return None;
+    }
+    let credentials = var("CORS_CREDENTIALS").is_ok_and(|x| x == "1" || x == "true");
+    let max_age = var("CORS_MAX_AGE")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(CORS_MAX_AGE);
+
+    // mirrored rather than `Any`, which browsers refuse with credentials
+    let allow_origin = AllowOrigin::predicate(move |origin: &HeaderValue, _: &Parts| {
+        let Ok(origin) = origin.to_str() else {
+            return false;
+        };
+        origins.iter().any(|x| x == "*" || matches(x, origin))
+    });
+    Some(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([Method::GET, Method::POST, Method::DELETE])
+            .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
+            .expose_headers([
+                header::CONTENT_DISPOSITION,
+                HeaderName::from_static(request_id::HEADER),
+            ])
+            .allow_credentials(credentials)
+            .max_age(Duration::from_secs(max_age)),
+    )
+}
+
+/// Any origin, for the dev server of the frontend on another port
+#[cfg(feature = "dev")]
+pub fn any() -> CorsLayer {
+    CorsLayer::new()
+        .allow_methods(AllowMethods::any())
+        .allow_origin(AllowOrigin::any())
+        .allow_headers(AllowHeaders::list([
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+        ]))
+}
+
+/// `*` match a run of host characters, never a `/` or `:`, so it cannot
+/// reach past the host or into the port
+fn matches(pattern: &str, origin: &str) -> bool {
+    let Some((prefix, rest)) = pattern.split_once('*') else {
+        return pattern.eq_ignore_ascii_case(origin);
+    };
+    let Some(tail) = strip_prefix(origin, prefix) else {
+        return false;
+    };
+    let host = |x: char| x.is_ascii_alphanumeric() || x == '-' || x == '.';
+    // at least one character, then the rest of the pattern at any boundary
+    tail.char_indices()
+        .take_while(|(_, x)| host(*x))
+        .map(|(i, x)| i + x.len_utf8())
+        .any(|end| matches(rest, &tail[end..]))
+}
+
+fn strip_prefix<'a>(x: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = x.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &x[prefix.len()..])
+}
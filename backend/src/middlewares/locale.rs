@@ -0,0 +1,109 @@
+//! The locale of every `/api` request, for `errors` and the prompts
+//!
+//! Taken from `Accept-Language`, the frontend sends the one the user chose.
+//! Clients sending none get the locale of the user's preference once `auth`
+//! knows who they are, and [`Locale::En`] before that
+
+use std::cell::Cell;
+
+use axum::{extract::Request, http::header, middleware::Next, response::Response};
+
+/// Locales the server speaks, the ones of `prompts::LOCALES`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    ZhTw,
+}
+
+impl Locale {
+    /// A language tag such as `zh-TW` or `en-US`, None for other languages
+    pub fn parse(tag: &str) -> Option<Self> {
+        let tag = tag.trim().to_ascii_lowercase();
+        match tag.split(['-', '_']).next()? {
+            "en" => Some(Self::En),
+            // the frontend has no simplified Chinese, traditional is closer than English
+            "zh" => Some(Self::ZhTw),
+            _ => None,
+        }
+    }
+
+    /// The locale of a preference, e.g. `UserPreference::locale`
+    pub fn of(preference: Option<&str>) -> Option<Self> {
+        preference.and_then(Self::parse)
+    }
+
+    /// The first supported language of an `Accept-Language` header, by weight
+    pub fn negotiate(accept: &str) -> Option<Self> {
+        let mut tags: Vec<(&str, f32)> = accept
+            .split(',')
+            .map(|x| {
+                let mut parts = x.split(';');
+                let tag = parts.next().unwrap_or_default();
+                let weight = parts
+                    .find_map(|x| x.trim().strip_prefix("q="))
+                    .and_then(|x| x.parse().ok())
+                    .unwrap_or(1.0);
+                (tag, weight)
+            })
+            .filter(|(_, weight)| *weight > 0.0)
+            .collect();
+        // stable, so equal weights keep the order of the header
+        tags.sort_by(|a, b| b.1.total_cmp(&a.1));
+        tags.into_iter().find_map(|(tag, _)| Self::parse(tag))
+    }
+
+    /// As in `prompts::LOCALES` and `UserPreference::locale`
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::ZhTw => "zh-tw",
+        }
+    }
+
+    /// Name of the language, for the model to answer in
+    pub fn language(self) -> &'static str {
+        match self {
+            Self::En => "English",
+            Self::ZhTw => "Traditional Chinese (繁體中文)",
+        }
+    }
+}
+
+tokio::task_local! {
+    static CURRENT: Cell<Option<Locale>>;
+}
+
+/// The locale of the request the running task serve, None in background jobs
+/// and for requests without a language
+pub fn current() -> Option<Locale> {
+    CURRENT.try_with(Cell::get).ok().flatten()
+}
+
+/// Locale of a user: the one of their preference, else the one of the request
+pub fn of_user(preference: Option<&str>) -> Locale {
+    Locale::of(preference).or_else(current).unwrap_or_default()
+}
+
+/// Use the locale of the user's preference if the request has none
+pub fn fill(preference: Option<&str>) {
+    let _ = CURRENT.try_with(|x| {
+        if x.get().is_none() {
+            x.set(Locale::of(preference));
+        }
+    });
+}
+
+/// Whether the request has a locale already, so `auth` can skip the lookup
+pub fn is_set() -> bool {
+    current().is_some()
+}
+
+pub async fn middleware(req: Request, next: Next) -> Response {
+    let locale = req
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|x| x.to_str().ok())
+        .and_then(Locale::negotiate);
+    CURRENT.scope(Cell::new(locale), next.run(req)).await
+}
@@ -0,0 +1,308 @@
+//! Counters exported in the Prometheus text format by `/metrics`
+//!
+//! Requests to `/api` are counted by matched route, method and status, with
+//! the time until the response head; streams are timed to their first byte
+//! only. Calls to the upstream are timed by [`Openrouter`](crate::openrouter),
+//! a stream until its first event, and tool calls are counted as the tool box
+//! hand the tools out. Pool stats of the database are read at scrape time.
+//! Everything is reset by a restart
+//!
+//! The registry is global since the upstream client and the tool store are
+//! built before [`AppState`]
+
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    sync::{
+        LazyLock, Mutex,
+        atomic::{AtomicI64, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+use sea_orm::{ConnectionTrait, DbBackend};
+use tokio::time::Instant;
+
+use crate::{
+    AppState,
+    config::{METRICS_HTTP_BUCKETS, METRICS_UPSTREAM_BUCKETS},
+};
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+/// Route of requests matching none, so probing does not grow the label set
+const UNMATCHED: &str = "unmatched";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Call {
+    Complete,
+    Stream,
+    Embed,
+    /// Completion of a federation peer sent on as is
+    Forward,
+}
+
+impl Call {
+    fn label(self) -> &'static str {
+        match self {
+            Call::Complete => "complete",
+            Call::Stream => "stream",
+            Call::Embed => "embed",
+            Call::Forward => "forward",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Metrics {
+    http: Mutex<HashMap<(String, &'static str), Route>>,
+    upstream: Mutex<HashMap<Call, Upstream>>,
+    tools: Mutex<HashMap<&'static str, u64>>,
+    sse: AtomicI64,
+}
+
+#[derive(Default)]
+struct Route {
+    statuses: HashMap<u16, u64>,
+    latency: Histogram,
+}
+
+#[derive(Default)]
+struct Upstream {
+    errors: u64,
+    latency: Histogram,
+}
+
+/// Count per bucket, not cumulative until rendered
+#[derive(Default)]
+struct Histogram {
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, bounds: &[f64], elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        self.buckets.resize(bounds.len(), 0);
+        if let Some(idx) = bounds.iter().position(|x| secs <= *x) {
+            self.buckets[idx] += 1;
+        }
+        self.sum += secs;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str, bounds: &[f64]) {
+        let mut cumulative = 0;
+        for (idx, bound) in bounds.iter().enumerate() {
+            cumulative += self.buckets.get(idx).copied().unwrap_or(0);
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{},le=\"{}\"}} {}",
+                name, labels, bound, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{},le=\"+Inf\"}} {}",
+            name, labels, self.count
+        );
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+impl Metrics {
+    fn request(&self, method: &'static str, route: String, status: u16, elapsed: Duration) {
+        let mut http = self.http.lock().unwrap();
+        let entry = http.entry((route, method)).or_default();
+        *entry.statuses.entry(status).or_default() += 1;
+        entry.latency.observe(METRICS_HTTP_BUCKETS, elapsed);
+    }
+
+    /// A call to the upstream, answered or failed after `elapsed`
+    pub fn upstream(&self, call: Call, elapsed: Duration, ok: bool) {
+        let mut upstream = self.upstream.lock().unwrap();
+        let entry = upstream.entry(call).or_default();
+        entry.latency.observe(METRICS_UPSTREAM_BUCKETS, elapsed);
+        if !ok {
+            entry.errors += 1;
+        }
+    }
+
+    /// A call already counted by [`Self::upstream`] failed later on, a stream
+    /// halfway
+    pub fn upstream_error(&self, call: Call) {
+        self.upstream
+            .lock()
+            .unwrap()
+            .entry(call)
+            .or_default()
+            .errors += 1;
+    }
+
+    pub fn tool_call(&self, name: &'static str) {
+        *self.tools.lock().unwrap().entry(name).or_default() += 1;
+    }
+
+    /// Held by every SSE subscriber, websocket ones included
+    pub fn sse_connected(&self) -> SseGuard {
+        self.sse.fetch_add(1, Ordering::Relaxed);
+        SseGuard
+    }
+
+    pub fn render(&self, app: &AppState) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP llumen_http_requests_total Requests to the API\n");
+        out.push_str("# TYPE llumen_http_requests_total counter\n");
+        let http = self.http.lock().unwrap();
+        let mut routes: Vec<_> = http.iter().collect();
+        routes.sort_by(|a, b| a.0.cmp(b.0));
+        for ((route, method), entry) in &routes {
+            let mut statuses: Vec<_> = entry.statuses.iter().collect();
+            statuses.sort();
+            for (status, count) in statuses {
+                let _ = writeln!(
+                    out,
+                    "llumen_http_requests_total{{route=\"{}\",method=\"{}\",status=\"{}\"}} {}",
+                    escape(route),
+                    method,
+                    status,
+                    count
+                );
+            }
+        }
+        out.push_str("# HELP llumen_http_request_duration_seconds Time until the response head\n");
+        out.push_str("# TYPE llumen_http_request_duration_seconds histogram\n");
+        for ((route, method), entry) in &routes {
+            let labels = format!("route=\"{}\",method=\"{}\"", escape(route), method);
+            entry.latency.render(
+                &mut out,
+                "llumen_http_request_duration_seconds",
+                &labels,
+                METRICS_HTTP_BUCKETS,
+            );
+        }
+        drop(http);
+
+        out.push_str("# HELP llumen_sse_connections Connections following a chat\n");
+        out.push_str("# TYPE llumen_sse_connections gauge\n");
+        let _ = writeln!(
+            out,
+            "llumen_sse_connections {}",
+            self.sse.load(Ordering::Relaxed)
+        );
+
+        let upstream = self.upstream.lock().unwrap();
+        let mut calls: Vec<_> = upstream.iter().collect();
+        calls.sort_by_key(|(call, _)| call.label());
+        out.push_str("# HELP llumen_upstream_errors_total Failed calls to the upstream\n");
+        out.push_str("# TYPE llumen_upstream_errors_total counter\n");
+        for (call, entry) in &calls {
+            let _ = writeln!(
+                out,
+                "llumen_upstream_errors_total{{call=\"{}\"}} {}",
+                call.label(),
+                entry.errors
+            );
+        }
+        out.push_str(
+            "# HELP llumen_upstream_duration_seconds Time until the upstream answered, a stream its first event\n",
+        );
+        out.push_str("# TYPE llumen_upstream_duration_seconds histogram\n");
+        for (call, entry) in &calls {
+            entry.latency.render(
+                &mut out,
+                "llumen_upstream_duration_seconds",
+                &format!("call=\"{}\"", call.label()),
+                METRICS_UPSTREAM_BUCKETS,
+            );
+        }
+        drop(upstream);
+
+        out.push_str("# HELP llumen_tool_calls_total Tool calls made by models\n");
+        out.push_str("# TYPE llumen_tool_calls_total counter\n");
+        let tools = self.tools.lock().unwrap();
+        let mut tools: Vec<_> = tools.iter().collect();
+        tools.sort();
+        for (name, count) in tools {
+            let _ = writeln!(
+                out,
+                "llumen_tool_calls_total{{tool=\"{}\"}} {}",
+                escape(name),
+                count
+            );
+        }
+
+        let (size, idle, max) = match app.conn.get_database_backend() {
+            DbBackend::Postgres => {
+                let pool = app.conn.get_postgres_connection_pool();
+                let max = pool.options().get_max_connections();
+                (pool.size(), pool.num_idle(), max)
+            }
+            _ => {
+                let pool = app.conn.get_sqlite_connection_pool();
+                let max = pool.options().get_max_connections();
+                (pool.size(), pool.num_idle(), max)
+            }
+        };
+        out.push_str("# HELP llumen_db_connections Open connections of the database pool\n");
+        out.push_str("# TYPE llumen_db_connections gauge\n");
+        let _ = writeln!(out, "llumen_db_connections {}", size);
+        out.push_str("# HELP llumen_db_idle_connections Idle connections of the database pool\n");
+        out.push_str("# TYPE llumen_db_idle_connections gauge\n");
+        let _ = writeln!(out, "llumen_db_idle_connections {}", idle);
+        out.push_str("# HELP llumen_db_max_connections Size limit of the database pool\n");
+        out.push_str("# TYPE llumen_db_max_connections gauge\n");
+        let _ = writeln!(out, "llumen_db_max_connections {}", max);
+
+        out
+    }
+}
+
+pub struct SseGuard;
+
+impl Drop for SseGuard {
+    fn drop(&mut self) {
+        METRICS.sse.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Label values are quoted, see the Prometheus text format
+fn escape(x: &str) -> String {
+    x.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+pub async fn middleware(req: Request, next: Next) -> Response {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|x| x.as_str().to_owned())
+        .unwrap_or(UNMATCHED.to_owned());
+    let method = match *req.method() {
+        Method::GET => "GET",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::PATCH => "PATCH",
+        Method::DELETE => "DELETE",
+        Method::HEAD => "HEAD",
+        Method::OPTIONS => "OPTIONS",
+        // any token is a method, so is any label
+        _ => "OTHER",
+    };
+    let start = Instant::now();
+
+    let res = next.run(req).await;
+
+    METRICS.request(method, route, res.status().as_u16(), start.elapsed());
+    res
+}
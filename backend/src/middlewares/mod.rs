@@ -1,2 +1,9 @@
 pub mod auth;
+pub mod body_limit;
 pub mod cache_control;
+pub mod compression;
+pub mod cors;
+pub mod locale;
+pub mod metrics;
+pub mod rate_limit;
+pub mod request_id;
@@ -0,0 +1,162 @@
+//! Token bucket of every user, or of every IP for requests without a token
+//!
+//! A bucket hold up to `burst` requests and refill at `per_minute`; a request
+//! finding it empty is answered 429 with `Retry-After`. The policy is set by
+//! admins with `admin/config`, see `config::Settings`. Requests made with an API
+//! key count for their IP, the key is only looked up by the auth middleware.
+//! Buckets do not survive a restart
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex, RwLock},
+};
+
+use axum::{
+    Json,
+    extract::{ConnectInfo, Request, State},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use pasetors::claims::ClaimsValidationRules;
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    config::{RATE_LIMIT_BURST, RATE_LIMIT_MAX_KEYS, RATE_LIMIT_PER_MINUTE},
+    errors::*,
+    utils::{api_key, client},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[typeshare]
+pub struct RateLimitPolicy {
+    /// 0 turns the limit off
+    pub per_minute: u32,
+    pub burst: u32,
+}
+
+impl Default for RateLimitPolicy {
+    fn default() -> Self {
+        Self {
+            per_minute: RATE_LIMIT_PER_MINUTE,
+            burst: RATE_LIMIT_BURST,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Key {
+    User(i32),
+    Ip(IpAddr),
+}
+
+struct Bucket {
+    tokens: f64,
+    at: Instant,
+}
+
+pub struct RateLimiter {
+    policy: RwLock<RateLimitPolicy>,
+    buckets: Mutex<HashMap<Key, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(policy: RateLimitPolicy) -> Self {
+        Self {
+            policy: RwLock::new(policy),
+            buckets: Default::default(),
+        }
+    }
+
+    /// Follow the policy of the settings, buckets start over on a change
+    pub fn spawn_reload(app: Arc<AppState>) {
+        let mut rx = app.settings.subscribe();
+        tokio::spawn(async move {
+            while rx.changed().await.is_ok() {
+                let policy = rx.borrow_and_update().rate_limit;
+                if policy != *app.rate_limit.policy.read().unwrap() {
+                    *app.rate_limit.policy.write().unwrap() = policy;
+                    app.rate_limit.buckets.lock().unwrap().clear();
+                }
+            }
+        });
+    }
+
+    /// Take a token of the bucket, or the seconds until one is back
+    fn take(&self, key: Key) -> Result<(), u64> {
+        let policy = *self.policy.read().unwrap();
+        if policy.per_minute == 0 {
+            return Ok(());
+        }
+        let burst = policy.burst.max(1) as f64;
+        let rate = policy.per_minute as f64 / 60.0;
+        let now = Instant::now();
+        let refill = |x: &Bucket| (x.tokens + (now - x.at).as_secs_f64() * rate).min(burst);
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= RATE_LIMIT_MAX_KEYS {
+            // a full bucket is the same as none
+            buckets.retain(|_, x| refill(x) < burst);
+        }
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: burst,
+            at: now,
+        });
+        bucket.tokens = refill(bucket);
+        bucket.at = now;
+        if bucket.tokens < 1.0 {
+            return Err(((1.0 - bucket.tokens) / rate).ceil() as u64);
+        }
+        bucket.tokens -= 1.0;
+        Ok(())
+    }
+}
+
+/// Apply to every route of `/api`, before the auth middleware
+pub async fn middleware(
+    State(app): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let key = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| token_user(&app, x))
+        .map(Key::User)
+        .unwrap_or_else(|| Key::Ip(client::ip(req.headers(), addr)));
+
+    match app.rate_limit.take(key) {
+        Ok(()) => next.run(req).await,
+        Err(secs) => (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, secs.to_string())],
+            Json(Error {
+                error: ErrorKind::RateLimited,
+                reason: format!("Too many requests, retry in {}s", secs),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// User of a token, without checking its session; a forged token cannot be
+/// decrypted
+fn token_user(app: &AppState, token: &str) -> Option<i32> {
+    // OpenAI clients send a bearer token, as in `middlewares::auth`
+    let token = token.strip_prefix("Bearer ").unwrap_or(token);
+    if token.starts_with(api_key::PREFIX) {
+        return None;
+    }
+    let token = app
+        .keyring
+        .decrypt(token, &ClaimsValidationRules::new())
+        .ok()?;
+    let user_id = token.payload_claims()?.get_claim("uid")?.as_i64()?;
+    Some(user_id as i32)
+}
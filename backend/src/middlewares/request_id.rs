@@ -0,0 +1,85 @@
+//! An id for every `/api` request, in the `x-request-id` header of the response
+//!
+//! Logs of the request are in a span with the id, the root of its trace in
+//! `telemetry`, and the id and IP of the client are kept in a task local for
+//! `audit`. Tasks spawned with [`spawn`] keep both, e.g. the completion of a
+//! message outliving its request
+
+use std::{
+    future::Future,
+    net::{IpAddr, SocketAddr},
+};
+
+use axum::{
+    extract::{ConnectInfo, MatchedPath, Request},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use tokio::task::JoinHandle;
+use tracing::{Instrument, Span, field};
+
+use crate::utils::client;
+
+pub const HEADER: &str = "x-request-id";
+
+#[derive(Debug, Clone)]
+pub struct RequestCtx {
+    pub id: String,
+    pub ip: IpAddr,
+}
+
+tokio::task_local! {
+    static CURRENT: RequestCtx;
+}
+
+/// The request the running task serve, None in background jobs
+pub fn current() -> Option<RequestCtx> {
+    CURRENT.try_with(Clone::clone).ok()
+}
+
+/// [`tokio::spawn`] keeping the request id of the caller, in `span`
+///
+/// The span should be created in the caller, so it is a child of the span of
+/// the request, which then ends with the response rather than the task
+pub fn spawn<F>(span: Span, fut: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let fut = fut.instrument(span);
+    match current() {
+        Some(ctx) => tokio::spawn(CURRENT.scope(ctx, fut)),
+        None => tokio::spawn(fut),
+    }
+}
+
+pub async fn middleware(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let ctx = RequestCtx {
+        id: format!("{:016x}", fastrand::u64(..)),
+        ip: client::ip(req.headers(), addr),
+    };
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(req.uri().path(), |x| x.as_str());
+    let span = tracing::info_span!(
+        "request",
+        id = %ctx.id,
+        otel.name = format!("{} {}", req.method(), route),
+        status = field::Empty,
+    );
+    let header = HeaderValue::from_str(&ctx.id).unwrap();
+
+    let mut res = CURRENT
+        .scope(ctx, next.run(req).instrument(span.clone()))
+        .await;
+
+    span.record("status", res.status().as_u16());
+    res.headers_mut().insert(HEADER, header);
+    res
+}
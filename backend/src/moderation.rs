@@ -0,0 +1,280 @@
+//! Screening of messages and replies, set by admins with `admin/config`
+//!
+//! Rules are case-insensitive regexes, a plain word matching anywhere in the
+//! text; with `MODERATION_PROVIDER` the text is also sent to a moderation
+//! endpoint. A match is written to `audit_log` for admins and, under the
+//! `block` action, also refused: a blocked message is never stored, a blocked
+//! reply stops and its text is replaced. Replies are checked against the
+//! rules every `MODERATION_OUTPUT_TOKENS` tokens while they stream, and by
+//! the provider once their text ends. A provider that fails lets the text
+//! through, a moderation outage should not take chats down with it
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use anyhow::{Result, bail};
+use dotenv::var;
+use entity::AuditKind;
+use regex::{RegexSet, RegexSetBuilder};
+use sea_orm::DbConn;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, audit, config::MODERATION_TIMEOUT};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[typeshare]
+#[serde(default)]
+pub struct ModerationPolicy {
+    pub action: ModerationAction,
+    /// Case-insensitive regexes, a plain word matches anywhere
+    pub rules: Vec<String>,
+    /// Also ask `MODERATION_PROVIDER`, ignored without it
+    pub provider: bool,
+    /// Screen replies too, not only the messages of users
+    pub output: bool,
+}
+
+/// What a match does
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationAction {
+    /// Nothing is screened
+    #[default]
+    Off,
+    /// Written to the audit log, the text goes through
+    Flag,
+    /// Written to the audit log and refused
+    Block,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    /// What matched, for the audit log
+    Flag(String),
+    Block(String),
+}
+
+/// Where the text screened comes from
+#[derive(Debug, Clone, Copy)]
+pub enum Stage {
+    Message,
+    Reply,
+}
+
+pub struct Moderation {
+    rules: RwLock<Compiled>,
+    provider: Option<Provider>,
+}
+
+struct Compiled {
+    policy: ModerationPolicy,
+    set: RegexSet,
+}
+
+/// `/moderations` of an OpenAI-compatible provider
+struct Provider {
+    endpoint: String,
+    api_key: Option<String>,
+    model: String,
+    client: reqwest::Client,
+}
+
+#[derive(Serialize)]
+struct ModerationReq<'a> {
+    model: &'a str,
+    input: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ModerationResp {
+    results: Vec<ModerationRespResult>,
+}
+
+#[derive(Deserialize)]
+struct ModerationRespResult {
+    flagged: bool,
+    #[serde(default)]
+    categories: BTreeMap<String, bool>,
+}
+
+/// Rules of a policy as one set, fail on an invalid regex
+pub fn compile(rules: &[String]) -> Result<RegexSet, regex::Error> {
+    RegexSetBuilder::new(rules).case_insensitive(true).build()
+}
+
+impl Moderation {
+    /// A policy whose rules do not compile screens nothing, `admin/config`
+    /// refuses them
+    pub fn new(policy: ModerationPolicy) -> Self {
+        Self {
+            rules: RwLock::new(Compiled::new(policy)),
+            provider: Provider::from_env(),
+        }
+    }
+
+    /// Follow the policy of the settings
+    pub fn spawn_reload(app: Arc<AppState>) {
+        let mut rx = app.settings.subscribe();
+        tokio::spawn(async move {
+            while rx.changed().await.is_ok() {
+                let policy = rx.borrow_and_update().moderation.clone();
+                if policy != app.moderation.rules.read().unwrap().policy {
+                    *app.moderation.rules.write().unwrap() = Compiled::new(policy);
+                }
+            }
+        });
+    }
+
+    pub fn has_provider(&self) -> bool {
+        self.provider.is_some()
+    }
+
+    pub fn screens_output(&self) -> bool {
+        let rules = self.rules.read().unwrap();
+        rules.policy.action != ModerationAction::Off && rules.policy.output
+    }
+
+    /// The rules only, cheap enough to run while a reply streams
+    pub fn check_rules(&self, text: &str) -> Verdict {
+        let rules = self.rules.read().unwrap();
+        let matched: Vec<&str> = rules
+            .set
+            .matches(text)
+            .iter()
+            .map(|x| rules.set.patterns()[x].as_str())
+            .collect();
+        if matched.is_empty() {
+            return Verdict::Pass;
+        }
+        verdict(
+            rules.policy.action,
+            format!("matched rule `{}`", matched.join("`, `")),
+        )
+    }
+
+    /// The rules, then the provider if the policy asks for it
+    pub async fn check(&self, text: &str) -> Verdict {
+        let by_rules = self.check_rules(text);
+        if by_rules != Verdict::Pass {
+            return by_rules;
+        }
+        let (action, ask) = {
+            let rules = self.rules.read().unwrap();
+            (rules.policy.action, rules.policy.provider)
+        };
+        let Some(provider) = self.provider.as_ref().filter(|_| ask) else {
+            return Verdict::Pass;
+        };
+        if action == ModerationAction::Off || text.trim().is_empty() {
+            return Verdict::Pass;
+        }
+        match provider.flagged(text).await {
+            Ok(None) => Verdict::Pass,
+            Ok(Some(categories)) => verdict(action, format!("flagged as {}", categories)),
+            Err(err) => {
+                tracing::warn!("cannot moderate, text let through: {}", err);
+                Verdict::Pass
+            }
+        }
+    }
+}
+
+impl Compiled {
+    fn new(policy: ModerationPolicy) -> Self {
+        let set = compile(&policy.rules).unwrap_or_else(|err| {
+            tracing::warn!("invalid moderation rules, ignored: {}", err);
+            RegexSet::empty()
+        });
+        Self { policy, set }
+    }
+}
+
+fn verdict(action: ModerationAction, reason: String) -> Verdict {
+    match action {
+        ModerationAction::Off => Verdict::Pass,
+        ModerationAction::Flag => Verdict::Flag(reason),
+        ModerationAction::Block => Verdict::Block(reason),
+    }
+}
+
+impl Provider {
+    /// None unless `MODERATION_PROVIDER` is `openai`
+    fn from_env() -> Option<Self> {
+        match var("MODERATION_PROVIDER").ok()?.as_str() {
+            "openai" => Some(Self {
+                endpoint: format!(
+                    "{}/moderations",
+                    var("MODERATION_API_BASE")
+                        .unwrap_or("https://api.openai.com/v1".to_owned())
+                        .trim_end_matches('/')
+                ),
+                api_key: var("MODERATION_API_KEY").ok(),
+                model: var("MODERATION_MODEL").unwrap_or("omni-moderation-latest".to_owned()),
+                client: reqwest::Client::builder()
+                    .timeout(Duration::from_secs(MODERATION_TIMEOUT))
+                    .build()
+                    .expect("Cannot build http client"),
+            }),
+            x => {
+                tracing::warn!(
+                    "unknown MODERATION_PROVIDER {}, expect openai, provider disabled",
+                    x
+                );
+                None
+            }
+        }
+    }
+
+    /// Categories the text is flagged in, joined, None if it is not
+    async fn flagged(&self, text: &str) -> Result<Option<String>> {
+        let req = self.client.post(&self.endpoint).json(&ModerationReq {
+            model: &self.model,
+            input: text,
+        });
+        let req = match &self.api_key {
+            Some(api_key) => req.bearer_auth(api_key),
+            None => req,
+        };
+        let res = req.send().await?;
+        if !res.status().is_success() {
+            bail!(
+                "moderation failed with {}: {}",
+                res.status(),
+                res.text().await.unwrap_or_default()
+            );
+        }
+        let res: ModerationResp = res.json().await?;
+        let categories: Vec<String> = res
+            .results
+            .into_iter()
+            .filter(|x| x.flagged)
+            .flat_map(|x| x.categories.into_iter().filter(|(_, v)| *v).map(|(k, _)| k))
+            .collect();
+        Ok(match categories.is_empty() {
+            // flagged without a category, or not flagged
+            true => None,
+            false => Some(categories.join(", ")),
+        })
+    }
+}
+
+/// Write a flag or block to the audit log, a pass is not recorded
+pub async fn record(conn: &DbConn, user_id: i32, chat_id: i32, stage: Stage, verdict: &Verdict) {
+    let (outcome, reason) = match verdict {
+        Verdict::Pass => return,
+        Verdict::Flag(reason) => ("flagged", reason),
+        Verdict::Block(reason) => ("blocked", reason),
+    };
+    let stage = match stage {
+        Stage::Message => "message",
+        Stage::Reply => "reply",
+    };
+    let detail = format!("{} in chat {} {}: {}", stage, chat_id, outcome, reason);
+    audit::record(conn, AuditKind::Moderation, Some(user_id), detail).await;
+}
@@ -0,0 +1,108 @@
+//! Messages background features post to a user, a chat of its own where only
+//! the assistant speaks, listed at `/api/notifications`
+//!
+//! Unlike the other notifications they are kept, so a user away when one is
+//! posted reads it later; each is also sent on `user/notifications` as
+//! [`Notification::Inbox`]. A user keeps [`INBOX_MAX_PER_USER`] of them, the
+//! oldest go first
+
+use anyhow::Result;
+use entity::{InboxSource, inbox_message, prelude::*};
+use sea_orm::{ActiveValue::Set, QueryOrder, QuerySelect, prelude::*};
+
+use crate::{
+    AppState,
+    config::INBOX_MAX_PER_USER,
+    notify::{Notification, NotificationInbox},
+};
+
+/// Keep `content` for the user and send it to their open streams
+pub async fn post(
+    app: &AppState,
+    user_id: i32,
+    source: InboxSource,
+    content: String,
+    chat_id: Option<i32>,
+) -> Result<()> {
+    let created_at = time::UtcDateTime::now().unix_timestamp();
+    let id = InboxMessage::insert(inbox_message::ActiveModel {
+        user_id: Set(user_id),
+        source: Set(source),
+        content: Set(content.clone()),
+        chat_id: Set(chat_id),
+        created_at: Set(created_at),
+        ..Default::default()
+    })
+    .exec(&app.conn)
+    .await?
+    .last_insert_id;
+
+    let stale: Vec<i32> = InboxMessage::find()
+        .select_only()
+        .column(inbox_message::Column::Id)
+        .filter(inbox_message::Column::UserId.eq(user_id))
+        .order_by_desc(inbox_message::Column::Id)
+        .offset(INBOX_MAX_PER_USER)
+        .into_tuple()
+        .all(&app.conn)
+        .await?;
+    if !stale.is_empty() {
+        InboxMessage::delete_many()
+            .filter(inbox_message::Column::Id.is_in(stale))
+            .exec(&app.conn)
+            .await?;
+    }
+
+    app.notifier.send(
+        user_id,
+        Notification::Inbox(NotificationInbox {
+            id,
+            source,
+            content,
+            chat_id,
+            created_at,
+        }),
+    );
+    Ok(())
+}
+
+/// Latest first, before `before` if given
+pub async fn list(
+    conn: &DbConn,
+    user_id: i32,
+    before: Option<i32>,
+    limit: u64,
+) -> Result<Vec<inbox_message::Model>> {
+    let mut q = InboxMessage::find().filter(inbox_message::Column::UserId.eq(user_id));
+    if let Some(before) = before {
+        q = q.filter(inbox_message::Column::Id.lt(before));
+    }
+    Ok(q.order_by_desc(inbox_message::Column::Id)
+        .limit(limit)
+        .all(conn)
+        .await?)
+}
+
+pub async fn unread(conn: &DbConn, user_id: i32) -> Result<u64> {
+    Ok(InboxMessage::find()
+        .filter(inbox_message::Column::UserId.eq(user_id))
+        .filter(inbox_message::Column::ReadAt.is_null())
+        .count(conn)
+        .await?)
+}
+
+/// Mark messages of the user as read, every one if `ids` is None
+pub async fn read(conn: &DbConn, user_id: i32, ids: Option<Vec<i32>>) -> Result<()> {
+    let mut q = InboxMessage::update_many()
+        .col_expr(
+            inbox_message::Column::ReadAt,
+            time::UtcDateTime::now().unix_timestamp().into(),
+        )
+        .filter(inbox_message::Column::UserId.eq(user_id))
+        .filter(inbox_message::Column::ReadAt.is_null());
+    if let Some(ids) = ids {
+        q = q.filter(inbox_message::Column::Id.is_in(ids));
+    }
+    q.exec(conn).await?;
+    Ok(())
+}
@@ -0,0 +1,167 @@
+//! Notification mails sent by the instance itself, see `SMTP_URL` env
+//!
+//! Unrelated to the mail tools, which act with the credentials of a user.
+//! A [`Mail`] is rendered to plain text and to the HTML of `mail.html` when
+//! queued, the job of `jobs` then sends both as alternatives and retries on
+//! failure
+
+use anyhow::Result;
+use dotenv::var;
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    message::{Mailbox, MultiPart},
+};
+use minijinja::{Environment, context};
+use serde::Serialize;
+
+use crate::jobs::Task;
+
+const TEMPLATE: &str = "mail.html";
+
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    /// Public url of this instance, links in mails point under it
+    public_url: String,
+    /// Escapes the text of mails, by the extension of [`TEMPLATE`]
+    env: Environment<'static>,
+}
+
+/// Content of a mail, in the order shown
+#[derive(Debug, Serialize)]
+pub struct Mail {
+    pub subject: String,
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "t", content = "c", rename_all = "snake_case")]
+pub enum Block {
+    Text(String),
+    List(Vec<String>),
+    /// A button in HTML, the bare url in plain text
+    Link {
+        label: String,
+        url: String,
+    },
+}
+
+impl Mail {
+    pub fn new(subject: impl Into<String>) -> Self {
+        Self {
+            subject: subject.into(),
+            blocks: Vec::new(),
+        }
+    }
+
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.blocks.push(Block::Text(text.into()));
+        self
+    }
+
+    pub fn list(mut self, items: Vec<String>) -> Self {
+        self.blocks.push(Block::List(items));
+        self
+    }
+
+    pub fn link(mut self, label: impl Into<String>, url: String) -> Self {
+        self.blocks.push(Block::Link {
+            label: label.into(),
+            url,
+        });
+        self
+    }
+
+    fn plain(&self) -> String {
+        self.blocks
+            .iter()
+            .map(|block| match block {
+                Block::Text(x) => x.clone(),
+                Block::List(items) => items
+                    .iter()
+                    .map(|x| format!("- {}", x))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                Block::Link { url, .. } => url.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+impl Mailer {
+    /// None unless `SMTP_URL`, `MAIL_FROM` and `PUBLIC_URL` are all set
+    pub fn from_env() -> Option<Self> {
+        let url = var("SMTP_URL").ok()?;
+        let from = var("MAIL_FROM").ok()?;
+        let public_url = var("PUBLIC_URL").ok()?;
+
+        let transport = match AsyncSmtpTransport::<Tokio1Executor>::from_url(&url) {
+            Ok(x) => x.build(),
+            Err(err) => {
+                tracing::warn!("invalid SMTP_URL, mails disabled: {}", err);
+                return None;
+            }
+        };
+        let from = match from.parse() {
+            Ok(x) => x,
+            Err(err) => {
+                tracing::warn!("invalid MAIL_FROM, mails disabled: {}", err);
+                return None;
+            }
+        };
+
+        let mut env = Environment::new();
+        env.add_template(TEMPLATE, include_str!("mail.html"))
+            .expect("Invalid mail template");
+
+        Some(Self {
+            transport,
+            from,
+            public_url: public_url.trim_end_matches('/').to_owned(),
+            env,
+        })
+    }
+
+    /// `path` should start with a slash
+    pub fn link(&self, path: &str) -> String {
+        format!("{}{}", self.public_url, path)
+    }
+
+    /// The job sending `mail` to `to`, in plain text only if the template
+    /// cannot render it
+    pub fn task(&self, to: String, mail: &Mail) -> Task {
+        let html = self
+            .env
+            .get_template(TEMPLATE)
+            .and_then(|x| x.render(context! { subject => mail.subject, blocks => mail.blocks }))
+            .inspect_err(|err| tracing::warn!("cannot render mail \"{}\": {}", mail.subject, err))
+            .ok();
+        Task::Mail {
+            to,
+            subject: mail.subject.clone(),
+            body: mail.plain(),
+            html,
+        }
+    }
+
+    /// `html` is sent as an alternative of `body`
+    pub async fn send(
+        &self,
+        to: &str,
+        subject: &str,
+        body: String,
+        html: Option<String>,
+    ) -> Result<()> {
+        let builder = Message::builder()
+            .from(self.from.clone())
+            .to(to.parse()?)
+            .subject(subject);
+        let message = match html {
+            Some(html) => builder.multipart(MultiPart::alternative_plain_html(body, html))?,
+            None => builder.body(body)?,
+        };
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
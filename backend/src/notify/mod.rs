@@ -0,0 +1,158 @@
+//! Notifications of a user outside of any chat, followed at
+//! `/api/user/notifications`
+//!
+//! Nothing is kept, a user without an open stream miss them; `push` reaches
+//! their browsers instead, so the notifier also counts the open streams of
+//! each user, chats and notifications alike. What a user should still read
+//! later is also posted to their `inbox`. Mails the instance sends itself go
+//! through `mailer`
+
+pub mod inbox;
+pub mod mailer;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use entity::InboxSource;
+use schemars::JsonSchema;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use typeshare::typeshare;
+
+use crate::config::NOTIFY_CAPACITY;
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[typeshare]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum Notification {
+    /// A scheduled task sent its prompt, the reply streams in the chat
+    ScheduleRun(NotificationScheduleRun),
+    /// Spending went past a monthly cap or most of it, see `spend::cap`
+    SpendCap(NotificationSpendCap),
+    /// The takeout asked for at `user/takeout` is ready to download
+    Takeout(NotificationTakeout),
+    /// A draft was saved or cleared, on this device or another one
+    DraftUpdated(NotificationDraftUpdated),
+    /// A message was posted to the inbox, see `inbox`
+    Inbox(NotificationInbox),
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[typeshare]
+pub struct NotificationScheduleRun {
+    pub schedule_id: i32,
+    pub name: String,
+    /// None if the run could not start
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<i32>,
+    /// Why the run could not start
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[typeshare]
+pub struct NotificationSpendCap {
+    /// Display name of the model capped, None for the cap of the user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// USD this month
+    pub spent: f64,
+    pub cap: f64,
+    /// false while it is only the warning
+    pub reached: bool,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[typeshare]
+pub struct NotificationTakeout {
+    /// Downloaded from `file/{id}`, deleted after `FILE_ORPHAN_SECS`
+    pub file_id: i32,
+    pub name: String,
+    /// Bytes
+    pub size: i64,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[typeshare]
+pub struct NotificationDraftUpdated {
+    pub chat_id: i32,
+    /// Empty once the draft is sent or cleared
+    pub content: String,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[typeshare]
+pub struct NotificationInbox {
+    pub id: i32,
+    pub source: InboxSource,
+    /// Markdown
+    pub content: String,
+    /// Chat the message is about
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<i32>,
+    pub created_at: i64,
+}
+
+#[derive(Default)]
+pub struct Notifier {
+    users: Mutex<HashMap<i32, broadcast::Sender<Notification>>>,
+    /// Open streams of each user
+    online: Arc<Mutex<HashMap<i32, usize>>>,
+}
+
+/// An open stream of a user, counted until dropped
+pub struct Online {
+    online: Arc<Mutex<HashMap<i32, usize>>>,
+    user_id: i32,
+}
+
+impl Drop for Online {
+    fn drop(&mut self) {
+        let mut online = self.online.lock().unwrap();
+        if let Some(count) = online.get_mut(&self.user_id) {
+            *count -= 1;
+            if *count == 0 {
+                online.remove(&self.user_id);
+            }
+        }
+    }
+}
+
+impl Notifier {
+    pub fn subscribe(&self, user_id: i32) -> broadcast::Receiver<Notification> {
+        self.users
+            .lock()
+            .unwrap()
+            .entry(user_id)
+            .or_insert_with(|| broadcast::channel(NOTIFY_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// Count a stream of the user open for as long as the guard lives
+    pub fn connect(&self, user_id: i32) -> Online {
+        *self.online.lock().unwrap().entry(user_id).or_default() += 1;
+        Online {
+            online: self.online.clone(),
+            user_id,
+        }
+    }
+
+    /// Whether the user has a stream open on this instance
+    pub fn is_online(&self, user_id: i32) -> bool {
+        self.online.lock().unwrap().contains_key(&user_id)
+    }
+
+    pub fn send(&self, user_id: i32, notification: Notification) {
+        let mut users = self.users.lock().unwrap();
+        if let Some(tx) = users.get(&user_id)
+            && tx.send(notification).is_err()
+        {
+            // every stream of the user is closed
+            users.remove(&user_id);
+        }
+    }
+}
@@ -0,0 +1,339 @@
+//! Social login with OAuth2 / OIDC, see `OAUTH_*` env
+//!
+//! Google and generic OIDC providers identify users by the `sub` of their
+//! userinfo, GitHub by its numeric id. The code flow use PKCE, pending flows
+//! are kept in memory so a restart cancel them. A flow only finishes in the
+//! browser that started it, which holds a hash of its state in a cookie
+
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use anyhow::{Context, Result, bail};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use dotenv::var;
+use http::{HeaderMap, header};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::time::Instant;
+use url::Url;
+
+use crate::config::OAUTH_STATE_SECS;
+
+const STATE_TTL: Duration = Duration::from_secs(OAUTH_STATE_SECS);
+/// Holds the hash of the state of the flow started by the browser
+const STATE_COOKIE: &str = "oauth_state";
+
+pub struct OAuth {
+    providers: HashMap<&'static str, Provider>,
+    /// Public url of this instance, callbacks and the login page are under it
+    redirect_base: String,
+    http_client: reqwest::Client,
+    /// state parameter to the flow it belongs to
+    pending: Mutex<HashMap<String, Pending>>,
+}
+
+struct Provider {
+    client_id: String,
+    client_secret: String,
+    authorize_url: String,
+    token_url: String,
+    userinfo_url: String,
+    scope: &'static str,
+}
+
+struct Pending {
+    provider: &'static str,
+    /// Link the identity to this user instead of logging in
+    link: Option<i32>,
+    verifier: String,
+    at: Instant,
+}
+
+/// A user as the provider know it
+pub struct Identity {
+    pub subject: String,
+    /// Suggested username for a new account
+    pub name: String,
+    /// Set if the flow was started by a logged-in user
+    pub link: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct Discovery {
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: String,
+}
+
+/// GitHub answer errors with 200
+#[derive(Deserialize)]
+struct TokenResp {
+    access_token: Option<String>,
+    error: Option<String>,
+}
+
+impl OAuth {
+    /// Providers without a client id are disabled, the OIDC one is discovered from its issuer
+    pub async fn from_env() -> Self {
+        let http_client = reqwest::Client::builder()
+            .user_agent("llumen")
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("Cannot build http client");
+
+        let mut providers = HashMap::new();
+        if let Some((client_id, client_secret)) = credential("GOOGLE") {
+            providers.insert(
+                "google",
+                Provider {
+                    client_id,
+                    client_secret,
+                    authorize_url: "https://accounts.google.com/o/oauth2/v2/auth".to_owned(),
+                    token_url: "https://oauth2.googleapis.com/token".to_owned(),
+                    userinfo_url: "https://openidconnect.googleapis.com/v1/userinfo".to_owned(),
+                    scope: "openid email profile",
+                },
+            );
+        }
+        if let Some((client_id, client_secret)) = credential("GITHUB") {
+            providers.insert(
+                "github",
+                Provider {
+                    client_id,
+                    client_secret,
+                    authorize_url: "https://github.com/login/oauth/authorize".to_owned(),
+                    token_url: "https://github.com/login/oauth/access_token".to_owned(),
+                    userinfo_url: "https://api.github.com/user".to_owned(),
+                    scope: "read:user",
+                },
+            );
+        }
+        if let (Some((client_id, client_secret)), Ok(issuer)) =
+            (credential("OIDC"), var("OAUTH_OIDC_ISSUER"))
+        {
+            match discover(&http_client, &issuer).await {
+                Ok(discovery) => {
+                    providers.insert(
+                        "oidc",
+                        Provider {
+                            client_id,
+                            client_secret,
+                            authorize_url: discovery.authorization_endpoint,
+                            token_url: discovery.token_endpoint,
+                            userinfo_url: discovery.userinfo_endpoint,
+                            scope: "openid email profile",
+                        },
+                    );
+                }
+                Err(err) => tracing::warn!("cannot discover OIDC issuer {}: {}", issuer, err),
+            }
+        }
+
+        let redirect_base = var("OAUTH_REDIRECT_BASE")
+            .map(|x| x.trim_end_matches('/').to_owned())
+            .unwrap_or_default();
+        if redirect_base.is_empty() && !providers.is_empty() {
+            tracing::warn!("OAUTH_REDIRECT_BASE is not set, social login disabled");
+            providers.clear();
+        }
+
+        Self {
+            providers,
+            redirect_base,
+            http_client,
+            pending: Default::default(),
+        }
+    }
+
+    /// Names of the enabled providers, sorted
+    pub fn providers(&self) -> Vec<&'static str> {
+        let mut list: Vec<_> = self.providers.keys().copied().collect();
+        list.sort();
+        list
+    }
+
+    /// Url of the login page, the result of a flow is passed in its fragment
+    pub fn login_page(&self) -> String {
+        format!("{}/login", self.redirect_base)
+    }
+
+    fn callback(&self, provider: &str) -> String {
+        format!(
+            "{}/api/auth/oauth/{}/callback",
+            self.redirect_base, provider
+        )
+    }
+
+    /// Start a flow, return the url to send the browser to and the
+    /// `Set-Cookie` tying the flow to it
+    pub fn authorize(&self, provider: &str, link: Option<i32>) -> Result<(String, String)> {
+        let (&name, config) = self
+            .providers
+            .get_key_value(provider)
+            .context("Unknown provider")?;
+
+        let state = random_hex(16)?;
+        let verifier = random_hex(32)?;
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+
+        let url = Url::parse_with_params(
+            &config.authorize_url,
+            [
+                ("response_type", "code"),
+                ("client_id", &config.client_id),
+                ("redirect_uri", &self.callback(name)),
+                ("scope", config.scope),
+                ("state", &state),
+                ("code_challenge", &challenge),
+                ("code_challenge_method", "S256"),
+            ],
+        )?;
+
+        let cookie = self.cookie(&state_hash(&state), OAUTH_STATE_SECS);
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, x| x.at.elapsed() < STATE_TTL);
+        pending.insert(
+            state,
+            Pending {
+                provider: name,
+                link,
+                verifier,
+                at: Instant::now(),
+            },
+        );
+        Ok((url.into(), cookie))
+    }
+
+    /// `Set-Cookie` removing the one set by [`Self::authorize`]
+    pub fn clear_cookie(&self) -> String {
+        self.cookie("", 0)
+    }
+
+    fn cookie(&self, value: &str, max_age: u64) -> String {
+        // the callback is a top-level navigation from the provider, Lax still sends it
+        let secure = match self.redirect_base.starts_with("https://") {
+            true => "; Secure",
+            false => "",
+        };
+        format!(
+            "{}={}; Path=/api/auth/oauth; Max-Age={}; HttpOnly; SameSite=Lax{}",
+            STATE_COOKIE, value, max_age, secure
+        )
+    }
+
+    /// Finish a flow, each state can only be used once and only by the
+    /// browser holding its cookie, see `state_cookie`
+    pub async fn identify(
+        &self,
+        provider: &str,
+        state: &str,
+        code: &str,
+        cookie: Option<&str>,
+    ) -> Result<Identity> {
+        if cookie != Some(state_hash(state).as_str()) {
+            bail!("Login was started in another browser, try again");
+        }
+        let pending = self
+            .pending
+            .lock()
+            .unwrap()
+            .remove(state)
+            .filter(|x| x.provider == provider && x.at.elapsed() < STATE_TTL)
+            .context("Login expired, try again")?;
+        let config = &self.providers[pending.provider];
+
+        let token: TokenResp = self
+            .http_client
+            .post(&config.token_url)
+            .header(http::header::ACCEPT, "application/json")
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", &self.callback(pending.provider)),
+                ("client_id", &config.client_id),
+                ("client_secret", &config.client_secret),
+                ("code_verifier", &pending.verifier),
+            ])
+            .send()
+            .await?
+            .json()
+            .await?;
+        let access_token = match (token.access_token, token.error) {
+            (Some(x), _) => x,
+            (None, error) => bail!("Token exchange failed: {}", error.unwrap_or_default()),
+        };
+
+        let info: Value = self
+            .http_client
+            .get(&config.userinfo_url)
+            .bearer_auth(access_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let subject = info
+            .get("sub")
+            .and_then(|x| x.as_str())
+            .map(|x| x.to_owned())
+            .or_else(|| {
+                info.get("id")
+                    .and_then(|x| x.as_i64())
+                    .map(|x| x.to_string())
+            })
+            .context("Provider did not return a user id")?;
+        let name = ["preferred_username", "login", "email", "name"]
+            .iter()
+            .find_map(|key| info.get(key).and_then(|x| x.as_str()))
+            .map(|x| x.split('@').next().unwrap_or(x).to_owned())
+            .unwrap_or_else(|| format!("{}-{}", pending.provider, subject));
+
+        Ok(Identity {
+            subject,
+            name,
+            link: pending.link,
+        })
+    }
+}
+
+/// `OAUTH_<PROVIDER>_CLIENT_ID` and `OAUTH_<PROVIDER>_CLIENT_SECRET`
+fn credential(provider: &str) -> Option<(String, String)> {
+    let id = var(format!("OAUTH_{}_CLIENT_ID", provider)).ok()?;
+    let secret = var(format!("OAUTH_{}_CLIENT_SECRET", provider)).ok()?;
+    Some((id, secret))
+}
+
+async fn discover(http_client: &reqwest::Client, issuer: &str) -> Result<Discovery> {
+    let url = format!(
+        "{}/.well-known/openid-configuration",
+        issuer.trim_end_matches('/')
+    );
+    Ok(http_client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
+}
+
+/// Value of the cookie set by [`OAuth::authorize`] among the `Cookie` headers
+pub fn state_cookie(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|x| x.to_str().ok())
+        .flat_map(|x| x.split(';'))
+        .find_map(|x| x.trim().strip_prefix(STATE_COOKIE)?.strip_prefix('='))
+}
+
+fn state_hash(state: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(state.as_bytes()))
+}
+
+fn random_hex(len: usize) -> Result<String> {
+    let mut bytes = vec![0u8; len];
+    getrandom::fill(&mut bytes).map_err(|e| anyhow::anyhow!("Cannot generate state: {}", e))?;
+    Ok(bytes.iter().map(|x| format!("{:02x}", x)).collect())
+}
@@ -1,11 +1,29 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
 use anyhow::{Context, Result};
 use dotenv::var;
+use tokio::time::Instant;
+use tracing::{Instrument, field};
 
+use super::embedding::EmbeddingConfig;
 use super::raw;
 use super::stream::StreamCompletion;
+use crate::{
+    AppState,
+    config::{
+        RuntimeConfig, UPSTREAM_CONNECT_TIMEOUT, UPSTREAM_IDLE_TIMEOUT, UPSTREAM_TOTAL_TIMEOUT,
+    },
+    federation::{Federation, PEER_PREFIX},
+    middlewares::metrics::{Call, METRICS},
+};
 
 static HTTP_REFERER: &str = "https://github.com/pinkfuwa/llumen";
 static X_TITLE: &str = "llumen";
+/// Probes retry on their own, a slow provider only fails this one
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[derive(Clone, Default)]
 pub struct Model {
@@ -14,32 +32,58 @@ pub struct Model {
     pub repeat_penalty: Option<f32>,
     pub top_k: Option<i32>,
     pub top_p: Option<f32>,
+    pub seed: Option<u32>,
     pub online: bool,
+    /// request reasoning tokens from the provider
+    pub reasoning: bool,
 }
 
 impl Model {
+    /// Providers that only cache prompts with explicit `cache_control` breakpoints,
+    /// others cache automatically
+    pub fn explicit_cache(&self) -> bool {
+        self.id.starts_with("anthropic/") || self.id.starts_with("google/gemini")
+    }
+
     pub fn get_model_id(&self) -> String {
         let mut id = self.id.clone();
         if self.online {
             id.push_str(":online");
         }
-        return id;
+        id
     }
 }
 
 pub struct Openrouter {
+    /// Replaced as the settings change, see [`Openrouter::spawn_reload`]
+    upstream: RwLock<Arc<Upstream>>,
+    pub(super) http_client: reqwest::Client,
+    idle_timeout: Duration,
+    federation: Option<Federation>,
+}
+
+/// The provider of `API_BASE` and `API_KEY` env, unless set in the settings
+pub(super) struct Upstream {
     api_key: String,
+    api_base: String,
     chat_completion_endpoint: String,
+    pub(super) models_endpoint: String,
     default_req: raw::CompletionReq,
-    http_client: reqwest::Client,
+    pub(super) embedding: EmbeddingConfig,
 }
 
-impl Openrouter {
-    pub fn new() -> Self {
-        let api_key = var("API_KEY").expect("API_KEY is required");
-        let api_base = var("API_BASE").unwrap_or("https://openrouter.ai/".to_string());
+impl Upstream {
+    /// None without a key
+    fn new(config: &RuntimeConfig) -> Option<Self> {
+        let api_key = config.api_key.clone().or_else(|| var("API_KEY").ok())?;
+        let api_base = config
+            .api_base
+            .clone()
+            .or_else(|| var("API_BASE").ok())
+            .unwrap_or("https://openrouter.ai/".to_string());
         let chat_completion_endpoint =
             format!("{}/api/v1/chat/completions", api_base.trim_end_matches('/'));
+        let models_endpoint = format!("{}/api/v1/models", api_base.trim_end_matches('/'));
         let mut default_req = raw::CompletionReq::default();
 
         if !api_base.contains("openrouter") {
@@ -47,19 +91,145 @@ impl Openrouter {
             default_req.plugins = None;
         }
 
-        Self {
+        let embedding = EmbeddingConfig::new(&api_key, &api_base);
+
+        Some(Self {
             api_key,
+            api_base,
             chat_completion_endpoint,
+            models_endpoint,
             default_req,
-            http_client: reqwest::Client::new(),
+            embedding,
+        })
+    }
+}
+
+impl Openrouter {
+    pub fn new(config: &RuntimeConfig) -> Self {
+        let upstream = Upstream::new(config).expect("API_KEY is required");
+
+        let http_client = reqwest::Client::builder()
+            .connect_timeout(timeout_var(
+                "UPSTREAM_CONNECT_TIMEOUT",
+                UPSTREAM_CONNECT_TIMEOUT,
+            ))
+            .timeout(timeout_var(
+                "UPSTREAM_TOTAL_TIMEOUT",
+                UPSTREAM_TOTAL_TIMEOUT,
+            ))
+            .build()
+            .expect("Cannot build http client");
+        let idle_timeout = timeout_var("UPSTREAM_IDLE_TIMEOUT", UPSTREAM_IDLE_TIMEOUT);
+
+        Self {
+            upstream: RwLock::new(Arc::new(upstream)),
+            http_client,
+            idle_timeout,
+            federation: Federation::from_env(),
         }
     }
-    pub fn stream(
+
+    /// Follow the provider of the settings, requests already sent keep theirs
+    pub fn spawn_reload(app: Arc<AppState>) {
+        let mut rx = app.settings.subscribe();
+        let provider = |x: &RuntimeConfig| (x.api_key.clone(), x.api_base.clone());
+        let mut last = provider(&rx.borrow());
+        tokio::spawn(async move {
+            while rx.changed().await.is_ok() {
+                let config = rx.borrow_and_update().clone();
+                if provider(&config) == last {
+                    continue;
+                }
+                last = provider(&config);
+                match Upstream::new(&config) {
+                    Some(upstream) => {
+                        tracing::info!("upstream set to {}", upstream.api_base);
+                        *app.openrouter.upstream.write().unwrap() = Arc::new(upstream);
+                    }
+                    None => tracing::warn!("no API key is set, keeping the previous one"),
+                }
+            }
+        });
+    }
+
+    pub(super) fn upstream(&self) -> Arc<Upstream> {
+        self.upstream.read().unwrap().clone()
+    }
+
+    pub fn federation(&self) -> Option<&Federation> {
+        self.federation.as_ref()
+    }
+
+    /// Whether the provider answers with the current key, for `/readyz`
+    pub async fn ping(&self) -> Result<()> {
+        let upstream = self.upstream();
+        self.http_client
+            .get(&upstream.models_endpoint)
+            .bearer_auth(&upstream.api_key)
+            .header("HTTP-Referer", HTTP_REFERER)
+            .header("X-Title", X_TITLE)
+            .timeout(PING_TIMEOUT)
+            .send()
+            .await
+            .context("Failed to build request")?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Endpoint, bearer token and upstream model id of a model
+    fn target(&self, model: &Model) -> Result<(String, String, String)> {
+        let model_id = model.get_model_id();
+        let Some(model_id) = model_id.strip_prefix(PEER_PREFIX) else {
+            let upstream = self.upstream();
+            return Ok((
+                upstream.chat_completion_endpoint.clone(),
+                upstream.api_key.clone(),
+                model_id,
+            ));
+        };
+
+        let federation = self
+            .federation
+            .as_ref()
+            .context("Federation is not configured")?;
+        let endpoint = federation
+            .peer_endpoint()
+            .context("FEDERATION_PEER is not set")?;
+        Ok((endpoint, federation.sign()?, model_id.to_owned()))
+    }
+
+    /// Send a completion request of a peer to our upstream as is
+    pub async fn forward(&self, mut req: serde_json::Value) -> Result<reqwest::Response> {
+        let upstream = self.upstream();
+        // plugins are openrouter only
+        if upstream.default_req.plugins.is_none()
+            && let Some(req) = req.as_object_mut()
+        {
+            req.remove("plugins");
+        }
+
+        let start = Instant::now();
+        let res = self
+            .http_client
+            .post(&upstream.chat_completion_endpoint)
+            .bearer_auth(&upstream.api_key)
+            .header("HTTP-Referer", HTTP_REFERER)
+            .header("X-Title", X_TITLE)
+            .json(&req)
+            .send()
+            .instrument(tracing::info_span!("forward", otel.kind = "client"))
+            .await;
+        let ok = res.as_ref().is_ok_and(|x| x.status().is_success());
+        METRICS.upstream(Call::Forward, start.elapsed(), ok);
+        res.context("Failed to build request")
+    }
+
+    pub async fn stream(
         &self,
         mut messages: Vec<Message>,
         model: &Model,
         tools: Vec<Tool>,
-    ) -> impl std::future::Future<Output = Result<StreamCompletion>> {
+    ) -> Result<StreamCompletion> {
         tracing::info!("start streaming with model {}", &model.id);
 
         let tools = match tools.is_empty() {
@@ -72,27 +242,58 @@ impl Openrouter {
             messages.push(Message::User("".to_string()));
         }
 
+        let (endpoint, api_key, model_id) = self.target(model)?;
+        let span = tracing::info_span!(
+            "stream",
+            otel.kind = "client",
+            model = %model.id,
+            error = field::Empty,
+        );
         let req = raw::CompletionReq {
-            messages: messages.into_iter().map(|m| m.into()).collect(),
-            model: model.get_model_id(),
+            messages: to_raw_messages(messages, model),
+            model: model_id,
             temperature: model.temperature,
             repeat_penalty: model.repeat_penalty,
             top_k: model.top_k,
             top_p: model.top_p,
+            seed: model.seed,
             tools,
-            ..self.default_req.clone()
+            reasoning: model.reasoning.then_some(raw::Reasoning { exclude: false }),
+            ..self.upstream().default_req.clone()
         };
 
         req.log();
 
         StreamCompletion::request(
             &self.http_client,
-            &self.api_key,
-            &self.chat_completion_endpoint,
+            &api_key,
+            &endpoint,
             req,
+            self.idle_timeout,
+            span,
         )
+        .await
     }
-    pub async fn complete(
+    pub async fn complete(&self, messages: Vec<Message>, model: Model) -> Result<ChatCompletion> {
+        let span = tracing::info_span!(
+            "complete",
+            otel.kind = "client",
+            model = %model.id,
+            error = field::Empty,
+        );
+        let start = Instant::now();
+        let res = self
+            .complete_once(messages, model)
+            .instrument(span.clone())
+            .await;
+        METRICS.upstream(Call::Complete, start.elapsed(), res.is_ok());
+        if let Err(err) = &res {
+            span.record("error", field::display(err));
+        }
+        res
+    }
+
+    async fn complete_once(
         &self,
         mut messages: Vec<Message>,
         model: Model,
@@ -108,31 +309,32 @@ impl Openrouter {
             messages.push(Message::User("".to_string()));
         }
 
+        let (endpoint, api_key, model_id) = self.target(&model)?;
         let req = raw::CompletionReq {
-            messages: messages.into_iter().map(|m| m.into()).collect(),
-            model: model.get_model_id(),
+            messages: to_raw_messages(messages, &model),
+            model: model_id,
             temperature: model.temperature,
             repeat_penalty: model.repeat_penalty,
             top_k: model.top_k,
             top_p: model.top_p,
+            seed: model.seed,
             stream: false,
-            ..self.default_req.clone()
+            ..self.upstream().default_req.clone()
         };
 
         req.log();
 
         let res = self
             .http_client
-            .post(&self.chat_completion_endpoint)
-            .bearer_auth(&self.api_key)
+            .post(&endpoint)
+            .bearer_auth(&api_key)
             .header("HTTP-Referer", HTTP_REFERER)
             .header("X-Title", X_TITLE)
             .json(&req)
             .send()
             .await
-            .map_err(|err| {
-                tracing::warn!("openrouter finish with error: {}", &err);
-                err
+            .inspect_err(|err| {
+                tracing::warn!("openrouter finish with error: {}", err);
             })
             .context("Failed to build request")?;
 
@@ -171,8 +373,10 @@ pub struct ChatCompletion {
 
 #[derive(Debug, Clone)]
 pub struct File {
-    name: String,
-    data: Vec<u8>,
+    pub name: String,
+    pub data: Vec<u8>,
+    /// Text of a document, sent instead of the file, see `utils::extract`
+    pub text: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -225,18 +429,23 @@ impl From<Message> for raw::Message {
             Message::MultipartUser(MessageMultipartUser { text, files }) => {
                 let files = files
                     .into_iter()
-                    .map(|f| {
-                        let (first, second) = raw::MessagePart::unknown(&f.name, f.data);
-                        vec![first, second]
+                    .flat_map(|f| match f.text {
+                        Some(text) => vec![raw::MessagePart::text(format!(
+                            "Content of the uploaded file {}:\n\n{}",
+                            f.name, text
+                        ))],
+                        None => {
+                            let (first, second) = raw::MessagePart::unknown(&f.name, f.data);
+                            vec![first, second]
+                        }
                     })
-                    .flatten()
                     .collect::<Vec<_>>();
 
                 raw::Message {
                     role: raw::Role::User,
                     contents: Some(
                         std::iter::once(raw::MessagePart::text(text))
-                            .chain(files.into_iter())
+                            .chain(files)
                             .collect(),
                     ),
                     ..Default::default()
@@ -287,3 +496,32 @@ impl From<Tool> for raw::Tool {
         }
     }
 }
+
+/// Timeout in seconds from env, fallback to the default
+fn timeout_var(key: &str, default: u64) -> Duration {
+    let secs = var(key)
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(default);
+    Duration::from_secs(secs)
+}
+
+fn to_raw_messages(messages: Vec<Message>, model: &Model) -> Vec<raw::Message> {
+    let mut messages: Vec<raw::Message> = messages.into_iter().map(|m| m.into()).collect();
+
+    if model.explicit_cache() {
+        // the system prompt, and the end of history so the next turn reuse it
+        if let Some(system) = messages.iter_mut().find(|m| m.role == raw::Role::System) {
+            system.cache_breakpoint();
+        }
+        if let Some(last) = messages
+            .iter_mut()
+            .rev()
+            .find(|m| m.role == raw::Role::User && !m.is_empty())
+        {
+            last.cache_breakpoint();
+        }
+    }
+
+    messages
+}
@@ -0,0 +1,113 @@
+use anyhow::{Context, Result, anyhow};
+use dotenv::var;
+use tokio::time::{Duration, Instant, sleep};
+use tracing::{Instrument, field};
+
+use super::{HTTP_REFERER, Openrouter, X_TITLE, raw};
+use crate::middlewares::metrics::{Call, METRICS};
+
+/// Inputs per embedding request
+const EMBEDDING_BATCH: usize = 64;
+const MAX_RETRY: u32 = 3;
+
+pub struct EmbeddingConfig {
+    api_key: String,
+    endpoint: String,
+    model: String,
+}
+
+impl EmbeddingConfig {
+    /// Default to the chat completion provider
+    pub fn new(api_key: &str, api_base: &str) -> Self {
+        let api_key = var("EMBEDDING_API_KEY").unwrap_or(api_key.to_owned());
+        let api_base = var("EMBEDDING_API_BASE").unwrap_or(api_base.to_owned());
+        let model = var("EMBEDDING_MODEL").unwrap_or("openai/text-embedding-3-small".to_owned());
+        let endpoint = format!("{}/api/v1/embeddings", api_base.trim_end_matches('/'));
+
+        Self {
+            api_key,
+            endpoint,
+            model,
+        }
+    }
+}
+
+impl Openrouter {
+    /// Embed texts, the output is in the same order as the input
+    pub async fn embed(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(inputs.len());
+
+        for batch in inputs.chunks(EMBEDDING_BATCH) {
+            embeddings.extend(self.embed_batch_with_retry(batch).await?);
+        }
+
+        Ok(embeddings)
+    }
+
+    async fn embed_batch_with_retry(&self, batch: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut attempt = 0;
+        loop {
+            let span = tracing::info_span!(
+                "embed",
+                otel.kind = "client",
+                inputs = batch.len(),
+                error = field::Empty,
+            );
+            let start = Instant::now();
+            let res = self.embed_batch(batch).instrument(span.clone()).await;
+            METRICS.upstream(Call::Embed, start.elapsed(), res.is_ok());
+            if let Err(err) = &res {
+                span.record("error", field::display(err));
+            }
+            match res {
+                Ok(embeddings) => return Ok(embeddings),
+                Err(err) if attempt < MAX_RETRY => {
+                    attempt += 1;
+                    tracing::warn!("embedding failed (attempt {}): {}", attempt, &err);
+                    sleep(Duration::from_millis(500 * 2u64.pow(attempt))).await;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    async fn embed_batch(&self, batch: &[String]) -> Result<Vec<Vec<f32>>> {
+        let upstream = self.upstream();
+        let req = raw::EmbeddingReq {
+            model: upstream.embedding.model.clone(),
+            input: batch.to_vec(),
+        };
+
+        let res = self
+            .http_client
+            .post(&upstream.embedding.endpoint)
+            .bearer_auth(&upstream.embedding.api_key)
+            .header("HTTP-Referer", HTTP_REFERER)
+            .header("X-Title", X_TITLE)
+            .json(&req)
+            .send()
+            .await
+            .context("Failed to build request")?;
+
+        let json = res
+            .json::<raw::EmbeddingResp>()
+            .await
+            .context("Failed to parse response")?;
+
+        if let Some(error) = json.error {
+            return Err(anyhow!("Embedding API error: {}", error.message));
+        }
+
+        let mut data = json.data.context("Malformed response")?;
+        if data.len() != batch.len() {
+            return Err(anyhow!(
+                "Embedding API returned {} embeddings for {} inputs",
+                data.len(),
+                batch.len()
+            ));
+        }
+        data.sort_by_key(|x| x.index);
+
+        Ok(data.into_iter().map(|x| x.embedding).collect())
+    }
+}
@@ -1,4 +1,6 @@
 mod completion;
+mod embedding;
+mod pricing;
 #[allow(dead_code)]
 mod raw;
 mod stream;
@@ -6,5 +8,8 @@ mod stream;
 static HTTP_REFERER: &str = "https://github.com/pinkfuwa/llumen";
 static X_TITLE: &str = "llumen";
 
-pub use completion::{File, Message, MessageToolCall, MessageToolResult, Model, Openrouter, Tool};
-pub use stream::{StreamCompletion, StreamCompletionResp};
+pub use completion::{
+    File, Message, MessageMultipartUser, MessageToolCall, MessageToolResult, Model, Openrouter,
+    Tool,
+};
+pub use stream::StreamCompletionResp;
@@ -0,0 +1,48 @@
+use anyhow::{Context, Result};
+
+use super::{HTTP_REFERER, Openrouter, X_TITLE, raw};
+
+/// Current price of a model, USD per token
+#[derive(Debug, Clone)]
+pub struct ModelPrice {
+    pub id: String,
+    pub prompt: f64,
+    pub completion: f64,
+    pub context_length: Option<usize>,
+}
+
+impl Openrouter {
+    /// Prices of every model the provider list
+    pub async fn prices(&self) -> Result<Vec<ModelPrice>> {
+        let res = self
+            .http_client
+            .get(&self.upstream().models_endpoint)
+            .header("HTTP-Referer", HTTP_REFERER)
+            .header("X-Title", X_TITLE)
+            .send()
+            .await
+            .context("Failed to build request")?
+            .error_for_status()?;
+
+        let json = res
+            .json::<raw::ModelsResp>()
+            .await
+            .context("Failed to parse response")?;
+
+        Ok(json
+            .data
+            .into_iter()
+            .filter_map(|model| {
+                let pricing = model.pricing?;
+                Some(ModelPrice {
+                    id: model.id,
+                    prompt: pricing.prompt.parse().ok()?,
+                    completion: pricing.completion.parse().ok()?,
+                    context_length: model.context_length.map(|x| x as usize),
+                })
+            })
+            // negative prices mean variable pricing, e.g. openrouter/auto
+            .filter(|x| x.prompt >= 0.0 && x.completion >= 0.0)
+            .collect())
+    }
+}
@@ -20,9 +20,13 @@ pub struct CompletionReq {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<Tool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub plugins: Option<Vec<Plugin>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<Reasoning>,
 }
 
 impl Default for CompletionReq {
@@ -36,6 +40,8 @@ impl Default for CompletionReq {
             repeat_penalty: None,
             top_k: None,
             top_p: None,
+            seed: None,
+            reasoning: None,
             plugins: Some(vec![Plugin {
                 id: "file-parser".to_string(),
                 pdf: PdfPlugin {
@@ -57,6 +63,12 @@ impl CompletionReq {
     }
 }
 
+/// https://openrouter.ai/docs/use-cases/reasoning-tokens
+#[derive(Debug, Clone, Serialize)]
+pub struct Reasoning {
+    pub exclude: bool,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct Plugin {
     pub id: String,
@@ -84,15 +96,47 @@ pub struct FunctionTool {
 #[derive(Debug, Clone, Serialize, Default)]
 pub struct Message {
     pub role: Role,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub content: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCallReq>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_call_id: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// multipart form of `content`, only one of them should be set
+    #[serde(rename = "content", skip_serializing_if = "Option::is_none")]
     pub contents: Option<Vec<MessagePart>>,
 }
 
+impl Message {
+    /// Mark the end of this message as a prompt cache breakpoint
+    pub fn cache_breakpoint(&mut self) {
+        if let Some(text) = self.content.take() {
+            self.contents = Some(vec![MessagePart::text(text)]);
+        }
+        if let Some(part) = self.contents.as_mut().and_then(|parts| parts.last_mut()) {
+            part.cache_control = Some(CacheControl::ephemeral());
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.content.as_deref().is_none_or(str::is_empty) && self.contents.is_none()
+    }
+}
+
+/// https://openrouter.ai/docs/features/prompt-caching
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheControl {
+    pub r#type: String,
+}
+
+impl CacheControl {
+    pub fn ephemeral() -> Self {
+        Self {
+            r#type: "ephemeral".to_string(),
+        }
+    }
+}
+
 // `data:image/jpeg;base64,${base64Image}`;
 #[derive(Debug, Clone, Serialize, Default)]
 #[serde(rename_all = "snake_case")]
@@ -107,10 +151,16 @@ pub enum MultiPartMessageType {
 #[derive(Debug, Clone, Serialize, Default)]
 pub struct MessagePart {
     pub r#type: MultiPartMessageType,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub input_audio: Option<InputAudio>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub file: Option<InputFile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub image_url: Option<InputImage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
 }
 
 impl MessagePart {
@@ -222,7 +272,10 @@ pub struct CompletionInfoResp {
 #[derive(Debug, Clone, Deserialize)]
 pub struct Usage {
     pub total_tokens: Option<i64>,
-    pub cost: f64,
+    pub prompt_tokens: Option<i64>,
+    pub completion_tokens: Option<i64>,
+    /// openrouter only
+    pub cost: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
@@ -256,6 +309,8 @@ pub struct Choice {
 pub struct Delta {
     pub role: Option<Role>,
     pub content: Option<String>,
+    // openai-compatible providers (e.g. deepseek, vllm) use `reasoning_content`
+    #[serde(alias = "reasoning_content")]
     pub reasoning: Option<String>,
     pub tool_calls: Option<Vec<ToolCall>>,
 }
@@ -306,3 +361,42 @@ pub struct ErrorInfo {
     pub message: String,
     pub code: Option<i32>,
 }
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbeddingReq {
+    pub model: String,
+    pub input: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmbeddingResp {
+    pub data: Option<Vec<EmbeddingData>>,
+    pub error: Option<ErrorInfo>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmbeddingData {
+    pub index: usize,
+    pub embedding: Vec<f32>,
+}
+
+/// https://openrouter.ai/docs/api-reference/list-available-models
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModelsResp {
+    pub data: Vec<ModelInfo>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModelInfo {
+    pub id: String,
+    pub pricing: Option<ModelPricing>,
+    /// tokens of prompt and completion together
+    pub context_length: Option<u64>,
+}
+
+/// USD per token, as decimal strings
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModelPricing {
+    pub prompt: String,
+    pub completion: String,
+}
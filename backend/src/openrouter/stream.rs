@@ -2,8 +2,11 @@ use anyhow::{Context, Result, anyhow};
 use futures_util::StreamExt;
 use reqwest::Client;
 use reqwest_eventsource::{Event, EventSource};
+use tokio::time::{Duration, Instant, timeout};
+use tracing::{Instrument, Span, field};
 
 use super::{HTTP_REFERER, X_TITLE, raw};
+use crate::middlewares::metrics::{Call, METRICS};
 
 #[derive(Default)]
 struct ToolCall {
@@ -15,6 +18,20 @@ struct ToolCall {
 pub struct StreamCompletion {
     source: EventSource,
     toolcall: Option<ToolCall>,
+    /// response held back when a single delta carries both reasoning and content
+    pending: Option<StreamCompletionResp>,
+    /// model reported by the provider, may differ from the requested one
+    model: Option<String>,
+    finish_reason: Option<raw::FinishReason>,
+    idle_timeout: Duration,
+    /// any event has been received
+    received: bool,
+    /// the upstream timed out halfway, what has been streamed is all we get
+    truncated: bool,
+    /// taken once the first event or error is timed
+    started: Option<Instant>,
+    /// the events are awaited in, see `telemetry`
+    span: Span,
 }
 
 impl StreamCompletion {
@@ -23,6 +40,8 @@ impl StreamCompletion {
         api_key: &str,
         endpoint: &str,
         req: raw::CompletionReq,
+        idle_timeout: Duration,
+        span: Span,
     ) -> Result<StreamCompletion> {
         let builder = http_client
             .post(endpoint)
@@ -35,6 +54,14 @@ impl StreamCompletion {
             Ok(source) => Ok(Self {
                 source,
                 toolcall: None,
+                pending: None,
+                model: None,
+                finish_reason: None,
+                idle_timeout,
+                received: false,
+                truncated: false,
+                started: Some(Instant::now()),
+                span,
             }),
             Err(e) => {
                 tracing::error!("Failed to create event source: {}", e);
@@ -47,16 +74,53 @@ impl StreamCompletion {
         self.source.close();
     }
 
-    fn handle_choice(&mut self, choice: raw::Choice) -> StreamCompletionResp {
-        let delta = choice.delta;
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
 
-        let content = delta.content.unwrap_or("".to_string());
+    /// Stop streaming, fail if nothing can be salvaged
+    fn truncate(&mut self) -> Option<Result<StreamCompletionResp>> {
+        self.source.close();
+        self.truncated = true;
+        match self.received {
+            true => None,
+            false => Some(Err(anyhow!("Upstream timed out before responding"))),
+        }
+    }
+
+    pub fn model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
 
-        if let Some(reasoning) = delta.reasoning {
-            return StreamCompletionResp::ReasoningToken(reasoning);
+    pub fn finish_reason(&self) -> Option<&'static str> {
+        self.finish_reason.as_ref().map(|reason| match reason {
+            raw::FinishReason::Stop => "stop",
+            raw::FinishReason::Length => "length",
+            raw::FinishReason::ToolCalls => "tool_calls",
+        })
+    }
+
+    fn handle_choice(&mut self, mut choice: raw::Choice) -> StreamCompletionResp {
+        let reasoning = choice.delta.reasoning.take().filter(|x| !x.is_empty());
+
+        let resp = self.handle_delta(choice);
+
+        match reasoning {
+            Some(reasoning) => {
+                self.pending = Some(resp);
+                StreamCompletionResp::ReasoningToken(reasoning)
+            }
+            None => resp,
         }
+    }
+
+    fn handle_delta(&mut self, choice: raw::Choice) -> StreamCompletionResp {
+        let delta = choice.delta;
+
+        let content = delta.content.unwrap_or("".to_string());
+        let mut args_delta = String::new();
 
-        if let Some(call) = delta.tool_calls.map(|x| x.into_iter().next()).flatten() {
+        if let Some(call) = delta.tool_calls.and_then(|x| x.into_iter().next()) {
             if let Some(id) = call.id {
                 self.toolcall = Some(ToolCall {
                     id,
@@ -69,11 +133,13 @@ impl StreamCompletion {
                 }
                 if let Some(args) = call.function.arguments {
                     state.args.push_str(&args);
+                    args_delta = args;
                 }
             }
         }
 
         if let Some(reason) = choice.finish_reason {
+            self.finish_reason = Some(reason.clone());
             return match reason {
                 raw::FinishReason::Stop => StreamCompletionResp::ResponseToken(content),
                 raw::FinishReason::Length => StreamCompletionResp::ResponseToken(content),
@@ -87,34 +153,75 @@ impl StreamCompletion {
                 },
             };
         }
-        return StreamCompletionResp::ResponseToken(content);
+        if let Some(call) = &self.toolcall
+            && content.is_empty()
+            && !args_delta.is_empty()
+        {
+            return StreamCompletionResp::ToolCallDelta {
+                name: call.name.clone(),
+                args: args_delta,
+            };
+        }
+        StreamCompletionResp::ResponseToken(content)
     }
 
     fn handle_data(&mut self, data: &str) -> Result<StreamCompletionResp> {
         // this approach made it compatible with both openrouter and openai
         if let Ok(resp) = serde_json::from_str::<raw::CompletionInfoResp>(data) {
+            self.model = Some(resp.model);
             return Ok(StreamCompletionResp::Usage {
                 price: resp.usage.cost,
                 // cloak model may return null for total_tokens
                 token: resp.usage.total_tokens.map(|x| x as usize).unwrap_or(0),
+                prompt_token: resp.usage.prompt_tokens.map(|x| x as usize),
+                completion_token: resp.usage.completion_tokens.map(|x| x as usize),
             });
         }
 
         let resp = serde_json::from_str::<raw::CompletionResp>(data).context("Parse error")?;
+        self.model = Some(resp.model);
 
         let choice = resp.choices.into_iter().next();
 
         match choice {
-            None => return Ok(StreamCompletionResp::ResponseToken("".to_string())),
-            Some(c) => return Ok(self.handle_choice(c)),
+            None => Ok(StreamCompletionResp::ResponseToken("".to_string())),
+            Some(c) => Ok(self.handle_choice(c)),
         }
     }
 
     pub async fn next(&mut self) -> Option<Result<StreamCompletionResp>> {
+        let span = self.span.clone();
+        let resp = self.next_event().instrument(span).await;
+        if let Some(Err(err)) = &resp {
+            self.span.record("error", field::display(err));
+        }
+        match (&resp, self.started.take()) {
+            (Some(resp), Some(started)) => {
+                METRICS.upstream(Call::Stream, started.elapsed(), resp.is_ok())
+            }
+            (Some(Err(_)), None) => METRICS.upstream_error(Call::Stream),
+            (_, started) => self.started = started,
+        }
+        resp
+    }
+
+    async fn next_event(&mut self) -> Option<Result<StreamCompletionResp>> {
+        if let Some(resp) = self.pending.take() {
+            return Some(Ok(resp));
+        }
+
         loop {
-            match self.source.next().await? {
+            let event = match timeout(self.idle_timeout, self.source.next()).await {
+                Ok(event) => event?,
+                Err(_) => {
+                    tracing::warn!("Upstream idle for {:?}", self.idle_timeout);
+                    return self.truncate();
+                }
+            };
+            match event {
                 Ok(Event::Open) => continue,
                 Ok(Event::Message(e)) if &e.data != "[DONE]" => {
+                    self.received = true;
                     return Some(self.handle_data(&e.data));
                 }
                 Err(e) => match e {
@@ -141,6 +248,13 @@ impl StreamCompletion {
                             };
                         }
 
+                        if let reqwest_eventsource::Error::Transport(err) = &e
+                            && err.is_timeout()
+                        {
+                            tracing::warn!("Upstream exceeded total timeout");
+                            return self.truncate();
+                        }
+
                         tracing::error!("Stream error: {}", e);
 
                         return Some(Err(e.into()));
@@ -166,9 +280,17 @@ pub enum StreamCompletionResp {
         args: String,
         id: String,
     },
+    /// Arguments of the tool call being streamed, only the new part
+    ToolCallDelta {
+        name: String,
+        args: String,
+    },
     ToolToken(String),
     Usage {
-        price: f64,
+        /// None if the provider does not report cost
+        price: Option<f64>,
         token: usize,
+        prompt_token: Option<usize>,
+        completion_token: Option<usize>,
     },
 }
@@ -0,0 +1,75 @@
+//! Chat histories built while the user is still typing
+//!
+//! Loading and converting the history dominate the time before the provider
+//! call on long chats, so `message/draft` build it ahead and `message/create`
+//! take it if no message was added since
+
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use tokio::time::Instant;
+
+use crate::{config::PREFETCH_TTL, openrouter};
+
+const TTL: Duration = Duration::from_secs(PREFETCH_TTL);
+
+#[derive(Debug, Default)]
+pub struct Prefetch {
+    map: Mutex<HashMap<i32, Entry>>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    /// Who the history was built for, it leaves out the private messages
+    /// of the other members
+    user_id: i32,
+    /// Last message the history include, None for an empty chat
+    last_message_id: Option<i32>,
+    /// Without the system prompt, it is rendered on send
+    history: Vec<openrouter::Message>,
+    at: Instant,
+}
+
+impl Prefetch {
+    pub fn put(
+        &self,
+        chat_id: i32,
+        user_id: i32,
+        last_message_id: Option<i32>,
+        history: Vec<openrouter::Message>,
+    ) {
+        let mut map = self.map.lock().unwrap();
+        map.retain(|_, x| x.at.elapsed() < TTL);
+        map.insert(
+            chat_id,
+            Entry {
+                user_id,
+                last_message_id,
+                history,
+                at: Instant::now(),
+            },
+        );
+    }
+
+    /// Drop the history, e.g. a message it include was deleted
+    pub fn forget(&self, chat_id: i32) {
+        self.map.lock().unwrap().remove(&chat_id);
+    }
+
+    /// Take the history if it was built for `user_id` and still end at
+    /// `last_message_id`
+    pub fn take(
+        &self,
+        chat_id: i32,
+        user_id: i32,
+        last_message_id: Option<i32>,
+    ) -> Option<Vec<openrouter::Message>> {
+        self.map
+            .lock()
+            .unwrap()
+            .remove(&chat_id)
+            .filter(|x| {
+                x.user_id == user_id && x.last_message_id == last_message_id && x.at.elapsed() < TTL
+            })
+            .map(|x| x.history)
+    }
+}
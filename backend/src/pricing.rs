@@ -0,0 +1,154 @@
+//! Model prices synced from the provider, every change is kept as a new row
+//!
+//! Openrouter report the cost of each completion, other providers are priced
+//! with the rate effective at generation time
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use entity::{prelude::*, price};
+use sea_orm::{ActiveValue::Set, DbConn, EntityTrait, QueryOrder};
+
+use crate::{AppState, config::PRICE_SYNC_INTERVAL, openrouter::Openrouter};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rate {
+    /// USD per token
+    pub prompt: f64,
+    pub completion: f64,
+    /// unix seconds
+    pub effective_at: i64,
+}
+
+pub struct Pricing {
+    conn: DbConn,
+    /// Latest rate of each model
+    current: Mutex<HashMap<String, Rate>>,
+    /// Context length of each model as of the last sync, not persisted
+    context: Mutex<HashMap<String, usize>>,
+}
+
+impl Pricing {
+    pub async fn new(conn: DbConn) -> Result<Self> {
+        let mut current = HashMap::new();
+        for row in Price::find()
+            .order_by_asc(price::Column::EffectiveAt)
+            .all(&conn)
+            .await?
+        {
+            current.insert(
+                row.model_id,
+                Rate {
+                    prompt: row.prompt,
+                    completion: row.completion,
+                    effective_at: row.effective_at,
+                },
+            );
+        }
+
+        Ok(Self {
+            conn,
+            current: Mutex::new(current),
+            context: Default::default(),
+        })
+    }
+
+    pub fn rate(&self, model_id: &str) -> Option<Rate> {
+        self.current.lock().unwrap().get(model_id).copied()
+    }
+
+    /// Unknown until the first sync
+    pub fn context_length(&self, model_id: &str) -> Option<usize> {
+        let model_id = model_id.strip_suffix(":online").unwrap_or(model_id);
+        self.context.lock().unwrap().get(model_id).copied()
+    }
+
+    /// Every model with a known rate, sorted by id
+    pub fn list(&self) -> Vec<(String, Rate)> {
+        let mut list: Vec<_> = self
+            .current
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, rate)| (id.clone(), *rate))
+            .collect();
+        list.sort_by(|a, b| a.0.cmp(&b.0));
+        list
+    }
+
+    /// Cost of a completion at the current rate
+    pub fn cost(&self, model_id: &str, prompt_token: usize, completion_token: usize) -> Option<f64> {
+        let model_id = model_id.strip_suffix(":online").unwrap_or(model_id);
+        let rate = self.rate(model_id)?;
+        Some(rate.prompt * prompt_token as f64 + rate.completion * completion_token as f64)
+    }
+
+    /// Record the prices that changed since the last sync
+    ///
+    /// Return the number of models updated
+    pub async fn sync(&self, openrouter: &Openrouter) -> Result<usize> {
+        let prices = openrouter.prices().await?;
+        let now = time::UtcDateTime::now().unix_timestamp();
+        *self.context.lock().unwrap() = prices
+            .iter()
+            .filter_map(|x| Some((x.id.clone(), x.context_length?)))
+            .collect();
+
+        let changed: Vec<_> = {
+            let current = self.current.lock().unwrap();
+            prices
+                .into_iter()
+                .filter(|x| {
+                    current
+                        .get(&x.id)
+                        .is_none_or(|rate| rate.prompt != x.prompt || rate.completion != x.completion)
+                })
+                .collect()
+        };
+        if changed.is_empty() {
+            return Ok(0);
+        }
+
+        Price::insert_many(changed.iter().map(|x| price::ActiveModel {
+            model_id: Set(x.id.clone()),
+            prompt: Set(x.prompt),
+            completion: Set(x.completion),
+            effective_at: Set(now),
+            ..Default::default()
+        }))
+        .exec(&self.conn)
+        .await?;
+
+        let mut current = self.current.lock().unwrap();
+        for x in &changed {
+            current.insert(
+                x.id.clone(),
+                Rate {
+                    prompt: x.prompt,
+                    completion: x.completion,
+                    effective_at: now,
+                },
+            );
+        }
+        Ok(changed.len())
+    }
+
+    /// Periodically sync prices, the first sync run on start
+    pub fn spawn_sync(app: Arc<AppState>) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(PRICE_SYNC_INTERVAL));
+            loop {
+                interval.tick().await;
+                match app.pricing.sync(&app.openrouter).await {
+                    Ok(0) => {}
+                    Ok(updated) => tracing::info!("prices of {} models updated", updated),
+                    Err(err) => tracing::warn!("cannot sync model prices: {}", err),
+                }
+            }
+        });
+    }
+}
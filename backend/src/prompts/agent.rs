@@ -3,6 +3,7 @@ use crate::prompts::{PromptStore, PromptTemplate};
 pub struct AgentStore;
 
 impl PromptStore for AgentStore {
+    const NAME: &'static str = "agent";
     type Source = &'static str;
     type Extra = ();
     type Pipe = ();
@@ -16,7 +17,7 @@ impl PromptStore for AgentStore {
                 env!("CARGO_MANIFEST_DIR"),
                 "/../prompts/agent/zh-tw.md"
             )),
-            Some("en") | _ => include_str!(concat!(
+            _ => include_str!(concat!(
                 env!("CARGO_MANIFEST_DIR"),
                 "/../prompts/agent/zh-tw.md"
             )),
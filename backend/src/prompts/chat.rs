@@ -3,6 +3,7 @@ use crate::prompts::{PromptStore, PromptTemplate};
 pub struct ChatStore;
 
 impl PromptStore for ChatStore {
+    const NAME: &'static str = "normal";
     type Source = &'static str;
     type Extra = ();
     type Pipe = ();
@@ -16,7 +17,7 @@ impl PromptStore for ChatStore {
                 env!("CARGO_MANIFEST_DIR"),
                 "/../prompts/normal/zh-tw.md"
             )),
-            Some("en") | _ => include_str!(concat!(
+            _ => include_str!(concat!(
                 env!("CARGO_MANIFEST_DIR"),
                 "/../prompts/normal/en.md"
             )),
@@ -0,0 +1,28 @@
+use crate::prompts::{PromptStore, PromptTemplate};
+
+pub struct DelegateStore;
+
+impl PromptStore for DelegateStore {
+    const NAME: &'static str = "delegate";
+    type Source = &'static str;
+    type Extra = ();
+    type Pipe = ();
+
+    async fn template(
+        &self,
+        locale: Option<&str>,
+    ) -> anyhow::Result<super::PromptTemplate<Self::Source, Self::Extra, Self::Pipe>> {
+        let template = match locale {
+            Some("zh-tw") => include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/../prompts/delegate/zh-tw.md"
+            )),
+            _ => include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/../prompts/delegate/en.md"
+            )),
+        };
+
+        Ok(PromptTemplate::new(template))
+    }
+}
@@ -1,31 +1,76 @@
 mod agent;
 mod chat;
+mod delegate;
 mod search;
 mod title_gen;
+pub mod variant;
 
-use std::{marker::PhantomData, sync::Arc};
+use std::{collections::BTreeMap, marker::PhantomData, sync::Arc};
 
 use anyhow::{Context, Result};
-use entity::prelude::*;
+use entity::{policy, prelude::*, prompt_template, user};
 use minijinja::Environment;
-use sea_orm::{DbConn, EntityTrait};
+use sea_orm::{ColumnTrait, DbConn, DbErr, EntityTrait, QueryFilter, QueryOrder};
 use serde::Serialize;
-use time::{UtcDateTime, format_description::well_known::Rfc2822};
+use serde_json::Value;
+use time::{UtcDateTime, UtcOffset, format_description::well_known::Rfc2822};
+
+use crate::{
+    cache::Cache,
+    config::MEMORY_PROMPT_FACTS,
+    memory,
+    middlewares::locale,
+    utils::{chat_variable, preference},
+};
 
 pub use agent::AgentStore;
 pub use chat::ChatStore;
+pub use delegate::DelegateStore;
 pub use search::SearchStore;
 pub use title_gen::TitleGenStore;
 
+/// Built-in prompts an admin can replace, see `prompt_template`
+pub const NAMES: [&str; 5] = [
+    ChatStore::NAME,
+    SearchStore::NAME,
+    AgentStore::NAME,
+    TitleGenStore::NAME,
+    DelegateStore::NAME,
+];
+/// Locales the built-in prompts are written in, others use the first
+pub const LOCALES: [&str; 2] = ["en", "zh-tw"];
+/// Variables of every template, with their type
+pub const VARIABLES: [(&str, &str, &str); 9] = [
+    ("user.name", "string", "name of the owner of the chat"),
+    ("user.locale", "string", "locale of the owner, e.g. zh-tw"),
+    ("user.language", "string", "language of the locale"),
+    (
+        "user.timezone",
+        "string | none",
+        "time zone of the owner, e.g. Asia/Taipei, if known",
+    ),
+    (
+        "date",
+        "string",
+        "current date and time, RFC 2822 at the offset of the owner, else in UTC",
+    ),
+    ("chat.id", "number", "id of the chat"),
+    ("chat.title", "string | none", "title of the chat, if any"),
+    ("chat.vars", "map", "variables tools set on the chat"),
+    ("tools", "list of string", "usage hints of the tools"),
+];
+
 pub trait PromptStore {
+    /// Name of the prompt in `prompt_template`
+    const NAME: &'static str;
     type Source;
     type Extra;
     type Pipe;
 
-    async fn template(
+    fn template(
         &self,
         locale: Option<&str>,
-    ) -> Result<PromptTemplate<Self::Source, Self::Extra, Self::Pipe>>;
+    ) -> impl Future<Output = Result<PromptTemplate<Self::Source, Self::Extra, Self::Pipe>>> + Send;
 }
 
 pub struct PromptTemplate<T, E = (), P = ()> {
@@ -36,6 +81,8 @@ pub struct PromptTemplate<T, E = (), P = ()> {
 pub struct PromptEnv {
     env: Arc<Environment<'static>>,
     conn: DbConn,
+    /// Templates of admins by name, locale and workspace, see `cache`
+    stored: Cache<(String, &'static str, i32), Option<prompt_template::Model>>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -50,14 +97,38 @@ pub struct PromptContext<E = (), P = ()> {
 
 #[derive(Debug, Clone, Serialize)]
 pub struct UserInfo {
+    /// Of the user's preference, else of the request, see `middlewares::locale`
     pub locale: String,
+    pub language: String,
     pub name: String,
+    /// IANA name, see `utils::preference`
+    pub timezone: Option<String>,
+}
+
+impl UserInfo {
+    fn new(user: &user::Model) -> Self {
+        let locale = locale::of_user(user.preference.locale.as_deref());
+        Self {
+            locale: locale.code().to_owned(),
+            language: locale.language().to_owned(),
+            name: user.name.clone(),
+            timezone: user.preference.timezone.clone(),
+        }
+    }
+}
+
+/// Now in the time zone of the user, so the model tells their times
+fn date(user: &user::Model) -> Result<String> {
+    let offset = preference::utc_offset(&user.preference).unwrap_or(UtcOffset::UTC);
+    Ok(UtcDateTime::now().to_offset(offset).format(&Rfc2822)?)
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct ChatInfo {
     pub id: i32,
     pub title: Option<String>,
+    /// Set by tools, e.g. the thread being replied to
+    pub vars: BTreeMap<String, Value>,
 }
 
 impl<T, E, P> PromptTemplate<T, E, P>
@@ -73,6 +144,37 @@ where
         }
     }
 
+    /// The source of a prompt variant in place of this one, if any
+    pub fn replace(self, template: Option<String>) -> PromptTemplate<String, E, P> {
+        PromptTemplate::new(template.unwrap_or_else(|| self.template.as_ref().to_owned()))
+    }
+
+    /// The prompt of a chat after this one, if any, see `chat.system_prompt`
+    pub fn append(self, template: Option<&str>) -> PromptTemplate<String, E, P> {
+        let source = self.template.as_ref();
+        PromptTemplate::new(match template {
+            Some(template) => format!("{}\n\n{}", source, template),
+            None => source.to_owned(),
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        self.template.as_ref()
+    }
+
+    /// Stable hash of the template source, change whenever the prompt is edited
+    pub fn version(&self) -> String {
+        // FNV-1a, `DefaultHasher` is not stable across releases
+        let hash = self
+            .template
+            .as_ref()
+            .bytes()
+            .fold(0xcbf29ce484222325u64, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            });
+        format!("{:016x}", hash)
+    }
+
     pub async fn render(
         &self,
         env: &PromptEnv,
@@ -82,8 +184,26 @@ where
         pipe: P,
     ) -> Result<String> {
         let ctx = PromptContext::new(&env.conn, chat_id, tools, extra, pipe).await?;
-        let res = env.env.render_str(self.template.as_ref(), ctx)?;
-        Ok(res)
+        let template = env.env.template_from_str(self.template.as_ref())?;
+        let mut res = template.render(&ctx)?;
+
+        // answer in the language of the user even if the template does not say
+        let named = template
+            .undeclared_variables(true)
+            .iter()
+            .any(|x| x == "user.locale" || x == "user.language");
+        if !named {
+            res.push_str(&format!(
+                "\n\nAnswer in {} unless the user writes in or asks for another language.",
+                ctx.user.language
+            ));
+        }
+
+        // the policy is not a template, members cannot opt out of it
+        match env.policy().await? {
+            Some(policy) => Ok(format!("{}\n\n---\n\n{}", policy, res)),
+            None => Ok(res),
+        }
     }
 }
 
@@ -92,8 +212,164 @@ impl PromptEnv {
         Self {
             env: Arc::new(Environment::new()),
             conn,
+            stored: Cache::default(),
         }
     }
+    /// Forget the templates read, after admins wrote one
+    pub fn invalidate(&self) {
+        self.stored.clear();
+    }
+    /// Whether a template compile, before it is saved
+    pub fn check(&self, template: &str) -> Result<(), String> {
+        self.env
+            .template_from_str(template)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+    /// Whether a template compile and use only [`VARIABLES`]
+    pub fn check_variables(&self, template: &str) -> Result<(), String> {
+        let template = self
+            .env
+            .template_from_str(template)
+            .map_err(|e| e.to_string())?;
+        let mut unknown: Vec<String> = template
+            .undeclared_variables(false)
+            .into_iter()
+            .filter(|x| {
+                !VARIABLES
+                    .iter()
+                    .any(|(name, ..)| name.split('.').next() == Some(x))
+            })
+            .collect();
+        if unknown.is_empty() {
+            return Ok(());
+        }
+        unknown.sort();
+        Err(format!("unknown variables: {}", unknown.join(", ")))
+    }
+
+    /// The prompt of `store`, the one an admin wrote in its place for the
+    /// workspace or every workspace if any
+    pub async fn template<S>(
+        &self,
+        store: &S,
+        locale: Option<&str>,
+        workspace_id: i32,
+    ) -> Result<PromptTemplate<String, S::Extra, S::Pipe>>
+    where
+        S: PromptStore<Source = &'static str>,
+        S::Extra: Serialize,
+        S::Pipe: Serialize,
+    {
+        Ok(self.versioned(store, locale, workspace_id).await?.0)
+    }
+
+    /// [`Self::template`] and the id of the template version it is, None for
+    /// the built-in prompt
+    pub async fn versioned<S>(
+        &self,
+        store: &S,
+        locale: Option<&str>,
+        workspace_id: i32,
+    ) -> Result<(PromptTemplate<String, S::Extra, S::Pipe>, Option<i32>)>
+    where
+        S: PromptStore<Source = &'static str>,
+        S::Extra: Serialize,
+        S::Pipe: Serialize,
+    {
+        let builtin = store.template(locale).await?;
+        let stored = self.stored(S::NAME, locale, workspace_id).await?;
+        let version_id = stored.as_ref().and_then(|x| x.version_id);
+        Ok((builtin.replace(stored.map(|x| x.content)), version_id))
+    }
+
+    /// Template of an admin, the one of the workspace before the one of every
+    /// workspace, then the one of the locale before the one of every locale
+    async fn stored(
+        &self,
+        name: &str,
+        locale: Option<&str>,
+        workspace_id: i32,
+    ) -> Result<Option<prompt_template::Model>> {
+        let locale = locale_of(locale);
+        let load = prompt_template::Entity::find()
+            .filter(prompt_template::Column::Name.eq(name))
+            .filter(
+                prompt_template::Column::Locale
+                    .eq(locale)
+                    .or(prompt_template::Column::Locale.is_null()),
+            )
+            .filter(
+                prompt_template::Column::WorkspaceId
+                    .eq(workspace_id)
+                    .or(prompt_template::Column::WorkspaceId.is_null()),
+            )
+            .all(&self.conn);
+        let load = async {
+            Ok::<_, DbErr>(
+                load.await?
+                    .into_iter()
+                    .max_by_key(|x| (x.workspace_id.is_some(), x.locale.is_some())),
+            )
+        };
+        let key = (name.to_owned(), locale, workspace_id);
+        Ok(self.stored.get_or_load(key, load).await?)
+    }
+
+    /// Render a template as it would be for `user`, in a chat of sample values
+    pub fn preview(
+        &self,
+        template: &str,
+        user: &user::Model,
+        tools: Vec<&'static str>,
+    ) -> Result<String> {
+        let ctx = PromptContext {
+            user: UserInfo::new(user),
+            date: date(user)?,
+            chat: ChatInfo {
+                id: 0,
+                title: Some("Preview".to_owned()),
+                vars: BTreeMap::new(),
+            },
+            tools,
+            extra: (),
+            pipe: (),
+        };
+        Ok(self.env.render_str(template, ctx)?)
+    }
+
+    /// Facts kept about the user most relevant to the text, appended to the
+    /// system prompt of a reply; empty without facts
+    pub async fn memories(&self, user_id: i32, text: &str) -> Result<String> {
+        let facts = memory::relevant(&self.conn, user_id, text, MEMORY_PROMPT_FACTS).await?;
+        if facts.is_empty() {
+            return Ok(String::new());
+        }
+        let mut prompt = "\n\nFacts the user asked you to remember in earlier chats, \
+use them where they matter without repeating them back:"
+            .to_owned();
+        for x in facts {
+            prompt.push_str(&format!("\n- {}", x.content));
+        }
+        Ok(prompt)
+    }
+
+    /// Content of the latest organization-wide policy, if not empty
+    async fn policy(&self) -> Result<Option<String>> {
+        let policy = Policy::find()
+            .order_by_desc(policy::Column::Id)
+            .one(&self.conn)
+            .await?;
+        Ok(policy.map(|x| x.content).filter(|x| !x.trim().is_empty()))
+    }
+}
+
+/// Locale of [`LOCALES`] the prompts of `locale` are written in
+pub fn locale_of(locale: Option<&str>) -> &'static str {
+    LOCALES
+        .into_iter()
+        .find(|x| Some(*x) == locale)
+        .unwrap_or(LOCALES[0])
 }
 
 impl<E, P> PromptContext<E, P> {
@@ -114,14 +390,12 @@ impl<E, P> PromptContext<E, P> {
             .context("Cannot find user")?;
 
         Ok(Self {
-            user: UserInfo {
-                locale: user.preference.locale.unwrap_or("en_us".to_owned()),
-                name: user.name,
-            },
-            date: UtcDateTime::now().format(&Rfc2822)?,
+            user: UserInfo::new(&user),
+            date: date(&user)?,
             chat: ChatInfo {
                 id: chat_id,
                 title: chat.title,
+                vars: chat_variable::all(conn, chat_id).await?,
             },
             extra,
             pipe,
@@ -3,6 +3,7 @@ use crate::prompts::{PromptStore, PromptTemplate};
 pub struct SearchStore;
 
 impl PromptStore for SearchStore {
+    const NAME: &'static str = "search";
     type Source = &'static str;
     type Extra = ();
     type Pipe = ();
@@ -16,7 +17,7 @@ impl PromptStore for SearchStore {
                 env!("CARGO_MANIFEST_DIR"),
                 "/../prompts/search/zh-tw.md"
             )),
-            Some("en") | _ => include_str!(concat!(
+            _ => include_str!(concat!(
                 env!("CARGO_MANIFEST_DIR"),
                 "/../prompts/search/en.md"
             )),
@@ -3,6 +3,7 @@ use crate::prompts::{PromptStore, PromptTemplate};
 pub struct TitleGenStore;
 
 impl PromptStore for TitleGenStore {
+    const NAME: &'static str = "title_gen";
     type Source = &'static str;
     type Extra = ();
     type Pipe = ();
@@ -16,7 +17,7 @@ impl PromptStore for TitleGenStore {
                 env!("CARGO_MANIFEST_DIR"),
                 "/../prompts/title_gen/zh-tw.md"
             )),
-            Some("en") | _ => include_str!(concat!(
+            _ => include_str!(concat!(
                 env!("CARGO_MANIFEST_DIR"),
                 "/../prompts/title_gen/en.md"
             )),
@@ -0,0 +1,56 @@
+//! Prompt experiments, admins attach weighted variants of the system prompt
+//! to a model and each chat is sampled into one of them
+//!
+//! The chat keep its variant so a conversation never switch prompt midway,
+//! it is sampled again when the variant is deleted or the chat move to
+//! another model
+
+use anyhow::Result;
+use entity::{chat, prelude::*, prompt_variant};
+use sea_orm::{ActiveValue::Set, ColumnTrait, DbConn, EntityTrait, QueryFilter, QueryOrder};
+
+/// Variant of the chat, None when its model has no experiment running
+pub async fn assign(conn: &DbConn, chat: &chat::Model) -> Result<Option<prompt_variant::Model>> {
+    let variants = PromptVariant::find()
+        .filter(prompt_variant::Column::ModelId.eq(chat.model_id))
+        .order_by_asc(prompt_variant::Column::Id)
+        .all(conn)
+        .await?;
+    if let Some(current) = variants
+        .iter()
+        .find(|x| Some(x.id) == chat.prompt_variant_id)
+    {
+        return Ok(Some(current.clone()));
+    }
+
+    let Some(variant) = sample(variants) else {
+        return Ok(None);
+    };
+    Chat::update(chat::ActiveModel {
+        id: Set(chat.id),
+        prompt_variant_id: Set(Some(variant.id)),
+        ..Default::default()
+    })
+    .exec(conn)
+    .await?;
+    Ok(Some(variant))
+}
+
+/// Pick by weight, variants of weight 0 are kept for existing chats only
+fn sample(variants: Vec<prompt_variant::Model>) -> Option<prompt_variant::Model> {
+    let total: u32 = variants.iter().map(|x| x.weight.max(0) as u32).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut pick = fastrand::u32(..total);
+    variants.into_iter().find(|x| {
+        let weight = x.weight.max(0) as u32;
+        match pick < weight {
+            true => true,
+            false => {
+                pick -= weight;
+                false
+            }
+        }
+    })
+}
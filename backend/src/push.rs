@@ -0,0 +1,331 @@
+//! Web Push to the browsers of a user who has no stream open
+//!
+//! A browser subscribes with the VAPID public key of the instance
+//! (`user/push/key`) and registers the endpoint its push service gave it with
+//! `user/push/subscribe`. When a reply ends or a scheduled task runs while the
+//! user follows neither a chat nor their notifications on this instance (see
+//! `notify`), a job of `jobs` is queued per browser, so a message survives
+//! restarts and is retried with the backoff of the queue. The message is
+//! encrypted for the browser (RFC 8291, `aes128gcm`) and the request signed
+//! with the VAPID key (RFC 8292); a push service answering 404 or 410 forgot
+//! the browser, whose subscription is then deleted. Users turn either kind
+//! off with `notify_replies` and `notify_schedules` of their preference.
+//!
+//! The key pair is generated on first start and kept in `config`, unless
+//! `VAPID_PRIVATE_KEY` and `VAPID_PUBLIC_KEY` give one, as the base64url of
+//! the raw scalar and of the uncompressed point that most tools print
+
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use dotenv::var;
+use entity::{ChunkKind, UserPreference, chunk, config, prelude::*, push_subscription};
+use ring::{
+    aead, agreement, hkdf,
+    rand::{SecureRandom, SystemRandom},
+    signature::{ECDSA_P256_SHA256_FIXED_SIGNING, EcdsaKeyPair, KeyPair},
+};
+use sea_orm::{ActiveValue::Set, DbConn, QueryOrder, prelude::*};
+use serde::Serialize;
+
+use crate::{
+    AppState,
+    config::{PUSH_BODY_CHARS, PUSH_TIMEOUT, PUSH_TTL},
+    jobs::Task,
+    middlewares::auth,
+    notify::NotificationScheduleRun,
+    sse::EndKind,
+};
+
+/// PKCS#8 document of the generated key pair
+const KEY: &str = "vapid_key";
+/// Seconds a signed request is valid, push services refuse more than a day
+const VAPID_EXP: i64 = 12 * 3600;
+/// Size of a record, a message always fits in one
+const RECORD_SIZE: u32 = 4096;
+
+/// What the service worker of the frontend shows
+#[derive(Debug, Serialize)]
+pub struct Message {
+    pub title: String,
+    pub body: String,
+    /// Path opened by a click
+    pub url: String,
+    /// A notification replaces the shown one of the same tag
+    pub tag: String,
+}
+
+pub struct Push {
+    key: EcdsaKeyPair,
+    /// Uncompressed public key in base64url, the `applicationServerKey` of
+    /// browsers
+    public_key: String,
+    /// `VAPID_SUBJECT`, a `mailto:` or `https:` URL where push services reach
+    /// the admin
+    subject: Option<String>,
+    rng: SystemRandom,
+}
+
+impl Push {
+    pub async fn load(conn: &DbConn) -> Result<Self> {
+        let rng = SystemRandom::new();
+        let key = match (var("VAPID_PRIVATE_KEY"), var("VAPID_PUBLIC_KEY")) {
+            (Ok(private), Ok(public)) => EcdsaKeyPair::from_private_key_and_public_key(
+                &ECDSA_P256_SHA256_FIXED_SIGNING,
+                &URL_SAFE_NO_PAD.decode(private.trim())?,
+                &URL_SAFE_NO_PAD.decode(public.trim())?,
+                &rng,
+            )
+            .map_err(|e| anyhow!("Invalid VAPID_PRIVATE_KEY or VAPID_PUBLIC_KEY: {}", e))?,
+            (Err(_), Err(_)) => {
+                let pkcs8 = stored_key(conn, &rng).await?;
+                EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &pkcs8, &rng)
+                    .map_err(|e| anyhow!("Invalid stored VAPID key: {}", e))?
+            }
+            _ => bail!("VAPID_PRIVATE_KEY and VAPID_PUBLIC_KEY go together"),
+        };
+        let public_key = URL_SAFE_NO_PAD.encode(key.public_key().as_ref());
+        Ok(Self {
+            key,
+            public_key,
+            subject: var("VAPID_SUBJECT").ok().filter(|x| !x.trim().is_empty()),
+            rng,
+        })
+    }
+
+    pub fn public_key(&self) -> &str {
+        &self.public_key
+    }
+
+    /// `Authorization` header of a request to `endpoint`
+    fn authorization(&self, endpoint: &url::Url) -> Result<String> {
+        let header = URL_SAFE_NO_PAD.encode(r#"{"typ":"JWT","alg":"ES256"}"#);
+        let mut claims = serde_json::json!({
+            "aud": endpoint.origin().ascii_serialization(),
+            "exp": now() + VAPID_EXP,
+        });
+        if let Some(subject) = &self.subject {
+            claims["sub"] = subject.clone().into();
+        }
+        let claims = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims)?);
+        let signed = format!("{}.{}", header, claims);
+        let signature = self
+            .key
+            .sign(&self.rng, signed.as_bytes())
+            .map_err(|_| anyhow!("Cannot sign VAPID token"))?;
+        Ok(format!(
+            "vapid t={}.{}, k={}",
+            signed,
+            URL_SAFE_NO_PAD.encode(signature.as_ref()),
+            self.public_key
+        ))
+    }
+
+    /// `payload` encrypted for a browser, as the body of a request
+    fn encrypt(&self, sub: &push_subscription::Model, payload: &[u8]) -> Result<Vec<u8>> {
+        let ua_public = URL_SAFE_NO_PAD.decode(&sub.p256dh)?;
+        let auth = URL_SAFE_NO_PAD.decode(&sub.auth)?;
+        let fail = |_| anyhow!("Cannot encrypt push message");
+
+        let private = agreement::EphemeralPrivateKey::generate(&agreement::ECDH_P256, &self.rng)
+            .map_err(fail)?;
+        let as_public = private.compute_public_key().map_err(fail)?;
+        let peer = agreement::UnparsedPublicKey::new(&agreement::ECDH_P256, &ua_public);
+        let shared = agreement::agree_ephemeral(private, &peer, |x| x.to_vec()).map_err(fail)?;
+
+        // the input key of the browser and of this message, then the key and
+        // nonce of the content
+        let mut info = b"WebPush: info\0".to_vec();
+        info.extend_from_slice(&ua_public);
+        info.extend_from_slice(as_public.as_ref());
+        let ikm = expand(&auth, &shared, &info, 32)?;
+        let mut salt = [0u8; 16];
+        self.rng.fill(&mut salt).map_err(fail)?;
+        let cek = expand(&salt, &ikm, b"Content-Encoding: aes128gcm\0", 16)?;
+        let nonce = expand(&salt, &ikm, b"Content-Encoding: nonce\0", 12)?;
+
+        let key = aead::UnboundKey::new(&aead::AES_128_GCM, &cek).map_err(fail)?;
+        let nonce = aead::Nonce::try_assume_unique_for_key(&nonce).map_err(fail)?;
+        // the delimiter of the last record, without padding
+        let mut content = payload.to_vec();
+        content.push(2);
+        aead::LessSafeKey::new(key)
+            .seal_in_place_append_tag(nonce, aead::Aad::empty(), &mut content)
+            .map_err(fail)?;
+
+        let mut body = salt.to_vec();
+        body.extend_from_slice(&RECORD_SIZE.to_be_bytes());
+        body.push(as_public.as_ref().len() as u8);
+        body.extend_from_slice(as_public.as_ref());
+        body.extend_from_slice(&content);
+        Ok(body)
+    }
+}
+
+async fn stored_key(conn: &DbConn, rng: &SystemRandom) -> Result<Vec<u8>> {
+    if let Some(x) = Config::find_by_id(KEY).one(conn).await? {
+        return Ok(x.value);
+    }
+    let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, rng)
+        .map_err(|_| anyhow!("Cannot generate VAPID key"))?;
+    Config::insert(config::ActiveModel {
+        key: Set(KEY.to_owned()),
+        value: Set(pkcs8.as_ref().to_vec()),
+    })
+    .exec(conn)
+    .await?;
+    Ok(pkcs8.as_ref().to_vec())
+}
+
+/// HKDF-SHA256 of `ikm` salted with `salt`, `len` bytes for `info`
+fn expand(salt: &[u8], ikm: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>> {
+    struct Len(usize);
+    impl hkdf::KeyType for Len {
+        fn len(&self) -> usize {
+            self.0
+        }
+    }
+    let mut out = vec![0; len];
+    hkdf::Salt::new(hkdf::HKDF_SHA256, salt)
+        .extract(ikm)
+        .expand(&[info], Len(len))
+        .and_then(|x| x.fill(&mut out))
+        .map_err(|_| anyhow!("Cannot derive push key"))?;
+    Ok(out)
+}
+
+/// Queue the reply `message_id` that ended as `kind` to the browsers of the
+/// user who sent the message, unless they are watching or halted it
+pub async fn message_completed(
+    app: &AppState,
+    user_id: i32,
+    chat_id: i32,
+    message_id: i32,
+    kind: EndKind,
+) -> Result<()> {
+    if matches!(kind, EndKind::Halt) || app.notifier.is_online(user_id) {
+        return Ok(());
+    }
+    if !wanted(app, user_id, |x| x.notify_replies).await? {
+        return Ok(());
+    }
+    let title = Chat::find_by_id(chat_id)
+        .one(&app.conn)
+        .await?
+        .and_then(|x| x.title);
+    let text: String = Chunk::find()
+        .filter(chunk::Column::MessageId.eq(message_id))
+        .filter(chunk::Column::Kind.eq(ChunkKind::Text))
+        .order_by_asc(chunk::Column::Id)
+        .all(&app.conn)
+        .await?
+        .into_iter()
+        .map(|x| x.content)
+        .collect();
+    let message = Message {
+        title: title.unwrap_or("Llumen".to_owned()),
+        body: excerpt(&text),
+        url: format!("/chat/{}", chat_id),
+        tag: format!("chat-{}", chat_id),
+    };
+    queue(app, user_id, message).await
+}
+
+/// The reply of a run that started follows with its own message, under the
+/// same tag
+pub async fn schedule_run(
+    app: &AppState,
+    user_id: i32,
+    run: &NotificationScheduleRun,
+) -> Result<()> {
+    if app.notifier.is_online(user_id) || !wanted(app, user_id, |x| x.notify_schedules).await? {
+        return Ok(());
+    }
+    let (url, tag) = match run.chat_id {
+        Some(chat_id) => (format!("/chat/{}", chat_id), format!("chat-{}", chat_id)),
+        None => ("/".to_owned(), format!("schedule-{}", run.schedule_id)),
+    };
+    let message = Message {
+        title: run.name.clone(),
+        body: run.error.clone().unwrap_or_default(),
+        url,
+        tag,
+    };
+    queue(app, user_id, message).await
+}
+
+/// Whether the user kept a kind of notification on, as they are by default
+async fn wanted(
+    app: &AppState,
+    user_id: i32,
+    kind: impl Fn(&UserPreference) -> Option<bool>,
+) -> Result<bool> {
+    Ok(auth::user(app, user_id)
+        .await?
+        .is_none_or(|x| kind(&x.preference) != Some(false)))
+}
+
+fn excerpt(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match text.char_indices().nth(PUSH_BODY_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    }
+}
+
+async fn queue(app: &AppState, user_id: i32, message: Message) -> Result<()> {
+    let subscriptions = PushSubscription::find()
+        .filter(push_subscription::Column::UserId.eq(user_id))
+        .all(&app.conn)
+        .await?;
+    if subscriptions.is_empty() {
+        return Ok(());
+    }
+    let body = serde_json::to_string(&message)?;
+    for sub in subscriptions {
+        let task = Task::Push {
+            subscription_id: sub.id,
+            body: body.clone(),
+        };
+        app.jobs.push(&app.conn, task).await?;
+    }
+    Ok(())
+}
+
+/// Run as a job of `jobs`, dropped if the browser unsubscribed since
+pub async fn deliver(app: &AppState, subscription_id: i32, body: String) -> Result<()> {
+    let Some(sub) = PushSubscription::find_by_id(subscription_id)
+        .one(&app.conn)
+        .await?
+    else {
+        return Ok(());
+    };
+    let endpoint = url::Url::parse(&sub.endpoint).context("Invalid push endpoint")?;
+    let res = reqwest::Client::new()
+        .post(endpoint.clone())
+        .timeout(Duration::from_secs(PUSH_TIMEOUT))
+        .header(
+            http::header::AUTHORIZATION,
+            app.push.authorization(&endpoint)?,
+        )
+        .header(http::header::CONTENT_ENCODING, "aes128gcm")
+        .header(http::header::CONTENT_TYPE, "application/octet-stream")
+        .header("TTL", PUSH_TTL)
+        .body(app.push.encrypt(&sub, body.as_bytes())?)
+        .send()
+        .await?;
+    if matches!(res.status().as_u16(), 404 | 410) {
+        PushSubscription::delete_by_id(subscription_id)
+            .exec(&app.conn)
+            .await?;
+        tracing::info!("push subscription {} expired", subscription_id);
+        return Ok(());
+    }
+    res.error_for_status()?;
+    Ok(())
+}
+
+fn now() -> i64 {
+    time::UtcDateTime::now().unix_timestamp()
+}
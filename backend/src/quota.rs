@@ -0,0 +1,277 @@
+//! Per-user usage quotas, see `QUOTA_DAILY_*` and `QUOTA_MONTHLY_*` env
+//!
+//! Replies asked for, tokens and tool calls are counted per UTC day in
+//! `usage`, a month is the sum of its days. The limits of the env apply to
+//! every user, an admin can override them per user and admins themselves are
+//! never limited. Quotas are checked before a reply starts, a reply started
+//! within them runs to its end
+
+use anyhow::Result;
+use dotenv::var;
+use entity::{prelude::*, quota, usage};
+use schemars::JsonSchema;
+use sea_orm::{
+    ActiveValue::Set,
+    ConnectionTrait, QuerySelect,
+    prelude::*,
+    sea_query::{Expr, OnConflict},
+};
+use serde::{Deserialize, Serialize};
+use time::{Date, Month, Time, UtcDateTime};
+use typeshare::typeshare;
+
+const DAY: i64 = 24 * 3600;
+
+/// Limits of one period, None for no limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct QuotaLimit {
+    pub messages: Option<u32>,
+    pub tokens: Option<i64>,
+    pub tool_calls: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[typeshare]
+pub struct QuotaLimits {
+    pub daily: QuotaLimit,
+    pub monthly: QuotaLimit,
+}
+
+impl QuotaLimit {
+    fn or(self, other: Self) -> Self {
+        Self {
+            messages: self.messages.or(other.messages),
+            tokens: self.tokens.or(other.tokens),
+            tool_calls: self.tool_calls.or(other.tool_calls),
+        }
+    }
+}
+
+/// Counts of one period
+#[derive(Debug, Clone, Copy, Default, Serialize, JsonSchema)]
+#[typeshare]
+pub struct QuotaUsed {
+    pub messages: u32,
+    pub tokens: i64,
+    pub tool_calls: u32,
+}
+
+impl QuotaUsed {
+    fn add(&mut self, other: &Self) {
+        self.messages += other.messages;
+        self.tokens += other.tokens;
+        self.tool_calls += other.tool_calls;
+    }
+}
+
+pub struct Quotas {
+    defaults: QuotaLimits,
+}
+
+impl Quotas {
+    /// Unset or unparsable limits are no limit
+    pub fn from_env() -> Self {
+        fn limit(period: &str) -> QuotaLimit {
+            let get = |name: &str| var(format!("QUOTA_{}_{}", period, name)).ok();
+            QuotaLimit {
+                messages: get("MESSAGES").and_then(|x| x.parse().ok()),
+                tokens: get("TOKENS").and_then(|x| x.parse().ok()),
+                tool_calls: get("TOOL_CALLS").and_then(|x| x.parse().ok()),
+            }
+        }
+        Self {
+            defaults: QuotaLimits {
+                daily: limit("DAILY"),
+                monthly: limit("MONTHLY"),
+            },
+        }
+    }
+
+    pub fn defaults(&self) -> QuotaLimits {
+        self.defaults
+    }
+
+    /// Limits of the user, the override of an admin or the env
+    pub async fn limits(&self, conn: &impl ConnectionTrait, user_id: i32) -> Result<QuotaLimits> {
+        let own = get_override(conn, user_id).await?;
+        Ok(QuotaLimits {
+            daily: own.daily.or(self.defaults.daily),
+            monthly: own.monthly.or(self.defaults.monthly),
+        })
+    }
+
+    /// Why the user cannot ask for another reply, if they cannot
+    pub async fn exceeded(
+        &self,
+        conn: &impl ConnectionTrait,
+        user_id: i32,
+    ) -> Result<Option<String>> {
+        let limits = self.limits(conn, user_id).await?;
+        if limits == QuotaLimits::default() {
+            return Ok(None);
+        }
+        let (today, month) = used(conn, user_id).await?;
+        Ok(over(&limits.daily, &today)
+            .map(|x| format!("Daily quota of {} used up", x))
+            .or_else(|| {
+                over(&limits.monthly, &month).map(|x| format!("Monthly quota of {} used up", x))
+            }))
+    }
+}
+
+fn over(limit: &QuotaLimit, used: &QuotaUsed) -> Option<&'static str> {
+    if limit.messages.is_some_and(|x| used.messages >= x) {
+        return Some("messages");
+    }
+    if limit.tokens.is_some_and(|x| used.tokens >= x) {
+        return Some("tokens");
+    }
+    if limit.tool_calls.is_some_and(|x| used.tool_calls >= x) {
+        return Some("tool calls");
+    }
+    None
+}
+
+/// Start of the current UTC day and month, and when they end, unix seconds
+pub fn periods() -> ((i64, i64), (i64, i64)) {
+    let now = UtcDateTime::now();
+    let day = now.replace_time(Time::MIDNIGHT).unix_timestamp();
+    let first = |year, month| {
+        Date::from_calendar_date(year, month, 1)
+            .unwrap()
+            .midnight()
+            .as_utc()
+            .unix_timestamp()
+    };
+    let (year, month) = (now.year(), now.month());
+    let next = match month {
+        Month::December => first(year + 1, Month::January),
+        _ => first(year, month.next()),
+    };
+    ((day, day + DAY), (first(year, month), next))
+}
+
+/// Counts of the user today and this month
+pub async fn used(conn: &impl ConnectionTrait, user_id: i32) -> Result<(QuotaUsed, QuotaUsed)> {
+    let ((day, _), (month, _)) = periods();
+    let rows: Vec<(i64, i32, i64, i32)> = Usage::find()
+        .select_only()
+        .column(usage::Column::Day)
+        .column(usage::Column::Messages)
+        .column(usage::Column::Tokens)
+        .column(usage::Column::ToolCalls)
+        .filter(usage::Column::UserId.eq(user_id))
+        .filter(usage::Column::Day.gte(month))
+        .into_tuple()
+        .all(conn)
+        .await?;
+    let mut today = QuotaUsed::default();
+    let mut total = QuotaUsed::default();
+    for (at, messages, tokens, tool_calls) in rows {
+        let row = QuotaUsed {
+            messages: messages as u32,
+            tokens,
+            tool_calls: tool_calls as u32,
+        };
+        total.add(&row);
+        if at == day {
+            today = row;
+        }
+    }
+    Ok((today, total))
+}
+
+/// Add to the counts of the user today
+pub async fn add(
+    conn: &impl ConnectionTrait,
+    user_id: i32,
+    messages: i32,
+    tokens: i64,
+    tool_calls: i32,
+) -> Result<(), DbErr> {
+    let ((day, _), _) = periods();
+    let col = |x: usage::Column| Expr::col((usage::Entity, x));
+    Usage::insert(usage::ActiveModel {
+        user_id: Set(user_id),
+        day: Set(day),
+        messages: Set(messages),
+        tokens: Set(tokens),
+        tool_calls: Set(tool_calls),
+    })
+    .on_conflict(
+        OnConflict::columns([usage::Column::UserId, usage::Column::Day])
+            .value(
+                usage::Column::Messages,
+                col(usage::Column::Messages).add(messages),
+            )
+            .value(
+                usage::Column::Tokens,
+                col(usage::Column::Tokens).add(tokens),
+            )
+            .value(
+                usage::Column::ToolCalls,
+                col(usage::Column::ToolCalls).add(tool_calls),
+            )
+            .to_owned(),
+    )
+    .exec(conn)
+    .await?;
+    Ok(())
+}
+
+/// Replace the override of an admin, a limit left None follow the env
+pub async fn set_override(
+    conn: &impl ConnectionTrait,
+    user_id: i32,
+    limits: QuotaLimits,
+) -> Result<(), DbErr> {
+    if limits == QuotaLimits::default() {
+        Quota::delete_by_id(user_id).exec(conn).await?;
+        return Ok(());
+    }
+    let (daily, monthly) = (limits.daily, limits.monthly);
+    Quota::insert(quota::ActiveModel {
+        user_id: Set(user_id),
+        daily_messages: Set(daily.messages.map(|x| x as i32)),
+        daily_tokens: Set(daily.tokens),
+        daily_tool_calls: Set(daily.tool_calls.map(|x| x as i32)),
+        monthly_messages: Set(monthly.messages.map(|x| x as i32)),
+        monthly_tokens: Set(monthly.tokens),
+        monthly_tool_calls: Set(monthly.tool_calls.map(|x| x as i32)),
+    })
+    .on_conflict(
+        OnConflict::column(quota::Column::UserId)
+            .update_columns([
+                quota::Column::DailyMessages,
+                quota::Column::DailyTokens,
+                quota::Column::DailyToolCalls,
+                quota::Column::MonthlyMessages,
+                quota::Column::MonthlyTokens,
+                quota::Column::MonthlyToolCalls,
+            ])
+            .to_owned(),
+    )
+    .exec(conn)
+    .await?;
+    Ok(())
+}
+
+/// The override of an admin alone, without the env
+pub async fn get_override(conn: &impl ConnectionTrait, user_id: i32) -> Result<QuotaLimits, DbErr> {
+    let Some(x) = Quota::find_by_id(user_id).one(conn).await? else {
+        return Ok(QuotaLimits::default());
+    };
+    Ok(QuotaLimits {
+        daily: QuotaLimit {
+            messages: x.daily_messages.map(|x| x as u32),
+            tokens: x.daily_tokens,
+            tool_calls: x.daily_tool_calls.map(|x| x as u32),
+        },
+        monthly: QuotaLimit {
+            messages: x.monthly_messages.map(|x| x as u32),
+            tokens: x.monthly_tokens,
+            tool_calls: x.monthly_tool_calls.map(|x| x as u32),
+        },
+    })
+}
@@ -0,0 +1,47 @@
+//! What every reply cost and how fast it came, for `admin/stats`
+//!
+//! A row of `reply_stat` is written as a reply ends, apart from the message
+//! so deleting chats keeps them counted, and purged after [`STATS_DAYS`]
+
+use anyhow::Result;
+use entity::{ToolNames, prelude::*, reply_stat};
+use sea_orm::{ActiveValue::Set, ColumnTrait, DbConn, EntityTrait, QueryFilter};
+
+use crate::config::STATS_DAYS;
+
+pub struct ReplyRecord {
+    pub model_id: String,
+    pub tokens: i64,
+    pub cost: f64,
+    pub ttft_ms: i64,
+    pub tools: Vec<String>,
+    pub failed: bool,
+}
+
+pub async fn record(conn: &DbConn, record: ReplyRecord) -> Result<()> {
+    ReplyStat::insert(reply_stat::ActiveModel {
+        model_id: Set(record.model_id),
+        tokens: Set(record.tokens),
+        cost: Set(record.cost),
+        ttft_ms: Set(record.ttft_ms),
+        tools: Set(ToolNames(record.tools)),
+        failed: Set(record.failed),
+        created_at: Set(now()),
+        ..Default::default()
+    })
+    .exec_without_returning(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn purge_due(conn: &DbConn) -> Result<()> {
+    ReplyStat::delete_many()
+        .filter(reply_stat::Column::CreatedAt.lt(now() - i64::from(STATS_DAYS) * 24 * 3600))
+        .exec(conn)
+        .await?;
+    Ok(())
+}
+
+fn now() -> i64 {
+    time::UtcDateTime::now().unix_timestamp()
+}
@@ -0,0 +1,373 @@
+//! Deletion of idle chats, see `RETENTION_DAYS` env
+//!
+//! The instance has a policy: admins can override the default of the env with
+//! a number of days or keep chats forever. Users may keep their own chats for
+//! less with `UserPreference::retention_days`, never for more. A chat is idle
+//! since its latest message; its owner is told [`RETENTION_GRACE_SECS`] before
+//! it is deleted, and a new message keep it. Pinned chats are never deleted.
+//!
+//! The output of tool calls older than `TOOL_LOG_DAYS` is emptied in the same
+//! sweep, the calls themselves are kept
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+    sync::RwLock,
+};
+
+use anyhow::Result;
+use entity::{ChunkKind, chat, chunk, config, message, prelude::*};
+use sea_orm::{
+    ActiveValue::Set, ConnectionTrait, DbConn, JoinType, QuerySelect, prelude::*,
+    sea_query::OnConflict,
+};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    config::{RETENTION_GRACE_SECS, RETENTION_NOTICE_TITLES, TOOL_LOG_DAYS},
+    notify::mailer::Mail,
+    utils::sql,
+};
+
+const POLICY_KEY: &str = "retention";
+
+/// What the output of a purged tool call is replaced with
+const PURGED: &str = "[removed by the retention policy]";
+
+/// How long idle chats are kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[typeshare]
+#[serde(tag = "t", content = "c", rename_all = "snake_case")]
+pub enum RetentionPolicy {
+    /// Follow `RETENTION_DAYS`, forever if unset
+    Default,
+    Forever,
+    /// Delete chats without new messages for this many days
+    Days(u32),
+}
+
+pub struct Retention {
+    conn: DbConn,
+    policy: RwLock<RetentionPolicy>,
+    /// `RETENTION_DAYS`
+    default_days: Option<u32>,
+    /// `TOOL_LOG_DAYS`, None keeps tool outputs forever
+    tool_log_days: Option<u32>,
+}
+
+impl Retention {
+    /// Restore the policy saved by [`Retention::set_policy`]
+    pub async fn load(conn: DbConn) -> Result<Self> {
+        let policy = match Config::find_by_id(POLICY_KEY).one(&conn).await? {
+            Some(x) => serde_json::from_slice(&x.value)?,
+            None => RetentionPolicy::Default,
+        };
+        let default_days = dotenv::var("RETENTION_DAYS")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .filter(|x| *x > 0);
+        let tool_log_days = match dotenv::var("TOOL_LOG_DAYS") {
+            Ok(x) => x.parse().ok().filter(|x| *x > 0),
+            Err(_) => Some(TOOL_LOG_DAYS),
+        };
+        Ok(Self {
+            conn,
+            policy: RwLock::new(policy),
+            default_days,
+            tool_log_days,
+        })
+    }
+
+    pub fn policy(&self) -> RetentionPolicy {
+        *self.policy.read().unwrap()
+    }
+
+    pub fn default_days(&self) -> Option<u32> {
+        self.default_days
+    }
+
+    /// Days chats are kept under the current policy, None for forever
+    pub fn days(&self) -> Option<u32> {
+        match self.policy() {
+            RetentionPolicy::Default => self.default_days,
+            RetentionPolicy::Forever => None,
+            RetentionPolicy::Days(days) => Some(days),
+        }
+    }
+
+    /// Chats noticed under a shorter policy still wait for the grace period
+    pub async fn set_policy(&self, policy: RetentionPolicy) -> Result<()> {
+        Config::insert(config::ActiveModel {
+            key: Set(POLICY_KEY.to_owned()),
+            value: Set(serde_json::to_vec(&policy)?),
+        })
+        .on_conflict(
+            OnConflict::column(config::Column::Key)
+                .update_column(config::Column::Value)
+                .to_owned(),
+        )
+        .exec(&self.conn)
+        .await?;
+        *self.policy.write().unwrap() = policy;
+        Ok(())
+    }
+}
+
+/// Days of `UserPreference::retention_days`, None if empty or not a
+/// positive number
+pub fn user_days(preference: &str) -> Option<u32> {
+    preference.trim().parse().ok().filter(|x| *x > 0)
+}
+
+/// The shorter of two policies, None being forever
+fn shortest(a: Option<u32>, b: Option<u32>) -> Option<u32> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Owners of the chats a policy applies to
+enum Owners {
+    Only(HashSet<i32>),
+    Except(HashSet<i32>),
+}
+
+impl Owners {
+    fn has(&self, owner_id: i32) -> bool {
+        match self {
+            Owners::Only(x) => x.contains(&owner_id),
+            Owners::Except(x) => !x.contains(&owner_id),
+        }
+    }
+}
+
+/// When a chat noticed at `notice_at` is deleted
+pub fn delete_at(notice_at: i64) -> i64 {
+    notice_at + RETENTION_GRACE_SECS
+}
+
+/// Unpinned chats of `owners` whose latest message is older than `before`, a
+/// chat without message is idle since ever
+async fn idle(
+    conn: &DbConn,
+    owners: &Owners,
+    before: i64,
+    noticed: bool,
+) -> Result<Vec<chat::Model>> {
+    let notice = match noticed {
+        true => "chat.retention_notice_at IS NOT NULL",
+        false => "chat.retention_notice_at IS NULL",
+    };
+    Ok(Chat::find()
+        .from_raw_sql(sql::statement(
+            conn.get_database_backend(),
+            format!(
+                "SELECT chat.* FROM chat
+                LEFT JOIN (SELECT chat_id, MAX(created_at) AS last_at FROM message GROUP BY chat_id) latest
+                    ON latest.chat_id = chat.id
+                WHERE COALESCE(latest.last_at, 0) < ? AND NOT chat.pinned AND {}",
+                notice
+            ),
+            [before.into()],
+        ))
+        .all(conn)
+        .await?
+        .into_iter()
+        .filter(|x| owners.has(x.owner_id))
+        .collect())
+}
+
+/// Notice the owners of idle chats, delete those past their grace period and
+/// empty old tool outputs, run every `RETENTION_INTERVAL` by `jobs`
+pub async fn sweep(app: &Arc<AppState>) -> Result<()> {
+    let instance = app.retention.days();
+    let mut personal: HashMap<Option<u32>, HashSet<i32>> = HashMap::new();
+    for user in User::find().all(&app.conn).await? {
+        let days = user
+            .preference
+            .retention_days
+            .as_deref()
+            .and_then(user_days);
+        let days = shortest(instance, days);
+        if days != instance {
+            personal.entry(days).or_default().insert(user.id);
+        }
+    }
+
+    let others = personal.values().flatten().copied().collect();
+    sweep_policy(app, instance, Owners::Except(others)).await?;
+    for (days, owners) in personal {
+        sweep_policy(app, days, Owners::Only(owners)).await?;
+    }
+
+    if let Some(days) = app.retention.tool_log_days {
+        purge_tool_logs(&app.conn, days).await?;
+    }
+    Ok(())
+}
+
+/// Sweep the chats of `owners` under a policy of `days`
+async fn sweep_policy(app: &Arc<AppState>, days: Option<u32>, owners: Owners) -> Result<()> {
+    let conn = &app.conn;
+    let now = time::UtcDateTime::now().unix_timestamp();
+    let noticed: Vec<i32> = Chat::find()
+        .filter(chat::Column::RetentionNoticeAt.is_not_null())
+        .all(conn)
+        .await?
+        .into_iter()
+        .filter(|x| owners.has(x.owner_id))
+        .map(|x| x.id)
+        .collect();
+    let Some(days) = days else {
+        // nothing expire, the notices sent are void
+        return clear_notices(conn, noticed).await;
+    };
+    let keep = days as i64 * 24 * 3600;
+    let notice_before = now - (keep - RETENTION_GRACE_SECS).max(0);
+
+    // chats with a new message, under a longer policy or pinned since are kept
+    // again
+    let still: HashSet<i32> = idle(conn, &owners, notice_before, true)
+        .await?
+        .into_iter()
+        .map(|x| x.id)
+        .collect();
+    clear_notices(
+        conn,
+        noticed.into_iter().filter(|x| !still.contains(x)).collect(),
+    )
+    .await?;
+
+    let expired: Vec<i32> = idle(conn, &owners, now - keep, true)
+        .await?
+        .into_iter()
+        .filter(|x| x.retention_notice_at.is_some_and(|at| delete_at(at) <= now))
+        .map(|x| x.id)
+        .collect();
+    if !expired.is_empty() {
+        Chat::delete_many()
+            .filter(chat::Column::Id.is_in(expired.clone()))
+            .exec(conn)
+            .await?;
+        tracing::info!("deleted {} chats past the retention policy", expired.len());
+    }
+
+    let noticed = idle(conn, &owners, notice_before, false).await?;
+    if noticed.is_empty() {
+        return Ok(());
+    }
+    Chat::update_many()
+        .col_expr(chat::Column::RetentionNoticeAt, Expr::value(now))
+        .filter(chat::Column::Id.is_in(noticed.iter().map(|x| x.id)))
+        .exec(conn)
+        .await?;
+
+    let mut owners: BTreeMap<i32, Vec<chat::Model>> = BTreeMap::new();
+    for chat in noticed {
+        owners.entry(chat.owner_id).or_default().push(chat);
+    }
+    for (owner_id, chats) in owners {
+        notify(app, owner_id, days, &chats).await;
+    }
+    Ok(())
+}
+
+async fn clear_notices(conn: &DbConn, ids: Vec<i32>) -> Result<()> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+    Chat::update_many()
+        .col_expr(
+            chat::Column::RetentionNoticeAt,
+            Expr::value(Option::<i64>::None),
+        )
+        .filter(chat::Column::Id.is_in(ids))
+        .exec(conn)
+        .await?;
+    Ok(())
+}
+
+/// Replace the output of tool calls older than `days` with [`PURGED`], except
+/// in pinned chats
+async fn purge_tool_logs(conn: &DbConn, days: u32) -> Result<()> {
+    let before = time::UtcDateTime::now().unix_timestamp() - days as i64 * 24 * 3600;
+    let chunks = Chunk::find()
+        .join(JoinType::InnerJoin, chunk::Relation::Message.def())
+        .join(JoinType::InnerJoin, message::Relation::Chat.def())
+        .filter(chunk::Column::Kind.eq(ChunkKind::ToolCall))
+        .filter(message::Column::CreatedAt.lt(before))
+        .filter(chat::Column::Pinned.eq(false))
+        // purged ones end with it, `ToolCall::content` is the last field
+        .filter(chunk::Column::Content.not_like(format!("%\"content\":\"{}\"}}", PURGED)))
+        .all(conn)
+        .await?;
+    let mut purged = 0;
+    for chunk in chunks {
+        let Ok(mut call) = chunk.as_tool_call() else {
+            continue;
+        };
+        if call.content == PURGED {
+            continue;
+        }
+        call.content = PURGED.to_owned();
+        Chunk::update_many()
+            .col_expr(chunk::Column::Content, serde_json::to_string(&call)?.into())
+            .filter(chunk::Column::Id.eq(chunk.id))
+            .exec(conn)
+            .await?;
+        purged += 1;
+    }
+    if purged > 0 {
+        tracing::info!("emptied {} tool outputs past {} days", purged, days);
+    }
+    Ok(())
+}
+
+/// Mail the owner if it has a verified address, the chat list show the date
+/// either way
+async fn notify(app: &Arc<AppState>, owner_id: i32, days: u32, chats: &[chat::Model]) {
+    let Some(mailer) = app.mailer.as_ref() else {
+        return;
+    };
+    let user = match User::find_by_id(owner_id).one(&app.conn).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return,
+        Err(err) => {
+            tracing::warn!("cannot find user {}: {}", owner_id, err);
+            return;
+        }
+    };
+    let Some(email) = user.email.filter(|_| user.email_verified) else {
+        return;
+    };
+
+    let mut titles: Vec<String> = chats
+        .iter()
+        .take(RETENTION_NOTICE_TITLES)
+        .map(|x| x.title.as_deref().unwrap_or("Untitled").to_owned())
+        .collect();
+    if chats.len() > RETENTION_NOTICE_TITLES {
+        titles.push(format!(
+            "and {} more",
+            chats.len() - RETENTION_NOTICE_TITLES
+        ));
+    }
+    let mail = Mail::new("Your idle chats will be deleted")
+        .text(format!(
+            "{} of your llumen chats have had no new message for a while. Chats are \
+             kept {} days on this instance, they will be deleted in {} days:",
+            chats.len(),
+            days,
+            RETENTION_GRACE_SECS / (24 * 3600),
+        ))
+        .list(titles)
+        .text("Send a message in a chat to keep it, or export it first.")
+        .link("Open llumen", mailer.link("/chat"));
+    let mail = mailer.task(email, &mail);
+    if let Err(err) = app.jobs.push(&app.conn, mail).await {
+        tracing::warn!("cannot mail retention notice to user {}: {}", owner_id, err);
+    }
+}
@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{AuditKind, audit_log, prelude::*, user};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    config::AUDIT_PAGE,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct AuditReq {
+    #[serde(default)]
+    pub kind: Option<AuditKind>,
+    #[serde(default)]
+    pub user_id: Option<i32>,
+    /// id of the last entry of the previous page
+    #[serde(default)]
+    pub before: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct AuditResp {
+    /// newest first, a full page if more are left
+    pub list: Vec<AuditRespEntry>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct AuditRespEntry {
+    pub id: i32,
+    pub kind: AuditKind,
+    pub user_id: Option<i32>,
+    /// None if the user was deleted since
+    pub username: Option<String>,
+    pub ip: Option<String>,
+    /// `x-request-id` of the request, to find its logs
+    pub request_id: Option<String>,
+    pub detail: String,
+    /// unix seconds
+    pub created_at: u32,
+}
+
+/// Logins, token refreshes, tool calls and changes by admins, see `audit`
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<AuditReq>,
+) -> JsonResult<AuditResp> {
+    let mut query = AuditLog::find();
+    if let Some(kind) = req.kind {
+        query = query.filter(audit_log::Column::Kind.eq(kind));
+    }
+    if let Some(user_id) = req.user_id {
+        query = query.filter(audit_log::Column::UserId.eq(user_id));
+    }
+    if let Some(before) = req.before {
+        query = query.filter(audit_log::Column::Id.lt(before));
+    }
+    let entries = query
+        .order_by_desc(audit_log::Column::Id)
+        .limit(AUDIT_PAGE)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let mut user_ids: Vec<i32> = entries.iter().filter_map(|x| x.user_id).collect();
+    user_ids.sort_unstable();
+    user_ids.dedup();
+    let users = User::find()
+        .filter(user::Column::Id.is_in(user_ids))
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let list = entries
+        .into_iter()
+        .map(|x| AuditRespEntry {
+            id: x.id,
+            kind: x.kind,
+            user_id: x.user_id,
+            username: users
+                .iter()
+                .find(|u| Some(u.id) == x.user_id)
+                .map(|u| u.name.clone()),
+            ip: x.ip,
+            request_id: x.request_id,
+            detail: x.detail,
+            created_at: x.created_at as u32,
+        })
+        .collect();
+
+    Ok(Json(AuditResp { list }))
+}
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, body::Body, extract::State, http::header, response::IntoResponse};
+use entity::AuditKind;
+use futures_util::{StreamExt, TryStreamExt, future, stream};
+use serde::{Deserialize, Serialize};
+use tokio_util::io::{ReaderStream, StreamReader};
+use typeshare::typeshare;
+
+use crate::{
+    AppState, audit,
+    backup::{self, Snapshot},
+    config::BACKUP_STREAM_BUFFER,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct BackupCreateReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct BackupRestoreResp {
+    pub users: u32,
+    pub chats: u32,
+    pub files: u32,
+}
+
+/// Stream a tar archive of the database and the uploaded files
+pub async fn create(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
+    Json(_): Json<BackupCreateReq>,
+) -> Result<impl IntoResponse, Json<Error>> {
+    let snapshot = Snapshot::take(&app.conn).await.kind(ErrorKind::Internal)?;
+    audit::record(
+        &app.conn,
+        AuditKind::Config,
+        Some(user_id),
+        "backup downloaded".to_owned(),
+    )
+    .await;
+
+    let (reader, writer) = tokio::io::duplex(BACKUP_STREAM_BUFFER);
+    let task = tokio::spawn(async move { snapshot.write(&app.conn, &app.files, writer).await });
+    // a failure past the headers can only cut the body short, as an error
+    let failed = stream::once(async move {
+        let err = match task.await {
+            Ok(Ok(())) => return None,
+            Ok(Err(err)) => err.to_string(),
+            Err(err) => err.to_string(),
+        };
+        tracing::warn!("backup failed: {}", err);
+        Some(Err(std::io::Error::other(err)))
+    })
+    .filter_map(future::ready);
+    let name = format!(
+        "attachment; filename=\"llumen-{}.tar\"",
+        time::UtcDateTime::now().date()
+    );
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-tar".to_owned()),
+            (header::CONTENT_DISPOSITION, name),
+        ],
+        Body::from_stream(ReaderStream::new(reader).chain(failed)),
+    ))
+}
+
+/// Restore a fresh instance from the archive of [`create`], sent as the body
+pub async fn restore(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
+    body: Body,
+) -> JsonResult<BackupRestoreResp> {
+    let input = StreamReader::new(body.into_data_stream().map_err(std::io::Error::other));
+    let restored = backup::restore(&app.conn, &app.files, input)
+        .await
+        .kind(ErrorKind::MalformedRequest)?;
+    app.cache.clear();
+    app.prompt.invalidate();
+    let detail = format!(
+        "backup restored, {} users, {} chats and {} files",
+        restored.users, restored.chats, restored.files
+    );
+    audit::record(&app.conn, AuditKind::Config, Some(user_id), detail).await;
+
+    Ok(Json(BackupRestoreResp {
+        users: restored.users as u32,
+        chats: restored.chats as u32,
+        files: restored.files as u32,
+    }))
+}
@@ -0,0 +1,219 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use dotenv::var;
+use entity::{AuditKind, prelude::*};
+use sea_orm::EntityTrait;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState, audit,
+    config::RuntimeConfig,
+    errors::*,
+    middlewares::{
+        auth::{AdminOnly, UserId},
+        rate_limit::RateLimitPolicy,
+    },
+    moderation::{self, ModerationPolicy},
+    routes::message::budget::AgentLoopPolicy,
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct AdminConfigReadReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct AdminConfigReadResp {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_model_id: Option<i32>,
+    pub rate_limit: RateLimitPolicy,
+    /// Set here, otherwise `API_BASE` env is used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_base: Option<String>,
+    /// Keys are never sent back, only whether they are set here
+    pub api_key_set: bool,
+    pub google_map_api_key_set: bool,
+    pub moderation: ModerationPolicy,
+    /// Whether `MODERATION_PROVIDER` is configured, `moderation.provider`
+    /// does nothing otherwise
+    pub moderation_provider: bool,
+    pub agent_loop: AgentLoopPolicy,
+    pub webhook_private_networks: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct AdminConfigWriteReq {
+    #[serde(default)]
+    pub default_model_id: Option<i32>,
+    pub rate_limit: RateLimitPolicy,
+    /// Empty or missing follow `API_BASE` env
+    #[serde(default)]
+    pub api_base: Option<String>,
+    /// Missing keep the key set, empty clear it to follow `API_KEY` env
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Missing keep the key set, empty clear it to follow `GOOGLE_MAP_API_KEY`
+    /// env
+    #[serde(default)]
+    pub google_map_api_key: Option<String>,
+    /// Missing keep the policy set
+    #[serde(default)]
+    pub moderation: Option<ModerationPolicy>,
+    /// Missing keep the limits set
+    #[serde(default)]
+    pub agent_loop: Option<AgentLoopPolicy>,
+    /// Missing keep the setting
+    #[serde(default)]
+    pub webhook_private_networks: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct AdminConfigWriteResp {
+    pub wrote: bool,
+}
+
+pub async fn read(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
+    Json(_): Json<AdminConfigReadReq>,
+) -> JsonResult<AdminConfigReadResp> {
+    let config = app.settings.current();
+    Ok(Json(AdminConfigReadResp {
+        default_model_id: config.default_model_id,
+        rate_limit: config.rate_limit,
+        api_base: config.api_base,
+        api_key_set: config.api_key.is_some(),
+        google_map_api_key_set: config.google_map_api_key.is_some(),
+        moderation: config.moderation,
+        moderation_provider: app.moderation.has_provider(),
+        agent_loop: config.agent_loop,
+        webhook_private_networks: config.webhook_private_networks,
+    }))
+}
+
+/// Replace the settings, applied without a restart
+pub async fn write(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<AdminConfigWriteReq>,
+) -> JsonResult<AdminConfigWriteResp> {
+    let current = app.settings.current();
+
+    if let Some(id) = req.default_model_id {
+        Model::find_by_id(id)
+            .one(&app.conn)
+            .await
+            .kind(ErrorKind::Internal)?
+            .ok_or("Cannot find model")
+            .kind(ErrorKind::ResourceNotFound)?;
+    }
+    if req.rate_limit.per_minute > 0 && req.rate_limit.burst == 0 {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "the burst must allow a request".to_owned(),
+        }));
+    }
+    let api_base = req
+        .api_base
+        .map(|x| x.trim().to_owned())
+        .filter(|x| !x.is_empty());
+    if let Some(base) = &api_base {
+        url::Url::parse(base).kind(ErrorKind::MalformedRequest)?;
+    }
+    let api_key = key(req.api_key, current.api_key.clone());
+    if api_key.is_none() && var("API_KEY").is_err() {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "API_KEY is not set, the key cannot be cleared".to_owned(),
+        }));
+    }
+    let moderation = req.moderation.unwrap_or(current.moderation.clone());
+    if let Err(err) = moderation::compile(&moderation.rules) {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!("invalid moderation rule: {}", err),
+        }));
+    }
+    let agent_loop = req.agent_loop.unwrap_or(current.agent_loop);
+    if agent_loop.max_depth == 0 || agent_loop.agent_max_secs == 0 {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "the tool loop must allow a round and a second".to_owned(),
+        }));
+    }
+
+    let config = RuntimeConfig {
+        default_model_id: req.default_model_id,
+        rate_limit: req.rate_limit,
+        api_base,
+        api_key,
+        google_map_api_key: key(req.google_map_api_key, current.google_map_api_key.clone()),
+        moderation,
+        agent_loop,
+        webhook_private_networks: req
+            .webhook_private_networks
+            .unwrap_or(current.webhook_private_networks),
+    };
+    if config == current {
+        return Ok(Json(AdminConfigWriteResp { wrote: false }));
+    }
+
+    app.settings
+        .set(config.clone())
+        .await
+        .kind(ErrorKind::Internal)?;
+    audit::record(
+        &app.conn,
+        AuditKind::Config,
+        Some(user_id),
+        format!("settings {} changed", changed(&current, &config).join(", ")),
+    )
+    .await;
+
+    Ok(Json(AdminConfigWriteResp { wrote: true }))
+}
+
+/// A key of the request, None keeps the one set
+fn key(req: Option<String>, current: Option<String>) -> Option<String> {
+    match req.map(|x| x.trim().to_owned()) {
+        Some(x) if x.is_empty() => None,
+        Some(x) => Some(x),
+        None => current,
+    }
+}
+
+/// Names of the settings changed, keys are not logged
+fn changed(old: &RuntimeConfig, new: &RuntimeConfig) -> Vec<&'static str> {
+    let mut changed = Vec::new();
+    if old.default_model_id != new.default_model_id {
+        changed.push("default_model_id");
+    }
+    if old.rate_limit != new.rate_limit {
+        changed.push("rate_limit");
+    }
+    if old.api_base != new.api_base {
+        changed.push("api_base");
+    }
+    if old.api_key != new.api_key {
+        changed.push("api_key");
+    }
+    if old.google_map_api_key != new.google_map_api_key {
+        changed.push("google_map_api_key");
+    }
+    if old.moderation != new.moderation {
+        changed.push("moderation");
+    }
+    if old.agent_loop != new.agent_loop {
+        changed.push("agent_loop");
+    }
+    if old.webhook_private_networks != new.webhook_private_networks {
+        changed.push("webhook_private_networks");
+    }
+    changed
+}
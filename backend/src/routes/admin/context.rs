@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{context_stat, prelude::*};
+use sea_orm::{EntityTrait, QueryOrder, QuerySelect};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+};
+
+/// Chats listed, the ones hitting the ceiling the longest first
+const LIMIT: u64 = 50;
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct ContextReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct ContextResp {
+    pub list: Vec<ContextRespList>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct ContextRespList {
+    pub chat_id: i32,
+    pub owner_id: i32,
+    pub completions: u32,
+    /// completions cut by the output limit of the provider
+    pub truncations: u32,
+    pub last_prompt_tokens: u32,
+    pub max_prompt_tokens: u32,
+    /// None if the model is not in the synced model list
+    pub context_length: Option<u32>,
+    /// completions in a row at the ceiling, see `CONTEXT_CEILING_RATIO`
+    pub streak: u32,
+    /// unix seconds
+    pub updated_at: u32,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
+    Json(_): Json<ContextReq>,
+) -> JsonResult<ContextResp> {
+    let stats = ContextStat::find()
+        .find_also_related(Chat)
+        .order_by_desc(context_stat::Column::Streak)
+        .order_by_desc(context_stat::Column::MaxPromptTokens)
+        .limit(LIMIT)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let list = stats
+        .into_iter()
+        .filter_map(|(stat, chat)| {
+            Some(ContextRespList {
+                chat_id: stat.chat_id,
+                owner_id: chat?.owner_id,
+                completions: stat.completions as u32,
+                truncations: stat.truncations as u32,
+                last_prompt_tokens: stat.last_prompt_tokens as u32,
+                max_prompt_tokens: stat.max_prompt_tokens as u32,
+                context_length: stat.context_length.map(|x| x as u32),
+                streak: stat.streak as u32,
+                updated_at: stat.updated_at as u32,
+            })
+        })
+        .collect();
+
+    Ok(Json(ContextResp { list }))
+}
@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{FeedbackRating, feedback, prelude::*};
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, FromQueryResult, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    config::FEEDBACK_COMMENTS,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+    utils::sql,
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct FeedbackReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct FeedbackResp {
+    pub up: i64,
+    pub down: i64,
+    /// by the model of the chat, the most rated first
+    pub models: Vec<FeedbackRespModel>,
+    /// latest ratings with a comment
+    pub comments: Vec<FeedbackRespComment>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct FeedbackRespModel {
+    pub model_id: i32,
+    /// None if the model config is malformed
+    pub display_name: Option<String>,
+    pub up: i64,
+    pub down: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct FeedbackRespComment {
+    pub message_id: i32,
+    pub chat_id: i32,
+    pub rating: FeedbackRating,
+    pub comment: String,
+    /// hash of the prompt the reply was written with, see `Generation`
+    pub prompt_version: Option<String>,
+    /// version of the admin template it was built on, see `prompt/versions`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template_version: Option<i32>,
+    /// unix seconds
+    pub created_at: u32,
+}
+
+#[derive(FromQueryResult)]
+struct ModelRow {
+    model_id: i32,
+    up: i64,
+    down: i64,
+}
+
+/// Ratings of replies across the instance, to tell which prompts and models
+/// users are unhappy with
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
+    Json(_): Json<FeedbackReq>,
+) -> JsonResult<FeedbackResp> {
+    let rows = ModelRow::find_by_statement(sql::statement(
+        app.conn.get_database_backend(),
+        "SELECT chat.model_id AS model_id,
+            COUNT(CASE WHEN feedback.rating = ? THEN 1 END) AS up,
+            COUNT(CASE WHEN feedback.rating = ? THEN 1 END) AS down
+        FROM feedback
+        JOIN message ON message.id = feedback.message_id
+        JOIN chat ON chat.id = message.chat_id
+        GROUP BY 1 ORDER BY COUNT(*) DESC",
+        [
+            (FeedbackRating::Up as i32).into(),
+            (FeedbackRating::Down as i32).into(),
+        ],
+    ))
+    .all(&app.conn)
+    .await
+    .kind(ErrorKind::Internal)?;
+
+    let configs = Model::find()
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let models: Vec<_> = rows
+        .into_iter()
+        .map(|x| FeedbackRespModel {
+            model_id: x.model_id,
+            display_name: configs
+                .iter()
+                .find(|m| m.id == x.model_id)
+                .and_then(|m| m.get_config())
+                .map(|c| c.display_name),
+            up: x.up,
+            down: x.down,
+        })
+        .collect();
+
+    let comments = Feedback::find()
+        .find_also_related(Message)
+        .filter(feedback::Column::Comment.is_not_null())
+        .order_by_desc(feedback::Column::CreatedAt)
+        .limit(FEEDBACK_COMMENTS)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .into_iter()
+        .filter_map(|(feedback, message)| {
+            let message = message?;
+            let generation = message.get_generation();
+            Some(FeedbackRespComment {
+                message_id: feedback.message_id,
+                chat_id: message.chat_id,
+                rating: feedback.rating,
+                comment: feedback.comment?,
+                template_version: generation.as_ref().and_then(|x| x.template_version),
+                prompt_version: generation.map(|x| x.prompt_version),
+                created_at: feedback.created_at as u32,
+            })
+        })
+        .collect();
+
+    Ok(Json(FeedbackResp {
+        up: models.iter().map(|x| x.up).sum(),
+        down: models.iter().map(|x| x.down).sum(),
+        models,
+        comments,
+    }))
+}
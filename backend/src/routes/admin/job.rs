@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{AuditKind, JobKind, JobStatus, job, prelude::*};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState, audit,
+    config::JOB_PAGE,
+    errors::*,
+    jobs::Task,
+    middlewares::auth::{AdminOnly, UserId},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct JobListReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct JobListResp {
+    /// failed jobs and the ones waiting for a retry, latest failure first
+    pub list: Vec<JobListRespEntry>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct JobListRespEntry {
+    pub id: i32,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    /// what the job is about, e.g. the chat to title or the recipient of a mail
+    pub target: String,
+    pub attempts: i32,
+    pub error: String,
+    /// unix seconds of the next attempt of a pending job
+    pub run_at: u32,
+    /// unix seconds
+    pub created_at: u32,
+    /// unix seconds of the latest failure
+    pub updated_at: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct JobRetryReq {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct JobRetryResp {
+    /// false if the job is not failed, e.g. retried already
+    pub retried: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct JobDeleteReq {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct JobDeleteResp {
+    pub deleted: bool,
+}
+
+/// Jobs of `jobs` that failed at least once, see `JOB_MAX_ATTEMPTS`
+pub async fn list(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
+    Json(_): Json<JobListReq>,
+) -> JsonResult<JobListResp> {
+    let jobs = Job::find()
+        .filter(job::Column::LastError.is_not_null())
+        .order_by_desc(job::Column::UpdatedAt)
+        .limit(JOB_PAGE)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let list = jobs
+        .into_iter()
+        .map(|x| JobListRespEntry {
+            id: x.id,
+            kind: x.kind,
+            status: x.status,
+            target: serde_json::from_str::<Task>(&x.payload)
+                .map(|task| task.describe())
+                .unwrap_or_default(),
+            attempts: x.attempts,
+            error: x.last_error.unwrap_or_default(),
+            run_at: x.run_at as u32,
+            created_at: x.created_at as u32,
+            updated_at: x.updated_at as u32,
+        })
+        .collect();
+
+    Ok(Json(JobListResp { list }))
+}
+
+/// Run a failed job again from its first attempt
+pub async fn retry(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<JobRetryReq>,
+) -> JsonResult<JobRetryResp> {
+    let retried = app
+        .jobs
+        .retry(&app.conn, req.id)
+        .await
+        .kind(ErrorKind::Internal)?;
+    if retried {
+        let detail = format!("failed job {} retried", req.id);
+        audit::record(&app.conn, AuditKind::Config, Some(user_id), detail).await;
+    }
+    Ok(Json(JobRetryResp { retried }))
+}
+
+/// Give up a job, a recurring one is scheduled again on the next start
+pub async fn delete(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<JobDeleteReq>,
+) -> JsonResult<JobDeleteResp> {
+    let res = Job::delete_many()
+        .filter(job::Column::Id.eq(req.id))
+        .filter(job::Column::Status.ne(JobStatus::Running))
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let deleted = res.rows_affected > 0;
+    if deleted {
+        let detail = format!("job {} deleted", req.id);
+        audit::record(&app.conn, AuditKind::Config, Some(user_id), detail).await;
+    }
+    Ok(Json(JobDeleteResp { deleted }))
+}
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::AuditKind;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState, audit,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct KeysRotateReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct KeysRotateResp {
+    /// Older keys still accepted until the tokens they encrypted expire
+    pub retired: u32,
+}
+
+/// Encrypt new tokens with a new key, nobody is signed out
+pub async fn rotate(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
+    Json(_): Json<KeysRotateReq>,
+) -> JsonResult<KeysRotateResp> {
+    let retired = app.keyring.rotate().await.kind(ErrorKind::Internal)?;
+    audit::record(
+        &app.conn,
+        AuditKind::Config,
+        Some(user_id),
+        "paseto key rotated".to_owned(),
+    )
+    .await;
+
+    Ok(Json(KeysRotateResp {
+        retired: retired as u32,
+    }))
+}
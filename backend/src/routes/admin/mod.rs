@@ -0,0 +1,51 @@
+mod audit;
+mod backup;
+mod config;
+mod context;
+mod feedback;
+mod job;
+mod keys;
+mod openapi;
+mod quota;
+mod spend;
+mod spend_cap;
+mod stats;
+mod system;
+mod tags;
+mod workspace;
+
+use std::sync::Arc;
+
+use axum::{Router, routing::post};
+
+use crate::AppState;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/audit", post(audit::route))
+        .route("/backup", post(backup::create))
+        .route("/backup/restore", post(backup::restore))
+        .route("/config/read", post(config::read))
+        .route("/config/write", post(config::write))
+        .route("/context", post(context::route))
+        .route("/feedback", post(feedback::route))
+        .route("/job/delete", post(job::delete))
+        .route("/job/list", post(job::list))
+        .route("/job/retry", post(job::retry))
+        .route("/keys/rotate", post(keys::rotate))
+        .route("/quota/read", post(quota::read))
+        .route("/quota/write", post(quota::write))
+        .route("/spend/cap/read", post(spend_cap::read))
+        .route("/spend/cap/write", post(spend_cap::write))
+        .route("/spend/read", post(spend::read))
+        .route("/spend/resume", post(spend::resume))
+        .route("/stats", post(stats::route))
+        .route("/system", post(system::route))
+        .route("/tags", post(tags::route))
+        .route("/workspace/create", post(workspace::create))
+        .route("/workspace/delete", post(workspace::delete))
+        .route("/workspace/list", post(workspace::list))
+        .route("/workspace/member", post(workspace::member))
+        .route("/workspace/write", post(workspace::write))
+        .nest("/openapi", openapi::routes())
+}
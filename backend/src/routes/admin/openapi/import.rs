@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::AuditKind;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState, audit,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+    tools::{
+        ToolSource,
+        declared::{self, openapi::Document},
+    },
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct OpenApiImportReq {
+    pub url: String,
+    /// Ids from the preview
+    pub operations: Vec<String>,
+    /// Environment variable holding the API credential, filled into the
+    /// security scheme of the operations
+    pub credential: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct OpenApiImportResp {
+    /// Ids of the tools added, offered in agent mode
+    pub tools: Vec<String>,
+}
+
+/// Save the generated specs to `TOOLS_DIR` and register them
+///
+/// Nothing is imported if any of the operations fail
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<OpenApiImportReq>,
+) -> JsonResult<OpenApiImportResp> {
+    let doc = Document::fetch(&req.url).await.kind(ErrorKind::ApiFail)?;
+    let credential = req.credential.as_deref().filter(|x| !x.is_empty());
+
+    let mut ids = req.operations;
+    ids.sort();
+    ids.dedup();
+
+    let mut specs = vec![];
+    for id in &ids {
+        let spec = doc.spec(id, credential).kind(ErrorKind::MalformedRequest)?;
+        declared::check(&spec).kind(ErrorKind::MalformedRequest)?;
+        if app.tools.has(&ToolSource::Declared.id(&spec.name)) {
+            return Err(format!("`{}` is already a tool", spec.name))
+                .kind(ErrorKind::MalformedRequest);
+        }
+        specs.push(spec);
+    }
+
+    let dir = declared::dir();
+    let mut tools = vec![];
+    for spec in specs {
+        declared::save(&dir, &spec).kind(ErrorKind::Internal)?;
+        tools.push(ToolSource::Declared.id(&spec.name));
+        app.tools.add_declared(spec).kind(ErrorKind::Internal)?;
+    }
+
+    let detail = format!("tools {} imported from {}", tools.join(", "), req.url);
+    audit::record(&app.conn, AuditKind::Config, Some(user_id), detail).await;
+
+    Ok(Json(OpenApiImportResp { tools }))
+}
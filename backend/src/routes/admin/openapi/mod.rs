@@ -0,0 +1,14 @@
+use std::sync::Arc;
+
+use axum::{Router, routing::post};
+
+use crate::AppState;
+
+mod import;
+mod preview;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/preview", post(preview::route))
+        .route("/import", post(import::route))
+}
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+    tools::{ToolSource, declared::openapi::Document},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct OpenApiPreviewReq {
+    /// Url of a JSON OpenAPI 3 document
+    pub url: String,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct OpenApiPreviewResp {
+    pub title: String,
+    pub operations: Vec<OpenApiOperation>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct OpenApiOperation {
+    /// Name of the tool it would become
+    pub id: String,
+    pub method: String,
+    pub path: String,
+    pub summary: String,
+    /// A tool with the same name exists, importing it would fail
+    pub exists: bool,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<OpenApiPreviewReq>,
+) -> JsonResult<OpenApiPreviewResp> {
+    let doc = Document::fetch(&req.url).await.kind(ErrorKind::ApiFail)?;
+
+    let operations = doc
+        .operations()
+        .into_iter()
+        .map(|x| OpenApiOperation {
+            exists: app.tools.has(&ToolSource::Declared.id(&x.id)),
+            id: x.id,
+            method: x.method,
+            path: x.path,
+            summary: x.summary,
+        })
+        .collect();
+
+    Ok(Json(OpenApiPreviewResp {
+        title: doc.title(),
+        operations,
+    }))
+}
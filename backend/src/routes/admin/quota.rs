@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{AuditKind, prelude::*};
+use sea_orm::EntityTrait;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState, audit,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+    quota::{self, QuotaLimits, QuotaUsed},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct QuotaReadReq {
+    pub user_id: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct QuotaReadResp {
+    /// `QUOTA_DAILY_*` and `QUOTA_MONTHLY_*`
+    pub defaults: QuotaLimits,
+    /// Limits set for the user, None follow the defaults
+    pub limits: QuotaLimits,
+    pub daily: QuotaUsed,
+    pub monthly: QuotaUsed,
+}
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct QuotaWriteReq {
+    pub user_id: i32,
+    /// Replace the limits set for the user, None follow the defaults
+    pub limits: QuotaLimits,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct QuotaWriteResp {}
+
+/// Limits and usage of a user
+pub async fn read(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<QuotaReadReq>,
+) -> JsonResult<QuotaReadResp> {
+    let limits = quota::get_override(&app.conn, req.user_id)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let (daily, monthly) = quota::used(&app.conn, req.user_id)
+        .await
+        .kind(ErrorKind::Internal)?;
+    Ok(Json(QuotaReadResp {
+        defaults: app.quotas.defaults(),
+        limits,
+        daily,
+        monthly,
+    }))
+}
+
+pub async fn write(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<QuotaWriteReq>,
+) -> JsonResult<QuotaWriteResp> {
+    User::find_by_id(req.user_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Cannot find user")
+        .kind(ErrorKind::ResourceNotFound)?;
+    quota::set_override(&app.conn, req.user_id, req.limits)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let detail = format!("quotas of user {} set", req.user_id);
+    audit::record(&app.conn, AuditKind::Config, Some(user_id), detail).await;
+    Ok(Json(QuotaWriteResp {}))
+}
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::AuditKind;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState, audit,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+    spend::SpendPause,
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct SpendReadReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct SpendReadResp {
+    /// Set while generations of users other than admins are paused
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paused: Option<SpendPause>,
+    /// `SPEND_GUARD_MULTIPLE`, the guard is off without it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub multiple: Option<f64>,
+    /// USD spent on the upstream in the current hour
+    pub hour_cost: f64,
+    /// USD per hour over the trailing hours
+    pub average: f64,
+}
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct SpendResumeReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct SpendResumeResp {
+    /// false if generations were not paused
+    pub resumed: bool,
+}
+
+/// Spending of the instance as the spend guard see it
+pub async fn read(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
+    Json(_): Json<SpendReadReq>,
+) -> JsonResult<SpendReadResp> {
+    let (hour_cost, average) = app.spend.current().await.kind(ErrorKind::Internal)?;
+    Ok(Json(SpendReadResp {
+        paused: app.spend.paused(),
+        multiple: app.spend.multiple(),
+        hour_cost,
+        average,
+    }))
+}
+
+/// Acknowledge a burst of spending, generations run again
+pub async fn resume(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
+    Json(_): Json<SpendResumeReq>,
+) -> JsonResult<SpendResumeResp> {
+    let resumed = app.spend.resume().await.kind(ErrorKind::Internal)?;
+    if resumed {
+        let detail = "generations paused by the spend guard resumed".to_owned();
+        audit::record(&app.conn, AuditKind::Config, Some(user_id), detail).await;
+    }
+    Ok(Json(SpendResumeResp { resumed }))
+}
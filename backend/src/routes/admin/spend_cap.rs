@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{AuditKind, prelude::*};
+use sea_orm::EntityTrait;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState, audit,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+    spend::cap::{self, SpendCapTarget},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct SpendCapReadReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct SpendCapReadResp {
+    /// `SPEND_CAP_MONTHLY`, the cap of users without their own
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<f64>,
+    /// Caps set by admins
+    pub list: Vec<SpendCapReadRespItem>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct SpendCapReadRespItem {
+    pub target: SpendCapTarget,
+    /// USD per UTC month
+    pub monthly: f64,
+    /// USD spent in the current month
+    pub spent: f64,
+}
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct SpendCapWriteReq {
+    pub target: SpendCapTarget,
+    /// Replace the cap, None removes it and a user follows the default
+    #[serde(default)]
+    pub monthly: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct SpendCapWriteResp {}
+
+/// Caps set by admins and what was spent against them this month
+pub async fn read(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
+    Json(_): Json<SpendCapReadReq>,
+) -> JsonResult<SpendCapReadResp> {
+    let month = cap::month();
+    let mut list = Vec::new();
+    for (target, monthly) in cap::list(&app.conn).await.kind(ErrorKind::Internal)? {
+        let spent = match target {
+            SpendCapTarget::User(id) => cap::user_spent(&app.conn, id, month).await,
+            SpendCapTarget::Model(id) => cap::model_spent(&app.conn, id, month).await,
+        }
+        .kind(ErrorKind::Internal)?;
+        list.push(SpendCapReadRespItem {
+            target,
+            monthly,
+            spent,
+        });
+    }
+    Ok(Json(SpendCapReadResp {
+        default: app.spend_caps.default(),
+        list,
+    }))
+}
+
+/// Set or remove the cap of a user or a model, applied to the next completion
+pub async fn write(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<SpendCapWriteReq>,
+) -> JsonResult<SpendCapWriteResp> {
+    if req.monthly.is_some_and(|x| !x.is_finite() || x < 0.0) {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "the cap must be a positive amount".to_owned(),
+        }));
+    }
+    let detail = match req.target {
+        SpendCapTarget::User(id) => {
+            User::find_by_id(id)
+                .one(&app.conn)
+                .await
+                .kind(ErrorKind::Internal)?
+                .ok_or("Cannot find user")
+                .kind(ErrorKind::ResourceNotFound)?;
+            format!("spending cap of user {} set", id)
+        }
+        SpendCapTarget::Model(id) => {
+            Model::find_by_id(id)
+                .one(&app.conn)
+                .await
+                .kind(ErrorKind::Internal)?
+                .ok_or("Cannot find model")
+                .kind(ErrorKind::ResourceNotFound)?;
+            format!("spending cap of model {} set", id)
+        }
+    };
+    cap::set(&app.conn, req.target, req.monthly)
+        .await
+        .kind(ErrorKind::Internal)?;
+    audit::record(&app.conn, AuditKind::Config, Some(user_id), detail).await;
+    Ok(Json(SpendCapWriteResp {}))
+}
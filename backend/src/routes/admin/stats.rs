@@ -0,0 +1,238 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{Extension, Json, extract::State};
+use entity::{prelude::*, reply_stat, usage};
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, QuerySelect};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    config::STATS_TOP,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+};
+
+const DAY: i64 = 24 * 3600;
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct AdminStatsReq {
+    /// default to `week`
+    pub range: Option<AdminStatsRange>,
+}
+
+/// UTC days up to the current one
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum AdminStatsRange {
+    Day,
+    #[default]
+    Week,
+    Month,
+    /// The 90 days of `STATS_DAYS`
+    Quarter,
+}
+
+impl AdminStatsRange {
+    fn days(self) -> i64 {
+        match self {
+            AdminStatsRange::Day => 1,
+            AdminStatsRange::Week => 7,
+            AdminStatsRange::Month => 30,
+            AdminStatsRange::Quarter => 90,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct AdminStatsResp {
+    /// unix seconds the range starts at, the start of a UTC day
+    pub since: u32,
+    pub users: u32,
+    /// Users who asked for a reply in the range
+    pub active_users: u32,
+    /// Every day of the range, oldest first
+    pub daily: Vec<AdminStatsRespDay>,
+    pub replies: u32,
+    /// Replies that ended in an error
+    pub failed: u32,
+    /// `failed` over `replies`, 0 without replies
+    pub error_rate: f64,
+    /// Milliseconds until the first token, for 95% of the replies that got one
+    pub ttft_p95_ms: u32,
+    /// USD, of the models with a known price
+    pub cost: f64,
+    /// Most replies first, at most `STATS_TOP`
+    pub models: Vec<AdminStatsRespModel>,
+    /// Most calls first, at most `STATS_TOP`
+    pub tools: Vec<AdminStatsRespTool>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct AdminStatsRespDay {
+    /// unix seconds, the start of the UTC day
+    pub day: u32,
+    /// Replies asked for, regenerations included
+    pub messages: u32,
+    pub tokens: i64,
+    pub active_users: u32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct AdminStatsRespModel {
+    pub model_id: String,
+    pub replies: u32,
+    pub failed: u32,
+    /// Output tokens
+    pub tokens: i64,
+    pub cost: f64,
+    pub ttft_p95_ms: u32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct AdminStatsRespTool {
+    pub name: String,
+    pub calls: u32,
+}
+
+#[derive(Default)]
+struct ModelEntry {
+    replies: u32,
+    failed: u32,
+    tokens: i64,
+    cost: f64,
+    ttft: Vec<i64>,
+}
+
+/// Activity, cost and reliability of the instance, from `usage` and
+/// `reply_stat`
+///
+/// Reachable with the `stats` scope of API keys, for external dashboards
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<AdminStatsReq>,
+) -> JsonResult<AdminStatsResp> {
+    let days = req.range.unwrap_or_default().days();
+    let today = time::UtcDateTime::now().unix_timestamp() / DAY * DAY;
+    let since = today - (days - 1) * DAY;
+
+    let users = User::find()
+        .count(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let usages = Usage::find()
+        .select_only()
+        .columns([
+            usage::Column::UserId,
+            usage::Column::Day,
+            usage::Column::Messages,
+            usage::Column::Tokens,
+        ])
+        .filter(usage::Column::Day.gte(since))
+        .filter(usage::Column::Messages.gt(0))
+        .into_tuple::<(i32, i64, i32, i64)>()
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let mut daily: Vec<AdminStatsRespDay> = (0..days)
+        .map(|x| AdminStatsRespDay {
+            day: (since + x * DAY) as u32,
+            messages: 0,
+            tokens: 0,
+            active_users: 0,
+        })
+        .collect();
+    let mut active: Vec<i32> = vec![];
+    for (user_id, day, messages, tokens) in usages {
+        let Some(entry) = daily.get_mut(((day - since) / DAY) as usize) else {
+            continue;
+        };
+        entry.messages += messages as u32;
+        entry.tokens += tokens;
+        entry.active_users += 1;
+        active.push(user_id);
+    }
+    active.sort_unstable();
+    active.dedup();
+
+    let replies = ReplyStat::find()
+        .filter(reply_stat::Column::CreatedAt.gte(since))
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let mut total = ModelEntry::default();
+    let mut models: HashMap<String, ModelEntry> = HashMap::new();
+    let mut tools: HashMap<String, u32> = HashMap::new();
+    for reply in replies {
+        for name in reply.tools.0 {
+            *tools.entry(name).or_default() += 1;
+        }
+        let model = models.entry(reply.model_id).or_default();
+        for entry in [&mut total, model] {
+            entry.replies += 1;
+            entry.failed += reply.failed as u32;
+            entry.tokens += reply.tokens;
+            entry.cost += reply.cost;
+            if reply.ttft_ms > 0 {
+                entry.ttft.push(reply.ttft_ms);
+            }
+        }
+    }
+
+    let mut models: Vec<AdminStatsRespModel> = models
+        .into_iter()
+        .map(|(model_id, mut entry)| AdminStatsRespModel {
+            model_id,
+            replies: entry.replies,
+            failed: entry.failed,
+            tokens: entry.tokens,
+            cost: entry.cost,
+            ttft_p95_ms: p95(&mut entry.ttft),
+        })
+        .collect();
+    models.sort_by(|a, b| b.replies.cmp(&a.replies).then(a.model_id.cmp(&b.model_id)));
+    models.truncate(STATS_TOP);
+
+    let mut tools: Vec<AdminStatsRespTool> = tools
+        .into_iter()
+        .map(|(name, calls)| AdminStatsRespTool { name, calls })
+        .collect();
+    tools.sort_by(|a, b| b.calls.cmp(&a.calls).then(a.name.cmp(&b.name)));
+    tools.truncate(STATS_TOP);
+
+    Ok(Json(AdminStatsResp {
+        since: since as u32,
+        users: users as u32,
+        active_users: active.len() as u32,
+        daily,
+        replies: total.replies,
+        failed: total.failed,
+        error_rate: match total.replies {
+            0 => 0.0,
+            replies => total.failed as f64 / replies as f64,
+        },
+        ttft_p95_ms: p95(&mut total.ttft),
+        cost: total.cost,
+        models,
+        tools,
+    }))
+}
+
+/// Nearest-rank percentile, 0 without values
+fn p95(values: &mut [i64]) -> u32 {
+    if values.is_empty() {
+        return 0;
+    }
+    values.sort_unstable();
+    let rank = (values.len() * 95).div_ceil(100);
+    values[rank.saturating_sub(1)] as u32
+}
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use sea_orm::{ConnectionTrait, DbBackend, Statement};
+use serde::{Deserialize, Serialize};
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    config::SYSTEM_WARN_RATIO,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct SystemReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct SystemResp {
+    /// Resident memory of this process in KiB
+    pub rss_kb: u32,
+    /// Memory limit of the container in KiB, None outside a cgroup
+    pub memory_limit_kb: Option<u32>,
+    /// None on platforms other than linux
+    pub open_files: Option<u32>,
+    pub open_files_limit: Option<u32>,
+    /// Tasks alive in the tokio runtime, including the reaper and the syncs
+    pub tasks: u32,
+    /// Size of the database in KiB, including free pages
+    pub db_size_kb: u32,
+    pub uptime_secs: u32,
+    /// Resources close to their limit, in english
+    pub warnings: Vec<String>,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
+    Json(_): Json<SystemReq>,
+) -> JsonResult<SystemResp> {
+    let pid = sysinfo::get_current_pid().kind(ErrorKind::Internal)?;
+    let mut system = System::new();
+    // cgroup limits are capped by the physical memory
+    system.refresh_memory();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        false,
+        ProcessRefreshKind::nothing().with_memory(),
+    );
+    let process = system
+        .process(pid)
+        .ok_or("cannot find current process")
+        .kind(ErrorKind::Internal)?;
+
+    let rss = process.memory();
+    let cgroup = system.cgroup_limits();
+    let memory_limit = cgroup.as_ref().map(|x| x.total_memory);
+    let open_files = process.open_files();
+    let open_files_limit = process.open_files_limit();
+    let uptime = process.run_time();
+
+    let db_size = app
+        .conn
+        .query_one(Statement::from_string(
+            app.conn.get_database_backend(),
+            match app.conn.get_database_backend() {
+                DbBackend::Postgres => "SELECT pg_database_size(current_database()) AS size",
+                _ => "SELECT page_count * page_size AS size FROM pragma_page_count(), pragma_page_size()",
+            },
+        ))
+        .await
+        .kind(ErrorKind::Internal)?
+        .map(|row| row.try_get::<i64>("", "size"))
+        .transpose()
+        .kind(ErrorKind::Internal)?
+        .unwrap_or_default();
+
+    let mut warnings = vec![];
+    // the whole cgroup is killed, not only this process
+    if let Some(cgroup) = cgroup {
+        let used = cgroup.total_memory - cgroup.free_memory;
+        if used as f64 > cgroup.total_memory as f64 * SYSTEM_WARN_RATIO {
+            warnings.push(format!(
+                "Memory usage {} MiB is close to the container limit {} MiB",
+                used >> 20,
+                cgroup.total_memory >> 20
+            ));
+        }
+    }
+    if let (Some(open), Some(limit)) = (open_files, open_files_limit)
+        && open as f64 > limit as f64 * SYSTEM_WARN_RATIO
+    {
+        warnings.push(format!(
+            "{} files are open, close to the limit {}",
+            open, limit
+        ));
+    }
+
+    Ok(Json(SystemResp {
+        rss_kb: (rss >> 10) as u32,
+        memory_limit_kb: memory_limit.map(|x| (x >> 10) as u32),
+        open_files: open_files.map(|x| x as u32),
+        open_files_limit: open_files_limit.map(|x| x as u32),
+        tasks: tokio::runtime::Handle::current()
+            .metrics()
+            .num_alive_tasks() as u32,
+        db_size_kb: (db_size >> 10) as u32,
+        uptime_secs: uptime as u32,
+        warnings,
+    }))
+}
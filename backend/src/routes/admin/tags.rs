@@ -0,0 +1,28 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use serde::Deserialize;
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+    routes::chat::tags::{ChatTagsResp, count},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct TagsReq {}
+
+/// Tags of every chat of the instance, see `utils::tagger`
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
+    Json(_): Json<TagsReq>,
+) -> JsonResult<ChatTagsResp> {
+    Ok(Json(
+        count(&app.conn, None).await.kind(ErrorKind::Internal)?,
+    ))
+}
@@ -0,0 +1,409 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{Extension, Json, extract::State};
+use entity::{
+    AuditKind, api_key, chat, prelude::*, prompt_template, session, workspace,
+    workspace_credential, workspace_member, workspace_model,
+};
+use sea_orm::{
+    ActiveValue::Set,
+    ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, TransactionTrait, Value,
+    sea_query::{Expr, OnConflict},
+};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState, audit,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+    utils::workspace::{self as workspaces, DEFAULT_WORKSPACE},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct AdminWorkspaceListReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct AdminWorkspaceListResp {
+    pub list: Vec<AdminWorkspace>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct AdminWorkspace {
+    pub id: i32,
+    pub name: String,
+    pub member_ids: Vec<i32>,
+    /// Models offered, empty for every model
+    pub model_ids: Vec<i32>,
+    /// Names of the credentials set, values are never sent back
+    pub credentials: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct AdminWorkspaceCreateReq {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct AdminWorkspaceCreateResp {
+    pub id: i32,
+}
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct AdminWorkspaceWriteReq {
+    pub id: i32,
+    pub name: String,
+    /// Replace the models offered, empty for every model
+    pub model_ids: Vec<i32>,
+    /// Set by name, an empty value removes one, names left out are kept
+    #[serde(default)]
+    pub credentials: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct AdminWorkspaceWriteResp {}
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct AdminWorkspaceDeleteReq {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct AdminWorkspaceDeleteResp {}
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct AdminWorkspaceMemberReq {
+    pub workspace_id: i32,
+    pub user_id: i32,
+    /// false to remove the user
+    pub member: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct AdminWorkspaceMemberResp {}
+
+pub async fn list(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
+    Json(_): Json<AdminWorkspaceListReq>,
+) -> JsonResult<AdminWorkspaceListResp> {
+    let workspaces = Workspace::find()
+        .order_by_asc(workspace::Column::Id)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let members = WorkspaceMember::find()
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let models = WorkspaceModel::find()
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let credentials = WorkspaceCredential::find()
+        .order_by_asc(workspace_credential::Column::Name)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let list = workspaces
+        .into_iter()
+        .map(|x| AdminWorkspace {
+            id: x.id,
+            name: x.name,
+            member_ids: members
+                .iter()
+                .filter(|m| m.workspace_id == x.id)
+                .map(|m| m.user_id)
+                .collect(),
+            model_ids: models
+                .iter()
+                .filter(|m| m.workspace_id == x.id)
+                .map(|m| m.model_id)
+                .collect(),
+            credentials: credentials
+                .iter()
+                .filter(|c| c.workspace_id == x.id)
+                .map(|c| c.name.clone())
+                .collect(),
+        })
+        .collect();
+    Ok(Json(AdminWorkspaceListResp { list }))
+}
+
+/// An empty workspace, offering every model
+pub async fn create(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<AdminWorkspaceCreateReq>,
+) -> JsonResult<AdminWorkspaceCreateResp> {
+    let name = name(&req.name)?;
+    let id = Workspace::insert(workspace::ActiveModel {
+        name: Set(name.clone()),
+        created_at: Set(time::UtcDateTime::now().unix_timestamp()),
+        ..Default::default()
+    })
+    .exec(&app.conn)
+    .await
+    .kind(ErrorKind::MalformedRequest)?
+    .last_insert_id;
+
+    let detail = format!("workspace {} ({}) created", id, name);
+    audit::record(&app.conn, AuditKind::Config, Some(user_id), detail).await;
+    Ok(Json(AdminWorkspaceCreateResp { id }))
+}
+
+/// Rename, pick the models offered and set credentials
+pub async fn write(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<AdminWorkspaceWriteReq>,
+) -> JsonResult<AdminWorkspaceWriteResp> {
+    let name = name(&req.name)?;
+    find(&app, req.id).await?;
+    let known = Model::find()
+        .filter(entity::model::Column::Id.is_in(req.model_ids.clone()))
+        .count(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    if known as usize != req.model_ids.len() {
+        return Err(Json(Error {
+            error: ErrorKind::ResourceNotFound,
+            reason: "Cannot find model".to_owned(),
+        }));
+    }
+
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+    Workspace::update(workspace::ActiveModel {
+        id: Set(req.id),
+        name: Set(name),
+        ..Default::default()
+    })
+    .exec(&txn)
+    .await
+    .kind(ErrorKind::MalformedRequest)?;
+
+    WorkspaceModel::delete_many()
+        .filter(workspace_model::Column::WorkspaceId.eq(req.id))
+        .exec(&txn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    if !req.model_ids.is_empty() {
+        WorkspaceModel::insert_many(req.model_ids.iter().map(|model_id| {
+            workspace_model::ActiveModel {
+                workspace_id: Set(req.id),
+                model_id: Set(*model_id),
+            }
+        }))
+        .on_conflict(
+            OnConflict::columns([
+                workspace_model::Column::WorkspaceId,
+                workspace_model::Column::ModelId,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .do_nothing()
+        .exec(&txn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    }
+
+    let mut changed: Vec<&str> = req.credentials.keys().map(|x| x.as_str()).collect();
+    changed.sort();
+    for (name, value) in &req.credentials {
+        match value.trim() {
+            "" => {
+                WorkspaceCredential::delete_by_id((req.id, name.clone()))
+                    .exec(&txn)
+                    .await
+                    .kind(ErrorKind::Internal)?;
+            }
+            value => {
+                WorkspaceCredential::insert(workspace_credential::ActiveModel {
+                    workspace_id: Set(req.id),
+                    name: Set(name.clone()),
+                    value: Set(app.secrets.seal(value).kind(ErrorKind::Internal)?),
+                })
+                .on_conflict(
+                    OnConflict::columns([
+                        workspace_credential::Column::WorkspaceId,
+                        workspace_credential::Column::Name,
+                    ])
+                    .update_column(workspace_credential::Column::Value)
+                    .to_owned(),
+                )
+                .exec(&txn)
+                .await
+                .kind(ErrorKind::Internal)?;
+            }
+        }
+    }
+    txn.commit().await.kind(ErrorKind::Internal)?;
+
+    // values are not logged
+    let mut detail = format!("workspace {} written", req.id);
+    if !changed.is_empty() {
+        detail = format!("{}, credentials {} changed", detail, changed.join(", "));
+    }
+    audit::record(&app.conn, AuditKind::Config, Some(user_id), detail).await;
+    Ok(Json(AdminWorkspaceWriteResp {}))
+}
+
+/// Only an empty workspace, whose members all have another one
+pub async fn delete(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<AdminWorkspaceDeleteReq>,
+) -> JsonResult<AdminWorkspaceDeleteResp> {
+    if req.id == DEFAULT_WORKSPACE {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "the default workspace cannot be deleted".to_owned(),
+        }));
+    }
+    let workspace = find(&app, req.id).await?;
+    let chats = Chat::find()
+        .filter(chat::Column::WorkspaceId.eq(req.id))
+        .count(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    if chats > 0 {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!("the workspace still has {} chats", chats),
+        }));
+    }
+    let members = WorkspaceMember::find()
+        .filter(workspace_member::Column::WorkspaceId.eq(req.id))
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    for member in members {
+        last_workspace(&app, req.id, member.user_id).await?;
+    }
+
+    // these point at it without a foreign key
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+    PromptTemplate::delete_many()
+        .filter(prompt_template::Column::WorkspaceId.eq(req.id))
+        .exec(&txn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    ApiKey::update_many()
+        .col_expr(api_key::Column::WorkspaceId, Expr::value(Value::Int(None)))
+        .filter(api_key::Column::WorkspaceId.eq(req.id))
+        .exec(&txn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    Session::update_many()
+        .col_expr(session::Column::WorkspaceId, Expr::value(Value::Int(None)))
+        .filter(session::Column::WorkspaceId.eq(req.id))
+        .exec(&txn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    Workspace::delete_by_id(req.id)
+        .exec(&txn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    txn.commit().await.kind(ErrorKind::Internal)?;
+    app.cache.members.invalidate_if(|(id, _), _| *id == req.id);
+    app.prompt.invalidate();
+    let detail = format!("workspace {} ({}) deleted", req.id, workspace.name);
+    audit::record(&app.conn, AuditKind::Config, Some(user_id), detail).await;
+    Ok(Json(AdminWorkspaceDeleteResp {}))
+}
+
+/// Add or remove a member, the chats of a removed member stay in the workspace
+pub async fn member(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(admin_id)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<AdminWorkspaceMemberReq>,
+) -> JsonResult<AdminWorkspaceMemberResp> {
+    find(&app, req.workspace_id).await?;
+    let user = User::find_by_id(req.user_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Cannot find user")
+        .kind(ErrorKind::ResourceNotFound)?;
+
+    let detail = match req.member {
+        true => {
+            workspaces::join(&app.conn, req.workspace_id, req.user_id)
+                .await
+                .kind(ErrorKind::Internal)?;
+            format!("{} joined workspace {}", user.name, req.workspace_id)
+        }
+        false => {
+            last_workspace(&app, req.workspace_id, req.user_id).await?;
+            WorkspaceMember::delete_by_id((req.workspace_id, req.user_id))
+                .exec(&app.conn)
+                .await
+                .kind(ErrorKind::Internal)?;
+            format!("{} left workspace {}", user.name, req.workspace_id)
+        }
+    };
+    app.cache
+        .members
+        .invalidate(&(req.workspace_id, req.user_id));
+    audit::record(&app.conn, AuditKind::User, Some(admin_id), detail).await;
+    Ok(Json(AdminWorkspaceMemberResp {}))
+}
+
+async fn find(app: &AppState, id: i32) -> Result<workspace::Model, Json<Error>> {
+    Workspace::find_by_id(id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Cannot find workspace")
+        .kind(ErrorKind::ResourceNotFound)
+}
+
+/// Refuse to leave a user in no workspace, they could not work anywhere
+async fn last_workspace(
+    app: &AppState,
+    workspace_id: i32,
+    user_id: i32,
+) -> Result<(), Json<Error>> {
+    let others = WorkspaceMember::find()
+        .filter(workspace_member::Column::UserId.eq(user_id))
+        .filter(workspace_member::Column::WorkspaceId.ne(workspace_id))
+        .count(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    match others {
+        0 => Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!("it is the only workspace of user {}", user_id),
+        })),
+        _ => Ok(()),
+    }
+}
+
+fn name(name: &str) -> Result<String, Json<Error>> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "the name cannot be empty".to_owned(),
+        }));
+    }
+    Ok(name.to_owned())
+}
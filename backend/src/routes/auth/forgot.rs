@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use axum::{Json, extract::State};
+use entity::{prelude::*, user};
+use schemars::JsonSchema;
+use sea_orm::prelude::*;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState, config::PASSWORD_RESET_SECS, errors::*, notify::mailer::Mail, utils::password_reset,
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ForgotReq {
+    pub username: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ForgotResp {}
+
+/// Mail a reset link to the address of the user
+///
+/// Answer the same whether the user exists or has an address, and before the
+/// mail is sent, so it cannot be used to probe accounts
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Json(req): Json<ForgotReq>,
+) -> JsonResult<ForgotResp> {
+    if app.mailer.is_none() {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "Password reset by mail is not configured".to_owned(),
+        }));
+    }
+
+    let Some((user_id, email)) = User::find()
+        .filter(user::Column::Name.eq(req.username))
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .and_then(|x| x.email.map(|email| (x.id, email)))
+    else {
+        return Ok(Json(ForgotResp {}));
+    };
+
+    let token = password_reset::create(&app.conn, user_id)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    // safety:
+    // checked above, the mailer never change at runtime
+    let mailer = app.mailer.as_ref().unwrap();
+    let mail = Mail::new("Reset your password")
+        .text("Someone asked to reset your llumen password.")
+        .text(format!(
+            "Open the link below within {} minutes to choose a new one.",
+            PASSWORD_RESET_SECS / 60
+        ))
+        .link("Reset password", mailer.link(&format!("/reset#{}", token)))
+        .text("If it was not you, ignore this mail.");
+    let mail = mailer.task(email, &mail);
+    if let Err(err) = app.jobs.push(&app.conn, mail).await {
+        tracing::warn!("cannot mail reset link to user {}: {}", user_id, err);
+    }
+
+    Ok(Json(ForgotResp {}))
+}
@@ -1,58 +1,152 @@
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
-use axum::{Json, extract::State};
-use entity::{prelude::*, user};
-use pasetors::{claims::Claims, local};
+use axum::{
+    Json,
+    extract::{ConnectInfo, State},
+};
+use entity::{AuditKind, prelude::*, user};
+use http::HeaderMap;
+use schemars::JsonSchema;
 use sea_orm::prelude::*;
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-use crate::{AppState, errors::*};
+use crate::{
+    AppState, audit,
+    errors::*,
+    utils::{
+        account_purge, client,
+        login_throttle::{self, Key},
+        session::{self, Device},
+        totp,
+    },
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct LoginReq {
     pub username: String,
     pub password: String,
+    /// TOTP or recovery code, required once TOTP is enabled
+    #[serde(default)]
+    pub totp: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct LoginResp {
     pub token: String,
     pub exp: String,
+    /// exchange it at `/api/auth/refresh` before `exp`
+    pub refresh_token: String,
 }
 
 pub async fn route(
     State(app): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(req): Json<LoginReq>,
 ) -> JsonResult<LoginResp> {
-    let model = User::find()
-        .filter(user::Column::Name.eq(req.username))
-        .one(&app.conn)
+    let keys = [
+        Key::Account(&req.username),
+        Key::Ip(client::ip(&headers, addr)),
+    ];
+    if let Some(wait) = login_throttle::locked(&app.conn, &keys)
         .await
         .kind(ErrorKind::Internal)?
-        .ok_or("")
-        .kind(ErrorKind::LoginFail)?;
-
-    if !app.hasher.verify_password(&model.password, &req.password) {
+    {
         return Err(Json(Error {
             error: ErrorKind::LoginFail,
-            reason: "".to_owned(),
+            reason: format!("Too many failed logins, retry in {} seconds", wait),
         }));
     }
 
-    let mut claim = Claims::new().kind(ErrorKind::Internal)?;
+    let model = User::find()
+        .filter(user::Column::Name.eq(&req.username))
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
 
+    let failure = match &model {
+        None => Some(""),
+        Some(model)
+            if !app
+                .hasher
+                .verify_password(&model.password, &req.password)
+                .await =>
+        {
+            Some("")
+        }
+        Some(model)
+            if req.totp.is_some()
+                && !totp::verify(&app.conn, model.id, req.totp.as_deref())
+                    .await
+                    .kind(ErrorKind::Internal)? =>
+        {
+            Some("Wrong code")
+        }
+        _ => None,
+    };
+    if let Some(reason) = failure {
+        login_throttle::fail(&app.conn, &keys)
+            .await
+            .kind(ErrorKind::Internal)?;
+        audit::record(
+            &app.conn,
+            AuditKind::LoginFail,
+            model.as_ref().map(|x| x.id),
+            format!(
+                "login as {} failed",
+                req.username.chars().take(64).collect::<String>()
+            ),
+        )
+        .await;
+        return Err(Json(Error {
+            error: ErrorKind::LoginFail,
+            reason: reason.to_owned(),
+        }));
+    }
     // safety:
-    // "uid" is not reserve
-    claim.add_additional("uid", model.id).unwrap();
+    // checked above
+    let model = model.unwrap();
 
-    // safety:
-    // "exp" must exists
-    let exp = claim.get_claim("exp").unwrap().as_str().unwrap().to_owned();
+    // not a failure, the password is right
+    if req.totp.is_none()
+        && !totp::verify(&app.conn, model.id, None)
+            .await
+            .kind(ErrorKind::Internal)?
+    {
+        return Err(Json(Error {
+            error: ErrorKind::TotpRequired,
+            reason: "".to_owned(),
+        }));
+    }
 
-    let token = local::encrypt(&app.key, &claim, None, None).kind(ErrorKind::Internal)?;
+    login_throttle::succeed(&app.conn, &req.username)
+        .await
+        .kind(ErrorKind::Internal)?;
+    account_purge::cancel(&app.conn, model.id)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let (session_id, refresh_token) =
+        session::create(&app.conn, model.id, Device::new(&headers, addr))
+            .await
+            .kind(ErrorKind::Internal)?;
+    let (token, exp) = session::access_token(&app.conn, &app.keyring, model.id, session_id)
+        .await
+        .kind(ErrorKind::Internal)?;
+    audit::record(
+        &app.conn,
+        AuditKind::Login,
+        Some(model.id),
+        format!("session {} by password", session_id),
+    )
+    .await;
 
-    Ok(Json(LoginResp { token, exp }))
+    Ok(Json(LoginResp {
+        token,
+        exp,
+        refresh_token,
+    }))
 }
@@ -0,0 +1,38 @@
+use std::sync::Arc;
+
+use axum::{Json, extract::State};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, utils::session};
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct LogoutReq {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[typeshare]
+pub struct LogoutResp {
+    /// false if the token was already revoked or unknown
+    pub revoked: bool,
+}
+
+/// Revoke the session of the refresh token, its access token stops working right away
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Json(req): Json<LogoutReq>,
+) -> JsonResult<LogoutResp> {
+    let revoked = session::revoke(&app.conn, &req.refresh_token)
+        .await
+        .kind(ErrorKind::Internal)?;
+    if let Some(session_id) = revoked {
+        app.cache.sessions.invalidate(&session_id);
+    }
+
+    Ok(Json(LogoutResp {
+        revoked: revoked.is_some(),
+    }))
+}
@@ -2,13 +2,70 @@ use std::sync::Arc;
 
 use axum::{Router, routing::post};
 
-use crate::AppState;
+use crate::{AppState, utils::openapi::Builder};
 
+mod forgot;
 mod login;
-mod renew;
+mod logout;
+mod oauth;
+mod refresh;
+mod reset;
+pub mod totp;
+mod verify;
 
 pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/login", post(login::route))
-        .route("/renew", post(renew::route))
+        .route("/refresh", post(refresh::route))
+        .route("/logout", post(logout::route))
+        .route("/forgot", post(forgot::route))
+        .route("/reset", post(reset::route))
+        .route("/verify", post(verify::route))
+        .nest("/oauth", oauth::routes())
+}
+
+/// Describe the routes above but OAuth, which is browser redirects, see
+/// `routes::openapi`
+pub fn spec(api: &mut Builder) {
+    api.op("POST", "/auth/login", "Exchange a password for tokens")
+        .public()
+        .body::<login::LoginReq>()
+        .json::<login::LoginResp>();
+    api.op(
+        "POST",
+        "/auth/refresh",
+        "Exchange a refresh token for new tokens",
+    )
+    .public()
+    .body::<refresh::RefreshReq>()
+    .json::<refresh::RefreshResp>();
+    api.op("POST", "/auth/logout", "Revoke a refresh token")
+        .public()
+        .body::<logout::LogoutReq>()
+        .json::<logout::LogoutResp>();
+    api.op(
+        "POST",
+        "/auth/forgot",
+        "Mail a reset link to the address of the user",
+    )
+    .public()
+    .body::<forgot::ForgotReq>()
+    .json::<forgot::ForgotResp>();
+    api.op(
+        "POST",
+        "/auth/reset",
+        "Set a new password with a mailed token",
+    )
+    .public()
+    .body::<reset::ResetReq>()
+    .json::<reset::ResetResp>();
+    api.op(
+        "POST",
+        "/auth/verify",
+        "Verify the address of the user with a mailed token",
+    )
+    .public()
+    .body::<verify::VerifyReq>()
+    .json::<verify::VerifyResp>();
+    totp::spec(api);
 }
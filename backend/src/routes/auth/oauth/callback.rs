@@ -0,0 +1,151 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use anyhow::{Result, bail};
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    response::Redirect,
+};
+use entity::{AuditKind, identity, prelude::*, user};
+use http::{HeaderMap, HeaderName, header};
+use sea_orm::{ActiveValue::Set, ConnectionTrait, TransactionTrait, prelude::*};
+use serde::Deserialize;
+use url::form_urlencoded;
+
+use crate::{
+    AppState, audit, oauth,
+    utils::account_purge,
+    utils::password_hash::Hasher,
+    utils::session::{self, Device},
+    utils::workspace,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct CallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    /// set by the provider if the user denied access
+    error: Option<String>,
+}
+
+/// Redirect to the login page with `token`, `exp` and `refresh_token` in the fragment,
+/// or `error` if the login failed
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Path(provider): Path<String>,
+    Query(query): Query<CallbackQuery>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> ([(HeaderName, String); 1], Redirect) {
+    let cookie = oauth::state_cookie(&headers);
+    let res = login(&app, &provider, query, cookie, Device::new(&headers, addr)).await;
+    if let Err(err) = &res {
+        tracing::info!("{} login failed: {}", provider, err);
+        audit::record(
+            &app.conn,
+            AuditKind::LoginFail,
+            None,
+            format!("login with {} failed: {}", provider, err),
+        )
+        .await;
+    }
+    let mut fragment = form_urlencoded::Serializer::new(String::new());
+    match res {
+        Ok((token, exp, refresh_token)) => {
+            fragment.extend_pairs([
+                ("token", token),
+                ("exp", exp),
+                ("refresh_token", refresh_token),
+            ]);
+        }
+        Err(err) => {
+            fragment.append_pair("error", &err.to_string());
+        }
+    }
+
+    (
+        [(header::SET_COOKIE, app.oauth.clear_cookie())],
+        Redirect::to(&format!("{}#{}", app.oauth.login_page(), fragment.finish())),
+    )
+}
+
+async fn login(
+    app: &AppState,
+    provider: &str,
+    query: CallbackQuery,
+    cookie: Option<&str>,
+    device: Device,
+) -> Result<(String, String, String)> {
+    let (Some(code), Some(state)) = (query.code, query.state) else {
+        bail!("{}", query.error.unwrap_or("Missing code".to_owned()));
+    };
+    let identity = app.oauth.identify(provider, &state, &code, cookie).await?;
+
+    let txn = app.conn.begin().await?;
+    let linked = Identity::find()
+        .filter(identity::Column::Provider.eq(provider))
+        .filter(identity::Column::Subject.eq(&identity.subject))
+        .one(&txn)
+        .await?;
+    let user_id = match (linked, identity.link) {
+        (Some(linked), Some(user_id)) if linked.user_id != user_id => {
+            bail!("This account is already linked to another user")
+        }
+        (Some(linked), _) => linked.user_id,
+        (None, link) => {
+            let user_id = match link {
+                Some(user_id) => user_id,
+                None => create_user(&txn, &app.hasher, &identity.name).await?,
+            };
+            Identity::insert(identity::ActiveModel {
+                user_id: Set(user_id),
+                provider: Set(provider.to_owned()),
+                subject: Set(identity.subject),
+                created_at: Set(time::UtcDateTime::now().unix_timestamp()),
+                ..Default::default()
+            })
+            .exec(&txn)
+            .await?;
+            tracing::info!("{} identity linked to user {}", provider, user_id);
+            user_id
+        }
+    };
+    account_purge::cancel(&txn, user_id).await?;
+    let (session_id, refresh_token) = session::create(&txn, user_id, device).await?;
+    txn.commit().await?;
+
+    let (token, exp) = session::access_token(&app.conn, &app.keyring, user_id, session_id).await?;
+    audit::record(
+        &app.conn,
+        AuditKind::Login,
+        Some(user_id),
+        format!("session {} by {}", session_id, provider),
+    )
+    .await;
+    Ok((token, exp, refresh_token))
+}
+
+/// Pick a free username from the one the provider suggested
+async fn create_user(conn: &impl ConnectionTrait, hasher: &Hasher, name: &str) -> Result<i32> {
+    let mut candidate = name.to_owned();
+    while User::find()
+        .filter(user::Column::Name.eq(&candidate))
+        .one(conn)
+        .await?
+        .is_some()
+    {
+        candidate = format!("{}-{:04x}", name, fastrand::u16(..));
+    }
+
+    // nobody knows the password, the provider is the only way in
+    let password = format!("{:032x}", fastrand::u128(..));
+    let user_id = User::insert(user::ActiveModel {
+        name: Set(candidate),
+        password: Set(hasher.hash_password(&password).await),
+        ..Default::default()
+    })
+    .exec(conn)
+    .await?
+    .last_insert_id;
+    workspace::join(conn, workspace::DEFAULT_WORKSPACE, user_id).await?;
+    Ok(user_id)
+}
@@ -0,0 +1,19 @@
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    routing::{get, post},
+};
+
+use crate::AppState;
+
+mod callback;
+mod providers;
+mod start;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/providers", post(providers::route))
+        .route("/{provider}", post(start::route))
+        .route("/{provider}/callback", get(callback::route))
+}
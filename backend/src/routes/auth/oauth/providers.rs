@@ -0,0 +1,32 @@
+use std::sync::Arc;
+
+use axum::{Json, extract::State};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct OauthProvidersReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct OauthProvidersResp {
+    /// `google`, `github` or `oidc`, empty if social login is disabled
+    pub list: Vec<String>,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Json(_): Json<OauthProvidersReq>,
+) -> JsonResult<OauthProvidersResp> {
+    Ok(Json(OauthProvidersResp {
+        list: app
+            .oauth
+            .providers()
+            .into_iter()
+            .map(|x| x.to_owned())
+            .collect(),
+    }))
+}
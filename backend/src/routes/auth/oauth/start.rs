@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use http::{HeaderMap, HeaderName, header};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{UserId, verify},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct OauthStartReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct OauthStartResp {
+    /// send the browser here, it come back to `/login` with the token in the fragment
+    pub url: String,
+}
+
+/// Link the identity to the current user if the request is authorized
+///
+/// Set a cookie the callback checks, so the flow ends in this browser
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Path(provider): Path<String>,
+    headers: HeaderMap,
+    Json(_): Json<OauthStartReq>,
+) -> Result<([(HeaderName, String); 1], Json<OauthStartResp>), Json<Error>> {
+    let link = match headers.get(header::AUTHORIZATION) {
+        Some(token) => {
+            let token = token.to_str().kind(ErrorKind::MalformedToken)?;
+            match verify(&app, token).await? {
+                (_, _, Some(_), _) => {
+                    return Err(Json(Error {
+                        error: ErrorKind::Unauthorized,
+                        reason: "not available in demo".to_owned(),
+                    }));
+                }
+                (UserId(user_id), _, None, _) => Some(user_id),
+            }
+        }
+        None => None,
+    };
+
+    let (url, cookie) = app
+        .oauth
+        .authorize(&provider, link)
+        .kind(ErrorKind::ResourceNotFound)?;
+
+    Ok(([(header::SET_COOKIE, cookie)], Json(OauthStartResp { url })))
+}
@@ -0,0 +1,66 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    Json,
+    extract::{ConnectInfo, State},
+};
+use entity::AuditKind;
+use http::HeaderMap;
+use schemars::JsonSchema;
+use sea_orm::TransactionTrait;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState, audit,
+    errors::*,
+    utils::session::{self, Device},
+};
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct RefreshReq {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[typeshare]
+pub struct RefreshResp {
+    pub token: String,
+    pub exp: String,
+    /// the old refresh token is revoked, use this one next time
+    pub refresh_token: String,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(req): Json<RefreshReq>,
+) -> JsonResult<RefreshResp> {
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+    let device = Device::new(&headers, addr);
+    let (user_id, session_id, refresh_token) = session::rotate(&txn, &req.refresh_token, device)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("refresh token is revoked or expired")
+        .kind(ErrorKind::MalformedToken)?;
+    txn.commit().await.kind(ErrorKind::Internal)?;
+
+    let (token, exp) = session::access_token(&app.conn, &app.keyring, user_id, session_id)
+        .await
+        .kind(ErrorKind::Internal)?;
+    audit::record(
+        &app.conn,
+        AuditKind::Refresh,
+        Some(user_id),
+        format!("session {}", session_id),
+    )
+    .await;
+
+    Ok(Json(RefreshResp {
+        token,
+        exp,
+        refresh_token,
+    }))
+}
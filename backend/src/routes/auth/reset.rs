@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use axum::{Json, extract::State};
+use entity::{prelude::*, user};
+use schemars::JsonSchema;
+use sea_orm::{ActiveValue::Set, EntityTrait, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    utils::{password_reset, session},
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ResetReq {
+    /// From the mailed link
+    pub token: String,
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ResetResp {
+    /// To sign in with the new password
+    pub username: String,
+}
+
+/// Set a new password with a mailed token, every session of the user is revoked
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Json(req): Json<ResetReq>,
+) -> JsonResult<ResetResp> {
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+
+    let user_id = password_reset::consume(&txn, &req.token)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Reset link expired, ask for a new one")
+        .kind(ErrorKind::MalformedToken)?;
+
+    let model = User::update(user::ActiveModel {
+        id: Set(user_id),
+        password: Set(app.hasher.hash_password(&req.password).await),
+        ..Default::default()
+    })
+    .exec(&txn)
+    .await
+    .kind(ErrorKind::Internal)?;
+    session::revoke_all(&txn, user_id)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    txn.commit().await.kind(ErrorKind::Internal)?;
+    app.cache.forget_user(user_id);
+
+    Ok(Json(ResetResp {
+        username: model.name,
+    }))
+}
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{prelude::*, recovery_code};
+use schemars::JsonSchema;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId, utils::totp as otp};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct TotpDisableReq {
+    /// a TOTP or recovery code
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct TotpDisableResp {
+    /// false if TOTP was not enabled
+    pub disabled: bool,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<TotpDisableReq>,
+) -> JsonResult<TotpDisableResp> {
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+    let enabled = Totp::find_by_id(user_id)
+        .one(&txn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .is_some_and(|x| x.enabled_at.is_some());
+    if enabled
+        && !otp::verify(&txn, user_id, Some(&req.code))
+            .await
+            .kind(ErrorKind::Internal)?
+    {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "Wrong code".to_owned(),
+        }));
+    }
+
+    // also drop a secret that was set up but never enabled
+    Totp::delete_by_id(user_id)
+        .exec(&txn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    RecoveryCode::delete_many()
+        .filter(recovery_code::Column::UserId.eq(user_id))
+        .exec(&txn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    txn.commit().await.kind(ErrorKind::Internal)?;
+
+    if enabled {
+        tracing::info!("User {} disabled TOTP", user_id);
+    }
+
+    Ok(Json(TotpDisableResp { disabled: enabled }))
+}
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use axum::{Extension, Json, extract::State};
+use entity::{prelude::*, totp};
+use schemars::JsonSchema;
+use sea_orm::{ActiveValue::Set, EntityTrait, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId, utils::totp as otp};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct TotpEnableReq {
+    /// from the authenticator app, proving the secret was saved
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct TotpEnableResp {
+    /// each works once in place of a code, only shown here
+    pub recovery_codes: Vec<String>,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<TotpEnableReq>,
+) -> JsonResult<TotpEnableResp> {
+    let model = Totp::find_by_id(user_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .filter(|x| x.enabled_at.is_none())
+        .context("Call /api/auth/totp/setup first")
+        .kind(ErrorKind::MalformedRequest)?;
+
+    let step = otp::check_code(&model.secret, &req.code, None)
+        .context("Wrong code")
+        .kind(ErrorKind::MalformedRequest)?;
+
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+    Totp::update(totp::ActiveModel {
+        user_id: Set(user_id),
+        enabled_at: Set(Some(time::UtcDateTime::now().unix_timestamp())),
+        last_step: Set(Some(step)),
+        ..Default::default()
+    })
+    .exec(&txn)
+    .await
+    .kind(ErrorKind::Internal)?;
+    let recovery_codes = otp::recovery_codes(&txn, user_id)
+        .await
+        .kind(ErrorKind::Internal)?;
+    txn.commit().await.kind(ErrorKind::Internal)?;
+
+    tracing::info!("User {} enabled TOTP", user_id);
+
+    Ok(Json(TotpEnableResp { recovery_codes }))
+}
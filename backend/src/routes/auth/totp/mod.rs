@@ -0,0 +1,38 @@
+use std::sync::Arc;
+
+use axum::{Router, routing::post};
+
+use crate::{AppState, utils::openapi::Builder};
+
+mod disable;
+mod enable;
+mod setup;
+mod status;
+
+/// Behind the auth middleware, unlike the rest of `/auth`
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/status", post(status::route))
+        .route("/setup", post(setup::route))
+        .route("/enable", post(enable::route))
+        .route("/disable", post(disable::route))
+}
+
+pub fn spec(api: &mut Builder) {
+    api.op("POST", "/auth/totp/status", "Whether TOTP is enabled")
+        .body::<status::TotpStatusReq>()
+        .json::<status::TotpStatusResp>();
+    api.op("POST", "/auth/totp/setup", "Start over with a new secret")
+        .body::<setup::TotpSetupReq>()
+        .json::<setup::TotpSetupResp>();
+    api.op(
+        "POST",
+        "/auth/totp/enable",
+        "Enforce the secret once a code is confirmed",
+    )
+    .body::<enable::TotpEnableReq>()
+    .json::<enable::TotpEnableResp>();
+    api.op("POST", "/auth/totp/disable", "Stop asking for codes")
+        .body::<disable::TotpDisableReq>()
+        .json::<disable::TotpDisableResp>();
+}
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use axum::{Extension, Json, extract::State};
+use entity::{prelude::*, totp};
+use schemars::JsonSchema;
+use sea_orm::{ActiveValue::Set, EntityTrait, sea_query::OnConflict};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId, utils::totp as otp};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct TotpSetupReq {}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct TotpSetupResp {
+    /// `otpauth://` uri for authenticator apps, usually shown as a QR code
+    pub uri: String,
+    /// base32, for entering by hand
+    pub secret: String,
+}
+
+/// Start over with a new secret, it is only enforced after `/enable`
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(_): Json<TotpSetupReq>,
+) -> JsonResult<TotpSetupResp> {
+    let user = User::find_by_id(user_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .context("Cannot find user")
+        .kind(ErrorKind::Internal)?;
+    let enabled = Totp::find_by_id(user_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .is_some_and(|x| x.enabled_at.is_some());
+    if enabled {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "TOTP is already enabled, disable it first".to_owned(),
+        }));
+    }
+
+    let secret = otp::secret().kind(ErrorKind::Internal)?;
+    Totp::insert(totp::ActiveModel {
+        user_id: Set(user_id),
+        secret: Set(secret.clone()),
+        enabled_at: Set(None),
+        last_step: Set(None),
+    })
+    .on_conflict(
+        OnConflict::column(totp::Column::UserId)
+            .update_columns([
+                totp::Column::Secret,
+                totp::Column::EnabledAt,
+                totp::Column::LastStep,
+            ])
+            .to_owned(),
+    )
+    .exec(&app.conn)
+    .await
+    .kind(ErrorKind::Internal)?;
+
+    Ok(Json(TotpSetupResp {
+        uri: otp::uri(&secret, &user.name),
+        secret,
+    }))
+}
@@ -0,0 +1,35 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::prelude::*;
+use schemars::JsonSchema;
+use sea_orm::EntityTrait;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct TotpStatusReq {}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct TotpStatusResp {
+    pub enabled: bool,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(_): Json<TotpStatusReq>,
+) -> JsonResult<TotpStatusResp> {
+    let totp = Totp::find_by_id(user_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    Ok(Json(TotpStatusResp {
+        enabled: totp.is_some_and(|x| x.enabled_at.is_some()),
+    }))
+}
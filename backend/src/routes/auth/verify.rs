@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use axum::{Json, extract::State};
+use entity::{prelude::*, user};
+use schemars::JsonSchema;
+use sea_orm::{ActiveValue::Set, EntityTrait, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, utils::email_verification};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct VerifyReq {
+    /// From the mailed link
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct VerifyResp {
+    pub username: String,
+    pub email: String,
+}
+
+/// Mark the address of the user verified with a mailed token
+///
+/// No login needed, the link may be opened on another device
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Json(req): Json<VerifyReq>,
+) -> JsonResult<VerifyResp> {
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+
+    let (user_id, email) = email_verification::consume(&txn, &req.token)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Verification link expired, change your email to get a new one")
+        .kind(ErrorKind::MalformedToken)?;
+
+    let model = User::find_by_id(user_id)
+        .one(&txn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("")
+        .kind(ErrorKind::ResourceNotFound)?;
+    // the address changed after the mail was sent
+    if model.email.as_deref() != Some(email.as_str()) {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedToken,
+            reason: "The link is for a previous email".to_owned(),
+        }));
+    }
+
+    User::update(user::ActiveModel {
+        id: Set(user_id),
+        email_verified: Set(true),
+        ..Default::default()
+    })
+    .exec(&txn)
+    .await
+    .kind(ErrorKind::Internal)?;
+
+    txn.commit().await.kind(ErrorKind::Internal)?;
+    app.cache.forget_user(user_id);
+
+    Ok(Json(VerifyResp {
+        username: model.name,
+        email,
+    }))
+}
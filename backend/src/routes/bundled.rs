@@ -0,0 +1,74 @@
+//! The frontend build embedded by `build.rs`, served like `ServeDir` would
+//!
+//! The `.br` and `.gz` files the build precompressed are sent to browsers
+//! accepting them. Unknown paths get `index.html`, routing is up to the
+//! frontend; everything but html is cached like `CacheControlLayer` does
+
+use std::{collections::HashMap, sync::LazyLock};
+
+use axum::{
+    body::Body,
+    http::{HeaderMap, HeaderValue, Method, StatusCode, Uri, header},
+    response::{IntoResponse, Response},
+};
+
+include!(concat!(env!("OUT_DIR"), "/bundled.rs"));
+
+static INDEX: LazyLock<HashMap<&str, &[u8]>> = LazyLock::new(|| FILES.iter().copied().collect());
+
+/// Encodings tried in order, with the extension of their files
+const ENCODINGS: [(&str, &str); 2] = [("br", "br"), ("gzip", "gz")];
+
+pub async fn route(method: Method, uri: Uri, headers: HeaderMap) -> Response {
+    if method != Method::GET && method != Method::HEAD {
+        return StatusCode::METHOD_NOT_ALLOWED.into_response();
+    }
+
+    let path = uri.path().trim_start_matches('/');
+    let path = match path.is_empty() || path.ends_with('/') {
+        true => format!("{}index.html", path),
+        false => path.to_owned(),
+    };
+    let path = match INDEX.contains_key(path.as_str()) {
+        true => path,
+        false => "index.html".to_owned(),
+    };
+    let Some(plain) = INDEX.get(path.as_str()) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let accepted = headers
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|x| x.to_str().ok())
+        .unwrap_or_default();
+    let (encoding, data) = ENCODINGS
+        .iter()
+        .filter(|(name, _)| accepted.split(',').any(|x| x.trim().starts_with(name)))
+        .find_map(|(name, ext)| {
+            let data = INDEX.get(format!("{}.{}", path, ext).as_str())?;
+            Some((Some(*name), *data))
+        })
+        .unwrap_or((None, *plain));
+
+    let content_type = mime_guess::from_path(&path).first_or_octet_stream();
+    let mut res = Response::new(match method {
+        Method::HEAD => Body::empty(),
+        _ => Body::from(data),
+    });
+    let headers = res.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(content_type.as_ref()) {
+        headers.insert(header::CONTENT_TYPE, value);
+    }
+    headers.insert(header::CONTENT_LENGTH, HeaderValue::from(data.len()));
+    headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+    if let Some(encoding) = encoding {
+        headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding));
+    }
+    if content_type != mime_guess::mime::TEXT_HTML {
+        headers.insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("max-age=604800"),
+        );
+    }
+    res
+}
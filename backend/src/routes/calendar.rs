@@ -0,0 +1,246 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::header,
+    response::IntoResponse,
+};
+use entity::{prelude::*, schedule, user};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use time::{UtcDateTime, macros::format_description};
+
+use crate::{
+    AppState,
+    config::{CALENDAR_DAYS, CALENDAR_MAX_RUNS},
+    errors::*,
+    utils::cron::Cron,
+};
+
+/// iCalendar feed of the upcoming runs of the scheduled tasks of a user, at
+/// the link of `/api/user/calendar`, without authentication
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Path(token): Path<String>,
+) -> Result<impl IntoResponse, Json<Error>> {
+    // calendar apps like the link to end as a file would
+    let token = token.strip_suffix(".ics").unwrap_or(&token);
+    let user = User::find()
+        .filter(user::Column::CalendarToken.eq(token))
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("")
+        .kind(ErrorKind::ResourceNotFound)?;
+    let tasks = Schedule::find()
+        .filter(schedule::Column::OwnerId.eq(user.id))
+        .filter(schedule::Column::Enabled.eq(true))
+        .order_by_asc(schedule::Column::Id)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let now = UtcDateTime::now().unix_timestamp();
+    let mut feed = Feed::default();
+    for task in &tasks {
+        for at in runs(task, now + CALENDAR_DAYS * 86400) {
+            feed.event(&task_event(&app.instance_id, task, at, now));
+        }
+    }
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8"),
+            // a revoked link must not be served from a cache
+            (header::CACHE_CONTROL, "no-store"),
+            (header::REFERRER_POLICY, "no-referrer"),
+            (header::HeaderName::from_static("x-robots-tag"), "noindex"),
+        ],
+        feed.finish(),
+    ))
+}
+
+/// Times the task runs at until `until`, [`CALENDAR_MAX_RUNS`] at most
+fn runs(task: &schedule::Model, until: i64) -> Vec<i64> {
+    let (Some(first), Ok(cron)) = (task.next_run_at, Cron::parse(&task.cron)) else {
+        return vec![];
+    };
+    std::iter::successors(Some(first), |x| cron.next(*x, task.utc_offset))
+        .take_while(|x| *x <= until)
+        .take(CALENDAR_MAX_RUNS)
+        .collect()
+}
+
+struct Event<'a> {
+    uid: String,
+    stamp: i64,
+    start: i64,
+    summary: &'a str,
+    description: &'a str,
+}
+
+fn task_event<'a>(instance_id: &str, task: &'a schedule::Model, at: i64, now: i64) -> Event<'a> {
+    Event {
+        uid: format!("schedule-{}-{}@{}", task.id, at, instance_id),
+        stamp: now,
+        start: at,
+        summary: &task.name,
+        description: &task.prompt,
+    }
+}
+
+/// An iCalendar (RFC 5545) document, runs are instants without an end
+struct Feed {
+    out: String,
+}
+
+impl Default for Feed {
+    fn default() -> Self {
+        let mut feed = Feed { out: String::new() };
+        feed.line("BEGIN:VCALENDAR");
+        feed.line("VERSION:2.0");
+        feed.line("PRODID:-//llumen//scheduled tasks//EN");
+        feed.line("CALSCALE:GREGORIAN");
+        feed.line("X-WR-CALNAME:llumen");
+        feed
+    }
+}
+
+impl Feed {
+    fn event(&mut self, event: &Event) {
+        self.line("BEGIN:VEVENT");
+        self.line(&format!("UID:{}", escape(&event.uid)));
+        self.line(&format!("DTSTAMP:{}", date_time(event.stamp)));
+        self.line(&format!("DTSTART:{}", date_time(event.start)));
+        self.line(&format!("SUMMARY:{}", escape(event.summary)));
+        self.line(&format!("DESCRIPTION:{}", escape(event.description)));
+        self.line("TRANSP:TRANSPARENT");
+        self.line("END:VEVENT");
+    }
+
+    fn finish(mut self) -> String {
+        self.line("END:VCALENDAR");
+        self.out
+    }
+
+    /// Fold past 75 bytes, a continuation starting with a space
+    fn line(&mut self, line: &str) {
+        let mut width = 0;
+        for c in line.chars() {
+            if width + c.len_utf8() > 75 {
+                self.out.push_str("\r\n ");
+                width = 1;
+            }
+            self.out.push(c);
+            width += c.len_utf8();
+        }
+        self.out.push_str("\r\n");
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn date_time(at: i64) -> String {
+    UtcDateTime::from_unix_timestamp(at)
+        .ok()
+        .and_then(|x| {
+            x.format(format_description!(
+                "[year][month][day]T[hour][minute][second]Z"
+            ))
+            .ok()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(cron: &str, next_run_at: Option<i64>) -> schedule::Model {
+        schedule::Model {
+            id: 7,
+            owner_id: 1,
+            model_id: 1,
+            chat_id: None,
+            name: "Morning digest".to_owned(),
+            prompt: "Summarize my mail; then, the feeds\nbriefly".to_owned(),
+            cron: cron.to_owned(),
+            utc_offset: 0,
+            enabled: true,
+            next_run_at,
+            last_run_at: None,
+            last_error: None,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn lists_runs_until_the_horizon() {
+        // 2026-10-14 08:00 UTC
+        let first = 1_791_964_800;
+        let runs = runs(&task("0 8 * * *", Some(first)), first + 3 * 86400);
+        assert_eq!(
+            runs,
+            vec![first, first + 86400, first + 2 * 86400, first + 3 * 86400]
+        );
+    }
+
+    #[test]
+    fn caps_frequent_tasks() {
+        let runs = runs(&task("* * * * *", Some(0)), 86400);
+        assert_eq!(runs.len(), CALENDAR_MAX_RUNS);
+    }
+
+    #[test]
+    fn skips_tasks_that_never_run() {
+        assert!(runs(&task("0 8 * * *", None), i64::MAX).is_empty());
+        assert!(runs(&task("not cron", Some(0)), i64::MAX).is_empty());
+    }
+
+    #[test]
+    fn escapes_text() {
+        assert_eq!(escape("a;b,c\\d\r\ne"), r"a\;b\,c\\d\ne");
+    }
+
+    #[test]
+    fn folds_long_lines() {
+        let mut feed = Feed { out: String::new() };
+        feed.line(&format!("DESCRIPTION:{}", "é".repeat(100)));
+        for line in feed.out.split("\r\n") {
+            assert!(line.len() <= 75, "{}", line);
+        }
+        assert_eq!(
+            feed.out.replace("\r\n ", ""),
+            format!("DESCRIPTION:{}\r\n", "é".repeat(100))
+        );
+    }
+
+    #[test]
+    fn renders_an_event() {
+        let task = task("0 8 * * *", Some(1_791_964_800));
+        let mut feed = Feed::default();
+        feed.event(&task_event("abc", &task, 1_791_964_800, 0));
+        let out = feed.finish();
+        assert!(out.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(out.contains("UID:schedule-7-1791964800@abc\r\n"));
+        assert!(out.contains("DTSTART:20261014T080000Z\r\n"));
+        assert!(out.contains("SUMMARY:Morning digest\r\n"));
+        assert!(out.contains(r"DESCRIPTION:Summarize my mail\; then\, the feeds\nbriefly"));
+        assert!(out.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+    }
+}
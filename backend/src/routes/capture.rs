@@ -0,0 +1,219 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State, http::HeaderMap};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use dotenv::var;
+use entity::{chat, prelude::*};
+use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    config::{CAPTURE_SELECTION_MAX_CHARS, FILE_MAX_BYTES},
+    errors::*,
+    files,
+    middlewares::auth::{ApiKeyUser, UserId, WorkspaceId},
+    routes::message::create::{self, MessageCreateReq, MessageCreateReqMode},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct CaptureReq {
+    /// Page the user is on
+    pub url: String,
+    pub title: Option<String>,
+    /// Text selected on the page
+    pub selection: Option<String>,
+    /// PNG or JPEG in base64, a data url such as the one of
+    /// `chrome.tabs.captureVisibleTab` is accepted
+    pub screenshot: Option<String>,
+    /// Without it the model is asked about the page alone
+    pub question: Option<String>,
+    /// Append to this chat, a new one is created otherwise
+    pub chat_id: Option<i32>,
+    /// Model of a new chat, default to the one of the last chat of the user
+    pub model_id: Option<i32>,
+    /// default to normal
+    pub mode: Option<MessageCreateReqMode>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct CaptureResp {
+    pub chat_id: i32,
+    /// The user message with the page, the reply stream on `/api/chat/sse`
+    pub id: i32,
+    /// Where to open the chat, absolute when `PUBLIC_URL` is set
+    pub link: String,
+}
+
+/// Ask about a page from a browser extension
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    api_key: Option<Extension<ApiKeyUser>>,
+    Json(req): Json<CaptureReq>,
+) -> JsonResult<CaptureResp> {
+    let url = url::Url::parse(req.url.trim()).kind(ErrorKind::MalformedRequest)?;
+    let screenshot = match &req.screenshot {
+        Some(data) => Some(decode_screenshot(data)?),
+        None => None,
+    };
+
+    let chat_id = match req.chat_id {
+        Some(chat_id) => chat_id,
+        None => {
+            let model_id = match req.model_id {
+                Some(model_id) => model_id,
+                None => default_model(&app, user_id, workspace_id).await?,
+            };
+            Chat::insert(chat::ActiveModel {
+                owner_id: Set(user_id),
+                workspace_id: Set(workspace_id),
+                model_id: Set(model_id),
+                title: Set(req.title.clone()),
+                reproducible: Set(false),
+                ..Default::default()
+            })
+            .exec(&app.conn)
+            .await
+            .kind(ErrorKind::Internal)?
+            .last_insert_id
+        }
+    };
+
+    let files = match screenshot {
+        Some((data, extension)) => {
+            // the model tell images by the extension
+            let file = files::create(
+                &app.conn,
+                &app.files,
+                user_id,
+                format!("screenshot.{}", extension),
+                data,
+            )
+            .await
+            .kind(ErrorKind::Internal)?;
+            vec![file.id]
+        }
+        None => vec![],
+    };
+
+    let Json(res) = create::route(
+        State(app),
+        Extension(UserId(user_id)),
+        Extension(WorkspaceId(workspace_id)),
+        api_key,
+        HeaderMap::new(),
+        Json(MessageCreateReq {
+            chat_id,
+            mode: req.mode.unwrap_or(MessageCreateReqMode::Normal),
+            text: text(
+                &url,
+                req.title.as_deref(),
+                req.selection.as_deref(),
+                req.question,
+            ),
+            files,
+        }),
+    )
+    .await?;
+
+    let path = format!("/chat/{}", chat_id);
+    let link = match var("PUBLIC_URL") {
+        Ok(base) => format!("{}{}", base.trim_end_matches('/'), path),
+        Err(_) => path,
+    };
+    Ok(Json(CaptureResp {
+        chat_id,
+        id: res.id,
+        link,
+    }))
+}
+
+/// Only images, the screenshot is sent to the model as one
+fn decode_screenshot(data: &str) -> Result<(Vec<u8>, &'static str), Json<Error>> {
+    let data = match data.split_once(";base64,") {
+        Some((_, data)) => data,
+        None => data,
+    };
+    if data.len() as u64 > FILE_MAX_BYTES / 3 * 4 + 4 {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!("screenshot is larger than {} bytes", FILE_MAX_BYTES),
+        }));
+    }
+    let data = STANDARD
+        .decode(data.trim())
+        .kind(ErrorKind::MalformedRequest)?;
+    match files::sniff(&data[..data.len().min(files::HEAD_BYTES)]) {
+        "image/png" => Ok((data, "png")),
+        "image/jpeg" => Ok((data, "jpeg")),
+        "image/webp" => Ok((data, "webp")),
+        x => Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!("screenshot is {}, expect png, jpeg or webp", x),
+        })),
+    }
+}
+
+async fn default_model(
+    app: &AppState,
+    user_id: i32,
+    workspace_id: i32,
+) -> Result<i32, Json<Error>> {
+    let last = Chat::find()
+        .filter(chat::Column::OwnerId.eq(user_id))
+        .filter(chat::Column::WorkspaceId.eq(workspace_id))
+        .order_by_desc(chat::Column::Id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    if let Some(chat) = last {
+        return Ok(chat.model_id);
+    }
+    if let Some(id) = app.settings.current().default_model_id {
+        return Ok(id);
+    }
+    Model::find()
+        .order_by_asc(entity::model::Column::Id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .map(|x| x.id)
+        .ok_or("no model is configured")
+        .kind(ErrorKind::ResourceNotFound)
+}
+
+/// The page as markdown, then the question
+fn text(
+    url: &url::Url,
+    title: Option<&str>,
+    selection: Option<&str>,
+    question: Option<String>,
+) -> String {
+    let title = title.map(str::trim).filter(|x| !x.is_empty());
+    let mut text = match title {
+        Some(title) => format!("From the page [{}]({})", title.replace(['[', ']'], ""), url),
+        None => format!("From the page <{}>", url),
+    };
+    if let Some(selection) = selection.map(str::trim).filter(|x| !x.is_empty()) {
+        text.push_str(":\n\n");
+        let selection: String = selection
+            .chars()
+            .take(CAPTURE_SELECTION_MAX_CHARS)
+            .collect();
+        let quote: Vec<_> = selection.lines().map(|x| format!("> {}", x)).collect();
+        text.push_str(&quote.join("\n"));
+    }
+    match question.as_deref().map(str::trim).filter(|x| !x.is_empty()) {
+        Some(question) => {
+            text.push_str("\n\n");
+            text.push_str(question);
+        }
+        None => text.push_str("\n\nWhat is this about?"),
+    }
+    text
+}
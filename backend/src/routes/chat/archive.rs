@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{chat, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, sea_query::Expr};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use super::pin::broadcast_status;
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ChatArchiveReq {
+    pub chat_id: i32,
+    /// false to bring it back
+    pub archived: bool,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatArchiveResp {
+    pub wrote: bool,
+}
+
+/// Archived chats are left out of `chat/paginate` unless its `archived` filter is set
+///
+/// Archiving again keep the first time
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<ChatArchiveReq>,
+) -> JsonResult<ChatArchiveResp> {
+    let archived_at = req
+        .archived
+        .then(|| time::UtcDateTime::now().unix_timestamp());
+    let res = Chat::update_many()
+        .col_expr(
+            chat::Column::ArchivedAt,
+            match archived_at {
+                Some(at) => Expr::col(chat::Column::ArchivedAt).if_null(at),
+                None => Expr::value(None::<i64>),
+            },
+        )
+        .filter(chat::Column::Id.eq(req.chat_id))
+        .filter(chat::Column::OwnerId.eq(user_id))
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let wrote = res.rows_affected > 0;
+    if wrote {
+        broadcast_status(&app, req.chat_id).await?;
+    }
+    Ok(Json(ChatArchiveResp { wrote }))
+}
@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+    utils::{branch, member},
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ChatBranchReq {
+    pub chat_id: i32,
+    /// any message of the branch, e.g. one of `siblings`
+    pub message_id: i32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatBranchResp {
+    /// last message of the branch now shown
+    pub head_id: i32,
+}
+
+/// Show the branch a message is on, down to its latest message
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Json(req): Json<ChatBranchReq>,
+) -> JsonResult<ChatBranchResp> {
+    member::find(&app.conn, req.chat_id, user_id, workspace_id).await?;
+
+    // the reply being written is appended to the head
+    if app.sse.is_publishing(req.chat_id).await {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "Cannot switch branch while a reply is generating".to_owned(),
+        }));
+    }
+
+    let head_id = branch::latest_leaf(&app.conn, req.chat_id, req.message_id)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Cannot find the message in the chat")
+        .kind(ErrorKind::ResourceNotFound)?;
+    branch::checkout(&app.conn, req.chat_id, Some(head_id))
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    Ok(Json(ChatBranchResp { head_id }))
+}
@@ -1,20 +1,31 @@
 use std::sync::Arc;
 
 use axum::{Extension, Json, extract::State};
-use entity::{chat, prelude::*};
-use sea_orm::{ActiveValue::Set, EntityTrait};
+use entity::{chat, persona, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter};
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-use crate::{AppState, errors::*, middlewares::auth::UserId};
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{DemoUser, UserId, WorkspaceId},
+    utils::workspace,
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct ChatCreateReq {
     pub model_id: i32,
+    /// pin model, params and seed on every message, default to false
+    pub reproducible: Option<bool>,
+    /// Persona of the user the chat talks as, its model if it has one stands
+    /// in for `model_id`
+    pub persona_id: Option<i32>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct ChatCreateResp {
     pub id: i32,
@@ -23,12 +34,48 @@ pub struct ChatCreateResp {
 pub async fn route(
     State(app): State<Arc<AppState>>,
     Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    demo_user: Option<Extension<DemoUser>>,
     Json(req): Json<ChatCreateReq>,
 ) -> JsonResult<ChatCreateResp> {
+    // demo chats all use the demo assistant
+    let persona = match req.persona_id {
+        Some(persona_id) => Some(
+            Persona::find_by_id(persona_id)
+                .filter(persona::Column::OwnerId.eq(user_id))
+                .one(&app.conn)
+                .await
+                .kind(ErrorKind::Internal)?
+                .ok_or("Cannot find the persona")
+                .kind(ErrorKind::ResourceNotFound)?,
+        ),
+        None => None,
+    };
+    let model_id = match (demo_user, app.demo.as_ref().and_then(|x| x.model_id)) {
+        (Some(_), Some(model_id)) => model_id,
+        _ => persona
+            .as_ref()
+            .and_then(|x| x.model_id)
+            .unwrap_or(req.model_id),
+    };
+
+    if !workspace::allows_model(&app.conn, workspace_id, model_id)
+        .await
+        .kind(ErrorKind::Internal)?
+    {
+        return Err(Json(Error {
+            error: ErrorKind::Unauthorized,
+            reason: "the model is not offered in this workspace".to_owned(),
+        }));
+    }
+
     let chat_id = Chat::insert(chat::ActiveModel {
         owner_id: Set(user_id),
-        model_id: Set(req.model_id),
+        workspace_id: Set(workspace_id),
+        model_id: Set(model_id),
         title: Set(None),
+        reproducible: Set(req.reproducible.unwrap_or_default()),
+        persona_id: Set(persona.map(|x| x.id)),
         ..Default::default()
     })
     .exec(&app.conn)
@@ -2,36 +2,56 @@ use std::sync::Arc;
 
 use axum::{Extension, Json, extract::State};
 use entity::chat;
-use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use schemars::JsonSchema;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, TransactionTrait};
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-use crate::{AppState, errors::*, middlewares::auth::UserId};
+use crate::{AppState, errors::*, middlewares::auth::UserId, trash, undo};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct ChatDeleteReq {
     pub id: i32,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct ChatDeleteResp {
     pub deleted: bool,
+    /// `POST /api/undo/{token}` within `UNDO_WINDOW` restore the chat
+    pub undo_token: Option<String>,
 }
 
+/// Move the chat to the trash
 pub async fn route(
     State(app): State<Arc<AppState>>,
     Extension(UserId(user_id)): Extension<UserId>,
     Json(req): Json<ChatDeleteReq>,
 ) -> JsonResult<ChatDeleteResp> {
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+    let snapshot = undo::snapshot_chat(&txn, user_id, req.id)
+        .await
+        .kind(ErrorKind::Internal)?;
+
     let result = chat::Entity::delete_by_id(req.id)
         .filter(chat::Column::OwnerId.eq(user_id))
-        .exec(&app.conn)
+        .exec(&txn)
         .await
         .kind(ErrorKind::Internal)?;
 
     let deleted = result.rows_affected > 0;
+    let trash = match deleted {
+        true => trash::put(&txn, user_id, snapshot.into_iter().collect())
+            .await
+            .kind(ErrorKind::Internal)?,
+        false => vec![],
+    };
+    txn.commit().await.kind(ErrorKind::Internal)?;
+    let undo_token = app.undo.stage(user_id, trash).kind(ErrorKind::Internal)?;
 
-    Ok(Json(ChatDeleteResp { deleted }))
+    Ok(Json(ChatDeleteResp {
+        deleted,
+        undo_token,
+    }))
 }
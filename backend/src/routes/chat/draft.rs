@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+};
+use entity::{chat_draft, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{ActiveValue::Set, DbConn, EntityTrait, sea_query::OnConflict};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    config::CHAT_DRAFT_MAX_CHARS,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+    notify::{Notification, NotificationDraftUpdated},
+    utils::member,
+};
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatDraftResp {
+    /// Empty without a draft
+    pub content: String,
+    /// None without a draft
+    pub updated_at: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ChatDraftWriteReq {
+    /// An empty text removes the draft
+    pub content: String,
+}
+
+/// The message the user was writing in the chat, on any device
+pub async fn read(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Path(id): Path<i32>,
+) -> JsonResult<ChatDraftResp> {
+    member::find(&app.conn, id, user_id, workspace_id).await?;
+    let draft = ChatDraft::find_by_id((id, user_id))
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    Ok(Json(match draft {
+        Some(draft) => ChatDraftResp {
+            content: draft.content,
+            updated_at: Some(draft.updated_at),
+        },
+        None => ChatDraftResp {
+            content: String::new(),
+            updated_at: None,
+        },
+    }))
+}
+
+/// Replace the draft, the other devices of the user get `draft_updated` on
+/// `user/notifications`
+pub async fn write(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Path(id): Path<i32>,
+    Json(req): Json<ChatDraftWriteReq>,
+) -> JsonResult<ChatDraftResp> {
+    member::find(&app.conn, id, user_id, workspace_id).await?;
+    if req.content.chars().count() > CHAT_DRAFT_MAX_CHARS {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!("Draft is longer than {} characters", CHAT_DRAFT_MAX_CHARS),
+        }));
+    }
+    if req.content.trim().is_empty() {
+        clear(&app, id, user_id).await.kind(ErrorKind::Internal)?;
+        return Ok(Json(ChatDraftResp {
+            content: String::new(),
+            updated_at: None,
+        }));
+    }
+
+    let now = time::UtcDateTime::now().unix_timestamp();
+    ChatDraft::insert(chat_draft::ActiveModel {
+        chat_id: Set(id),
+        user_id: Set(user_id),
+        content: Set(req.content.clone()),
+        updated_at: Set(now),
+    })
+    .on_conflict(
+        OnConflict::columns([chat_draft::Column::ChatId, chat_draft::Column::UserId])
+            .update_columns([chat_draft::Column::Content, chat_draft::Column::UpdatedAt])
+            .to_owned(),
+    )
+    .exec(&app.conn)
+    .await
+    .kind(ErrorKind::Internal)?;
+    notify(&app, id, user_id, req.content.clone(), now);
+
+    Ok(Json(ChatDraftResp {
+        content: req.content,
+        updated_at: Some(now),
+    }))
+}
+
+/// Drop the draft of the user once their message is sent
+pub async fn clear(app: &AppState, chat_id: i32, user_id: i32) -> Result<(), sea_orm::DbErr> {
+    if delete(&app.conn, chat_id, user_id).await? {
+        let now = time::UtcDateTime::now().unix_timestamp();
+        notify(app, chat_id, user_id, String::new(), now);
+    }
+    Ok(())
+}
+
+/// Whether there was a draft
+async fn delete(conn: &DbConn, chat_id: i32, user_id: i32) -> Result<bool, sea_orm::DbErr> {
+    let res = ChatDraft::delete_by_id((chat_id, user_id))
+        .exec(conn)
+        .await?;
+    Ok(res.rows_affected > 0)
+}
+
+fn notify(app: &AppState, chat_id: i32, user_id: i32, content: String, updated_at: i64) {
+    app.notifier.send(
+        user_id,
+        Notification::DraftUpdated(NotificationDraftUpdated {
+            chat_id,
+            content,
+            updated_at,
+        }),
+    );
+}
@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::header,
+    response::IntoResponse,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+    utils::{export::Transcript, member},
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ChatExportReq {
+    pub format: ChatExportReqFormat,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum ChatExportReqFormat {
+    Md,
+    Html,
+    /// with the tool calls, for programs
+    Json,
+    /// only with the `pdf` feature
+    Pdf,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Path(id): Path<i32>,
+    Query(req): Query<ChatExportReq>,
+) -> Result<impl IntoResponse, Json<Error>> {
+    // private messages of the other members are left out
+    let (chat, _) = member::find(&app.conn, id, user_id, workspace_id).await?;
+
+    let transcript = Transcript::load(&app.conn, app.instance_id.clone(), &chat, user_id)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let (content_type, extension, body) = match req.format {
+        ChatExportReqFormat::Md => (
+            "text/markdown; charset=utf-8",
+            "md",
+            transcript.markdown().into_bytes(),
+        ),
+        ChatExportReqFormat::Html => (
+            "text/html; charset=utf-8",
+            "html",
+            transcript.html().into_bytes(),
+        ),
+        ChatExportReqFormat::Json => ("application/json", "json", transcript.json().into_bytes()),
+        ChatExportReqFormat::Pdf => ("application/pdf", "pdf", pdf(&transcript).await?),
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_owned()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"chat-{}.{}\"", id, extension),
+            ),
+        ],
+        body,
+    ))
+}
+
+#[cfg(feature = "pdf")]
+async fn pdf(transcript: &Transcript) -> Result<Vec<u8>, Json<Error>> {
+    crate::utils::pdf::html_to_pdf(transcript.html())
+        .await
+        .kind(ErrorKind::Internal)
+}
+
+#[cfg(not(feature = "pdf"))]
+async fn pdf(_: &Transcript) -> Result<Vec<u8>, Json<Error>> {
+    Err(Json(Error {
+        error: ErrorKind::MalformedRequest,
+        reason: "PDF export is not enabled on this server".to_owned(),
+    }))
+}
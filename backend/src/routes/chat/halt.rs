@@ -1,40 +1,38 @@
 use std::sync::Arc;
 
 use axum::{Extension, Json, extract::State};
-use entity::prelude::*;
-use sea_orm::EntityTrait;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-use crate::{AppState, errors::*, middlewares::auth::UserId};
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+    utils::member,
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct ChatHaltReq {
     pub id: i32,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
-pub struct ChatHaltResp {}
+pub struct ChatHaltResp {
+    /// false if the chat is not generating
+    pub halted: bool,
+}
 
 pub async fn route(
     State(app): State<Arc<AppState>>,
     Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
     Json(req): Json<ChatHaltReq>,
 ) -> JsonResult<ChatHaltResp> {
-    let res = Chat::find_by_id(req.id)
-        .one(&app.conn)
-        .await
-        .kind(ErrorKind::Internal)?;
-
-    if res.is_none_or(|x| x.owner_id != user_id) {
-        return Err(Json(Error {
-            error: ErrorKind::ResourceNotFound,
-            reason: "".to_owned(),
-        }));
-    }
+    member::find(&app.conn, req.id, user_id, workspace_id).await?;
 
-    app.sse.halt(req.id).await;
-    Ok(Json(ChatHaltResp {}))
+    let halted = app.sse.halt(req.id).await;
+    Ok(Json(ChatHaltResp { halted }))
 }
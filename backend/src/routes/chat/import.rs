@@ -0,0 +1,341 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::bail;
+use axum::{Extension, Json, extract::State};
+use entity::{
+    Generation, MessageKind, ToolCall, chat, chunk, message, patch::ChunkKind, prelude::*,
+};
+use schemars::JsonSchema;
+use sea_orm::{ActiveValue::Set, ConnectionTrait, DbErr, EntityTrait, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    config::CHAT_IMPORT_MAX_CHATS,
+    errors::*,
+    middlewares::auth::{DemoUser, UserId, WorkspaceId},
+    utils::workspace,
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ChatImportReq {
+    /// model of the imported chats
+    pub model_id: i32,
+    pub format: ChatImportReqFormat,
+    /// content of the exported file
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum ChatImportReqFormat {
+    /// `json` export of a chat, or the export of every chat
+    Llumen,
+    /// `conversations.json` of a ChatGPT data export
+    Chatgpt,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatImportResp {
+    /// in the order of the file
+    pub ids: Vec<i32>,
+}
+
+/// A chat ready to be inserted
+struct Imported {
+    title: Option<String>,
+    messages: Vec<ImportedMessage>,
+}
+
+struct ImportedMessage {
+    kind: MessageKind,
+    created_at: i64,
+    chunks: Vec<(ChunkKind, String)>,
+    generation: Option<String>,
+}
+
+/// Recreate exported chats under the current user, nothing is written if any
+/// chat fails to parse
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    demo_user: Option<Extension<DemoUser>>,
+    Json(req): Json<ChatImportReq>,
+) -> JsonResult<ChatImportResp> {
+    if demo_user.is_some() {
+        return Err(Json(Error {
+            error: ErrorKind::Unauthorized,
+            reason: "Demo sessions cannot import chats".to_owned(),
+        }));
+    }
+    Model::find_by_id(req.model_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Cannot find the model")
+        .kind(ErrorKind::ResourceNotFound)?;
+
+    let chats = match req.format {
+        ChatImportReqFormat::Llumen => parse_llumen(&req.data),
+        ChatImportReqFormat::Chatgpt => parse_chatgpt(&req.data),
+    }
+    .kind(ErrorKind::MalformedRequest)?;
+    if chats.len() > CHAT_IMPORT_MAX_CHATS {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!(
+                "At most {} chats can be imported at once",
+                CHAT_IMPORT_MAX_CHATS
+            ),
+        }));
+    }
+
+    if !workspace::allows_model(&app.conn, workspace_id, req.model_id)
+        .await
+        .kind(ErrorKind::Internal)?
+    {
+        return Err(Json(Error {
+            error: ErrorKind::Unauthorized,
+            reason: "the model is not offered in this workspace".to_owned(),
+        }));
+    }
+
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+    let mut ids = Vec::with_capacity(chats.len());
+    for imported in chats {
+        ids.push(
+            insert(&txn, user_id, workspace_id, req.model_id, imported)
+                .await
+                .kind(ErrorKind::Internal)?,
+        );
+    }
+    txn.commit().await.kind(ErrorKind::Internal)?;
+
+    Ok(Json(ChatImportResp { ids }))
+}
+
+async fn insert(
+    conn: &impl ConnectionTrait,
+    user_id: i32,
+    workspace_id: i32,
+    model_id: i32,
+    imported: Imported,
+) -> Result<i32, DbErr> {
+    let chat_id = Chat::insert(chat::ActiveModel {
+        owner_id: Set(user_id),
+        workspace_id: Set(workspace_id),
+        model_id: Set(model_id),
+        title: Set(imported.title),
+        ..Default::default()
+    })
+    .exec(conn)
+    .await?
+    .last_insert_id;
+
+    let mut parent_id = None;
+    for message in imported.messages {
+        let message_id = Message::insert(message::ActiveModel {
+            chat_id: Set(chat_id),
+            kind: Set(message.kind),
+            generation: Set(message.generation),
+            created_at: Set(message.created_at),
+            parent_id: Set(parent_id),
+            ..Default::default()
+        })
+        .exec(conn)
+        .await?
+        .last_insert_id;
+        parent_id = Some(message_id);
+
+        if !message.chunks.is_empty() {
+            Chunk::insert_many(message.chunks.into_iter().map(|(kind, content)| {
+                chunk::ActiveModel {
+                    content: Set(content),
+                    kind: Set(kind),
+                    message_id: Set(message_id),
+                    ..Default::default()
+                }
+            }))
+            .exec(conn)
+            .await?;
+        }
+    }
+    Ok(chat_id)
+}
+
+#[derive(Deserialize)]
+struct Export {
+    provenance: ExportProvenance,
+    messages: Vec<ExportMessage>,
+}
+
+#[derive(Deserialize)]
+struct ExportProvenance {
+    title: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ExportMessage {
+    role: String,
+    #[serde(default)]
+    created_at: i64,
+    parts: Vec<ExportPart>,
+    generation: Option<Generation>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ExportPart {
+    Text {
+        content: String,
+    },
+    ToolCall {
+        name: String,
+        args: String,
+        result: String,
+    },
+}
+
+/// A single chat or the array of every chat, see `utils::export`
+fn parse_llumen(data: &str) -> anyhow::Result<Vec<Imported>> {
+    // not untagged, its errors do not tell what is wrong
+    let exports: Vec<Export> = match data.trim_start().starts_with('[') {
+        true => serde_json::from_str(data)?,
+        false => vec![serde_json::from_str(data)?],
+    };
+
+    let mut chats = Vec::with_capacity(exports.len());
+    for export in exports {
+        let mut messages = Vec::with_capacity(export.messages.len());
+        for (idx, message) in export.messages.into_iter().enumerate() {
+            let kind = match message.role.as_str() {
+                "User" => MessageKind::User,
+                "Assistant" => MessageKind::Assistant,
+                "System" => MessageKind::Marker,
+                role => bail!("Unknown role `{}`", role),
+            };
+            let mut chunks = Vec::with_capacity(message.parts.len());
+            for (part_idx, part) in message.parts.into_iter().enumerate() {
+                chunks.push(match part {
+                    ExportPart::Text { content } => (ChunkKind::Text, content),
+                    ExportPart::ToolCall { name, args, result } => {
+                        // the original ids are not exported, they only need to pair call and result
+                        let tool_call = ToolCall {
+                            id: format!("call_import_{}_{}", idx, part_idx),
+                            name,
+                            args,
+                            content: result,
+                        };
+                        (ChunkKind::ToolCall, serde_json::to_string(&tool_call)?)
+                    }
+                });
+            }
+            messages.push(ImportedMessage {
+                kind,
+                created_at: message.created_at,
+                chunks,
+                generation: message
+                    .generation
+                    .map(|x| serde_json::to_string(&x))
+                    .transpose()?,
+            });
+        }
+        chats.push(Imported {
+            title: export.provenance.title,
+            messages,
+        });
+    }
+    Ok(chats)
+}
+
+#[derive(Deserialize)]
+struct ChatgptConversation {
+    title: Option<String>,
+    mapping: HashMap<String, ChatgptNode>,
+    current_node: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatgptNode {
+    message: Option<ChatgptMessage>,
+    parent: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatgptMessage {
+    author: ChatgptAuthor,
+    create_time: Option<f64>,
+    content: ChatgptContent,
+}
+
+#[derive(Deserialize)]
+struct ChatgptAuthor {
+    role: String,
+}
+
+#[derive(Deserialize)]
+struct ChatgptContent {
+    content_type: String,
+    #[serde(default)]
+    parts: Vec<Value>,
+}
+
+/// Only the branch the user was last on, and only its text
+///
+/// System and tool messages, images and the hidden context ChatGPT inject are
+/// skipped
+fn parse_chatgpt(data: &str) -> anyhow::Result<Vec<Imported>> {
+    let conversations: Vec<ChatgptConversation> = serde_json::from_str(data)?;
+
+    let mut chats = Vec::with_capacity(conversations.len());
+    for mut conversation in conversations {
+        // walk up from the leaf, the mapping is a tree of every edit and regeneration
+        let mut branch = vec![];
+        let mut cursor = conversation.current_node.take();
+        while let Some(node) = cursor.and_then(|id| conversation.mapping.remove(&id)) {
+            cursor = node.parent;
+            branch.extend(node.message);
+        }
+        branch.reverse();
+
+        let messages = branch
+            .into_iter()
+            .filter(|x| x.content.content_type == "text")
+            .filter_map(|message| {
+                let kind = match message.author.role.as_str() {
+                    "user" => MessageKind::User,
+                    "assistant" => MessageKind::Assistant,
+                    _ => return None,
+                };
+                let text = message
+                    .content
+                    .parts
+                    .iter()
+                    .filter_map(|x| x.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n\n");
+                if text.trim().is_empty() {
+                    return None;
+                }
+                Some(ImportedMessage {
+                    kind,
+                    created_at: message.create_time.unwrap_or_default() as i64,
+                    chunks: vec![(ChunkKind::Text, text)],
+                    generation: None,
+                })
+            })
+            .collect();
+
+        chats.push(Imported {
+            title: conversation.title,
+            messages,
+        });
+    }
+    Ok(chats)
+}
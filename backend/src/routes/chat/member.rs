@@ -0,0 +1,174 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+};
+use entity::{ChatMemberRole, chat_member, prelude::*, user};
+use schemars::JsonSchema;
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, sea_query::OnConflict,
+};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+    utils::member,
+};
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatMemberListResp {
+    /// the creator first, then by the time they joined
+    pub list: Vec<ChatMemberListRespItem>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatMemberListRespItem {
+    pub user_id: i32,
+    pub name: String,
+    pub role: ChatMemberRole,
+    /// the creator cannot be removed
+    pub creator: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ChatMemberAddReq {
+    /// username of the account to add
+    pub name: String,
+    /// adding a member again change their role
+    pub role: ChatMemberRole,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatMemberAddResp {
+    pub user_id: i32,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ChatMemberRemoveReq {
+    pub user_id: i32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatMemberRemoveResp {
+    /// false if the user was not a member
+    pub removed: bool,
+}
+
+/// Everyone taking part in the chat, visible to every member
+pub async fn list(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Path(id): Path<i32>,
+) -> JsonResult<ChatMemberListResp> {
+    let (chat, _) = member::find(&app.conn, id, user_id, workspace_id).await?;
+
+    let creator = User::find_by_id(chat.owner_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let members = ChatMember::find()
+        .find_also_related(User)
+        .filter(chat_member::Column::ChatId.eq(id))
+        .order_by_asc(chat_member::Column::JoinedAt)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let creator = creator.map(|x| ChatMemberListRespItem {
+        user_id: x.id,
+        name: x.name,
+        role: ChatMemberRole::Owner,
+        creator: true,
+    });
+    let members = members.into_iter().filter_map(|(member, user)| {
+        user.map(|x| ChatMemberListRespItem {
+            user_id: x.id,
+            name: x.name,
+            role: member.role,
+            creator: false,
+        })
+    });
+    Ok(Json(ChatMemberListResp {
+        list: creator.into_iter().chain(members).collect(),
+    }))
+}
+
+/// Let another account read and write the chat, only owners add members
+pub async fn add(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Path(id): Path<i32>,
+    Json(req): Json<ChatMemberAddReq>,
+) -> JsonResult<ChatMemberAddResp> {
+    let chat = member::owned(&app.conn, id, user_id, workspace_id).await?;
+
+    // accounts being purged cannot be added
+    let user = User::find()
+        .filter(user::Column::Name.eq(req.name.trim()))
+        .filter(user::Column::PurgeAt.is_null())
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Cannot find the user")
+        .kind(ErrorKind::ResourceNotFound)?;
+    if user.id == chat.owner_id {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "The creator of the chat is always an owner".to_owned(),
+        }));
+    }
+
+    ChatMember::insert(chat_member::ActiveModel {
+        chat_id: Set(id),
+        user_id: Set(user.id),
+        role: Set(req.role),
+        joined_at: Set(time::UtcDateTime::now().unix_timestamp()),
+    })
+    .on_conflict(
+        OnConflict::columns([chat_member::Column::ChatId, chat_member::Column::UserId])
+            .update_column(chat_member::Column::Role)
+            .to_owned(),
+    )
+    .exec(&app.conn)
+    .await
+    .kind(ErrorKind::Internal)?;
+
+    Ok(Json(ChatMemberAddResp { user_id: user.id }))
+}
+
+/// Owners remove anyone but the creator, members only leave
+pub async fn remove(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Path(id): Path<i32>,
+    Json(req): Json<ChatMemberRemoveReq>,
+) -> JsonResult<ChatMemberRemoveResp> {
+    let (_, role) = member::find(&app.conn, id, user_id, workspace_id).await?;
+    if req.user_id != user_id && role != ChatMemberRole::Owner {
+        return Err(Json(Error {
+            error: ErrorKind::Unauthorized,
+            reason: "Only owners of the chat can do this".to_owned(),
+        }));
+    }
+
+    let res = ChatMember::delete_by_id((id, req.user_id))
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    Ok(Json(ChatMemberRemoveResp {
+        removed: res.rows_affected > 0,
+    }))
+}
@@ -0,0 +1,206 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{MessageKind, chat, chunk, link, message, patch::ChunkKind, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{
+    ActiveValue::Set, JoinType, QueryOrder, QuerySelect, RelationTrait, TransactionTrait,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+    utils::{self, branch},
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ChatMergeReq {
+    pub first_id: i32,
+    pub second_id: i32,
+    /// default to be generated after the next message
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatMergeResp {
+    pub id: i32,
+}
+
+/// Concatenate the active branches of two chats chronologically into a new
+/// one, a marker message is inserted wherever the source chat changes
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Json(req): Json<ChatMergeReq>,
+) -> JsonResult<ChatMergeResp> {
+    if req.first_id == req.second_id {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "Cannot merge a chat with itself".to_owned(),
+        }));
+    }
+
+    let chats = Chat::find()
+        .filter(chat::Column::Id.is_in([req.first_id, req.second_id]))
+        .filter(chat::Column::OwnerId.eq(user_id))
+        .filter(chat::Column::WorkspaceId.eq(workspace_id))
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let (Some(first), Some(second)) = (
+        chats.iter().find(|x| x.id == req.first_id),
+        chats.iter().find(|x| x.id == req.second_id),
+    ) else {
+        return Err(Json(Error {
+            error: ErrorKind::ResourceNotFound,
+            reason: "".to_owned(),
+        }));
+    };
+
+    let mut ids = branch::active(&app.conn, first.id)
+        .await
+        .kind(ErrorKind::Internal)?;
+    ids.extend(
+        branch::active(&app.conn, second.id)
+            .await
+            .kind(ErrorKind::Internal)?,
+    );
+    // private messages of members stay in their chat, the owner cannot read them
+    let messages = Message::find()
+        .join(JoinType::InnerJoin, message::Relation::Chat.def())
+        .filter(message::Column::Id.is_in(ids))
+        .filter(utils::message::visible_to(user_id))
+        .order_by_asc(message::Column::Id)
+        .find_with_related(Chunk)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let links = Link::find()
+        .filter(link::Column::MessageId.is_in(messages.iter().map(|(message, _)| message.id)))
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+
+    let chat_id = Chat::insert(chat::ActiveModel {
+        owner_id: Set(user_id),
+        workspace_id: Set(workspace_id),
+        model_id: Set(first.model_id),
+        title: Set(req.title),
+        ..Default::default()
+    })
+    .exec(&txn)
+    .await
+    .kind(ErrorKind::Internal)?
+    .last_insert_id;
+
+    let mut source = None;
+    let mut parent_id = None;
+    for (message, chunks) in messages {
+        if source != Some(message.chat_id) {
+            source = Some(message.chat_id);
+            let origin = match message.chat_id == first.id {
+                true => first,
+                false => second,
+            };
+            parent_id = Some(
+                insert_marker(&txn, chat_id, parent_id, origin)
+                    .await
+                    .kind(ErrorKind::Internal)?,
+            );
+        }
+
+        let message_id = Message::insert(message::ActiveModel {
+            chat_id: Set(chat_id),
+            kind: Set(message.kind),
+            generation: Set(message.generation),
+            truncated: Set(message.truncated),
+            private: Set(message.private),
+            author_id: Set(message.author_id),
+            created_at: Set(message.created_at),
+            tokens: Set(message.tokens),
+            parent_id: Set(parent_id),
+            ..Default::default()
+        })
+        .exec(&txn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .last_insert_id;
+        parent_id = Some(message_id);
+
+        if !chunks.is_empty() {
+            Chunk::insert_many(chunks.into_iter().map(|chunk| chunk::ActiveModel {
+                content: Set(chunk.content),
+                kind: Set(chunk.kind),
+                message_id: Set(message_id),
+                ..Default::default()
+            }))
+            .exec(&txn)
+            .await
+            .kind(ErrorKind::Internal)?;
+        }
+
+        let links: Vec<_> = links
+            .iter()
+            .filter(|link| link.message_id == message.id)
+            .map(|link| link::ActiveModel {
+                message_id: Set(message_id),
+                kind: Set(link.kind),
+                entity_id: Set(link.entity_id.clone()),
+                label: Set(link.label.clone()),
+                ..Default::default()
+            })
+            .collect();
+        if !links.is_empty() {
+            Link::insert_many(links)
+                .exec(&txn)
+                .await
+                .kind(ErrorKind::Internal)?;
+        }
+    }
+
+    txn.commit().await.kind(ErrorKind::Internal)?;
+
+    Ok(Json(ChatMergeResp { id: chat_id }))
+}
+
+async fn insert_marker(
+    conn: &impl ConnectionTrait,
+    chat_id: i32,
+    parent_id: Option<i32>,
+    origin: &chat::Model,
+) -> Result<i32, DbErr> {
+    let content = match &origin.title {
+        Some(title) => format!("Merged from chat \"{}\" (#{})", title, origin.id),
+        None => format!("Merged from chat #{}", origin.id),
+    };
+
+    let message_id = Message::insert(message::ActiveModel {
+        chat_id: Set(chat_id),
+        kind: Set(MessageKind::Marker),
+        created_at: Set(time::UtcDateTime::now().unix_timestamp()),
+        parent_id: Set(parent_id),
+        ..Default::default()
+    })
+    .exec(conn)
+    .await?
+    .last_insert_id;
+
+    Chunk::insert(chunk::ActiveModel {
+        content: Set(content),
+        kind: Set(ChunkKind::Text),
+        message_id: Set(message_id),
+        ..Default::default()
+    })
+    .exec(conn)
+    .await?;
+    Ok(message_id)
+}
@@ -1,16 +1,34 @@
+mod archive;
+mod branch;
 mod create;
 mod delete;
-mod halt;
+pub mod draft;
+mod export;
+pub mod halt;
+mod import;
+mod member;
+mod merge;
 mod paginate;
+mod pin;
 mod read;
-mod sse;
+mod resume;
+mod settings;
+mod share;
+pub mod tags;
+pub mod sse;
+mod tool_input;
+mod trash;
+mod voice;
 mod write;
 
 use std::sync::Arc;
 
-use axum::{Router, routing::post};
+use axum::{
+    Router,
+    routing::{get, post},
+};
 
-use crate::AppState;
+use crate::{AppState, sse::SseEvent, utils::openapi::Builder};
 
 pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
@@ -21,4 +39,170 @@ pub fn routes() -> Router<Arc<AppState>> {
         .route("/create", post(create::route))
         .route("/halt", post(halt::route))
         .route("/write", post(write::route))
+        .route("/merge", post(merge::route))
+        .route("/branch", post(branch::route))
+        .route("/tags", post(tags::route))
+        .route("/pin", post(pin::route))
+        .route("/archive", post(archive::route))
+        .route("/trash/list", post(trash::list))
+        .route("/trash/restore", post(trash::restore))
+        // limited like uploads, see `middlewares::body_limit`
+        .route("/import", post(import::route))
+        .route("/{id}/export", get(export::route))
+        .route("/{id}/draft", get(draft::read).put(draft::write))
+        .route(
+            "/{id}/member",
+            get(member::list).post(member::add).delete(member::remove),
+        )
+        .route("/{id}/settings", get(settings::read).post(settings::write))
+        .route("/{id}/continue", post(resume::route))
+        .route("/{id}/share", post(share::create).delete(share::revoke))
+        .route("/{id}/tool/{call_id}/input", post(tool_input::route))
+        .route("/{id}/voice", post(voice::route))
+}
+
+/// Describe the routes above, served by `routes::openapi`
+pub fn spec(api: &mut Builder) {
+    api.op(
+        "POST",
+        "/chat/sse",
+        "Follow the replies of a chat as they stream",
+    )
+    .body::<sse::SseReq>()
+    .events::<SseEvent>();
+    api.op("POST", "/chat/delete", "Move the chat to the trash")
+        .body::<delete::ChatDeleteReq>()
+        .json::<delete::ChatDeleteResp>();
+    api.op("POST", "/chat/paginate", "List the chats of the user")
+        .body::<paginate::ChatPaginateReq>()
+        .json::<paginate::ChatPaginateResp>();
+    api.op("POST", "/chat/read", "Read a chat")
+        .body::<read::ChatReadReq>()
+        .json::<read::ChatReadResp>();
+    api.op("POST", "/chat/create", "Create a chat")
+        .body::<create::ChatCreateReq>()
+        .json::<create::ChatCreateResp>();
+    api.op("POST", "/chat/halt", "Stop the reply being generated")
+        .body::<halt::ChatHaltReq>()
+        .json::<halt::ChatHaltResp>();
+    api.op("POST", "/chat/write", "Rename a chat")
+        .body::<write::ChatUpdateReq>()
+        .json::<write::ChatUpdateResp>();
+    api.op(
+        "POST",
+        "/chat/merge",
+        "Concatenate two chats into a new one",
+    )
+    .body::<merge::ChatMergeReq>()
+    .json::<merge::ChatMergeResp>();
+    api.op("POST", "/chat/branch", "Show the branch a message is on")
+        .body::<branch::ChatBranchReq>()
+        .json::<branch::ChatBranchResp>();
+    api.op("POST", "/chat/tags", "Tags of the chats of the user")
+        .body::<tags::ChatTagsReq>()
+        .json::<tags::ChatTagsResp>();
+    api.op("POST", "/chat/pin", "Pin or unpin a chat")
+        .body::<pin::ChatPinReq>()
+        .json::<pin::ChatPinResp>();
+    api.op("POST", "/chat/archive", "Archive or unarchive a chat")
+        .body::<archive::ChatArchiveReq>()
+        .json::<archive::ChatArchiveResp>();
+    api.op(
+        "POST",
+        "/chat/trash/list",
+        "Chats and messages the user deleted",
+    )
+    .body::<trash::ChatTrashListReq>()
+    .json::<trash::ChatTrashListResp>();
+    api.op(
+        "POST",
+        "/chat/trash/restore",
+        "Put a deleted chat or message back",
+    )
+    .body::<trash::ChatTrashRestoreReq>()
+    .json::<trash::ChatTrashRestoreResp>();
+    api.op("POST", "/chat/import", "Recreate exported chats")
+        .body::<import::ChatImportReq>()
+        .json::<import::ChatImportResp>();
+    api.op("GET", "/chat/{id}/export", "Download a chat")
+        .query::<export::ChatExportReq>()
+        .file("application/octet-stream");
+    api.op(
+        "GET",
+        "/chat/{id}/draft",
+        "Message the user was writing in the chat",
+    )
+    .json::<draft::ChatDraftResp>();
+    api.op(
+        "PUT",
+        "/chat/{id}/draft",
+        "Save the message the user is writing, an empty one removes it",
+    )
+    .body::<draft::ChatDraftWriteReq>()
+    .json::<draft::ChatDraftResp>();
+    api.op(
+        "GET",
+        "/chat/{id}/member",
+        "Everyone taking part in the chat",
+    )
+    .json::<member::ChatMemberListResp>();
+    api.op(
+        "POST",
+        "/chat/{id}/member",
+        "Let another account read and write the chat",
+    )
+    .body::<member::ChatMemberAddReq>()
+    .json::<member::ChatMemberAddResp>();
+    api.op(
+        "DELETE",
+        "/chat/{id}/member",
+        "Remove a member, or leave the chat",
+    )
+    .body::<member::ChatMemberRemoveReq>()
+    .json::<member::ChatMemberRemoveResp>();
+    api.op(
+        "GET",
+        "/chat/{id}/settings",
+        "System prompt and variables of the chat",
+    )
+    .json::<settings::ChatSettingsResp>();
+    api.op(
+        "POST",
+        "/chat/{id}/settings",
+        "Change the system prompt and variables of the chat",
+    )
+    .body::<settings::ChatSettingsWriteReq>()
+    .json::<settings::ChatSettingsResp>();
+    api.op(
+        "POST",
+        "/chat/{id}/continue",
+        "Finish a reply cut off by a restart of the server",
+    )
+    .body::<resume::ChatContinueReq>()
+    .json::<resume::ChatContinueResp>();
+    api.op(
+        "POST",
+        "/chat/{id}/share",
+        "Return the link of the chat, created on the first call",
+    )
+    .json::<share::ChatShareResp>();
+    api.op("DELETE", "/chat/{id}/share", "Stop sharing the chat")
+        .json::<share::ChatUnshareResp>();
+    api.op(
+        "POST",
+        "/chat/{id}/tool/{call_id}/input",
+        "Answer the question of a tool call",
+    )
+    .body::<tool_input::ChatToolInputReq>()
+    .json::<tool_input::ChatToolInputResp>();
+    api.op("POST", "/chat/{id}/voice", "Send a recorded message")
+        .form(&[
+            ("file", "the recording"),
+            (
+                "mode",
+                "normal, search, agent or research, normal if left out",
+            ),
+            ("language", "ISO 639-1 code of the language spoken"),
+        ])
+        .json::<voice::ChatVoiceResp>();
 }
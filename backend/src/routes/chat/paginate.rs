@@ -1,14 +1,22 @@
 use std::sync::Arc;
 
 use axum::{Extension, Json, extract::State};
-use entity::{chat, prelude::*};
-use sea_orm::{QueryOrder, QuerySelect, prelude::*};
+use entity::{ChatSentiment, ChatTask, chat, chat_label, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{QueryOrder, QuerySelect, QueryTrait, Select, prelude::*, sea_query::Query};
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-use crate::{AppState, config::MAX_PAGINATE_LIMIT, errors::*, middlewares::auth::UserId};
+use crate::{
+    AppState,
+    config::MAX_PAGINATE_LIMIT,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+    retention,
+    utils::{folder, member},
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 #[serde(tag = "t", content = "c", rename_all = "snake_case")]
 pub enum ChatPaginateReq {
@@ -16,7 +24,7 @@ pub enum ChatPaginateReq {
     Range(ChatPaginateReqRange),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct ChatPaginateReqLimit {
     /// Default to the beginning
@@ -25,18 +33,38 @@ pub struct ChatPaginateReqLimit {
     pub id: Option<i32>,
     pub order: ChatPaginateReqOrder,
     pub limit: Option<u32>,
+    #[serde(default)]
+    pub filter: Option<ChatPaginateReqFilter>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 /// Does not include upper & lower
 /// lower [... return items ... ] upper
 pub struct ChatPaginateReqRange {
     pub upper: i32,
     pub lower: i32,
+    #[serde(default)]
+    pub filter: Option<ChatPaginateReqFilter>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[typeshare]
+/// Only chats with all the given tags, see `utils::tagger`, and in the given
+/// folder and label, see `utils::folder`
+pub struct ChatPaginateReqFilter {
+    pub topic: Option<String>,
+    pub sentiment: Option<ChatSentiment>,
+    pub task: Option<ChatTask>,
+    pub folder_id: Option<i32>,
+    pub label_id: Option<i32>,
+    pub pinned: Option<bool>,
+    /// true for only the archived chats, they are left out otherwise
+    #[serde(default)]
+    pub archived: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 #[serde(rename_all = "snake_case")]
 pub enum ChatPaginateReqOrder {
@@ -46,37 +74,82 @@ pub enum ChatPaginateReqOrder {
     Lt,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct ChatPaginateResp {
     pub list: Vec<ChatPaginateRespList>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct ChatPaginateRespList {
     pub id: i32,
     pub model_id: i32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sentiment: Option<ChatSentiment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task: Option<ChatTask>,
+    /// When the retention policy delete it, unless a message is sent before
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delete_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub folder_id: Option<i32>,
+    pub label_ids: Vec<i32>,
+    pub pinned: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<i64>,
+}
+
+fn filtered(q: Select<Chat>, filter: Option<ChatPaginateReqFilter>) -> Select<Chat> {
+    let filter = filter.unwrap_or_default();
+    let q = match filter.archived {
+        true => q.filter(chat::Column::ArchivedAt.is_not_null()),
+        false => q.filter(chat::Column::ArchivedAt.is_null()),
+    };
+    q.apply_if(filter.topic, |q, x| q.filter(chat::Column::Topic.eq(x)))
+        .apply_if(filter.sentiment, |q, x| {
+            q.filter(chat::Column::Sentiment.eq(x))
+        })
+        .apply_if(filter.task, |q, x| q.filter(chat::Column::Task.eq(x)))
+        .apply_if(filter.folder_id, |q, x| {
+            q.filter(chat::Column::FolderId.eq(x))
+        })
+        .apply_if(filter.pinned, |q, x| q.filter(chat::Column::Pinned.eq(x)))
+        .apply_if(filter.label_id, |q, x| {
+            q.filter(
+                chat::Column::Id.in_subquery(
+                    Query::select()
+                        .column(chat_label::Column::ChatId)
+                        .from(ChatLabel)
+                        .and_where(chat_label::Column::LabelId.eq(x))
+                        .to_owned(),
+                ),
+            )
+        })
 }
 
 pub async fn route(
     State(app): State<Arc<AppState>>,
     Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
     Json(req): Json<ChatPaginateReq>,
 ) -> JsonResult<ChatPaginateResp> {
     let q = match req {
         ChatPaginateReq::Limit(limit) => {
-            let q = Chat::find()
-                .filter(chat::Column::OwnerId.eq(user_id))
+            let q = filtered(Chat::find(), limit.filter)
+                .filter(member::joined(user_id, workspace_id))
                 .limit(
                     limit
                         .limit
                         .map(|x| x.min(MAX_PAGINATE_LIMIT))
                         .unwrap_or(MAX_PAGINATE_LIMIT) as u64,
                 );
-            let q = match (limit.order, limit.id) {
+
+            match (limit.order, limit.id) {
                 (ChatPaginateReqOrder::Gt, None) => q.order_by_asc(chat::Column::Id),
                 (ChatPaginateReqOrder::Gt, Some(id)) => q
                     .filter(chat::Column::Id.gt(id))
@@ -85,25 +158,33 @@ pub async fn route(
                 (ChatPaginateReqOrder::Lt, Some(id)) => q
                     .filter(chat::Column::Id.lt(id))
                     .order_by_desc(chat::Column::Id),
-            };
-            q
+            }
         }
-        ChatPaginateReq::Range(range) => Chat::find()
-            .filter(chat::Column::OwnerId.eq(user_id))
+        ChatPaginateReq::Range(range) => filtered(Chat::find(), range.filter)
+            .filter(member::joined(user_id, workspace_id))
             .filter(chat::Column::Id.gt(range.lower))
             .filter(chat::Column::Id.lt(range.upper))
             .limit(MAX_PAGINATE_LIMIT as u64),
     };
 
-    let list = q
-        .all(&app.conn)
+    let chats = q.all(&app.conn).await.kind(ErrorKind::Internal)?;
+    let mut labels = folder::labels(&app.conn, chats.iter().map(|x| x.id).collect())
         .await
-        .kind(ErrorKind::Internal)?
+        .kind(ErrorKind::Internal)?;
+    let list = chats
         .into_iter()
         .map(|x| ChatPaginateRespList {
+            label_ids: labels.remove(&x.id).unwrap_or_default(),
+            folder_id: x.folder_id,
             id: x.id,
             model_id: x.model_id,
             title: x.title,
+            topic: x.topic,
+            sentiment: x.sentiment,
+            task: x.task,
+            delete_at: x.retention_notice_at.map(retention::delete_at),
+            pinned: x.pinned,
+            archived_at: x.archived_at,
         })
         .collect();
     Ok(Json(ChatPaginateResp { list }))
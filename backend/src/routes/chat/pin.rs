@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{chat, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, sea_query::Expr};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId, sse};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ChatPinReq {
+    pub chat_id: i32,
+    pub pinned: bool,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatPinResp {
+    pub wrote: bool,
+}
+
+/// Pinned chats are listed apart with the `pinned` filter of `chat/paginate`
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<ChatPinReq>,
+) -> JsonResult<ChatPinResp> {
+    let res = Chat::update_many()
+        .col_expr(chat::Column::Pinned, Expr::value(req.pinned))
+        .filter(chat::Column::Id.eq(req.chat_id))
+        .filter(chat::Column::OwnerId.eq(user_id))
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let wrote = res.rows_affected > 0;
+    if wrote {
+        broadcast_status(&app, req.chat_id).await?;
+    }
+    Ok(Json(ChatPinResp { wrote }))
+}
+
+/// Tell the open tabs of the chat it was pinned or archived
+pub async fn broadcast_status(app: &AppState, chat_id: i32) -> Result<(), Json<Error>> {
+    let chat = Chat::find_by_id(chat_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("")
+        .kind(ErrorKind::ResourceNotFound)?;
+    app.sse
+        .broadcast(
+            chat_id,
+            sse::Token::ChatStatus(chat.pinned, chat.archived_at),
+        )
+        .await;
+    Ok(())
+}
@@ -1,50 +1,74 @@
 use std::sync::Arc;
 
 use axum::{Extension, Json, extract::State};
-use entity::{chat, model};
-use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use entity::ChatMemberRole;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-use crate::{AppState, errors::*, middlewares::auth::UserId};
+use crate::{
+    AppState,
+    errors::*,
+    kb,
+    middlewares::auth::{UserId, WorkspaceId},
+    utils::{member, model},
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct ChatReadReq {
     pub id: i32,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct ChatReadResp {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub model_id: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
+    pub reproducible: bool,
+    /// Set while the chat is shared at `/share/{token}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub share_token: Option<String>,
+    pub pinned: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<i64>,
+    /// Role of the user, see `chat/{id}/member`
+    pub role: ChatMemberRole,
+    /// Persona the chat talks as, see `persona/assign`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub persona_id: Option<i32>,
+    /// Collections the documents searched are in, empty for all, see
+    /// `kb/assign`
+    pub collection_ids: Vec<i32>,
 }
 
 pub async fn route(
     State(app): State<Arc<AppState>>,
     Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
     Json(req): Json<ChatReadReq>,
 ) -> JsonResult<ChatReadResp> {
-    let res = chat::Entity::find_by_id(req.id)
-        .filter(chat::Column::OwnerId.eq(user_id))
-        .find_also_related(model::Entity)
-        .one(&app.conn)
+    let (chat, role) = member::find(&app.conn, req.id, user_id, workspace_id).await?;
+    // a reply cut by a restart is offered to continue
+    app.sse.interrupt(chat.id).await.kind(ErrorKind::Internal)?;
+    let model = model::find(&app, chat.model_id)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let collection_ids = kb::scope(&app.conn, chat.id)
         .await
         .kind(ErrorKind::Internal)?;
 
-    match res {
-        Some((chat, model)) => Ok(Json(ChatReadResp {
-            model_id: model.map(|x| x.id),
-            title: chat.title,
-        })),
-        None => {
-            return Err(Json(Error {
-                error: ErrorKind::ResourceNotFound,
-                reason: "".to_owned(),
-            }));
-        }
-    }
+    Ok(Json(ChatReadResp {
+        model_id: model.map(|x| x.id),
+        title: chat.title,
+        reproducible: chat.reproducible,
+        share_token: chat.share_token,
+        pinned: chat.pinned,
+        archived_at: chat.archived_at,
+        role,
+        persona_id: chat.persona_id,
+        collection_ids,
+    }))
 }
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+};
+use entity::{MessageKind, message, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{ActiveValue::Set, prelude::*};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{ApiKeyUser, UserId, WorkspaceId},
+    routes::message::create::{MessageCreateReqMode, Turn, start},
+    utils::member,
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ChatContinueReq {
+    /// id of the interrupted assistant message
+    pub message_id: i32,
+    pub mode: MessageCreateReqMode,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatContinueResp {
+    /// the message the new reply goes on from
+    pub parent_id: i32,
+}
+
+/// Finish a reply the server was stopped in the middle of
+///
+/// The partial reply is kept and the model is asked to go on from its text,
+/// the rest streams as a new reply after it like a normal completion
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    api_key: Option<Extension<ApiKeyUser>>,
+    Path(id): Path<i32>,
+    Json(req): Json<ChatContinueReq>,
+) -> JsonResult<ChatContinueResp> {
+    let (chat, _) = member::find(&app.conn, id, user_id, workspace_id).await?;
+    app.sse.interrupt(id).await.kind(ErrorKind::Internal)?;
+
+    let message = Message::find_by_id(req.message_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .filter(|x| x.chat_id == id && x.kind == MessageKind::Assistant)
+        .ok_or("Cannot find the assistant message")
+        .kind(ErrorKind::ResourceNotFound)?;
+    if !message.interrupted {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "the reply was not interrupted".to_owned(),
+        }));
+    }
+
+    let turn = Turn::Continue {
+        message_id: message.id,
+    };
+    start(app.clone(), user_id, api_key, chat, req.mode, turn).await?;
+    // continued once, the new reply carries on
+    Message::update(message::ActiveModel {
+        id: Set(message.id),
+        interrupted: Set(false),
+        ..Default::default()
+    })
+    .exec(&app.conn)
+    .await
+    .kind(ErrorKind::Internal)?;
+
+    Ok(Json(ChatContinueResp {
+        parent_id: message.id,
+    }))
+}
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+};
+use entity::{chat, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, EntityTrait};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    config::SYSTEM_PROMPT_MAX_CHARS,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+    utils::member,
+};
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatSettingsResp {
+    /// Instructions for this chat, a template with the variables of the
+    /// built-in prompt, e.g. `{{ user.name }}`
+    pub system_prompt: Option<String>,
+    /// Whether `system_prompt` stands in for the built-in prompt of the
+    /// normal mode, otherwise it follows the prompt of every mode
+    pub system_prompt_replace: bool,
+}
+
+/// Missing fields are left unchanged
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ChatSettingsWriteReq {
+    /// An empty prompt removes it
+    pub system_prompt: Option<String>,
+    pub system_prompt_replace: Option<bool>,
+}
+
+pub async fn read(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Path(id): Path<i32>,
+) -> JsonResult<ChatSettingsResp> {
+    let (chat, _) = member::find(&app.conn, id, user_id, workspace_id).await?;
+    Ok(Json(ChatSettingsResp {
+        system_prompt: chat.system_prompt,
+        system_prompt_replace: chat.system_prompt_replace,
+    }))
+}
+
+/// Apply to the next reply, earlier ones keep the prompt they were written with;
+/// only owners of the chat change it
+pub async fn write(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Path(id): Path<i32>,
+    Json(req): Json<ChatSettingsWriteReq>,
+) -> JsonResult<ChatSettingsResp> {
+    let chat = member::owned(&app.conn, id, user_id, workspace_id).await?;
+
+    let mut model = chat::ActiveModel {
+        id: Set(id),
+        ..Default::default()
+    };
+    if let Some(prompt) = req.system_prompt {
+        let prompt = prompt.trim();
+        if prompt.chars().count() > SYSTEM_PROMPT_MAX_CHARS {
+            return Err(Json(Error {
+                error: ErrorKind::MalformedRequest,
+                reason: format!(
+                    "System prompt is longer than {} characters",
+                    SYSTEM_PROMPT_MAX_CHARS
+                ),
+            }));
+        }
+        app.prompt.check(prompt).map_err(|e| {
+            Json(Error {
+                error: ErrorKind::MalformedRequest,
+                reason: format!("Malformed template: {}", e),
+            })
+        })?;
+        model.system_prompt = Set(Some(prompt.to_owned()).filter(|x| !x.is_empty()));
+    }
+    if let Some(replace) = req.system_prompt_replace {
+        model.system_prompt_replace = Set(replace);
+    }
+    if !model.is_changed() {
+        return Ok(Json(ChatSettingsResp {
+            system_prompt: chat.system_prompt,
+            system_prompt_replace: chat.system_prompt_replace,
+        }));
+    }
+
+    let chat = Chat::update(model)
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    Ok(Json(ChatSettingsResp {
+        system_prompt: chat.system_prompt,
+        system_prompt_replace: chat.system_prompt_replace,
+    }))
+}
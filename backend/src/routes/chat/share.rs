@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+};
+use entity::{chat, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{ActiveValue::Set, EntityTrait};
+use serde::Serialize;
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatShareResp {
+    /// Anyone can read the chat at `/share/{token}`
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatUnshareResp {
+    /// false if the chat was not shared
+    pub revoked: bool,
+}
+
+async fn owned(app: &AppState, user_id: i32, id: i32) -> Result<chat::Model, Json<Error>> {
+    Chat::find_by_id(id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .filter(|x| x.owner_id == user_id)
+        .ok_or("")
+        .kind(ErrorKind::ResourceNotFound)
+}
+
+/// Return the link of the chat, created on the first call
+pub async fn create(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Path(id): Path<i32>,
+) -> JsonResult<ChatShareResp> {
+    let chat = owned(&app, user_id, id).await?;
+    if let Some(token) = chat.share_token {
+        return Ok(Json(ChatShareResp { token }));
+    }
+
+    let mut bytes = [0u8; 24];
+    getrandom::fill(&mut bytes)
+        .map_err(|e| anyhow::anyhow!("Cannot generate token: {}", e))
+        .kind(ErrorKind::Internal)?;
+    let token: String = bytes.iter().map(|x| format!("{:02x}", x)).collect();
+
+    Chat::update(chat::ActiveModel {
+        id: Set(id),
+        share_token: Set(Some(token.clone())),
+        ..Default::default()
+    })
+    .exec(&app.conn)
+    .await
+    .kind(ErrorKind::Internal)?;
+
+    Ok(Json(ChatShareResp { token }))
+}
+
+/// The link stops working at once, sharing again gives a new one
+pub async fn revoke(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Path(id): Path<i32>,
+) -> JsonResult<ChatUnshareResp> {
+    let chat = owned(&app, user_id, id).await?;
+    let revoked = chat.share_token.is_some();
+    if revoked {
+        Chat::update(chat::ActiveModel {
+            id: Set(id),
+            share_token: Set(None),
+            ..Default::default()
+        })
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    }
+
+    Ok(Json(ChatUnshareResp { revoked }))
+}
@@ -3,186 +3,73 @@ use std::{sync::Arc, time::Duration};
 use axum::{
     Extension, Json,
     extract::State,
+    http::HeaderMap,
     response::{
-        Sse,
+        IntoResponse, Sse,
         sse::{Event, KeepAlive},
     },
 };
-use entity::prelude::*;
-use futures_util::{Stream, StreamExt};
-use sea_orm::EntityTrait;
-use serde::{Deserialize, Serialize};
+use futures_util::StreamExt;
+use schemars::JsonSchema;
+use serde::Deserialize;
 use typeshare::typeshare;
 
 use crate::{
     AppState,
+    config::SSE_KEEP_ALIVE,
     errors::*,
-    middlewares::auth::UserId,
-    sse::{EndKind, Token},
+    middlewares::auth::{UserId, WorkspaceId},
+    sse::SseEvent,
+    utils::member,
 };
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct SseReq {
+    /// chat to follow, every chat has its own stream so other chats of the
+    /// user are never delivered here
+    #[serde(alias = "chat_id")]
     pub id: i32,
 }
 
-#[derive(Debug, Serialize)]
-#[typeshare]
-#[serde(tag = "t", content = "c", rename_all = "snake_case")]
-pub enum SseResp {
-    LastMessage(SseRespLastMessage),
-
-    Token(SseRespToken),
-    ReasoningToken(SseRespToken),
-    ChunkEnd(SseRespChunkEnd),
-
-    ToolCall(SseRespToolCall),
-    ToolCallEnd(SseRespToolCallEnd),
-
-    MessageEnd(SseRespMessageEnd),
-
-    UserMessage(SseRespUserMessage),
-
-    ChangeTitle(SseRespUserTitle),
-}
-
-#[derive(Debug, Serialize)]
-#[typeshare]
-pub struct SseRespUserTitle {
-    pub title: String,
-}
-
-#[derive(Debug, Serialize)]
-#[typeshare]
-pub struct SseRespLastMessage {
-    pub id: i32,
-    pub version: u32,
-}
-
-#[derive(Debug, Serialize)]
-#[typeshare]
-pub struct SseRespToken {
-    pub content: String,
-}
-
-#[derive(Debug, Serialize)]
-#[typeshare]
-pub struct SseRespChunkEnd {
-    pub id: i32,
-    pub kind: SseRespEndKind,
-}
-
-#[derive(Debug, Serialize)]
-#[typeshare]
-pub struct SseRespMessageEnd {
-    pub id: i32,
-    pub kind: SseRespEndKind,
-}
-
-#[derive(Debug, Serialize)]
-#[typeshare]
-#[serde(rename_all = "snake_case")]
-pub enum SseRespEndKind {
-    Complete,
-    Halt,
-    Error,
-}
-
-#[derive(Debug, Serialize)]
-#[typeshare]
-pub struct SseRespUserMessage {
-    pub message_id: i32,
-    pub chunk_id: i32,
-    pub content: String,
-}
-
-#[derive(Debug, Serialize)]
-#[typeshare]
-pub struct SseRespToolCall {
-    pub name: String,
-    pub args: String,
-}
-
-#[derive(Debug, Serialize)]
-#[typeshare]
-pub struct SseRespToolCallEnd {
-    pub chunk_id: i32,
-    pub name: String,
-    pub args: String,
-    pub content: String,
-}
-
 pub async fn route(
     State(app): State<Arc<AppState>>,
     Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    headers: HeaderMap,
     Json(req): Json<SseReq>,
-) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, Json<Error>> {
-    let res = Chat::find_by_id(req.id)
-        .one(&app.conn)
-        .await
-        .kind(ErrorKind::Internal)?
-        .ok_or("")
-        .kind(ErrorKind::ResourceNotFound)?;
-
-    if res.owner_id != user_id {
-        return Err(Json(Error {
-            error: ErrorKind::ResourceNotFound,
-            reason: "".to_owned(),
-        }));
-    }
+) -> Result<impl IntoResponse, Json<Error>> {
+    // every member follow the same stream
+    member::find(&app.conn, req.id, user_id, workspace_id).await?;
+    // before subscribing, a reply left generating by a restart never ends
+    app.sse.interrupt(req.id).await.kind(ErrorKind::Internal)?;
+
+    // a reconnecting client catch up from the last event it got
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.parse().ok());
 
     let sub = app
         .sse
-        .subscribe(req.id)
+        .subscribe(req.id, last_event_id)
         .await
         .kind(ErrorKind::MalformedRequest)?;
-    let st = sub
-        .map(|x| {
-            x.map(|v| match v {
-                Token::LastMessage(id, version) => {
-                    SseResp::LastMessage(SseRespLastMessage { id, version })
-                }
-                Token::Token(content) => SseResp::Token(SseRespToken { content }),
-                Token::ReasoningToken(content) => SseResp::ReasoningToken(SseRespToken { content }),
-                Token::ChunkEnd(id, end_kind) => SseResp::ChunkEnd(SseRespChunkEnd {
-                    id,
-                    kind: match end_kind {
-                        EndKind::Complete => SseRespEndKind::Complete,
-                        EndKind::Halt => SseRespEndKind::Halt,
-                        EndKind::Error => SseRespEndKind::Error,
-                    },
-                }),
-                Token::MessageEnd(id, end_kind) => SseResp::MessageEnd(SseRespMessageEnd {
-                    id,
-                    kind: match end_kind {
-                        EndKind::Complete => SseRespEndKind::Complete,
-                        EndKind::Halt => SseRespEndKind::Halt,
-                        EndKind::Error => SseRespEndKind::Error,
-                    },
-                }),
-                Token::UserMessage(message_id, chunk_id, content) => {
-                    SseResp::UserMessage(SseRespUserMessage {
-                        message_id,
-                        chunk_id,
-                        content,
-                    })
-                }
-                Token::ToolCall(name, args) => SseResp::ToolCall(SseRespToolCall {
-                    name: name.to_owned(),
-                    args,
-                }),
-                Token::ToolCallEnd(name, args, content, chunk_id) => {
-                    SseResp::ToolCallEnd(SseRespToolCallEnd {
-                        chunk_id,
-                        name: name.to_owned(),
-                        args,
-                        content,
-                    })
-                }
-                Token::ChangeTitle(title) => SseResp::ChangeTitle(SseRespUserTitle { title }),
-            })
-        })
-        .map(|x| Event::default().json_data(JsonUnion::from(x)));
-    Ok(Sse::new(st).keep_alive(KeepAlive::new().interval(Duration::from_secs(10))))
+    // the user is not sent pushes while they follow a chat
+    let online = app.notifier.connect(user_id);
+    let st = sub.map(move |(id, x)| {
+        let _ = &online;
+        match id {
+            Some(id) => Event::default().id(id.to_string()),
+            None => Event::default(),
+        }
+        .json_data(SseEvent::from(x))
+    });
+    let sse = Sse::new(st).keep_alive(
+        KeepAlive::new()
+            .interval(Duration::from_secs(SSE_KEEP_ALIVE))
+            .text("ping"),
+    );
+    // nginx buffer the response by default, which hold back tokens and pings
+    Ok(([("x-accel-buffering", "no")], sse))
 }
@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{ChatSentiment, ChatTask, chat, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{ConnectionTrait, DbErr, QueryOrder, QuerySelect, QueryTrait, Select, prelude::*};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, config::TAG_TOP_TOPICS, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ChatTagsReq {}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatTagsResp {
+    /// Most frequent first, at most [`TAG_TOP_TOPICS`]
+    pub topics: Vec<ChatTagsRespTopic>,
+    pub sentiments: Vec<ChatTagsRespSentiment>,
+    pub tasks: Vec<ChatTagsRespTask>,
+    /// Chats the tagger has not read yet
+    pub untagged: u32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatTagsRespTopic {
+    pub topic: String,
+    pub count: u32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatTagsRespSentiment {
+    pub sentiment: ChatSentiment,
+    pub count: u32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatTagsRespTask {
+    pub task: ChatTask,
+    pub count: u32,
+}
+
+/// Tags of the chats of the user, to filter the chat list by
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(_): Json<ChatTagsReq>,
+) -> JsonResult<ChatTagsResp> {
+    Ok(Json(
+        count(&app.conn, Some(user_id))
+            .await
+            .kind(ErrorKind::Internal)?,
+    ))
+}
+
+/// Chats per tag of a user, or of every user
+pub async fn count(
+    conn: &impl ConnectionTrait,
+    owner_id: Option<i32>,
+) -> Result<ChatTagsResp, DbErr> {
+    let chats = || Chat::find().apply_if(owner_id, |q, x| q.filter(chat::Column::OwnerId.eq(x)));
+    fn grouped(q: Select<Chat>, column: chat::Column) -> Select<Chat> {
+        q.select_only()
+            .column(column)
+            .column_as(chat::Column::Id.count(), "count")
+            .filter(column.is_not_null())
+            .group_by(column)
+            .order_by_desc(chat::Column::Id.count())
+    }
+
+    let topics = grouped(chats(), chat::Column::Topic)
+        .limit(TAG_TOP_TOPICS)
+        .into_tuple::<(String, i64)>()
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|(topic, count)| ChatTagsRespTopic {
+            topic,
+            count: count as u32,
+        })
+        .collect();
+    let sentiments = grouped(chats(), chat::Column::Sentiment)
+        .into_tuple::<(ChatSentiment, i64)>()
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|(sentiment, count)| ChatTagsRespSentiment {
+            sentiment,
+            count: count as u32,
+        })
+        .collect();
+    let tasks = grouped(chats(), chat::Column::Task)
+        .into_tuple::<(ChatTask, i64)>()
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|(task, count)| ChatTagsRespTask {
+            task,
+            count: count as u32,
+        })
+        .collect();
+    let untagged = chats()
+        .filter(chat::Column::TaggedAt.is_null())
+        .count(conn)
+        .await?;
+
+    Ok(ChatTagsResp {
+        topics,
+        sentiments,
+        tasks,
+        untagged: untagged as u32,
+    })
+}
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+    utils::member,
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ChatToolInputReq {
+    /// JSON matching the schema of the question
+    pub answer: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatToolInputResp {
+    /// false if the call no longer wait, e.g. it timed out or was halted
+    pub accepted: bool,
+}
+
+/// Answer the question of a tool call, the call resumes with it
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Path((id, call_id)): Path<(i32, String)>,
+    Json(req): Json<ChatToolInputReq>,
+) -> JsonResult<ChatToolInputResp> {
+    member::find(&app.conn, id, user_id, workspace_id).await?;
+
+    let answer = serde_json::from_str(&req.answer).kind(ErrorKind::MalformedRequest)?;
+    let accepted = app.inputs.answer(id, &call_id, answer);
+    Ok(Json(ChatToolInputResp { accepted }))
+}
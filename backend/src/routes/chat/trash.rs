@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{prelude::*, trash};
+use schemars::JsonSchema;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::UserId,
+    trash::{Restore, purge_at},
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ChatTrashListReq {}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatTrashListResp {
+    /// Latest deletion first
+    pub list: Vec<ChatTrashListRespItem>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatTrashListRespItem {
+    pub id: i32,
+    pub chat_id: i32,
+    /// Absent when the whole chat was deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<i32>,
+    /// Title of the chat, or the start of the text of the message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// unix seconds
+    pub deleted_at: i64,
+    /// unix seconds
+    pub purge_at: i64,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ChatTrashRestoreReq {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatTrashRestoreResp {
+    /// Chat the restored rows belong to, refetch it
+    pub chat_id: i32,
+}
+
+/// Chats and messages the user deleted, kept until their `purge_at`
+pub async fn list(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(_): Json<ChatTrashListReq>,
+) -> JsonResult<ChatTrashListResp> {
+    let list = Trash::find()
+        .filter(trash::Column::OwnerId.eq(user_id))
+        .order_by_desc(trash::Column::Id)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .into_iter()
+        .map(|x| ChatTrashListRespItem {
+            id: x.id,
+            chat_id: x.chat_id,
+            message_id: x.message_id,
+            title: x.title,
+            deleted_at: x.deleted_at,
+            purge_at: purge_at(x.deleted_at),
+        })
+        .collect();
+    Ok(Json(ChatTrashListResp { list }))
+}
+
+/// Put a deleted chat or message back with its ids
+pub async fn restore(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<ChatTrashRestoreReq>,
+) -> JsonResult<ChatTrashRestoreResp> {
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+    let chat_id = match crate::trash::restore(&txn, user_id, req.id)
+        .await
+        .kind(ErrorKind::Internal)?
+    {
+        Restore::Restored(chat_id) => chat_id,
+        Restore::NotFound => {
+            return Err(Json(Error {
+                error: ErrorKind::ResourceNotFound,
+                reason: "not in the trash, it may have been purged".to_owned(),
+            }));
+        }
+        Restore::ChatGone => {
+            return Err(Json(Error {
+                error: ErrorKind::MalformedRequest,
+                reason: "the chat of this message is deleted, restore it first".to_owned(),
+            }));
+        }
+    };
+    txn.commit().await.kind(ErrorKind::Internal)?;
+    app.prefetch.forget(chat_id);
+    Ok(Json(ChatTrashRestoreResp { chat_id }))
+}
@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Multipart, Path, State},
+    http::HeaderMap,
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    config::VOICE_MAX_BYTES,
+    errors::*,
+    files,
+    middlewares::auth::{ApiKeyUser, UserId, WorkspaceId},
+    routes::message::create::{self, MessageCreateReq, MessageCreateReqMode},
+    utils::member,
+};
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ChatVoiceResp {
+    /// The user message, the reply stream on `/api/chat/sse` as usual
+    pub id: i32,
+    /// What was heard
+    pub text: String,
+}
+
+/// Multipart form with the recording in a `file` field, and optionally a
+/// `mode` (default to normal) and the ISO 639-1 `language` spoken
+///
+/// The transcription is sent like a message typed by the user
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    api_key: Option<Extension<ApiKeyUser>>,
+    Path(id): Path<i32>,
+    mut multipart: Multipart,
+) -> JsonResult<ChatVoiceResp> {
+    let stt = app
+        .stt
+        .as_ref()
+        .ok_or("voice input is not enabled on this server")
+        .kind(ErrorKind::MalformedRequest)?;
+    // before paying for the transcription
+    member::find(&app.conn, id, user_id, workspace_id).await?;
+
+    let mut audio = None;
+    let mut mode = MessageCreateReqMode::Normal;
+    let mut language = None;
+    while let Some(mut field) = multipart
+        .next_field()
+        .await
+        .kind(ErrorKind::MalformedRequest)?
+    {
+        match field.name() {
+            Some("file") => {
+                let mut data = vec![];
+                while let Some(chunk) = field.chunk().await.kind(ErrorKind::MalformedRequest)? {
+                    if data.len() + chunk.len() > VOICE_MAX_BYTES {
+                        return Err(Json(Error {
+                            error: ErrorKind::MalformedRequest,
+                            reason: format!("recording is larger than {} bytes", VOICE_MAX_BYTES),
+                        }));
+                    }
+                    data.extend_from_slice(&chunk);
+                }
+                audio = Some(data);
+            }
+            Some("mode") => {
+                let text = field.text().await.kind(ErrorKind::MalformedRequest)?;
+                mode = serde_json::from_value(serde_json::Value::String(text))
+                    .kind(ErrorKind::MalformedRequest)?;
+            }
+            Some("language") => {
+                language = Some(field.text().await.kind(ErrorKind::MalformedRequest)?);
+            }
+            _ => {}
+        }
+    }
+    let audio = audio
+        .ok_or("missing file field")
+        .kind(ErrorKind::MalformedRequest)?;
+
+    let content_type = files::sniff(&audio[..audio.len().min(files::HEAD_BYTES)]);
+    if !content_type.starts_with("audio/") && !content_type.starts_with("video/") {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!("expect a recording, got {}", content_type),
+        }));
+    }
+
+    let text = stt
+        .transcribe(audio, content_type, language.as_deref())
+        .await
+        .kind(ErrorKind::ApiFail)?;
+    if text.is_empty() {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "no speech was recognized".to_owned(),
+        }));
+    }
+
+    let Json(res) = create::route(
+        State(app),
+        Extension(UserId(user_id)),
+        Extension(WorkspaceId(workspace_id)),
+        api_key,
+        HeaderMap::new(),
+        Json(MessageCreateReq {
+            chat_id: id,
+            mode,
+            text: text.clone(),
+            files: vec![],
+        }),
+    )
+    .await?;
+
+    Ok(Json(ChatVoiceResp { id: res.id, text }))
+}
@@ -2,20 +2,26 @@ use std::sync::Arc;
 
 use axum::{Extension, Json, extract::State};
 use entity::chat;
+use schemars::JsonSchema;
 use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-use crate::{AppState, errors::*, middlewares::auth::UserId};
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+    utils::member,
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct ChatUpdateReq {
     pub chat_id: i32,
     pub title: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct ChatUpdateResp {
     pub wrote: bool,
@@ -24,6 +30,7 @@ pub struct ChatUpdateResp {
 pub async fn route(
     State(app): State<Arc<AppState>>,
     Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
     Json(req): Json<ChatUpdateReq>,
 ) -> JsonResult<ChatUpdateResp> {
     // TODO: sync Mode with remote
@@ -34,13 +41,11 @@ pub async fn route(
 
     let title = req.title.unwrap();
 
+    // members follow the title the owners give
+    member::owned(&app.conn, req.chat_id, user_id, workspace_id).await?;
     let res = chat::Entity::update_many()
         .col_expr(chat::Column::Title, title.into())
-        .filter(
-            chat::Column::Id
-                .eq(req.chat_id)
-                .and(chat::Column::OwnerId.eq(user_id)),
-        )
+        .filter(chat::Column::Id.eq(req.chat_id))
         .exec(&app.conn)
         .await
         .kind(ErrorKind::Internal)?;
@@ -0,0 +1,98 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    Json,
+    extract::{ConnectInfo, State},
+    http::HeaderMap,
+};
+use entity::{prelude::*, user};
+use pasetors::claims::Claims;
+use sea_orm::{ActiveValue::Set, EntityTrait};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    config::DEMO_SESSION_SECS,
+    errors::*,
+    utils::{client, workspace},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct DemoLoginReq {
+    pub captcha_id: u32,
+    pub answer: u32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct DemoLoginResp {
+    pub token: String,
+    pub exp: String,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(req): Json<DemoLoginReq>,
+) -> JsonResult<DemoLoginResp> {
+    let demo = app
+        .demo
+        .as_ref()
+        .ok_or("demo mode is disabled")
+        .kind(ErrorKind::ResourceNotFound)?;
+
+    if !demo.solve(req.captcha_id, req.answer) {
+        return Err(Json(Error {
+            error: ErrorKind::LoginFail,
+            reason: "wrong captcha".to_owned(),
+        }));
+    }
+
+    let ip = client::ip(&headers, addr);
+    if !demo.start_session(ip) {
+        return Err(Json(Error {
+            error: ErrorKind::LoginFail,
+            reason: "demo session limit reached, try again later".to_owned(),
+        }));
+    }
+
+    // nobody knows the password, the token is the only way in
+    let password = format!("{:032x}", fastrand::u128(..));
+    let user_id = User::insert(user::ActiveModel {
+        name: Set(format!("demo-{:016x}", fastrand::u64(..))),
+        password: Set(app.hasher.hash_password(&password).await),
+        demo_expires_at: Set(Some(
+            time::UtcDateTime::now().unix_timestamp() + DEMO_SESSION_SECS,
+        )),
+        ..Default::default()
+    })
+    .exec(&app.conn)
+    .await
+    .kind(ErrorKind::Internal)?
+    .last_insert_id;
+    workspace::join(&app.conn, workspace::DEFAULT_WORKSPACE, user_id)
+        .await
+        .kind(ErrorKind::Internal)?;
+    demo.bind(user_id, ip);
+
+    let mut claim = Claims::new().kind(ErrorKind::Internal)?;
+
+    // safety:
+    // "uid", "wid" and "demo" are not reserve
+    claim.add_additional("uid", user_id).unwrap();
+    claim
+        .add_additional("wid", workspace::DEFAULT_WORKSPACE)
+        .unwrap();
+    claim.add_additional("demo", true).unwrap();
+
+    // safety:
+    // "exp" must exists
+    let exp = claim.get_claim("exp").unwrap().as_str().unwrap().to_owned();
+
+    let token = app.keyring.encrypt(&claim).kind(ErrorKind::Internal)?;
+
+    Ok(Json(DemoLoginResp { token, exp }))
+}
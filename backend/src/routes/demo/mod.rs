@@ -0,0 +1,14 @@
+use std::sync::Arc;
+
+use axum::{Router, routing::post};
+
+use crate::AppState;
+
+mod login;
+mod status;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/status", post(status::route))
+        .route("/login", post(login::route))
+}
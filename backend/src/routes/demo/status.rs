@@ -0,0 +1,44 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    Json,
+    extract::{ConnectInfo, State},
+    http::HeaderMap,
+};
+use serde::Serialize;
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, utils::client};
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct DemoStatusResp {
+    /// a fresh captcha, needed to start a session
+    pub captcha_id: u32,
+    pub question: String,
+    /// left for this IP in the current hour
+    pub sessions_left: u32,
+    pub messages_left: u32,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> JsonResult<DemoStatusResp> {
+    let demo = app
+        .demo
+        .as_ref()
+        .ok_or("demo mode is disabled")
+        .kind(ErrorKind::ResourceNotFound)?;
+
+    let remaining = demo.remaining(client::ip(&headers, addr));
+    let (captcha_id, question) = demo.captcha();
+
+    Ok(Json(DemoStatusResp {
+        captcha_id,
+        question,
+        sessions_left: remaining.sessions,
+        messages_left: remaining.messages,
+    }))
+}
@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    body::Body,
+    extract::State,
+    http::{HeaderMap, header},
+    response::{IntoResponse, Response},
+};
+
+use super::{authorize, models::served_models};
+use crate::{AppState, errors::*, federation::PEER_PREFIX};
+
+/// Answer an openai style completion request of a peer with our upstream
+///
+/// The response is streamed back untouched, so the peer parse it as if it
+/// came from its own upstream
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<serde_json::Value>,
+) -> Result<Response, Json<Error>> {
+    authorize(&app, &headers)?;
+
+    let model_id = req
+        .get("model")
+        .and_then(|x| x.as_str())
+        .ok_or("missing model")
+        .kind(ErrorKind::MalformedRequest)?;
+    // a chain of peers could loop forever
+    if model_id.starts_with(PEER_PREFIX) {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "cannot forward a peer model again".to_owned(),
+        }));
+    }
+    let base_id = model_id.strip_suffix(":online").unwrap_or(model_id);
+    if !served_models(&app).await?.iter().any(|x| x == base_id) {
+        return Err(Json(Error {
+            error: ErrorKind::ResourceNotFound,
+            reason: format!("model {} is not served here", model_id),
+        }));
+    }
+
+    tracing::info!("forwarding a peer completion with model {}", model_id);
+    let res = app.openrouter.forward(req).await.kind(ErrorKind::ApiFail)?;
+
+    let content_type = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .cloned()
+        .unwrap_or(header::HeaderValue::from_static("application/json"));
+    Ok((
+        res.status(),
+        [(header::CONTENT_TYPE, content_type)],
+        Body::from_stream(res.bytes_stream()),
+    )
+        .into_response())
+}
@@ -0,0 +1,39 @@
+mod completions;
+mod models;
+
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    http::{HeaderMap, header},
+    routing::post,
+};
+
+use crate::{AppState, errors::*, federation::Federation};
+
+/// Called by peers, authenticated by a federation token rather than a user token
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/completions", post(completions::route))
+        .route("/models", post(models::route))
+}
+
+/// The federation config if this instance serve peers and the request is signed
+fn authorize<'a>(app: &'a AppState, headers: &HeaderMap) -> Result<&'a Federation, Json<Error>> {
+    let federation = app
+        .openrouter
+        .federation()
+        .filter(|x| x.serve)
+        .ok_or("federation is disabled")
+        .kind(ErrorKind::ResourceNotFound)?;
+
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.strip_prefix("Bearer "))
+        .ok_or("missing federation token")
+        .kind(ErrorKind::Unauthorized)?;
+    federation.verify(token).kind(ErrorKind::Unauthorized)?;
+
+    Ok(federation)
+}
@@ -0,0 +1,35 @@
+use std::sync::Arc;
+
+use axum::{Json, extract::State, http::HeaderMap};
+use serde::Serialize;
+use typeshare::typeshare;
+
+use super::authorize;
+use crate::{AppState, errors::*, utils::model};
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct FederationModelsResp {
+    /// upstream model ids, use them as `peer/{id}` on the home instance
+    pub list: Vec<String>,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> JsonResult<FederationModelsResp> {
+    authorize(&app, &headers)?;
+
+    let list = served_models(&app).await?;
+    Ok(Json(FederationModelsResp { list }))
+}
+
+/// Peers can only use models configured here
+pub(super) async fn served_models(app: &AppState) -> Result<Vec<String>, Json<Error>> {
+    let models = model::all(app).await.kind(ErrorKind::Internal)?;
+    Ok(models
+        .iter()
+        .filter_map(|m| m.get_config())
+        .map(|x| x.model_id)
+        .collect())
+}
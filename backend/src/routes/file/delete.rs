@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{file, prelude::*};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, files, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct FileDeleteReq {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct FileDeleteResp {
+    pub deleted: bool,
+}
+
+/// Messages the file is attached to lose it
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<FileDeleteReq>,
+) -> JsonResult<FileDeleteResp> {
+    let Some(file) = File::find_by_id(req.id)
+        .filter(file::Column::OwnerId.eq(user_id))
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+    else {
+        return Ok(Json(FileDeleteResp { deleted: false }));
+    };
+    File::delete_by_id(file.id)
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    files::discard(&app.files, &file.storage_key).await;
+
+    Ok(Json(FileDeleteResp { deleted: true }))
+}
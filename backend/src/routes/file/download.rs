@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::header,
+    response::IntoResponse,
+};
+use entity::{attachment, chat, prelude::*};
+use sea_orm::{QuerySelect, QueryTrait, prelude::*};
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+    utils::member,
+};
+
+/// Content of a file of the user or sent in a chat they take part in, always
+/// as an attachment
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, Json<Error>> {
+    let file = File::find_by_id(id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("")
+        .kind(ErrorKind::ResourceNotFound)?;
+    if file.owner_id != user_id {
+        let chats = Chat::find()
+            .select_only()
+            .column(chat::Column::Id)
+            .filter(member::joined(user_id, workspace_id))
+            .into_query();
+        let shared = Attachment::find()
+            .filter(attachment::Column::FileId.eq(id))
+            .filter(attachment::Column::ChatId.in_subquery(chats))
+            .count(&app.conn)
+            .await
+            .kind(ErrorKind::Internal)?;
+        if shared == 0 {
+            return Err(Json(Error {
+                error: ErrorKind::ResourceNotFound,
+                reason: "".to_owned(),
+            }));
+        }
+    }
+
+    let body = app
+        .files
+        .get(&file.storage_key)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, file.content_type),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{}\"",
+                    file.name.replace(['"', '\\'], "_")
+                ),
+            ),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_owned()),
+        ],
+        body,
+    ))
+}
@@ -0,0 +1,19 @@
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    routing::{get, post},
+};
+
+use crate::AppState;
+
+mod delete;
+mod download;
+mod upload;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/upload", post(upload::route))
+        .route("/delete", post(delete::route))
+        .route("/{id}", get(download::route))
+}
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Multipart, State},
+};
+use entity::{file, prelude::*};
+use sea_orm::{ActiveValue::Set, EntityTrait};
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    config::{FILE_MAX_BYTES, FILE_NAME_MAX_CHARS},
+    errors::*,
+    files,
+    middlewares::auth::UserId,
+};
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct FileUploadResp {
+    pub id: i32,
+    pub name: String,
+    /// Sniffed from the content
+    pub content_type: String,
+    pub size: i64,
+}
+
+/// Multipart form with the file in a `file` field
+///
+/// Pass the id to `message/create` within [`crate::config::FILE_ORPHAN_SECS`],
+/// files attached to no message are purged afterward
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    mut multipart: Multipart,
+) -> JsonResult<FileUploadResp> {
+    let mut field = loop {
+        let field = multipart
+            .next_field()
+            .await
+            .kind(ErrorKind::MalformedRequest)?
+            .ok_or("missing file field")
+            .kind(ErrorKind::MalformedRequest)?;
+        if field.name() == Some("file") {
+            break field;
+        }
+    };
+    let name = sanitize(field.file_name().unwrap_or_default());
+
+    let key = files::new_key().kind(ErrorKind::Internal)?;
+    let spool = std::env::temp_dir().join(format!("llumen-upload-{}", key));
+    let mut out = tokio::fs::File::create(&spool)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let mut head = Vec::with_capacity(files::HEAD_BYTES);
+    let mut size = 0u64;
+    let res: Result<(), Json<Error>> = async {
+        while let Some(chunk) = field.chunk().await.kind(ErrorKind::MalformedRequest)? {
+            size += chunk.len() as u64;
+            if size > FILE_MAX_BYTES {
+                return Err(Json(Error {
+                    error: ErrorKind::MalformedRequest,
+                    reason: format!("file is larger than {} bytes", FILE_MAX_BYTES),
+                }));
+            }
+            if head.len() < files::HEAD_BYTES {
+                let take = chunk.len().min(files::HEAD_BYTES - head.len());
+                head.extend_from_slice(&chunk[..take]);
+            }
+            out.write_all(&chunk).await.kind(ErrorKind::Internal)?;
+        }
+        out.flush().await.kind(ErrorKind::Internal)
+    }
+    .await;
+    drop(out);
+    if let Err(err) = res {
+        tokio::fs::remove_file(&spool).await.ok();
+        return Err(err);
+    }
+
+    app.files
+        .put(&key, &spool, size)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let content_type = files::sniff(&head).to_owned();
+    let res = File::insert(file::ActiveModel {
+        owner_id: Set(user_id),
+        name: Set(name.clone()),
+        content_type: Set(content_type.clone()),
+        size: Set(size as i64),
+        storage_key: Set(key.clone()),
+        created_at: Set(time::UtcDateTime::now().unix_timestamp()),
+        ..Default::default()
+    })
+    .exec(&app.conn)
+    .await;
+    let id = match res {
+        Ok(x) => x.last_insert_id,
+        Err(err) => {
+            files::discard(&app.files, &key).await;
+            return Err(err).kind(ErrorKind::Internal);
+        }
+    };
+
+    Ok(Json(FileUploadResp {
+        id,
+        name,
+        content_type,
+        size: size as i64,
+    }))
+}
+
+/// Base name without control characters, `file` when nothing is left
+fn sanitize(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name
+        .chars()
+        .filter(|x| !x.is_control())
+        .take(FILE_NAME_MAX_CHARS)
+        .collect();
+    match name.trim() {
+        "" | "." | ".." => "file".to_owned(),
+        x => x.to_owned(),
+    }
+}
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{chat, folder, prelude::*};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, sea_query::Expr};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct FolderAssignReq {
+    pub chat_id: i32,
+    /// None to move the chat out of its folder
+    pub folder_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct FolderAssignResp {
+    pub wrote: bool,
+}
+
+/// A chat is in at most one folder
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<FolderAssignReq>,
+) -> JsonResult<FolderAssignResp> {
+    if let Some(folder_id) = req.folder_id {
+        Folder::find_by_id(folder_id)
+            .filter(folder::Column::OwnerId.eq(user_id))
+            .one(&app.conn)
+            .await
+            .kind(ErrorKind::Internal)?
+            .ok_or("")
+            .kind(ErrorKind::ResourceNotFound)?;
+    }
+
+    let res = Chat::update_many()
+        .col_expr(chat::Column::FolderId, Expr::value(req.folder_id))
+        .filter(chat::Column::Id.eq(req.chat_id))
+        .filter(chat::Column::OwnerId.eq(user_id))
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    Ok(Json(FolderAssignResp {
+        wrote: res.rows_affected > 0,
+    }))
+}
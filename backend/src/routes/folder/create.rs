@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{folder, prelude::*};
+use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId, utils::folder::name};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct FolderCreateReq {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct FolderCreateResp {
+    pub id: i32,
+}
+
+/// Return the folder of the same name if there is one
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<FolderCreateReq>,
+) -> JsonResult<FolderCreateResp> {
+    let name = name(&req.name)?;
+
+    if let Some(folder) = Folder::find()
+        .filter(folder::Column::OwnerId.eq(user_id))
+        .filter(folder::Column::Name.eq(&name))
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+    {
+        return Ok(Json(FolderCreateResp { id: folder.id }));
+    }
+
+    let id = Folder::insert(folder::ActiveModel {
+        owner_id: Set(user_id),
+        name: Set(name),
+        ..Default::default()
+    })
+    .exec(&app.conn)
+    .await
+    .kind(ErrorKind::Internal)?
+    .last_insert_id;
+
+    Ok(Json(FolderCreateResp { id }))
+}
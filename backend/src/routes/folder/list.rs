@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{chat, folder, prelude::*};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct FolderListReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct FolderListResp {
+    pub list: Vec<FolderListRespItem>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct FolderListRespItem {
+    pub id: i32,
+    pub name: String,
+    pub chats: u32,
+}
+
+/// Folders of the current user by name
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(_): Json<FolderListReq>,
+) -> JsonResult<FolderListResp> {
+    let folders = Folder::find()
+        .filter(folder::Column::OwnerId.eq(user_id))
+        .order_by_asc(folder::Column::Name)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let counts: Vec<(i32, i64)> = Chat::find()
+        .select_only()
+        .column(chat::Column::FolderId)
+        .column_as(chat::Column::Id.count(), "count")
+        .filter(chat::Column::OwnerId.eq(user_id))
+        .filter(chat::Column::FolderId.is_not_null())
+        .group_by(chat::Column::FolderId)
+        .into_tuple()
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let list = folders
+        .into_iter()
+        .map(|x| FolderListRespItem {
+            chats: counts
+                .iter()
+                .find(|(id, _)| *id == x.id)
+                .map(|(_, count)| *count as u32)
+                .unwrap_or_default(),
+            id: x.id,
+            name: x.name,
+        })
+        .collect();
+
+    Ok(Json(FolderListResp { list }))
+}
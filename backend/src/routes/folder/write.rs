@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{folder, prelude::*};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId, utils::folder::name};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct FolderWriteReq {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct FolderWriteResp {
+    /// false if the folder does not exist or the name is taken
+    pub wrote: bool,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<FolderWriteReq>,
+) -> JsonResult<FolderWriteResp> {
+    let name = name(&req.name)?;
+
+    let taken = Folder::find()
+        .filter(folder::Column::OwnerId.eq(user_id))
+        .filter(folder::Column::Name.eq(&name))
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .is_some();
+    if taken {
+        return Ok(Json(FolderWriteResp { wrote: false }));
+    }
+
+    let res = Folder::update_many()
+        .col_expr(folder::Column::Name, name.into())
+        .filter(folder::Column::Id.eq(req.id))
+        .filter(folder::Column::OwnerId.eq(user_id))
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    Ok(Json(FolderWriteResp {
+        wrote: res.rows_affected > 0,
+    }))
+}
@@ -0,0 +1,72 @@
+//! Probes for orchestrators, served without authentication
+//!
+//! `/healthz` answers as long as the process serves requests; `/readyz` also
+//! checks the database, and the provider when `READY_CHECK_UPSTREAM` is set,
+//! answering 503 until they are fine
+
+use std::sync::Arc;
+
+use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+use dotenv::var;
+use migration::MigratorTrait;
+use serde::Serialize;
+
+use crate::AppState;
+
+#[derive(Debug, Serialize)]
+pub struct HealthResp {
+    pub status: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadyResp {
+    pub status: &'static str,
+    pub database: bool,
+    /// No migration is pending
+    pub migrations: bool,
+    /// Only with `READY_CHECK_UPSTREAM`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream: Option<bool>,
+}
+
+pub async fn healthz() -> Json<HealthResp> {
+    Json(HealthResp { status: "ok" })
+}
+
+pub async fn readyz(State(app): State<Arc<AppState>>) -> impl IntoResponse {
+    let database = app
+        .conn
+        .ping()
+        .await
+        .inspect_err(|e| tracing::warn!("database is unreachable: {}", e))
+        .is_ok();
+    // skipped when the database is down, it would only fail again
+    let migrations = database
+        && migration::Migrator::get_pending_migrations(&app.conn)
+            .await
+            .inspect_err(|e| tracing::warn!("cannot read migrations: {}", e))
+            .is_ok_and(|x| x.is_empty());
+    let upstream = match var("READY_CHECK_UPSTREAM").is_ok_and(|x| x == "1" || x == "true") {
+        true => Some(
+            app.openrouter
+                .ping()
+                .await
+                .inspect_err(|e| tracing::warn!("provider is unreachable: {}", e))
+                .is_ok(),
+        ),
+        false => None,
+    };
+
+    let ready = database && migrations && upstream.unwrap_or(true);
+    let status = match ready {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    let resp = ReadyResp {
+        status: if ready { "ok" } else { "unavailable" },
+        database,
+        migrations,
+        upstream,
+    };
+    (status, Json(resp))
+}
@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{chat_collection, prelude::*};
+use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use super::check_collections;
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+    utils::member,
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct KbAssignReq {
+    pub chat_id: i32,
+    /// Replace the scope of the chat, empty to search every document
+    pub collection_ids: Vec<i32>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct KbAssignResp {
+    pub wrote: bool,
+}
+
+/// Restrict the documents searched for the replies of the chat to those in
+/// the collections; only owners of the chat scope it, to their own
+/// collections, and members find none of their documents in a scoped chat
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Json(req): Json<KbAssignReq>,
+) -> JsonResult<KbAssignResp> {
+    let mut collection_ids = req.collection_ids;
+    collection_ids.sort_unstable();
+    collection_ids.dedup();
+    member::owned(&app.conn, req.chat_id, user_id, workspace_id).await?;
+    check_collections(&app.conn, user_id, &collection_ids).await?;
+
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+    ChatCollection::delete_many()
+        .filter(chat_collection::Column::ChatId.eq(req.chat_id))
+        .exec(&txn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    if !collection_ids.is_empty() {
+        ChatCollection::insert_many(collection_ids.into_iter().map(|collection_id| {
+            chat_collection::ActiveModel {
+                chat_id: Set(req.chat_id),
+                collection_id: Set(collection_id),
+            }
+        }))
+        .exec(&txn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    }
+    txn.commit().await.kind(ErrorKind::Internal)?;
+
+    Ok(Json(KbAssignResp { wrote: true }))
+}
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{collection, prelude::*};
+use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState, config::KB_MAX_COLLECTIONS_PER_USER, errors::*, middlewares::auth::UserId,
+    utils::folder::name,
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct KbCollectionCreateReq {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct KbCollectionCreateResp {
+    pub id: i32,
+}
+
+/// Return the collection of the same name if there is one
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<KbCollectionCreateReq>,
+) -> JsonResult<KbCollectionCreateResp> {
+    let name = name(&req.name)?;
+
+    let owned = Collection::find().filter(collection::Column::OwnerId.eq(user_id));
+    if let Some(collection) = owned
+        .clone()
+        .filter(collection::Column::Name.eq(&name))
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+    {
+        return Ok(Json(KbCollectionCreateResp { id: collection.id }));
+    }
+    if owned.count(&app.conn).await.kind(ErrorKind::Internal)? >= KB_MAX_COLLECTIONS_PER_USER {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!(
+                "a user has at most {} collections",
+                KB_MAX_COLLECTIONS_PER_USER
+            ),
+        }));
+    }
+
+    let id = Collection::insert(collection::ActiveModel {
+        owner_id: Set(user_id),
+        name: Set(name),
+        ..Default::default()
+    })
+    .exec(&app.conn)
+    .await
+    .kind(ErrorKind::Internal)?
+    .last_insert_id;
+
+    Ok(Json(KbCollectionCreateResp { id }))
+}
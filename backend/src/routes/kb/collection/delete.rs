@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{collection, document, prelude::*};
+use migration::Expr;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct KbCollectionDeleteReq {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct KbCollectionDeleteResp {
+    pub deleted: bool,
+}
+
+/// Its documents are kept outside of any collection, chats scoped to it lose
+/// the scope
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<KbCollectionDeleteReq>,
+) -> JsonResult<KbCollectionDeleteResp> {
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+    let res = Collection::delete_many()
+        .filter(collection::Column::Id.eq(req.id))
+        .filter(collection::Column::OwnerId.eq(user_id))
+        .exec(&txn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    if res.rows_affected > 0 {
+        Document::update_many()
+            .col_expr(document::Column::CollectionId, Expr::value(None::<i32>))
+            .filter(document::Column::CollectionId.eq(req.id))
+            .exec(&txn)
+            .await
+            .kind(ErrorKind::Internal)?;
+    }
+    txn.commit().await.kind(ErrorKind::Internal)?;
+
+    Ok(Json(KbCollectionDeleteResp {
+        deleted: res.rows_affected > 0,
+    }))
+}
@@ -0,0 +1,62 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{Extension, Json, extract::State};
+use entity::{collection, document, prelude::*};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct KbCollectionListReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct KbCollectionListResp {
+    /// By name
+    pub list: Vec<KbCollectionListRespItem>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct KbCollectionListRespItem {
+    pub id: i32,
+    pub name: String,
+    pub documents: u32,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(_): Json<KbCollectionListReq>,
+) -> JsonResult<KbCollectionListResp> {
+    let counts: HashMap<i32, i64> = Document::find()
+        .select_only()
+        .column(document::Column::CollectionId)
+        .column_as(document::Column::Id.count(), "count")
+        .filter(document::Column::OwnerId.eq(user_id))
+        .filter(document::Column::CollectionId.is_not_null())
+        .group_by(document::Column::CollectionId)
+        .into_tuple::<(i32, i64)>()
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .into_iter()
+        .collect();
+    let list = Collection::find()
+        .filter(collection::Column::OwnerId.eq(user_id))
+        .order_by_asc(collection::Column::Name)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .into_iter()
+        .map(|x| KbCollectionListRespItem {
+            documents: counts.get(&x.id).copied().unwrap_or_default() as u32,
+            id: x.id,
+            name: x.name,
+        })
+        .collect();
+    Ok(Json(KbCollectionListResp { list }))
+}
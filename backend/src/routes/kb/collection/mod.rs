@@ -0,0 +1,18 @@
+//! Collections sort documents, a chat can be scoped to some of them
+
+use std::sync::Arc;
+
+use axum::{Router, routing::post};
+
+use crate::AppState;
+
+mod create;
+mod delete;
+mod list;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/create", post(create::route))
+        .route("/list", post(list::route))
+        .route("/delete", post(delete::route))
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{DocumentStatus, document, file, prelude::*};
+use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use super::check_collections;
+use crate::{
+    AppState, config::KB_MAX_DOCUMENTS_PER_USER, errors::*, jobs::Task, middlewares::auth::UserId,
+    utils::extract,
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct KbCreateReq {
+    /// Uploaded with `/api/file/upload`
+    pub file_id: i32,
+    #[serde(default)]
+    pub collection_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct KbCreateResp {
+    pub id: i32,
+}
+
+/// The document is pending until ingested, see `kb/list`
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<KbCreateReq>,
+) -> JsonResult<KbCreateResp> {
+    let file = File::find_by_id(req.file_id)
+        .filter(file::Column::OwnerId.eq(user_id))
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Cannot find the file")
+        .kind(ErrorKind::ResourceNotFound)?;
+    check_collections(&app.conn, user_id, req.collection_id.as_slice()).await?;
+    if extract::format(&file.content_type, &file.name).is_none() {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!("cannot read {} files", file.content_type),
+        }));
+    }
+
+    let count = Document::find()
+        .filter(document::Column::OwnerId.eq(user_id))
+        .count(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    if count >= KB_MAX_DOCUMENTS_PER_USER {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!("a user has at most {} documents", KB_MAX_DOCUMENTS_PER_USER),
+        }));
+    }
+
+    let id = Document::insert(document::ActiveModel {
+        owner_id: Set(user_id),
+        file_id: Set(Some(file.id)),
+        name: Set(file.name),
+        status: Set(DocumentStatus::Pending),
+        collection_id: Set(req.collection_id),
+        created_at: Set(time::UtcDateTime::now().unix_timestamp()),
+        ..Default::default()
+    })
+    .exec(&app.conn)
+    .await
+    .kind(ErrorKind::Internal)?
+    .last_insert_id;
+    app.jobs
+        .push(&app.conn, Task::Ingest { document_id: id })
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    Ok(Json(KbCreateResp { id }))
+}
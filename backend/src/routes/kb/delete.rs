@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{document, prelude::*};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct KbDeleteReq {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct KbDeleteResp {
+    /// false if the document does not exist
+    pub deleted: bool,
+}
+
+/// Its chunks go with it, the file is purged with the other unattached files
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<KbDeleteReq>,
+) -> JsonResult<KbDeleteResp> {
+    let res = Document::delete_many()
+        .filter(document::Column::Id.eq(req.id))
+        .filter(document::Column::OwnerId.eq(user_id))
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    Ok(Json(KbDeleteResp {
+        deleted: res.rows_affected > 0,
+    }))
+}
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{DocumentStatus, document, prelude::*};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QueryTrait};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct KbListReq {
+    /// Only the documents of the collection
+    #[serde(default)]
+    pub collection_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct KbListResp {
+    /// Latest first
+    pub list: Vec<KbListRespItem>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct KbListRespItem {
+    pub id: i32,
+    pub name: String,
+    pub status: DocumentStatus,
+    /// Why the ingestion failed
+    pub error: Option<String>,
+    pub chunks: i32,
+    pub created_at: i64,
+    pub collection_id: Option<i32>,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<KbListReq>,
+) -> JsonResult<KbListResp> {
+    let list = Document::find()
+        .filter(document::Column::OwnerId.eq(user_id))
+        .apply_if(req.collection_id, |q, x| {
+            q.filter(document::Column::CollectionId.eq(x))
+        })
+        .order_by_desc(document::Column::Id)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .into_iter()
+        .map(|x| KbListRespItem {
+            id: x.id,
+            name: x.name,
+            status: x.status,
+            error: x.error,
+            chunks: x.chunks,
+            created_at: x.created_at,
+            collection_id: x.collection_id,
+        })
+        .collect();
+    Ok(Json(KbListResp { list }))
+}
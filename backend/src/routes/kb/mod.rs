@@ -0,0 +1,53 @@
+//! Documents of the knowledge base of a user, passages of them are added to
+//! the prompt of their replies, see `kb`
+
+use std::sync::Arc;
+
+use axum::{Json, Router, routing::post};
+use entity::prelude::*;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, PaginatorTrait, QueryFilter};
+
+use crate::{AppState, errors::*};
+
+mod assign;
+mod collection;
+mod create;
+mod delete;
+mod list;
+mod r#move;
+mod reindex;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/create", post(create::route))
+        .route("/list", post(list::route))
+        .route("/delete", post(delete::route))
+        .route("/move", post(r#move::route))
+        .route("/reindex", post(reindex::route))
+        .route("/assign", post(assign::route))
+        .nest("/collection", collection::routes())
+}
+
+/// Refuse collections the user does not own
+async fn check_collections(
+    conn: &impl ConnectionTrait,
+    user_id: i32,
+    ids: &[i32],
+) -> Result<(), Json<Error>> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+    let owned = Collection::find()
+        .filter(entity::collection::Column::Id.is_in(ids.to_vec()))
+        .filter(entity::collection::Column::OwnerId.eq(user_id))
+        .count(conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    if owned as usize != ids.len() {
+        return Err(Json(Error {
+            error: ErrorKind::ResourceNotFound,
+            reason: "Cannot find the collection".to_owned(),
+        }));
+    }
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{document, prelude::*};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use super::check_collections;
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct KbMoveReq {
+    /// id of the document
+    pub id: i32,
+    /// None to take it out of its collection
+    pub collection_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct KbMoveResp {
+    pub wrote: bool,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<KbMoveReq>,
+) -> JsonResult<KbMoveResp> {
+    check_collections(&app.conn, user_id, req.collection_id.as_slice()).await?;
+    let res = Document::update_many()
+        .col_expr(document::Column::CollectionId, req.collection_id.into())
+        .filter(document::Column::Id.eq(req.id))
+        .filter(document::Column::OwnerId.eq(user_id))
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    Ok(Json(KbMoveResp {
+        wrote: res.rows_affected > 0,
+    }))
+}
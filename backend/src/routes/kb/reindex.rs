@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{DocumentStatus, document, prelude::*};
+use migration::Expr;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, jobs::Task, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct KbReindexReq {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct KbReindexResp {
+    /// false if the document is being read already
+    pub reindexed: bool,
+}
+
+/// Read the file of the document again, e.g. after a failure or a change of
+/// `EMBEDDING_MODEL`; its chunks are replaced once done
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<KbReindexReq>,
+) -> JsonResult<KbReindexResp> {
+    let document = Document::find_by_id(req.id)
+        .filter(document::Column::OwnerId.eq(user_id))
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Cannot find the document")
+        .kind(ErrorKind::ResourceNotFound)?;
+    if document.file_id.is_none() {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "the file of the document was deleted".to_owned(),
+        }));
+    }
+
+    // the status guards against two ingestions of the same document
+    let res = Document::update_many()
+        .col_expr(document::Column::Status, DocumentStatus::Pending.into())
+        .col_expr(document::Column::Error, Expr::value(None::<String>))
+        .filter(document::Column::Id.eq(document.id))
+        .filter(document::Column::Status.ne(DocumentStatus::Pending))
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let reindexed = res.rows_affected > 0;
+    if reindexed {
+        let task = Task::Ingest {
+            document_id: document.id,
+        };
+        app.jobs
+            .push(&app.conn, task)
+            .await
+            .kind(ErrorKind::Internal)?;
+    }
+
+    Ok(Json(KbReindexResp { reindexed }))
+}
@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{chat, chat_label, label, prelude::*};
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, config::CHAT_MAX_LABELS, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct LabelAssignReq {
+    pub chat_id: i32,
+    /// Replace the labels of the chat, empty to remove them all
+    pub label_ids: Vec<i32>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct LabelAssignResp {
+    pub wrote: bool,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<LabelAssignReq>,
+) -> JsonResult<LabelAssignResp> {
+    let mut label_ids = req.label_ids;
+    label_ids.sort();
+    label_ids.dedup();
+    if label_ids.len() > CHAT_MAX_LABELS {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!("At most {} labels per chat", CHAT_MAX_LABELS),
+        }));
+    }
+
+    let owned = Chat::find_by_id(req.chat_id)
+        .filter(chat::Column::OwnerId.eq(user_id))
+        .count(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        > 0;
+    let labels = Label::find()
+        .filter(label::Column::Id.is_in(label_ids.clone()))
+        .filter(label::Column::OwnerId.eq(user_id))
+        .count(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    if !owned || labels as usize != label_ids.len() {
+        return Err(Json(Error {
+            error: ErrorKind::ResourceNotFound,
+            reason: "".to_owned(),
+        }));
+    }
+
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+    ChatLabel::delete_many()
+        .filter(chat_label::Column::ChatId.eq(req.chat_id))
+        .exec(&txn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    if !label_ids.is_empty() {
+        ChatLabel::insert_many(
+            label_ids
+                .into_iter()
+                .map(|label_id| chat_label::ActiveModel {
+                    chat_id: Set(req.chat_id),
+                    label_id: Set(label_id),
+                }),
+        )
+        .exec(&txn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    }
+    txn.commit().await.kind(ErrorKind::Internal)?;
+
+    Ok(Json(LabelAssignResp { wrote: true }))
+}
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{label, prelude::*};
+use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId, utils::folder::name};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct LabelCreateReq {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct LabelCreateResp {
+    pub id: i32,
+}
+
+/// Return the label of the same name if there is one
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<LabelCreateReq>,
+) -> JsonResult<LabelCreateResp> {
+    let name = name(&req.name)?;
+
+    if let Some(label) = Label::find()
+        .filter(label::Column::OwnerId.eq(user_id))
+        .filter(label::Column::Name.eq(&name))
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+    {
+        return Ok(Json(LabelCreateResp { id: label.id }));
+    }
+
+    let id = Label::insert(label::ActiveModel {
+        owner_id: Set(user_id),
+        name: Set(name),
+        ..Default::default()
+    })
+    .exec(&app.conn)
+    .await
+    .kind(ErrorKind::Internal)?
+    .last_insert_id;
+
+    Ok(Json(LabelCreateResp { id }))
+}
@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{label, prelude::*};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct LabelDeleteReq {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct LabelDeleteResp {
+    pub deleted: bool,
+}
+
+/// Chats lose the label, they are kept
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<LabelDeleteReq>,
+) -> JsonResult<LabelDeleteResp> {
+    let res = Label::delete_many()
+        .filter(label::Column::Id.eq(req.id))
+        .filter(label::Column::OwnerId.eq(user_id))
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    Ok(Json(LabelDeleteResp {
+        deleted: res.rows_affected > 0,
+    }))
+}
@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{chat_label, label, prelude::*};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct LabelListReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct LabelListResp {
+    pub list: Vec<LabelListRespItem>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct LabelListRespItem {
+    pub id: i32,
+    pub name: String,
+    pub chats: u32,
+}
+
+/// Labels of the current user by name
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(_): Json<LabelListReq>,
+) -> JsonResult<LabelListResp> {
+    let labels = Label::find()
+        .filter(label::Column::OwnerId.eq(user_id))
+        .order_by_asc(label::Column::Name)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let counts: Vec<(i32, i64)> = ChatLabel::find()
+        .select_only()
+        .column(chat_label::Column::LabelId)
+        .column_as(chat_label::Column::ChatId.count(), "count")
+        .filter(chat_label::Column::LabelId.is_in(labels.iter().map(|x| x.id)))
+        .group_by(chat_label::Column::LabelId)
+        .into_tuple()
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let list = labels
+        .into_iter()
+        .map(|x| LabelListRespItem {
+            chats: counts
+                .iter()
+                .find(|(id, _)| *id == x.id)
+                .map(|(_, count)| *count as u32)
+                .unwrap_or_default(),
+            id: x.id,
+            name: x.name,
+        })
+        .collect();
+
+    Ok(Json(LabelListResp { list }))
+}
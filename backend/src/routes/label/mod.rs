@@ -0,0 +1,20 @@
+use std::sync::Arc;
+
+use axum::{Router, routing::post};
+
+use crate::AppState;
+
+mod assign;
+mod create;
+mod delete;
+mod list;
+mod write;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/create", post(create::route))
+        .route("/list", post(list::route))
+        .route("/write", post(write::route))
+        .route("/delete", post(delete::route))
+        .route("/assign", post(assign::route))
+}
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{label, prelude::*};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId, utils::folder::name};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct LabelWriteReq {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct LabelWriteResp {
+    /// false if the label does not exist or the name is taken
+    pub wrote: bool,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<LabelWriteReq>,
+) -> JsonResult<LabelWriteResp> {
+    let name = name(&req.name)?;
+
+    let taken = Label::find()
+        .filter(label::Column::OwnerId.eq(user_id))
+        .filter(label::Column::Name.eq(&name))
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .is_some();
+    if taken {
+        return Ok(Json(LabelWriteResp { wrote: false }));
+    }
+
+    let res = Label::update_many()
+        .col_expr(label::Column::Name, name.into())
+        .filter(label::Column::Id.eq(req.id))
+        .filter(label::Column::OwnerId.eq(user_id))
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    Ok(Json(LabelWriteResp {
+        wrote: res.rows_affected > 0,
+    }))
+}
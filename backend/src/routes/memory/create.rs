@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    config::{MEMORY_FACT_MAX_CHARS, MEMORY_MAX_PER_USER},
+    errors::*,
+    memory::{self, Saved},
+    middlewares::auth::UserId,
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct MemoryCreateReq {
+    /// A short sentence about the user, e.g. `I am vegetarian`
+    pub content: String,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct MemoryCreateResp {
+    /// None if the same fact is already kept
+    pub id: Option<i32>,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<MemoryCreateReq>,
+) -> JsonResult<MemoryCreateResp> {
+    let count = req.content.trim().chars().count();
+    if count == 0 || count > MEMORY_FACT_MAX_CHARS {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!("a fact has 1 to {} characters", MEMORY_FACT_MAX_CHARS),
+        }));
+    }
+    let id = match memory::save(&app.conn, user_id, None, &req.content)
+        .await
+        .kind(ErrorKind::Internal)?
+    {
+        Saved::New(id) => Some(id),
+        Saved::Known => None,
+        Saved::Full => {
+            return Err(Json(Error {
+                error: ErrorKind::MalformedRequest,
+                reason: format!("a user has at most {} facts", MEMORY_MAX_PER_USER),
+            }));
+        }
+    };
+
+    Ok(Json(MemoryCreateResp { id }))
+}
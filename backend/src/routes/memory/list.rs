@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, memory, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct MemoryListReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct MemoryListResp {
+    /// Latest first
+    pub list: Vec<MemoryListRespItem>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct MemoryListRespItem {
+    pub id: i32,
+    pub content: String,
+    /// Chat the model saved the fact in, None if the user wrote it
+    pub chat_id: Option<i32>,
+    pub created_at: i64,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(_): Json<MemoryListReq>,
+) -> JsonResult<MemoryListResp> {
+    let list = memory::all(&app.conn, user_id)
+        .await
+        .kind(ErrorKind::Internal)?
+        .into_iter()
+        .map(|x| MemoryListRespItem {
+            id: x.id,
+            content: x.content,
+            chat_id: x.chat_id,
+            created_at: x.created_at,
+        })
+        .collect();
+    Ok(Json(MemoryListResp { list }))
+}
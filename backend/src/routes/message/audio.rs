@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    body::Body,
+    extract::{Path, State},
+    http::header,
+    response::IntoResponse,
+};
+use entity::{ChunkKind, MessageKind, chunk, prelude::*};
+use sea_orm::{QueryOrder, prelude::*};
+
+use super::create::joined_chat;
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+    tts::speakable,
+};
+
+/// Speech of an assistant reply in MP3, streamed as it is synthesized with
+/// the voice settings of the user
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, Json<Error>> {
+    let tts = app
+        .tts
+        .as_ref()
+        .ok_or("speech is not enabled on this server")
+        .kind(ErrorKind::MalformedRequest)?;
+    let message = Message::find_by_id(id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .filter(|x| x.kind == MessageKind::Assistant)
+        .ok_or("Cannot find the reply")
+        .kind(ErrorKind::ResourceNotFound)?;
+    joined_chat(&app.conn, user_id, workspace_id, message.chat_id).await?;
+
+    let text = Chunk::find()
+        .filter(chunk::Column::MessageId.eq(id))
+        .filter(chunk::Column::Kind.eq(ChunkKind::Text))
+        .order_by_asc(chunk::Column::Id)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .into_iter()
+        .map(|x| x.content)
+        .collect::<String>();
+    let text = speakable(&text);
+    if text.is_empty() {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "Nothing in the reply to read aloud".to_owned(),
+        }));
+    }
+
+    let preference = User::find_by_id(user_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Cannot find user")
+        .kind(ErrorKind::Internal)?
+        .preference;
+    let speed = preference.voice_speed.and_then(|x| x.parse().ok());
+    let res = tts
+        .synthesize(
+            &text,
+            preference.voice.as_deref().filter(|x| !x.is_empty()),
+            speed,
+        )
+        .await
+        .kind(ErrorKind::ApiFail)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "audio/mpeg"),
+            (header::CACHE_CONTROL, "private, max-age=3600"),
+        ],
+        Body::from_stream(res.bytes_stream()),
+    ))
+}
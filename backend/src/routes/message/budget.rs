@@ -0,0 +1,127 @@
+use std::future::pending;
+
+use serde::{Deserialize, Serialize};
+use tokio::time::{Duration, Instant, sleep_until};
+use typeshare::typeshare;
+
+use crate::config::{
+    AGENT_MAX_COST, AGENT_MAX_SECS, AGENT_MAX_STEPS, MAX_TOOL_DEPTH, MAX_TOOL_STEPS,
+};
+
+/// Limits of the tool loop admins set at `admin/config`, read when a reply
+/// starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[typeshare]
+#[serde(default)]
+pub struct AgentLoopPolicy {
+    /// Rounds of tool calls of a reply, the model answers without tools after
+    pub max_depth: u32,
+    /// Tool calls of a reply outside of agent mode
+    pub max_tool_calls: u32,
+    /// Tool calls of a reply in agent mode
+    pub agent_max_tool_calls: u32,
+    /// Seconds a reply in agent mode runs before it writes its final report
+    pub agent_max_secs: u32,
+}
+
+impl Default for AgentLoopPolicy {
+    fn default() -> Self {
+        Self {
+            max_depth: MAX_TOOL_DEPTH as u32,
+            max_tool_calls: MAX_TOOL_STEPS as u32,
+            agent_max_tool_calls: AGENT_MAX_STEPS as u32,
+            agent_max_secs: AGENT_MAX_SECS as u32,
+        }
+    }
+}
+
+/// Server-side limit on how far a single assistant turn can run the tool loop
+#[derive(Debug, Clone)]
+pub struct Budget {
+    pub max_steps: usize,
+    pub max_depth: usize,
+    pub max_cost: Option<f64>,
+    pub deadline: Option<Instant>,
+    pub start: Instant,
+
+    pub steps: usize,
+    /// Rounds of tool calls ended
+    pub depth: usize,
+    pub cost: f64,
+    /// Stop before the next completion while the spend guard pause generations
+    /// or a spending cap is reached
+    pub spend_guard: bool,
+}
+
+impl Budget {
+    pub fn normal(policy: &AgentLoopPolicy) -> Self {
+        Self {
+            max_steps: policy.max_tool_calls as usize,
+            max_depth: policy.max_depth as usize,
+            max_cost: None,
+            deadline: None,
+            start: Instant::now(),
+            steps: 0,
+            depth: 0,
+            cost: 0.0,
+            spend_guard: false,
+        }
+    }
+
+    pub fn agent(policy: &AgentLoopPolicy) -> Self {
+        let start = Instant::now();
+        Self {
+            max_steps: policy.agent_max_tool_calls as usize,
+            max_depth: policy.max_depth as usize,
+            max_cost: Some(AGENT_MAX_COST),
+            deadline: Some(start + Duration::from_secs(policy.agent_max_secs as u64)),
+            start,
+            steps: 0,
+            depth: 0,
+            cost: 0.0,
+            spend_guard: false,
+        }
+    }
+
+    /// The reason why the budget is exhausted, if it is
+    pub fn exhausted(&self) -> Option<&'static str> {
+        if self.steps >= self.max_steps {
+            return Some("maximum number of tool calls reached");
+        }
+        if self.depth >= self.max_depth {
+            return Some("maximum depth of tool calls reached");
+        }
+        if self.max_cost.is_some_and(|max| self.cost >= max) {
+            return Some("maximum cost reached");
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Some("maximum time reached");
+        }
+        None
+    }
+
+    /// Resolve when the wall time limit is hit, never resolve if there is no limit
+    pub async fn timeout(&self) {
+        match self.deadline {
+            Some(deadline) => sleep_until(deadline).await,
+            None => pending().await,
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// Appended as the last message once the budget is exhausted, so the model wraps up
+pub fn final_report_prompt(reason: &str) -> String {
+    format!(
+        "The autonomy budget for this task is exhausted ({}). \
+        Do not call any more tools. \
+        Write a final report of what has been done, what is left, and any result obtained so far.",
+        reason
+    )
+}
@@ -1,33 +1,60 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use anyhow::{Context, Result};
-use axum::{Extension, Json, extract::State};
-use entity::{MessageKind, message, patch::ChunkKind, prelude::*};
-use migration::Expr;
-use sea_orm::{ActiveValue, EntityOrSelect, IntoActiveModel, QueryOrder, prelude::*};
+use axum::{Extension, Json, extract::State, http::HeaderMap};
+use dotenv::var;
+use entity::{
+    ApiKeyScope, LinkKind, MessageKind, UserRole, chat, link, message, patch::ChunkKind, prelude::*,
+};
+use schemars::JsonSchema;
+use sea_orm::{ActiveValue, IntoActiveModel, QueryOrder, prelude::*};
 use serde::{Deserialize, Serialize};
-use tokio::{select, task::yield_now};
+use tokio::{select, task::yield_now, time::timeout};
+use tracing::Instrument;
 use typeshare::typeshare;
 
+use super::{
+    budget::{Budget, final_report_prompt},
+    stats::Stats,
+};
 use crate::{
-    AppState,
+    AppState, audit, compaction,
+    config::{
+        FILE_MAX_PER_MESSAGE, MODERATION_OUTPUT_TOKENS, TOOL_INPUT_MAX_ROUNDS, TOOL_INPUT_TIMEOUT,
+        TOOL_REPAIR_MAX_ROUNDS,
+    },
     errors::*,
-    middlewares::auth::UserId,
+    files::Files,
+    idempotency::{self, Claim},
+    jobs::Task,
+    kb,
+    middlewares::{
+        auth::{ApiKeyUser, UserId, WorkspaceId},
+        locale, request_id,
+    },
+    moderation::{self, Stage, Verdict},
     openrouter::{self, StreamCompletionResp},
-    prompts::{self, PromptStore},
-    sse::{self, AssistantMessage, BufferChunk, EndKind, Publisher},
-    tools::{self, ToolBox},
+    prompts, push, quota, reply_stats,
+    routes::chat::draft,
+    routing,
+    sse::{self, AssistantMessage, BufferChunk, EndKind, PlanStatus, PlanStep, Publisher},
+    tools::{self, EntityLink, ToolBox, ToolCtx},
+    utils::{self, branch, context_stat, member, workspace},
+    webhook,
 };
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct MessageCreateReq {
     pub chat_id: i32,
     pub mode: MessageCreateReqMode,
     pub text: String,
+    /// Uploaded with `/api/file/upload`, sent to the model along the text
+    #[serde(default)]
+    pub files: Vec<i32>,
 }
 
-#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[typeshare]
 #[serde(rename_all = "snake_case")]
 pub enum MessageCreateReqMode {
@@ -37,33 +64,165 @@ pub enum MessageCreateReqMode {
     Research,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct MessageCreateResp {
     pub id: i32,
 }
 
+/// With an `Idempotency-Key` header, a retry is answered with the message of
+/// the first request, see `idempotency`
 pub async fn route(
     State(app): State<Arc<AppState>>,
     Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    api_key: Option<Extension<ApiKeyUser>>,
+    headers: HeaderMap,
     Json(req): Json<MessageCreateReq>,
 ) -> JsonResult<MessageCreateResp> {
-    let chat = Chat::find_by_id(req.chat_id)
-        .one(&app.conn)
+    let Some(key) = idempotency::key(&headers)? else {
+        let id = send(app, user_id, workspace_id, api_key, req).await?;
+        return Ok(Json(MessageCreateResp { id }));
+    };
+    let fingerprint = idempotency::fingerprint((req.chat_id, &req.mode, &req.text, &req.files));
+    let id = match app.idempotency.claim(user_id, key, fingerprint)? {
+        Claim::Seen(rx) => idempotency::wait(rx).await?,
+        Claim::New(pending) => {
+            let task = request_id::spawn(tracing::info_span!("send"), async move {
+                let res = send(app.clone(), user_id, workspace_id, api_key, req).await;
+                app.idempotency.finish(pending, res.clone());
+                res
+            });
+            task.await.kind(ErrorKind::Internal)??
+        }
+    };
+    Ok(Json(MessageCreateResp { id }))
+}
+
+async fn send(
+    app: Arc<AppState>,
+    user_id: i32,
+    workspace_id: i32,
+    api_key: Option<Extension<ApiKeyUser>>,
+    req: MessageCreateReq,
+) -> Result<i32, Json<Error>> {
+    let chat = joined_chat(&app.conn, user_id, workspace_id, req.chat_id).await?;
+    let mut files = req.files;
+    files.sort_unstable();
+    files.dedup();
+    if files.len() > FILE_MAX_PER_MESSAGE {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!("a message carry at most {} files", FILE_MAX_PER_MESSAGE),
+        }));
+    }
+    if !crate::files::all_owned(&app.conn, user_id, &files)
         .await
         .kind(ErrorKind::Internal)?
-        .context("The request chat is not exists")
-        .kind(ErrorKind::ResourceNotFound)?;
-
-    if chat.owner_id != user_id {
+    {
         return Err(Json(Error {
             error: ErrorKind::ResourceNotFound,
-            reason: "".to_owned(),
+            reason: "cannot find the files".to_owned(),
         }));
     }
+    let chat_id = chat.id;
+    let id = start(
+        app.clone(),
+        user_id,
+        api_key,
+        chat,
+        req.mode,
+        Turn::Append {
+            text: req.text,
+            files,
+        },
+    )
+    .await?
+    .context("No user message was sent")
+    .kind(ErrorKind::Internal)?;
 
-    let model = Model::find_by_id(chat.model_id)
-        .one(&app.conn)
+    // the draft was this message
+    if let Err(err) = draft::clear(&app, chat_id, user_id).await {
+        tracing::warn!("cannot clear the draft of chat {}: {}", chat_id, err);
+    }
+    Ok(id)
+}
+
+/// Where a completion start in the tree of messages, see `utils::branch`
+pub(crate) enum Turn {
+    /// A user message at the end of the active branch
+    Append { text: String, files: Vec<i32> },
+    /// A user message next to the one being edited
+    Edit {
+        parent_id: Option<i32>,
+        text: String,
+        files: Vec<i32>,
+    },
+    /// Another reply to the same message
+    Regenerate { parent_id: Option<i32> },
+    /// A reply going on from an interrupted one, see `chat/{id}/continue`
+    Continue { message_id: i32 },
+}
+
+/// A chat of the workspace the user created or is a member of, see
+/// `utils::member`
+pub(super) async fn joined_chat(
+    conn: &DbConn,
+    user_id: i32,
+    workspace_id: i32,
+    chat_id: i32,
+) -> Result<chat::Model, Json<Error>> {
+    let (chat, _) = member::find(conn, chat_id, user_id, workspace_id).await?;
+    Ok(chat)
+}
+
+/// Send the user message of `turn` if any, then stream the reply in the
+/// background
+///
+/// Return the id of the user message
+pub(crate) async fn start(
+    app: Arc<AppState>,
+    user_id: i32,
+    api_key: Option<Extension<ApiKeyUser>>,
+    mut chat: chat::Model,
+    mode: MessageCreateReqMode,
+    turn: Turn,
+) -> Result<Option<i32>, Json<Error>> {
+    let chat_id = chat.id;
+
+    // only this reply is routed, the chat is not saved with the model
+    let routed_text = match &turn {
+        Turn::Append { text, .. } | Turn::Edit { text, .. } => Some(text.clone()),
+        Turn::Regenerate {
+            parent_id: Some(id),
+        } => Some(
+            kb::message_text(&app.conn, *id)
+                .await
+                .kind(ErrorKind::Internal)?,
+        ),
+        Turn::Regenerate { parent_id: None } => Some(String::new()),
+        // the reply goes on with the model of the chat
+        Turn::Continue { .. } => None,
+    };
+    if let Some(text) = routed_text
+        && let Some(model_id) = routing::route(&app.conn, user_id, &chat, &text)
+            .await
+            .kind(ErrorKind::Internal)?
+    {
+        chat.model_id = model_id;
+    }
+
+    // the model may have been taken off the workspace since the chat started
+    if !workspace::allows_model(&app.conn, chat.workspace_id, chat.model_id)
+        .await
+        .kind(ErrorKind::Internal)?
+    {
+        return Err(Json(Error {
+            error: ErrorKind::Unauthorized,
+            reason: "the model is not offered in this workspace".to_owned(),
+        }));
+    }
+    let model = utils::model::find(&app, chat.model_id)
         .await
         .kind(ErrorKind::Internal)?
         .context("Malformde database")
@@ -72,85 +231,321 @@ pub async fn route(
         .context("Malformed model config")
         .kind(ErrorKind::Internal)?;
 
-    let puber = app
-        .sse
-        .publish(req.chat_id)
+    let user = User::find_by_id(user_id)
+        .one(&app.conn)
         .await
+        .kind(ErrorKind::Internal)?
+        .context("Cannot find user")
         .kind(ErrorKind::Internal)?;
-    let msg_id = puber
-        .user_message(req.text)
+    if user.demo_expires_at.is_some() {
+        app.demo
+            .as_ref()
+            .ok_or("demo mode is disabled")
+            .and_then(|demo| demo.allow_message(user_id))
+            .kind(ErrorKind::Unauthorized)?;
+    }
+    // admins still run, they are the ones to look into it
+    let spend_guard = user.role != UserRole::Admin;
+    if spend_guard && app.spend.paused().is_some() {
+        return Err(Json(Error {
+            error: ErrorKind::Paused,
+            reason: SPEND_PAUSED.to_owned(),
+        }));
+    }
+    if user.role != UserRole::Admin
+        && let Some(reason) = app
+            .quotas
+            .exceeded(&app.conn, user_id)
+            .await
+            .kind(ErrorKind::Internal)?
+    {
+        return Err(Json(Error {
+            error: ErrorKind::QuotaExceeded,
+            reason,
+        }));
+    }
+    if spend_guard
+        && let Some(reason) = app
+            .spend_caps
+            .exceeded(&app.conn, user_id, chat.model_id)
+            .await
+            .kind(ErrorKind::Internal)?
+    {
+        return Err(Json(Error {
+            error: ErrorKind::SpendCapped,
+            reason,
+        }));
+    }
+    if !user.email_verified && app.mailer.is_some() {
+        return Err(Json(Error {
+            error: ErrorKind::Unauthorized,
+            reason: "Verify your email with the mailed link before sending messages".to_owned(),
+        }));
+    }
+    if let Turn::Append { text, .. } | Turn::Edit { text, .. } = &turn {
+        let verdict = app.moderation.check(text).await;
+        moderation::record(&app.conn, user_id, chat_id, Stage::Message, &verdict).await;
+        if let Verdict::Block(_) = verdict {
+            return Err(Json(Error {
+                error: ErrorKind::Moderated,
+                reason: "The message was blocked by the moderation policy".to_owned(),
+            }));
+        }
+    }
+
+    let puber = app.sse.publish(chat_id).await.kind(ErrorKind::Internal)?;
+    let continued = matches!(turn, Turn::Continue { .. });
+    // moved only now, the publisher keep other completions of the chat out
+    let (text, files, fork) = match turn {
+        Turn::Append { text, files } => (Some(text), files, None),
+        Turn::Edit {
+            parent_id,
+            text,
+            files,
+        } => (Some(text), files, Some(parent_id)),
+        Turn::Regenerate { parent_id } => (None, vec![], Some(parent_id)),
+        Turn::Continue { message_id } => (None, vec![], Some(Some(message_id))),
+    };
+    if let Some(parent_id) = fork {
+        branch::checkout(&app.conn, chat_id, parent_id)
+            .await
+            .kind(ErrorKind::Internal)?;
+    }
+    let last_message_id = branch::head(&app.conn, chat_id)
         .await
         .kind(ErrorKind::Internal)?;
 
-    tracing::debug!("MessageCreateReqMode: {:?}", req.mode);
+    // a regenerated reply search with the message it answers
+    let query = match &text {
+        Some(text) => Some(text.clone()),
+        None => match last_message_id {
+            Some(id) => Some(
+                kb::message_text(&app.conn, id)
+                    .await
+                    .kind(ErrorKind::Internal)?,
+            ),
+            None => None,
+        },
+    };
+    let (msg_id, history) = match text {
+        Some(text) => {
+            let msg_id = puber
+                .user_message(user_id, text.clone())
+                .await
+                .kind(ErrorKind::Internal)?;
+            crate::files::attach(&app.conn, chat_id, msg_id, &files)
+                .await
+                .kind(ErrorKind::Internal)?;
+            // the prefetched history end before the files
+            let history = app
+                .prefetch
+                .take(chat_id, user_id, last_message_id)
+                .filter(|_| files.is_empty())
+                .map(|mut history| {
+                    tracing::debug!("chat {} use prefetched history", chat_id);
+                    history.push(openrouter::Message::User(text));
+                    history
+                });
+            (Some(msg_id), history)
+        }
+        None => (None, None),
+    };
+    // the partial reply ends the history, the model is asked to finish it
+    let history = match continued {
+        true => {
+            let (mut history, _) = get_history(chat_id, user_id, &app.conn, &app.files)
+                .await
+                .kind(ErrorKind::Internal)?;
+            history.push(openrouter::Message::System(CONTINUE_PROMPT.to_owned()));
+            Some(history)
+        }
+        false => history,
+    };
 
-    let tool_set = match req.mode {
+    tracing::debug!("MessageCreateReqMode: {:?}", mode);
+
+    let tool_set = match mode {
         MessageCreateReqMode::Normal => tools::NORMAL,
         MessageCreateReqMode::Search => tools::SEARCH,
         MessageCreateReqMode::Agent => tools::AGENT,
         MessageCreateReqMode::Research => tools::RESEARCH,
     };
-    let (tool_prompts, tools) = app.tools.list(tool_set);
+    // scripts only get tools if their key allow it
+    let tool_set = match api_key {
+        Some(Extension(ApiKeyUser(scopes))) if !scopes.has(ApiKeyScope::Tools) => tools::NORMAL,
+        _ => tool_set,
+    };
+    let persona = match chat.persona_id {
+        Some(persona_id) => Persona::find_by_id(persona_id)
+            .one(&app.conn)
+            .await
+            .kind(ErrorKind::Internal)?,
+        None => None,
+    };
+    // a persona keeps the tools of the mode it names
+    let tool_names: Vec<&'static str> = app
+        .tools
+        .names(tool_set)
+        .into_iter()
+        .filter(|name| {
+            let kept = persona.as_ref().and_then(|x| x.tools.as_ref());
+            kept.is_none_or(|kept| kept.0.iter().any(|x| x == name))
+        })
+        .collect();
+    let (tool_prompts, tools) = app.tools.list_by_name(tool_names.iter().copied());
     let mut tool_box = app
         .tools
-        .grab(req.chat_id, tool_set)
+        .grab_by_name(chat_id, tool_names.iter().copied())
         .await
         .kind(ErrorKind::Internal)?;
 
-    let user = User::find_by_id(user_id)
-        .one(&app.conn)
-        .await
-        .kind(ErrorKind::Internal)?
-        .context("Cannot find user")
-        .kind(ErrorKind::Internal)?;
-    let system_prompt = match req.mode {
-        MessageCreateReqMode::Search => prompts::SearchStore
-            .template(user.preference.locale.as_deref())
-            .await
-            .kind(ErrorKind::Internal)?
-            .render(&app.prompt, req.chat_id, tool_prompts, (), ())
-            .await
-            .kind(ErrorKind::Internal)?,
-        MessageCreateReqMode::Agent => prompts::AgentStore
-            .template(user.preference.locale.as_deref())
-            .await
-            .kind(ErrorKind::Internal)?
-            .render(&app.prompt, req.chat_id, tool_prompts, (), ())
-            .await
-            .kind(ErrorKind::Internal)?,
-        _ => prompts::ChatStore
-            .template(user.preference.locale.as_deref())
-            .await
-            .kind(ErrorKind::Internal)?
-            .render(&app.prompt, req.chat_id, tool_prompts, (), ())
+    // the prompt of the chat only stands in for the plain chat prompt, tools
+    // of the other modes need theirs
+    let own_prompt = chat.system_prompt.clone();
+    let replace =
+        own_prompt.is_some() && chat.system_prompt_replace && mode == MessageCreateReqMode::Normal;
+    // experiments only cover the plain chat prompt
+    let variant = match mode {
+        MessageCreateReqMode::Normal if !replace => prompts::variant::assign(&app.conn, &chat)
             .await
             .kind(ErrorKind::Internal)?,
+        _ => None,
     };
-    let title_gen_model: openrouter::Model = model.into();
-    let mut stream_model = title_gen_model.clone();
+    let locale = Some(locale::of_user(user.preference.locale.as_deref()).code());
+    let (template, template_version) = match mode {
+        MessageCreateReqMode::Search => {
+            app.prompt
+                .versioned(&prompts::SearchStore, locale, chat.workspace_id)
+                .await
+        }
+        MessageCreateReqMode::Agent => {
+            app.prompt
+                .versioned(&prompts::AgentStore, locale, chat.workspace_id)
+                .await
+        }
+        _ => {
+            app.prompt
+                .versioned(&prompts::ChatStore, locale, chat.workspace_id)
+                .await
+        }
+    }
+    .kind(ErrorKind::Internal)?;
+    let stand_in = match replace {
+        true => own_prompt.clone(),
+        false => variant.as_ref().and_then(|x| x.template.clone()),
+    };
+    // the admin template is not used if something stands in for it
+    let template_version = template_version.filter(|_| stand_in.is_none());
+    let template = template
+        .replace(stand_in)
+        .append(
+            persona
+                .as_ref()
+                .map(|x| x.prompt.as_str())
+                .filter(|x| !x.is_empty()),
+        )
+        .append(own_prompt.as_deref().filter(|_| !replace));
+    let variant_id = variant.map(|x| x.id);
+    let mut system_prompt = template
+        .render(&app.prompt, chat_id, tool_prompts, (), ())
+        .await
+        .kind(ErrorKind::Internal)?;
+    if app.tools.disabled() {
+        system_prompt.push_str(TOOLS_DISABLED_PROMPT);
+    }
+    let memories = app
+        .prompt
+        .memories(user_id, query.as_deref().unwrap_or_default())
+        .await
+        .kind(ErrorKind::Internal)?;
+    system_prompt.push_str(&memories);
+
+    let generation = match chat.reproducible {
+        true => pin_generation(
+            &app.conn,
+            chat_id,
+            &model,
+            template.version(),
+            template_version,
+        )
+        .await
+        .kind(ErrorKind::Internal)?,
+        false => entity::Generation {
+            model_id: model.model_id.clone(),
+            parameter: model.parameter.clone(),
+            prompt_version: template.version(),
+            template_version,
+            created_at: now(),
+        },
+    };
+    let model = match chat.reproducible {
+        true => entity::ModelConfig {
+            model_id: generation.model_id.clone(),
+            parameter: generation.parameter.clone(),
+            ..model
+        },
+        false => model,
+    };
+
+    let mut stream_model: openrouter::Model = model.into();
+    let title_model_id = stream_model.id.clone();
+    let policy = app.settings.current().agent_loop;
+    let mut budget = match mode {
+        MessageCreateReqMode::Agent => Budget::agent(&policy),
+        _ => Budget::normal(&policy),
+    };
+    budget.spend_guard = spend_guard;
 
-    if req.mode == MessageCreateReqMode::Search {
+    if mode == MessageCreateReqMode::Search {
         stream_model.online = true;
     }
 
-    tokio::spawn(async move {
+    quota::add(&app.conn, user_id, 1, 0, 0)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let span = tracing::info_span!("completion", chat_id, model = %stream_model.id);
+    request_id::spawn(span, async move {
         puber
             .scope(|puber| async move {
                 let assistant = puber
-                    .new_assistant_message()
+                    .new_assistant_message(user_id)
+                    .await
+                    .raw_kind(ErrorKind::Internal)?;
+                record_generation(&app.conn, assistant.id(), &generation, variant_id)
                     .await
                     .raw_kind(ErrorKind::Internal)?;
+                let mut system_prompt = system_prompt;
+                let passages = match &query {
+                    Some(query) => kb::search(&app, user_id, chat_id, query)
+                        .await
+                        .inspect_err(|e| tracing::warn!("cannot search documents: {}", e))
+                        .unwrap_or_default(),
+                    None => vec![],
+                };
+                if !passages.is_empty() {
+                    system_prompt.push_str(&kb::prompt(&passages));
+                    save_links(&app.conn, assistant.id(), kb::links(&passages))
+                        .await
+                        .raw_kind(ErrorKind::Internal)?;
+                }
                 let mut buffer_chunk = None;
+                let mut stats = Stats::new(&stream_model.id);
 
                 let res = handle_sse(
                     app.clone(),
-                    req.chat_id,
+                    chat_id,
+                    user_id,
                     &assistant,
                     &mut buffer_chunk,
                     &stream_model,
+                    chat.model_id,
                     system_prompt,
+                    history,
                     tools,
                     &mut tool_box,
+                    budget,
+                    &mut stats,
                     puber,
                 )
                 .await;
@@ -167,23 +562,47 @@ pub async fn route(
                         .await
                         .raw_kind(ErrorKind::Internal)?;
                 }
+                let message_id = assistant.id();
                 assistant
                     .end_message(kind)
                     .await
                     .raw_kind(ErrorKind::Internal)?;
+                record_tokens(&app.conn, message_id, stats.tokens())
+                    .await
+                    .raw_kind(ErrorKind::Internal)?;
+                if let Err(err) = record_trace(&app.conn, message_id, &stats.trace()).await {
+                    tracing::warn!("cannot record the trace of message {}: {}", message_id, err);
+                }
+                if let Some(demo) = &app.demo {
+                    demo.add_cost(user_id, stats.cost());
+                }
+                if let Err(err) = reply_stats::record(&app.conn, stats.record(kind)).await {
+                    tracing::warn!("cannot record the stats of message {}: {}", message_id, err);
+                }
+                puber.raw_token(Ok(sse::Token::Meta(message_id, stats.meta(kind))));
+                if let Err(err) =
+                    push::message_completed(&app, user_id, chat_id, message_id, kind).await
+                {
+                    tracing::warn!("cannot queue the pushes of message {}: {}", message_id, err);
+                }
+                if let Err(err) =
+                    webhook::message_completed(&app, user_id, chat_id, message_id, kind).await
+                {
+                    tracing::warn!(
+                        "cannot queue the webhooks of message {}: {}",
+                        message_id,
+                        err
+                    );
+                }
 
-                // TODO: We should generate title with fix params
                 if chat.title.is_none() {
-                    if let Ok(title) =
-                        generate_title(app.clone(), req.chat_id, &user.preference, &title_gen_model)
-                            .await
-                    {
-                        let mut chat = chat.into_active_model();
-                        chat.title = ActiveValue::set(Some(title.clone()));
-                        if chat.update(&app.conn).await.is_ok() {
-                            tracing::info!("Chat {} title updated to \"{}\"", req.chat_id, &title);
-                            puber.raw_token(Ok(sse::Token::ChangeTitle(title)));
-                        }
+                    let title = Task::Title {
+                        chat_id,
+                        user_id,
+                        model_id: title_model_id,
+                    };
+                    if let Err(err) = app.jobs.push(&app.conn, title).await {
+                        tracing::warn!("cannot queue the title of chat {}: {}", chat_id, err);
                     }
                 }
 
@@ -196,86 +615,450 @@ pub async fn route(
             .await;
     });
 
-    Ok(Json(MessageCreateResp { id: msg_id }))
+    Ok(msg_id)
 }
 
+const SPEND_PAUSED: &str =
+    "Generations are paused after unusual spending, an admin has to resume them";
+
+/// Let the model tell the user instead of pretending to act
+const TOOLS_DISABLED_PROMPT: &str = "\n\nAll tools are temporarily disabled by the administrator. \
+If the user asks for an action that needs a tool, such as searching, reading or sending mail, \
+tell them it is unavailable right now instead of attempting it.";
+
+/// Sent after the partial text of an interrupted reply
+const CONTINUE_PROMPT: &str = "Your previous reply was cut off by an interruption. \
+Continue it from exactly where it stopped, without repeating any of it or mentioning the interruption.";
+
 // These characters are commonly found as leading or trailing artifacts in model-generated titles,
 // such as extra whitespace, quotes, or formatting marks. We trim them to clean up the output.
 static TRIMS: &[char] = &['\n', ' ', '\t', '`', '"', '\''];
 
+/// Reuse the model and params of the first message in a reproducible chat,
+/// so later edits to the model config do not change the output
+async fn pin_generation(
+    conn: &DbConn,
+    chat_id: i32,
+    model: &entity::ModelConfig,
+    prompt_version: String,
+    template_version: Option<i32>,
+) -> Result<entity::Generation> {
+    let pinned = Message::find()
+        .filter(message::Column::ChatId.eq(chat_id))
+        .filter(message::Column::Generation.is_not_null())
+        .order_by_asc(message::Column::Id)
+        .one(conn)
+        .await?
+        .and_then(|x| x.get_generation());
+
+    match pinned {
+        Some(pinned) => {
+            if pinned.prompt_version != prompt_version {
+                tracing::warn!(
+                    "Prompt of reproducible chat {} changed from {} to {}",
+                    chat_id,
+                    pinned.prompt_version,
+                    prompt_version
+                );
+            }
+            Ok(entity::Generation {
+                prompt_version,
+                template_version,
+                created_at: now(),
+                ..pinned
+            })
+        }
+        None => {
+            let mut parameter = model.parameter.clone();
+            parameter.seed.get_or_insert_with(|| fastrand::u32(..));
+            Ok(entity::Generation {
+                model_id: model.model_id.clone(),
+                parameter,
+                prompt_version,
+                template_version,
+                created_at: now(),
+            })
+        }
+    }
+}
+
+async fn save_links(conn: &DbConn, message_id: i32, links: Vec<EntityLink>) -> Result<()> {
+    if links.is_empty() {
+        return Ok(());
+    }
+
+    Link::insert_many(links.into_iter().map(|link| link::ActiveModel {
+        message_id: ActiveValue::Set(message_id),
+        kind: ActiveValue::Set(link.kind),
+        entity_id: ActiveValue::Set(link.id),
+        label: ActiveValue::Set(link.label),
+        ..Default::default()
+    }))
+    .exec(conn)
+    .await?;
+    Ok(())
+}
+
+fn now() -> u32 {
+    time::UtcDateTime::now().unix_timestamp() as u32
+}
+
+async fn record_generation(
+    conn: &DbConn,
+    message_id: i32,
+    generation: &entity::Generation,
+    prompt_variant_id: Option<i32>,
+) -> Result<()> {
+    message::ActiveModel {
+        id: ActiveValue::Unchanged(message_id),
+        generation: ActiveValue::Set(Some(serde_json::to_string(generation)?)),
+        prompt_variant_id: ActiveValue::Set(prompt_variant_id),
+        ..Default::default()
+    }
+    .update(conn)
+    .await?;
+    Ok(())
+}
+
+/// Kept for the activity of the user, see `user/activity`
+async fn record_tokens(conn: &DbConn, message_id: i32, tokens: usize) -> Result<()> {
+    message::ActiveModel {
+        id: ActiveValue::Unchanged(message_id),
+        tokens: ActiveValue::Set(tokens as i64),
+        ..Default::default()
+    }
+    .update(conn)
+    .await?;
+    Ok(())
+}
+
+/// Kept for `message/{id}/trace`
+async fn record_trace(conn: &DbConn, message_id: i32, trace: &entity::Trace) -> Result<()> {
+    message::ActiveModel {
+        id: ActiveValue::Unchanged(message_id),
+        trace: ActiveValue::Set(Some(serde_json::to_string(trace)?)),
+        ..Default::default()
+    }
+    .update(conn)
+    .await?;
+    Ok(())
+}
+
+/// Title generation use a cheap model with fixed params when `TITLE_MODEL` is set
+fn title_model(chat_model_id: &str) -> openrouter::Model {
+    openrouter::Model {
+        id: var("TITLE_MODEL").unwrap_or(chat_model_id.to_owned()),
+        temperature: Some(0.3),
+        repeat_penalty: None,
+        top_k: None,
+        top_p: None,
+        seed: None,
+        online: false,
+        reasoning: false,
+    }
+}
+
+/// Title the chat after its first exchange, run as a job of `jobs`
+///
+/// Done if the chat was deleted or titled since, e.g. by an earlier job
+pub async fn update_title(
+    app: &Arc<AppState>,
+    chat_id: i32,
+    user_id: i32,
+    chat_model_id: &str,
+) -> Result<()> {
+    let Some(chat) = Chat::find_by_id(chat_id).one(&app.conn).await? else {
+        return Ok(());
+    };
+    if chat.title.is_some() {
+        return Ok(());
+    }
+    let Some(user) = User::find_by_id(user_id).one(&app.conn).await? else {
+        return Ok(());
+    };
+    let model = title_model(chat_model_id);
+    let title = generate_title(app.clone(), &chat, &user, &model).await?;
+
+    let mut chat = chat.into_active_model();
+    chat.title = ActiveValue::set(Some(title.clone()));
+    chat.update(&app.conn).await?;
+    tracing::info!("Chat {} title updated to \"{}\"", chat_id, &title);
+    app.sse
+        .broadcast(chat_id, sse::Token::ChatTitle(title))
+        .await;
+    Ok(())
+}
+
 async fn generate_title(
     app: Arc<AppState>,
-    chat_id: i32,
-    preference: &entity::UserPreference,
+    chat: &chat::Model,
+    user: &entity::user::Model,
     model: &openrouter::Model,
 ) -> Result<String> {
-    let system_prompt = prompts::TitleGenStore
-        .template(preference.locale.as_deref())
+    let chat_id = chat.id;
+    let system_prompt = app
+        .prompt
+        .template(
+            &prompts::TitleGenStore,
+            Some(locale::of_user(user.preference.locale.as_deref()).code()),
+            chat.workspace_id,
+        )
         .await?
         .render(&app.prompt, chat_id, vec![], (), ())
         .await?;
 
-    let messages = get_message(chat_id, &app.conn, system_prompt).await?;
+    let messages = get_message(chat_id, user.id, &app.conn, &app.files, system_prompt).await?;
 
-    let completion = app
-        .openrouter
-        .complete(messages, model.clone().into())
-        .await?;
+    let completion = app.openrouter.complete(messages, model.clone()).await?;
 
-    let title = completion.response.trim_matches(&TRIMS[..]);
+    let title = completion.response.trim_matches(TRIMS);
 
     Ok(title.to_string())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_sse<'a>(
     app: Arc<AppState>,
     chat_id: i32,
+    user_id: i32,
     assistant: &'a AssistantMessage<'a>,
     buffer_chunk: &mut Option<BufferChunk<'a, 'a>>,
     model: &'a openrouter::Model,
+    model_id: i32,
     system_prompt: String,
+    mut history: Option<Vec<openrouter::Message>>,
     tools: Vec<openrouter::Tool>,
     tool_box: &mut ToolBox,
+    mut budget: Budget,
+    stats: &mut Stats,
     puber: &Publisher,
 ) -> Result<EndKind, Error> {
+    let ctx = ToolCtx::new(app.clone(), chat_id, user_id);
+    match compaction::compact_if_due(&app, chat_id, model).await {
+        // the prefetched history still hold the summarized messages
+        Ok(true) => history = None,
+        Ok(false) => {}
+        // the whole history is sent, the provider may still take it
+        Err(err) => tracing::warn!("cannot compact chat {}: {}", chat_id, err),
+    }
     let mut tool_calls: Vec<openrouter::MessageToolCall> = vec![];
+    let mut plan: Vec<PlanStep> = vec![];
+    let mut repairs = 0;
+    let mut screen = Screen {
+        on: app.moderation.screens_output(),
+        ..Default::default()
+    };
 
     loop {
-        for tool_call in tool_calls.drain(..) {
-            let Some((name, tool)) = tool_box.get(&tool_call.name.as_str()) else {
+        let has_tool_calls = !tool_calls.is_empty();
+        let offset = plan.len();
+        plan.extend(tool_calls.iter().map(|call| PlanStep {
+            name: call.name.clone(),
+            status: PlanStatus::Pending,
+        }));
+
+        for (idx, tool_call) in tool_calls.drain(..).enumerate() {
+            let step = offset + idx;
+            if budget.exhausted().is_some() {
+                plan[step].status = PlanStatus::Skipped;
+                assistant.plan(&plan, step);
+                stats.skip_tool(&tool_call.name);
+                continue;
+            }
+            let problems = tool_box.check(&tool_call.name, &tool_call.arguments);
+            let Some((name, tool)) = tool_box.get(tool_call.name.as_str()) else {
+                plan[step].status = PlanStatus::Skipped;
+                assistant.plan(&plan, step);
+                stats.skip_tool(&tool_call.name);
                 continue;
             };
 
-            assistant.start_tool_call(name, tool_call.arguments.clone());
-            let output = tool
-                .call(&tool_call.arguments)
+            plan[step].status = PlanStatus::Running;
+            assistant.plan(&plan, step);
+            budget.steps += 1;
+            stats.tool(name);
+            quota::add(&app.conn, user_id, 0, 0, 1)
                 .await
-                .raw_kind(ErrorKind::ToolCallFail);
-            let content =
-                serde_json::to_string(&JsonUnion::from(output)).raw_kind(ErrorKind::Internal)?;
+                .raw_kind(ErrorKind::Internal)?;
+
+            assistant.start_tool_call(name, tool_call.arguments.clone());
+            let span = tracing::info_span!("tool", name, error = tracing::field::Empty);
+            let mut answer = None;
+            let mut rounds = 0;
+            // the call is not made, the model is told what to fix instead
+            let output = match problems.is_empty() {
+                false => {
+                    repairs += 1;
+                    let invalid =
+                        tools::InvalidArgs::new(problems, repairs <= TOOL_REPAIR_MAX_ROUNDS);
+                    Err(invalid.into())
+                }
+                true => loop {
+                    ctx.set_answer(answer.take());
+                    let output = select! {
+                        biased;
+                        _ = puber.on_halt() => {
+                            plan[step].status = PlanStatus::Failed;
+                            assistant.plan(&plan, step);
+                            return Ok(EndKind::Halt);
+                        }
+                        output = tool.call(&tool_call.arguments, &ctx).instrument(span.clone()) => output,
+                    };
+                    // the tool asks the user, then runs again with the answer
+                    let input = match output.map_err(|err| err.downcast::<tools::NeedsInput>()) {
+                        Ok(output) => break Ok(output),
+                        Err(Ok(input)) if rounds < TOOL_INPUT_MAX_ROUNDS => input,
+                        Err(Ok(input)) => break Err(input.into()),
+                        Err(Err(err)) => break Err(err),
+                    };
+                    rounds += 1;
+
+                    let rx = app.inputs.wait(chat_id, &tool_call.id);
+                    assistant
+                        .ask_input(
+                            tool_call.id.clone(),
+                            name,
+                            input.question.clone(),
+                            input.schema.to_string(),
+                        )
+                        .await;
+                    let res = select! {
+                        biased;
+                        _ = puber.on_halt() => None,
+                        res = timeout(Duration::from_secs(TOOL_INPUT_TIMEOUT), rx) => Some(res),
+                    };
+                    app.inputs.cancel(chat_id, &tool_call.id);
+                    assistant.end_input().await;
+                    match res {
+                        None => {
+                            plan[step].status = PlanStatus::Failed;
+                            assistant.plan(&plan, step);
+                            return Ok(EndKind::Halt);
+                        }
+                        Some(Ok(Ok(value))) => answer = Some(value),
+                        // timed out, the model can still ask in its reply
+                        Some(_) => break Err(input.into()),
+                    }
+                },
+            };
+            if let Err(err) = &output {
+                span.record("error", tracing::field::display(err));
+            }
+            let invalid = output
+                .as_ref()
+                .err()
+                .and_then(|err| err.downcast_ref::<tools::InvalidArgs>())
+                .map(serde_json::to_string)
+                .transpose()
+                .raw_kind(ErrorKind::Internal)?;
+            let output = output.raw_kind(ErrorKind::ToolCallFail);
+
+            plan[step].status = match output {
+                Ok(_) => PlanStatus::Done,
+                Err(_) => PlanStatus::Failed,
+            };
+            assistant.plan(&plan, step);
+            stats.end_tool(output.is_ok());
+            audit::tool_call(&ctx, name, output.is_ok()).await;
+            let content = match invalid {
+                Some(content) => content,
+                None => {
+                    serde_json::to_string(&JsonUnion::from(output)).raw_kind(ErrorKind::Internal)?
+                }
+            };
             assistant
                 .end_tool_call(name, tool_call.arguments, content, tool_call.id)
                 .await
                 .raw_kind(ErrorKind::Internal)?;
+            save_links(&app.conn, assistant.id(), ctx.take_links())
+                .await
+                .raw_kind(ErrorKind::Internal)?;
+            assistant
+                .attach(chat_id, ctx.take_files())
+                .await
+                .raw_kind(ErrorKind::Internal)?;
+        }
+
+        if has_tool_calls {
+            budget.depth += 1;
+            assistant.progress(
+                budget.steps,
+                budget.max_steps,
+                budget.cost,
+                budget.elapsed().as_millis() as u64,
+            );
         }
 
-        let messages = get_message(chat_id, &app.conn, system_prompt.clone())
+        let exhausted = budget.exhausted();
+
+        // only the first round can use the prefetched history
+        let mut messages = match history.take() {
+            Some(history) => {
+                let mut messages = vec![openrouter::Message::System(system_prompt.clone())];
+                messages.extend(history);
+                messages
+            }
+            None => get_message(
+                chat_id,
+                user_id,
+                &app.conn,
+                &app.files,
+                system_prompt.clone(),
+            )
             .await
-            .raw_kind(ErrorKind::Internal)?;
+            .raw_kind(ErrorKind::Internal)?,
+        };
+        let tools = match exhausted {
+            Some(reason) => {
+                tracing::info!("chat {} ran out of budget: {}", chat_id, reason);
+                stats.stop(reason);
+                messages.push(openrouter::Message::User(final_report_prompt(reason)));
+                vec![]
+            }
+            None => tools.clone(),
+        };
+        if budget.spend_guard && app.spend.paused().is_some() {
+            return Err(Error {
+                error: ErrorKind::Paused,
+                reason: SPEND_PAUSED.to_owned(),
+            });
+        }
+        if budget.spend_guard
+            && let Some(reason) = app
+                .spend_caps
+                .exceeded(&app.conn, user_id, model_id)
+                .await
+                .raw_kind(ErrorKind::Internal)?
+        {
+            return Err(Error {
+                error: ErrorKind::SpendCapped,
+                reason,
+            });
+        }
+        stats.start_completion();
         let mut completion = app
             .openrouter
-            .stream(messages.clone(), model, tools.clone())
+            .stream(messages, model, tools)
             .await
             .raw_kind(ErrorKind::ApiFail)?;
+        let mut timeout = false;
+        let mut prompt_tokens = None;
 
         loop {
             select! {
                 biased;
                 _ = puber.on_halt() => {
                     completion.close();
+                    stats.end_completion(completion.model(), completion.finish_reason());
                     return Ok(EndKind::Halt);
                 }
 
+                _ = budget.timeout(), if exhausted.is_none() => {
+                    completion.close();
+                    timeout = true;
+                    break;
+                }
+
                 token = completion.next() => {
                     match token {
                         Some(Ok(token)) => match token {
@@ -283,6 +1066,11 @@ async fn handle_sse<'a>(
                                 if token.is_empty() {
                                     continue;
                                 }
+                                stats.token();
+                                // the text before the reasoning is whole
+                                screen
+                                    .check(&app, user_id, chat_id, buffer_chunk.as_ref(), true)
+                                    .await?;
 
                                 match buffer_chunk.take_if(|bc| bc.kind() != ChunkKind::Reasoning) {
                                     Some(bc) => {
@@ -310,6 +1098,7 @@ async fn handle_sse<'a>(
                                 if token.is_empty() {
                                     continue;
                                 }
+                                stats.token();
 
                                 match buffer_chunk.take_if(|bc|bc.kind() != ChunkKind::Text) {
                                     Some(bc) => {
@@ -330,6 +1119,16 @@ async fn handle_sse<'a>(
                                     .send_token(&token)
                                     .await
                                     .raw_kind(ErrorKind::Internal)?;
+                                screen.tokens += 1;
+                                if screen.tokens >= MODERATION_OUTPUT_TOKENS {
+                                    screen
+                                        .check(&app, user_id, chat_id, buffer_chunk.as_ref(), false)
+                                        .await?;
+                                }
+                            }
+                            // nothing runs before the call is complete, halting now skips it
+                            StreamCompletionResp::ToolCallDelta { name, args } => {
+                                assistant.tool_call_delta(name, args);
                             }
                             StreamCompletionResp::ToolCall { name, args, id } => {
                                 tool_calls.push(openrouter::MessageToolCall {
@@ -338,6 +1137,32 @@ async fn handle_sse<'a>(
                                     arguments: args,
                                 })
                             }
+                            StreamCompletionResp::Usage { price, token, prompt_token, completion_token, .. } => {
+                                // only openrouter report the cost
+                                let price = price
+                                    .or_else(|| {
+                                        app.pricing.cost(
+                                            &model.id,
+                                            prompt_token?,
+                                            completion_token?,
+                                        )
+                                    })
+                                    .unwrap_or(0.0);
+                                budget.cost += price;
+                                stats.usage(completion_token, price);
+                                app.spend
+                                    .add(&app, price)
+                                    .await
+                                    .raw_kind(ErrorKind::Internal)?;
+                                app.spend_caps
+                                    .add(&app, user_id, model_id, price)
+                                    .await
+                                    .raw_kind(ErrorKind::Internal)?;
+                                quota::add(&app.conn, user_id, 0, token as i64, 0)
+                                    .await
+                                    .raw_kind(ErrorKind::Internal)?;
+                                prompt_tokens = prompt_token.or(prompt_tokens);
+                            }
                             _ => {}
                         },
                         Some(Err(err)) => {
@@ -351,12 +1176,35 @@ async fn handle_sse<'a>(
                 }
             };
         }
+        stats.end_completion(completion.model(), completion.finish_reason());
+        let sample = context_stat::Sample {
+            prompt_tokens,
+            context_length: app.pricing.context_length(&model.id),
+            truncated: completion.truncated(),
+        };
+        record_context(&app, chat_id, sample, puber).await;
+        let end_kind = match completion.truncated() {
+            true => EndKind::Truncated,
+            false => EndKind::Complete,
+        };
+        screen
+            .check(&app, user_id, chat_id, buffer_chunk.as_ref(), true)
+            .await?;
         if let Some(bc) = buffer_chunk.take() {
-            bc.end_buffer_chunk(EndKind::Complete)
+            bc.end_buffer_chunk(end_kind)
                 .await
                 .raw_kind(ErrorKind::Internal)?;
         }
-        if tool_calls.is_empty() {
+        if completion.truncated() {
+            return Ok(EndKind::Truncated);
+        }
+        if timeout {
+            // the next round writes the final report
+            tool_calls.clear();
+            continue;
+        }
+        // no tool to answer the calls, e.g. tools are disabled
+        if tool_calls.is_empty() || exhausted.is_some() || tool_box.tools.is_empty() {
             break;
         }
     }
@@ -364,28 +1212,176 @@ async fn handle_sse<'a>(
     Ok(EndKind::Complete)
 }
 
+/// Moderation of the text chunks of a reply, see `moderation`
+#[derive(Default)]
+struct Screen {
+    on: bool,
+    /// Tokens since the rules last ran
+    tokens: usize,
+    /// A reply is flagged once in the audit log
+    flagged: bool,
+}
+
+impl Screen {
+    /// Check the chunk if it is text, by the provider too if `whole`; a block
+    /// replaces the text and fails the reply
+    async fn check(
+        &mut self,
+        app: &AppState,
+        user_id: i32,
+        chat_id: i32,
+        bc: Option<&BufferChunk<'_, '_>>,
+        whole: bool,
+    ) -> Result<(), Error> {
+        let Some(bc) = bc.filter(|bc| self.on && bc.kind() == ChunkKind::Text) else {
+            return Ok(());
+        };
+        self.tokens = 0;
+        let text = bc.text().await;
+        let verdict = match whole {
+            true => app.moderation.check(&text).await,
+            false => app.moderation.check_rules(&text),
+        };
+        match &verdict {
+            Verdict::Pass => Ok(()),
+            Verdict::Flag(_) if self.flagged => Ok(()),
+            Verdict::Flag(_) => {
+                self.flagged = true;
+                moderation::record(&app.conn, user_id, chat_id, Stage::Reply, &verdict).await;
+                Ok(())
+            }
+            Verdict::Block(_) => {
+                moderation::record(&app.conn, user_id, chat_id, Stage::Reply, &verdict).await;
+                bc.redact(MODERATED_REPLY).await;
+                Err(Error {
+                    error: ErrorKind::Moderated,
+                    reason: "The reply was blocked by the moderation policy".to_owned(),
+                })
+            }
+        }
+    }
+}
+
+/// What is kept of a blocked reply
+const MODERATED_REPLY: &str = "[removed by moderation]";
+
+/// Log the context size of a completion, warn the user when the chat keeps
+/// hitting the context of the model
+async fn record_context(
+    app: &AppState,
+    chat_id: i32,
+    sample: context_stat::Sample,
+    puber: &Publisher,
+) {
+    tracing::info!(
+        chat_id,
+        prompt_tokens = sample.prompt_tokens,
+        context_length = sample.context_length,
+        truncated = sample.truncated,
+        "completion context"
+    );
+    match context_stat::record(&app.conn, chat_id, sample).await {
+        Ok(false) => {}
+        Ok(true) => {
+            tracing::warn!(
+                chat_id,
+                prompt_tokens = sample.prompt_tokens,
+                context_length = sample.context_length,
+                "chat keeps hitting the context ceiling"
+            );
+            if let (Some(prompt_tokens), Some(context_length)) =
+                (sample.prompt_tokens, sample.context_length)
+            {
+                puber.raw_token(Ok(sse::Token::ContextWarning(
+                    prompt_tokens,
+                    context_length,
+                )));
+            }
+        }
+        Err(err) => tracing::warn!("cannot record context of chat {}: {}", chat_id, err),
+    }
+}
+
 async fn get_message(
     chat_id: i32,
+    viewer_id: i32,
     conn: &DbConn,
+    files: &Files,
     system_prompt: String,
 ) -> Result<Vec<openrouter::Message>> {
-    let res = Message::find()
-        .select()
-        .filter(Expr::col(message::Column::ChatId).eq(chat_id))
+    let mut messages = vec![openrouter::Message::System(system_prompt)];
+    messages.extend(get_history(chat_id, viewer_id, conn, files).await?.0);
+    Ok(messages)
+}
+
+/// Messages of the active branch as provider messages, without the system
+/// prompt, the summarized ones by their summary
+///
+/// Only those `viewer_id` can see, the private messages of other members
+/// stay out of the context of their replies. Also return the id of the last
+/// message loaded
+pub(super) async fn get_history(
+    chat_id: i32,
+    viewer_id: i32,
+    conn: &DbConn,
+    files: &Files,
+) -> Result<(Vec<openrouter::Message>, Option<i32>)> {
+    let mut ids = branch::active(conn, chat_id).await?;
+    let chat = Chat::find_by_id(chat_id)
+        .one(conn)
+        .await?
+        .context("Cannot find the chat")?;
+    let summary = compaction::summary(&chat, &ids);
+    if let Some((_, until)) = summary {
+        ids.retain(|x| *x > until);
+    }
+    let res = utils::message::visible_messages(chat_id, viewer_id)
+        .filter(message::Column::Id.is_in(ids))
         .order_by_asc(message::Column::Id)
         .find_with_related(Chunk)
         .all(conn)
         .await?;
+    let ids: Vec<i32> = res.iter().map(|(x, _)| x.id).collect();
 
-    let mut messages = vec![openrouter::Message::System(system_prompt)];
+    let mut links: HashMap<i32, Vec<link::Model>> = HashMap::new();
+    let mut attached = crate::files::load(conn, files, ids.clone()).await?;
+    for link in Link::find()
+        .filter(link::Column::MessageId.is_in(ids))
+        .order_by_asc(link::Column::Id)
+        .all(conn)
+        .await?
+    {
+        links.entry(link.message_id).or_default().push(link);
+    }
+
+    let last_message_id = res.last().map(|(x, _)| x.id);
+    let mut messages = vec![];
+    if let Some((summary, _)) = summary {
+        messages.push(openrouter::Message::System(compaction::summary_prompt(
+            summary,
+        )));
+    }
     for (message, chunks) in res {
         match message.kind {
             MessageKind::Hidden => continue,
-            MessageKind::User => messages.extend(
+            MessageKind::Marker => messages.extend(
                 chunks
                     .into_iter()
-                    .map(|chunk| openrouter::Message::User(chunk.content)),
+                    .map(|chunk| openrouter::Message::System(chunk.content)),
             ),
+            MessageKind::User => match attached.remove(&message.id) {
+                Some(files) => messages.push(openrouter::Message::MultipartUser(
+                    openrouter::MessageMultipartUser {
+                        text: chunks.into_iter().map(|x| x.content).collect(),
+                        files,
+                    },
+                )),
+                None => messages.extend(
+                    chunks
+                        .into_iter()
+                        .map(|chunk| openrouter::Message::User(chunk.content)),
+                ),
+            },
             MessageKind::Assistant => {
                 for chunk in chunks {
                     match chunk.kind {
@@ -410,9 +1406,32 @@ async fn get_message(
                         }
                     }
                 }
+                if let Some(links) = links.remove(&message.id) {
+                    messages.push(openrouter::Message::System(links_prompt(&links)));
+                }
             }
         }
     }
 
-    Ok(messages)
+    Ok((messages, last_message_id))
+}
+
+/// Let the model resolve "that email" by id rather than searching again
+fn links_prompt(links: &[link::Model]) -> String {
+    let mut prompt =
+        "Entities referenced in the previous reply, use their ids for follow-up actions:"
+            .to_owned();
+    for link in links {
+        let kind = match link.kind {
+            LinkKind::Mail => "mail_id",
+            LinkKind::MailThread => "thread_id",
+            LinkKind::Place => "place_id",
+            LinkKind::Document => "document_id",
+        };
+        prompt.push_str(&format!(
+            "\n- {} `{}`: {}",
+            kind, link.entity_id, link.label
+        ));
+    }
+    prompt
 }
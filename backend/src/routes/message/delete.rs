@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{chat, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, TransactionTrait, sea_query::Expr};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId, trash, undo, utils::branch};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct MessageDeleteReq {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct MessageDeleteResp {
+    pub deleted: bool,
+    /// `POST /api/undo/{token}` within `UNDO_WINDOW` restore the message
+    pub undo_token: Option<String>,
+}
+
+/// Move a message to the trash, later messages are kept on its branch
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<MessageDeleteReq>,
+) -> JsonResult<MessageDeleteResp> {
+    let Some(snapshot) = undo::snapshot_message(&app.conn, user_id, req.id)
+        .await
+        .kind(ErrorKind::Internal)?
+    else {
+        return Ok(Json(MessageDeleteResp {
+            deleted: false,
+            undo_token: None,
+        }));
+    };
+    let chat_id = snapshot.chat_id();
+
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+    if let Some(message) = Message::find_by_id(req.id)
+        .one(&txn)
+        .await
+        .kind(ErrorKind::Internal)?
+    {
+        branch::detach(&txn, &message)
+            .await
+            .kind(ErrorKind::Internal)?;
+        // the summary would still tell about it
+        Chat::update_many()
+            .col_expr(chat::Column::Summary, Expr::value(Option::<String>::None))
+            .col_expr(chat::Column::SummaryUntil, Expr::value(Option::<i32>::None))
+            .filter(chat::Column::Id.eq(message.chat_id))
+            .filter(chat::Column::SummaryUntil.gte(message.id))
+            .exec(&txn)
+            .await
+            .kind(ErrorKind::Internal)?;
+    }
+    let result = Message::delete_by_id(req.id)
+        .exec(&txn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let deleted = result.rows_affected > 0;
+    let trash = match deleted {
+        true => trash::put(&txn, user_id, vec![snapshot])
+            .await
+            .kind(ErrorKind::Internal)?,
+        false => vec![],
+    };
+    txn.commit().await.kind(ErrorKind::Internal)?;
+    // the prefetched history may still hold it
+    app.prefetch.forget(chat_id);
+
+    let undo_token = app.undo.stage(user_id, trash).kind(ErrorKind::Internal)?;
+
+    Ok(Json(MessageDeleteResp {
+        deleted,
+        undo_token,
+    }))
+}
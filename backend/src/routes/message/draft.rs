@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use super::create::get_history;
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+    utils::member,
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct MessageDraftReq {
+    pub chat_id: i32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct MessageDraftResp {
+    /// false while a completion of the chat is in-flight
+    pub prefetched: bool,
+}
+
+/// Sent when the user start typing, build the history ahead of the message
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Json(req): Json<MessageDraftReq>,
+) -> JsonResult<MessageDraftResp> {
+    member::find(&app.conn, req.chat_id, user_id, workspace_id).await?;
+
+    // the last message is still being written
+    if app.sse.is_publishing(req.chat_id).await {
+        return Ok(Json(MessageDraftResp { prefetched: false }));
+    }
+
+    let (history, last_message_id) = get_history(req.chat_id, user_id, &app.conn, &app.files)
+        .await
+        .kind(ErrorKind::Internal)?;
+    // a completion started while loading
+    if app.sse.is_publishing(req.chat_id).await {
+        return Ok(Json(MessageDraftResp { prefetched: false }));
+    }
+    app.prefetch
+        .put(req.chat_id, user_id, last_message_id, history);
+
+    Ok(Json(MessageDraftResp { prefetched: true }))
+}
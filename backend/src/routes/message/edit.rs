@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+};
+use entity::{ChunkKind, chunk, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{ActiveValue::Set, QueryOrder, TransactionTrait, prelude::*};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use super::{
+    create::{MessageCreateReqMode, Turn, start},
+    write::user_message,
+};
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{ApiKeyUser, UserId, WorkspaceId},
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct MessageEditReq {
+    pub text: String,
+    /// Answer the edited message again on a new branch, like
+    /// `/api/message/write`; default to false, which fix the text in place and
+    /// keep the replies
+    pub rerun: Option<bool>,
+    /// Only used with `rerun`, default to normal
+    pub mode: Option<MessageCreateReqMode>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct MessageEditResp {
+    /// The edited message, a new sibling of it with `rerun`
+    pub id: i32,
+}
+
+/// Edit a sent user message, e.g. to fix a typo in a long prompt
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    api_key: Option<Extension<ApiKeyUser>>,
+    Path(id): Path<i32>,
+    Json(req): Json<MessageEditReq>,
+) -> JsonResult<MessageEditResp> {
+    let (message, chat) = user_message(&app.conn, user_id, workspace_id, id).await?;
+
+    if req.rerun.unwrap_or(false) {
+        let turn = Turn::Edit {
+            parent_id: message.parent_id,
+            text: req.text,
+            files: crate::files::attached_ids(&app.conn, message.id)
+                .await
+                .kind(ErrorKind::Internal)?,
+        };
+        let mode = req.mode.unwrap_or(MessageCreateReqMode::Normal);
+        let id = start(app, user_id, api_key, chat, mode, turn)
+            .await?
+            .ok_or("No user message was sent")
+            .kind(ErrorKind::Internal)?;
+        return Ok(Json(MessageEditResp { id }));
+    }
+
+    // the text of a user message is a single chunk, imports may have split it
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+    let mut texts = Chunk::find()
+        .filter(chunk::Column::MessageId.eq(id))
+        .filter(chunk::Column::Kind.eq(ChunkKind::Text))
+        .order_by_asc(chunk::Column::Id)
+        .all(&txn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .into_iter();
+    match texts.next() {
+        Some(first) => {
+            Chunk::update(chunk::ActiveModel {
+                id: Set(first.id),
+                content: Set(req.text),
+                ..Default::default()
+            })
+            .exec(&txn)
+            .await
+            .kind(ErrorKind::Internal)?;
+        }
+        None => {
+            Chunk::insert(chunk::ActiveModel {
+                content: Set(req.text),
+                kind: Set(ChunkKind::Text),
+                message_id: Set(id),
+                ..Default::default()
+            })
+            .exec(&txn)
+            .await
+            .kind(ErrorKind::Internal)?;
+        }
+    }
+    Chunk::delete_many()
+        .filter(chunk::Column::Id.is_in(texts.map(|x| x.id)))
+        .exec(&txn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    txn.commit().await.kind(ErrorKind::Internal)?;
+
+    // the history cached for the next message has the old text
+    app.prefetch.forget(chat.id);
+    Ok(Json(MessageEditResp { id }))
+}
@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+};
+use entity::{FeedbackRating, MessageKind, feedback, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{ActiveValue::Set, EntityTrait, sea_query::OnConflict};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use super::create::joined_chat;
+use crate::{
+    AppState,
+    config::FEEDBACK_COMMENT_MAX_CHARS,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct MessageFeedbackReq {
+    /// None take the rating back
+    pub rating: Option<FeedbackRating>,
+    /// What was wrong or right with the reply
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct MessageFeedbackResp {
+    pub rating: Option<FeedbackRating>,
+}
+
+/// Rate an assistant reply, rating it again replace the previous rating
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Path(id): Path<i32>,
+    Json(req): Json<MessageFeedbackReq>,
+) -> JsonResult<MessageFeedbackResp> {
+    let message = Message::find_by_id(id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .filter(|x| x.kind == MessageKind::Assistant)
+        .ok_or("Cannot find the reply")
+        .kind(ErrorKind::ResourceNotFound)?;
+    joined_chat(&app.conn, user_id, workspace_id, message.chat_id).await?;
+
+    let Some(rating) = req.rating else {
+        Feedback::delete_by_id(id)
+            .exec(&app.conn)
+            .await
+            .kind(ErrorKind::Internal)?;
+        return Ok(Json(MessageFeedbackResp { rating: None }));
+    };
+    let comment = req
+        .comment
+        .map(|x| {
+            x.trim()
+                .chars()
+                .take(FEEDBACK_COMMENT_MAX_CHARS)
+                .collect::<String>()
+        })
+        .filter(|x| !x.is_empty());
+    Feedback::insert(feedback::ActiveModel {
+        message_id: Set(id),
+        user_id: Set(user_id),
+        rating: Set(rating),
+        comment: Set(comment),
+        created_at: Set(time::UtcDateTime::now().unix_timestamp()),
+    })
+    .on_conflict(
+        OnConflict::column(feedback::Column::MessageId)
+            .update_columns([
+                feedback::Column::UserId,
+                feedback::Column::Rating,
+                feedback::Column::Comment,
+                feedback::Column::CreatedAt,
+            ])
+            .to_owned(),
+    )
+    .exec(&app.conn)
+    .await
+    .kind(ErrorKind::Internal)?;
+
+    Ok(Json(MessageFeedbackResp {
+        rating: Some(rating),
+    }))
+}
@@ -1,16 +1,106 @@
-mod create;
-mod paginate;
+mod audio;
+pub mod budget;
+pub mod create;
+mod delete;
+mod draft;
+mod edit;
+mod feedback;
+pub mod paginate;
+mod regenerate;
+mod search;
+mod stats;
+mod summarize;
+mod trace;
+mod visibility;
 mod write;
 
 use std::sync::Arc;
 
-use axum::{Router, routing::post};
+use axum::{
+    Router,
+    routing::{get, patch, post},
+};
 
-use crate::AppState;
+use crate::{AppState, utils::openapi::Builder};
 
 pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/create", post(create::route))
+        .route("/delete", post(delete::route))
+        .route("/draft", post(draft::route))
         .route("/write", post(write::route))
         .route("/paginate", post(paginate::route))
+        .route("/regenerate", post(regenerate::route))
+        .route("/search", get(search::route))
+        .route("/summarize", post(summarize::route))
+        .route("/visibility", post(visibility::route))
+        .route("/{id}", patch(edit::route))
+        .route("/{id}/audio", get(audio::route))
+        .route("/{id}/feedback", post(feedback::route))
+        .route("/{id}/trace", get(trace::route))
+}
+
+/// Describe the routes above, served by `routes::openapi`
+pub fn spec(api: &mut Builder) {
+    api.op(
+        "POST",
+        "/message/create",
+        "Send a message and stream the reply over `chat/sse`",
+    )
+    .body::<create::MessageCreateReq>()
+    .json::<create::MessageCreateResp>();
+    api.op("POST", "/message/delete", "Move a message to the trash")
+        .body::<delete::MessageDeleteReq>()
+        .json::<delete::MessageDeleteResp>();
+    api.op(
+        "POST",
+        "/message/draft",
+        "Build the history ahead of the message being typed",
+    )
+    .body::<draft::MessageDraftReq>()
+    .json::<draft::MessageDraftResp>();
+    api.op(
+        "POST",
+        "/message/write",
+        "Edit a user message on a new branch",
+    )
+    .body::<write::MessageWriteReq>()
+    .json::<write::MessageWriteResp>();
+    api.op("POST", "/message/paginate", "List the messages of a chat")
+        .body::<paginate::MessagePaginateReq>()
+        .json::<paginate::MessagePaginateResp>();
+    api.op("POST", "/message/regenerate", "Reply again on a new branch")
+        .body::<regenerate::MessageRegenerateReq>()
+        .json::<regenerate::MessageRegenerateResp>();
+    api.op("GET", "/message/search", "Search the messages of the user")
+        .query::<search::MessageSearchReq>()
+        .json::<search::MessageSearchResp>();
+    api.op("POST", "/message/summarize", "Ask for a summary of a file")
+        .body::<summarize::MessageSummarizeReq>()
+        .json::<summarize::MessageSummarizeResp>();
+    api.op(
+        "POST",
+        "/message/visibility",
+        "Hide a message from other members",
+    )
+    .body::<visibility::MessageVisibilityReq>()
+    .json::<visibility::MessageVisibilityResp>();
+    api.op(
+        "PATCH",
+        "/message/{id}",
+        "Edit a sent user message in place",
+    )
+    .body::<edit::MessageEditReq>()
+    .json::<edit::MessageEditResp>();
+    api.op("GET", "/message/{id}/audio", "Speech of an assistant reply")
+        .file("audio/mpeg");
+    api.op("POST", "/message/{id}/feedback", "Rate an assistant reply")
+        .body::<feedback::MessageFeedbackReq>()
+        .json::<feedback::MessageFeedbackResp>();
+    api.op(
+        "GET",
+        "/message/{id}/trace",
+        "Rounds of the tool loop of an assistant reply",
+    )
+    .json::<trace::MessageTraceResp>();
 }
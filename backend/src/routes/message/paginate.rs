@@ -1,15 +1,25 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use axum::{Extension, Json, extract::State};
-use entity::{ChunkKind, MessageKind, message, prelude::*};
+use entity::{
+    ChunkKind, FeedbackRating, LinkKind, MessageKind, attachment, feedback, link, message,
+    prelude::*,
+};
 use migration::ExprTrait;
-use sea_orm::{QueryOrder, QuerySelect, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{LoaderTrait, QueryOrder, QuerySelect, prelude::*};
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-use crate::{AppState, config::MAX_PAGINATE_LIMIT, errors::*, middlewares::auth::UserId};
+use crate::{
+    AppState,
+    config::MAX_PAGINATE_LIMIT,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+    utils::{branch, member, message::visible_messages},
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 #[serde(tag = "t", content = "c", rename_all = "snake_case")]
 pub enum MessagePaginateReq {
@@ -17,18 +27,20 @@ pub enum MessagePaginateReq {
     Range(MessagePaginateReqRange),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct MessagePaginateReqLimit {
     pub chat_id: i32,
 
-    /// default to i32::MAX
+    /// Cursor, exclusive. Default to the latest message for `Lt`, the
+    /// first for `Gt`
     pub id: Option<i32>,
     pub order: MessagePaginateReqOrder,
+    /// default to and capped at 100
     pub limit: Option<u32>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 /// Does not include upper & lower
 /// lower [... return items ... ] upper
@@ -38,7 +50,7 @@ pub struct MessagePaginateReqRange {
     pub lower: i32,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 #[serde(rename_all = "snake_case")]
 pub enum MessagePaginateReqOrder {
@@ -48,36 +60,95 @@ pub enum MessagePaginateReqOrder {
     Lt,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct MessagePaginateResp {
     pub list: Vec<MessagePaginateRespList>,
+    /// `id` of the next page in the same order, None once the history is
+    /// exhausted or for ranges
+    pub next: Option<i32>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct MessagePaginateRespList {
     pub id: i32,
     pub role: MessagePaginateRespRole,
     pub chunks: Vec<MessagePaginateRespChunk>,
+    /// the upstream timed out and the reply is partial
+    pub truncated: bool,
+    /// the server stopped while the reply streamed, see `chat/{id}/continue`
+    pub interrupted: bool,
+    /// only visible to its author
+    pub private: bool,
+    /// member who sent the message or asked for the reply, None for the owner
+    /// in older messages, see `chat/{id}/member`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author_id: Option<i32>,
+    /// only on assistant messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generation: Option<entity::Generation>,
+    /// entities referenced by tool results
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<MessagePaginateRespLink>,
+    /// sent along a user message, download with `/api/file/{id}`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<MessagePaginateRespFile>,
+    /// rating of the user, only on assistant messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feedback: Option<FeedbackRating>,
+    /// previous message of its branch, None for the first message
+    pub parent_id: Option<i32>,
+    /// ids of the edits or regenerations of this message, itself included,
+    /// only if there are some
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub siblings: Vec<i32>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct MessagePaginateRespLink {
+    pub kind: MessagePaginateRespLinkKind,
+    pub id: String,
+    pub label: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct MessagePaginateRespFile {
+    pub id: i32,
+    pub name: String,
+    pub content_type: String,
+    pub size: i64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum MessagePaginateRespLinkKind {
+    Mail,
+    MailThread,
+    Place,
+    Document,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct MessagePaginateRespChunk {
     pub id: i32,
     pub kind: MessagePaginateRespChunkKind,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 #[serde(rename_all = "snake_case")]
 pub enum MessagePaginateRespRole {
     User,
     Assistant,
+    Marker,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 #[serde(tag = "t", content = "c", rename_all = "snake_case")]
 pub enum MessagePaginateRespChunkKind {
@@ -85,19 +156,19 @@ pub enum MessagePaginateRespChunkKind {
     Reasoning(MessagePaginateRespChunkKindReasoning),
     ToolCall(MessagePaginateRespChunkKindToolCall),
 }
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct MessagePaginateRespChunkKindText {
     pub context: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct MessagePaginateRespChunkKindReasoning {
     pub context: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct MessagePaginateRespChunkKindToolCall {
     pub name: String,
@@ -108,24 +179,24 @@ pub struct MessagePaginateRespChunkKindToolCall {
 pub async fn route(
     State(app): State<Arc<AppState>>,
     Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
     Json(req): Json<MessagePaginateReq>,
 ) -> JsonResult<MessagePaginateResp> {
-    let q = match req {
+    let (q, limit) = match req {
         MessagePaginateReq::Limit(limit) => {
-            let res = Chat::find_by_id(limit.chat_id)
-                .one(&app.conn)
+            member::find(&app.conn, limit.chat_id, user_id, workspace_id).await?;
+
+            let size = limit
+                .limit
+                .unwrap_or(MAX_PAGINATE_LIMIT)
+                .min(MAX_PAGINATE_LIMIT);
+            let ids = branch::active(&app.conn, limit.chat_id)
                 .await
                 .kind(ErrorKind::Internal)?;
-            if res.is_none_or(|x| x.owner_id != user_id) {
-                return Err(Json(Error {
-                    error: ErrorKind::ResourceNotFound,
-                    reason: "".to_owned(),
-                }));
-            }
-
-            let q = Message::find()
-                .filter(message::Column::ChatId.eq(limit.chat_id))
-                .limit(limit.limit.unwrap_or(MAX_PAGINATE_LIMIT) as u64);
+            let q = visible_messages(limit.chat_id, user_id)
+                .filter(message::Column::Id.is_in(ids))
+                .limit(size as u64);
+
             let q = match (limit.order, limit.id) {
                 (MessagePaginateReqOrder::Gt, None) => q.order_by_asc(message::Column::Id),
                 (MessagePaginateReqOrder::Gt, Some(id)) => q
@@ -136,41 +207,121 @@ pub async fn route(
                     .filter(message::Column::Id.lt(id))
                     .order_by_desc(message::Column::Id),
             };
-            q
+            (q, Some(size))
         }
         MessagePaginateReq::Range(range) => {
-            let res = Chat::find_by_id(range.chat_id)
-                .one(&app.conn)
+            member::find(&app.conn, range.chat_id, user_id, workspace_id).await?;
+
+            let ids = branch::active(&app.conn, range.chat_id)
                 .await
                 .kind(ErrorKind::Internal)?;
-            if res.is_none_or(|x| x.owner_id != user_id) {
-                return Err(Json(Error {
-                    error: ErrorKind::ResourceNotFound,
-                    reason: "".to_owned(),
-                }));
-            }
-
-            let q = Message::find()
-                .filter(message::Column::ChatId.eq(range.chat_id))
+            let q = visible_messages(range.chat_id, user_id)
+                .filter(message::Column::Id.is_in(ids))
                 .limit(MAX_PAGINATE_LIMIT as u64)
                 .filter(message::Column::Id.gt(range.lower).lt(range.upper));
-            q
+            (q, None)
         }
     };
 
-    let res = q
-        .find_with_related(Chunk)
+    // hidden messages would leave holes in a page and end the scroll early
+    let messages = q
+        .filter(message::Column::Kind.ne(MessageKind::Hidden))
         .all(&app.conn)
         .await
         .kind(ErrorKind::Internal)?;
-    let list = res
+    let next = match limit {
+        Some(limit) if messages.len() as u32 >= limit => messages.last().map(|x| x.id),
+        _ => None,
+    };
+    let list = load_list(&app.conn, messages).await?;
+
+    Ok(Json(MessagePaginateResp { list, next }))
+}
+
+/// Chunks, links, files, ratings and siblings of `messages`, hidden ones are dropped
+pub async fn load_list(
+    conn: &DatabaseConnection,
+    messages: Vec<message::Model>,
+) -> Result<Vec<MessagePaginateRespList>, Json<Error>> {
+    let mut siblings = branch::siblings(conn, &messages)
+        .await
+        .kind(ErrorKind::Internal)?;
+    // loaded apart, a limit on a join counts chunks rather than messages
+    let chunks = messages
+        .load_many(Chunk, conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let res: Vec<_> = messages.into_iter().zip(chunks).collect();
+
+    let mut links: HashMap<i32, Vec<MessagePaginateRespLink>> = HashMap::new();
+    for link in Link::find()
+        .filter(link::Column::MessageId.is_in(res.iter().map(|(message, _)| message.id)))
+        .order_by_asc(link::Column::Id)
+        .all(conn)
+        .await
+        .kind(ErrorKind::Internal)?
+    {
+        links
+            .entry(link.message_id)
+            .or_default()
+            .push(MessagePaginateRespLink {
+                kind: match link.kind {
+                    LinkKind::Mail => MessagePaginateRespLinkKind::Mail,
+                    LinkKind::MailThread => MessagePaginateRespLinkKind::MailThread,
+                    LinkKind::Place => MessagePaginateRespLinkKind::Place,
+                    LinkKind::Document => MessagePaginateRespLinkKind::Document,
+                },
+                id: link.entity_id,
+                label: link.label,
+            });
+    }
+
+    let mut files: HashMap<i32, Vec<MessagePaginateRespFile>> = HashMap::new();
+    for (attachment, file) in Attachment::find()
+        .find_also_related(File)
+        .filter(attachment::Column::MessageId.is_in(res.iter().map(|(message, _)| message.id)))
+        .order_by_asc(attachment::Column::FileId)
+        .all(conn)
+        .await
+        .kind(ErrorKind::Internal)?
+    {
+        let Some(file) = file else {
+            continue;
+        };
+        files
+            .entry(attachment.message_id)
+            .or_default()
+            .push(MessagePaginateRespFile {
+                id: file.id,
+                name: file.name,
+                content_type: file.content_type,
+                size: file.size,
+            });
+    }
+
+    let mut ratings: HashMap<i32, FeedbackRating> = Feedback::find()
+        .filter(feedback::Column::MessageId.is_in(res.iter().map(|(message, _)| message.id)))
+        .all(conn)
+        .await
+        .kind(ErrorKind::Internal)?
         .into_iter()
-        .filter_map(|(message, chunks)| {
+        .map(|x| (x.message_id, x.rating))
+        .collect();
+
+    res.into_iter()
+        .filter_map(|(message, mut chunks)| {
             let role = match message.kind {
                 MessageKind::User => MessagePaginateRespRole::User,
                 MessageKind::Assistant => MessagePaginateRespRole::Assistant,
+                MessageKind::Marker => MessagePaginateRespRole::Marker,
                 MessageKind::Hidden => return None,
             };
+            let generation = message.get_generation();
+            let links = links.remove(&message.id).unwrap_or_default();
+            let files = files.remove(&message.id).unwrap_or_default();
+            let feedback = ratings.remove(&message.id);
+            let siblings = siblings.remove(&message.id).unwrap_or_default();
+            chunks.sort_by_key(|x| x.id);
             let chunks: Result<_, Json<Error>> = chunks
                 .into_iter()
                 .map(|chunk| {
@@ -206,9 +357,17 @@ pub async fn route(
                 id: message.id,
                 role,
                 chunks,
+                truncated: message.truncated,
+                interrupted: message.interrupted,
+                private: message.private,
+                author_id: message.author_id,
+                generation,
+                links,
+                files,
+                feedback,
+                parent_id: message.parent_id,
+                siblings,
             }))
         })
-        .collect::<Result<_, _>>()?;
-
-    Ok(Json(MessagePaginateResp { list }))
+        .collect()
 }
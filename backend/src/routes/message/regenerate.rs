@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{MessageKind, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::EntityTrait;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use super::create::{MessageCreateReqMode, Turn, joined_chat, start};
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{ApiKeyUser, UserId, WorkspaceId},
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct MessageRegenerateReq {
+    /// id of the assistant message to replace
+    pub id: i32,
+    pub mode: MessageCreateReqMode,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct MessageRegenerateResp {
+    /// the message the new reply answer
+    pub parent_id: Option<i32>,
+}
+
+/// Reply again to the message before an assistant message, the old reply stay
+/// on its branch and the new one stream like a normal completion
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    api_key: Option<Extension<ApiKeyUser>>,
+    Json(req): Json<MessageRegenerateReq>,
+) -> JsonResult<MessageRegenerateResp> {
+    let message = Message::find_by_id(req.id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .filter(|x| x.kind == MessageKind::Assistant)
+        .ok_or("Cannot find the assistant message")
+        .kind(ErrorKind::ResourceNotFound)?;
+    let chat = joined_chat(&app.conn, user_id, workspace_id, message.chat_id).await?;
+
+    let turn = Turn::Regenerate {
+        parent_id: message.parent_id,
+    };
+    start(app, user_id, api_key, chat, req.mode, turn).await?;
+
+    Ok(Json(MessageRegenerateResp {
+        parent_id: message.parent_id,
+    }))
+}
@@ -0,0 +1,243 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Query, State},
+};
+use entity::{MessageKind, SearchTermKind};
+use schemars::JsonSchema;
+use sea_orm::{ConnectionTrait, DbBackend, FromQueryResult, Value};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use super::paginate::MessagePaginateRespRole;
+use crate::{
+    AppState,
+    config::{SEARCH_MAX_RESULTS, SEARCH_SNIPPET_CHARS},
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+    utils::sql,
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct MessageSearchReq {
+    /// Words separated by spaces, all of them must appear
+    pub q: String,
+    /// Only in this chat
+    pub chat_id: Option<i32>,
+    /// default to and capped at 50
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct MessageSearchResp {
+    /// Best match first
+    pub list: Vec<MessageSearchRespItem>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct MessageSearchRespItem {
+    pub chat_id: i32,
+    pub chat_title: Option<String>,
+    pub message_id: i32,
+    pub role: MessagePaginateRespRole,
+    /// Text around the first hit, split where the words start and end
+    pub snippet: Vec<MessageSearchRespPart>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct MessageSearchRespPart {
+    pub text: String,
+    /// One of the searched words
+    pub hit: bool,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct Row {
+    message_id: i32,
+    content: String,
+    chat_id: i32,
+    title: Option<String>,
+    kind: MessageKind,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Query(req): Query<MessageSearchReq>,
+) -> JsonResult<MessageSearchResp> {
+    let terms: Vec<&str> = req.q.split_whitespace().collect();
+    if terms.is_empty() {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "Nothing to search".to_owned(),
+        }));
+    }
+    let limit = req
+        .limit
+        .unwrap_or(SEARCH_MAX_RESULTS)
+        .min(SEARCH_MAX_RESULTS);
+
+    let backend = app.conn.get_database_backend();
+    // boosted terms of the owner in the content, see `SearchTermKind`
+    let boosts = format!(
+        "(SELECT COUNT(*) FROM search_term
+        WHERE search_term.user_id = chat.owner_id AND search_term.kind = {}
+            AND {} > 0)",
+        SearchTermKind::Boost as i32,
+        sql::position(
+            backend,
+            "lower(message_fts.content)",
+            "lower(search_term.term)"
+        )
+    );
+
+    let mut values: Vec<Value> = vec![];
+    // trigrams cannot match words shorter than 3 characters, scan for those;
+    // PostgreSQL has no bm25, its trigram index serves the scan instead
+    let ranked = backend == DbBackend::Sqlite && terms.iter().all(|x| x.chars().count() >= 3);
+    let (filter, order) = match ranked {
+        true => {
+            values.push(
+                terms
+                    .iter()
+                    .map(|x| format!("\"{}\"", x.replace('"', "\"\"")))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .into(),
+            );
+            // bm25 is negative, lower is better
+            (
+                "message_fts MATCH ?".to_owned(),
+                format!("bm25(message_fts) * (1 + {})", boosts),
+            )
+        }
+        false => {
+            let filter = terms
+                .iter()
+                .map(|x| {
+                    let escaped = x
+                        .replace('\\', "\\\\")
+                        .replace('%', "\\%")
+                        .replace('_', "\\_");
+                    values.push(format!("%{}%", escaped).into());
+                    match backend {
+                        DbBackend::Postgres => "message_fts.content ILIKE ? ESCAPE '\\'",
+                        _ => "message_fts.content LIKE ? ESCAPE '\\'",
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" AND ");
+            (filter, format!("{} DESC, message_fts.rowid DESC", boosts))
+        }
+    };
+    values.push(user_id.into());
+    values.push(workspace_id.into());
+    values.push((MessageKind::Hidden as i32).into());
+    // private messages of members, see `utils::message::visible_messages`
+    values.push(false.into());
+    values.push(user_id.into());
+    values.push(user_id.into());
+    let chat_filter = match req.chat_id {
+        Some(chat_id) => {
+            values.push(chat_id.into());
+            "AND chat.id = ?"
+        }
+        None => "",
+    };
+    // a message can match in several chunks
+    values.push((limit * 4).into());
+
+    let sql = format!(
+        "SELECT message_fts.message_id AS message_id, message_fts.content AS content,
+            message.chat_id AS chat_id, chat.title AS title, message.kind AS kind
+        FROM message_fts
+        JOIN message ON message.id = message_fts.message_id
+        JOIN chat ON chat.id = message.chat_id
+        WHERE {} AND chat.owner_id = ? AND chat.workspace_id = ? AND message.kind != ?
+            AND (message.private = ? OR message.author_id = ?
+                OR (message.author_id IS NULL AND chat.owner_id = ?)) {}
+        ORDER BY {} LIMIT ?",
+        filter, chat_filter, order
+    );
+    let rows = Row::find_by_statement(sql::statement(backend, sql, values))
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let mut list: Vec<MessageSearchRespItem> = vec![];
+    for row in rows {
+        if list.len() as u32 >= limit {
+            break;
+        }
+        if list.iter().any(|x| x.message_id == row.message_id) {
+            continue;
+        }
+        list.push(MessageSearchRespItem {
+            chat_id: row.chat_id,
+            chat_title: row.title,
+            message_id: row.message_id,
+            role: match row.kind {
+                MessageKind::Assistant => MessagePaginateRespRole::Assistant,
+                MessageKind::Marker => MessagePaginateRespRole::Marker,
+                _ => MessagePaginateRespRole::User,
+            },
+            snippet: snippet(&row.content, &terms),
+        });
+    }
+
+    Ok(Json(MessageSearchResp { list }))
+}
+
+/// Window of `content` around the first hit of `terms`, ignoring case
+fn snippet(content: &str, terms: &[&str]) -> Vec<MessageSearchRespPart> {
+    let lower = |x: char| x.to_lowercase().next().unwrap_or(x);
+    let chars: Vec<char> = content.chars().collect();
+    let folded: Vec<char> = chars.iter().copied().map(lower).collect();
+
+    let mut hit = vec![false; chars.len()];
+    for term in terms {
+        let term: Vec<char> = term.chars().map(lower).collect();
+        for start in 0..folded.len().saturating_sub(term.len() - 1) {
+            if folded[start..start + term.len()] == term[..] {
+                hit[start..start + term.len()].fill(true);
+            }
+        }
+    }
+
+    let first = hit.iter().position(|x| *x).unwrap_or(0);
+    let start = first.saturating_sub(SEARCH_SNIPPET_CHARS / 4);
+    let end = (start + SEARCH_SNIPPET_CHARS).min(chars.len());
+
+    let mut parts: Vec<MessageSearchRespPart> = vec![];
+    for i in start..end {
+        match parts.last_mut() {
+            Some(part) if part.hit == hit[i] => part.text.push(chars[i]),
+            _ => parts.push(MessageSearchRespPart {
+                text: chars[i].to_string(),
+                hit: hit[i],
+            }),
+        }
+    }
+    if start > 0 {
+        parts.insert(
+            0,
+            MessageSearchRespPart {
+                text: "…".to_owned(),
+                hit: false,
+            },
+        );
+    }
+    if end < chars.len() {
+        parts.push(MessageSearchRespPart {
+            text: "…".to_owned(),
+            hit: false,
+        });
+    }
+    parts
+}
@@ -0,0 +1,199 @@
+use entity::{Trace, TraceRound, TraceStatus, TraceTool};
+use tokio::time::{Duration, Instant};
+
+use crate::{
+    reply_stats::ReplyRecord,
+    sse::{EndKind, MessageMeta},
+};
+
+/// Throughput and latency of an assistant message, across every completion of the tool loop
+#[derive(Debug)]
+pub struct Stats {
+    start: Instant,
+    ttft: Option<Duration>,
+    model: String,
+    finish_reason: Option<&'static str>,
+
+    /// first token of the current completion
+    first_token: Option<Instant>,
+    /// token chunks of the current completion, used when usage is not reported
+    chunks: usize,
+    usage: Option<usize>,
+
+    tokens: usize,
+    streaming: Duration,
+    /// in USD, as reported by openrouter
+    cost: f64,
+    /// names of the tools called, once per call
+    tools: Vec<String>,
+
+    /// start of the current completion
+    round_start: Option<Instant>,
+    round_cost: f64,
+    /// start of the current tool call
+    tool_start: Option<Instant>,
+    rounds: Vec<TraceRound>,
+    stopped: Option<&'static str>,
+}
+
+impl Stats {
+    pub fn new(model: &str) -> Self {
+        Self {
+            start: Instant::now(),
+            ttft: None,
+            model: model.to_owned(),
+            finish_reason: None,
+            first_token: None,
+            chunks: 0,
+            usage: None,
+            tokens: 0,
+            streaming: Duration::ZERO,
+            cost: 0.0,
+            tools: vec![],
+            round_start: None,
+            round_cost: 0.0,
+            tool_start: None,
+            rounds: vec![],
+            stopped: None,
+        }
+    }
+
+    /// A completion of the tool loop starts, ended by [`Stats::end_completion`]
+    pub fn start_completion(&mut self) {
+        self.round_start = Some(Instant::now());
+        self.round_cost = 0.0;
+    }
+
+    pub fn token(&mut self) {
+        let now = Instant::now();
+        self.ttft.get_or_insert(now - self.start);
+        self.first_token.get_or_insert(now);
+        self.chunks += 1;
+    }
+
+    pub fn usage(&mut self, completion_token: Option<usize>, price: f64) {
+        self.usage = completion_token.or(self.usage);
+        self.cost += price;
+        self.round_cost += price;
+    }
+
+    pub fn cost(&self) -> f64 {
+        self.cost
+    }
+
+    /// A tool call starts, ended by [`Stats::end_tool`]
+    pub fn tool(&mut self, name: &str) {
+        self.tools.push(name.to_owned());
+        self.tool_start = Some(Instant::now());
+        self.trace_tool(name, TraceStatus::Halted);
+    }
+
+    pub fn end_tool(&mut self, ok: bool) {
+        let duration = self.tool_start.take().map(|x| x.elapsed());
+        if let Some(tool) = self.rounds.last_mut().and_then(|x| x.tools.last_mut()) {
+            tool.duration_ms = ms(duration.unwrap_or_default());
+            tool.status = match ok {
+                true => TraceStatus::Done,
+                false => TraceStatus::Failed,
+            };
+        }
+    }
+
+    /// A tool call the loop did not make
+    pub fn skip_tool(&mut self, name: &str) {
+        self.trace_tool(name, TraceStatus::Skipped);
+    }
+
+    /// The limit the loop ran into
+    pub fn stop(&mut self, reason: &'static str) {
+        self.stopped = Some(reason);
+    }
+
+    fn trace_tool(&mut self, name: &str, status: TraceStatus) {
+        let start_ms = ms(self.start.elapsed());
+        if let Some(round) = self.rounds.last_mut() {
+            round.tools.push(TraceTool {
+                name: name.to_owned(),
+                start_ms,
+                duration_ms: 0,
+                status,
+            });
+        }
+    }
+
+    /// What `message/{id}/trace` serves
+    pub fn trace(&self) -> Trace {
+        Trace {
+            rounds: self.rounds.clone(),
+            duration_ms: ms(self.start.elapsed()),
+            stopped: self.stopped.map(str::to_owned),
+        }
+    }
+
+    /// What `reply_stat` keeps of the reply
+    pub fn record(&self, kind: EndKind) -> ReplyRecord {
+        ReplyRecord {
+            model_id: self.model.clone(),
+            tokens: self.tokens as i64,
+            cost: self.cost,
+            ttft_ms: self.ttft.unwrap_or_default().as_millis() as i64,
+            tools: self.tools.clone(),
+            failed: matches!(kind, EndKind::Error),
+        }
+    }
+
+    /// Output tokens of the completions ended so far
+    pub fn tokens(&self) -> usize {
+        self.tokens
+    }
+
+    pub fn end_completion(&mut self, model: Option<&str>, finish_reason: Option<&'static str>) {
+        if let Some(first_token) = self.first_token.take() {
+            self.streaming += first_token.elapsed();
+        }
+        let tokens = self.usage.take().unwrap_or(self.chunks);
+        self.tokens += tokens;
+        self.chunks = 0;
+
+        if let Some(model) = model {
+            self.model = model.to_owned();
+        }
+        self.finish_reason = finish_reason.or(self.finish_reason);
+
+        if let Some(start) = self.round_start.take() {
+            self.rounds.push(TraceRound {
+                model: self.model.clone(),
+                start_ms: ms(start - self.start),
+                duration_ms: ms(start.elapsed()),
+                tokens: tokens as u32,
+                cost: self.round_cost,
+                finish_reason: finish_reason.map(str::to_owned),
+                tools: vec![],
+            });
+        }
+    }
+
+    pub fn meta(self, kind: EndKind) -> MessageMeta {
+        let secs = self.streaming.as_secs_f64();
+        let finish_reason = match kind {
+            EndKind::Complete => self.finish_reason.unwrap_or("stop"),
+            EndKind::Halt => "halt",
+            EndKind::Error => "error",
+            EndKind::Truncated => "truncated",
+        };
+
+        MessageMeta {
+            tokens_per_sec: match secs > 0.0 {
+                true => self.tokens as f64 / secs,
+                false => 0.0,
+            },
+            ttft_ms: self.ttft.unwrap_or_default().as_millis() as u64,
+            model: self.model,
+            finish_reason: finish_reason.to_owned(),
+        }
+    }
+}
+
+fn ms(duration: Duration) -> u32 {
+    duration.as_millis() as u32
+}
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{file, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use super::create::{MessageCreateReqMode, Turn, joined_chat, start};
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{ApiKeyUser, UserId, WorkspaceId},
+    utils::extract,
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct MessageSummarizeReq {
+    pub chat_id: i32,
+    /// Uploaded with `/api/file/upload`, a text, HTML, PDF or DOCX file
+    pub file_id: i32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct MessageSummarizeResp {
+    pub id: i32,
+}
+
+/// Ask for a summary of the file in the chat, the file is attached to the
+/// user message and its text sent to the model, see `utils::extract`
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    api_key: Option<Extension<ApiKeyUser>>,
+    Json(req): Json<MessageSummarizeReq>,
+) -> JsonResult<MessageSummarizeResp> {
+    let chat = joined_chat(&app.conn, user_id, workspace_id, req.chat_id).await?;
+    let file = File::find_by_id(req.file_id)
+        .filter(file::Column::OwnerId.eq(user_id))
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Cannot find the file")
+        .kind(ErrorKind::ResourceNotFound)?;
+    if extract::format(&file.content_type, &file.name).is_none() {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!("cannot read {} files", file.content_type),
+        }));
+    }
+
+    let turn = Turn::Append {
+        text: format!(
+            "Summarize the attached file {}: its purpose, main points and conclusions.",
+            file.name
+        ),
+        files: vec![file.id],
+    };
+    let id = start(
+        app,
+        user_id,
+        api_key,
+        chat,
+        MessageCreateReqMode::Normal,
+        turn,
+    )
+    .await?
+    .ok_or("No user message was sent")
+    .kind(ErrorKind::Internal)?;
+
+    Ok(Json(MessageSummarizeResp { id }))
+}
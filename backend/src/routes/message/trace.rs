@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+};
+use entity::{MessageKind, Trace, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::EntityTrait;
+use serde::Serialize;
+use typeshare::typeshare;
+
+use super::create::joined_chat;
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+};
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct MessageTraceResp {
+    /// None for replies written before they were traced
+    pub trace: Option<Trace>,
+}
+
+/// Rounds of the tool loop of an assistant reply: each completion of the
+/// model, then the tools it called, with their durations
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Path(id): Path<i32>,
+) -> JsonResult<MessageTraceResp> {
+    let message = Message::find_by_id(id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .filter(|x| x.kind == MessageKind::Assistant)
+        .ok_or("Cannot find the reply")
+        .kind(ErrorKind::ResourceNotFound)?;
+    joined_chat(&app.conn, user_id, workspace_id, message.chat_id).await?;
+
+    Ok(Json(MessageTraceResp {
+        trace: message.get_trace(),
+    }))
+}
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{message, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{ActiveValue::Set, EntityTrait};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct MessageVisibilityReq {
+    /// message id
+    pub id: i32,
+    /// hide from other members and shared exports, still in the author's context
+    pub private: bool,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct MessageVisibilityResp {
+    pub wrote: bool,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<MessageVisibilityReq>,
+) -> JsonResult<MessageVisibilityResp> {
+    let res = Message::find_by_id(req.id)
+        .find_also_related(Chat)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let Some((message, Some(chat))) = res else {
+        return Err(Json(Error {
+            error: ErrorKind::ResourceNotFound,
+            reason: "".to_owned(),
+        }));
+    };
+    // only the author hides a message from the other members
+    if message.author_id.unwrap_or(chat.owner_id) != user_id {
+        return Err(Json(Error {
+            error: ErrorKind::ResourceNotFound,
+            reason: "".to_owned(),
+        }));
+    }
+
+    let wrote = message.private != req.private;
+    if wrote {
+        Message::update(message::ActiveModel {
+            id: Set(message.id),
+            private: Set(req.private),
+            ..Default::default()
+        })
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    }
+
+    Ok(Json(MessageVisibilityResp { wrote }))
+}
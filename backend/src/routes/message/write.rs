@@ -1,27 +1,82 @@
 use std::sync::Arc;
 
 use axum::{Extension, Json, extract::State};
+use entity::{MessageKind, chat, message, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{DbConn, EntityTrait};
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-use crate::{AppState, errors::*, middlewares::auth::UserId};
+use super::create::{MessageCreateReqMode, Turn, joined_chat, start};
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{ApiKeyUser, UserId, WorkspaceId},
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct MessageWriteReq {
-    /// message id
+    /// id of the user message to edit
     pub id: i32,
     pub text: String,
+    pub mode: MessageCreateReqMode,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
-pub struct Resp {}
+pub struct MessageWriteResp {
+    /// the edited message, a sibling of `id`
+    pub id: i32,
+}
 
+/// Edit a user message, the original and its replies stay on their branch
+/// and the chat continue on a new one
 pub async fn route(
     State(app): State<Arc<AppState>>,
     Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    api_key: Option<Extension<ApiKeyUser>>,
     Json(req): Json<MessageWriteReq>,
-) -> JsonResult<Resp> {
-    todo!()
+) -> JsonResult<MessageWriteResp> {
+    let (message, chat) = user_message(&app.conn, user_id, workspace_id, req.id).await?;
+
+    let turn = Turn::Edit {
+        parent_id: message.parent_id,
+        text: req.text,
+        files: crate::files::attached_ids(&app.conn, message.id)
+            .await
+            .kind(ErrorKind::Internal)?,
+    };
+    let id = start(app, user_id, api_key, chat, req.mode, turn)
+        .await?
+        .ok_or("No user message was sent")
+        .kind(ErrorKind::Internal)?;
+
+    Ok(Json(MessageWriteResp { id }))
+}
+
+/// A user message the user sent, with its chat
+pub(super) async fn user_message(
+    conn: &DbConn,
+    user_id: i32,
+    workspace_id: i32,
+    id: i32,
+) -> Result<(message::Model, chat::Model), Json<Error>> {
+    let message = Message::find_by_id(id)
+        .one(conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .filter(|x| x.kind == MessageKind::User)
+        .ok_or("Cannot find the user message")
+        .kind(ErrorKind::ResourceNotFound)?;
+    let chat = joined_chat(conn, user_id, workspace_id, message.chat_id).await?;
+    // members only edit their own messages
+    if message.author_id.unwrap_or(chat.owner_id) != user_id {
+        return Err(Json(Error {
+            error: ErrorKind::Unauthorized,
+            reason: "Only the author can edit the message".to_owned(),
+        }));
+    }
+    Ok((message, chat))
 }
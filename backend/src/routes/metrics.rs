@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::State,
+    http::{HeaderMap, header},
+    response::IntoResponse,
+};
+use dotenv::var;
+use sha2::{Digest, Sha256};
+
+use crate::{AppState, errors::*, middlewares::metrics::METRICS};
+
+/// Counters in the Prometheus text format, served when `METRICS_TOKEN` is set
+/// to scrapers sending it as a bearer token
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, Json<Error>> {
+    let expected = var("METRICS_TOKEN")
+        .ok()
+        .filter(|x| !x.is_empty())
+        .ok_or("metrics are disabled")
+        .kind(ErrorKind::ResourceNotFound)?;
+
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.strip_prefix("Bearer "))
+        // digests are compared, as hashes of the other secrets, so the time
+        // taken tells nothing of the token
+        .filter(|x| Sha256::digest(x.as_bytes()) == Sha256::digest(expected.as_bytes()))
+        .ok_or("missing or wrong metrics token")
+        .kind(ErrorKind::Unauthorized)?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        METRICS.render(&app),
+    ))
+}
@@ -1,5 +1,33 @@
+pub mod admin;
 pub mod auth;
+#[cfg(feature = "bundled")]
+pub mod bundled;
+pub mod calendar;
+pub mod capture;
 pub mod chat;
+pub mod demo;
+pub mod federation;
+pub mod file;
+pub mod folder;
+pub mod health;
+pub mod kb;
+pub mod label;
+pub mod memory;
 pub mod message;
+pub mod metrics;
 pub mod model;
+pub mod notifications;
+pub mod openai;
+pub mod openapi;
+pub mod persona;
+pub mod policy;
+pub mod pricing;
+pub mod prompt;
+pub mod schedule;
+pub mod setting;
+pub mod share;
+pub mod sync;
+pub mod undo;
 pub mod user;
+pub mod workspace;
+pub mod ws;
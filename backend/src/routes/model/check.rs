@@ -2,18 +2,23 @@ use std::sync::Arc;
 
 use axum::{Extension, Json, extract::State};
 use entity::model;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-use crate::{AppState, errors::*, middlewares::auth::UserId};
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct ModelCheckReq {
     pub config: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct ModelCheckResp {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -21,8 +26,9 @@ pub struct ModelCheckResp {
 }
 
 pub async fn route(
-    State(app): State<Arc<AppState>>,
+    State(_app): State<Arc<AppState>>,
     Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
     Json(req): Json<ModelCheckReq>,
 ) -> JsonResult<ModelCheckResp> {
     let config = req.config;
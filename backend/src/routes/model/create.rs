@@ -1,20 +1,25 @@
 use std::sync::Arc;
 
 use axum::{Extension, Json, extract::State};
-use entity::{model, prelude::*};
+use entity::{AuditKind, model, prelude::*};
+use schemars::JsonSchema;
 use sea_orm::{ActiveValue::Set, EntityTrait};
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-use crate::{AppState, errors::*, middlewares::auth::UserId};
+use crate::{
+    AppState, audit,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct ModelCreateReq {
     pub config: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct ModelCreateResp {
     pub id: i32,
@@ -23,7 +28,8 @@ pub struct ModelCreateResp {
 
 pub async fn route(
     State(app): State<Arc<AppState>>,
-    Extension(UserId(_)): Extension<UserId>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
     Json(req): Json<ModelCreateReq>,
 ) -> JsonResult<ModelCreateResp> {
     let config = req.config;
@@ -38,6 +44,9 @@ pub async fn route(
             .await
             .kind(ErrorKind::Internal)?
             .last_insert_id;
+            app.cache.models.clear();
+            let detail = format!("model {} ({}) created", id, cfg.display_name);
+            audit::record(&app.conn, AuditKind::Config, Some(user_id), detail).await;
 
             Ok(Json(ModelCreateResp {
                 id,
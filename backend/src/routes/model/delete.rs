@@ -1,20 +1,25 @@
 use std::sync::Arc;
 
 use axum::{Extension, Json, extract::State};
-use entity::model;
+use entity::{AuditKind, model};
+use schemars::JsonSchema;
 use sea_orm::EntityTrait;
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-use crate::{AppState, errors::*, middlewares::auth::UserId};
+use crate::{
+    AppState, audit,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct ModelDeleteReq {
     pub id: i32,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct ModelDeleteResp {
     pub deleted: bool,
@@ -22,13 +27,23 @@ pub struct ModelDeleteResp {
 
 pub async fn route(
     State(app): State<Arc<AppState>>,
-    Extension(UserId(_)): Extension<UserId>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
     Json(req): Json<ModelDeleteReq>,
 ) -> JsonResult<ModelDeleteResp> {
     model::Entity::delete_by_id(req.id)
         .exec(&app.conn)
         .await
         .kind(ErrorKind::ResourceNotFound)?;
+    app.cache.models.clear();
+    let detail = format!("model {} deleted", req.id);
+    audit::record(&app.conn, AuditKind::Config, Some(user_id), detail).await;
+
+    let mut config = app.settings.current();
+    if config.default_model_id == Some(req.id) {
+        config.default_model_id = None;
+        app.settings.set(config).await.kind(ErrorKind::Internal)?;
+    }
 
     Ok(Json(ModelDeleteResp { deleted: true }))
 }
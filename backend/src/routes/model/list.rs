@@ -1,41 +1,56 @@
 use std::sync::Arc;
 
 use axum::{Extension, Json, extract::State};
-use entity::model;
-use sea_orm::EntityTrait;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-use crate::{AppState, errors::*, middlewares::auth::UserId};
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{self, UserId, WorkspaceId, is_admin},
+    utils::{self, workspace},
+};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct ModelListResp {
     pub list: Vec<ModelList>,
+    /// Model new chats start with, the user's `default_model_id` else the
+    /// one admins set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_id: Option<i32>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct ModelList {
     pub id: i32,
     pub display_name: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct ModelListReq {}
 
+/// Only the models offered in the workspace of the request, every model for
+/// admins who manage them
 pub async fn route(
     State(app): State<Arc<AppState>>,
-    Extension(UserId(_)): Extension<UserId>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
     Json(_): Json<ModelListReq>,
 ) -> JsonResult<ModelListResp> {
-    let models = model::Entity::find()
-        .all(&app.conn)
-        .await
-        .kind(ErrorKind::Internal)?;
+    let models = utils::model::all(&app).await.kind(ErrorKind::Internal)?;
+    let offered = match is_admin(&app, user_id).await? {
+        true => None,
+        false => workspace::models(&app.conn, workspace_id)
+            .await
+            .kind(ErrorKind::Internal)?,
+    };
     let list = models
-        .into_iter()
+        .iter()
+        .filter(|m| offered.as_ref().is_none_or(|x| x.contains(&m.id)))
         .filter_map(|m| {
             Some(ModelList {
                 id: m.id,
@@ -43,5 +58,14 @@ pub async fn route(
             })
         })
         .collect::<Vec<_>>();
-    Ok(Json(ModelListResp { list }))
+    // the user's own before the one of admins, if the workspace offers it
+    let preferred = auth::user(&app, user_id)
+        .await
+        .kind(ErrorKind::Internal)?
+        .and_then(|x| x.preference.default_model_id);
+    let default_id = preferred
+        .into_iter()
+        .chain(app.settings.current().default_model_id)
+        .find(|x| list.iter().any(|m| m.id == *x));
+    Ok(Json(ModelListResp { list, default_id }))
 }
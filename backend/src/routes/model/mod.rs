@@ -3,13 +3,14 @@ mod create;
 mod delete;
 mod list;
 mod read;
+mod variant;
 mod write;
 
 use std::sync::Arc;
 
 use axum::{Router, routing::post};
 
-use crate::AppState;
+use crate::{AppState, utils::openapi::Builder};
 
 pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
@@ -19,4 +20,66 @@ pub fn routes() -> Router<Arc<AppState>> {
         .route("/list", post(list::route))
         .route("/read", post(read::route))
         .route("/check", post(check::route))
+        .route("/variant/list", post(variant::list))
+        .route("/variant/create", post(variant::create))
+        .route("/variant/write", post(variant::write))
+        .route("/variant/delete", post(variant::delete))
+}
+
+/// Describe the routes above, served by `routes::openapi`
+pub fn spec(api: &mut Builder) {
+    api.op("POST", "/model/create", "Add a model, admins only")
+        .body::<create::ModelCreateReq>()
+        .json::<create::ModelCreateResp>();
+    api.op("POST", "/model/delete", "Remove a model, admins only")
+        .body::<delete::ModelDeleteReq>()
+        .json::<delete::ModelDeleteResp>();
+    api.op(
+        "POST",
+        "/model/write",
+        "Replace the config of a model, admins only",
+    )
+    .body::<write::ModelWriteReq>()
+    .json::<write::ModelWriteResp>();
+    api.op("POST", "/model/list", "Models offered to the user")
+        .body::<list::ModelListReq>()
+        .json::<list::ModelListResp>();
+    api.op("POST", "/model/read", "Read the config of a model")
+        .body::<read::ModelReadReq>()
+        .json::<read::ModelReadResp>();
+    api.op(
+        "POST",
+        "/model/check",
+        "Validate a model config, admins only",
+    )
+    .body::<check::ModelCheckReq>()
+    .json::<check::ModelCheckResp>();
+    api.op(
+        "POST",
+        "/model/variant/list",
+        "Variants of a model with their stats, admins only",
+    )
+    .body::<variant::ModelVariantListReq>()
+    .json::<variant::ModelVariantListResp>();
+    api.op(
+        "POST",
+        "/model/variant/create",
+        "Add a variant to a model, admins only",
+    )
+    .body::<variant::ModelVariantCreateReq>()
+    .json::<variant::ModelVariantCreateResp>();
+    api.op(
+        "POST",
+        "/model/variant/write",
+        "Replace a variant, admins only",
+    )
+    .body::<variant::ModelVariantWriteReq>()
+    .json::<variant::ModelVariantWriteResp>();
+    api.op(
+        "POST",
+        "/model/variant/delete",
+        "Remove a variant, admins only",
+    )
+    .body::<variant::ModelVariantDeleteReq>()
+    .json::<variant::ModelVariantDeleteResp>();
 }
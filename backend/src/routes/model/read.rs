@@ -1,20 +1,21 @@
 use std::sync::Arc;
 
 use axum::{Extension, Json, extract::State};
-use entity::{model, patch::ModelConfig};
+use entity::model;
+use schemars::JsonSchema;
 use sea_orm::EntityTrait;
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
 use crate::{AppState, errors::*, middlewares::auth::UserId};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct ModelReadReq {
     pub id: i32,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct ModelReadResp {
     raw: String,
@@ -0,0 +1,295 @@
+//! Prompt variants of a model, see `prompts::variant`
+
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{Extension, Json, extract::State};
+use entity::{FeedbackRating, MessageKind, chat, prelude::*, prompt_variant};
+use schemars::JsonSchema;
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, ConnectionTrait, EntityTrait, FromQueryResult, QueryFilter,
+    QueryOrder,
+};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+    utils::sql,
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ModelVariantListReq {
+    pub model_id: i32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ModelVariantListResp {
+    pub list: Vec<ModelVariantListRespItem>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ModelVariantListRespItem {
+    pub id: i32,
+    pub name: String,
+    /// None for the built-in prompt
+    pub template: Option<String>,
+    pub weight: u32,
+    /// unix seconds
+    pub created_at: u32,
+    pub stats: ModelVariantStats,
+}
+
+/// Quality of the replies written with a variant
+#[derive(Debug, Default, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ModelVariantStats {
+    /// chats with a reply of the variant
+    pub chats: i64,
+    pub replies: i64,
+    /// replies the user asked to write again
+    pub regenerated: i64,
+    /// replies cut by the output limit of the provider
+    pub truncated: i64,
+    /// output tokens of the replies
+    pub tokens: i64,
+    /// replies rated by their user, see `message/{id}/feedback`
+    pub up: i64,
+    pub down: i64,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ModelVariantCreateReq {
+    pub model_id: i32,
+    pub name: String,
+    /// None for the built-in prompt, the control of the experiment
+    pub template: Option<String>,
+    pub weight: u32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ModelVariantCreateResp {
+    pub id: i32,
+}
+
+/// Replace a variant, chats already sampled into it keep it
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ModelVariantWriteReq {
+    pub id: i32,
+    pub name: String,
+    pub template: Option<String>,
+    /// 0 stop sampling new chats into it
+    pub weight: u32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ModelVariantWriteResp {
+    pub wrote: bool,
+}
+
+/// Chats of a deleted variant are sampled again on their next message
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ModelVariantDeleteReq {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ModelVariantDeleteResp {
+    pub deleted: bool,
+}
+
+#[derive(FromQueryResult)]
+struct StatsRow {
+    id: i32,
+    chats: i64,
+    replies: i64,
+    regenerated: i64,
+    truncated: i64,
+    tokens: i64,
+    up: i64,
+    down: i64,
+}
+
+pub async fn list(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<ModelVariantListReq>,
+) -> JsonResult<ModelVariantListResp> {
+    let variants = PromptVariant::find()
+        .filter(prompt_variant::Column::ModelId.eq(req.model_id))
+        .order_by_asc(prompt_variant::Column::Id)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let mut stats = stats(&app.conn, req.model_id)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let list = variants
+        .into_iter()
+        .map(|x| ModelVariantListRespItem {
+            id: x.id,
+            name: x.name,
+            template: x.template,
+            weight: x.weight as u32,
+            created_at: x.created_at as u32,
+            stats: stats.remove(&x.id).unwrap_or_default(),
+        })
+        .collect();
+    Ok(Json(ModelVariantListResp { list }))
+}
+
+pub async fn create(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<ModelVariantCreateReq>,
+) -> JsonResult<ModelVariantCreateResp> {
+    check(&app, &req.name, req.template.as_deref())?;
+    Model::find_by_id(req.model_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Cannot find model")
+        .kind(ErrorKind::ResourceNotFound)?;
+
+    let id = PromptVariant::insert(prompt_variant::ActiveModel {
+        model_id: Set(req.model_id),
+        name: Set(req.name),
+        template: Set(req.template),
+        weight: Set(req.weight.min(i32::MAX as u32) as i32),
+        created_at: Set(time::UtcDateTime::now().unix_timestamp()),
+        ..Default::default()
+    })
+    .exec(&app.conn)
+    .await
+    .kind(ErrorKind::Internal)?
+    .last_insert_id;
+    Ok(Json(ModelVariantCreateResp { id }))
+}
+
+pub async fn write(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<ModelVariantWriteReq>,
+) -> JsonResult<ModelVariantWriteResp> {
+    check(&app, &req.name, req.template.as_deref())?;
+    let res = PromptVariant::update_many()
+        .col_expr(prompt_variant::Column::Name, req.name.into())
+        .col_expr(prompt_variant::Column::Template, req.template.into())
+        .col_expr(
+            prompt_variant::Column::Weight,
+            (req.weight.min(i32::MAX as u32) as i32).into(),
+        )
+        .filter(prompt_variant::Column::Id.eq(req.id))
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    Ok(Json(ModelVariantWriteResp {
+        wrote: res.rows_affected > 0,
+    }))
+}
+
+pub async fn delete(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<ModelVariantDeleteReq>,
+) -> JsonResult<ModelVariantDeleteResp> {
+    let res = PromptVariant::delete_by_id(req.id)
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    // replies keep the tag, their stats are gone with the variant anyway
+    Chat::update_many()
+        .col_expr(chat::Column::PromptVariantId, None::<i32>.into())
+        .filter(chat::Column::PromptVariantId.eq(req.id))
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    Ok(Json(ModelVariantDeleteResp {
+        deleted: res.rows_affected > 0,
+    }))
+}
+
+fn check(app: &AppState, name: &str, template: Option<&str>) -> Result<(), Json<Error>> {
+    if name.trim().is_empty() {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "Name cannot be empty".to_owned(),
+        }));
+    }
+    if let Some(template) = template {
+        app.prompt.check(template).map_err(|e| {
+            Json(Error {
+                error: ErrorKind::MalformedRequest,
+                reason: format!("Malformed template: {}", e),
+            })
+        })?;
+    }
+    Ok(())
+}
+
+/// Stats of the variants of a model by id, a reply is regenerated when a later
+/// reply share its parent
+async fn stats(
+    conn: &impl ConnectionTrait,
+    model_id: i32,
+) -> Result<HashMap<i32, ModelVariantStats>, sea_orm::DbErr> {
+    let rows = StatsRow::find_by_statement(sql::statement(
+        conn.get_database_backend(),
+        "SELECT m.prompt_variant_id AS id,
+            COUNT(DISTINCT m.chat_id) AS chats,
+            COUNT(*) AS replies,
+            COUNT(CASE WHEN EXISTS (
+                SELECT 1 FROM message r
+                WHERE r.chat_id = m.chat_id AND r.kind = ?
+                AND r.parent_id IS NOT DISTINCT FROM m.parent_id AND r.id > m.id
+            ) THEN 1 END) AS regenerated,
+            COUNT(CASE WHEN m.truncated THEN 1 END) AS truncated,
+            CAST(SUM(m.tokens) AS bigint) AS tokens,
+            COUNT(CASE WHEN f.rating = ? THEN 1 END) AS up,
+            COUNT(CASE WHEN f.rating = ? THEN 1 END) AS down
+        FROM message m
+        JOIN prompt_variant v ON v.id = m.prompt_variant_id
+        LEFT JOIN feedback f ON f.message_id = m.id
+        WHERE v.model_id = ? AND m.kind = ?
+        GROUP BY 1",
+        [
+            (MessageKind::Assistant as i32).into(),
+            (FeedbackRating::Up as i32).into(),
+            (FeedbackRating::Down as i32).into(),
+            model_id.into(),
+            (MessageKind::Assistant as i32).into(),
+        ],
+    ))
+    .all(conn)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|x| {
+            let stats = ModelVariantStats {
+                chats: x.chats,
+                replies: x.replies,
+                regenerated: x.regenerated,
+                truncated: x.truncated,
+                tokens: x.tokens,
+                up: x.up,
+                down: x.down,
+            };
+            (x.id, stats)
+        })
+        .collect())
+}
@@ -1,21 +1,26 @@
 use std::sync::Arc;
 
 use axum::{Extension, Json, extract::State};
-use entity::model;
+use entity::{AuditKind, model};
+use schemars::JsonSchema;
 use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-use crate::{AppState, errors::*, middlewares::auth::UserId};
+use crate::{
+    AppState, audit,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct ModelWriteReq {
     pub id: i32,
     pub config: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct ModelWriteResp {
     display_name: String,
@@ -25,6 +30,7 @@ pub struct ModelWriteResp {
 pub async fn route(
     State(app): State<Arc<AppState>>,
     Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
     Json(req): Json<ModelWriteReq>,
 ) -> JsonResult<ModelWriteResp> {
     let config = req.config;
@@ -46,6 +52,11 @@ pub async fn route(
         .kind(ErrorKind::ResourceNotFound)?;
 
     let wrote = result.rows_affected > 0;
+    if wrote {
+        app.cache.models.clear();
+        let detail = format!("model {} ({}) written", req.id, display_name);
+        audit::record(&app.conn, AuditKind::Config, Some(user_id), detail).await;
+    }
 
     Ok(Json(ModelWriteResp {
         display_name,
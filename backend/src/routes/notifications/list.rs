@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Query, State},
+};
+use entity::InboxSource;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, config::INBOX_PAGE, errors::*, middlewares::auth::UserId, notify::inbox};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct NotificationListReq {
+    /// Cursor, exclusive, `next` of the previous page
+    pub before: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct NotificationListResp {
+    /// Latest first
+    pub list: Vec<NotificationListRespItem>,
+    /// Unread messages of the whole inbox
+    pub unread: u64,
+    /// `before` of the next page, None once the inbox is exhausted
+    pub next: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct NotificationListRespItem {
+    pub id: i32,
+    pub source: InboxSource,
+    /// Markdown
+    pub content: String,
+    /// Chat the message is about
+    pub chat_id: Option<i32>,
+    pub created_at: i64,
+    pub read: bool,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Query(req): Query<NotificationListReq>,
+) -> JsonResult<NotificationListResp> {
+    let messages = inbox::list(&app.conn, user_id, req.before, INBOX_PAGE)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let unread = inbox::unread(&app.conn, user_id)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let next = match messages.len() as u64 >= INBOX_PAGE {
+        true => messages.last().map(|x| x.id),
+        false => None,
+    };
+    let list = messages
+        .into_iter()
+        .map(|x| NotificationListRespItem {
+            id: x.id,
+            source: x.source,
+            content: x.content,
+            chat_id: x.chat_id,
+            created_at: x.created_at,
+            read: x.read_at.is_some(),
+        })
+        .collect();
+    Ok(Json(NotificationListResp { list, unread, next }))
+}
@@ -0,0 +1,19 @@
+//! Inbox of the user, see `notify::inbox`
+
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    routing::{get, post},
+};
+
+use crate::AppState;
+
+mod list;
+mod read;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list::route))
+        .route("/read", post(read::route))
+}
@@ -0,0 +1,35 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId, notify::inbox};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct NotificationReadReq {
+    /// None to mark the whole inbox
+    pub ids: Option<Vec<i32>>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct NotificationReadResp {
+    /// Unread messages left
+    pub unread: u64,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<NotificationReadReq>,
+) -> JsonResult<NotificationReadResp> {
+    inbox::read(&app.conn, user_id, req.ids)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let unread = inbox::unread(&app.conn, user_id)
+        .await
+        .kind(ErrorKind::Internal)?;
+    Ok(Json(NotificationReadResp { unread }))
+}
@@ -0,0 +1,550 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    Extension, Json,
+    extract::State,
+    http::HeaderMap,
+    response::{
+        IntoResponse, Response, Sse,
+        sse::{Event, KeepAlive},
+    },
+};
+use entity::{ChunkKind, MessageKind, chat, chunk, message, prelude::*};
+use futures_util::{StreamExt, stream};
+use sea_orm::{ActiveValue::Set, ConnectionTrait, DbErr, EntityTrait, TransactionTrait};
+use serde::{Deserialize, Serialize};
+
+use super::models::offered;
+use crate::{
+    AppState,
+    config::{COMPLETIONS_MAX_MESSAGES, SSE_KEEP_ALIVE, SYSTEM_PROMPT_MAX_CHARS},
+    errors::*,
+    middlewares::auth::{ApiKeyUser, UserId, WorkspaceId},
+    routes::message::create::{self, MessageCreateReq, MessageCreateReqMode},
+    sse::{Subscriber, Token},
+    utils::{branch, member},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct CompletionReq {
+    /// `model_id` or display name of a model offered in the workspace,
+    /// ignored when continuing a chat
+    pub model: String,
+    pub messages: Vec<CompletionReqMessage>,
+    #[serde(default)]
+    pub stream: bool,
+    /// Not in OpenAI: continue this chat, only the last message is sent
+    pub chat_id: Option<i32>,
+    /// Not in OpenAI: default to normal
+    pub mode: Option<MessageCreateReqMode>,
+    // sampling params are left to the model config, like in the web app
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompletionReqMessage {
+    pub role: CompletionReqRole,
+    /// null on the assistant messages with tool calls
+    #[serde(default)]
+    pub content: Option<CompletionReqContent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompletionReqRole {
+    System,
+    Developer,
+    User,
+    Assistant,
+    /// Tools of the client are not supported, their output is skipped
+    Tool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum CompletionReqContent {
+    Text(String),
+    Parts(Vec<CompletionReqPart>),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompletionReqPart {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompletionResp {
+    pub id: String,
+    pub object: &'static str,
+    pub created: i64,
+    pub model: String,
+    pub choices: Vec<CompletionRespChoice>,
+    pub usage: CompletionUsage,
+    /// Not in OpenAI: chat of the reply, to continue it
+    pub chat_id: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompletionRespChoice {
+    pub index: u32,
+    pub message: CompletionRespMessage,
+    pub finish_reason: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompletionRespMessage {
+    pub role: &'static str,
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_content: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompletionChunk {
+    pub id: String,
+    pub object: &'static str,
+    pub created: i64,
+    pub model: String,
+    pub choices: Vec<CompletionChunkChoice>,
+    /// On the last chunk only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<CompletionUsage>,
+    pub chat_id: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompletionChunkChoice {
+    pub index: u32,
+    pub delta: CompletionDelta,
+    pub finish_reason: Option<&'static str>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CompletionDelta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_content: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CompletionUsage {
+    /// Prompt of the last completion of the tool loop
+    pub prompt_tokens: i64,
+    /// Every completion of the tool loop
+    pub completion_tokens: i64,
+    pub total_tokens: i64,
+}
+
+/// Send the last user message of the request and answer with the reply, as
+/// `chat.completion` or as a stream of `chat.completion.chunk`
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    api_key: Option<Extension<ApiKeyUser>>,
+    Json(req): Json<CompletionReq>,
+) -> Result<Response, Json<Error>> {
+    if req.messages.len() > COMPLETIONS_MAX_MESSAGES {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!(
+                "a request carry at most {} messages",
+                COMPLETIONS_MAX_MESSAGES
+            ),
+        }));
+    }
+    let mut system = vec![];
+    let mut turns = vec![];
+    for message in req.messages {
+        let text = match message.content {
+            Some(content) => content.text()?,
+            None => continue,
+        };
+        match message.role {
+            CompletionReqRole::System | CompletionReqRole::Developer => system.push(text),
+            CompletionReqRole::User => turns.push((MessageKind::User, text)),
+            CompletionReqRole::Assistant => turns.push((MessageKind::Assistant, text)),
+            CompletionReqRole::Tool => {}
+        }
+    }
+    let Some((MessageKind::User, text)) = turns.pop() else {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "the last message must be from the user".to_owned(),
+        }));
+    };
+
+    let (chat_id, created_here) = match req.chat_id {
+        Some(chat_id) => {
+            member::find(&app.conn, chat_id, user_id, workspace_id).await?;
+            (chat_id, false)
+        }
+        None => {
+            let system_prompt = system_prompt(&app, system)?;
+            let model_id = find_model(&app, workspace_id, &req.model).await?;
+            let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+            let chat_id = insert(&txn, user_id, workspace_id, model_id, system_prompt, turns)
+                .await
+                .kind(ErrorKind::Internal)?;
+            txn.commit().await.kind(ErrorKind::Internal)?;
+            (chat_id, true)
+        }
+    };
+
+    // subscribed before sending, so no token of the reply is missed
+    let sub = app
+        .sse
+        .subscribe(chat_id, None)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let sent = create::route(
+        State(app.clone()),
+        Extension(UserId(user_id)),
+        Extension(WorkspaceId(workspace_id)),
+        api_key,
+        HeaderMap::new(),
+        Json(MessageCreateReq {
+            chat_id,
+            mode: req.mode.unwrap_or(MessageCreateReqMode::Normal),
+            text,
+            files: vec![],
+        }),
+    )
+    .await;
+    let Json(sent) = match sent {
+        Ok(x) => x,
+        Err(err) => {
+            // refused before anything was sent, the chat would stay empty
+            if created_here && let Err(err) = Chat::delete_by_id(chat_id).exec(&app.conn).await {
+                tracing::warn!("cannot delete chat {}: {}", chat_id, err);
+            }
+            return Err(err);
+        }
+    };
+
+    let follow = Follow {
+        app,
+        sub,
+        user_message_id: sent.id,
+        started: false,
+        id: format!("chatcmpl-{}", sent.id),
+        created: time::UtcDateTime::now().unix_timestamp(),
+        model: req.model,
+        chat_id,
+    };
+    match req.stream {
+        true => Ok(stream(follow)),
+        false => Ok(Json(collect(follow).await?).into_response()),
+    }
+}
+
+impl CompletionReqContent {
+    /// Text parts joined, other parts are refused
+    fn text(self) -> Result<String, Json<Error>> {
+        let parts = match self {
+            CompletionReqContent::Text(text) => return Ok(text),
+            CompletionReqContent::Parts(parts) => parts,
+        };
+        let mut text = vec![];
+        for part in parts {
+            match (part.kind.as_str(), part.text) {
+                ("text", Some(x)) => text.push(x),
+                (kind, _) => {
+                    return Err(Json(Error {
+                        error: ErrorKind::MalformedRequest,
+                        reason: format!("content of type {} is not supported", kind),
+                    }));
+                }
+            }
+        }
+        Ok(text.join("\n"))
+    }
+}
+
+/// System messages follow the built-in prompt, checked like the one of
+/// `chat/{id}/settings`
+fn system_prompt(app: &AppState, system: Vec<String>) -> Result<Option<String>, Json<Error>> {
+    let prompt = system.join("\n\n");
+    let prompt = prompt.trim();
+    if prompt.is_empty() {
+        return Ok(None);
+    }
+    if prompt.chars().count() > SYSTEM_PROMPT_MAX_CHARS {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!(
+                "System prompt is longer than {} characters",
+                SYSTEM_PROMPT_MAX_CHARS
+            ),
+        }));
+    }
+    app.prompt.check(prompt).map_err(|e| {
+        Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!("Malformed template: {}", e),
+        })
+    })?;
+    Ok(Some(prompt.to_owned()))
+}
+
+/// The first offered model whose `model_id` or display name is `name`
+async fn find_model(app: &AppState, workspace_id: i32, name: &str) -> Result<i32, Json<Error>> {
+    offered(app, workspace_id)
+        .await?
+        .into_iter()
+        .find(|(_, config)| config.model_id == name || config.display_name == name)
+        .map(|(id, _)| id)
+        .ok_or_else(|| {
+            Json(Error {
+                error: ErrorKind::ResourceNotFound,
+                reason: format!("model {} is not offered in this workspace", name),
+            })
+        })
+}
+
+/// A chat with the earlier messages of the request, see `chat/import`
+async fn insert(
+    conn: &impl ConnectionTrait,
+    user_id: i32,
+    workspace_id: i32,
+    model_id: i32,
+    system_prompt: Option<String>,
+    turns: Vec<(MessageKind, String)>,
+) -> Result<i32, DbErr> {
+    let chat_id = Chat::insert(chat::ActiveModel {
+        owner_id: Set(user_id),
+        workspace_id: Set(workspace_id),
+        model_id: Set(model_id),
+        reproducible: Set(false),
+        system_prompt: Set(system_prompt),
+        ..Default::default()
+    })
+    .exec(conn)
+    .await?
+    .last_insert_id;
+
+    let now = time::UtcDateTime::now().unix_timestamp();
+    for (kind, content) in turns {
+        let message_id = branch::append(
+            conn,
+            chat_id,
+            message::ActiveModel {
+                kind: Set(kind),
+                created_at: Set(now),
+                author_id: Set((kind == MessageKind::User).then_some(user_id)),
+                ..Default::default()
+            },
+        )
+        .await
+        .map_err(|e| DbErr::Custom(e.to_string()))?;
+        Chunk::insert(chunk::ActiveModel {
+            content: Set(content),
+            kind: Set(ChunkKind::Text),
+            message_id: Set(message_id),
+            ..Default::default()
+        })
+        .exec(conn)
+        .await?;
+    }
+    Ok(chat_id)
+}
+
+/// The reply to one user message, read off the stream of its chat
+struct Follow {
+    app: Arc<AppState>,
+    sub: Subscriber,
+    user_message_id: i32,
+    /// Tokens before the user message belong to an earlier reply
+    started: bool,
+    id: String,
+    created: i64,
+    model: String,
+    chat_id: i32,
+}
+
+enum Piece {
+    Content(String),
+    Reasoning(String),
+    End(&'static str, CompletionUsage),
+    Failed(Error),
+}
+
+impl Follow {
+    async fn next(&mut self) -> Piece {
+        while let Some((_, token)) = self.sub.next().await {
+            let token = match token {
+                Ok(token) => token,
+                Err(err) if self.started => return Piece::Failed(err),
+                Err(_) => continue,
+            };
+            match token {
+                Token::UserMessage(id, ..) if id == self.user_message_id => self.started = true,
+                _ if !self.started => {}
+                Token::Token(x) => return Piece::Content(x),
+                Token::ReasoningToken(x) => return Piece::Reasoning(x),
+                Token::Meta(message_id, meta) => {
+                    return match usage(&self.app, self.chat_id, message_id).await {
+                        Ok(usage) => Piece::End(finish_reason(&meta.finish_reason), usage),
+                        Err(Json(err)) => Piece::Failed(err),
+                    };
+                }
+                Token::Lagged => break,
+                _ => {}
+            }
+        }
+        Piece::Failed(Error {
+            error: ErrorKind::Internal,
+            reason: "the reply stream was lost".to_owned(),
+        })
+    }
+
+    fn chunk(
+        &self,
+        delta: CompletionDelta,
+        finish_reason: Option<&'static str>,
+        usage: Option<CompletionUsage>,
+    ) -> CompletionChunk {
+        CompletionChunk {
+            id: self.id.clone(),
+            object: "chat.completion.chunk",
+            created: self.created,
+            model: self.model.clone(),
+            choices: vec![CompletionChunkChoice {
+                index: 0,
+                delta,
+                finish_reason,
+            }],
+            usage,
+            chat_id: self.chat_id,
+        }
+    }
+}
+
+/// Tokens of the reply, recorded before its `Meta` token
+async fn usage(
+    app: &AppState,
+    chat_id: i32,
+    message_id: i32,
+) -> Result<CompletionUsage, Json<Error>> {
+    let completion_tokens = Message::find_by_id(message_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .map(|x| x.tokens)
+        .unwrap_or_default();
+    let prompt_tokens = ContextStat::find_by_id(chat_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .map(|x| x.last_prompt_tokens)
+        .unwrap_or_default();
+    Ok(CompletionUsage {
+        prompt_tokens,
+        completion_tokens,
+        total_tokens: prompt_tokens + completion_tokens,
+    })
+}
+
+/// Ours are `halt` and `truncated`, clients know the ones of OpenAI only
+fn finish_reason(reason: &str) -> &'static str {
+    match reason {
+        "length" | "truncated" => "length",
+        "content_filter" => "content_filter",
+        _ => "stop",
+    }
+}
+
+async fn collect(mut follow: Follow) -> Result<CompletionResp, Json<Error>> {
+    let mut content = String::new();
+    let mut reasoning = String::new();
+    let (finish_reason, usage) = loop {
+        match follow.next().await {
+            Piece::Content(x) => content.push_str(&x),
+            Piece::Reasoning(x) => reasoning.push_str(&x),
+            Piece::End(finish_reason, usage) => break (finish_reason, usage),
+            Piece::Failed(err) => return Err(Json(err)),
+        }
+    };
+    Ok(CompletionResp {
+        id: follow.id,
+        object: "chat.completion",
+        created: follow.created,
+        model: follow.model,
+        choices: vec![CompletionRespChoice {
+            index: 0,
+            message: CompletionRespMessage {
+                role: "assistant",
+                content,
+                reasoning_content: Some(reasoning).filter(|x| !x.is_empty()),
+            },
+            finish_reason,
+        }],
+        usage,
+        chat_id: follow.chat_id,
+    })
+}
+
+enum Phase {
+    Start(Follow),
+    Follow(Follow),
+    Done,
+}
+
+/// Chunks as server-sent events ending with `[DONE]`, an error is sent as
+/// an event of its own and ends the stream
+fn stream(follow: Follow) -> Response {
+    let st = stream::unfold(Some(Phase::Start(follow)), |phase| async move {
+        let event = Event::default();
+        match phase? {
+            Phase::Start(follow) => {
+                let delta = CompletionDelta {
+                    role: Some("assistant"),
+                    content: Some(String::new()),
+                    ..Default::default()
+                };
+                let event = event.json_data(follow.chunk(delta, None, None));
+                Some((event, Some(Phase::Follow(follow))))
+            }
+            Phase::Follow(mut follow) => match follow.next().await {
+                Piece::Content(x) => {
+                    let delta = CompletionDelta {
+                        content: Some(x),
+                        ..Default::default()
+                    };
+                    let event = event.json_data(follow.chunk(delta, None, None));
+                    Some((event, Some(Phase::Follow(follow))))
+                }
+                Piece::Reasoning(x) => {
+                    let delta = CompletionDelta {
+                        reasoning_content: Some(x),
+                        ..Default::default()
+                    };
+                    let event = event.json_data(follow.chunk(delta, None, None));
+                    Some((event, Some(Phase::Follow(follow))))
+                }
+                Piece::End(finish_reason, usage) => {
+                    let chunk = follow.chunk(Default::default(), Some(finish_reason), Some(usage));
+                    Some((event.json_data(chunk), Some(Phase::Done)))
+                }
+                Piece::Failed(err) => Some((event.json_data(ErrorEvent { error: err }), None)),
+            },
+            Phase::Done => Some((Ok(event.data("[DONE]")), None)),
+        }
+    });
+    let sse = Sse::new(st).keep_alive(
+        KeepAlive::new()
+            .interval(Duration::from_secs(SSE_KEEP_ALIVE))
+            .text("ping"),
+    );
+    // nginx buffer the response by default, see `chat/sse`
+    ([("x-accel-buffering", "no")], sse).into_response()
+}
+
+#[derive(Serialize)]
+struct ErrorEvent {
+    error: Error,
+}
@@ -0,0 +1,25 @@
+//! OpenAI-compatible routes, so clients and scripts made for OpenAI use
+//! `https://{host}/api/v1` as their base URL with an API key of the `chat`
+//! scope
+//!
+//! A completion is a real chat: the messages of the request start a new one
+//! and the last user message is sent like from the web app, with the same
+//! prompt, tools, quota, spending caps and moderation. The chat stays in the
+//! list of the user, and can be continued by passing its `chat_id` back
+mod completions;
+mod models;
+
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    routing::{get, post},
+};
+
+use crate::AppState;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/chat/completions", post(completions::route))
+        .route("/models", get(models::route))
+}
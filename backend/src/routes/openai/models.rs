@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use serde::Serialize;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::WorkspaceId,
+    utils::{model, workspace},
+};
+
+#[derive(Debug, Serialize)]
+pub struct ModelsResp {
+    pub object: &'static str,
+    pub data: Vec<ModelsRespModel>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModelsRespModel {
+    /// `model_id` of the config, the display name is accepted as well
+    pub id: String,
+    pub object: &'static str,
+    pub created: i64,
+    pub owned_by: &'static str,
+}
+
+/// Models offered in the workspace of the key
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+) -> JsonResult<ModelsResp> {
+    let offered = offered(&app, workspace_id).await?;
+    let mut data: Vec<ModelsRespModel> = Vec::with_capacity(offered.len());
+    for (_, config) in offered {
+        // configs of the same upstream model with other params share an id
+        if data.iter().any(|x| x.id == config.model_id) {
+            continue;
+        }
+        data.push(ModelsRespModel {
+            id: config.model_id,
+            object: "model",
+            created: 0,
+            owned_by: "llumen",
+        });
+    }
+    Ok(Json(ModelsResp {
+        object: "list",
+        data,
+    }))
+}
+
+/// Id and config of every model the workspace offers
+pub(super) async fn offered(
+    app: &AppState,
+    workspace_id: i32,
+) -> Result<Vec<(i32, entity::ModelConfig)>, Json<Error>> {
+    let allowed = workspace::models(&app.conn, workspace_id)
+        .await
+        .kind(ErrorKind::Internal)?;
+    Ok(model::all(app)
+        .await
+        .kind(ErrorKind::Internal)?
+        .iter()
+        .filter(|m| allowed.as_ref().is_none_or(|x| x.contains(&m.id)))
+        .filter_map(|m| Some((m.id, m.get_config()?)))
+        .collect())
+}
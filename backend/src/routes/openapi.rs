@@ -0,0 +1,56 @@
+//! Machine-readable contract of the chat, message, user, model and auth
+//! routes, served without authentication
+//!
+//! Each module describes its routes next to them with a `spec` function; the
+//! document is built once on the first request. Dev builds also serve Swagger
+//! UI at `/api/docs`, loading its assets from a CDN
+
+use std::sync::{Arc, LazyLock};
+
+use axum::{Json, Router, routing::get};
+use serde_json::Value;
+
+use crate::{AppState, routes, utils::openapi::Builder};
+
+static SPEC: LazyLock<Value> = LazyLock::new(|| {
+    let mut api = Builder::new();
+    routes::auth::spec(&mut api);
+    routes::chat::spec(&mut api);
+    routes::message::spec(&mut api);
+    routes::model::spec(&mut api);
+    routes::user::spec(&mut api);
+    api.build("llumen", env!("CARGO_PKG_VERSION"))
+});
+
+pub fn routes() -> Router<Arc<AppState>> {
+    let router = Router::new().route("/openapi.json", get(spec));
+    #[cfg(feature = "dev")]
+    let router = router.route("/docs", get(docs));
+    router
+}
+
+async fn spec() -> Json<Value> {
+    Json(SPEC.clone())
+}
+
+#[cfg(feature = "dev")]
+async fn docs() -> axum::response::Html<&'static str> {
+    axum::response::Html(
+        r##"<!doctype html>
+<html>
+  <head>
+    <meta charset="utf-8" />
+    <title>llumen API</title>
+    <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css" />
+  </head>
+  <body>
+    <div id="swagger-ui"></div>
+    <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+    <script>
+      SwaggerUIBundle({ url: "/api/openapi.json", dom_id: "#swagger-ui" });
+    </script>
+  </body>
+</html>
+"##,
+    )
+}
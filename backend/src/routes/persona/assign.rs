@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{chat, persona, prelude::*};
+use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+    utils::member,
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct PersonaAssignReq {
+    pub chat_id: i32,
+    /// None to go back to the built-in prompt
+    pub persona_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PersonaAssignResp {
+    /// Model of the chat, the one of the persona if it has one
+    pub model_id: i32,
+}
+
+/// Apply to the next reply; only owners of the chat pick its persona, among
+/// their own
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Json(req): Json<PersonaAssignReq>,
+) -> JsonResult<PersonaAssignResp> {
+    let chat = member::owned(&app.conn, req.chat_id, user_id, workspace_id).await?;
+
+    let mut model = chat::ActiveModel {
+        id: Set(chat.id),
+        persona_id: Set(req.persona_id),
+        ..Default::default()
+    };
+    let mut model_id = chat.model_id;
+    if let Some(persona_id) = req.persona_id {
+        let persona = Persona::find_by_id(persona_id)
+            .filter(persona::Column::OwnerId.eq(user_id))
+            .one(&app.conn)
+            .await
+            .kind(ErrorKind::Internal)?
+            .ok_or("Cannot find the persona")
+            .kind(ErrorKind::ResourceNotFound)?;
+        if let Some(x) = persona.model_id {
+            model_id = x;
+            model.model_id = Set(x);
+        }
+    }
+    Chat::update(model)
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    Ok(Json(PersonaAssignResp { model_id }))
+}
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{persona, prelude::*};
+use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, config::PERSONA_MAX_PER_USER, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct PersonaCreateReq {
+    pub name: String,
+    /// Follow the built-in prompt, a template with its variables, e.g.
+    /// `{{ user.name }}`
+    pub prompt: String,
+    /// Model chats created with the persona start on
+    pub model_id: Option<i32>,
+    /// Tools offered in its chats, None for every tool of the mode
+    pub tools: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PersonaCreateResp {
+    pub id: i32,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<PersonaCreateReq>,
+) -> JsonResult<PersonaCreateResp> {
+    let name = super::name(&req.name)?;
+    let prompt = super::prompt(&app, &req.prompt)?;
+    let tools = super::tools(&app, req.tools)?;
+    if let Some(model_id) = req.model_id {
+        super::model_exists(&app.conn, model_id).await?;
+    }
+
+    let count = Persona::find()
+        .filter(persona::Column::OwnerId.eq(user_id))
+        .count(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    if count >= PERSONA_MAX_PER_USER {
+        return Err(super::malformed(format!(
+            "a user has at most {} personas",
+            PERSONA_MAX_PER_USER
+        )));
+    }
+
+    let id = Persona::insert(persona::ActiveModel {
+        owner_id: Set(user_id),
+        name: Set(name),
+        prompt: Set(prompt),
+        model_id: Set(req.model_id),
+        tools: Set(tools),
+        created_at: Set(time::UtcDateTime::now().unix_timestamp()),
+        ..Default::default()
+    })
+    .exec(&app.conn)
+    .await
+    .kind(ErrorKind::Internal)?
+    .last_insert_id;
+
+    Ok(Json(PersonaCreateResp { id }))
+}
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{chat, persona, prelude::*};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, TransactionTrait, sea_query::Expr};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct PersonaDeleteReq {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PersonaDeleteResp {
+    /// false if the persona does not exist
+    pub deleted: bool,
+}
+
+/// Its chats go back to the built-in prompt
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<PersonaDeleteReq>,
+) -> JsonResult<PersonaDeleteResp> {
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+    let res = Persona::delete_many()
+        .filter(persona::Column::Id.eq(req.id))
+        .filter(persona::Column::OwnerId.eq(user_id))
+        .exec(&txn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    if res.rows_affected > 0 {
+        Chat::update_many()
+            .col_expr(chat::Column::PersonaId, Expr::value(Option::<i32>::None))
+            .filter(chat::Column::PersonaId.eq(req.id))
+            .exec(&txn)
+            .await
+            .kind(ErrorKind::Internal)?;
+    }
+    txn.commit().await.kind(ErrorKind::Internal)?;
+
+    Ok(Json(PersonaDeleteResp {
+        deleted: res.rows_affected > 0,
+    }))
+}
@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{persona, prelude::*};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId, tools};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct PersonaListReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PersonaListResp {
+    /// Oldest first
+    pub list: Vec<PersonaListRespItem>,
+    /// Tools a persona can keep, those of the agent mode
+    pub available_tools: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PersonaListRespItem {
+    pub id: i32,
+    pub name: String,
+    pub prompt: String,
+    /// None once the model is deleted
+    pub model_id: Option<i32>,
+    /// None for every tool of the mode
+    pub tools: Option<Vec<String>>,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(_): Json<PersonaListReq>,
+) -> JsonResult<PersonaListResp> {
+    let list = Persona::find()
+        .filter(persona::Column::OwnerId.eq(user_id))
+        .order_by_asc(persona::Column::Id)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .into_iter()
+        .map(|x| PersonaListRespItem {
+            id: x.id,
+            name: x.name,
+            prompt: x.prompt,
+            model_id: x.model_id,
+            tools: x.tools.map(|x| x.0),
+        })
+        .collect();
+    let available_tools = app
+        .tools
+        .names(tools::AGENT)
+        .into_iter()
+        .map(str::to_owned)
+        .collect();
+    Ok(Json(PersonaListResp {
+        list,
+        available_tools,
+    }))
+}
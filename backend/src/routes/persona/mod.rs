@@ -0,0 +1,84 @@
+//! Personas a user writes once and picks for a chat, their prompt follows the
+//! built-in prompt, see `message::create`
+
+use std::sync::Arc;
+
+use axum::{Json, Router, routing::post};
+use entity::{ToolNames, prelude::*};
+use sea_orm::{DbConn, EntityTrait};
+
+use crate::{
+    AppState,
+    config::{PERSONA_NAME_MAX_CHARS, PERSONA_PROMPT_MAX_CHARS},
+    errors::*,
+};
+
+mod assign;
+mod create;
+mod delete;
+mod list;
+mod write;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/create", post(create::route))
+        .route("/list", post(list::route))
+        .route("/write", post(write::route))
+        .route("/delete", post(delete::route))
+        .route("/assign", post(assign::route))
+}
+
+fn malformed(reason: String) -> Json<Error> {
+    Json(Error {
+        error: ErrorKind::MalformedRequest,
+        reason,
+    })
+}
+
+/// Trimmed name of a persona
+fn name(x: &str) -> Result<String, Json<Error>> {
+    let x = x.trim();
+    if x.is_empty() || x.chars().count() > PERSONA_NAME_MAX_CHARS {
+        return Err(malformed(format!(
+            "a name has 1 to {} characters",
+            PERSONA_NAME_MAX_CHARS
+        )));
+    }
+    Ok(x.to_owned())
+}
+
+/// Trimmed prompt, a template with the variables of the built-in prompt
+fn prompt(app: &AppState, x: &str) -> Result<String, Json<Error>> {
+    let x = x.trim();
+    if x.chars().count() > PERSONA_PROMPT_MAX_CHARS {
+        return Err(malformed(format!(
+            "a prompt has at most {} characters",
+            PERSONA_PROMPT_MAX_CHARS
+        )));
+    }
+    app.prompt
+        .check(x)
+        .map_err(|e| malformed(format!("Malformed template: {}", e)))?;
+    Ok(x.to_owned())
+}
+
+/// Known tools of the list, None for every tool of the mode
+fn tools(app: &AppState, x: Option<Vec<String>>) -> Result<Option<ToolNames>, Json<Error>> {
+    let Some(names) = x else {
+        return Ok(None);
+    };
+    if let Some(unknown) = names.iter().find(|x| !app.tools.has(x)) {
+        return Err(malformed(format!("unknown tool {}", unknown)));
+    }
+    Ok(Some(ToolNames(names)))
+}
+
+async fn model_exists(conn: &DbConn, model_id: i32) -> Result<(), Json<Error>> {
+    Model::find_by_id(model_id)
+        .one(conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Cannot find the model")
+        .kind(ErrorKind::ResourceNotFound)?;
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{persona, prelude::*};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter,
+};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct PersonaWriteReq {
+    pub id: i32,
+    pub name: Option<String>,
+    pub prompt: Option<String>,
+    pub model_id: Option<i32>,
+    /// An empty list is no tool, see `tools_all` for every tool of the mode
+    pub tools: Option<Vec<String>>,
+    /// Offer every tool of the mode again
+    #[serde(default)]
+    pub tools_all: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PersonaWriteResp {}
+
+/// Fields left None are kept, chats of the persona use it from their next
+/// reply
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<PersonaWriteReq>,
+) -> JsonResult<PersonaWriteResp> {
+    let persona = Persona::find_by_id(req.id)
+        .filter(persona::Column::OwnerId.eq(user_id))
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Cannot find the persona")
+        .kind(ErrorKind::ResourceNotFound)?;
+
+    let mut model = persona.into_active_model();
+    if let Some(name) = req.name {
+        model.name = Set(super::name(&name)?);
+    }
+    if let Some(prompt) = req.prompt {
+        model.prompt = Set(super::prompt(&app, &prompt)?);
+    }
+    if let Some(model_id) = req.model_id {
+        super::model_exists(&app.conn, model_id).await?;
+        model.model_id = Set(Some(model_id));
+    }
+    if req.tools_all {
+        model.tools = Set(None);
+    } else if req.tools.is_some() {
+        model.tools = Set(super::tools(&app, req.tools)?);
+    }
+    model.update(&app.conn).await.kind(ErrorKind::Internal)?;
+
+    Ok(Json(PersonaWriteResp {}))
+}
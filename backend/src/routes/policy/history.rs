@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{policy, prelude::*};
+use sea_orm::{EntityTrait, QueryOrder, QuerySelect};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, config::MAX_PAGINATE_LIMIT, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct PolicyHistoryReq {
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PolicyHistoryResp {
+    /// newest first
+    pub list: Vec<PolicyHistoryRespList>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PolicyHistoryRespList {
+    pub version: i32,
+    pub content: String,
+    /// missing if the author was deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// unix timestamp in seconds
+    pub created_at: u32,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    Json(req): Json<PolicyHistoryReq>,
+) -> JsonResult<PolicyHistoryResp> {
+    let limit = req
+        .limit
+        .unwrap_or(MAX_PAGINATE_LIMIT)
+        .min(MAX_PAGINATE_LIMIT);
+    let res = Policy::find()
+        .order_by_desc(policy::Column::Id)
+        .limit(limit as u64)
+        .find_also_related(User)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let list = res
+        .into_iter()
+        .map(|(policy, author)| PolicyHistoryRespList {
+            version: policy.id,
+            content: policy.content,
+            author: author.map(|x| x.name),
+            created_at: policy.created_at as u32,
+        })
+        .collect();
+
+    Ok(Json(PolicyHistoryResp { list }))
+}
@@ -0,0 +1,16 @@
+mod history;
+mod read;
+mod write;
+
+use std::sync::Arc;
+
+use axum::{Router, routing::post};
+
+use crate::AppState;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/read", post(read::route))
+        .route("/write", post(write::route))
+        .route("/history", post(history::route))
+}
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{policy, prelude::*};
+use sea_orm::{EntityTrait, QueryOrder};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct PolicyReadReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PolicyReadResp {
+    /// missing if no policy was ever written
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<i32>,
+    pub content: String,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    Json(_): Json<PolicyReadReq>,
+) -> JsonResult<PolicyReadResp> {
+    let policy = Policy::find()
+        .order_by_desc(policy::Column::Id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    Ok(Json(match policy {
+        Some(policy) => PolicyReadResp {
+            version: Some(policy.id),
+            content: policy.content,
+        },
+        None => PolicyReadResp {
+            version: None,
+            content: "".to_owned(),
+        },
+    }))
+}
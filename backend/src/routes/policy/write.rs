@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{AuditKind, policy, prelude::*};
+use sea_orm::{ActiveValue::Set, EntityTrait};
+use serde::{Deserialize, Serialize};
+use time::UtcDateTime;
+use typeshare::typeshare;
+
+use crate::{
+    AppState, audit,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct PolicyWriteReq {
+    /// empty to disable the policy
+    pub content: String,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PolicyWriteResp {
+    pub version: i32,
+}
+
+/// Every write is kept as a new version for audit
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<PolicyWriteReq>,
+) -> JsonResult<PolicyWriteResp> {
+    let version = Policy::insert(policy::ActiveModel {
+        content: Set(req.content),
+        author_id: Set(Some(user_id)),
+        created_at: Set(UtcDateTime::now().unix_timestamp()),
+        ..Default::default()
+    })
+    .exec(&app.conn)
+    .await
+    .kind(ErrorKind::Internal)?
+    .last_insert_id;
+
+    let detail = format!("policy version {} written", version);
+    audit::record(&app.conn, AuditKind::Config, Some(user_id), detail).await;
+
+    Ok(Json(PolicyWriteResp { version }))
+}
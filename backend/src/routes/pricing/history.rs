@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{prelude::*, price};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, config::MAX_PAGINATE_LIMIT, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct PricingHistoryReq {
+    pub model_id: String,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PricingHistoryResp {
+    /// newest first
+    pub list: Vec<PricingHistoryRespList>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PricingHistoryRespList {
+    /// USD per token
+    pub prompt: f64,
+    pub completion: f64,
+    /// unix timestamp in seconds, effective until the next entry
+    pub effective_at: u32,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    Json(req): Json<PricingHistoryReq>,
+) -> JsonResult<PricingHistoryResp> {
+    let limit = req
+        .limit
+        .unwrap_or(MAX_PAGINATE_LIMIT)
+        .min(MAX_PAGINATE_LIMIT);
+    let res = Price::find()
+        .filter(price::Column::ModelId.eq(req.model_id))
+        .order_by_desc(price::Column::EffectiveAt)
+        .limit(limit as u64)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let list = res
+        .into_iter()
+        .map(|x| PricingHistoryRespList {
+            prompt: x.prompt,
+            completion: x.completion,
+            effective_at: x.effective_at as u32,
+        })
+        .collect();
+
+    Ok(Json(PricingHistoryResp { list }))
+}
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct PricingListReq {
+    /// only these upstream model ids, every synced model if missing
+    pub model_ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PricingListResp {
+    pub list: Vec<PricingListRespList>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PricingListRespList {
+    pub model_id: String,
+    /// USD per token
+    pub prompt: f64,
+    pub completion: f64,
+    /// unix timestamp in seconds
+    pub effective_at: u32,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    Json(req): Json<PricingListReq>,
+) -> JsonResult<PricingListResp> {
+    let list = app
+        .pricing
+        .list()
+        .into_iter()
+        .filter(|(id, _)| req.model_ids.as_ref().is_none_or(|x| x.contains(id)))
+        .map(|(model_id, rate)| PricingListRespList {
+            model_id,
+            prompt: rate.prompt,
+            completion: rate.completion,
+            effective_at: rate.effective_at as u32,
+        })
+        .collect();
+
+    Ok(Json(PricingListResp { list }))
+}
@@ -0,0 +1,14 @@
+mod history;
+mod list;
+
+use std::sync::Arc;
+
+use axum::{Router, routing::post};
+
+use crate::AppState;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/list", post(list::route))
+        .route("/history", post(history::route))
+}
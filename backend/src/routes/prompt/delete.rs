@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{AuditKind, prelude::*};
+use sea_orm::EntityTrait;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState, audit,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct PromptDeleteReq {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PromptDeleteResp {
+    pub deleted: bool,
+}
+
+/// The built-in prompt is used again
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<PromptDeleteReq>,
+) -> JsonResult<PromptDeleteResp> {
+    let res = PromptTemplate::delete_by_id(req.id)
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    app.prompt.invalidate();
+    if res.rows_affected > 0 {
+        let detail = format!("prompt template {} deleted", req.id);
+        audit::record(&app.conn, AuditKind::Config, Some(user_id), detail).await;
+    }
+    Ok(Json(PromptDeleteResp {
+        deleted: res.rows_affected > 0,
+    }))
+}
@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{prelude::*, prompt_template};
+use sea_orm::{EntityTrait, QueryOrder};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+    prompts,
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct PromptListReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PromptListResp {
+    /// Built-in prompts a template can replace
+    pub names: Vec<String>,
+    pub locales: Vec<String>,
+    /// Variables a template can use
+    pub variables: Vec<PromptVariable>,
+    pub list: Vec<PromptListRespItem>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[typeshare]
+pub struct PromptVariable {
+    /// path in the template, e.g. `user.name`
+    pub name: String,
+    pub kind: String,
+    pub description: String,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PromptListRespItem {
+    pub id: i32,
+    pub name: String,
+    /// None for every locale
+    pub locale: Option<String>,
+    /// None for every workspace
+    pub workspace_id: Option<i32>,
+    /// missing if the author was deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// unix seconds
+    pub updated_at: i64,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
+    Json(_): Json<PromptListReq>,
+) -> JsonResult<PromptListResp> {
+    let list = PromptTemplate::find()
+        .order_by_asc(prompt_template::Column::Name)
+        .order_by_asc(prompt_template::Column::Locale)
+        .order_by_asc(prompt_template::Column::WorkspaceId)
+        .find_also_related(User)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .into_iter()
+        .map(|(x, author)| PromptListRespItem {
+            id: x.id,
+            name: x.name,
+            locale: x.locale,
+            workspace_id: x.workspace_id,
+            author: author.map(|x| x.name),
+            updated_at: x.updated_at,
+        })
+        .collect();
+
+    Ok(Json(PromptListResp {
+        names: prompts::NAMES.map(str::to_owned).to_vec(),
+        locales: prompts::LOCALES.map(str::to_owned).to_vec(),
+        variables: prompts::VARIABLES
+            .map(|(name, kind, description)| PromptVariable {
+                name: name.to_owned(),
+                kind: kind.to_owned(),
+                description: description.to_owned(),
+            })
+            .to_vec(),
+        list,
+    }))
+}
@@ -0,0 +1,52 @@
+//! Templates admins write in place of the built-in prompts, see
+//! `prompts::PromptEnv::template`. Every write is kept as a version an admin
+//! can go back to
+
+mod delete;
+mod list;
+mod pin;
+mod preview;
+mod read;
+mod versions;
+mod write;
+
+use std::sync::Arc;
+
+use axum::{Json, Router, routing::post};
+
+use crate::{AppState, errors::*, prompts};
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/list", post(list::route))
+        .route("/read", post(read::route))
+        .route("/write", post(write::route))
+        .route("/delete", post(delete::route))
+        .route("/versions", post(versions::route))
+        .route("/pin", post(pin::route))
+        .route("/preview", post(preview::route))
+}
+
+fn malformed(reason: String) -> Json<Error> {
+    Json(Error {
+        error: ErrorKind::MalformedRequest,
+        reason,
+    })
+}
+
+/// The name and locale of a template, if the built-in prompts have them
+fn key(name: &str, locale: Option<&str>) -> Result<(), Json<Error>> {
+    if !prompts::NAMES.contains(&name) {
+        return Err(malformed(format!(
+            "a template is named one of {}",
+            prompts::NAMES.join(", ")
+        )));
+    }
+    if locale.is_some_and(|x| !prompts::LOCALES.contains(&x)) {
+        return Err(malformed(format!(
+            "a template is in one of {} or every locale",
+            prompts::LOCALES.join(", ")
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{AuditKind, prelude::*, prompt_template};
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, EntityTrait};
+use serde::{Deserialize, Serialize};
+use time::UtcDateTime;
+use typeshare::typeshare;
+
+use crate::{
+    AppState, audit,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct PromptPinReq {
+    /// id of the template
+    pub id: i32,
+    /// One of its versions, e.g. an earlier one to roll back to
+    pub version_id: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PromptPinResp {}
+
+/// Use a version of the template from the next reply, later versions are kept
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<PromptPinReq>,
+) -> JsonResult<PromptPinResp> {
+    let version = PromptTemplateVersion::find_by_id(req.version_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .filter(|x| x.template_id == req.id)
+        .ok_or("")
+        .kind(ErrorKind::ResourceNotFound)?;
+
+    prompt_template::ActiveModel {
+        id: Set(req.id),
+        content: Set(version.content),
+        version_id: Set(Some(version.id)),
+        updated_at: Set(UtcDateTime::now().unix_timestamp()),
+        ..Default::default()
+    }
+    .update(&app.conn)
+    .await
+    .kind(ErrorKind::Internal)?;
+    app.prompt.invalidate();
+
+    let detail = format!(
+        "version {} of prompt template {} pinned",
+        version.id, req.id
+    );
+    audit::record(&app.conn, AuditKind::Config, Some(user_id), detail).await;
+
+    Ok(Json(PromptPinResp {}))
+}
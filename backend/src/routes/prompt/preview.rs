@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::prelude::*;
+use sea_orm::EntityTrait;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+    prompts::{self, PromptStore},
+    tools,
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct PromptPreviewReq {
+    /// decide the tools whose hints are listed
+    pub name: String,
+    pub content: String,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PromptPreviewResp {
+    pub rendered: String,
+}
+
+/// Render a template for the admin, in a chat of sample values
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<PromptPreviewReq>,
+) -> JsonResult<PromptPreviewResp> {
+    super::key(&req.name, None)?;
+    app.prompt
+        .check_variables(&req.content)
+        .map_err(super::malformed)?;
+    let user = User::find_by_id(user_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Cannot find user")
+        .kind(ErrorKind::Internal)?;
+
+    let tool_set = match req.name.as_str() {
+        prompts::SearchStore::NAME => tools::SEARCH,
+        prompts::AgentStore::NAME | prompts::DelegateStore::NAME => tools::AGENT,
+        _ => tools::NORMAL,
+    };
+    let (hints, _) = app.tools.list(tool_set);
+    let rendered = app
+        .prompt
+        .preview(&req.content, &user, hints)
+        .map_err(|err| super::malformed(format!("{:#}", err)))?;
+
+    Ok(Json(PromptPreviewResp { rendered }))
+}
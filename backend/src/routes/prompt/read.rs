@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{prelude::*, prompt_template};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+    prompts::{self, PromptStore},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct PromptReadReq {
+    pub name: String,
+    /// None for every locale
+    pub locale: Option<String>,
+    /// None for every workspace
+    #[serde(default)]
+    pub workspace_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PromptReadResp {
+    /// The prompt shipped with the server, in the locale or the default one
+    pub builtin: String,
+    /// missing if no template replace it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<PromptReadRespTemplate>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PromptReadRespTemplate {
+    pub id: i32,
+    /// Content of the version in use
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<i32>,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<PromptReadReq>,
+) -> JsonResult<PromptReadResp> {
+    super::key(&req.name, req.locale.as_deref())?;
+
+    let locale = req.locale.as_deref();
+    let builtin = match req.name.as_str() {
+        prompts::SearchStore::NAME => builtin(&prompts::SearchStore, locale).await,
+        prompts::AgentStore::NAME => builtin(&prompts::AgentStore, locale).await,
+        prompts::TitleGenStore::NAME => builtin(&prompts::TitleGenStore, locale).await,
+        prompts::DelegateStore::NAME => builtin(&prompts::DelegateStore, locale).await,
+        _ => builtin(&prompts::ChatStore, locale).await,
+    }
+    .kind(ErrorKind::Internal)?;
+
+    let template = PromptTemplate::find()
+        .filter(prompt_template::Column::Name.eq(&req.name))
+        .filter(match &req.locale {
+            Some(locale) => prompt_template::Column::Locale.eq(locale),
+            None => prompt_template::Column::Locale.is_null(),
+        })
+        .filter(match req.workspace_id {
+            Some(workspace_id) => prompt_template::Column::WorkspaceId.eq(workspace_id),
+            None => prompt_template::Column::WorkspaceId.is_null(),
+        })
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .map(|x| PromptReadRespTemplate {
+            id: x.id,
+            content: x.content,
+            version_id: x.version_id,
+        });
+
+    Ok(Json(PromptReadResp { builtin, template }))
+}
+
+async fn builtin<S>(store: &S, locale: Option<&str>) -> anyhow::Result<String>
+where
+    S: PromptStore<Source = &'static str>,
+    S::Extra: Serialize,
+    S::Pipe: Serialize,
+{
+    Ok(store.template(locale).await?.source().to_owned())
+}
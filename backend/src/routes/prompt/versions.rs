@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{prelude::*, prompt_template_version};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct PromptVersionsReq {
+    /// id of the template
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PromptVersionsResp {
+    /// Newest first
+    pub list: Vec<PromptVersionsRespItem>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PromptVersionsRespItem {
+    pub id: i32,
+    pub content: String,
+    /// missing if the author was deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// unix seconds
+    pub created_at: i64,
+    /// Whether the template uses this version
+    pub active: bool,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<PromptVersionsReq>,
+) -> JsonResult<PromptVersionsResp> {
+    let template = PromptTemplate::find_by_id(req.id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("")
+        .kind(ErrorKind::ResourceNotFound)?;
+
+    let list = PromptTemplateVersion::find()
+        .filter(prompt_template_version::Column::TemplateId.eq(req.id))
+        .order_by_desc(prompt_template_version::Column::Id)
+        .find_also_related(User)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .into_iter()
+        .map(|(x, author)| PromptVersionsRespItem {
+            id: x.id,
+            active: template.version_id == Some(x.id),
+            content: x.content,
+            author: author.map(|x| x.name),
+            created_at: x.created_at,
+        })
+        .collect();
+
+    Ok(Json(PromptVersionsResp { list }))
+}
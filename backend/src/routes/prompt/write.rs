@@ -0,0 +1,126 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{AuditKind, prelude::*, prompt_template, prompt_template_version};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use time::UtcDateTime;
+use typeshare::typeshare;
+
+use crate::{
+    AppState, audit,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct PromptWriteReq {
+    pub name: String,
+    /// None for every locale without a template of its own
+    pub locale: Option<String>,
+    /// minijinja template, see `variables` of `prompt/list`
+    pub content: String,
+    /// None for every workspace without a template of its own
+    #[serde(default)]
+    pub workspace_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct PromptWriteResp {
+    pub id: i32,
+    /// The new version, now in use
+    pub version_id: i32,
+}
+
+/// Save the content as a new version of the template of the name, locale and
+/// workspace and use it, the template is created on its first version
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<PromptWriteReq>,
+) -> JsonResult<PromptWriteResp> {
+    super::key(&req.name, req.locale.as_deref())?;
+    if req.content.trim().is_empty() {
+        return Err(super::malformed(
+            "delete the template to use the built-in prompt".to_owned(),
+        ));
+    }
+    app.prompt
+        .check_variables(&req.content)
+        .map_err(super::malformed)?;
+
+    if let Some(workspace_id) = req.workspace_id {
+        Workspace::find_by_id(workspace_id)
+            .one(&app.conn)
+            .await
+            .kind(ErrorKind::Internal)?
+            .ok_or("Cannot find workspace")
+            .kind(ErrorKind::ResourceNotFound)?;
+    }
+
+    let now = UtcDateTime::now().unix_timestamp();
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+    let existing = PromptTemplate::find()
+        .filter(prompt_template::Column::Name.eq(&req.name))
+        .filter(match &req.locale {
+            Some(locale) => prompt_template::Column::Locale.eq(locale),
+            None => prompt_template::Column::Locale.is_null(),
+        })
+        .filter(match req.workspace_id {
+            Some(workspace_id) => prompt_template::Column::WorkspaceId.eq(workspace_id),
+            None => prompt_template::Column::WorkspaceId.is_null(),
+        })
+        .one(&txn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let template = match existing {
+        Some(x) => x,
+        None => prompt_template::ActiveModel {
+            name: Set(req.name.clone()),
+            locale: Set(req.locale.clone()),
+            workspace_id: Set(req.workspace_id),
+            content: Set(req.content.clone()),
+            author_id: Set(Some(user_id)),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await
+        .kind(ErrorKind::Internal)?,
+    };
+    let id = template.id;
+
+    let version_id = PromptTemplateVersion::insert(prompt_template_version::ActiveModel {
+        template_id: Set(id),
+        content: Set(req.content.clone()),
+        author_id: Set(Some(user_id)),
+        created_at: Set(now),
+        ..Default::default()
+    })
+    .exec(&txn)
+    .await
+    .kind(ErrorKind::Internal)?
+    .last_insert_id;
+
+    let mut model: prompt_template::ActiveModel = template.into();
+    model.content = Set(req.content);
+    model.author_id = Set(Some(user_id));
+    model.updated_at = Set(now);
+    model.version_id = Set(Some(version_id));
+    model.update(&txn).await.kind(ErrorKind::Internal)?;
+    txn.commit().await.kind(ErrorKind::Internal)?;
+    app.prompt.invalidate();
+
+    let detail = format!(
+        "version {} of prompt template {} ({:?}, workspace {:?}) written",
+        version_id, req.name, req.locale, req.workspace_id
+    );
+    audit::record(&app.conn, AuditKind::Config, Some(user_id), detail).await;
+
+    Ok(Json(PromptWriteResp { id, version_id }))
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{prelude::*, schedule};
+use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, config::SCHEDULE_MAX_PER_USER, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct ScheduleCreateReq {
+    pub name: String,
+    /// Sent to the agent on every run
+    pub prompt: String,
+    /// `minute hour day-of-month month day-of-week`, e.g. `0 8 * * *`
+    pub cron: String,
+    /// Minutes ahead of UTC the expression is read in, e.g. 480 in Taipei,
+    /// default to the `utc_offset` of the user's preference, else 0
+    pub utc_offset: Option<i32>,
+    pub model_id: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct ScheduleCreateResp {
+    pub id: i32,
+    /// unix seconds
+    pub next_run_at: i64,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<ScheduleCreateReq>,
+) -> JsonResult<ScheduleCreateResp> {
+    let utc_offset = match req.utc_offset {
+        Some(x) => x,
+        None => User::find_by_id(user_id)
+            .one(&app.conn)
+            .await
+            .kind(ErrorKind::Internal)?
+            .and_then(|x| x.preference.utc_offset)
+            .unwrap_or(0),
+    };
+    let name = super::name(&req.name)?;
+    let prompt = super::prompt(&req.prompt)?;
+    let (cron, next_run_at) = super::cron(&req.cron, utc_offset)?;
+    super::model_exists(&app.conn, req.model_id).await?;
+
+    let count = Schedule::find()
+        .filter(schedule::Column::OwnerId.eq(user_id))
+        .count(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    if count >= SCHEDULE_MAX_PER_USER {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!("a user has at most {} tasks", SCHEDULE_MAX_PER_USER),
+        }));
+    }
+
+    let id = Schedule::insert(schedule::ActiveModel {
+        owner_id: Set(user_id),
+        model_id: Set(req.model_id),
+        name: Set(name),
+        prompt: Set(prompt),
+        cron: Set(cron),
+        utc_offset: Set(utc_offset),
+        enabled: Set(true),
+        next_run_at: Set(Some(next_run_at)),
+        created_at: Set(time::UtcDateTime::now().unix_timestamp()),
+        ..Default::default()
+    })
+    .exec(&app.conn)
+    .await
+    .kind(ErrorKind::Internal)?
+    .last_insert_id;
+
+    Ok(Json(ScheduleCreateResp { id, next_run_at }))
+}
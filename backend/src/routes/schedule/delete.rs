@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{prelude::*, schedule};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct ScheduleDeleteReq {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct ScheduleDeleteResp {
+    /// false if the task does not exist
+    pub deleted: bool,
+}
+
+/// The chat of the task is kept
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<ScheduleDeleteReq>,
+) -> JsonResult<ScheduleDeleteResp> {
+    let res = Schedule::delete_many()
+        .filter(schedule::Column::Id.eq(req.id))
+        .filter(schedule::Column::OwnerId.eq(user_id))
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    Ok(Json(ScheduleDeleteResp {
+        deleted: res.rows_affected > 0,
+    }))
+}
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{prelude::*, schedule};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct ScheduleListReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct ScheduleListResp {
+    /// Oldest first
+    pub list: Vec<ScheduleListRespItem>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct ScheduleListRespItem {
+    pub id: i32,
+    pub name: String,
+    pub prompt: String,
+    pub cron: String,
+    /// Minutes ahead of UTC the expression is read in
+    pub utc_offset: i32,
+    pub model_id: i32,
+    /// Chat the runs post in, None until the first run
+    pub chat_id: Option<i32>,
+    pub enabled: bool,
+    /// unix seconds
+    pub next_run_at: Option<i64>,
+    pub last_run_at: Option<i64>,
+    /// Why the last run could not start
+    pub last_error: Option<String>,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(_): Json<ScheduleListReq>,
+) -> JsonResult<ScheduleListResp> {
+    let list = Schedule::find()
+        .filter(schedule::Column::OwnerId.eq(user_id))
+        .order_by_asc(schedule::Column::Id)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .into_iter()
+        .map(|x| ScheduleListRespItem {
+            id: x.id,
+            name: x.name,
+            prompt: x.prompt,
+            cron: x.cron,
+            utc_offset: x.utc_offset,
+            model_id: x.model_id,
+            chat_id: x.chat_id,
+            enabled: x.enabled,
+            next_run_at: x.next_run_at,
+            last_run_at: x.last_run_at,
+            last_error: x.last_error,
+        })
+        .collect();
+    Ok(Json(ScheduleListResp { list }))
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use axum::{Json, Router, routing::post};
+use entity::prelude::*;
+use sea_orm::{DbConn, EntityTrait};
+
+use crate::{
+    AppState,
+    config::{SCHEDULE_NAME_MAX_CHARS, SCHEDULE_PROMPT_MAX_CHARS},
+    errors::*,
+    schedule,
+    utils::cron::Cron,
+};
+
+mod create;
+mod delete;
+mod list;
+mod run;
+mod write;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/create", post(create::route))
+        .route("/list", post(list::route))
+        .route("/write", post(write::route))
+        .route("/delete", post(delete::route))
+        .route("/run", post(run::route))
+}
+
+fn malformed(reason: String) -> Json<Error> {
+    Json(Error {
+        error: ErrorKind::MalformedRequest,
+        reason,
+    })
+}
+
+/// Trimmed name of a task
+fn name(x: &str) -> Result<String, Json<Error>> {
+    let x = x.trim();
+    if x.is_empty() || x.chars().count() > SCHEDULE_NAME_MAX_CHARS {
+        return Err(malformed(format!(
+            "a name has 1 to {} characters",
+            SCHEDULE_NAME_MAX_CHARS
+        )));
+    }
+    Ok(x.to_owned())
+}
+
+fn prompt(x: &str) -> Result<String, Json<Error>> {
+    let x = x.trim();
+    if x.is_empty() || x.chars().count() > SCHEDULE_PROMPT_MAX_CHARS {
+        return Err(malformed(format!(
+            "a prompt has 1 to {} characters",
+            SCHEDULE_PROMPT_MAX_CHARS
+        )));
+    }
+    Ok(x.to_owned())
+}
+
+/// Normalized expression and when it match next, read `utc_offset` minutes
+/// ahead of UTC
+fn cron(x: &str, utc_offset: i32) -> Result<(String, i64), Json<Error>> {
+    if utc_offset.abs() > 14 * 60 {
+        return Err(malformed("utc_offset is out of -840 to 840".to_owned()));
+    }
+    Cron::parse(x).map_err(|err| malformed(format!("malformed cron: {:#}", err)))?;
+    let next = schedule::next_run(x, utc_offset)
+        .ok_or_else(|| malformed("the cron expression never match".to_owned()))?;
+    Ok((x.split_whitespace().collect::<Vec<_>>().join(" "), next))
+}
+
+async fn model_exists(conn: &DbConn, model_id: i32) -> Result<(), Json<Error>> {
+    Model::find_by_id(model_id)
+        .one(conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Cannot find the model")
+        .kind(ErrorKind::ResourceNotFound)?;
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{prelude::*, schedule};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct ScheduleRunReq {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct ScheduleRunResp {
+    /// Chat the prompt was sent in, None if the run could not start
+    pub chat_id: Option<i32>,
+    /// Why the run could not start
+    pub error: Option<String>,
+}
+
+/// Run the task now, its next run is unchanged
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<ScheduleRunReq>,
+) -> JsonResult<ScheduleRunResp> {
+    let task = Schedule::find_by_id(req.id)
+        .filter(schedule::Column::OwnerId.eq(user_id))
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Cannot find the task")
+        .kind(ErrorKind::ResourceNotFound)?;
+    let (chat_id, error) = crate::schedule::run(&app, task)
+        .await
+        .kind(ErrorKind::Internal)?;
+    Ok(Json(ScheduleRunResp { chat_id, error }))
+}
@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{prelude::*, schedule};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter,
+};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct ScheduleWriteReq {
+    pub id: i32,
+    pub name: Option<String>,
+    pub prompt: Option<String>,
+    pub cron: Option<String>,
+    pub utc_offset: Option<i32>,
+    pub model_id: Option<i32>,
+    /// Enabling a task schedule its next run from now
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct ScheduleWriteResp {
+    /// unix seconds, None while disabled
+    pub next_run_at: Option<i64>,
+}
+
+/// Fields left None are kept
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<ScheduleWriteReq>,
+) -> JsonResult<ScheduleWriteResp> {
+    let task = Schedule::find_by_id(req.id)
+        .filter(schedule::Column::OwnerId.eq(user_id))
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Cannot find the task")
+        .kind(ErrorKind::ResourceNotFound)?;
+
+    let utc_offset = req.utc_offset.unwrap_or(task.utc_offset);
+    let (cron, next_run_at) = super::cron(req.cron.as_deref().unwrap_or(&task.cron), utc_offset)?;
+    let enabled = req.enabled.unwrap_or(task.enabled);
+    let next_run_at = enabled.then_some(next_run_at);
+
+    let mut model = task.into_active_model();
+    if let Some(name) = req.name {
+        model.name = Set(super::name(&name)?);
+    }
+    if let Some(prompt) = req.prompt {
+        model.prompt = Set(super::prompt(&prompt)?);
+    }
+    if let Some(model_id) = req.model_id {
+        super::model_exists(&app.conn, model_id).await?;
+        model.model_id = Set(model_id);
+    }
+    model.cron = Set(cron);
+    model.utc_offset = Set(utc_offset);
+    model.enabled = Set(enabled);
+    model.next_run_at = Set(next_run_at);
+    model.update(&app.conn).await.kind(ErrorKind::Internal)?;
+
+    Ok(Json(ScheduleWriteResp { next_run_at }))
+}
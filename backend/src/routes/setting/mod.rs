@@ -0,0 +1,14 @@
+mod read;
+mod write;
+
+use std::sync::Arc;
+
+use axum::{Router, routing::post};
+
+use crate::AppState;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/read", post(read::route))
+        .route("/write", post(write::route))
+}
@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState, errors::*, middlewares::auth::UserId, retention::RetentionPolicy, tools::ToolSource,
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct SettingReadReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct SettingReadResp {
+    pub tools_disabled: bool,
+    pub disabled_sources: Vec<ToolSource>,
+    pub retention: RetentionPolicy,
+    /// Days of `RETENTION_DAYS`, what the default policy keep
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retention_default_days: Option<u32>,
+    /// Days idle chats are kept under the policy, none for forever; users may
+    /// choose fewer for theirs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retention_days: Option<u32>,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(_)): Extension<UserId>,
+    Json(_): Json<SettingReadReq>,
+) -> JsonResult<SettingReadResp> {
+    Ok(Json(SettingReadResp {
+        tools_disabled: app.tools.disabled(),
+        disabled_sources: app.tools.disabled_sources(),
+        retention: app.retention.policy(),
+        retention_default_days: app.retention.default_days(),
+        retention_days: app.retention.days(),
+    }))
+}
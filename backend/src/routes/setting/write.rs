@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::AuditKind;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState, audit,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+    retention::RetentionPolicy,
+    tools::ToolSource,
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct SettingWriteReq {
+    /// Stop advertising tools to every model, for incident response
+    pub tools_disabled: Option<bool>,
+    /// Stop advertising the tools of these sources, the others stay
+    pub disabled_sources: Option<Vec<ToolSource>>,
+    /// Owners of idle chats are mailed before they are deleted
+    pub retention: Option<RetentionPolicy>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct SettingWriteResp {
+    pub wrote: bool,
+}
+
+/// Organization-wide settings, missing fields are left unchanged
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    _: AdminOnly,
+    Json(req): Json<SettingWriteReq>,
+) -> JsonResult<SettingWriteResp> {
+    if req.retention == Some(RetentionPolicy::Days(0)) {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "retention must be at least a day".to_owned(),
+        }));
+    }
+
+    let mut wrote = false;
+
+    if let Some(disabled) = req.tools_disabled.filter(|x| *x != app.tools.disabled()) {
+        app.tools
+            .set_disabled(disabled)
+            .await
+            .kind(ErrorKind::Internal)?;
+        let detail = format!("tools {}", if disabled { "disabled" } else { "enabled" });
+        audit::record(&app.conn, AuditKind::Config, Some(user_id), detail).await;
+        wrote = true;
+    }
+
+    if let Some(mut sources) = req.disabled_sources {
+        sources.sort_by_key(|x| *x as u8);
+        sources.dedup();
+        if sources != app.tools.disabled_sources() {
+            app.tools
+                .set_disabled_sources(sources.clone())
+                .await
+                .kind(ErrorKind::Internal)?;
+            let detail = format!("tool sources {:?} disabled", sources);
+            audit::record(&app.conn, AuditKind::Config, Some(user_id), detail).await;
+            wrote = true;
+        }
+    }
+
+    if let Some(policy) = req.retention.filter(|x| *x != app.retention.policy()) {
+        app.retention
+            .set_policy(policy)
+            .await
+            .kind(ErrorKind::Internal)?;
+        let detail = format!("retention set to {:?}", policy);
+        audit::record(&app.conn, AuditKind::Config, Some(user_id), detail).await;
+        wrote = true;
+    }
+
+    Ok(Json(SettingWriteResp { wrote }))
+}
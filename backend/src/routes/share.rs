@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::header,
+    response::IntoResponse,
+};
+use entity::{chat, prelude::*};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+use crate::{AppState, errors::*, utils::export::Transcript};
+
+/// Ids start at 1, so the private messages of the owner are left out
+const ANONYMOUS: i32 = 0;
+
+/// Read-only transcript of a chat shared with `/api/chat/{id}/share`, without
+/// authentication
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Path(token): Path<String>,
+) -> Result<impl IntoResponse, Json<Error>> {
+    let chat = Chat::find()
+        .filter(chat::Column::ShareToken.eq(token))
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("")
+        .kind(ErrorKind::ResourceNotFound)?;
+
+    let transcript = Transcript::load(&app.conn, app.instance_id.clone(), &chat, ANONYMOUS)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/html; charset=utf-8"),
+            // a revoked link must not be served from a cache
+            (header::CACHE_CONTROL, "no-store"),
+            (header::REFERRER_POLICY, "no-referrer"),
+            (header::HeaderName::from_static("x-robots-tag"), "noindex"),
+        ],
+        transcript.html(),
+    ))
+}
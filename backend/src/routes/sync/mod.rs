@@ -0,0 +1,32 @@
+//! Differential sync for clients working offline
+//!
+//! Every write to chats, messages and preferences is recorded in
+//! `sync_change` by triggers, its ids are the cursors
+
+mod read;
+mod write;
+
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    routing::{get, post},
+};
+use serde::Serialize;
+use typeshare::typeshare;
+
+use crate::AppState;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(read::route))
+        .route("/write", post(write::route))
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum SyncEntityKind {
+    Chat,
+    Message,
+}
@@ -0,0 +1,182 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    Extension, Json,
+    extract::{Query, State},
+};
+use entity::{SyncEntity, UserPreference, chat, message, prelude::*, sync_change};
+use sea_orm::{JoinType, QueryOrder, QuerySelect, RelationTrait, prelude::*};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use super::SyncEntityKind;
+use crate::{
+    AppState,
+    config::SYNC_MAX_CHANGES,
+    errors::*,
+    middlewares::auth::UserId,
+    routes::message::paginate::{MessagePaginateRespList, load_list},
+    utils,
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct SyncReadReq {
+    /// `cursor` of the previous sync, default to everything
+    pub since: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct SyncReadResp {
+    /// `since` of the next sync and `base` of writes made on top of this one
+    pub cursor: i32,
+    /// Changes past `cursor` were left out, sync again right away
+    pub more: bool,
+    /// Created or changed since `since`
+    pub chats: Vec<SyncReadRespChat>,
+    pub messages: Vec<SyncReadRespMessage>,
+    /// Only when changed
+    pub preference: Option<UserPreference>,
+    /// Tombstones, messages of a deleted chat are not listed
+    pub deleted: Vec<SyncReadRespDeleted>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct SyncReadRespChat {
+    pub id: i32,
+    pub model_id: i32,
+    pub title: Option<String>,
+    pub reproducible: bool,
+    /// last message of the active branch, None for the latest message
+    pub head_id: Option<i32>,
+    pub pinned: bool,
+    pub archived_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct SyncReadRespMessage {
+    pub chat_id: i32,
+    pub message: MessagePaginateRespList,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct SyncReadRespDeleted {
+    pub entity: SyncEntityKind,
+    pub id: i32,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Query(req): Query<SyncReadReq>,
+) -> JsonResult<SyncReadResp> {
+    let since = req.since.unwrap_or(0);
+    let mut changes = SyncChange::find()
+        .filter(sync_change::Column::UserId.eq(user_id))
+        .filter(sync_change::Column::Id.gt(since))
+        .order_by_asc(sync_change::Column::Id)
+        .limit(SYNC_MAX_CHANGES + 1)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let more = changes.len() as u64 > SYNC_MAX_CHANGES;
+    changes.truncate(SYNC_MAX_CHANGES as usize);
+    let cursor = changes.last().map(|x| x.id).unwrap_or(since);
+
+    let mut deleted = vec![];
+    let mut chat_ids = vec![];
+    let mut message_ids = vec![];
+    let mut preference = false;
+    for change in changes {
+        match (change.entity, change.deleted) {
+            (SyncEntity::Chat, true) => deleted.push(SyncReadRespDeleted {
+                entity: SyncEntityKind::Chat,
+                id: change.entity_id,
+            }),
+            (SyncEntity::Message, true) => deleted.push(SyncReadRespDeleted {
+                entity: SyncEntityKind::Message,
+                id: change.entity_id,
+            }),
+            (SyncEntity::Chat, false) => chat_ids.push(change.entity_id),
+            (SyncEntity::Message, false) => message_ids.push(change.entity_id),
+            (SyncEntity::Preference, _) => preference = true,
+        }
+    }
+
+    let chats = Chat::find()
+        .filter(chat::Column::Id.is_in(chat_ids.clone()))
+        .filter(chat::Column::OwnerId.eq(user_id))
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let messages = Message::find()
+        .join(JoinType::InnerJoin, message::Relation::Chat.def())
+        .filter(message::Column::Id.is_in(message_ids.clone()))
+        .filter(chat::Column::OwnerId.eq(user_id))
+        // private messages of members are gone for the owner
+        .filter(utils::message::visible_to(user_id))
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    // gone without a tombstone, e.g. messages removed with their chat
+    for id in chat_ids {
+        if !chats.iter().any(|x| x.id == id) {
+            deleted.push(SyncReadRespDeleted {
+                entity: SyncEntityKind::Chat,
+                id,
+            });
+        }
+    }
+    for id in message_ids {
+        if !messages.iter().any(|x| x.id == id) {
+            deleted.push(SyncReadRespDeleted {
+                entity: SyncEntityKind::Message,
+                id,
+            });
+        }
+    }
+
+    let chat_of: HashMap<i32, i32> = messages.iter().map(|x| (x.id, x.chat_id)).collect();
+    let messages = load_list(&app.conn, messages)
+        .await?
+        .into_iter()
+        .map(|message| SyncReadRespMessage {
+            chat_id: chat_of[&message.id],
+            message,
+        })
+        .collect();
+
+    let preference = match preference {
+        true => User::find_by_id(user_id)
+            .one(&app.conn)
+            .await
+            .kind(ErrorKind::Internal)?
+            .map(|x| x.preference),
+        false => None,
+    };
+
+    Ok(Json(SyncReadResp {
+        cursor,
+        more,
+        chats: chats
+            .into_iter()
+            .map(|x| SyncReadRespChat {
+                id: x.id,
+                model_id: x.model_id,
+                title: x.title,
+                reproducible: x.reproducible,
+                head_id: x.head_id,
+                pinned: x.pinned,
+                archived_at: x.archived_at,
+            })
+            .collect(),
+        messages,
+        preference,
+        deleted,
+    }))
+}
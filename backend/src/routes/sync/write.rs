@@ -0,0 +1,396 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{SyncEntity, UserPreference, chat, prelude::*, sync_change};
+use sea_orm::{ActiveValue::Set, ConnectionTrait, IntoActiveModel, TransactionTrait, prelude::*};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::UserId,
+    trash,
+    undo::{self, Snapshot},
+    utils,
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct SyncWriteReq {
+    /// `cursor` of the last sync before the client went offline
+    pub base: i32,
+    /// Applied in order
+    pub ops: Vec<SyncWriteReqOp>,
+}
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+#[serde(tag = "t", content = "c", rename_all = "snake_case")]
+pub enum SyncWriteReqOp {
+    ChatTitle(SyncWriteReqChatTitle),
+    ChatDelete(SyncWriteReqChatDelete),
+    /// Unset fields are kept
+    Preference(UserPreference),
+}
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct SyncWriteReqChatTitle {
+    pub id: i32,
+    pub title: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct SyncWriteReqChatDelete {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct SyncWriteResp {
+    /// One per op, in order
+    pub results: Vec<SyncWriteRespResult>,
+    /// Restore every chat deleted by the batch, see `ChatDeleteResp`
+    pub undo_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum SyncWriteRespResult {
+    Applied,
+    /// Changed on the server after `base`, the server copy is kept and
+    /// arrives with the next sync
+    Conflict,
+    NotFound,
+    /// The merged value is not valid, nothing is written
+    Rejected,
+}
+
+/// Apply changes made offline, ops on entities changed on the server since
+/// `base`, e.g. from another device, lose
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<SyncWriteReq>,
+) -> JsonResult<SyncWriteResp> {
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+    let batch = apply(&txn, user_id, req).await?;
+    let trash = trash::put(&txn, user_id, batch.deleted)
+        .await
+        .kind(ErrorKind::Internal)?;
+    txn.commit().await.kind(ErrorKind::Internal)?;
+    if batch
+        .written
+        .iter()
+        .any(|(x, _)| *x == SyncEntity::Preference)
+    {
+        app.cache.forget_user(user_id);
+    }
+    let undo_token = app.undo.stage(user_id, trash).kind(ErrorKind::Internal)?;
+    Ok(Json(SyncWriteResp {
+        results: batch.results,
+        undo_token,
+    }))
+}
+
+/// Ops of a batch written in `conn`, left to commit
+struct Batch {
+    results: Vec<SyncWriteRespResult>,
+    written: Vec<(SyncEntity, i32)>,
+    /// Chats deleted, for the trash
+    deleted: Vec<Snapshot>,
+}
+
+async fn apply(
+    conn: &impl ConnectionTrait,
+    user_id: i32,
+    req: SyncWriteReq,
+) -> Result<Batch, Json<Error>> {
+    // earlier ops of the batch are not conflicts of the later ones
+    let mut written: Vec<(SyncEntity, i32)> = vec![];
+    let mut results = vec![];
+    let mut deleted = vec![];
+    for op in req.ops {
+        let target = match &op {
+            SyncWriteReqOp::ChatTitle(x) => (SyncEntity::Chat, x.id),
+            SyncWriteReqOp::ChatDelete(x) => (SyncEntity::Chat, x.id),
+            SyncWriteReqOp::Preference(_) => (SyncEntity::Preference, user_id),
+        };
+        if !written.contains(&target) {
+            let changed = SyncChange::find()
+                .filter(sync_change::Column::UserId.eq(user_id))
+                .filter(sync_change::Column::Entity.eq(target.0))
+                .filter(sync_change::Column::EntityId.eq(target.1))
+                .filter(sync_change::Column::Id.gt(req.base))
+                .one(conn)
+                .await
+                .kind(ErrorKind::Internal)?;
+            if changed.is_some() {
+                results.push(SyncWriteRespResult::Conflict);
+                continue;
+            }
+        }
+
+        let applied = match op {
+            SyncWriteReqOp::ChatTitle(x) => {
+                Chat::update_many()
+                    .col_expr(chat::Column::Title, Expr::value(x.title))
+                    .filter(chat::Column::Id.eq(x.id))
+                    .filter(chat::Column::OwnerId.eq(user_id))
+                    .exec(conn)
+                    .await
+                    .kind(ErrorKind::Internal)?
+                    .rows_affected
+                    > 0
+            }
+            SyncWriteReqOp::ChatDelete(x) => {
+                deleted.extend(
+                    undo::snapshot_chat(conn, user_id, x.id)
+                        .await
+                        .kind(ErrorKind::Internal)?,
+                );
+                Chat::delete_many()
+                    .filter(chat::Column::Id.eq(x.id))
+                    .filter(chat::Column::OwnerId.eq(user_id))
+                    .exec(conn)
+                    .await
+                    .kind(ErrorKind::Internal)?
+                    .rows_affected
+                    > 0
+            }
+            SyncWriteReqOp::Preference(x) => {
+                let user = User::find_by_id(user_id)
+                    .one(conn)
+                    .await
+                    .kind(ErrorKind::Internal)?
+                    .ok_or("")
+                    .kind(ErrorKind::ResourceNotFound)?;
+                let mut preference = user.preference.clone();
+                preference.theme = x.theme.or(preference.theme);
+                preference.locale = x.locale.or(preference.locale);
+                preference.submit_on_enter = x.submit_on_enter.or(preference.submit_on_enter);
+                preference.voice = x.voice.or(preference.voice);
+                preference.voice_speed = x.voice_speed.or(preference.voice_speed);
+                preference.retention_days = x.retention_days.or(preference.retention_days);
+                preference.timezone = x.timezone.or(preference.timezone);
+                preference.utc_offset = x.utc_offset.or(preference.utc_offset);
+                preference.default_model_id = x.default_model_id.or(preference.default_model_id);
+                preference.notify_replies = x.notify_replies.or(preference.notify_replies);
+                preference.notify_schedules = x.notify_schedules.or(preference.notify_schedules);
+                if !utils::preference::problems(&preference).is_empty() {
+                    results.push(SyncWriteRespResult::Rejected);
+                    continue;
+                }
+                let version = user.preference_version + 1;
+                let mut user = user.into_active_model();
+                user.preference = Set(preference);
+                user.preference_version = Set(version);
+                user.update(conn).await.kind(ErrorKind::Internal)?;
+                true
+            }
+        };
+        results.push(match applied {
+            true => {
+                written.push(target);
+                SyncWriteRespResult::Applied
+            }
+            false => SyncWriteRespResult::NotFound,
+        });
+    }
+
+    Ok(Batch {
+        results,
+        written,
+        deleted,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::user;
+    use migration::MigratorTrait;
+    use sea_orm::{Database, DatabaseConnection, QueryOrder};
+
+    use super::*;
+    use SyncWriteRespResult::*;
+
+    /// The admin and the model of a new database are 1
+    async fn database() -> DatabaseConnection {
+        let conn = Database::connect("sqlite::memory:").await.unwrap();
+        migration::Migrator::up(&conn, None).await.unwrap();
+        conn
+    }
+
+    async fn chat(conn: &DatabaseConnection, owner_id: i32) -> i32 {
+        chat::ActiveModel {
+            owner_id: Set(owner_id),
+            model_id: Set(1),
+            title: Set(Some("from the server".to_owned())),
+            ..Default::default()
+        }
+        .insert(conn)
+        .await
+        .unwrap()
+        .id
+    }
+
+    /// What a client that just synced holds as `cursor`
+    async fn cursor(conn: &DatabaseConnection) -> i32 {
+        SyncChange::find()
+            .order_by_desc(sync_change::Column::Id)
+            .one(conn)
+            .await
+            .unwrap()
+            .map_or(0, |x| x.id)
+    }
+
+    async fn rename(conn: &DatabaseConnection, id: i32, title: &str) {
+        Chat::update_many()
+            .col_expr(chat::Column::Title, Expr::value(title))
+            .filter(chat::Column::Id.eq(id))
+            .exec(conn)
+            .await
+            .unwrap();
+    }
+
+    async fn title(conn: &DatabaseConnection, id: i32) -> Option<String> {
+        Chat::find_by_id(id)
+            .one(conn)
+            .await
+            .unwrap()
+            .and_then(|x| x.title)
+    }
+
+    async fn write(
+        conn: &DatabaseConnection,
+        base: i32,
+        ops: Vec<SyncWriteReqOp>,
+    ) -> Vec<SyncWriteRespResult> {
+        apply(conn, 1, SyncWriteReq { base, ops })
+            .await
+            .unwrap()
+            .results
+    }
+
+    fn title_op(id: i32, title: &str) -> SyncWriteReqOp {
+        SyncWriteReqOp::ChatTitle(SyncWriteReqChatTitle {
+            id,
+            title: title.to_owned(),
+        })
+    }
+
+    fn locale_op(locale: &str) -> SyncWriteReqOp {
+        SyncWriteReqOp::Preference(UserPreference {
+            locale: Some(locale.to_owned()),
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn applies_ops_on_entities_unchanged_since_base() {
+        let conn = database().await;
+        let id = chat(&conn, 1).await;
+        let base = cursor(&conn).await;
+
+        let results = write(&conn, base, vec![title_op(id, "offline")]).await;
+        assert_eq!(results, vec![Applied]);
+        assert_eq!(title(&conn, id).await.as_deref(), Some("offline"));
+    }
+
+    #[tokio::test]
+    async fn the_server_copy_wins_a_conflict() {
+        let conn = database().await;
+        let id = chat(&conn, 1).await;
+        let base = cursor(&conn).await;
+        // another device renames the chat while this one is offline
+        rename(&conn, id, "other device").await;
+
+        let results = write(&conn, base, vec![title_op(id, "offline")]).await;
+        assert_eq!(results, vec![Conflict]);
+        assert_eq!(title(&conn, id).await.as_deref(), Some("other device"));
+    }
+
+    #[tokio::test]
+    async fn conflicts_are_per_entity() {
+        let conn = database().await;
+        let (a, b) = (chat(&conn, 1).await, chat(&conn, 1).await);
+        let base = cursor(&conn).await;
+        rename(&conn, a, "other device").await;
+
+        let results = write(&conn, base, vec![title_op(a, "x"), title_op(b, "y")]).await;
+        assert_eq!(results, vec![Conflict, Applied]);
+        assert_eq!(title(&conn, b).await.as_deref(), Some("y"));
+    }
+
+    #[tokio::test]
+    async fn earlier_ops_of_the_batch_are_not_conflicts() {
+        let conn = database().await;
+        let id = chat(&conn, 1).await;
+        let base = cursor(&conn).await;
+
+        let ops = vec![
+            title_op(id, "first"),
+            title_op(id, "second"),
+            SyncWriteReqOp::ChatDelete(SyncWriteReqChatDelete { id }),
+        ];
+        let batch = apply(&conn, 1, SyncWriteReq { base, ops }).await.unwrap();
+        assert_eq!(batch.results, vec![Applied, Applied, Applied]);
+        assert_eq!(batch.deleted.len(), 1);
+        assert!(Chat::find_by_id(id).one(&conn).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn chats_of_others_are_not_found() {
+        let conn = database().await;
+        let other = user::ActiveModel {
+            name: Set("other".to_owned()),
+            password: Set(String::new()),
+            ..Default::default()
+        }
+        .insert(&conn)
+        .await
+        .unwrap()
+        .id;
+        let id = chat(&conn, other).await;
+        let base = cursor(&conn).await;
+        // changes of other users are not conflicts either
+        rename(&conn, id, "theirs").await;
+
+        let ops = vec![
+            title_op(id, "mine"),
+            SyncWriteReqOp::ChatDelete(SyncWriteReqChatDelete { id }),
+            title_op(id + 1, "missing"),
+        ];
+        assert_eq!(write(&conn, base, ops).await, vec![NotFound; 3]);
+        assert_eq!(title(&conn, id).await.as_deref(), Some("theirs"));
+    }
+
+    #[tokio::test]
+    async fn preferences_conflict_and_are_validated() {
+        let conn = database().await;
+        let base = cursor(&conn).await;
+
+        assert_eq!(
+            write(&conn, base, vec![locale_op("xx")]).await,
+            vec![Rejected]
+        );
+        let results = write(&conn, base, vec![locale_op("en"), locale_op("zh-tw")]).await;
+        assert_eq!(results, vec![Applied, Applied]);
+        let user = User::find_by_id(1).one(&conn).await.unwrap().unwrap();
+        assert_eq!(user.preference.locale.as_deref(), Some("zh-tw"));
+
+        // written above, after `base`, as by another device
+        assert_eq!(
+            write(&conn, base, vec![locale_op("en")]).await,
+            vec![Conflict]
+        );
+        let base = cursor(&conn).await;
+        assert_eq!(
+            write(&conn, base, vec![locale_op("en")]).await,
+            vec![Applied]
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+};
+use sea_orm::TransactionTrait;
+use serde::Serialize;
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::UserId,
+    trash::{self, Restore},
+};
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct UndoResp {
+    /// Chats the restored rows belong to, refetch them
+    pub chat_ids: Vec<i32>,
+}
+
+/// Restore what was deleted with the `undo_token`, within `UNDO_WINDOW`
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Path(token): Path<String>,
+) -> JsonResult<UndoResp> {
+    let entries = app
+        .undo
+        .take(user_id, &token)
+        .ok_or("Nothing to undo, the window may have passed")
+        .kind(ErrorKind::ResourceNotFound)?;
+
+    let mut chat_ids = vec![];
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+    for id in entries {
+        // entries restored from the trash since are skipped
+        if let Restore::Restored(chat_id) = trash::restore(&txn, user_id, id)
+            .await
+            .kind(ErrorKind::Internal)?
+        {
+            chat_ids.push(chat_id);
+        }
+    }
+    txn.commit().await.kind(ErrorKind::Internal)?;
+    chat_ids.dedup();
+
+    for chat_id in &chat_ids {
+        app.prefetch.forget(*chat_id);
+    }
+    Ok(Json(UndoResp { chat_ids }))
+}
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Query, State},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState, activity,
+    config::{ACTIVITY_DAYS, ACTIVITY_HOUR_DAYS},
+    errors::*,
+    middlewares::auth::UserId,
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct UserActivityReq {
+    /// default to `day`
+    pub granularity: Option<UserActivityReqGranularity>,
+    /// Minutes ahead of UTC buckets start at, e.g. 480 in Taipei, default to 0
+    pub offset: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema)]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum UserActivityReqGranularity {
+    /// The last year
+    #[default]
+    Day,
+    /// The last 14 days
+    Hour,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct UserActivityResp {
+    /// Start of the first bucket, unix seconds
+    pub from: i64,
+    /// Seconds a bucket covers
+    pub width: u32,
+    /// Oldest first, buckets without messages are left out
+    pub buckets: Vec<UserActivityRespBucket>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct UserActivityRespBucket {
+    /// unix seconds
+    pub start: i64,
+    /// Sent by the user
+    pub messages: u32,
+    /// Output tokens of the replies, 0 for replies before tokens were recorded
+    pub tokens: u32,
+}
+
+/// Messages and tokens of the user per day or hour, for a heatmap
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Query(req): Query<UserActivityReq>,
+) -> JsonResult<UserActivityResp> {
+    let offset = req.offset.unwrap_or_default();
+    if offset.abs() > 14 * 60 {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "offset is beyond 14 hours".to_owned(),
+        }));
+    }
+    let offset = offset as i64 * 60;
+
+    let (width, days) = match req.granularity.unwrap_or_default() {
+        UserActivityReqGranularity::Day => (24 * 3600, ACTIVITY_DAYS),
+        UserActivityReqGranularity::Hour => (3600, ACTIVITY_HOUR_DAYS),
+    };
+    let buckets = app
+        .activity
+        .buckets(&app.conn, user_id, width, offset, days)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    Ok(Json(UserActivityResp {
+        from: activity::today(offset) - days * 24 * 3600,
+        width: width as u32,
+        buckets: buckets
+            .into_iter()
+            .map(|x| UserActivityRespBucket {
+                start: x.start,
+                messages: x.messages as u32,
+                tokens: x.tokens as u32,
+            })
+            .collect(),
+    }))
+}
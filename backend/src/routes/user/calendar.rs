@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{prelude::*, user};
+use schemars::JsonSchema;
+use sea_orm::{ActiveValue::Set, EntityTrait};
+use serde::Serialize;
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct UserCalendarResp {
+    /// Calendar apps subscribe to `/calendar/{token}` without logging in
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct UserCalendarRevokeResp {
+    /// false if the user had no feed
+    pub revoked: bool,
+}
+
+async fn find(app: &AppState, user_id: i32) -> Result<user::Model, Json<Error>> {
+    User::find_by_id(user_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("")
+        .kind(ErrorKind::ResourceNotFound)
+}
+
+/// Return the link of the feed of the user, created on the first call
+pub async fn create(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+) -> JsonResult<UserCalendarResp> {
+    let user = find(&app, user_id).await?;
+    if let Some(token) = user.calendar_token {
+        return Ok(Json(UserCalendarResp { token }));
+    }
+
+    let mut bytes = [0u8; 24];
+    getrandom::fill(&mut bytes)
+        .map_err(|e| anyhow::anyhow!("Cannot generate token: {}", e))
+        .kind(ErrorKind::Internal)?;
+    let token: String = bytes.iter().map(|x| format!("{:02x}", x)).collect();
+
+    User::update(user::ActiveModel {
+        id: Set(user_id),
+        calendar_token: Set(Some(token.clone())),
+        ..Default::default()
+    })
+    .exec(&app.conn)
+    .await
+    .kind(ErrorKind::Internal)?;
+
+    Ok(Json(UserCalendarResp { token }))
+}
+
+/// The link stops working at once, asking again gives a new one
+pub async fn revoke(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+) -> JsonResult<UserCalendarRevokeResp> {
+    let user = find(&app, user_id).await?;
+    let revoked = user.calendar_token.is_some();
+    if revoked {
+        User::update(user::ActiveModel {
+            id: Set(user_id),
+            calendar_token: Set(None),
+            ..Default::default()
+        })
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    }
+
+    Ok(Json(UserCalendarRevokeResp { revoked }))
+}
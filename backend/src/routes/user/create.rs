@@ -1,21 +1,33 @@
 use std::sync::Arc;
 
 use axum::{Extension, Json, extract::State};
-use entity::{prelude::*, user};
-use sea_orm::{ActiveValue, EntityTrait};
+use entity::{AuditKind, UserRole, prelude::*, user};
+use schemars::JsonSchema;
+use sea_orm::{ActiveValue, EntityTrait, TransactionTrait};
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-use crate::{AppState, errors::*, middlewares::auth::UserId};
+use crate::{
+    AppState, audit,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+    utils::{email_verification, workspace},
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct UserCreateReq {
     pub username: String,
     pub password: String,
+    /// Default to [`UserRole::User`]
+    pub role: Option<UserRole>,
+    /// Mailed a verification link, the user cannot send messages until it is opened
+    pub email: Option<String>,
+    /// Workspace the user joins, default to the default one
+    pub workspace_id: Option<i32>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct UserCreateResp {
     pub user_id: i32,
@@ -23,22 +35,57 @@ pub struct UserCreateResp {
 
 pub async fn route(
     State(app): State<Arc<AppState>>,
-    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(UserId(admin_id)): Extension<UserId>,
+    _: AdminOnly,
     Json(req): Json<UserCreateReq>,
 ) -> JsonResult<UserCreateResp> {
-    let password_hash = app.hasher.hash_password(&req.password);
+    let workspace_id = req.workspace_id.unwrap_or(workspace::DEFAULT_WORKSPACE);
+    Workspace::find_by_id(workspace_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Cannot find workspace")
+        .kind(ErrorKind::ResourceNotFound)?;
+    let password_hash = app.hasher.hash_password(&req.password).await;
+    let email = req
+        .email
+        .map(|x| x.trim().to_owned())
+        .filter(|x| !x.is_empty());
+    let role = req.role.unwrap_or(UserRole::User);
+    let detail = format!("user {} created as {:?}", req.username, role);
     let new_user = user::ActiveModel {
         name: ActiveValue::Set(req.username),
         password: ActiveValue::Set(password_hash),
+        role: ActiveValue::Set(role),
+        email: ActiveValue::Set(email.clone()),
+        email_verified: ActiveValue::Set(email.is_none() || app.mailer.is_none()),
         ..Default::default()
     };
 
-    let new_user = User::insert(new_user)
-        .exec(&app.conn)
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+    let user_id = User::insert(new_user)
+        .exec(&txn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .last_insert_id;
+    workspace::join(&txn, workspace_id, user_id)
         .await
         .kind(ErrorKind::Internal)?;
+    let verification = match email {
+        Some(email) if app.mailer.is_some() => {
+            let token = email_verification::create(&txn, user_id, &email)
+                .await
+                .kind(ErrorKind::Internal)?;
+            Some((email, token))
+        }
+        _ => None,
+    };
+    txn.commit().await.kind(ErrorKind::Internal)?;
+    audit::record(&app.conn, AuditKind::User, Some(admin_id), detail).await;
+
+    if let Some((email, token)) = verification {
+        email_verification::send(&app, user_id, email, token).await;
+    }
 
-    Ok(Json(UserCreateResp {
-        user_id: new_user.last_insert_id,
-    }))
+    Ok(Json(UserCreateResp { user_id }))
 }
@@ -1,20 +1,26 @@
 use std::sync::Arc;
 
 use axum::{Extension, Json, extract::State};
-use entity::prelude::*;
+use entity::{AuditKind, prelude::*};
+use schemars::JsonSchema;
 use sea_orm::EntityTrait;
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-use crate::{AppState, errors::*, middlewares::auth::UserId};
+use crate::{
+    AppState, audit,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+    utils::account_purge,
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct UserDeleteReq {
     pub user_id: i32,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct UserDeleteResp {
     pub deleted: bool,
@@ -22,15 +28,23 @@ pub struct UserDeleteResp {
 
 pub async fn route(
     State(app): State<Arc<AppState>>,
-    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(UserId(admin_id)): Extension<UserId>,
+    _: AdminOnly,
     Json(req): Json<UserDeleteReq>,
 ) -> JsonResult<UserDeleteResp> {
-    let res = User::delete_by_id(req.user_id)
-        .exec(&app.conn)
+    let exists = User::find_by_id(req.user_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .is_some();
+    account_purge::purge(&app.conn, &[req.user_id])
         .await
         .kind(ErrorKind::Internal)?;
+    app.cache.forget_user(req.user_id);
+    if exists {
+        let detail = format!("user {} deleted", req.user_id);
+        audit::record(&app.conn, AuditKind::User, Some(admin_id), detail).await;
+    }
 
-    Ok(Json(UserDeleteResp {
-        deleted: res.rows_affected == 1,
-    }))
+    Ok(Json(UserDeleteResp { deleted: exists }))
 }
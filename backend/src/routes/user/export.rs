@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State, http::header, response::IntoResponse};
+use entity::{chat, prelude::*};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+
+use crate::{AppState, errors::*, middlewares::auth::UserId, utils::export::Transcript};
+
+/// Every chat of the user as a JSON array of transcripts, same shape as the
+/// `json` format of a chat export
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+) -> Result<impl IntoResponse, Json<Error>> {
+    let chats = Chat::find()
+        .filter(chat::Column::OwnerId.eq(user_id))
+        .order_by_asc(chat::Column::Id)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let mut transcripts = Vec::with_capacity(chats.len());
+    for chat in &chats {
+        transcripts.push(
+            Transcript::load(&app.conn, app.instance_id.clone(), chat, user_id)
+                .await
+                .kind(ErrorKind::Internal)?,
+        );
+    }
+    let body = serde_json::to_vec_pretty(&transcripts).kind(ErrorKind::Internal)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"chats.json\"",
+            ),
+        ],
+        body,
+    ))
+}
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{ApiKeyScope, ApiKeyScopes};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+    utils::api_key,
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ApiKeyCreateReq {
+    /// Shown in the list, e.g. what script use it
+    pub name: String,
+    pub scopes: Vec<ApiKeyScope>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ApiKeyCreateResp {
+    pub id: i32,
+    /// Only shown here, send it as the authorization header
+    pub key: String,
+}
+
+/// The key works in the workspace of the request
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Json(req): Json<ApiKeyCreateReq>,
+) -> JsonResult<ApiKeyCreateResp> {
+    let mut scopes = req.scopes;
+    scopes.sort_by_key(|x| *x as u8);
+    scopes.dedup();
+
+    let (id, key) = api_key::create(
+        &app.conn,
+        user_id,
+        workspace_id,
+        req.name,
+        ApiKeyScopes(scopes),
+    )
+    .await
+    .kind(ErrorKind::Internal)?;
+
+    Ok(Json(ApiKeyCreateResp { id, key }))
+}
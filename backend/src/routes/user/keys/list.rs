@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{ApiKeyScope, api_key, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ApiKeyListReq {}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ApiKeyListResp {
+    pub list: Vec<ApiKeyListRespItem>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ApiKeyListRespItem {
+    pub id: i32,
+    pub name: String,
+    /// First characters of the key
+    pub prefix: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub created_at: u32,
+    /// Updated at most once a minute
+    pub last_used_at: Option<u32>,
+}
+
+/// Keys of the current user, newest first
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(_): Json<ApiKeyListReq>,
+) -> JsonResult<ApiKeyListResp> {
+    let list = ApiKey::find()
+        .filter(api_key::Column::UserId.eq(user_id))
+        .order_by_desc(api_key::Column::Id)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .into_iter()
+        .map(|x| ApiKeyListRespItem {
+            id: x.id,
+            name: x.name,
+            prefix: x.prefix,
+            scopes: x.scopes.0,
+            created_at: x.created_at as u32,
+            last_used_at: x.last_used_at.map(|x| x as u32),
+        })
+        .collect();
+
+    Ok(Json(ApiKeyListResp { list }))
+}
@@ -0,0 +1,32 @@
+use std::sync::Arc;
+
+use axum::{Router, routing::post};
+
+use crate::{AppState, utils::openapi::Builder};
+
+mod create;
+mod list;
+mod revoke;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/create", post(create::route))
+        .route("/list", post(list::route))
+        .route("/revoke", post(revoke::route))
+}
+
+pub fn spec(api: &mut Builder) {
+    api.op("POST", "/user/keys/create", "Create an API key")
+        .body::<create::ApiKeyCreateReq>()
+        .json::<create::ApiKeyCreateResp>();
+    api.op(
+        "POST",
+        "/user/keys/list",
+        "API keys of the user, newest first",
+    )
+    .body::<list::ApiKeyListReq>()
+    .json::<list::ApiKeyListResp>();
+    api.op("POST", "/user/keys/revoke", "Revoke an API key")
+        .body::<revoke::ApiKeyRevokeReq>()
+        .json::<revoke::ApiKeyRevokeResp>();
+}
@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{api_key, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct ApiKeyRevokeReq {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct ApiKeyRevokeResp {
+    /// false if the key does not exist or belong to someone else
+    pub revoked: bool,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<ApiKeyRevokeReq>,
+) -> JsonResult<ApiKeyRevokeResp> {
+    let res = ApiKey::delete_many()
+        .filter(api_key::Column::Id.eq(req.id))
+        .filter(api_key::Column::UserId.eq(user_id))
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    Ok(Json(ApiKeyRevokeResp {
+        revoked: res.rows_affected > 0,
+    }))
+}
@@ -1,33 +1,40 @@
 use std::sync::Arc;
 
 use axum::{Extension, Json, extract::State};
-use entity::user;
+use entity::{UserRole, user};
+use schemars::JsonSchema;
 use sea_orm::EntityTrait;
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-use crate::{AppState, errors::*, middlewares::auth::UserId};
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{AdminOnly, UserId},
+};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct UserListResp {
     pub list: Vec<UserList>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct UserList {
     pub id: i32,
     pub name: String,
+    pub role: UserRole,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct UserListReq {}
 
 pub async fn route(
     State(app): State<Arc<AppState>>,
     Extension(UserId(_)): Extension<UserId>,
+    _: AdminOnly,
     Json(_): Json<UserListReq>,
 ) -> JsonResult<UserListResp> {
     let models = user::Entity::find()
@@ -36,11 +43,10 @@ pub async fn route(
         .kind(ErrorKind::Internal)?;
     let list = models
         .into_iter()
-        .filter_map(|m| {
-            Some(UserList {
-                id: m.id,
-                name: m.name,
-            })
+        .map(|m| UserList {
+            id: m.id,
+            name: m.name,
+            role: m.role,
         })
         .collect::<Vec<_>>();
     Ok(Json(UserListResp { list }))
@@ -1,20 +1,141 @@
 use std::sync::Arc;
 
-use axum::{Router, routing::post};
+use axum::{
+    Router,
+    routing::{delete, get, post},
+};
 
-use crate::AppState;
+use crate::{AppState, notify::Notification, utils::openapi::Builder};
 
+mod activity;
+mod calendar;
 mod create;
 mod delete;
+mod export;
+mod keys;
 mod list;
+mod notifications;
+mod preferences;
+mod purge;
+mod purge_token;
+mod push;
 mod read;
+mod routing_rules;
+mod search_terms;
+mod sessions;
+mod stats;
+mod takeout;
 mod update;
+mod usage;
+mod webhooks;
 
 pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
+        .route("/", delete(purge::route))
+        .route("/activity", get(activity::route))
+        .route("/calendar", post(calendar::create).delete(calendar::revoke))
         .route("/create", post(create::route))
         .route("/delete", post(delete::route))
+        .route("/export", get(export::route))
         .route("/read", post(read::route))
         .route("/update", post(update::route))
         .route("/list", post(list::route))
+        .route("/notifications", get(notifications::route))
+        .route("/purge_token", post(purge_token::route))
+        .route("/stats", post(stats::route))
+        .route("/takeout", get(takeout::route))
+        .route("/usage", get(usage::route))
+        .nest("/keys", keys::routes())
+        .nest("/preferences", preferences::routes())
+        .nest("/push", push::routes())
+        .nest("/routing", routing_rules::routes())
+        .nest("/search_terms", search_terms::routes())
+        .nest("/sessions", sessions::routes())
+        .nest("/webhooks", webhooks::routes())
+}
+
+/// Describe the routes above, served by `routes::openapi`
+pub fn spec(api: &mut Builder) {
+    api.op("DELETE", "/user", "Delete the own account")
+        .body::<purge::UserPurgeReq>()
+        .json::<purge::UserPurgeResp>();
+    api.op(
+        "GET",
+        "/user/activity",
+        "Messages and tokens of the user per day or hour",
+    )
+    .query::<activity::UserActivityReq>()
+    .json::<activity::UserActivityResp>();
+    api.op(
+        "POST",
+        "/user/calendar",
+        "Return the link of the calendar feed, created on the first call",
+    )
+    .json::<calendar::UserCalendarResp>();
+    api.op("DELETE", "/user/calendar", "Stop serving the calendar feed")
+        .json::<calendar::UserCalendarRevokeResp>();
+    api.op("POST", "/user/create", "Create a user, admins only")
+        .body::<create::UserCreateReq>()
+        .json::<create::UserCreateResp>();
+    api.op("POST", "/user/delete", "Delete a user, admins only")
+        .body::<delete::UserDeleteReq>()
+        .json::<delete::UserDeleteResp>();
+    api.op(
+        "GET",
+        "/user/export",
+        "Every chat of the user as a JSON array of transcripts",
+    )
+    .file("application/json");
+    api.op(
+        "POST",
+        "/user/read",
+        "Read the current user, or another one for admins",
+    )
+    .body::<read::UserReadReq>()
+    .json::<read::UserReadResp>();
+    api.op(
+        "POST",
+        "/user/update",
+        "Change the preference, password or address of a user",
+    )
+    .body::<update::UserUpdateReq>()
+    .json::<update::UserUpdateResp>();
+    api.op("POST", "/user/list", "List the users, admins only")
+        .body::<list::UserListReq>()
+        .json::<list::UserListResp>();
+    api.op(
+        "GET",
+        "/user/notifications",
+        "Notifications of the user as they happen",
+    )
+    .events::<Notification>();
+    api.op(
+        "POST",
+        "/user/purge_token",
+        "First step of deleting the own account",
+    )
+    .body::<purge_token::UserPurgeTokenReq>()
+    .json::<purge_token::UserPurgeTokenResp>();
+    api.op("POST", "/user/stats", "Usage counts of the user")
+        .body::<stats::UserStatsReq>()
+        .json::<stats::UserStatsResp>();
+    api.op(
+        "GET",
+        "/user/takeout",
+        "Build a zip of everything kept about the user, told on notifications",
+    )
+    .json::<takeout::UserTakeoutResp>();
+    api.op(
+        "GET",
+        "/user/usage",
+        "What the user used of their quotas and what is left",
+    )
+    .json::<usage::UserUsageResp>();
+    keys::spec(api);
+    preferences::spec(api);
+    push::spec(api);
+    routing_rules::spec(api);
+    search_terms::spec(api);
+    sessions::spec(api);
+    webhooks::spec(api);
 }
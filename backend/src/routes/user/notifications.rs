@@ -0,0 +1,42 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    Extension,
+    extract::State,
+    response::{
+        IntoResponse, Sse,
+        sse::{Event, KeepAlive},
+    },
+};
+use futures_util::stream;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{AppState, config::SSE_KEEP_ALIVE, middlewares::auth::UserId};
+
+/// Notifications of the user as they happen, see [`crate::notify`]
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+) -> impl IntoResponse {
+    let rx = app.notifier.subscribe(user_id);
+    let online = app.notifier.connect(user_id);
+    let st = stream::unfold((rx, online), |(mut rx, online)| async move {
+        loop {
+            match rx.recv().await {
+                Ok(x) => {
+                    let event = Event::default().json_data(x);
+                    return Some((event, (rx, online)));
+                }
+                // a notification missed is not worth closing the stream
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    let sse = Sse::new(st).keep_alive(
+        KeepAlive::new()
+            .interval(Duration::from_secs(SSE_KEEP_ALIVE))
+            .text("ping"),
+    );
+    ([("x-accel-buffering", "no")], sse)
+}
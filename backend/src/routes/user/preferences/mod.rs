@@ -0,0 +1,28 @@
+use std::sync::Arc;
+
+use axum::{Router, routing::get};
+
+use crate::{AppState, utils::openapi::Builder};
+
+mod read;
+mod write;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/", get(read::route).patch(write::route))
+}
+
+pub fn spec(api: &mut Builder) {
+    api.op(
+        "GET",
+        "/user/preferences",
+        "Preferences of the user with their version",
+    )
+    .json::<read::PreferencesReadResp>();
+    api.op(
+        "PATCH",
+        "/user/preferences",
+        "Set or remove preference keys, refused if changed since the version",
+    )
+    .body::<write::PreferencesWriteReq>()
+    .json::<write::PreferencesWriteResp>();
+}
@@ -0,0 +1,35 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{UserPreference, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::EntityTrait;
+use serde::Serialize;
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct PreferencesReadResp {
+    pub preference: UserPreference,
+    /// Give it back when writing, see `PreferencesWriteReq`
+    pub version: i32,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+) -> JsonResult<PreferencesReadResp> {
+    // not the cached row, the version has to be the one stored
+    let user = User::find_by_id(user_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("")
+        .kind(ErrorKind::ResourceNotFound)?;
+    Ok(Json(PreferencesReadResp {
+        preference: user.preference,
+        version: user.preference_version,
+    }))
+}
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{UserPreference, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::EntityTrait;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::UserId,
+    utils::{model, preference},
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct PreferencesWriteReq {
+    /// `version` of `PreferencesReadResp` the change is based on
+    pub version: i32,
+    /// Keys to set, the others are kept
+    #[serde(default)]
+    pub set: UserPreference,
+    /// Keys to remove, back to their default
+    #[serde(default)]
+    pub unset: Vec<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct PreferencesWriteResp {
+    pub preference: UserPreference,
+    pub version: i32,
+}
+
+/// Apply the change if nothing was written since `version`, otherwise
+/// `ErrorKind::Conflict` and the client reads them again
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<PreferencesWriteReq>,
+) -> JsonResult<PreferencesWriteResp> {
+    let user = User::find_by_id(user_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("")
+        .kind(ErrorKind::ResourceNotFound)?;
+    if user.preference_version != req.version {
+        return Err(Json(Error {
+            error: ErrorKind::Conflict,
+            reason: format!("preferences are at version {}", user.preference_version),
+        }));
+    }
+    let merged = preference::apply(&user.preference, &req.set, &req.unset).map_err(|x| {
+        Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: x.join("; "),
+        })
+    })?;
+    // workspaces are checked when a chat starts, the default applies
+    // wherever its model is offered
+    if let Some(id) = req.set.default_model_id {
+        let models = model::all(&app).await.kind(ErrorKind::Internal)?;
+        if !models.iter().any(|x| x.id == id) {
+            return Err(Json(Error {
+                error: ErrorKind::ResourceNotFound,
+                reason: format!("cannot find model {}", id),
+            }));
+        }
+    }
+
+    let version = preference::write(&app.conn, user_id, req.version, merged.clone())
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("another write came first")
+        .kind(ErrorKind::Conflict)?;
+    app.cache.forget_user(user_id);
+
+    Ok(Json(PreferencesWriteResp {
+        preference: merged,
+        version,
+    }))
+}
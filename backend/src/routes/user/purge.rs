@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId, utils::account_purge};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct UserPurgeReq {
+    /// From `/api/user/purge_token`
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct UserPurgeResp {
+    /// Unix seconds, until then signing in cancel the deletion
+    pub purge_at: i64,
+}
+
+/// Delete the own account with its chats, files, keys and sessions
+///
+/// Every session and API key is revoked now, the rest is removed after a grace
+/// period
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<UserPurgeReq>,
+) -> JsonResult<UserPurgeResp> {
+    if !account_purge::confirmed(&app.keyring, &req.token, user_id) {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedToken,
+            reason: "Confirmation expired, start again".to_owned(),
+        }));
+    }
+
+    let purge_at = account_purge::schedule(&app.conn, user_id)
+        .await
+        .kind(ErrorKind::Internal)?;
+    app.cache.forget_user(user_id);
+    tracing::info!("user {} deleted, purged at {}", user_id, purge_at);
+
+    Ok(Json(UserPurgeResp { purge_at }))
+}
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{identity, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    config::{ACCOUNT_PURGE_CONFIRM_SECS, ACCOUNT_PURGE_GRACE_SECS},
+    errors::*,
+    middlewares::auth::UserId,
+    utils::account_purge,
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct UserPurgeTokenReq {
+    /// Not needed by users who only sign in with a provider
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct UserPurgeTokenResp {
+    /// Give it to `DELETE /api/user` within `expires_in_secs`
+    pub token: String,
+    pub expires_in_secs: u32,
+    /// How long the account is kept once deleted, signing in cancel the deletion
+    pub grace_secs: u32,
+}
+
+/// First step of deleting the own account
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<UserPurgeTokenReq>,
+) -> JsonResult<UserPurgeTokenResp> {
+    let user = User::find_by_id(user_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("")
+        .kind(ErrorKind::ResourceNotFound)?;
+    let linked = Identity::find()
+        .filter(identity::Column::UserId.eq(user_id))
+        .count(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        > 0;
+
+    let password_ok = match &req.password {
+        Some(password) => app.hasher.verify_password(&user.password, password).await,
+        // nobody knows the password of accounts created by a provider
+        None => linked,
+    };
+    if !password_ok {
+        return Err(Json(Error {
+            error: ErrorKind::LoginFail,
+            reason: "Wrong password".to_owned(),
+        }));
+    }
+
+    let token = account_purge::confirmation(&app.keyring, user_id).kind(ErrorKind::Internal)?;
+    Ok(Json(UserPurgeTokenResp {
+        token,
+        expires_in_secs: ACCOUNT_PURGE_CONFIRM_SECS as u32,
+        grace_secs: ACCOUNT_PURGE_GRACE_SECS as u32,
+    }))
+}
@@ -0,0 +1,28 @@
+use std::sync::Arc;
+
+use axum::{Json, extract::State};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct PushKeyReq {}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct PushKeyResp {
+    /// `applicationServerKey` of `PushManager.subscribe`, in base64url
+    pub public_key: String,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Json(_): Json<PushKeyReq>,
+) -> JsonResult<PushKeyResp> {
+    Ok(Json(PushKeyResp {
+        public_key: app.push.public_key().to_owned(),
+    }))
+}
@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use axum::{Router, routing::post};
+
+use crate::{AppState, utils::openapi::Builder};
+
+mod key;
+mod subscribe;
+mod unsubscribe;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/key", post(key::route))
+        .route("/subscribe", post(subscribe::route))
+        .route("/unsubscribe", post(unsubscribe::route))
+}
+
+pub fn spec(api: &mut Builder) {
+    api.op(
+        "POST",
+        "/user/push/key",
+        "VAPID public key browsers subscribe with",
+    )
+    .body::<key::PushKeyReq>()
+    .json::<key::PushKeyResp>();
+    api.op(
+        "POST",
+        "/user/push/subscribe",
+        "Send Web Push to a browser while the user is away",
+    )
+    .body::<subscribe::PushSubscribeReq>()
+    .json::<subscribe::PushSubscribeResp>();
+    api.op(
+        "POST",
+        "/user/push/unsubscribe",
+        "Stop sending Web Push to a browser",
+    )
+    .body::<unsubscribe::PushUnsubscribeReq>()
+    .json::<unsubscribe::PushUnsubscribeResp>();
+}
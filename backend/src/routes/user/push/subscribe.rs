@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use entity::{prelude::*, push_subscription};
+use schemars::JsonSchema;
+use sea_orm::{ActiveValue::Set, prelude::*, sea_query::OnConflict};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, config::PUSH_MAX_PER_USER, errors::*, middlewares::auth::UserId};
+
+/// The fields of `PushSubscription.toJSON()`, flattened
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct PushSubscribeReq {
+    pub endpoint: String,
+    /// `keys.p256dh`, base64url
+    pub p256dh: String,
+    /// `keys.auth`, base64url
+    pub auth: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct PushSubscribeResp {
+    pub id: i32,
+}
+
+/// A browser subscribed again, or by another account, replaces its keys and
+/// owner
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<PushSubscribeReq>,
+) -> JsonResult<PushSubscribeResp> {
+    let endpoint = url::Url::parse(req.endpoint.trim()).kind(ErrorKind::MalformedRequest)?;
+    if endpoint.scheme() != "https" {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "push endpoints are https URLs".to_owned(),
+        }));
+    }
+    let p256dh = URL_SAFE_NO_PAD.decode(req.p256dh.trim_end_matches('='));
+    let auth = URL_SAFE_NO_PAD.decode(req.auth.trim_end_matches('='));
+    if !matches!(p256dh, Ok(x) if x.len() == 65 && x[0] == 4)
+        || !matches!(auth, Ok(x) if x.len() == 16)
+    {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "p256dh is a P-256 public key and auth 16 bytes, in base64url".to_owned(),
+        }));
+    }
+
+    let count = PushSubscription::find()
+        .filter(push_subscription::Column::UserId.eq(user_id))
+        .filter(push_subscription::Column::Endpoint.ne(endpoint.as_str()))
+        .count(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    if count >= PUSH_MAX_PER_USER {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!(
+                "a user subscribes at most {} browsers, unsubscribe one first",
+                PUSH_MAX_PER_USER
+            ),
+        }));
+    }
+
+    PushSubscription::insert(push_subscription::ActiveModel {
+        user_id: Set(user_id),
+        endpoint: Set(endpoint.to_string()),
+        p256dh: Set(req.p256dh.trim_end_matches('=').to_owned()),
+        auth: Set(req.auth.trim_end_matches('=').to_owned()),
+        created_at: Set(time::UtcDateTime::now().unix_timestamp()),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::column(push_subscription::Column::Endpoint)
+            .update_columns([
+                push_subscription::Column::UserId,
+                push_subscription::Column::P256dh,
+                push_subscription::Column::Auth,
+                push_subscription::Column::CreatedAt,
+            ])
+            .to_owned(),
+    )
+    .exec(&app.conn)
+    .await
+    .kind(ErrorKind::Internal)?;
+
+    let id = PushSubscription::find()
+        .filter(push_subscription::Column::Endpoint.eq(endpoint.as_str()))
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .map(|x| x.id)
+        .ok_or("subscription vanished")
+        .kind(ErrorKind::Internal)?;
+
+    Ok(Json(PushSubscribeResp { id }))
+}
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{prelude::*, push_subscription};
+use schemars::JsonSchema;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct PushUnsubscribeReq {
+    pub endpoint: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct PushUnsubscribeResp {
+    /// false if the browser was not subscribed by the user
+    pub deleted: bool,
+}
+
+/// Messages still queued are dropped
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<PushUnsubscribeReq>,
+) -> JsonResult<PushUnsubscribeResp> {
+    let endpoint = url::Url::parse(req.endpoint.trim())
+        .map(|x| x.to_string())
+        .unwrap_or(req.endpoint);
+    let res = PushSubscription::delete_many()
+        .filter(push_subscription::Column::Endpoint.eq(endpoint))
+        .filter(push_subscription::Column::UserId.eq(user_id))
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    Ok(Json(PushUnsubscribeResp {
+        deleted: res.rows_affected > 0,
+    }))
+}
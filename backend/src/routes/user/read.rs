@@ -1,26 +1,35 @@
 use std::sync::Arc;
 
 use axum::{Extension, Json, extract::State};
-use entity::{UserPreference, prelude::*};
+use entity::{UserPreference, UserRole, prelude::*};
+use schemars::JsonSchema;
 use sea_orm::EntityTrait;
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-use crate::{AppState, errors::*, middlewares::auth::UserId};
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{UserId, is_admin},
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct UserReadReq {
-    /// If omit will use the current user instead
+    /// If omit will use the current user instead, only admins can read others
     pub user_id: Option<i32>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct UserReadResp {
     pub user_id: i32,
     pub username: String,
     pub preference: UserPreference,
+    pub email: Option<String>,
+    /// False until the link mailed to `email` is opened, messages cannot be sent
+    pub email_verified: bool,
+    pub role: UserRole,
 }
 
 pub async fn route(
@@ -28,6 +37,12 @@ pub async fn route(
     Extension(UserId(user_id)): Extension<UserId>,
     Json(req): Json<UserReadReq>,
 ) -> JsonResult<UserReadResp> {
+    if req.user_id.is_some_and(|x| x != user_id) && !is_admin(&app, user_id).await? {
+        return Err(Json(Error {
+            error: ErrorKind::Unauthorized,
+            reason: "only admins can read other users".to_owned(),
+        }));
+    }
     let user_id = req.user_id.unwrap_or(user_id);
 
     let res = User::find_by_id(user_id)
@@ -41,5 +56,9 @@ pub async fn route(
         user_id: res.id,
         username: res.name,
         preference: res.preference,
+        email: res.email,
+        // nothing can be verified without mails
+        email_verified: res.email_verified || app.mailer.is_none(),
+        role: res.role,
     }))
 }
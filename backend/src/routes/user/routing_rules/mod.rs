@@ -0,0 +1,27 @@
+use std::sync::Arc;
+
+use axum::{Router, routing::post};
+
+use crate::{AppState, utils::openapi::Builder};
+
+mod read;
+mod write;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/read", post(read::route))
+        .route("/write", post(write::route))
+}
+
+pub fn spec(api: &mut Builder) {
+    api.op(
+        "POST",
+        "/user/routing/read",
+        "Rules routing messages to other models",
+    )
+    .body::<read::RoutingReadReq>()
+    .json::<read::RoutingReadResp>();
+    api.op("POST", "/user/routing/write", "Replace the routing rules")
+        .body::<write::RoutingWriteReq>()
+        .json::<write::RoutingWriteResp>();
+}
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::RoutingCondition;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId, routing};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct RoutingReadReq {}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct RoutingRule {
+    pub condition: RoutingCondition,
+    /// Model a matching message is answered by
+    pub model_id: i32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct RoutingReadResp {
+    /// Tried in order, the first that matches is used
+    pub rules: Vec<RoutingRule>,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(_): Json<RoutingReadReq>,
+) -> JsonResult<RoutingReadResp> {
+    let rules = routing::list(&app.conn, user_id)
+        .await
+        .kind(ErrorKind::Internal)?
+        .into_iter()
+        .map(|x| RoutingRule {
+            condition: x.condition,
+            model_id: x.model_id,
+        })
+        .collect();
+    Ok(Json(RoutingReadResp { rules }))
+}
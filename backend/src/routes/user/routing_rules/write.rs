@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::RoutingCondition;
+use schemars::JsonSchema;
+use sea_orm::TransactionTrait;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use super::read::RoutingRule;
+use crate::{
+    AppState,
+    config::{ROUTING_KEYWORD_MAX_CHARS, ROUTING_MAX_RULES},
+    errors::*,
+    middlewares::auth::UserId,
+    routing,
+    utils::model,
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct RoutingWriteReq {
+    /// Replace the rules, see `RoutingReadResp`
+    pub rules: Vec<RoutingRule>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct RoutingWriteResp {}
+
+/// Replace the rules of the user, used from their next message on
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<RoutingWriteReq>,
+) -> JsonResult<RoutingWriteResp> {
+    if req.rules.len() > ROUTING_MAX_RULES {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!("At most {} rules", ROUTING_MAX_RULES),
+        }));
+    }
+    let models = model::all(&app).await.kind(ErrorKind::Internal)?;
+    let mut rules = Vec::with_capacity(req.rules.len());
+    for rule in req.rules {
+        check(&rule.condition)?;
+        // workspaces are checked when a message is routed, a rule applies
+        // wherever its model is offered
+        if !models.iter().any(|x| x.id == rule.model_id) {
+            return Err(Json(Error {
+                error: ErrorKind::ResourceNotFound,
+                reason: format!("cannot find model {}", rule.model_id),
+            }));
+        }
+        rules.push((rule.condition, rule.model_id));
+    }
+
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+    routing::replace(&txn, user_id, rules)
+        .await
+        .kind(ErrorKind::Internal)?;
+    txn.commit().await.kind(ErrorKind::Internal)?;
+
+    Ok(Json(RoutingWriteResp {}))
+}
+
+fn check(condition: &RoutingCondition) -> Result<(), Json<Error>> {
+    let reason = match condition {
+        RoutingCondition::Tokens(x) | RoutingCondition::ToolCalls(x) if *x < 1 => {
+            "A threshold must be at least 1".to_owned()
+        }
+        RoutingCondition::Keyword(x) if x.trim().is_empty() => "A keyword is empty".to_owned(),
+        RoutingCondition::Keyword(x) if x.chars().count() > ROUTING_KEYWORD_MAX_CHARS => {
+            format!(
+                "A keyword is longer than {} characters",
+                ROUTING_KEYWORD_MAX_CHARS
+            )
+        }
+        _ => return Ok(()),
+    };
+    Err(Json(Error {
+        error: ErrorKind::MalformedRequest,
+        reason,
+    }))
+}
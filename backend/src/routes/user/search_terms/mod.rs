@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use axum::{Router, routing::post};
+
+use crate::{AppState, utils::openapi::Builder};
+
+mod read;
+mod write;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/read", post(read::route))
+        .route("/write", post(write::route))
+}
+
+pub fn spec(api: &mut Builder) {
+    api.op(
+        "POST",
+        "/user/search_terms/read",
+        "Terms boosted or ignored by search",
+    )
+    .body::<read::SearchTermsReadReq>()
+    .json::<read::SearchTermsReadResp>();
+    api.op(
+        "POST",
+        "/user/search_terms/write",
+        "Replace the terms and reindex the messages",
+    )
+    .body::<write::SearchTermsWriteReq>()
+    .json::<write::SearchTermsWriteResp>();
+}
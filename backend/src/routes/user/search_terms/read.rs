@@ -0,0 +1,37 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::SearchTermKind;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId, utils::search_term};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct SearchTermsReadReq {}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SearchTermsReadResp {
+    /// Messages containing one of them rank higher, ignoring case
+    pub boost: Vec<String>,
+    /// Removed from messages and tool results before they are indexed, exact
+    /// text such as a mail signature
+    pub ignore: Vec<String>,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(_): Json<SearchTermsReadReq>,
+) -> JsonResult<SearchTermsReadResp> {
+    let boost = search_term::load(&app.conn, user_id, SearchTermKind::Boost)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let ignore = search_term::load(&app.conn, user_id, SearchTermKind::Ignore)
+        .await
+        .kind(ErrorKind::Internal)?;
+    Ok(Json(SearchTermsReadResp { boost, ignore }))
+}
@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use schemars::JsonSchema;
+use sea_orm::TransactionTrait;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    config::{SEARCH_MAX_TERM_CHARS, SEARCH_MAX_TERMS},
+    errors::*,
+    middlewares::auth::UserId,
+    utils::search_term,
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct SearchTermsWriteReq {
+    /// Replace the boosted terms, see `SearchTermsReadResp`
+    pub boost: Vec<String>,
+    /// Replace the ignored terms
+    pub ignore: Vec<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SearchTermsWriteResp {}
+
+/// Replace the terms and reindex the messages of the user
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<SearchTermsWriteReq>,
+) -> JsonResult<SearchTermsWriteResp> {
+    // boosts are matched as words, ignored terms as they are written
+    let boost = terms(req.boost.into_iter().map(|x| x.trim().to_owned()))?;
+    let ignore = terms(req.ignore.into_iter())?;
+
+    let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
+    search_term::replace(&txn, user_id, boost, ignore)
+        .await
+        .kind(ErrorKind::Internal)?;
+    txn.commit().await.kind(ErrorKind::Internal)?;
+
+    Ok(Json(SearchTermsWriteResp {}))
+}
+
+fn terms(terms: impl Iterator<Item = String>) -> Result<Vec<String>, Json<Error>> {
+    let mut res: Vec<String> = vec![];
+    for term in terms {
+        if term.trim().is_empty() || res.contains(&term) {
+            continue;
+        }
+        if term.chars().count() > SEARCH_MAX_TERM_CHARS {
+            return Err(Json(Error {
+                error: ErrorKind::MalformedRequest,
+                reason: format!("A term is longer than {} characters", SEARCH_MAX_TERM_CHARS),
+            }));
+        }
+        res.push(term);
+    }
+    if res.len() > SEARCH_MAX_TERMS {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!("At most {} terms of each kind", SEARCH_MAX_TERMS),
+        }));
+    }
+    Ok(res)
+}
@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{prelude::*, session};
+use schemars::JsonSchema;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{SessionId, UserId},
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct SessionListReq {}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SessionListResp {
+    pub list: Vec<SessionListRespItem>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SessionListRespItem {
+    pub id: i32,
+    /// Browser and OS, e.g. "Firefox on Linux"
+    pub label: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: u32,
+    /// Updated when the device refresh its token, about every 15 minutes
+    pub last_seen_at: Option<u32>,
+    /// The session of this request
+    pub current: bool,
+}
+
+/// Signed in devices of the current user, most recently seen first
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    current: Option<Extension<SessionId>>,
+    Json(_): Json<SessionListReq>,
+) -> JsonResult<SessionListResp> {
+    let current = current.map(|Extension(SessionId(x))| x);
+    let now = time::UtcDateTime::now().unix_timestamp();
+
+    let list = Session::find()
+        .filter(session::Column::UserId.eq(user_id))
+        .filter(session::Column::ExpiresAt.gte(now))
+        .order_by_desc(session::Column::LastSeenAt)
+        .order_by_desc(session::Column::Id)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .into_iter()
+        .map(|x| SessionListRespItem {
+            current: current == Some(x.id),
+            id: x.id,
+            label: x.label,
+            ip: x.ip,
+            created_at: x.created_at as u32,
+            last_seen_at: x.last_seen_at.map(|x| x as u32),
+        })
+        .collect();
+
+    Ok(Json(SessionListResp { list }))
+}
@@ -0,0 +1,27 @@
+use std::sync::Arc;
+
+use axum::{Router, routing::post};
+
+use crate::{AppState, utils::openapi::Builder};
+
+mod list;
+mod revoke;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/list", post(list::route))
+        .route("/revoke", post(revoke::route))
+}
+
+pub fn spec(api: &mut Builder) {
+    api.op(
+        "POST",
+        "/user/sessions/list",
+        "Signed in devices, most recently seen first",
+    )
+    .body::<list::SessionListReq>()
+    .json::<list::SessionListResp>();
+    api.op("POST", "/user/sessions/revoke", "Sign out a device")
+        .body::<revoke::SessionRevokeReq>()
+        .json::<revoke::SessionRevokeResp>();
+}
@@ -0,0 +1,35 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId, utils::session};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct SessionRevokeReq {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SessionRevokeResp {
+    /// false if the session does not exist or belong to someone else
+    pub revoked: bool,
+}
+
+/// Sign out a device, its access token stop working right away
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<SessionRevokeReq>,
+) -> JsonResult<SessionRevokeResp> {
+    let revoked = session::revoke_by_id(&app.conn, user_id, req.id)
+        .await
+        .kind(ErrorKind::Internal)?;
+    app.cache.sessions.invalidate(&req.id);
+
+    Ok(Json(SessionRevokeResp { revoked }))
+}
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{MessageKind, chat, context_stat, message, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{
+    JoinType, QueryOrder, QuerySelect,
+    prelude::*,
+    sea_query::{Alias, Expr},
+};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId, utils::sql};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct UserStatsReq {}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct UserStatsResp {
+    pub chats: u32,
+    pub user_messages: u32,
+    pub assistant_messages: u32,
+    /// Completions of the tool loops, several per assistant message with tools
+    pub completions: u32,
+    /// Completions cut by the upstream timeouts or the output limit
+    pub truncations: u32,
+    /// Most used first
+    pub models: Vec<UserStatsRespModel>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct UserStatsRespModel {
+    pub model_id: String,
+    pub messages: u32,
+}
+
+/// Usage counts of the user, never the content of a chat
+///
+/// Reachable with the `stats` scope of API keys, for external dashboards
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(_): Json<UserStatsReq>,
+) -> JsonResult<UserStatsResp> {
+    let chats = Chat::find()
+        .filter(chat::Column::OwnerId.eq(user_id))
+        .count(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    let messages = || {
+        Message::find()
+            .join(JoinType::InnerJoin, message::Relation::Chat.def())
+            .filter(chat::Column::OwnerId.eq(user_id))
+    };
+    let kinds = messages()
+        .select_only()
+        .column(message::Column::Kind)
+        .column_as(message::Column::Id.count(), "count")
+        .group_by(message::Column::Kind)
+        .into_tuple::<(MessageKind, i64)>()
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let of_kind = |kind| {
+        kinds
+            .iter()
+            .find(|(x, _)| *x == kind)
+            .map_or(0, |(_, count)| *count as u32)
+    };
+
+    let backend = app.conn.get_database_backend();
+    let model_id = Expr::cust(sql::json_text(backend, "message.generation", "model_id"));
+    let models = messages()
+        .select_only()
+        .column_as(model_id.clone(), "model_id")
+        .column_as(message::Column::Id.count(), "count")
+        .filter(message::Column::Generation.is_not_null())
+        .group_by(model_id)
+        .order_by_desc(Expr::col(Alias::new("count")))
+        .into_tuple::<(String, i64)>()
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .into_iter()
+        .map(|(model_id, messages)| UserStatsRespModel {
+            model_id,
+            messages: messages as u32,
+        })
+        .collect();
+
+    let (completions, truncations) = ContextStat::find()
+        .join(JoinType::InnerJoin, context_stat::Relation::Chat.def())
+        .filter(chat::Column::OwnerId.eq(user_id))
+        .select_only()
+        .column_as(context_stat::Column::Completions.sum(), "completions")
+        .column_as(context_stat::Column::Truncations.sum(), "truncations")
+        .into_tuple::<(Option<i64>, Option<i64>)>()
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .unwrap_or_default();
+
+    Ok(Json(UserStatsResp {
+        chats: chats as u32,
+        user_messages: of_kind(MessageKind::User),
+        assistant_messages: of_kind(MessageKind::Assistant),
+        completions: completions.unwrap_or_default() as u32,
+        truncations: truncations.unwrap_or_default() as u32,
+        models,
+    }))
+}
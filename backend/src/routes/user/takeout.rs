@@ -0,0 +1,32 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use schemars::JsonSchema;
+use serde::Serialize;
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, jobs::Task, middlewares::auth::UserId};
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct UserTakeoutResp {
+    /// false if a takeout of the user is already being built
+    pub queued: bool,
+}
+
+/// Queue the takeout, a `takeout` notification tells when it is ready
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+) -> JsonResult<UserTakeoutResp> {
+    let queued = app
+        .jobs
+        .push_once(
+            &app.conn,
+            Task::Takeout { user_id },
+            &format!("takeout:{}", user_id),
+        )
+        .await
+        .kind(ErrorKind::Internal)?;
+    Ok(Json(UserTakeoutResp { queued }))
+}
@@ -2,22 +2,32 @@ use std::sync::Arc;
 
 use axum::{Extension, Json, extract::State};
 use entity::{UserPreference, prelude::*};
+use schemars::JsonSchema;
 use sea_orm::{ActiveModelTrait, EntityTrait, IntoActiveModel, TransactionTrait};
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-use crate::{AppState, errors::*, middlewares::auth::UserId};
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::UserId,
+    utils::{self, email_verification, session},
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[typeshare]
 pub struct UserUpdateReq {
     /// If omit will use the current user instead
     pub user_id: Option<i32>,
     pub preference: Option<UserPreference>,
     pub password: Option<String>,
+    /// Where password reset links are mailed, empty to remove it
+    ///
+    /// A new address must be verified with a mailed link before sending messages
+    pub email: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[typeshare]
 pub struct UserUpdateResp {
     pub user_id: i32,
@@ -32,14 +42,24 @@ pub async fn route(
         user_id: user_id_req,
         preference,
         password,
+        email,
     } = req;
     let user_id = user_id_req.unwrap_or(user_id);
 
     debug_assert!(
-        preference.is_some() || password.is_some(),
+        preference.is_some() || password.is_some() || email.is_some(),
         "no field to update"
     );
 
+    if let Some(found) = preference.as_ref().map(utils::preference::problems)
+        && !found.is_empty()
+    {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: found.join("; "),
+        }));
+    }
+
     let txn = app.conn.begin().await.kind(ErrorKind::Internal)?;
 
     let res = User::find_by_id(user_id)
@@ -62,16 +82,72 @@ pub async fn route(
         if let Some(language) = preference.submit_on_enter {
             new_preference.submit_on_enter = Some(language);
         }
+        if let Some(voice) = preference.voice {
+            new_preference.voice = Some(voice);
+        }
+        if let Some(speed) = preference.voice_speed {
+            new_preference.voice_speed = Some(speed);
+        }
+        if let Some(days) = preference.retention_days {
+            new_preference.retention_days = Some(days);
+        }
+        if let Some(timezone) = preference.timezone {
+            new_preference.timezone = Some(timezone);
+        }
+        if let Some(offset) = preference.utc_offset {
+            new_preference.utc_offset = Some(offset);
+        }
+        if let Some(model_id) = preference.default_model_id {
+            new_preference.default_model_id = Some(model_id);
+        }
+        if let Some(notify) = preference.notify_replies {
+            new_preference.notify_replies = Some(notify);
+        }
+        if let Some(notify) = preference.notify_schedules {
+            new_preference.notify_schedules = Some(notify);
+        }
         active_model.preference = sea_orm::ActiveValue::Set(new_preference);
+        // a client holding the old version has to read them again
+        let version = *active_model.preference_version.as_ref() + 1;
+        active_model.preference_version = sea_orm::ActiveValue::Set(version);
     }
     if let Some(password) = password {
-        let password_hash = app.hasher.hash_password(&password);
+        let password_hash = app.hasher.hash_password(&password).await;
         active_model.password = sea_orm::ActiveValue::Set(password_hash);
+        // whoever had the old password should not stay signed in
+        session::revoke_all(&txn, user_id)
+            .await
+            .kind(ErrorKind::Internal)?;
+    }
+
+    let mut verification = None;
+    if let Some(email) = email {
+        let email = email.trim();
+        // removing the address does not lift the restriction, set it again to
+        // get a new link
+        if app.mailer.is_some()
+            && !email.is_empty()
+            && (active_model.email.as_ref().as_deref() != Some(email)
+                || !*active_model.email_verified.as_ref())
+        {
+            active_model.email_verified = sea_orm::ActiveValue::Set(false);
+            let token = email_verification::create(&txn, user_id, email)
+                .await
+                .kind(ErrorKind::Internal)?;
+            verification = Some((email.to_owned(), token));
+        }
+        active_model.email =
+            sea_orm::ActiveValue::Set((!email.is_empty()).then(|| email.to_owned()));
     }
 
     active_model.update(&txn).await.kind(ErrorKind::Internal)?;
 
     txn.commit().await.kind(ErrorKind::Internal)?;
+    app.cache.forget_user(user_id);
+
+    if let Some((email, token)) = verification {
+        email_verification::send(&app, user_id, email, token).await;
+    }
 
     Ok(Json(UserUpdateResp { user_id }))
 }
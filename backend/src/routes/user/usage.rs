@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{UserRole, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::EntityTrait;
+use serde::Serialize;
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::UserId,
+    quota::{self, QuotaLimit, QuotaUsed},
+    spend::cap,
+};
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct UserUsageResp {
+    /// Admins are never limited, their usage is still counted
+    pub exempt: bool,
+    /// The current UTC day
+    pub daily: UserUsageRespPeriod,
+    /// The current UTC month
+    pub monthly: UserUsageRespPeriod,
+    /// USD spent this month against the spending cap
+    pub spend: UserUsageRespSpend,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct UserUsageRespPeriod {
+    pub used: QuotaUsed,
+    /// None for no limit
+    pub limit: QuotaLimit,
+    /// Left before the limit, None for no limit
+    pub remaining: QuotaLimit,
+    /// unix seconds
+    pub resets_at: i64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct UserUsageRespSpend {
+    pub spent: f64,
+    /// None for no cap, models can still have their own
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cap: Option<f64>,
+}
+
+/// What the user used of their quotas and what is left
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+) -> JsonResult<UserUsageResp> {
+    let user = User::find_by_id(user_id)
+        .one(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("Cannot find user")
+        .kind(ErrorKind::ResourceNotFound)?;
+    let exempt = user.role == UserRole::Admin;
+    let limits = match exempt {
+        true => Default::default(),
+        false => app
+            .quotas
+            .limits(&app.conn, user_id)
+            .await
+            .kind(ErrorKind::Internal)?,
+    };
+    let (today, month) = quota::used(&app.conn, user_id)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let ((_, tomorrow), (_, next_month)) = quota::periods();
+    let spend = UserUsageRespSpend {
+        spent: cap::user_spent(&app.conn, user_id, cap::month())
+            .await
+            .kind(ErrorKind::Internal)?,
+        cap: match exempt {
+            true => None,
+            false => app
+                .spend_caps
+                .user_cap(&app.conn, user_id)
+                .await
+                .kind(ErrorKind::Internal)?,
+        },
+    };
+
+    let period = |used: QuotaUsed, limit: QuotaLimit, resets_at| UserUsageRespPeriod {
+        used,
+        limit,
+        remaining: QuotaLimit {
+            messages: limit.messages.map(|x| x.saturating_sub(used.messages)),
+            tokens: limit.tokens.map(|x| (x - used.tokens).max(0)),
+            tool_calls: limit.tool_calls.map(|x| x.saturating_sub(used.tool_calls)),
+        },
+        resets_at,
+    };
+    Ok(Json(UserUsageResp {
+        exempt,
+        daily: period(today, limits.daily, tomorrow),
+        monthly: period(month, limits.monthly, next_month),
+        spend,
+    }))
+}
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{WebhookEvent, WebhookEvents, prelude::*, webhook};
+use schemars::JsonSchema;
+use sea_orm::{ActiveValue::Set, prelude::*};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, config::WEBHOOK_MAX_PER_USER, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct WebhookCreateReq {
+    /// http or https, never to loopback nor link-local addresses; those of
+    /// private networks, e.g. Home Assistant, only if admins allow them
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct WebhookCreateResp {
+    pub id: i32,
+    /// Only shown here, key of the HMAC-SHA256 in `X-Llumen-Signature`
+    pub secret: String,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<WebhookCreateReq>,
+) -> JsonResult<WebhookCreateResp> {
+    let url = url::Url::parse(req.url.trim()).kind(ErrorKind::MalformedRequest)?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "webhooks are http or https URLs".to_owned(),
+        }));
+    }
+    let private = app.settings.current().webhook_private_networks;
+    if let Err(err) = crate::webhook::resolve(&url, private).await {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: err.to_string(),
+        }));
+    }
+    let mut events = req.events;
+    events.sort_by_key(|x| *x as u8);
+    events.dedup();
+    if events.is_empty() {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: "a webhook listens to at least one event".to_owned(),
+        }));
+    }
+
+    let count = Webhook::find()
+        .filter(webhook::Column::UserId.eq(user_id))
+        .count(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+    if count >= WEBHOOK_MAX_PER_USER {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!("a user has at most {} webhooks", WEBHOOK_MAX_PER_USER),
+        }));
+    }
+
+    let secret = crate::webhook::secret().kind(ErrorKind::Internal)?;
+    let res = Webhook::insert(webhook::ActiveModel {
+        user_id: Set(user_id),
+        url: Set(url.to_string()),
+        secret: Set(secret.clone()),
+        events: Set(WebhookEvents(events)),
+        created_at: Set(time::UtcDateTime::now().unix_timestamp()),
+        ..Default::default()
+    })
+    .exec(&app.conn)
+    .await
+    .kind(ErrorKind::Internal)?;
+
+    Ok(Json(WebhookCreateResp {
+        id: res.last_insert_id,
+        secret,
+    }))
+}
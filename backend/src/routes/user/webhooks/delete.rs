@@ -0,0 +1,41 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{prelude::*, webhook};
+use schemars::JsonSchema;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct WebhookDeleteReq {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct WebhookDeleteResp {
+    /// false if the webhook does not exist or belong to someone else
+    pub deleted: bool,
+}
+
+/// Deliveries still queued are dropped
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(req): Json<WebhookDeleteReq>,
+) -> JsonResult<WebhookDeleteResp> {
+    let res = Webhook::delete_many()
+        .filter(webhook::Column::Id.eq(req.id))
+        .filter(webhook::Column::UserId.eq(user_id))
+        .exec(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    Ok(Json(WebhookDeleteResp {
+        deleted: res.rows_affected > 0,
+    }))
+}
@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{WebhookEvent, prelude::*, webhook};
+use schemars::JsonSchema;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{AppState, errors::*, middlewares::auth::UserId};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[typeshare]
+pub struct WebhookListReq {}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct WebhookListResp {
+    pub list: Vec<WebhookListRespItem>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct WebhookListRespItem {
+    pub id: i32,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub created_at: u32,
+    /// Latest attempt to deliver an event, successful or not
+    pub last_delivery_at: Option<u32>,
+    /// Why the latest attempt failed, it is retried with a backoff
+    pub last_error: Option<String>,
+}
+
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Json(_): Json<WebhookListReq>,
+) -> JsonResult<WebhookListResp> {
+    let list = Webhook::find()
+        .filter(webhook::Column::UserId.eq(user_id))
+        .order_by_desc(webhook::Column::Id)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .into_iter()
+        .map(|x| WebhookListRespItem {
+            id: x.id,
+            url: x.url,
+            events: x.events.0,
+            created_at: x.created_at as u32,
+            last_delivery_at: x.last_delivery_at.map(|x| x as u32),
+            last_error: x.last_error,
+        })
+        .collect();
+
+    Ok(Json(WebhookListResp { list }))
+}
@@ -0,0 +1,36 @@
+use std::sync::Arc;
+
+use axum::{Router, routing::post};
+
+use crate::{AppState, utils::openapi::Builder};
+
+mod create;
+mod delete;
+mod list;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/create", post(create::route))
+        .route("/list", post(list::route))
+        .route("/delete", post(delete::route))
+}
+
+pub fn spec(api: &mut Builder) {
+    api.op(
+        "POST",
+        "/user/webhooks/create",
+        "Register a URL called on events",
+    )
+    .body::<create::WebhookCreateReq>()
+    .json::<create::WebhookCreateResp>();
+    api.op(
+        "POST",
+        "/user/webhooks/list",
+        "Webhooks of the user, newest first",
+    )
+    .body::<list::WebhookListReq>()
+    .json::<list::WebhookListResp>();
+    api.op("POST", "/user/webhooks/delete", "Stop calling a webhook")
+        .body::<delete::WebhookDeleteReq>()
+        .json::<delete::WebhookDeleteResp>();
+}
@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use entity::{prelude::*, workspace, workspace_member};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, sea_query::Query};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct WorkspaceListReq {}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct WorkspaceListResp {
+    pub list: Vec<WorkspaceListRespItem>,
+    /// The workspace of the token
+    pub current_id: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct WorkspaceListRespItem {
+    pub id: i32,
+    pub name: String,
+}
+
+/// Workspaces the user is a member of
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Extension(WorkspaceId(workspace_id)): Extension<WorkspaceId>,
+    Json(_): Json<WorkspaceListReq>,
+) -> JsonResult<WorkspaceListResp> {
+    let list = Workspace::find()
+        .filter(
+            workspace::Column::Id.in_subquery(
+                Query::select()
+                    .column(workspace_member::Column::WorkspaceId)
+                    .from(WorkspaceMember)
+                    .and_where(workspace_member::Column::UserId.eq(user_id))
+                    .to_owned(),
+            ),
+        )
+        .order_by_asc(workspace::Column::Id)
+        .all(&app.conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .into_iter()
+        .map(|x| WorkspaceListRespItem {
+            id: x.id,
+            name: x.name,
+        })
+        .collect();
+
+    Ok(Json(WorkspaceListResp {
+        list,
+        current_id: workspace_id,
+    }))
+}
@@ -0,0 +1,60 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::State};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    errors::*,
+    middlewares::auth::{SessionId, UserId},
+    utils::{session, workspace},
+};
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct WorkspaceSelectReq {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct WorkspaceSelectResp {
+    /// Access token of the workspace, the one of the request still works in
+    /// the previous workspace until it expires
+    pub token: String,
+    pub exp: String,
+}
+
+/// Work in another workspace on this device, the refresh token is unchanged
+pub async fn route(
+    State(app): State<Arc<AppState>>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    session_id: Option<Extension<SessionId>>,
+    Json(req): Json<WorkspaceSelectReq>,
+) -> JsonResult<WorkspaceSelectResp> {
+    let Some(Extension(SessionId(session_id))) = session_id else {
+        return Err(Json(Error {
+            error: ErrorKind::Unauthorized,
+            reason: "sign in again to switch workspace".to_owned(),
+        }));
+    };
+    if !workspace::is_member(&app.conn, req.id, user_id)
+        .await
+        .kind(ErrorKind::Internal)?
+    {
+        return Err(Json(Error {
+            error: ErrorKind::ResourceNotFound,
+            reason: "not a member of this workspace".to_owned(),
+        }));
+    }
+
+    session::select_workspace(&app.conn, session_id, req.id)
+        .await
+        .kind(ErrorKind::Internal)?;
+    let (token, exp) = session::access_token(&app.conn, &app.keyring, user_id, session_id)
+        .await
+        .kind(ErrorKind::Internal)?;
+
+    Ok(Json(WorkspaceSelectResp { token, exp }))
+}
@@ -0,0 +1,223 @@
+use std::{future::pending, sync::Arc, time::Duration};
+
+use axum::{
+    Extension, Json,
+    extract::{Request, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::{select, sync::mpsc};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    config::SSE_KEEP_ALIVE,
+    errors::*,
+    middlewares::auth::{UserId, WorkspaceId, verify},
+    notify::Online,
+    routes::{
+        chat::halt::{self, ChatHaltReq, ChatHaltResp},
+        message::create::{self, MessageCreateReq, MessageCreateResp},
+    },
+    sse::{SseEvent, Subscriber},
+    utils::{
+        member,
+        websocket::{self, Frame, Io},
+    },
+};
+
+/// Client message, `auth` must come first
+#[derive(Debug, Deserialize)]
+#[typeshare]
+#[serde(tag = "t", content = "c", rename_all = "snake_case")]
+pub enum WsReq {
+    Auth(WsReqAuth),
+    /// replace the current subscription
+    Subscribe(WsReqSubscribe),
+    Send(MessageCreateReq),
+    Halt(ChatHaltReq),
+}
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct WsReqAuth {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[typeshare]
+pub struct WsReqSubscribe {
+    pub chat_id: i32,
+    /// same as the `Last-Event-ID` header of `/api/chat/sse`
+    pub last_event_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+#[serde(tag = "t", content = "c", rename_all = "snake_case")]
+pub enum WsResp {
+    Event(WsRespEvent),
+    Created(MessageCreateResp),
+    Halted(ChatHaltResp),
+    Error(Error),
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct WsRespEvent {
+    pub chat_id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub event: SseEvent,
+}
+
+pub async fn route(State(app): State<Arc<AppState>>, req: Request) -> Result<Response, StatusCode> {
+    websocket::upgrade(req, move |io| session(app, io))
+}
+
+#[derive(Default)]
+struct Session {
+    /// The user and their workspace once authenticated
+    user_id: Option<(i32, i32)>,
+    /// Keeps pushes to the user off while the socket is open
+    online: Option<Online>,
+    sub: Option<(i32, Subscriber)>,
+}
+
+async fn session(app: Arc<AppState>, io: Io) {
+    let (mut reader, mut writer) = websocket::split(io);
+
+    // reading a frame is not cancel safe, so it cannot be raced in select
+    let (tx, mut rx) = mpsc::channel(8);
+    let read_task = tokio::spawn(async move {
+        loop {
+            let frame = reader.recv().await;
+            let stop = !matches!(frame, Ok(Frame::Text(_) | Frame::Ping(_)));
+            if tx.send(frame).await.is_err() || stop {
+                break;
+            }
+        }
+    });
+
+    let mut session = Session::default();
+    let mut ping = tokio::time::interval(Duration::from_secs(SSE_KEEP_ALIVE));
+    loop {
+        let resp = select! {
+            frame = rx.recv() => match frame {
+                Some(Ok(Frame::Text(text))) => handle(&app, &mut session, &text).await,
+                Some(Ok(Frame::Ping(payload))) => {
+                    if writer.pong(&payload).await.is_err() {
+                        break;
+                    }
+                    None
+                }
+                _ => break,
+            },
+            (chat_id, (id, res)) = next_event(&mut session.sub) => Some(WsResp::Event(WsRespEvent {
+                chat_id,
+                id: id.map(|x| x.to_string()),
+                event: SseEvent::from(res),
+            })),
+            _ = ping.tick() => {
+                if writer.ping().await.is_err() {
+                    break;
+                }
+                None
+            }
+        };
+
+        if let Some(resp) = resp {
+            let text = serde_json::to_string(&resp).unwrap();
+            if writer.text(&text).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    writer.close().await.ok();
+    read_task.abort();
+}
+
+async fn next_event(sub: &mut Option<(i32, Subscriber)>) -> (i32, crate::sse::Event) {
+    match sub {
+        Some((chat_id, sub)) => match sub.next().await {
+            Some(event) => (*chat_id, event),
+            None => pending().await,
+        },
+        None => pending().await,
+    }
+}
+
+async fn handle(app: &Arc<AppState>, session: &mut Session, text: &str) -> Option<WsResp> {
+    let req = match serde_json::from_str::<WsReq>(text) {
+        Ok(req) => req,
+        Err(err) => {
+            return Some(WsResp::Error(Error {
+                error: ErrorKind::MalformedRequest,
+                reason: err.to_string(),
+            }));
+        }
+    };
+
+    let res = match (req, session.user_id) {
+        (WsReq::Auth(auth), _) => verify(app, &auth.token).await.map(
+            |(UserId(user_id), WorkspaceId(workspace_id), ..)| {
+                session.user_id = Some((user_id, workspace_id));
+                session.online = Some(app.notifier.connect(user_id));
+                None
+            },
+        ),
+        (_, None) => Err(Json(Error {
+            error: ErrorKind::Unauthorized,
+            reason: "authenticate first".to_owned(),
+        })),
+        (WsReq::Subscribe(req), Some((user_id, workspace_id))) => {
+            subscribe(app, user_id, workspace_id, req).await.map(|sub| {
+                session.sub = Some(sub);
+                None
+            })
+        }
+        (WsReq::Send(req), Some((user_id, workspace_id))) => {
+            // API keys cannot reach the socket
+            create::route(
+                State(app.clone()),
+                Extension(UserId(user_id)),
+                Extension(WorkspaceId(workspace_id)),
+                None,
+                HeaderMap::new(),
+                Json(req),
+            )
+            .await
+            .map(|Json(x)| Some(WsResp::Created(x)))
+        }
+        (WsReq::Halt(req), Some((user_id, workspace_id))) => halt::route(
+            State(app.clone()),
+            Extension(UserId(user_id)),
+            Extension(WorkspaceId(workspace_id)),
+            Json(req),
+        )
+        .await
+        .map(|Json(x)| Some(WsResp::Halted(x))),
+    };
+
+    res.unwrap_or_else(|Json(err)| Some(WsResp::Error(err)))
+}
+
+async fn subscribe(
+    app: &AppState,
+    user_id: i32,
+    workspace_id: i32,
+    req: WsReqSubscribe,
+) -> Result<(i32, Subscriber), Json<Error>> {
+    member::find(&app.conn, req.chat_id, user_id, workspace_id).await?;
+
+    let last_event_id = req.last_event_id.and_then(|x| x.parse().ok());
+    let sub = app
+        .sse
+        .subscribe(req.chat_id, last_event_id)
+        .await
+        .kind(ErrorKind::MalformedRequest)?;
+    Ok((req.chat_id, sub))
+}
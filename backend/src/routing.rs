@@ -0,0 +1,171 @@
+//! Rules a user routes messages to other models with, set at `user/routing`
+//!
+//! Before a reply is streamed, the rules of the sender are tried in order and
+//! the first that matches picks the model of this reply, as long as the
+//! workspace of the chat offers it. The chat keeps its own model, the next
+//! message is routed anew. Reproducible chats are never routed, they pin the
+//! model of their first reply
+
+use anyhow::Result;
+use entity::{
+    ChunkKind, MessageKind, RoutingCondition, chat, chunk, message, prelude::*, routing_rule,
+};
+use sea_orm::{ActiveValue::Set, ConnectionTrait, QueryOrder, QuerySelect, prelude::*};
+
+use crate::{
+    config::{ROUTING_CHARS_PER_TOKEN, ROUTING_CODE_LINES},
+    utils::{branch, workspace},
+};
+
+/// Starts of lines that are code rather than prose
+const CODE_STARTS: &[&str] = &[
+    "fn ",
+    "def ",
+    "function ",
+    "class ",
+    "import ",
+    "from ",
+    "#include",
+    "return ",
+    "let ",
+    "const ",
+    "var ",
+    "pub ",
+    "public ",
+    "private ",
+    "select ",
+    "if (",
+    "for (",
+    "while (",
+];
+
+/// Rules of the user in the order they are tried
+pub async fn list(conn: &impl ConnectionTrait, user_id: i32) -> Result<Vec<routing_rule::Model>> {
+    Ok(RoutingRule::find()
+        .filter(routing_rule::Column::UserId.eq(user_id))
+        .order_by_asc(routing_rule::Column::Position)
+        .all(conn)
+        .await?)
+}
+
+/// Replace every rule of the user, tried in the order given
+pub async fn replace(
+    conn: &impl ConnectionTrait,
+    user_id: i32,
+    rules: Vec<(RoutingCondition, i32)>,
+) -> Result<()> {
+    RoutingRule::delete_many()
+        .filter(routing_rule::Column::UserId.eq(user_id))
+        .exec(conn)
+        .await?;
+    let rules: Vec<_> = rules
+        .into_iter()
+        .enumerate()
+        .map(
+            |(position, (condition, model_id))| routing_rule::ActiveModel {
+                user_id: Set(user_id),
+                position: Set(position as i32),
+                condition: Set(condition),
+                model_id: Set(model_id),
+                ..Default::default()
+            },
+        )
+        .collect();
+    if !rules.is_empty() {
+        RoutingRule::insert_many(rules).exec(conn).await?;
+    }
+    Ok(())
+}
+
+/// Model the reply to `text` is routed to, None to keep the model of the chat
+pub async fn route(
+    conn: &DbConn,
+    user_id: i32,
+    chat: &chat::Model,
+    text: &str,
+) -> Result<Option<i32>> {
+    if chat.reproducible {
+        return Ok(None);
+    }
+    let rules = list(conn, user_id).await?;
+    if rules.is_empty() {
+        return Ok(None);
+    }
+    for rule in rules {
+        if rule.model_id == chat.model_id || !matches(conn, chat.id, &rule.condition, text).await? {
+            continue;
+        }
+        // a rule may name a model the workspace of this chat does not offer
+        if workspace::allows_model(conn, chat.workspace_id, rule.model_id).await? {
+            tracing::debug!(
+                "chat {} routed to model {} by {:?}",
+                chat.id,
+                rule.model_id,
+                rule.condition
+            );
+            return Ok(Some(rule.model_id));
+        }
+    }
+    Ok(None)
+}
+
+async fn matches(
+    conn: &DbConn,
+    chat_id: i32,
+    condition: &RoutingCondition,
+    text: &str,
+) -> Result<bool> {
+    Ok(match condition {
+        RoutingCondition::Code => is_code(text),
+        RoutingCondition::Tokens(min) => {
+            let history = ContextStat::find_by_id(chat_id)
+                .one(conn)
+                .await?
+                .map(|x| x.last_prompt_tokens)
+                .unwrap_or_default();
+            let tokens = history + (text.chars().count() / ROUTING_CHARS_PER_TOKEN) as i64;
+            tokens >= *min as i64
+        }
+        RoutingCondition::ToolCalls(min) => tool_calls(conn, chat_id).await? >= *min as u64,
+        RoutingCondition::Keyword(keyword) => text.to_lowercase().contains(&keyword.to_lowercase()),
+    })
+}
+
+fn is_code(text: &str) -> bool {
+    if text.contains("```") {
+        return true;
+    }
+    let lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| {
+            line.ends_with([';', '{', '}'])
+                || CODE_STARTS
+                    .iter()
+                    .any(|x| line.to_lowercase().starts_with(x))
+        })
+        .count();
+    lines >= ROUTING_CODE_LINES
+}
+
+/// Tools called by the last reply of the active branch
+async fn tool_calls(conn: &DbConn, chat_id: i32) -> Result<u64> {
+    let ids = branch::active(conn, chat_id).await?;
+    let last = Message::find()
+        .select_only()
+        .column(message::Column::Id)
+        .filter(message::Column::Id.is_in(ids))
+        .filter(message::Column::Kind.eq(MessageKind::Assistant))
+        .order_by_desc(message::Column::Id)
+        .into_tuple::<i32>()
+        .one(conn)
+        .await?;
+    let Some(last) = last else {
+        return Ok(0);
+    };
+    Ok(Chunk::find()
+        .filter(chunk::Column::MessageId.eq(last))
+        .filter(chunk::Column::Kind.eq(ChunkKind::ToolCall))
+        .count(conn)
+        .await?)
+}
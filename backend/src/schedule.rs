@@ -0,0 +1,195 @@
+//! Scheduled tasks, a prompt sent to the agent on a cron expression
+//!
+//! Due tasks are checked every [`SCHEDULE_INTERVAL`] and their runs queued as
+//! jobs of `jobs`. A run sends the prompt in the chat of the task as its owner
+//! would, in agent mode, so quotas and the spend guard apply and members
+//! following the chat see it stream; the owner is also notified and told in
+//! their inbox, see `notify`, and so are their webhooks and, without a stream open, their browsers (see
+//! `push`). Runs missed while the server was down run once, then the task
+//! follow its expression again
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use axum::{Extension, Json, extract::State, http::HeaderMap};
+use entity::{InboxSource, chat, prelude::*, schedule};
+use sea_orm::{ActiveValue::Set, TransactionTrait, prelude::*};
+
+use crate::{
+    AppState,
+    config::SCHEDULE_INTERVAL,
+    jobs::Task,
+    middlewares::auth::{UserId, WorkspaceId},
+    notify::{Notification, NotificationScheduleRun, inbox},
+    push,
+    routes::message::create::{self, MessageCreateReq, MessageCreateReqMode},
+    utils::{cron::Cron, workspace},
+    webhook,
+};
+
+pub fn spawn_runner(app: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(SCHEDULE_INTERVAL));
+        loop {
+            interval.tick().await;
+            if let Err(err) = run_due(&app).await {
+                tracing::warn!("cannot run scheduled tasks: {}", err);
+            }
+        }
+    });
+}
+
+/// When a task run next after now, None if its expression never match
+pub fn next_run(cron: &str, utc_offset: i32) -> Option<i64> {
+    let now = time::UtcDateTime::now().unix_timestamp();
+    Cron::parse(cron).ok()?.next(now, utc_offset)
+}
+
+async fn run_due(app: &Arc<AppState>) -> Result<()> {
+    let now = time::UtcDateTime::now().unix_timestamp();
+    let due = Schedule::find()
+        .filter(schedule::Column::Enabled.eq(true))
+        .filter(schedule::Column::NextRunAt.lte(now))
+        .all(&app.conn)
+        .await?;
+    for task in due {
+        // moved on with its run queued, a run that did not send is not retried
+        // before its next time; the instance moving it queues it when several
+        // share the database
+        let next = next_run(&task.cron, task.utc_offset);
+        let txn = app.conn.begin().await?;
+        let claimed = Schedule::update_many()
+            .col_expr(schedule::Column::NextRunAt, next.into())
+            .col_expr(schedule::Column::Enabled, next.is_some().into())
+            .filter(schedule::Column::Id.eq(task.id))
+            .filter(schedule::Column::NextRunAt.eq(task.next_run_at))
+            .exec(&txn)
+            .await?;
+        if claimed.rows_affected == 0 {
+            continue;
+        }
+        let run = Task::Schedule {
+            schedule_id: task.id,
+        };
+        app.jobs.push(&txn, run).await?;
+        txn.commit().await?;
+    }
+    Ok(())
+}
+
+/// A run queued by the runner, skipped if the task was deleted since
+pub async fn run_job(app: &Arc<AppState>, schedule_id: i32) -> Result<()> {
+    if let Some(task) = Schedule::find_by_id(schedule_id).one(&app.conn).await? {
+        run(app, task).await?;
+    }
+    Ok(())
+}
+
+/// Send the prompt of the task now, the outcome is recorded on the task
+///
+/// Return the chat the prompt was sent in, or why it could not be
+pub async fn run(
+    app: &Arc<AppState>,
+    task: schedule::Model,
+) -> Result<(Option<i32>, Option<String>)> {
+    let res = send(app, &task).await;
+    let (chat_id, error) = match res {
+        Ok(chat_id) => (Some(chat_id), None),
+        Err(err) => {
+            tracing::info!("scheduled task {} did not run: {}", task.id, err);
+            (None, Some(err))
+        }
+    };
+    Schedule::update_many()
+        .col_expr(
+            schedule::Column::LastRunAt,
+            time::UtcDateTime::now().unix_timestamp().into(),
+        )
+        .col_expr(schedule::Column::LastError, error.clone().into())
+        .filter(schedule::Column::Id.eq(task.id))
+        .exec(&app.conn)
+        .await?;
+    let run = NotificationScheduleRun {
+        schedule_id: task.id,
+        name: task.name,
+        chat_id,
+        error: error.clone(),
+    };
+    app.notifier
+        .send(task.owner_id, Notification::ScheduleRun(run.clone()));
+    let content = match &run.error {
+        Some(error) => format!("**{}** did not run: {}", run.name, error),
+        None => format!("**{}** ran, the reply is in its chat.", run.name),
+    };
+    if let Err(err) = inbox::post(app, task.owner_id, InboxSource::Schedule, content, chat_id).await
+    {
+        tracing::warn!("cannot post the run of task {}: {}", task.id, err);
+    }
+    if let Err(err) = push::schedule_run(app, task.owner_id, &run).await {
+        tracing::warn!("cannot queue the pushes of task {}: {}", task.id, err);
+    }
+    if let Err(err) = webhook::schedule_run(app, task.owner_id, run).await {
+        tracing::warn!("cannot queue the webhooks of task {}: {}", task.id, err);
+    }
+    Ok((chat_id, error))
+}
+
+/// Return the chat the prompt was sent in
+async fn send(app: &Arc<AppState>, task: &schedule::Model) -> Result<i32, String> {
+    let (chat_id, workspace_id) = match task.chat_id {
+        Some(chat_id) => {
+            let chat = Chat::find_by_id(chat_id)
+                .one(&app.conn)
+                .await
+                .map_err(|err| err.to_string())?
+                .ok_or("the chat was deleted")?;
+            (chat_id, chat.workspace_id)
+        }
+        None => {
+            // the first workspace of the owner, like their sign ins
+            let workspace_id = workspace::resolve(&app.conn, task.owner_id, None)
+                .await
+                .map_err(|err| err.to_string())?
+                .ok_or("the owner is in no workspace")?;
+            let chat_id = Chat::insert(chat::ActiveModel {
+                owner_id: Set(task.owner_id),
+                workspace_id: Set(workspace_id),
+                model_id: Set(task.model_id),
+                title: Set(Some(task.name.clone())),
+                reproducible: Set(false),
+                ..Default::default()
+            })
+            .exec(&app.conn)
+            .await
+            .map_err(|err| err.to_string())?
+            .last_insert_id;
+            Schedule::update_many()
+                .col_expr(schedule::Column::ChatId, chat_id.into())
+                .filter(schedule::Column::Id.eq(task.id))
+                .exec(&app.conn)
+                .await
+                .map_err(|err| err.to_string())?;
+            (chat_id, workspace_id)
+        }
+    };
+    // the reply streams on, it is not waited for
+    let Json(_) = create::route(
+        State(app.clone()),
+        Extension(UserId(task.owner_id)),
+        Extension(WorkspaceId(workspace_id)),
+        None,
+        HeaderMap::new(),
+        Json(MessageCreateReq {
+            chat_id,
+            mode: MessageCreateReqMode::Agent,
+            text: task.prompt.clone(),
+            files: vec![],
+        }),
+    )
+    .await
+    .map_err(|Json(err)| match err.reason.is_empty() {
+        true => format!("{:?}", err.error),
+        false => err.reason,
+    })?;
+    Ok(chat_id)
+}
@@ -0,0 +1,371 @@
+//! Monthly spending caps of users and models, see `SPEND_CAP_MONTHLY` env
+//!
+//! The cost of every completion is added to the user and the model it is for
+//! in `user_spend`. A user is capped by `SPEND_CAP_MONTHLY` unless an admin
+//! set their own cap, a model only by an admin, across every user. Caps are
+//! checked before every completion, admins are never capped. Going past
+//! [`SPEND_CAP_WARN_RATIO`] of a cap and past the cap itself are told once:
+//! to the user for their own cap, to the admins for a model, on their
+//! notification stream, in their inbox and by mail
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use dotenv::var;
+use entity::{InboxSource, UserRole, prelude::*, spend_cap, user, user_spend};
+use sea_orm::{
+    ActiveValue::Set,
+    ConnectionTrait, QuerySelect,
+    prelude::*,
+    sea_query::{Expr, OnConflict},
+};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    config::SPEND_CAP_WARN_RATIO,
+    notify::{Notification, NotificationSpendCap, inbox, mailer::Mail},
+    quota,
+};
+
+/// What a cap applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[typeshare]
+#[serde(tag = "t", content = "c", rename_all = "snake_case")]
+pub enum SpendCapTarget {
+    /// Every model the user chats with
+    User(i32),
+    /// Every user chatting with the model
+    Model(i32),
+}
+
+pub struct SpendCaps {
+    /// `SPEND_CAP_MONTHLY`
+    default: Option<f64>,
+}
+
+impl SpendCaps {
+    /// Unset, unparsable or not positive is no cap
+    pub fn from_env() -> Self {
+        Self {
+            default: var("SPEND_CAP_MONTHLY")
+                .ok()
+                .and_then(|x| x.parse().ok())
+                .filter(|x: &f64| *x > 0.0),
+        }
+    }
+
+    pub fn default(&self) -> Option<f64> {
+        self.default
+    }
+
+    /// Cap of the user, the one of an admin or the env
+    pub async fn user_cap(&self, conn: &impl ConnectionTrait, user_id: i32) -> Result<Option<f64>> {
+        Ok(get(conn, SpendCapTarget::User(user_id))
+            .await?
+            .or(self.default))
+    }
+
+    /// Why the user cannot have another completion of the model, if they
+    /// cannot
+    pub async fn exceeded(
+        &self,
+        conn: &impl ConnectionTrait,
+        user_id: i32,
+        model_id: i32,
+    ) -> Result<Option<String>> {
+        let month = month();
+        if let Some(cap) = self.user_cap(conn, user_id).await?
+            && user_spent(conn, user_id, month).await? >= cap
+        {
+            return Ok(Some(format!(
+                "Your monthly spending cap of {:.2} USD is reached",
+                cap
+            )));
+        }
+        if let Some(cap) = get(conn, SpendCapTarget::Model(model_id)).await?
+            && model_spent(conn, model_id, month).await? >= cap
+        {
+            return Ok(Some(format!(
+                "The monthly spending cap of {:.2} USD of this model is reached",
+                cap
+            )));
+        }
+        Ok(None)
+    }
+
+    /// Add the cost of a completion, telling whoever a cap it goes past
+    /// concerns
+    pub async fn add(
+        &self,
+        app: &Arc<AppState>,
+        user_id: i32,
+        model_id: i32,
+        cost: f64,
+    ) -> Result<()> {
+        if cost <= 0.0 {
+            return Ok(());
+        }
+        let conn = &app.conn;
+        let month = month();
+        UserSpend::insert(user_spend::ActiveModel {
+            user_id: Set(user_id),
+            model_id: Set(model_id),
+            month: Set(month),
+            cost: Set(cost),
+        })
+        .on_conflict(
+            OnConflict::columns([
+                user_spend::Column::UserId,
+                user_spend::Column::ModelId,
+                user_spend::Column::Month,
+            ])
+            .value(
+                user_spend::Column::Cost,
+                Expr::col((user_spend::Entity, user_spend::Column::Cost)).add(cost),
+            )
+            .to_owned(),
+        )
+        .exec(conn)
+        .await?;
+
+        if let Some(cap) = self.user_cap(conn, user_id).await? {
+            let spent = user_spent(conn, user_id, month).await?;
+            if let Some(reached) = crossed(cap, spent - cost, spent) {
+                let alert = NotificationSpendCap {
+                    model: None,
+                    spent,
+                    cap,
+                    reached,
+                };
+                tokio::spawn(alert_user(app.clone(), user_id, alert));
+            }
+        }
+        if let Some(cap) = get(conn, SpendCapTarget::Model(model_id)).await? {
+            let spent = model_spent(conn, model_id, month).await?;
+            if let Some(reached) = crossed(cap, spent - cost, spent) {
+                let name = Model::find_by_id(model_id)
+                    .one(conn)
+                    .await?
+                    .and_then(|x| x.get_config())
+                    .map(|x| x.display_name)
+                    .unwrap_or_else(|| format!("model {}", model_id));
+                let alert = NotificationSpendCap {
+                    model: Some(name),
+                    spent,
+                    cap,
+                    reached,
+                };
+                tokio::spawn(alert_admins(app.clone(), alert));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `before` to `after` went past the cap (true) or its warning
+/// (false)
+fn crossed(cap: f64, before: f64, after: f64) -> Option<bool> {
+    if before < cap && after >= cap {
+        return Some(true);
+    }
+    let warn = cap * SPEND_CAP_WARN_RATIO;
+    (before < warn && after >= warn).then_some(false)
+}
+
+/// Start of the current UTC month, unix seconds
+pub fn month() -> i64 {
+    quota::periods().1.0
+}
+
+/// USD the user spent in the month, on every model
+pub async fn user_spent(conn: &impl ConnectionTrait, user_id: i32, month: i64) -> Result<f64> {
+    spent(conn, user_spend::Column::UserId.eq(user_id), month).await
+}
+
+/// USD every user spent on the model in the month
+pub async fn model_spent(conn: &impl ConnectionTrait, model_id: i32, month: i64) -> Result<f64> {
+    spent(conn, user_spend::Column::ModelId.eq(model_id), month).await
+}
+
+async fn spent(
+    conn: &impl ConnectionTrait,
+    filter: sea_orm::sea_query::SimpleExpr,
+    month: i64,
+) -> Result<f64> {
+    Ok(UserSpend::find()
+        .select_only()
+        .column_as(user_spend::Column::Cost.sum(), "total")
+        .filter(filter)
+        .filter(user_spend::Column::Month.eq(month))
+        .into_tuple::<Option<f64>>()
+        .one(conn)
+        .await?
+        .flatten()
+        .unwrap_or_default())
+}
+
+fn column(target: SpendCapTarget) -> (spend_cap::Column, i32) {
+    match target {
+        SpendCapTarget::User(id) => (spend_cap::Column::UserId, id),
+        SpendCapTarget::Model(id) => (spend_cap::Column::ModelId, id),
+    }
+}
+
+/// The cap set by an admin alone, without the env
+pub async fn get(
+    conn: &impl ConnectionTrait,
+    target: SpendCapTarget,
+) -> Result<Option<f64>, DbErr> {
+    let (col, id) = column(target);
+    Ok(SpendCap::find()
+        .filter(col.eq(id))
+        .one(conn)
+        .await?
+        .map(|x| x.monthly))
+}
+
+/// Every cap set by an admin
+pub async fn list(conn: &impl ConnectionTrait) -> Result<Vec<(SpendCapTarget, f64)>, DbErr> {
+    Ok(SpendCap::find()
+        .all(conn)
+        .await?
+        .into_iter()
+        .filter_map(|x| {
+            let target = match (x.user_id, x.model_id) {
+                (Some(id), _) => SpendCapTarget::User(id),
+                (None, Some(id)) => SpendCapTarget::Model(id),
+                (None, None) => return None,
+            };
+            Some((target, x.monthly))
+        })
+        .collect())
+}
+
+/// Replace the cap set by an admin, None for a user follows the env
+pub async fn set(
+    conn: &impl ConnectionTrait,
+    target: SpendCapTarget,
+    monthly: Option<f64>,
+) -> Result<(), DbErr> {
+    let (col, id) = column(target);
+    SpendCap::delete_many()
+        .filter(col.eq(id))
+        .exec(conn)
+        .await?;
+    let Some(monthly) = monthly else {
+        return Ok(());
+    };
+    let (user_id, model_id) = match target {
+        SpendCapTarget::User(id) => (Some(id), None),
+        SpendCapTarget::Model(id) => (None, Some(id)),
+    };
+    SpendCap::insert(spend_cap::ActiveModel {
+        user_id: Set(user_id),
+        model_id: Set(model_id),
+        monthly: Set(monthly),
+        ..Default::default()
+    })
+    .exec(conn)
+    .await?;
+    Ok(())
+}
+
+/// Subject and text of an alert
+fn alert_text(alert: &NotificationSpendCap) -> (String, String) {
+    let whose = match &alert.model {
+        Some(name) => format!("The monthly spending cap of {}", name),
+        None => "Your monthly spending cap".to_owned(),
+    };
+    let (subject, next) = match alert.reached {
+        true => (
+            format!("{} is reached", whose),
+            "New replies are refused until the next month or until an admin raises the cap.",
+        ),
+        false => (
+            format!("{} is almost reached", whose),
+            "New replies are refused once the cap is reached, until the next month or until \
+             an admin raises it.",
+        ),
+    };
+    let text = format!(
+        "{:.2} USD of the cap of {:.2} USD are spent this month. {}",
+        alert.spent, alert.cap, next
+    );
+    (subject, text)
+}
+
+fn mail(alert: &NotificationSpendCap) -> Mail {
+    let (subject, text) = alert_text(alert);
+    Mail::new(subject).text(text)
+}
+
+async fn post(app: &AppState, user_id: i32, alert: &NotificationSpendCap) {
+    let (subject, text) = alert_text(alert);
+    let content = format!("**{}**\n\n{}", subject, text);
+    if let Err(err) = inbox::post(app, user_id, InboxSource::SpendCap, content, None).await {
+        tracing::warn!("cannot post spend cap alert to user {}: {}", user_id, err);
+    }
+}
+
+/// Notify the user and mail them if their address is verified
+async fn alert_user(app: Arc<AppState>, user_id: i32, alert: NotificationSpendCap) {
+    app.notifier
+        .send(user_id, Notification::SpendCap(alert.clone()));
+    post(&app, user_id, &alert).await;
+    let Some(mailer) = app.mailer.as_ref() else {
+        return;
+    };
+    let email = match User::find_by_id(user_id).one(&app.conn).await {
+        Ok(Some(user::Model {
+            email: Some(email),
+            email_verified: true,
+            ..
+        })) => email,
+        Ok(_) => return,
+        Err(err) => {
+            tracing::warn!("cannot find user {} to alert: {}", user_id, err);
+            return;
+        }
+    };
+    let mail = mail(&alert).link("Open llumen", mailer.link("/chat"));
+    if let Err(err) = app.jobs.push(&app.conn, mailer.task(email, &mail)).await {
+        tracing::warn!("cannot mail spend cap alert to user {}: {}", user_id, err);
+    }
+}
+
+/// Notify every admin and mail those with a verified address
+async fn alert_admins(app: Arc<AppState>, alert: NotificationSpendCap) {
+    let admins = match User::find()
+        .filter(user::Column::Role.eq(UserRole::Admin))
+        .all(&app.conn)
+        .await
+    {
+        Ok(x) => x,
+        Err(err) => {
+            tracing::warn!("cannot find admins to alert: {}", err);
+            return;
+        }
+    };
+    let mail = app
+        .mailer
+        .as_ref()
+        .map(|mailer| mail(&alert).link("Open llumen", mailer.link("/chat")));
+    for admin in admins {
+        app.notifier
+            .send(admin.id, Notification::SpendCap(alert.clone()));
+        post(&app, admin.id, &alert).await;
+        let (Some(mailer), Some(mail), Some(email), true) = (
+            app.mailer.as_ref(),
+            mail.as_ref(),
+            admin.email,
+            admin.email_verified,
+        ) else {
+            continue;
+        };
+        if let Err(err) = app.jobs.push(&app.conn, mailer.task(email, mail)).await {
+            tracing::warn!("cannot mail spend cap alert to user {}: {}", admin.id, err);
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! Guard against bursts of spending on the upstream, see `SPEND_GUARD_MULTIPLE` env
+//!
+//! The cost of every completion is added to the hour it ends in. An hour going
+//! past the multiple of the average of the [`SPEND_TRAILING_HOURS`] before it
+//! (and past `SPEND_GUARD_MIN`) pause the generations of every user but the
+//! admins, who are alerted. The pause survive restarts and last until an admin
+//! resume them, the guard then trust the rest of the hour
+
+pub mod cap;
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use entity::{UserRole, config, prelude::*, spend, user};
+use sea_orm::{
+    ActiveValue::Set,
+    DbConn, QuerySelect,
+    prelude::*,
+    sea_query::{Expr, OnConflict},
+};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    AppState,
+    config::{SPEND_GUARD_MIN_COST, SPEND_TRAILING_HOURS},
+    notify::mailer::Mail,
+};
+
+const PAUSE_KEY: &str = "spend_pause";
+const HOUR: i64 = 3600;
+
+/// Why generations are paused
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[typeshare]
+pub struct SpendPause {
+    /// unix seconds
+    pub at: i64,
+    /// USD spent in the hour that tripped the guard, when it did
+    pub hour_cost: f64,
+    /// USD per hour over the trailing hours
+    pub average: f64,
+}
+
+pub struct SpendGuard {
+    conn: DbConn,
+    /// `SPEND_GUARD_MULTIPLE`, None disable the guard
+    multiple: Option<f64>,
+    /// `SPEND_GUARD_MIN`
+    min_cost: f64,
+    /// `ALERT_WEBHOOK_URL`
+    webhook: Option<String>,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    paused: Option<SpendPause>,
+    /// Hour and average of the hours before it, recomputed when the hour change
+    average: Option<(i64, f64)>,
+    /// Hour an admin resumed in, the guard does not trip again in it
+    resumed: Option<i64>,
+}
+
+impl SpendGuard {
+    /// Restore the pause saved when the guard tripped
+    pub async fn load(conn: DbConn) -> Result<Self> {
+        let paused = match Config::find_by_id(PAUSE_KEY).one(&conn).await? {
+            Some(x) => Some(serde_json::from_slice(&x.value)?),
+            None => None,
+        };
+        let multiple = dotenv::var("SPEND_GUARD_MULTIPLE")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .filter(|x: &f64| *x > 0.0);
+        let min_cost = dotenv::var("SPEND_GUARD_MIN")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(SPEND_GUARD_MIN_COST);
+        let webhook = dotenv::var("ALERT_WEBHOOK_URL").ok();
+        Ok(Self {
+            conn,
+            multiple,
+            min_cost,
+            webhook,
+            state: Mutex::new(State {
+                paused,
+                ..Default::default()
+            }),
+        })
+    }
+
+    pub fn paused(&self) -> Option<SpendPause> {
+        self.state.lock().unwrap().paused.clone()
+    }
+
+    pub fn multiple(&self) -> Option<f64> {
+        self.multiple
+    }
+
+    /// USD spent in the current hour and the average of the hours before it
+    pub async fn current(&self) -> Result<(f64, f64)> {
+        let hour = now() / HOUR * HOUR;
+        Ok((
+            hour_cost(&self.conn, hour).await?,
+            self.average(hour).await?,
+        ))
+    }
+
+    /// Add the cost of a completion, pausing generations on a burst
+    pub async fn add(&self, app: &Arc<AppState>, cost: f64) -> Result<()> {
+        if cost <= 0.0 {
+            return Ok(());
+        }
+        let now = now();
+        let hour = now / HOUR * HOUR;
+        Spend::insert(spend::ActiveModel {
+            hour: Set(hour),
+            cost: Set(cost),
+        })
+        .on_conflict(
+            OnConflict::column(spend::Column::Hour)
+                .value(
+                    spend::Column::Cost,
+                    Expr::col((spend::Entity, spend::Column::Cost)).add(cost),
+                )
+                .to_owned(),
+        )
+        .exec(&self.conn)
+        .await?;
+
+        let Some(multiple) = self.multiple else {
+            return Ok(());
+        };
+        {
+            let state = self.state.lock().unwrap();
+            if state.paused.is_some() || state.resumed == Some(hour) {
+                return Ok(());
+            }
+        }
+        let hour_cost = hour_cost(&self.conn, hour).await?;
+        let average = self.average(hour).await?;
+        if hour_cost <= self.min_cost.max(average * multiple) {
+            return Ok(());
+        }
+
+        let pause = SpendPause {
+            at: now,
+            hour_cost,
+            average,
+        };
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.paused.is_some() {
+                return Ok(());
+            }
+            state.paused = Some(pause.clone());
+        }
+        Config::insert(config::ActiveModel {
+            key: Set(PAUSE_KEY.to_owned()),
+            value: Set(serde_json::to_vec(&pause)?),
+        })
+        .on_conflict(
+            OnConflict::column(config::Column::Key)
+                .update_column(config::Column::Value)
+                .to_owned(),
+        )
+        .exec(&self.conn)
+        .await?;
+        tracing::warn!(
+            "spent {:.2} USD this hour against {:.2} on average, generations are paused",
+            hour_cost,
+            average
+        );
+        tokio::spawn(alert(app.clone(), pause));
+        Ok(())
+    }
+
+    /// Acknowledge the burst, generations run again
+    pub async fn resume(&self) -> Result<bool> {
+        let res = Config::delete_by_id(PAUSE_KEY).exec(&self.conn).await?;
+        let mut state = self.state.lock().unwrap();
+        state.resumed = Some(now() / HOUR * HOUR);
+        Ok(state.paused.take().is_some() || res.rows_affected > 0)
+    }
+
+    async fn average(&self, hour: i64) -> Result<f64> {
+        if let Some((at, average)) = self.state.lock().unwrap().average
+            && at == hour
+        {
+            return Ok(average);
+        }
+        let total = Spend::find()
+            .select_only()
+            .column_as(spend::Column::Cost.sum(), "total")
+            .filter(spend::Column::Hour.gte(hour - SPEND_TRAILING_HOURS * HOUR))
+            .filter(spend::Column::Hour.lt(hour))
+            .into_tuple::<Option<f64>>()
+            .one(&self.conn)
+            .await?
+            .flatten()
+            .unwrap_or_default();
+        // hours without any completion count as nothing spent
+        let average = total / SPEND_TRAILING_HOURS as f64;
+        self.state.lock().unwrap().average = Some((hour, average));
+        Ok(average)
+    }
+}
+
+async fn hour_cost(conn: &DbConn, hour: i64) -> Result<f64> {
+    Ok(Spend::find_by_id(hour)
+        .one(conn)
+        .await?
+        .map(|x| x.cost)
+        .unwrap_or_default())
+}
+
+/// Mail the admins with a verified address and call `ALERT_WEBHOOK_URL`
+async fn alert(app: Arc<AppState>, pause: SpendPause) {
+    if let Some(url) = &app.spend.webhook {
+        let body = serde_json::json!({
+            "event": "spend_paused",
+            "at": pause.at,
+            "hour_cost": pause.hour_cost,
+            "average": pause.average,
+        });
+        let res = reqwest::Client::new()
+            .post(url)
+            .json(&body)
+            .send()
+            .await
+            .and_then(|x| x.error_for_status());
+        if let Err(err) = res {
+            tracing::warn!("cannot call the alert webhook: {}", err);
+        }
+    }
+
+    let Some(mailer) = app.mailer.as_ref() else {
+        return;
+    };
+    let admins = match User::find()
+        .filter(user::Column::Role.eq(UserRole::Admin))
+        .filter(user::Column::EmailVerified.eq(true))
+        .all(&app.conn)
+        .await
+    {
+        Ok(x) => x,
+        Err(err) => {
+            tracing::warn!("cannot find admins to alert: {}", err);
+            return;
+        }
+    };
+    let mail = Mail::new("Generations paused on unusual spending")
+        .text(format!(
+            "llumen spent {:.2} USD on the upstream in the last hour, against {:.2} per hour \
+             on average. Generations of users other than admins are paused until an admin \
+             resume them in the admin settings.",
+            pause.hour_cost, pause.average,
+        ))
+        .link("Open llumen", mailer.link("/chat"));
+    for admin in admins {
+        let Some(email) = admin.email else {
+            continue;
+        };
+        let mail = mailer.task(email, &mail);
+        if let Err(err) = app.jobs.push(&app.conn, mail).await {
+            tracing::warn!("cannot mail spend alert to user {}: {}", admin.id, err);
+        }
+    }
+}
+
+fn now() -> i64 {
+    time::UtcDateTime::now().unix_timestamp()
+}
@@ -1,8 +1,15 @@
-use crate::sse::{EndKind, Publisher};
+use std::{sync::Mutex, time::Duration};
+
+use crate::{
+    config::{STREAM_CHECKPOINT_INTERVAL_MS, STREAM_CHECKPOINT_TOKENS},
+    database,
+    sse::{EndKind, PlanStep, Publisher},
+};
 
 use anyhow::Result;
-use entity::{ChunkKind, MessageKind, ToolCall, chunk, message, prelude::*};
-use sea_orm::{ActiveValue::Set, EntityTrait};
+use entity::{ChunkKind, MessageKind, ToolCall, chunk, file, message, prelude::*};
+use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter};
+use tokio::time::Instant;
 
 use super::Token;
 
@@ -16,22 +23,38 @@ impl<'a> AssistantMessage<'a> {
         Self { message_id, ctx }
     }
 
+    pub fn id(&self) -> i32 {
+        self.message_id
+    }
+
     pub async fn new_buffer_chunk<'b: 'c, 'c>(&'b self, kind: ChunkKind) -> BufferChunk<'c, 'b> {
         let mut inner = self.ctx.inner.write().await;
 
         inner.buffer.clear();
         inner.is_reasoning = kind == ChunkKind::Reasoning;
 
-        BufferChunk { ctx: self, kind }
+        BufferChunk {
+            ctx: self,
+            kind,
+            checkpoint: Mutex::new(Checkpoint {
+                chunk_id: None,
+                tokens: 0,
+                at: Instant::now(),
+            }),
+        }
     }
 
     pub async fn end_message(self, kind: EndKind) -> Result<()> {
-        Message::update(message::ActiveModel {
-            id: Set(self.message_id),
-            kind: Set(MessageKind::Assistant),
-            ..Default::default()
+        database::retry(|| {
+            Message::update(message::ActiveModel {
+                id: Set(self.message_id),
+                kind: Set(MessageKind::Assistant),
+                truncated: Set(matches!(kind, EndKind::Truncated)),
+                generating: Set(false),
+                ..Default::default()
+            })
+            .exec(&self.ctx.conn)
         })
-        .exec(&self.ctx.conn)
         .await?;
 
         let mut inner = self.ctx.inner.write().await;
@@ -41,10 +64,59 @@ impl<'a> AssistantMessage<'a> {
         Ok(())
     }
 
+    pub fn plan(&self, steps: &[PlanStep], current: usize) {
+        self.ctx.raw_token(Ok(Token::Plan(steps.to_vec(), current)));
+    }
+
+    pub fn progress(&self, steps: usize, max_steps: usize, cost: f64, elapsed_ms: u64) {
+        self.ctx
+            .raw_token(Ok(Token::Progress(steps, max_steps, cost, elapsed_ms)));
+    }
+
+    /// Preview of the arguments while the model is still writing them
+    pub fn tool_call_delta(&self, name: String, args: String) {
+        self.ctx.raw_token(Ok(Token::ToolCallDelta(name, args)));
+    }
+
     pub fn start_tool_call(&self, name: &'static str, args: String) {
         self.ctx.raw_token(Ok(Token::ToolCall(name, args)));
     }
 
+    /// Attach files made by a tool call, e.g. generated images
+    pub async fn attach(&self, chat_id: i32, files: Vec<file::Model>) -> Result<()> {
+        if files.is_empty() {
+            return Ok(());
+        }
+        let ids: Vec<i32> = files.iter().map(|x| x.id).collect();
+        crate::files::attach(&self.ctx.conn, chat_id, self.message_id, &ids).await?;
+        for file in files {
+            self.ctx.raw_token(Ok(Token::Attachment(
+                self.message_id,
+                file.id,
+                file.name,
+                file.content_type,
+            )));
+        }
+        Ok(())
+    }
+
+    /// Ask the user, until [`Self::end_input`] new subscribers see the question too
+    pub async fn ask_input(
+        &self,
+        call_id: String,
+        name: &'static str,
+        question: String,
+        schema: String,
+    ) {
+        let token = Token::ToolInput(call_id, name, question, schema);
+        self.ctx.inner.write().await.input = Some(token.clone());
+        self.ctx.raw_token(Ok(token));
+    }
+
+    pub async fn end_input(&self) {
+        self.ctx.inner.write().await.input = None;
+    }
+
     pub async fn end_tool_call(
         &self,
         name: &'static str,
@@ -58,13 +130,15 @@ impl<'a> AssistantMessage<'a> {
             args: args.clone(),
             content: content.clone(),
         })?;
-        let id = Chunk::insert(chunk::ActiveModel {
-            content: Set(chunk_content),
-            kind: Set(ChunkKind::ToolCall),
-            message_id: Set(self.message_id),
-            ..Default::default()
+        let id = database::retry(|| {
+            Chunk::insert(chunk::ActiveModel {
+                content: Set(chunk_content.clone()),
+                kind: Set(ChunkKind::ToolCall),
+                message_id: Set(self.message_id),
+                ..Default::default()
+            })
+            .exec(&self.ctx.conn)
         })
-        .exec(&self.ctx.conn)
         .await?
         .last_insert_id;
         self.ctx
@@ -74,39 +148,108 @@ impl<'a> AssistantMessage<'a> {
     }
 }
 
+/// Text streamed into a chunk, written every [`STREAM_CHECKPOINT_TOKENS`] or
+/// [`STREAM_CHECKPOINT_INTERVAL_MS`] and once more when it ends
+///
+/// A crash loses the tokens since the last checkpoint rather than the whole
+/// chunk. Short chunks are written once, at their end
 pub struct BufferChunk<'a, 'b: 'a> {
     ctx: &'a AssistantMessage<'b>,
     kind: ChunkKind,
+    checkpoint: Mutex<Checkpoint>,
+}
+
+struct Checkpoint {
+    /// Row of the chunk once written
+    chunk_id: Option<i32>,
+    /// Tokens since the last write
+    tokens: usize,
+    at: Instant,
 }
 
 impl<'a, 'b: 'a> BufferChunk<'a, 'b> {
     pub async fn end_buffer_chunk(self, end_kind: EndKind) -> Result<()> {
         let inner = self.ctx.ctx.inner.write().await;
-        let context = inner.buffer.clone();
-        let id = Chunk::insert(chunk::ActiveModel {
-            content: Set(context),
-            kind: Set(self.kind),
-            message_id: Set(self.ctx.message_id),
-            ..Default::default()
-        })
-        .exec(&self.ctx.ctx.conn)
-        .await?
-        .last_insert_id;
+        let id = self.save(inner.buffer.clone()).await?;
 
         self.ctx.ctx.raw_token(Ok(Token::ChunkEnd(id, end_kind)));
-        inner.on_receive.notify_waiters();
         Ok(())
     }
 
     pub async fn send_token(&self, token: &str) -> Result<()> {
-        let mut inner = self.ctx.ctx.inner.write().await;
-
-        inner.buffer.push_str(token);
-        inner.on_receive.notify_waiters();
+        let content = {
+            let mut inner = self.ctx.ctx.inner.write().await;
+
+            inner.buffer.push_str(token);
+            let token = token.to_owned();
+            self.ctx.ctx.text_token(if inner.is_reasoning {
+                Token::ReasoningToken(token)
+            } else {
+                Token::Token(token)
+            });
+
+            let mut checkpoint = self.checkpoint.lock().unwrap();
+            checkpoint.tokens += 1;
+            if checkpoint.tokens < STREAM_CHECKPOINT_TOKENS
+                && checkpoint.at.elapsed() < Duration::from_millis(STREAM_CHECKPOINT_INTERVAL_MS)
+            {
+                return Ok(());
+            }
+            checkpoint.tokens = 0;
+            checkpoint.at = Instant::now();
+            inner.buffer.clone()
+        };
+        // the reply goes on, the end of the chunk writes it again
+        if let Err(err) = self.save(content).await {
+            tracing::warn!("cannot checkpoint message {}: {}", self.ctx.message_id, err);
+        }
         Ok(())
     }
 
+    /// Write the text so far, the first write insert the row
+    async fn save(&self, content: String) -> Result<i32> {
+        let conn = &self.ctx.ctx.conn;
+        let chunk_id = self.checkpoint.lock().unwrap().chunk_id;
+        let id = match chunk_id {
+            Some(id) => {
+                database::retry(|| {
+                    Chunk::update_many()
+                        .col_expr(chunk::Column::Content, content.clone().into())
+                        .filter(chunk::Column::Id.eq(id))
+                        .exec(conn)
+                })
+                .await?;
+                id
+            }
+            None => {
+                database::retry(|| {
+                    Chunk::insert(chunk::ActiveModel {
+                        content: Set(content.clone()),
+                        kind: Set(self.kind),
+                        message_id: Set(self.ctx.message_id),
+                        ..Default::default()
+                    })
+                    .exec(conn)
+                })
+                .await?
+                .last_insert_id
+            }
+        };
+        self.checkpoint.lock().unwrap().chunk_id = Some(id);
+        Ok(id)
+    }
+
     pub fn kind(&self) -> ChunkKind {
         self.kind
     }
+
+    /// Text streamed so far
+    pub async fn text(&self) -> String {
+        self.ctx.ctx.inner.read().await.buffer.clone()
+    }
+
+    /// Replace the text streamed so far, written when the chunk ends
+    pub async fn redact(&self, text: &str) {
+        text.clone_into(&mut self.ctx.ctx.inner.write().await.buffer);
+    }
 }
@@ -1,14 +1,27 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
 
 use anyhow::Result;
 use entity::{message, prelude::*};
-use sea_orm::{DbConn, EntityTrait, QueryOrder};
+use sea_orm::{
+    ColumnTrait, DbConn, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, sea_query::Expr,
+};
 use serde::Serialize;
-use tokio::sync::{Mutex, Notify, RwLock, broadcast};
+use tokio::sync::{Mutex, Notify, RwLock, mpsc};
 
-use super::subscriber::Subscriber;
-use crate::{config::MAX_SSE_BUF, errors::Error, sse::Publisher};
+use super::subscriber::{Event, Subscriber};
+use crate::{
+    config::{MAX_SSE_REPLAY, SSE_REAP_INTERVAL},
+    errors::Error,
+    sse::Publisher,
+};
 
+/// Streams keyed by chat id, a subscriber only see the chat it subscribed
 #[derive(Debug, Clone)]
 pub struct SseContext {
     pub(super) map: Arc<Mutex<HashMap<i32, Arc<RwLock<SseInner>>>>>,
@@ -26,13 +39,13 @@ pub struct SseInner {
     /// When update it will +1
     pub version: u32,
 
-    /// `on_receive` will notify when buffer/id change
-    pub on_receive: Arc<Notify>,
     pub is_reasoning: bool,
     pub buffer: String,
+    /// Question of a tool call waiting for the user, replayed to new subscribers
+    pub input: Option<Token>,
 
-    /// Extra token
-    pub channel: broadcast::Sender<Result<Token, Error>>,
+    /// Every token in order, fanned out to the queue of each subscriber
+    pub log: Arc<std::sync::Mutex<EventLog>>,
 
     /// on halt completion
     pub on_halt: Arc<Notify>,
@@ -49,11 +62,11 @@ impl SseInner {
         let version = fastrand::u32(0..u16::MAX as u32);
         Ok(Self {
             buffer: "".to_owned(),
+            input: None,
             last_message_id: last_id,
             version,
-            on_receive: Arc::new(Notify::new()),
             on_halt: Arc::new(Notify::new()),
-            channel: broadcast::channel(MAX_SSE_BUF).0,
+            log: Arc::new(std::sync::Mutex::new(EventLog::new(version))),
             is_reasoning: true,
         })
     }
@@ -66,26 +79,219 @@ impl SseContext {
             conn,
         }
     }
-    pub async fn subscribe(&self, chat_id: i32) -> Result<Subscriber> {
-        Subscriber::new(self, chat_id).await
+    /// Resume after `last_event_id` if the events since are still buffered
+    pub async fn subscribe(
+        &self,
+        chat_id: i32,
+        last_event_id: Option<EventId>,
+    ) -> Result<Subscriber> {
+        Subscriber::new(self, chat_id, last_event_id).await
     }
 
     pub async fn publish(&self, chat_id: i32) -> Result<Publisher> {
         Publisher::new(self, chat_id).await
     }
 
-    pub async fn halt(&self, chat_id: i32) {
+    /// Stop the in-flight completion of a chat
+    ///
+    /// Return false if nothing is generating
+    pub async fn halt(&self, chat_id: i32) -> bool {
         let map = self.map.lock().await;
 
         let Some(v) = map.get(&chat_id) else {
-            return;
+            return false;
         };
 
-        v.read().await.on_halt.notify_waiters();
+        let inner = v.read().await;
+        // the context itself hold one log, publisher hold the other
+        if Arc::strong_count(&inner.log) == 1 {
+            return false;
+        }
+        inner.on_halt.notify_waiters();
+        true
+    }
+
+    /// Whether a completion of the chat is in-flight
+    pub async fn is_publishing(&self, chat_id: i32) -> bool {
+        let map = self.map.lock().await;
+
+        match map.get(&chat_id) {
+            Some(v) => Arc::strong_count(&v.read().await.log) != 1,
+            None => false,
+        }
+    }
+
+    /// Mark the replies of a chat left generating by a crash as interrupted
+    ///
+    /// A reply streams on the instance the chat is routed to, so one left
+    /// generating while this instance is not publishing the chat was cut by a
+    /// restart. Return the number of replies marked
+    pub async fn interrupt(&self, chat_id: i32) -> Result<u64> {
+        let generating = Message::find()
+            .filter(message::Column::ChatId.eq(chat_id))
+            .filter(message::Column::Generating.eq(true))
+            .count(&self.conn)
+            .await?;
+        if generating == 0 {
+            return Ok(0);
+        }
+        // held so no reply starts between the check and the update
+        let map = self.map.lock().await;
+        if let Some(v) = map.get(&chat_id)
+            && Arc::strong_count(&v.read().await.log) != 1
+        {
+            return Ok(0);
+        }
+        let res = Message::update_many()
+            .col_expr(message::Column::Generating, Expr::value(false))
+            .col_expr(message::Column::Interrupted, Expr::value(true))
+            .filter(message::Column::ChatId.eq(chat_id))
+            .filter(message::Column::Generating.eq(true))
+            .exec(&self.conn)
+            .await?;
+        drop(map);
+        if res.rows_affected != 0 {
+            tracing::info!(
+                "marked {} replies of chat {} as interrupted",
+                res.rows_affected,
+                chat_id
+            );
+        }
+        Ok(res.rows_affected)
+    }
+
+    /// Drop chat streams without publisher or subscriber
+    ///
+    /// Return the number of streams dropped
+    pub async fn reap(&self) -> usize {
+        let mut map = self.map.lock().await;
+        let len = map.len();
+        // publisher and subscribers each hold a clone, the map hold the last one
+        map.retain(|_, v| Arc::strong_count(v) > 1);
+        len - map.len()
+    }
+
+    /// Periodically reap streams whose clients went away
+    pub fn spawn_reaper(&self) {
+        let ctx = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(SSE_REAP_INTERVAL));
+            loop {
+                interval.tick().await;
+                let reaped = ctx.reap().await;
+                if reaped != 0 {
+                    tracing::debug!("reaped {} idle sse streams", reaped);
+                }
+            }
+        });
+    }
+
+    /// Send a token to subscribers of a chat without holding a publisher
+    pub async fn broadcast(&self, chat_id: i32, token: Token) {
+        let map = self.map.lock().await;
+
+        if let Some(v) = map.get(&chat_id) {
+            let inner = v.read().await;
+            inner.log.lock().unwrap().push(Ok(token));
+        }
+    }
+}
+
+/// Recent tokens of a chat, so a reconnecting client can catch up
+#[derive(Debug)]
+pub struct EventLog {
+    version: u32,
+    seq: u64,
+    events: VecDeque<(u64, Result<Token, Error>)>,
+    /// One bounded queue per connection, a full one is dropped
+    queues: Vec<mpsc::Sender<Event>>,
+}
+
+impl EventLog {
+    pub fn new(version: u32) -> Self {
+        Self {
+            version,
+            seq: 0,
+            events: VecDeque::new(),
+            queues: Vec::new(),
+        }
+    }
+
+    /// Return the id of the token
+    pub fn push(&mut self, token: Result<Token, Error>) -> u64 {
+        self.seq += 1;
+        let id = EventId {
+            version: self.version,
+            seq: self.seq,
+        };
+        // a slow connection must not hold back the other devices of the user
+        self.queues.retain(|tx| {
+            // the last slot is kept for the lagged notice
+            if tx.capacity() <= 1 {
+                tx.try_send((None, Ok(Token::Lagged))).ok();
+                return false;
+            }
+            tx.try_send((Some(id), token.clone())).is_ok()
+        });
+
+        self.events.push_back((self.seq, token));
+        if self.events.len() > MAX_SSE_REPLAY {
+            self.events.pop_front();
+        }
+        self.seq
+    }
+
+    /// Receive every token pushed from now on
+    pub fn attach(&mut self, tx: mpsc::Sender<Event>) {
+        self.queues.push(tx);
+    }
+
+    /// Id of the last token
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Tokens after `seq`
+    ///
+    /// Return None if some of them were dropped
+    pub fn since(&self, seq: u64) -> Option<Vec<(u64, Result<Token, Error>)>> {
+        let first = self.events.front().map_or(self.seq + 1, |x| x.0);
+        if seq > self.seq || seq + 1 < first {
+            return None;
+        }
+        Some(self.events.iter().filter(|x| x.0 > seq).cloned().collect())
+    }
+}
+
+/// SSE event id, `{version}-{seq}`
+///
+/// Version changes when the stream is recreated, so a stale id never resumes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventId {
+    pub version: u32,
+    pub seq: u64,
+}
+
+impl fmt::Display for EventId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.version, self.seq)
+    }
+}
+
+impl FromStr for EventId {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (version, seq) = s.split_once('-').ok_or(())?;
+        Ok(Self {
+            version: version.parse().map_err(|_| ())?,
+            seq: seq.parse().map_err(|_| ())?,
+        })
     }
 }
 
 #[derive(Debug, Clone, Serialize)]
+#[allow(clippy::enum_variant_names)]
 pub enum Token {
     // id, version
     LastMessage(i32, u32),
@@ -96,16 +302,39 @@ pub enum Token {
     ChunkEnd(i32, EndKind),
     MessageEnd(i32, EndKind),
 
-    /// message id, chunk id, content
-    UserMessage(i32, i32, String),
+    /// message id, chunk id, author id, content
+    UserMessage(i32, i32, i32, String),
 
+    /// name, part of the args, before the call is complete
+    ToolCallDelta(String, String),
     /// name, args
     ToolCall(&'static str, String),
+    /// tool call id, name, question, JSON schema of the answer
+    ToolInput(String, &'static str, String, String),
     /// name, args, context, id
     ToolCallEnd(&'static str, String, String, i32),
+    /// message id, file id, name, content type
+    Attachment(i32, i32, String, String),
 
     // change title
-    ChangeTitle(String),
+    ChatTitle(String),
+    /// pinned, archived at
+    ChatStatus(bool, Option<i64>),
+
+    /// steps, current step
+    Plan(Vec<PlanStep>, usize),
+
+    /// tool calls used, tool calls allowed, cost, elapsed milliseconds
+    Progress(usize, usize, f64, u64),
+
+    /// message id, stats
+    Meta(i32, MessageMeta),
+
+    /// prompt tokens, context length of the model
+    ContextWarning(usize, usize),
+
+    /// the connection fell behind and is closed, refetch and subscribe again
+    Lagged,
 }
 
 #[derive(Debug, Clone, Copy, Serialize)]
@@ -113,4 +342,31 @@ pub enum EndKind {
     Complete,
     Halt,
     Error,
+    /// upstream timed out, the partial reply is kept
+    Truncated,
+}
+
+/// Generation stats of an assistant message
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageMeta {
+    pub tokens_per_sec: f64,
+    pub ttft_ms: u64,
+    pub model: String,
+    pub finish_reason: String,
+}
+
+/// A tool call in the current multi-step tool loop
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanStep {
+    pub name: String,
+    pub status: PlanStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum PlanStatus {
+    Pending,
+    Running,
+    Done,
+    Failed,
+    Skipped,
 }
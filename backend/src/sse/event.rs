@@ -0,0 +1,347 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use typeshare::typeshare;
+
+use super::{EndKind, PlanStatus, Token};
+use crate::errors::Error;
+
+/// Version of the event schema, sent as `v` with every event
+///
+/// Events of version 1 had no `v` and were tagged with `t` and `c`
+pub const SSE_VERSION: u32 = 2;
+
+/// Every event sent on a chat stream, over SSE or WebSocket
+///
+/// Bump [`SSE_VERSION`] when an existing kind changes shape, adding a kind is not breaking
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SseEvent {
+    pub v: u32,
+    #[serde(flatten)]
+    pub resp: SseResp,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum SseResp {
+    LastMessage(SseRespLastMessage),
+
+    TokenDelta(SseRespDelta),
+    ReasoningDelta(SseRespDelta),
+    ChunkEnd(SseRespChunkEnd),
+
+    /// part of the arguments of a tool call still being written by the model
+    ToolCallDelta(SseRespToolCallDelta),
+    ToolCall(SseRespToolCall),
+    /// the tool call waits for the user, answer with `/api/chat/{id}/tool/{call_id}/input`
+    ToolInput(SseRespToolInput),
+    ToolResult(SseRespToolResult),
+    /// a file a tool made was attached to the message, e.g. a generated image
+    Attachment(SseRespAttachment),
+
+    MessageEnd(SseRespMessageEnd),
+
+    UserMessage(SseRespUserMessage),
+
+    ChatTitle(SseRespChatTitle),
+
+    /// the chat was pinned or archived, from this tab or another
+    ChatStatus(SseRespChatStatus),
+
+    Plan(SseRespPlan),
+
+    Progress(SseRespProgress),
+
+    Meta(SseRespMeta),
+
+    /// the chat keeps filling the context of the model, its beginning may get lost
+    ContextWarning(SseRespContextWarning),
+
+    /// the connection fell behind and is closed, refetch and subscribe again
+    Lagged,
+
+    Error(Error),
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SseRespMeta {
+    pub id: i32,
+    pub tokens_per_sec: f64,
+    /// time to first token
+    pub ttft_ms: u32,
+    pub model: String,
+    pub finish_reason: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SseRespContextWarning {
+    pub prompt_tokens: u32,
+    pub context_length: u32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SseRespProgress {
+    pub steps: u32,
+    pub max_steps: u32,
+    pub cost: f64,
+    pub elapsed_ms: u32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SseRespPlan {
+    pub steps: Vec<SseRespPlanStep>,
+    /// index of the step being updated
+    pub current: u32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SseRespPlanStep {
+    pub name: String,
+    pub status: SseRespPlanStatus,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum SseRespPlanStatus {
+    Pending,
+    Running,
+    Done,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SseRespChatTitle {
+    pub title: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SseRespChatStatus {
+    pub pinned: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SseRespLastMessage {
+    pub id: i32,
+    pub version: u32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SseRespDelta {
+    pub content: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SseRespChunkEnd {
+    pub id: i32,
+    pub kind: SseRespEndKind,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SseRespMessageEnd {
+    pub id: i32,
+    pub kind: SseRespEndKind,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum SseRespEndKind {
+    Complete,
+    Halt,
+    Error,
+    Truncated,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SseRespUserMessage {
+    pub message_id: i32,
+    pub chunk_id: i32,
+    /// member who sent it, see `chat/{id}/member`
+    pub author_id: i32,
+    pub content: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SseRespToolCall {
+    pub name: String,
+    pub args: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SseRespToolCallDelta {
+    pub name: String,
+    /// append to the args received so far
+    pub args: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SseRespToolInput {
+    pub call_id: String,
+    pub name: String,
+    pub question: String,
+    /// JSON schema of the answer
+    pub schema: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SseRespToolResult {
+    pub chunk_id: i32,
+    pub name: String,
+    pub args: String,
+    pub content: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[typeshare]
+pub struct SseRespAttachment {
+    pub message_id: i32,
+    /// download with `/api/file/{id}`
+    pub file_id: i32,
+    pub name: String,
+    pub content_type: String,
+}
+
+impl From<Token> for SseResp {
+    fn from(token: Token) -> Self {
+        match token {
+            Token::LastMessage(id, version) => {
+                SseResp::LastMessage(SseRespLastMessage { id, version })
+            }
+            Token::Token(content) => SseResp::TokenDelta(SseRespDelta { content }),
+            Token::ReasoningToken(content) => SseResp::ReasoningDelta(SseRespDelta { content }),
+            Token::ChunkEnd(id, end_kind) => SseResp::ChunkEnd(SseRespChunkEnd {
+                id,
+                kind: match end_kind {
+                    EndKind::Complete => SseRespEndKind::Complete,
+                    EndKind::Halt => SseRespEndKind::Halt,
+                    EndKind::Error => SseRespEndKind::Error,
+                    EndKind::Truncated => SseRespEndKind::Truncated,
+                },
+            }),
+            Token::MessageEnd(id, end_kind) => SseResp::MessageEnd(SseRespMessageEnd {
+                id,
+                kind: match end_kind {
+                    EndKind::Complete => SseRespEndKind::Complete,
+                    EndKind::Halt => SseRespEndKind::Halt,
+                    EndKind::Error => SseRespEndKind::Error,
+                    EndKind::Truncated => SseRespEndKind::Truncated,
+                },
+            }),
+            Token::UserMessage(message_id, chunk_id, author_id, content) => {
+                SseResp::UserMessage(SseRespUserMessage {
+                    message_id,
+                    chunk_id,
+                    author_id,
+                    content,
+                })
+            }
+            Token::ToolCallDelta(name, args) => {
+                SseResp::ToolCallDelta(SseRespToolCallDelta { name, args })
+            }
+            Token::ToolCall(name, args) => SseResp::ToolCall(SseRespToolCall {
+                name: name.to_owned(),
+                args,
+            }),
+            Token::ToolInput(call_id, name, question, schema) => {
+                SseResp::ToolInput(SseRespToolInput {
+                    call_id,
+                    name: name.to_owned(),
+                    question,
+                    schema,
+                })
+            }
+            Token::ToolCallEnd(name, args, content, chunk_id) => {
+                SseResp::ToolResult(SseRespToolResult {
+                    chunk_id,
+                    name: name.to_owned(),
+                    args,
+                    content,
+                })
+            }
+            Token::Attachment(message_id, file_id, name, content_type) => {
+                SseResp::Attachment(SseRespAttachment {
+                    message_id,
+                    file_id,
+                    name,
+                    content_type,
+                })
+            }
+            Token::ChatTitle(title) => SseResp::ChatTitle(SseRespChatTitle { title }),
+            Token::ChatStatus(pinned, archived_at) => SseResp::ChatStatus(SseRespChatStatus {
+                pinned,
+                archived_at,
+            }),
+            Token::Plan(steps, current) => SseResp::Plan(SseRespPlan {
+                steps: steps
+                    .into_iter()
+                    .map(|step| SseRespPlanStep {
+                        name: step.name,
+                        status: match step.status {
+                            PlanStatus::Pending => SseRespPlanStatus::Pending,
+                            PlanStatus::Running => SseRespPlanStatus::Running,
+                            PlanStatus::Done => SseRespPlanStatus::Done,
+                            PlanStatus::Failed => SseRespPlanStatus::Failed,
+                            PlanStatus::Skipped => SseRespPlanStatus::Skipped,
+                        },
+                    })
+                    .collect(),
+                current: current as u32,
+            }),
+            Token::Progress(steps, max_steps, cost, elapsed_ms) => {
+                SseResp::Progress(SseRespProgress {
+                    steps: steps as u32,
+                    max_steps: max_steps as u32,
+                    cost,
+                    elapsed_ms: elapsed_ms as u32,
+                })
+            }
+            Token::Meta(id, meta) => SseResp::Meta(SseRespMeta {
+                id,
+                tokens_per_sec: meta.tokens_per_sec,
+                ttft_ms: meta.ttft_ms as u32,
+                model: meta.model,
+                finish_reason: meta.finish_reason,
+            }),
+            Token::ContextWarning(prompt_tokens, context_length) => {
+                SseResp::ContextWarning(SseRespContextWarning {
+                    prompt_tokens: prompt_tokens as u32,
+                    context_length: context_length as u32,
+                })
+            }
+            Token::Lagged => SseResp::Lagged,
+        }
+    }
+}
+
+impl From<Result<Token, Error>> for SseEvent {
+    fn from(token: Result<Token, Error>) -> Self {
+        Self {
+            v: SSE_VERSION,
+            resp: match token {
+                Ok(token) => token.into(),
+                Err(err) => SseResp::Error(err),
+            },
+        }
+    }
+}
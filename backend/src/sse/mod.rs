@@ -1,9 +1,11 @@
 mod assistant_message;
 mod context;
+mod event;
 mod publisher;
 mod subscriber;
 
 pub use assistant_message::*;
 pub use context::*;
+pub use event::*;
 pub use publisher::*;
 pub use subscriber::*;
@@ -4,17 +4,19 @@ use anyhow::{Result, bail};
 use entity::{MessageKind, chunk, message, patch::ChunkKind, prelude::*};
 use futures_util::FutureExt;
 use sea_orm::{ActiveValue::Set, TransactionTrait, prelude::*};
-use tokio::sync::{Notify, RwLock, broadcast};
+use tokio::sync::{Notify, RwLock};
 
 use crate::{
+    database,
     errors::*,
-    sse::{AssistantMessage, SseContext, SseInner, Token},
+    sse::{AssistantMessage, EventLog, SseContext, SseInner, Token},
+    utils::branch,
 };
 
 #[derive(Debug)]
 pub struct Publisher {
     pub(super) chat_id: i32,
-    channel: broadcast::Sender<Result<Token, Error>>,
+    log: Arc<std::sync::Mutex<EventLog>>,
     pub(super) inner: Arc<RwLock<SseInner>>,
     pub(super) on_halt: Arc<Notify>,
     pub(super) conn: DbConn,
@@ -37,21 +39,23 @@ impl Publisher {
         self.on_halt.notified().await;
     }
 
-    pub async fn user_message(&self, t: String) -> Result<i32> {
-        let (message_id, chunk_id) = self
-            .conn
-            .transaction(|conn| {
+    pub async fn user_message(&self, author_id: i32, t: String) -> Result<i32> {
+        let (message_id, chunk_id) = database::retry(|| {
+            self.conn.transaction(|conn| {
                 let chat_id = self.chat_id;
                 let t = t.clone();
                 async move {
-                    let message_id = Message::insert(message::ActiveModel {
-                        chat_id: Set(chat_id),
-                        kind: Set(MessageKind::User),
-                        ..Default::default()
-                    })
-                    .exec(conn)
-                    .await?
-                    .last_insert_id;
+                    let message_id = branch::append(
+                        conn,
+                        chat_id,
+                        message::ActiveModel {
+                            kind: Set(MessageKind::User),
+                            created_at: Set(time::UtcDateTime::now().unix_timestamp()),
+                            author_id: Set(Some(author_id)),
+                            ..Default::default()
+                        },
+                    )
+                    .await?;
 
                     let chunk_id = Chunk::insert(chunk::ActiveModel {
                         content: Set(t),
@@ -67,10 +71,11 @@ impl Publisher {
                 }
                 .boxed()
             })
-            .await?;
+        })
+        .await?;
 
         self.inner.write().await.last_message_id = message_id + 1;
-        self.raw_token(Ok(Token::UserMessage(message_id, chunk_id, t)));
+        self.raw_token(Ok(Token::UserMessage(message_id, chunk_id, author_id, t)));
         Ok(message_id)
     }
 
@@ -79,18 +84,41 @@ impl Publisher {
     }
 
     pub fn raw_token(&self, t: Result<Token, Error>) {
-        self.channel.send(t).ok();
+        self.log.lock().unwrap().push(t);
     }
 
-    pub async fn new_assistant_message<'a>(&'a self) -> Result<AssistantMessage<'a>> {
-        let message_id = Message::insert(message::ActiveModel {
-            chat_id: Set(self.chat_id),
-            kind: Set(MessageKind::Assistant),
-            ..Default::default()
+    /// Push a text token, the caller must hold the write lock of `inner`
+    pub(super) fn text_token(&self, t: Token) {
+        self.log.lock().unwrap().push(Ok(t));
+    }
+
+    /// `author_id` is the member who asked for the reply
+    pub async fn new_assistant_message<'a>(
+        &'a self,
+        author_id: i32,
+    ) -> Result<AssistantMessage<'a>> {
+        // in a transaction, so a retry does not append the message twice
+        let message_id = database::retry(|| {
+            self.conn.transaction(|conn| {
+                let chat_id = self.chat_id;
+                async move {
+                    branch::append(
+                        conn,
+                        chat_id,
+                        message::ActiveModel {
+                            kind: Set(MessageKind::Assistant),
+                            created_at: Set(time::UtcDateTime::now().unix_timestamp()),
+                            author_id: Set(Some(author_id)),
+                            generating: Set(true),
+                            ..Default::default()
+                        },
+                    )
+                    .await
+                }
+                .boxed()
+            })
         })
-        .exec(&self.conn)
-        .await?
-        .last_insert_id;
+        .await?;
 
         Ok(AssistantMessage::new(message_id, self))
     }
@@ -99,16 +127,16 @@ impl Publisher {
         match ctx.map.lock().await.entry(chat_id) {
             Entry::Occupied(entry) => {
                 let inner = entry.get().write().await;
-                if inner.channel.strong_count() != 1 {
+                if Arc::strong_count(&inner.log) != 1 {
                     bail!("Only 1 publisher can exisit at the same time");
                 }
 
-                let channel = inner.channel.clone();
+                let log = inner.log.clone();
                 let on_halt = inner.on_halt.clone();
                 let inner = entry.get().clone();
 
                 Ok(Self {
-                    channel,
+                    log,
                     inner,
                     on_halt,
                     conn: ctx.conn.clone(),
@@ -117,12 +145,12 @@ impl Publisher {
             }
             Entry::Vacant(entry) => {
                 let inner = SseInner::new(ctx).await?;
-                let channel = inner.channel.clone();
+                let log = inner.log.clone();
                 let on_halt = inner.on_halt.clone();
                 let inner = entry.insert(Arc::new(RwLock::new(inner))).clone();
 
                 Ok(Self {
-                    channel,
+                    log,
                     inner,
                     on_halt,
                     conn: ctx.conn.clone(),
@@ -5,28 +5,27 @@ use futures_util::{
     Stream, StreamExt,
     stream::{self, BoxStream},
 };
-use tokio::sync::{Notify, RwLock};
-use tokio::{select, sync::broadcast};
+use tokio::sync::{RwLock, mpsc};
 
 use crate::{
+    config::SSE_QUEUE_SIZE,
     errors::*,
-    sse::{SseContext, Token},
+    middlewares::metrics::{METRICS, SseGuard},
+    sse::{EventId, EventLog, SseContext, Token},
 };
 
 use super::context::SseInner;
 
+/// A token, with an id if the client can resume after it
+pub type Event = (Option<EventId>, Result<Token, Error>);
+
 pub struct Subscriber {
-    st: BoxStream<'static, Result<Token, Error>>,
+    st: BoxStream<'static, Event>,
+    _guard: SseGuard,
 }
 
-struct State {
-    inner: Arc<RwLock<SseInner>>,
-    on_receive: Arc<Notify>,
-    channel: broadcast::Receiver<Result<Token, Error>>,
-    offset: usize,
-}
 impl Stream for Subscriber {
-    type Item = Result<Token, Error>;
+    type Item = Event;
 
     fn poll_next(
         mut self: std::pin::Pin<&mut Self>,
@@ -37,93 +36,114 @@ impl Stream for Subscriber {
 }
 
 impl Subscriber {
-    pub(super) async fn new(ctx: &SseContext, chat_id: i32) -> Result<Self> {
-        let (state, st) = match ctx.map.lock().await.entry(chat_id) {
-            Entry::Occupied(entry) => {
-                let inner = entry.get().read().await;
-
-                let st =
-                    stream::iter([Ok(Token::LastMessage(inner.last_message_id, inner.version))]);
-
-                let state = State {
-                    inner: entry.get().clone(),
-                    on_receive: inner.on_receive.clone(),
-                    channel: inner.channel.subscribe(),
-                    offset: 0,
-                };
-                (state, st)
-            }
-            Entry::Vacant(entry) => {
-                let inner = SseInner::new(ctx).await?;
-                let on_receive = inner.on_receive.clone();
-                let channel = inner.channel.subscribe();
-
-                let st =
-                    stream::iter([Ok(Token::LastMessage(inner.last_message_id, inner.version))]);
-                let inner = entry.insert(Arc::new(RwLock::new(inner))).clone();
-
-                let state = State {
-                    inner,
-                    on_receive,
-                    channel,
-                    offset: 0,
-                };
-                (state, st)
-            }
+    /// The stream ends after [`Token::Lagged`] if the connection cannot keep up
+    pub(super) async fn new(
+        ctx: &SseContext,
+        chat_id: i32,
+        last_event_id: Option<EventId>,
+    ) -> Result<Self> {
+        let inner = match ctx.map.lock().await.entry(chat_id) {
+            Entry::Occupied(entry) => entry.get().clone(),
+            Entry::Vacant(entry) => entry
+                .insert(Arc::new(RwLock::new(SseInner::new(ctx).await?)))
+                .clone(),
         };
 
-        let st = st
-            .chain(stream::unfold(state, |mut state| async move {
-                let res = select! {
-                    biased;
-
-                    _ = state.on_receive.notified() => {
-                        handle_buffer(&mut state).await
-                    }
+        // one extra slot for the lagged notice
+        let (tx, rx) = mpsc::channel(SSE_QUEUE_SIZE + 1);
+        let events = {
+            let inner_ref = inner.read().await;
+            let mut log = inner_ref.log.lock().unwrap();
+
+            let resumed = last_event_id
+                .filter(|id| id.version == inner_ref.version)
+                .and_then(|id| log.since(id.seq));
+            let events = match resumed {
+                Some(events) => merge(
+                    events
+                        .into_iter()
+                        .map(|(seq, token)| {
+                            let id = EventId {
+                                version: inner_ref.version,
+                                seq,
+                            };
+                            (Some(id), token)
+                        })
+                        .collect(),
+                ),
+                None => snapshot(&inner_ref, &log),
+            };
+            // attach under the same lock, so no token is missed or sent twice
+            log.attach(tx);
+            events
+        };
 
-                    res = state.channel.recv() => {
-                        handle_channel(&mut state, res).await
+        let st = stream::iter(events)
+            .chain(
+                // holding `inner` keep the stream from being reaped
+                stream::unfold((rx, inner), |(mut rx, inner)| async move {
+                    let first = rx.recv().await?;
+                    // join whatever else is already queued
+                    let mut events = vec![first];
+                    while let Ok(event) = rx.try_recv() {
+                        events.push(event);
                     }
-                };
-
-                Some((res, state))
-            }))
+                    Some((merge(events), (rx, inner)))
+                })
+                .flat_map(stream::iter),
+            )
             .boxed();
 
-        Ok(Subscriber { st })
+        Ok(Subscriber {
+            st,
+            _guard: METRICS.sse_connected(),
+        })
     }
 }
 
-async fn handle_channel(
-    state: &mut State,
-    res: Result<Result<Token, Error>, impl ToString>,
-) -> Result<Token, Error> {
-    let token = res.map_err(|e| Error {
-        error: ErrorKind::Internal,
-        reason: e.to_string(),
-    })??;
-
-    match token {
-        Token::ChunkEnd(..) => {
-            state.offset = 0;
-        }
-        // don't care token
-        _ => {}
+/// What a fresh client need: where to paginate from and the current buffer
+fn snapshot(inner: &SseInner, log: &EventLog) -> Vec<Event> {
+    let mut events = vec![(
+        None,
+        Ok(Token::LastMessage(inner.last_message_id, inner.version)),
+    )];
+    if !inner.buffer.is_empty() {
+        let id = EventId {
+            version: inner.version,
+            seq: log.seq(),
+        };
+        let token = if inner.is_reasoning {
+            Token::ReasoningToken(inner.buffer.clone())
+        } else {
+            Token::Token(inner.buffer.clone())
+        };
+        events.push((Some(id), Ok(token)));
+    }
+    if let Some(input) = &inner.input {
+        events.push((None, Ok(input.clone())));
     }
-    Ok(token)
+    events
 }
 
-async fn handle_buffer(state: &mut State) -> Result<Token, Error> {
-    let inner = state.inner.read().await;
-
-    let token = inner.buffer[state.offset..].to_owned();
-    state.offset = inner.buffer.len();
-
-    let is_reasoning = inner.is_reasoning;
-    drop(inner);
-    if is_reasoning {
-        Ok(Token::ReasoningToken(token))
-    } else {
-        Ok(Token::Token(token))
+/// Join consecutive text tokens (and tool call args) into one delta
+fn merge(events: Vec<Event>) -> Vec<Event> {
+    let mut res: Vec<Event> = Vec::with_capacity(events.len());
+    for (id, token) in events {
+        match (res.last_mut(), token) {
+            (Some((prev_id, Ok(Token::Token(prev)))), Ok(Token::Token(t)))
+            | (Some((prev_id, Ok(Token::ReasoningToken(prev)))), Ok(Token::ReasoningToken(t))) => {
+                prev.push_str(&t);
+                *prev_id = id;
+            }
+            (
+                Some((prev_id, Ok(Token::ToolCallDelta(prev_name, prev)))),
+                Ok(Token::ToolCallDelta(name, t)),
+            ) if *prev_name == name => {
+                prev.push_str(&t);
+                *prev_id = id;
+            }
+            (_, token) => res.push((id, token)),
+        }
     }
+    res
 }
@@ -0,0 +1,111 @@
+//! Speech to text for voice input, see `STT_PROVIDER` env
+
+use anyhow::{Result, bail};
+use dotenv::var;
+use reqwest::multipart::{Form, Part};
+use serde::Deserialize;
+
+pub enum Stt {
+    /// `/audio/transcriptions` of an OpenAI-compatible provider
+    OpenAi {
+        endpoint: String,
+        api_key: Option<String>,
+        model: String,
+    },
+    /// `/inference` of a whisper.cpp server
+    WhisperCpp { endpoint: String },
+}
+
+#[derive(Deserialize)]
+struct Transcription {
+    text: String,
+}
+
+impl Stt {
+    /// None unless `STT_PROVIDER` is `openai` or `whisper_cpp`
+    pub fn from_env() -> Option<Self> {
+        let base = var("STT_API_BASE").ok();
+        let base = |default: &str| base.clone().unwrap_or(default.to_owned());
+        match var("STT_PROVIDER").ok()?.as_str() {
+            "openai" => Some(Self::OpenAi {
+                endpoint: format!(
+                    "{}/audio/transcriptions",
+                    base("https://api.openai.com/v1").trim_end_matches('/')
+                ),
+                api_key: var("STT_API_KEY").ok(),
+                model: var("STT_MODEL").unwrap_or("whisper-1".to_owned()),
+            }),
+            "whisper_cpp" => Some(Self::WhisperCpp {
+                endpoint: format!(
+                    "{}/inference",
+                    base("http://127.0.0.1:8080").trim_end_matches('/')
+                ),
+            }),
+            x => {
+                tracing::warn!(
+                    "unknown STT_PROVIDER {}, expect openai or whisper_cpp, voice input disabled",
+                    x
+                );
+                None
+            }
+        }
+    }
+
+    /// Text of a recording, `language` is an ISO 639-1 code, detected when None
+    pub async fn transcribe(
+        &self,
+        audio: Vec<u8>,
+        content_type: &str,
+        language: Option<&str>,
+    ) -> Result<String> {
+        // providers tell the format by the extension
+        let file = Part::bytes(audio)
+            .file_name(format!("voice.{}", extension(content_type)))
+            .mime_str(content_type)?;
+        let mut form = Form::new().part("file", file);
+        if let Some(language) = language {
+            form = form.text("language", language.to_owned());
+        }
+
+        let client = reqwest::Client::new();
+        let req = match self {
+            Self::OpenAi {
+                endpoint,
+                api_key,
+                model,
+            } => {
+                let req = client
+                    .post(endpoint)
+                    .multipart(form.text("model", model.clone()));
+                match api_key {
+                    Some(api_key) => req.bearer_auth(api_key),
+                    None => req,
+                }
+            }
+            Self::WhisperCpp { endpoint } => client
+                .post(endpoint)
+                .multipart(form.text("response_format", "json")),
+        };
+        let res = req.send().await?;
+        if !res.status().is_success() {
+            bail!(
+                "transcription failed with {}: {}",
+                res.status(),
+                res.text().await.unwrap_or_default()
+            );
+        }
+        Ok(res.json::<Transcription>().await?.text.trim().to_owned())
+    }
+}
+
+fn extension(content_type: &str) -> &'static str {
+    match content_type {
+        "audio/mpeg" => "mp3",
+        "audio/wav" => "wav",
+        "audio/flac" => "flac",
+        "audio/ogg" => "ogg",
+        "audio/mp4" => "m4a",
+        "video/mp4" => "mp4",
+        _ => "webm",
+    }
+}
@@ -0,0 +1,265 @@
+//! Everything kept about a user as one zip, asked for at `user/takeout`
+//!
+//! The archive is built by a job of `jobs` and stored like an upload owned by
+//! the user, so it is downloaded from `file/{id}` and swept with the other
+//! files attached to nothing after `FILE_ORPHAN_SECS`. Once stored, a
+//! notification tells its id to the open streams of the user. It holds:
+//!
+//! - `profile.json`, the account without its secrets
+//! - `chats/{id}.json`, each chat owned with every message of every branch
+//! - `tool_calls.json`, the calls made in those chats with their output
+//! - `memories.json`
+//! - `contacts.json`, the address book of `tools::contacts`
+//! - `inbox.json`, the messages of `notify::inbox`
+//! - `files.json` and `files/{id}-{name}`, the uploads but older takeouts
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use entity::{
+    ChunkKind, InboxSource, UserPreference, UserRole, chat, chunk, file, memory, message,
+    prelude::*,
+};
+use sea_orm::{QueryOrder, prelude::*};
+use serde::Serialize;
+use time::{UtcDateTime, macros::format_description};
+
+use crate::{
+    AppState,
+    config::INBOX_MAX_PER_USER,
+    files,
+    notify::{Notification, NotificationTakeout, inbox},
+    tools,
+    utils::zip,
+};
+
+/// Takeouts are named after it, and left out of later ones
+const NAME_PREFIX: &str = "llumen-takeout-";
+
+#[derive(Serialize)]
+struct Profile {
+    id: i32,
+    name: String,
+    email: Option<String>,
+    email_verified: bool,
+    role: UserRole,
+    preference: UserPreference,
+}
+
+#[derive(Serialize)]
+struct ChatTakeout {
+    #[serde(flatten)]
+    chat: chat::Model,
+    messages: Vec<MessageTakeout>,
+}
+
+#[derive(Serialize)]
+struct MessageTakeout {
+    #[serde(flatten)]
+    message: message::Model,
+    chunks: Vec<chunk::Model>,
+}
+
+#[derive(Serialize)]
+struct ToolCallTakeout {
+    chat_id: i32,
+    message_id: i32,
+    name: String,
+    args: String,
+    /// Emptied after `TOOL_LOG_DAYS`
+    output: String,
+}
+
+#[derive(Serialize)]
+struct MemoryTakeout {
+    id: i32,
+    content: String,
+    chat_id: Option<i32>,
+    created_at: i64,
+}
+
+#[derive(Serialize)]
+struct ContactTakeout {
+    id: i32,
+    name: String,
+    email: String,
+    note: Option<String>,
+    created_at: i64,
+}
+
+#[derive(Serialize)]
+struct InboxTakeout {
+    id: i32,
+    source: InboxSource,
+    content: String,
+    chat_id: Option<i32>,
+    created_at: i64,
+    read_at: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct FileTakeout {
+    id: i32,
+    name: String,
+    content_type: String,
+    size: i64,
+    created_at: i64,
+    /// Where it is in the archive
+    path: String,
+}
+
+/// Build the takeout of the user and tell them it is ready
+pub async fn run_job(app: &Arc<AppState>, user_id: i32) -> Result<()> {
+    let Some(user) = User::find_by_id(user_id).one(&app.conn).await? else {
+        // deleted since it was asked for
+        return Ok(());
+    };
+    let now = UtcDateTime::now();
+    let mut archive = zip::Writer::new(now);
+
+    let profile = Profile {
+        id: user.id,
+        name: user.name,
+        email: user.email,
+        email_verified: user.email_verified,
+        role: user.role,
+        preference: user.preference,
+    };
+    archive.append("profile.json", &serde_json::to_vec_pretty(&profile)?)?;
+
+    let chats = Chat::find()
+        .filter(chat::Column::OwnerId.eq(user_id))
+        .order_by_asc(chat::Column::Id)
+        .all(&app.conn)
+        .await?;
+    let mut tool_calls = Vec::new();
+    for chat in chats {
+        let messages = Message::find()
+            .filter(message::Column::ChatId.eq(chat.id))
+            .order_by_asc(message::Column::Id)
+            .find_with_related(Chunk)
+            .all(&app.conn)
+            .await?;
+        let messages = messages
+            .into_iter()
+            .map(|(message, mut chunks)| {
+                chunks.sort_by_key(|x| x.id);
+                for chunk in chunks.iter().filter(|x| x.kind == ChunkKind::ToolCall) {
+                    if let Ok(call) = chunk.as_tool_call() {
+                        tool_calls.push(ToolCallTakeout {
+                            chat_id: chat.id,
+                            message_id: message.id,
+                            name: call.name,
+                            args: call.args,
+                            output: call.content,
+                        });
+                    }
+                }
+                MessageTakeout { message, chunks }
+            })
+            .collect();
+        let path = format!("chats/{}.json", chat.id);
+        let chat = ChatTakeout { chat, messages };
+        archive.append(&path, &serde_json::to_vec_pretty(&chat)?)?;
+    }
+    archive.append("tool_calls.json", &serde_json::to_vec_pretty(&tool_calls)?)?;
+
+    let memories: Vec<_> = Memory::find()
+        .filter(memory::Column::OwnerId.eq(user_id))
+        .order_by_asc(memory::Column::Id)
+        .all(&app.conn)
+        .await?
+        .into_iter()
+        .map(|x| MemoryTakeout {
+            id: x.id,
+            content: x.content,
+            chat_id: x.chat_id,
+            created_at: x.created_at,
+        })
+        .collect();
+    archive.append("memories.json", &serde_json::to_vec_pretty(&memories)?)?;
+
+    let contacts: Vec<_> = tools::contacts::all(&app.conn, user_id)
+        .await?
+        .into_iter()
+        .map(|x| ContactTakeout {
+            id: x.id,
+            name: x.name,
+            email: x.email,
+            note: x.note,
+            created_at: x.created_at,
+        })
+        .collect();
+    archive.append("contacts.json", &serde_json::to_vec_pretty(&contacts)?)?;
+
+    let messages: Vec<_> = inbox::list(&app.conn, user_id, None, INBOX_MAX_PER_USER)
+        .await?
+        .into_iter()
+        .map(|x| InboxTakeout {
+            id: x.id,
+            source: x.source,
+            content: x.content,
+            chat_id: x.chat_id,
+            created_at: x.created_at,
+            read_at: x.read_at,
+        })
+        .collect();
+    archive.append("inbox.json", &serde_json::to_vec_pretty(&messages)?)?;
+
+    let uploads = File::find()
+        .filter(file::Column::OwnerId.eq(user_id))
+        .filter(file::Column::Name.starts_with(NAME_PREFIX).not())
+        .order_by_asc(file::Column::Id)
+        .all(&app.conn)
+        .await?;
+    let mut index = Vec::with_capacity(uploads.len());
+    for upload in uploads {
+        let path = format!("files/{}-{}", upload.id, safe_name(&upload.name));
+        let data = app
+            .files
+            .get(&upload.storage_key)
+            .await
+            .with_context(|| format!("Cannot read file {}", upload.id))?;
+        archive.append(&path, &data)?;
+        index.push(FileTakeout {
+            id: upload.id,
+            name: upload.name,
+            content_type: upload.content_type,
+            size: upload.size,
+            created_at: upload.created_at,
+            path,
+        });
+    }
+    archive.append("files.json", &serde_json::to_vec_pretty(&index)?)?;
+
+    let data = archive.finish()?;
+    let date = now.format(format_description!("[year]-[month]-[day]"))?;
+    let file = files::create(
+        &app.conn,
+        &app.files,
+        user_id,
+        format!("{}{}.zip", NAME_PREFIX, date),
+        data,
+    )
+    .await?;
+    app.notifier.send(
+        user_id,
+        Notification::Takeout(NotificationTakeout {
+            file_id: file.id,
+            name: file.name,
+            size: file.size,
+        }),
+    );
+    Ok(())
+}
+
+/// Names are from clients, they must not climb out of `files/`
+fn safe_name(name: &str) -> String {
+    name.chars()
+        .map(|x| match x {
+            '/' | '\\' | ':' => '_',
+            x if x.is_control() => '_',
+            x => x,
+        })
+        .collect()
+}
@@ -0,0 +1,366 @@
+//! OpenTelemetry traces, sent over OTLP/HTTP in JSON to
+//! `OTEL_EXPORTER_OTLP_ENDPOINT` env, e.g. Jaeger or Tempo, when it is set
+//!
+//! Spans of `backend` are exported as they close, in the trace of the request
+//! span of `middlewares::request_id`; logs in a span become its events, and
+//! queries logged by sqlx become spans of their own, timed back from the log.
+//! A span ends when it is last exited rather than closed, since the request
+//! span is kept open by tasks it spawned. Finished spans are queued, at most
+//! `OTEL_MAX_QUEUE`, and sent every `OTEL_EXPORT_INTERVAL` seconds
+
+use std::{
+    fmt::Debug,
+    sync::{Mutex, OnceLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use dotenv::var;
+use serde_json::{Value, json};
+use tracing::{
+    Event, Level, Subscriber,
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+};
+use tracing_subscriber::{Layer, filter::Targets, layer::Context, registry::LookupSpan};
+
+use crate::config::{OTEL_EXPORT_INTERVAL, OTEL_MAX_EVENTS, OTEL_MAX_QUEUE};
+
+static EXPORTER: OnceLock<Exporter> = OnceLock::new();
+
+const KIND_INTERNAL: u8 = 1;
+const KIND_SERVER: u8 = 2;
+const KIND_CLIENT: u8 = 3;
+const STATUS_ERROR: u8 = 2;
+
+struct Exporter {
+    endpoint: String,
+    headers: Vec<(String, String)>,
+    service: String,
+    /// `db.system` of query spans, after the scheme of `DATABASE_URL`
+    db_system: &'static str,
+    queue: Mutex<Vec<Value>>,
+}
+
+/// The layer sending spans to the collector, None without the env
+pub fn layer<S>() -> Option<impl Layer<S>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let endpoint = var("OTEL_EXPORTER_OTLP_ENDPOINT").ok()?;
+    // `key=value` pairs separated by commas, as the other OTLP exporters
+    let headers = var("OTEL_EXPORTER_OTLP_HEADERS")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|x| x.split_once('='))
+        .map(|(key, value)| (key.trim().to_owned(), value.trim().to_owned()))
+        .collect();
+    let exporter = Exporter {
+        endpoint: format!("{}/v1/traces", endpoint.trim_end_matches('/')),
+        headers,
+        service: var("OTEL_SERVICE_NAME").unwrap_or("llumen".to_owned()),
+        db_system: match crate::app::database_url().starts_with("postgres") {
+            true => "postgresql",
+            false => "sqlite",
+        },
+        queue: Mutex::default(),
+    };
+    EXPORTER.set(exporter).ok()?;
+
+    let filter = Targets::new()
+        .with_target("backend", Level::TRACE)
+        .with_target("sqlx::query", Level::INFO);
+    Some(Otlp.with_filter(filter))
+}
+
+/// Send the queued spans until the server stops, if [`layer`] is in use
+pub fn spawn_export() {
+    let Some(exporter) = EXPORTER.get() else {
+        return;
+    };
+    tokio::spawn(async move {
+        let client = reqwest::Client::new();
+        let mut interval = tokio::time::interval(Duration::from_secs(OTEL_EXPORT_INTERVAL));
+        loop {
+            interval.tick().await;
+            let spans = std::mem::take(&mut *exporter.queue.lock().unwrap());
+            if spans.is_empty() {
+                continue;
+            }
+            // outside of any span, so not exported itself
+            if let Err(err) = exporter.send(&client, spans).await {
+                tracing::warn!("cannot export traces: {}", err);
+            }
+        }
+    });
+}
+
+impl Exporter {
+    async fn send(&self, client: &reqwest::Client, spans: Vec<Value>) -> Result<()> {
+        let body = json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [attribute("service.name", json!({ "stringValue": self.service }))],
+                },
+                "scopeSpans": [{ "scope": { "name": "backend" }, "spans": spans }],
+            }],
+        });
+        let mut req = client.post(&self.endpoint).json(&body);
+        for (key, value) in &self.headers {
+            req = req.header(key, value);
+        }
+        req.send().await?.error_for_status()?;
+        Ok(())
+    }
+
+    fn push(&self, span: Value) {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.len() < OTEL_MAX_QUEUE {
+            queue.push(span);
+        }
+    }
+}
+
+struct Otlp;
+
+/// Kept in the extensions of a span until it closes
+struct SpanData {
+    trace_id: u128,
+    span_id: u64,
+    parent_id: Option<u64>,
+    start: SystemTime,
+    end: Option<SystemTime>,
+    fields: Fields,
+    events: Vec<Value>,
+    /// Message of an error logged in the span or of its `error` field
+    error: Option<String>,
+}
+
+impl SpanData {
+    fn child_of(parent: Option<&SpanData>) -> Self {
+        Self {
+            trace_id: parent.map_or_else(|| fastrand::u128(1..), |x| x.trace_id),
+            span_id: fastrand::u64(1..),
+            parent_id: parent.map(|x| x.span_id),
+            start: SystemTime::now(),
+            end: None,
+            fields: Fields::default(),
+            events: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// In the JSON encoding of OTLP, `otel.name` and `otel.kind` fields
+    /// override the name and kind
+    fn encode(mut self, name: &str) -> Value {
+        let name = self.fields.take_str("otel.name").unwrap_or(name.to_owned());
+        let kind = match self.fields.take_str("otel.kind").as_deref() {
+            Some("server") => KIND_SERVER,
+            Some("client") => KIND_CLIENT,
+            Some(_) => KIND_INTERNAL,
+            None if self.parent_id.is_none() => KIND_SERVER,
+            None => KIND_INTERNAL,
+        };
+        let error = self.fields.take_str("error").or(self.error);
+        let status = match error {
+            Some(message) => json!({ "code": STATUS_ERROR, "message": message }),
+            None => json!({}),
+        };
+        json!({
+            "traceId": format!("{:032x}", self.trace_id),
+            "spanId": format!("{:016x}", self.span_id),
+            "parentSpanId": self.parent_id.map(|x| format!("{:016x}", x)).unwrap_or_default(),
+            "name": name,
+            "kind": kind,
+            "startTimeUnixNano": nanos(self.start),
+            "endTimeUnixNano": nanos(self.end.unwrap_or_else(SystemTime::now)),
+            "attributes": self.fields.attributes(),
+            "events": self.events,
+            "status": status,
+        })
+    }
+}
+
+impl<S> Layer<S> for Otlp
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let parent = span.parent();
+        let parent_ext = parent.as_ref().map(|x| x.extensions());
+        let mut data = SpanData::child_of(parent_ext.as_ref().and_then(|x| x.get()));
+        drop(parent_ext);
+        attrs.record(&mut data.fields);
+        span.extensions_mut().insert(data);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if let Some(data) = span.extensions_mut().get_mut::<SpanData>() {
+            values.record(&mut data.fields);
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if let Some(data) = span.extensions_mut().get_mut::<SpanData>() {
+            data.end = Some(SystemTime::now());
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        // logs outside of requests and background jobs belong to no trace
+        let Some(span) = ctx.event_span(event) else {
+            return;
+        };
+        let now = SystemTime::now();
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+
+        let mut ext = span.extensions_mut();
+        let Some(data) = ext.get_mut::<SpanData>() else {
+            return;
+        };
+        if event.metadata().target() == "sqlx::query" {
+            let query = query(data, fields, now);
+            drop(ext);
+            if let Some(exporter) = EXPORTER.get() {
+                exporter.push(query);
+            }
+            return;
+        }
+
+        let message = fields.take_str("message");
+        if *event.metadata().level() == Level::ERROR {
+            data.error = message.clone();
+        }
+        if data.events.len() < OTEL_MAX_EVENTS {
+            fields.push(
+                "level",
+                json!({ "stringValue": event.metadata().level().as_str() }),
+            );
+            data.events.push(json!({
+                "timeUnixNano": nanos(now),
+                "name": message.unwrap_or(event.metadata().name().to_owned()),
+                "attributes": fields.attributes(),
+            }));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(data) = span.extensions_mut().remove::<SpanData>() else {
+            return;
+        };
+        if let Some(exporter) = EXPORTER.get() {
+            exporter.push(data.encode(span.name()));
+        }
+    }
+}
+
+/// A span of a query logged by sqlx once it is done, in the span it ran in
+fn query(parent: &SpanData, mut fields: Fields, now: SystemTime) -> Value {
+    let elapsed = fields
+        .take("elapsed_secs")
+        .and_then(|x| x["doubleValue"].as_f64())
+        .unwrap_or_default();
+    fields.take("elapsed");
+    fields.take("message");
+    let summary = fields.take_str("summary").unwrap_or("query".to_owned());
+    // left empty when the summary is the whole statement
+    let statement = fields
+        .take_str("db.statement")
+        .map(|x| x.trim().to_owned())
+        .filter(|x| !x.is_empty())
+        .unwrap_or(summary.clone());
+    let db_system = EXPORTER.get().map_or("sqlite", |x| x.db_system);
+    fields.push("db.system", json!({ "stringValue": db_system }));
+    fields.push("db.statement", json!({ "stringValue": statement }));
+    fields.push("otel.kind", json!({ "stringValue": "client" }));
+
+    let mut data = SpanData::child_of(Some(parent));
+    data.start = now - Duration::from_secs_f64(elapsed);
+    data.end = Some(now);
+    data.fields = fields;
+    data.encode(&summary)
+}
+
+/// Fields of a span or event as OTLP values
+#[derive(Default)]
+struct Fields(Vec<(&'static str, Value)>);
+
+impl Fields {
+    fn push(&mut self, name: &'static str, value: Value) {
+        match self.0.iter_mut().find(|x| x.0 == name) {
+            Some(field) => field.1 = value,
+            None => self.0.push((name, value)),
+        }
+    }
+
+    fn take(&mut self, name: &str) -> Option<Value> {
+        let idx = self.0.iter().position(|x| x.0 == name)?;
+        Some(self.0.remove(idx).1)
+    }
+
+    fn take_str(&mut self, name: &str) -> Option<String> {
+        let value = self.take(name)?;
+        value["stringValue"].as_str().map(str::to_owned)
+    }
+
+    fn attributes(self) -> Vec<Value> {
+        self.0
+            .into_iter()
+            .map(|(key, value)| attribute(key, value))
+            .collect()
+    }
+}
+
+impl Visit for Fields {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.push(field.name(), json!({ "doubleValue": value }));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.push(field.name(), json!({ "intValue": value }));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.push(field.name(), json!({ "intValue": value }));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.push(field.name(), json!({ "boolValue": value }));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.push(field.name(), json!({ "stringValue": value }));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.push(
+            field.name(),
+            json!({ "stringValue": format!("{:?}", value) }),
+        );
+    }
+}
+
+fn attribute(key: &str, value: Value) -> Value {
+    json!({ "key": key, "value": value })
+}
+
+/// Nanoseconds since the epoch, a string as int64 in JSON
+fn nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
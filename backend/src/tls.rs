@@ -0,0 +1,181 @@
+//! TLS termination with rustls, when `TLS_CERT` and `TLS_KEY` env point at PEM
+//! files, so a small deployment serves HTTPS without a reverse proxy
+//!
+//! HTTP/2 is offered over ALPN, HTTP/1.1 to clients without it. The files are
+//! read again when they change, so a certificate renewed by certbot or another
+//! ACME client is served without a restart. Handshakes run in tasks of their
+//! own, a slow client cannot hold up the accept loop
+
+use std::{
+    io,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context, Result};
+use axum::serve::Listener;
+use dotenv::var;
+use rustls::{
+    ServerConfig,
+    crypto::ring,
+    pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+    time::timeout,
+};
+use tokio_rustls::{TlsAcceptor, server::TlsStream};
+
+use crate::config::{TLS_HANDSHAKE_TIMEOUT, TLS_RELOAD_INTERVAL};
+
+/// Connections past the handshake waiting for the server to take them
+const ACCEPT_QUEUE: usize = 64;
+
+pub struct Paths {
+    cert: PathBuf,
+    key: PathBuf,
+}
+
+impl Paths {
+    /// None without `TLS_CERT` and `TLS_KEY`, serving plain HTTP
+    pub fn from_env() -> Option<Self> {
+        match (var("TLS_CERT").ok(), var("TLS_KEY").ok()) {
+            (Some(cert), Some(key)) => Some(Self {
+                cert: cert.into(),
+                key: key.into(),
+            }),
+            (None, None) => None,
+            _ => panic!("TLS_CERT and TLS_KEY must be set together"),
+        }
+    }
+
+    fn load(&self) -> Result<CertifiedKey> {
+        let certs = CertificateDer::pem_file_iter(&self.cert)
+            .and_then(|x| x.collect::<Result<Vec<_>, _>>())
+            .with_context(|| format!("Cannot read {}", self.cert.display()))?;
+        let key = PrivateKeyDer::from_pem_file(&self.key)
+            .with_context(|| format!("Cannot read {}", self.key.display()))?;
+        let key = ring::sign::any_supported_type(&key)?;
+        Ok(CertifiedKey::new(certs, key))
+    }
+
+    fn modified(&self) -> Option<(SystemTime, SystemTime)> {
+        let modified = |x: &PathBuf| std::fs::metadata(x).and_then(|x| x.modified()).ok();
+        Some((modified(&self.cert)?, modified(&self.key)?))
+    }
+}
+
+/// The current certificate, swapped by [`Resolver::spawn_reload`]
+#[derive(Debug)]
+struct Resolver(RwLock<Arc<CertifiedKey>>);
+
+impl ResolvesServerCert for Resolver {
+    fn resolve(&self, _: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.0.read().unwrap().clone())
+    }
+}
+
+impl Resolver {
+    fn spawn_reload(self: Arc<Self>, paths: Paths) {
+        tokio::spawn(async move {
+            let mut last = paths.modified();
+            let mut interval = tokio::time::interval(Duration::from_secs(TLS_RELOAD_INTERVAL));
+            loop {
+                interval.tick().await;
+                let modified = paths.modified();
+                if modified.is_none() || modified == last {
+                    continue;
+                }
+                match paths.load() {
+                    Ok(key) => {
+                        tracing::info!("reloaded the TLS certificate");
+                        *self.0.write().unwrap() = Arc::new(key);
+                        last = modified;
+                    }
+                    // likely half written, tried again next time
+                    Err(e) => tracing::warn!("cannot reload the TLS certificate: {:#}", e),
+                }
+            }
+        });
+    }
+}
+
+/// Accepts connections once their handshake is done, served by `axum::serve`
+pub struct TlsListener {
+    rx: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    pub fn new(tcp: TcpListener, paths: Paths) -> Result<Self> {
+        let resolver = Arc::new(Resolver(RwLock::new(Arc::new(paths.load()?))));
+        let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_cert_resolver(resolver.clone());
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+        resolver.spawn_reload(paths);
+
+        let local_addr = tcp.local_addr()?;
+        let (tx, rx) = mpsc::channel(ACCEPT_QUEUE);
+        tokio::spawn(async move {
+            loop {
+                let (stream, addr) = match tcp.accept().await {
+                    Ok(x) => x,
+                    Err(e) => {
+                        accept_error(e).await;
+                        continue;
+                    }
+                };
+                let (acceptor, tx) = (acceptor.clone(), tx.clone());
+                tokio::spawn(async move {
+                    let handshake = acceptor.accept(stream);
+                    match timeout(Duration::from_secs(TLS_HANDSHAKE_TIMEOUT), handshake).await {
+                        Ok(Ok(stream)) => {
+                            tx.send((stream, addr)).await.ok();
+                        }
+                        Ok(Err(e)) => tracing::debug!("TLS handshake with {} failed: {}", addr, e),
+                        Err(_) => tracing::debug!("TLS handshake with {} timed out", addr),
+                    }
+                });
+            }
+        });
+
+        Ok(Self { rx, local_addr })
+    }
+}
+
+/// As `axum::serve` does for plain TCP: errors of one connection are
+/// skipped, others (e.g. out of file descriptors) are waited out
+async fn accept_error(e: io::Error) {
+    if matches!(
+        e.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+    ) {
+        return;
+    }
+    tracing::error!("cannot accept a connection: {}", e);
+    tokio::time::sleep(Duration::from_secs(1)).await;
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        // the sender lives in the accept loop, which never ends
+        self.rx.recv().await.expect("TLS accept loop stopped")
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::Context;
+use dotenv::var;
+use entity::prelude::*;
+use schemars::JsonSchema;
+use sea_orm::EntityTrait;
+use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+
+use crate::{
+    audit,
+    config::{DELEGATE_MAX_RUNS, DELEGATE_MAX_STEPS},
+    errors::JsonUnion,
+    middlewares::locale,
+    openrouter::{self, StreamCompletionResp},
+    prompts::DelegateStore,
+    tools::{AGENT, InvalidArgs, Tool, ToolCtx},
+};
+
+/// Run a subtask in a fresh conversation, only the final answer is returned to the caller
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Delegate {
+    /// recent runs, kept for inspection
+    runs: Vec<DelegateRun>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DelegateRun {
+    task: String,
+    tools: Vec<String>,
+    transcript: Vec<DelegateStep>,
+    result: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "t", content = "c", rename_all = "snake_case")]
+pub enum DelegateStep {
+    Assistant(String),
+    ToolCall {
+        name: String,
+        arguments: String,
+        content: String,
+    },
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DelegateInput {
+    /// a self-contained description of the subtask, the sub-agent cannot see the chat
+    task: String,
+    /// names of the tools the sub-agent may use, e.g. `["wttr"]`
+    tools: Option<Vec<String>>,
+}
+
+impl Tool for Delegate {
+    type Input = DelegateInput;
+    type Output = String;
+
+    const NAME: &str = "delegate";
+    const DESCRIPTION: &str = "delegate a subtask to a sub-agent with its own context and a subset of tools, return the result of the subtask";
+    const PROMPT: &str = "use `delegate` to hand off a self-contained subtask whose intermediate steps are not needed";
+
+    async fn call(&mut self, input: Self::Input, ctx: &ToolCtx) -> anyhow::Result<Self::Output> {
+        let app = &ctx.app;
+
+        let chat = Chat::find_by_id(ctx.chat_id)
+            .one(&app.conn)
+            .await?
+            .context("Cannot find chat")?;
+        let user = User::find_by_id(chat.owner_id)
+            .one(&app.conn)
+            .await?
+            .context("Cannot find user")?;
+        let mut model: openrouter::Model = Model::find_by_id(chat.model_id)
+            .one(&app.conn)
+            .await?
+            .context("Cannot find model")?
+            .get_config()
+            .context("Malformed model config")?
+            .into();
+        if let Ok(id) = var("DELEGATE_MODEL") {
+            model.id = id;
+            model.reasoning = false;
+        }
+
+        // never nest delegation
+        let names: Vec<&'static str> = AGENT
+            .toold()
+            .filter(|name| *name != Self::NAME)
+            .filter(|name| input.tools.iter().flatten().any(|x| x == name))
+            .collect();
+        let (tool_prompts, tools) = app.tools.list_by_name(names.iter().copied());
+        let mut tool_box = app
+            .tools
+            .grab_by_name(ctx.chat_id, names.iter().copied())
+            .await?;
+
+        let system_prompt = app
+            .prompt
+            .template(
+                &DelegateStore,
+                Some(locale::of_user(user.preference.locale.as_deref()).code()),
+                ctx.workspace_id().await?,
+            )
+            .await?
+            .render(&app.prompt, ctx.chat_id, tool_prompts, (), ())
+            .await?;
+        let mut messages = vec![
+            openrouter::Message::System(system_prompt),
+            openrouter::Message::User(input.task.clone()),
+        ];
+
+        let mut transcript = vec![];
+        let mut result = String::new();
+        for step in 0..=DELEGATE_MAX_STEPS {
+            let tools = match step == DELEGATE_MAX_STEPS {
+                true => vec![],
+                false => tools.clone(),
+            };
+            let mut completion = app
+                .openrouter
+                .stream(messages.clone(), &model, tools)
+                .await?;
+
+            let mut text = String::new();
+            let mut tool_calls = vec![];
+            while let Some(resp) = completion.next().await {
+                match resp? {
+                    StreamCompletionResp::ResponseToken(token) => text.push_str(&token),
+                    StreamCompletionResp::ToolCall { name, args, id } => {
+                        tool_calls.push(openrouter::MessageToolCall {
+                            id,
+                            name,
+                            arguments: args,
+                        })
+                    }
+                    _ => {}
+                }
+            }
+
+            if !text.is_empty() {
+                messages.push(openrouter::Message::Assistant(text.clone()));
+                transcript.push(DelegateStep::Assistant(text.clone()));
+            }
+            if tool_calls.is_empty() {
+                result = text;
+                break;
+            }
+
+            for tool_call in tool_calls {
+                // fixed by the model at the next step, bounded by the steps
+                let problems = tool_box.check(&tool_call.name, &tool_call.arguments);
+                let content = match problems.is_empty() {
+                    false => serde_json::to_string(&InvalidArgs::new(problems, true))?,
+                    true => {
+                        let output = match tool_box.get(&tool_call.name) {
+                            Some((name, tool)) => {
+                                let span = tracing::info_span!("tool", name);
+                                let output =
+                                    tool.call(&tool_call.arguments, ctx).instrument(span).await;
+                                audit::tool_call(ctx, name, output.is_ok()).await;
+                                output.map_err(|e| e.to_string())
+                            }
+                            None => Err(format!("tool `{}` is not available", tool_call.name)),
+                        };
+                        serde_json::to_string(&JsonUnion::from(output))?
+                    }
+                };
+
+                transcript.push(DelegateStep::ToolCall {
+                    name: tool_call.name.clone(),
+                    arguments: tool_call.arguments.clone(),
+                    content: content.clone(),
+                });
+                let id = tool_call.id.clone();
+                messages.push(openrouter::Message::ToolCall(tool_call));
+                messages.push(openrouter::Message::ToolResult(
+                    openrouter::MessageToolResult { id, content },
+                ));
+            }
+        }
+
+        app.tools.put_back(tool_box).await?;
+
+        if self.runs.len() >= DELEGATE_MAX_RUNS {
+            self.runs.remove(0);
+        }
+        self.runs.push(DelegateRun {
+            task: input.task,
+            tools: names.into_iter().map(ToOwned::to_owned).collect(),
+            transcript,
+            result: result.clone(),
+        });
+
+        Ok(result)
+    }
+}
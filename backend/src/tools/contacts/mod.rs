@@ -0,0 +1,193 @@
+//! Address book of a user, so mails can go to a name
+//!
+//! The model saves contacts with `addcontact` and looks them up with
+//! `findcontact` and `listcontacts`; `sendmail` and `replymail` resolve a
+//! recipient given by name through [`resolve`], asking the user when the name
+//! matches several contacts or none
+
+use anyhow::Result;
+use entity::{contact, prelude::*};
+use schemars::JsonSchema;
+use sea_orm::{ActiveValue::Set, IntoActiveModel, QueryOrder, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{CONTACT_FIELD_MAX_CHARS, CONTACT_LIST_LIMIT, CONTACT_MAX_PER_USER},
+    tools::{Tool, ToolCtx},
+};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AddContact;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FindContact;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ListContacts;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AddContactInput {
+    /// the name the user calls them by, e.g. `Alice Chen`
+    name: String,
+    email: String,
+    /// how they relate to the user, e.g. `sister` or `landlord`
+    note: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FindContactInput {
+    /// a name, part of an address, or a note such as `sister`
+    query: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ListContactsInput {}
+
+#[derive(Debug, Serialize)]
+pub struct ContactOutput {
+    name: String,
+    email: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+}
+
+impl From<contact::Model> for ContactOutput {
+    fn from(x: contact::Model) -> Self {
+        Self {
+            name: x.name,
+            email: x.email,
+            note: x.note,
+        }
+    }
+}
+
+/// Contacts of the user by name
+pub async fn all(conn: &DbConn, user_id: i32) -> Result<Vec<contact::Model>> {
+    Ok(Contact::find()
+        .filter(contact::Column::UserId.eq(user_id))
+        .order_by_asc(contact::Column::Name)
+        .all(conn)
+        .await?)
+}
+
+/// Contacts a name given for a recipient may stand for: those of exactly that
+/// name, ignoring case, otherwise those whose name or note contains it
+pub async fn resolve(conn: &DbConn, user_id: i32, name: &str) -> Result<Vec<contact::Model>> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() {
+        return Ok(vec![]);
+    }
+    let contacts = all(conn, user_id).await?;
+    let exact: Vec<_> = contacts
+        .iter()
+        .filter(|x| x.name.to_lowercase() == name)
+        .cloned()
+        .collect();
+    if !exact.is_empty() {
+        return Ok(exact);
+    }
+    Ok(contacts
+        .into_iter()
+        .filter(|x| {
+            x.name.to_lowercase().contains(&name)
+                || x.note
+                    .as_ref()
+                    .is_some_and(|note| note.to_lowercase().contains(&name))
+        })
+        .collect())
+}
+
+fn field(text: &str) -> String {
+    text.trim().chars().take(CONTACT_FIELD_MAX_CHARS).collect()
+}
+
+impl Tool for AddContact {
+    type Input = AddContactInput;
+    type Output = String;
+
+    const NAME: &str = "addcontact";
+    const DESCRIPTION: &str = "save a person to the address book of the user with their email address, an address saved before is renamed";
+    const PROMPT: &str =
+        "use `addcontact` when the user gives you the address of someone they may mail again";
+
+    async fn call(&mut self, input: Self::Input, ctx: &ToolCtx) -> anyhow::Result<Self::Output> {
+        let name = field(&input.name);
+        let email = field(&input.email).to_lowercase();
+        let note = input.note.as_deref().map(field).filter(|x| !x.is_empty());
+        if name.is_empty() || !email.contains('@') {
+            anyhow::bail!("a contact needs a name and an email address");
+        }
+        let conn = &ctx.app.conn;
+        let contacts = all(conn, ctx.user_id).await?;
+        if let Some(known) = contacts.iter().find(|x| x.email == email) {
+            let mut known = known.clone().into_active_model();
+            known.name = Set(name);
+            if note.is_some() {
+                known.note = Set(note);
+            }
+            known.update(conn).await?;
+            return Ok("updated".to_owned());
+        }
+        if contacts.len() >= CONTACT_MAX_PER_USER {
+            return Ok(
+                "the address book is full, the user has to remove contacts first".to_owned(),
+            );
+        }
+        Contact::insert(contact::ActiveModel {
+            user_id: Set(ctx.user_id),
+            name: Set(name),
+            email: Set(email),
+            note: Set(note),
+            created_at: Set(time::UtcDateTime::now().unix_timestamp()),
+            ..Default::default()
+        })
+        .exec(conn)
+        .await?;
+        Ok("saved".to_owned())
+    }
+}
+
+impl Tool for FindContact {
+    type Input = FindContactInput;
+    type Output = Vec<ContactOutput>;
+
+    const NAME: &str = "findcontact";
+    const DESCRIPTION: &str =
+        "look up people in the address book of the user by name, address or note";
+    const PROMPT: &str = "use `findcontact` to get the address of someone the user names";
+
+    async fn call(&mut self, input: Self::Input, ctx: &ToolCtx) -> anyhow::Result<Self::Output> {
+        let query = input.query.trim().to_lowercase();
+        Ok(all(&ctx.app.conn, ctx.user_id)
+            .await?
+            .into_iter()
+            .filter(|x| {
+                x.name.to_lowercase().contains(&query)
+                    || x.email.contains(&query)
+                    || x.note
+                        .as_ref()
+                        .is_some_and(|note| note.to_lowercase().contains(&query))
+            })
+            .take(CONTACT_LIST_LIMIT)
+            .map(Into::into)
+            .collect())
+    }
+}
+
+impl Tool for ListContacts {
+    type Input = ListContactsInput;
+    type Output = Vec<ContactOutput>;
+
+    const NAME: &str = "listcontacts";
+    const DESCRIPTION: &str = "list the address book of the user by name";
+    const PROMPT: &str = "use `listcontacts` when the user asks who is in their address book";
+
+    async fn call(&mut self, _: Self::Input, ctx: &ToolCtx) -> anyhow::Result<Self::Output> {
+        Ok(all(&ctx.app.conn, ctx.user_id)
+            .await?
+            .into_iter()
+            .take(CONTACT_LIST_LIMIT)
+            .map(Into::into)
+            .collect())
+    }
+}
@@ -0,0 +1,210 @@
+//! HTTP-backed tools declared in `TOOLS_DIR` (default `tools.d/`), one TOML file each
+//!
+//! ```toml
+//! name = "exchange_rate"
+//! description = "latest exchange rates for a currency"
+//! prompt = "use `exchange_rate` when user ask about currency conversion"
+//!
+//! [schema]
+//! type = "object"
+//! properties.base = { type = "string", description = "ISO 4217 code" }
+//! required = ["base"]
+//!
+//! [request]
+//! method = "GET"
+//! url = "https://api.example.com/latest?base={{ base | urlencode }}"
+//! headers = { Authorization = "Bearer {{ env('EXCHANGE_API_KEY') }}" }
+//! ```
+//!
+//! `url`, header values and `body` are minijinja templates rendered with the
+//! arguments of the call, `env(name)` read the credential of the workspace of
+//! the chat of that name, otherwise the environment variable, and `vars`
+//! hold the variables other tools set in the chat. Escape arguments with
+//! `urlencode` in urls and `tojson` in JSON bodies
+//!
+//! Specs can also be generated from an OpenAPI document, see [`openapi`]
+
+pub mod openapi;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result, bail};
+use futures_util::{FutureExt, future::BoxFuture};
+use minijinja::{Environment, context};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    config::DECLARED_TOOL_MAX_BYTES,
+    tools::{ToolCtx, UntypedTool},
+};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Spec {
+    pub name: String,
+    pub description: String,
+    #[serde(default)]
+    pub prompt: String,
+    /// JSON schema of the arguments
+    pub schema: Value,
+    pub request: Request,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Request {
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+}
+
+fn default_method() -> String {
+    "GET".to_owned()
+}
+
+/// `TOOLS_DIR`, default to `tools.d`
+pub fn dir() -> PathBuf {
+    dotenv::var("TOOLS_DIR")
+        .unwrap_or("tools.d".to_owned())
+        .into()
+}
+
+/// Every file ending with `.toml`, invalid ones are skipped with a warning
+pub fn load_dir(dir: &Path) -> Vec<Spec> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|x| x.ok().map(|x| x.path()))
+        .filter(|x| x.extension().is_some_and(|x| x == "toml"))
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .filter_map(|path| match load(&path) {
+            Ok(spec) => Some(spec),
+            Err(err) => {
+                tracing::warn!("skip declared tool {}: {:#}", path.display(), err);
+                None
+            }
+        })
+        .collect()
+}
+
+fn load(path: &Path) -> Result<Spec> {
+    let spec: Spec = toml::from_str(&std::fs::read_to_string(path)?)?;
+    check(&spec)?;
+    Ok(spec)
+}
+
+/// Write `spec` to `dir`, so it is loaded again on the next start
+pub fn save(dir: &Path, spec: &Spec) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}.toml", spec.name));
+    if path.exists() {
+        bail!("{} already exists", path.display());
+    }
+    std::fs::write(&path, toml::to_string(spec)?)?;
+    Ok(path)
+}
+
+pub fn check(spec: &Spec) -> Result<()> {
+    if spec.name.is_empty()
+        || !spec
+            .name
+            .chars()
+            .all(|x| x.is_ascii_alphanumeric() || x == '_' || x == '-')
+    {
+        bail!("name can only contain ascii letters, digits, `_` and `-`");
+    }
+    reqwest::Method::from_bytes(spec.request.method.as_bytes()).context("Invalid method")?;
+    // fail on start rather than on the first call
+    let env = environment(BTreeMap::new());
+    env.template_from_str(&spec.request.url)?;
+    for value in spec.request.headers.values() {
+        env.template_from_str(value)?;
+    }
+    if let Some(body) = &spec.request.body {
+        env.template_from_str(body)?;
+    }
+    Ok(())
+}
+
+/// `env(name)` gives the credential of the workspace, otherwise the env
+fn environment(credentials: BTreeMap<String, String>) -> Environment<'static> {
+    let mut env = Environment::new();
+    env.add_function("env", move |name: String| {
+        credentials
+            .get(&name)
+            .cloned()
+            .unwrap_or_else(|| dotenv::var(name).unwrap_or_default())
+    });
+    env
+}
+
+/// Stateless, every chat share the spec
+#[derive(Clone)]
+pub struct HttpTool {
+    spec: Arc<Spec>,
+    http_client: reqwest::Client,
+}
+
+impl HttpTool {
+    pub fn new(spec: Spec, http_client: reqwest::Client) -> Self {
+        Self {
+            spec: Arc::new(spec),
+            http_client,
+        }
+    }
+
+    async fn request(&self, input: &str, ctx: &ToolCtx) -> Result<Value> {
+        let args: Value = serde_json::from_str(input)?;
+        let args = context! {
+            vars => ctx.vars().await?,
+            ..minijinja::Value::from_serialize(&args)
+        };
+        let credentials = ctx.app.tools.credentials(ctx.workspace_id().await?).await?;
+        let env = environment(credentials);
+        let render = |template: &str| env.render_str(template, &args);
+
+        let request = &self.spec.request;
+        let mut builder = self.http_client.request(
+            reqwest::Method::from_bytes(request.method.as_bytes())?,
+            render(&request.url)?,
+        );
+        for (name, value) in &request.headers {
+            builder = builder.header(name, render(value)?);
+        }
+        if let Some(body) = &request.body {
+            builder = builder.body(render(body)?);
+        }
+
+        let resp = builder.send().await?;
+        let status = resp.status();
+        let bytes = resp.bytes().await?;
+        let text = String::from_utf8_lossy(&bytes[..bytes.len().min(DECLARED_TOOL_MAX_BYTES)]);
+        if !status.is_success() {
+            bail!("HTTP {}: {}", status, text);
+        }
+        // a cut JSON is no longer JSON, give it as text
+        Ok(serde_json::from_str(&text).unwrap_or(Value::String(text.into_owned())))
+    }
+}
+
+impl UntypedTool for HttpTool {
+    fn call<'a>(&'a mut self, input: &'a str, ctx: &'a ToolCtx) -> BoxFuture<'a, Result<Value>> {
+        self.request(input, ctx).boxed()
+    }
+
+    fn se(&self) -> Result<String> {
+        Ok("null".to_owned())
+    }
+}
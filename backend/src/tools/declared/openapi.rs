@@ -0,0 +1,318 @@
+//! Generate declared tools from the operations of an OpenAPI 3 document
+//!
+//! Only JSON documents are supported. Parameters in path, query and header
+//! become arguments, a JSON request body become the `body` argument.
+//! Credentials are read from an environment variable on each call, they never
+//! reach the generated spec
+
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::{Context, Result, bail};
+use serde_json::{Map, Value, json};
+use url::Url;
+
+use super::{Request, Spec};
+use crate::config::DECLARED_TOOL_TIMEOUT;
+
+const METHODS: [&str; 5] = ["get", "post", "put", "patch", "delete"];
+/// `$ref` followed in a row, recursive schemas are cut there
+const MAX_REF_DEPTH: usize = 3;
+/// Function names longer than 64 are refused by some providers, the rest is
+/// left for the namespace of declared tools
+const MAX_NAME_LEN: usize = 56;
+
+pub struct Document {
+    doc: Value,
+    base: Url,
+}
+
+pub struct Operation {
+    /// Name of the generated tool
+    pub id: String,
+    pub method: String,
+    pub path: String,
+    pub summary: String,
+}
+
+enum Auth {
+    Header(String, &'static str),
+    Query(String),
+}
+
+impl Document {
+    pub async fn fetch(url: &str) -> Result<Self> {
+        let url = Url::parse(url)?;
+        let client = reqwest::Client::builder()
+            .user_agent("llumen")
+            .timeout(Duration::from_secs(DECLARED_TOOL_TIMEOUT))
+            .build()?;
+        let text = client
+            .get(url.clone())
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let doc: Value =
+            serde_json::from_str(&text).context("Only JSON OpenAPI documents are supported")?;
+        if !doc["openapi"].as_str().is_some_and(|x| x.starts_with('3')) {
+            bail!("Not an OpenAPI 3 document");
+        }
+
+        // servers may be relative to the document
+        let base = match doc["servers"][0]["url"].as_str() {
+            Some(server) => url.join(server)?,
+            None => url.join("/")?,
+        };
+        Ok(Self { doc, base })
+    }
+
+    pub fn title(&self) -> String {
+        self.doc["info"]["title"]
+            .as_str()
+            .unwrap_or_default()
+            .to_owned()
+    }
+
+    pub fn operations(&self) -> Vec<Operation> {
+        let Some(paths) = self.doc["paths"].as_object() else {
+            return vec![];
+        };
+        let mut operations = vec![];
+        for (path, item) in paths {
+            for method in METHODS {
+                let Some(op) = item.get(method) else {
+                    continue;
+                };
+                let id = match op["operationId"].as_str() {
+                    Some(id) => sanitize(id),
+                    None => sanitize(&format!("{}{}", method, path)),
+                };
+                let method = method.to_uppercase();
+                // models pick tools by their description, never leave it empty
+                let summary = op["summary"]
+                    .as_str()
+                    .or(op["description"].as_str())
+                    .map(str::to_owned)
+                    .unwrap_or_else(|| format!("{} {}", method, path));
+                operations.push(Operation {
+                    id,
+                    method,
+                    path: path.clone(),
+                    summary,
+                });
+            }
+        }
+        operations
+    }
+
+    /// Name of the environment variable holding the credential is `credential`
+    pub fn spec(&self, id: &str, credential: Option<&str>) -> Result<Spec> {
+        let operation = self
+            .operations()
+            .into_iter()
+            .find(|x| x.id == id)
+            .with_context(|| format!("No operation `{}`", id))?;
+        let item = &self.doc["paths"][&operation.path];
+        let op = &item[operation.method.to_lowercase()];
+
+        let mut properties = Map::new();
+        let mut required = vec![];
+        let mut query = String::new();
+        let mut headers = HashMap::new();
+        let mut url = format!(
+            "{}{}",
+            self.base.as_str().trim_end_matches('/'),
+            operation.path
+        );
+
+        let params = [&item["parameters"], &op["parameters"]];
+        for param in params.into_iter().filter_map(|x| x.as_array()).flatten() {
+            let param = self.resolve(param, MAX_REF_DEPTH);
+            let (Some(name), Some(location)) = (param["name"].as_str(), param["in"].as_str())
+            else {
+                continue;
+            };
+            // template variables must be identifiers
+            let var = identifier(name);
+            match location {
+                "path" => {
+                    url = url.replace(
+                        &format!("{{{}}}", name),
+                        &format!("{{{{ {} | urlencode }}}}", var),
+                    );
+                }
+                "query" => {
+                    query.push_str(&format!(
+                        "{{% if {var} is defined %}}{}={{{{ {var} | urlencode }}}}&{{% endif %}}",
+                        urlencoding(name),
+                    ));
+                }
+                "header" => {
+                    headers.insert(name.to_owned(), format!("{{{{ {} }}}}", var));
+                }
+                _ => continue,
+            }
+
+            let mut schema = param.get("schema").cloned().unwrap_or(json!({}));
+            if let (Some(schema), Some(description)) =
+                (schema.as_object_mut(), param["description"].as_str())
+            {
+                schema.insert("description".to_owned(), description.into());
+            }
+            if location == "path" || param["required"].as_bool() == Some(true) {
+                required.push(Value::String(var.clone()));
+            }
+            properties.insert(var, schema);
+        }
+
+        let mut body = None;
+        let json_body = &op["requestBody"];
+        let json_body = self.resolve(json_body, MAX_REF_DEPTH);
+        if let Some(schema) = json_body["content"]["application/json"].get("schema") {
+            properties.insert("body".to_owned(), schema.clone());
+            if json_body["required"].as_bool() == Some(true) {
+                required.push("body".into());
+            }
+            headers.insert("Content-Type".to_owned(), "application/json".to_owned());
+            body = Some("{{ body | tojson }}".to_owned());
+        }
+
+        if let Some(credential) = credential {
+            if !credential
+                .chars()
+                .all(|x| x.is_ascii_alphanumeric() || x == '_')
+            {
+                bail!("Credential must be the name of an environment variable");
+            }
+            let value = format!("{{{{ env('{}') }}}}", credential);
+            match self.auth(op) {
+                Some(Auth::Header(name, prefix)) => {
+                    headers.insert(name, format!("{}{}", prefix, value));
+                }
+                Some(Auth::Query(name)) => {
+                    query.push_str(&format!("{}={}&", urlencoding(&name), value));
+                }
+                None => bail!("`{}` has no supported security scheme", id),
+            }
+        }
+
+        if !query.is_empty() {
+            url.push('?');
+            url.push_str(&query);
+        }
+
+        Ok(Spec {
+            name: operation.id,
+            description: operation.summary,
+            prompt: String::new(),
+            schema: json!({
+                "type": "object",
+                "properties": properties,
+                "required": required,
+            }),
+            request: Request {
+                method: operation.method,
+                url,
+                headers,
+                body,
+            },
+        })
+    }
+
+    /// First scheme required by the operation (or the document) that can be filled
+    fn auth(&self, op: &Value) -> Option<Auth> {
+        let security = op
+            .get("security")
+            .or(self.doc.get("security"))?
+            .as_array()?;
+        let schemes = &self.doc["components"]["securitySchemes"];
+        security
+            .iter()
+            .filter_map(|x| x.as_object())
+            .flat_map(|x| x.keys())
+            .find_map(|name| {
+                let scheme = &schemes[name];
+                match (scheme["type"].as_str()?, scheme["in"].as_str()) {
+                    ("apiKey", Some("header")) => {
+                        Some(Auth::Header(scheme["name"].as_str()?.to_owned(), ""))
+                    }
+                    ("apiKey", Some("query")) => {
+                        Some(Auth::Query(scheme["name"].as_str()?.to_owned()))
+                    }
+                    ("http", _) => {
+                        let prefix = match scheme["scheme"].as_str()?.to_lowercase().as_str() {
+                            "bearer" => "Bearer ",
+                            // the variable hold the base64 of `user:password`
+                            "basic" => "Basic ",
+                            _ => return None,
+                        };
+                        Some(Auth::Header("Authorization".to_owned(), prefix))
+                    }
+                    _ => None,
+                }
+            })
+    }
+
+    /// Inline local `$ref`, models cannot follow them, and drop nulls which
+    /// TOML cannot hold
+    fn resolve(&self, value: &Value, depth: usize) -> Value {
+        match value {
+            Value::Object(map) => {
+                if let Some(pointer) = map.get("$ref").and_then(|x| x.as_str()) {
+                    return match (depth, pointer.strip_prefix('#')) {
+                        (1.., Some(pointer)) => self
+                            .doc
+                            .pointer(pointer)
+                            .map(|x| self.resolve(x, depth - 1))
+                            .unwrap_or(json!({})),
+                        _ => json!({}),
+                    };
+                }
+                map.iter()
+                    .filter(|(_, v)| !v.is_null())
+                    .map(|(k, v)| (k.clone(), self.resolve(v, depth)))
+                    .collect()
+            }
+            Value::Array(list) => list
+                .iter()
+                .filter(|x| !x.is_null())
+                .map(|x| self.resolve(x, depth))
+                .collect(),
+            x => x.clone(),
+        }
+    }
+}
+
+/// Keep the characters allowed in tool names
+fn sanitize(raw: &str) -> String {
+    let mut name = String::new();
+    for x in name_chars(raw) {
+        if !(x == '_' && name.ends_with('_')) {
+            name.push(x);
+        }
+    }
+    let mut name = name.trim_matches('_').to_owned();
+    name.truncate(MAX_NAME_LEN);
+    name
+}
+
+fn name_chars(raw: &str) -> impl Iterator<Item = char> {
+    raw.chars()
+        .map(|x| match x.is_ascii_alphanumeric() || x == '-' {
+            true => x,
+            false => '_',
+        })
+}
+
+fn identifier(name: &str) -> String {
+    let name = sanitize(name).replace('-', "_");
+    match name.starts_with(|x: char| x.is_ascii_digit()) {
+        true => format!("_{}", name),
+        false => name,
+    }
+}
+
+fn urlencoding(name: &str) -> String {
+    url::form_urlencoded::byte_serialize(name.as_bytes()).collect()
+}
@@ -0,0 +1,246 @@
+//! Travel time and route between two places, so a place found by
+//! `nearbyplace` can be weighed by how long it takes to get there
+//!
+//! Routes come from an OSRM-compatible server at `DIRECTIONS_API`, the public
+//! demo of OSRM by default, which only routes cars. A place given by name or
+//! address is first looked up on a Nominatim-compatible server at
+//! `GEOCODER_API`
+
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use dotenv::var;
+use reqwest::{Client, Url};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{DIRECTIONS_MAX_STEPS, DIRECTIONS_TIMEOUT},
+    tools::{Tool, ToolCtx},
+};
+
+const DEFAULT_DIRECTIONS_API: &str = "https://router.project-osrm.org";
+const DEFAULT_GEOCODER_API: &str = "https://nominatim.openstreetmap.org";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Directions;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DirectionsInput {
+    /// where to start, `latitude,longitude` such as the `location` of a
+    /// `nearbyplace` result, or an address
+    from: String,
+    /// where to go, in the same form as `from`
+    to: String,
+    /// `driving` by default
+    mode: Option<TravelMode>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TravelMode {
+    #[default]
+    Driving,
+    Walking,
+    Cycling,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DirectionsOutput {
+    from: String,
+    to: String,
+    mode: TravelMode,
+    duration_minutes: u32,
+    distance_km: f64,
+    /// main roads of the route
+    summary: String,
+    steps: Vec<String>,
+}
+
+/// A place resolved to coordinates
+struct Point {
+    name: String,
+    lat: f64,
+    lng: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeocodeResp {
+    lat: String,
+    lon: String,
+    display_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RouteResp {
+    code: String,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    routes: Vec<Route>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Route {
+    /// seconds
+    duration: f64,
+    /// meters
+    distance: f64,
+    legs: Vec<Leg>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Leg {
+    #[serde(default)]
+    summary: String,
+    #[serde(default)]
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Step {
+    #[serde(default)]
+    name: String,
+    distance: f64,
+    maneuver: Maneuver,
+}
+
+#[derive(Debug, Deserialize)]
+struct Maneuver {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    modifier: Option<String>,
+}
+
+impl TravelMode {
+    /// Profile of the OSRM route service
+    fn profile(self) -> &'static str {
+        match self {
+            TravelMode::Driving => "driving",
+            TravelMode::Walking => "walking",
+            TravelMode::Cycling => "cycling",
+        }
+    }
+}
+
+fn base(env: &str, default: &str) -> Result<Url> {
+    let base = var(env).unwrap_or_else(|_| default.to_owned());
+    Ok(Url::parse(&format!("{}/", base.trim_end_matches('/')))?)
+}
+
+/// `latitude,longitude` as is, anything else through the geocoder
+async fn locate(client: &Client, place: &str) -> Result<Point> {
+    let place = place.trim();
+    if let Some((lat, lng)) = place.split_once(',')
+        && let (Ok(lat), Ok(lng)) = (lat.trim().parse::<f64>(), lng.trim().parse::<f64>())
+        && (-90.0..=90.0).contains(&lat)
+        && (-180.0..=180.0).contains(&lng)
+    {
+        return Ok(Point {
+            name: place.to_owned(),
+            lat,
+            lng,
+        });
+    }
+    let mut url = base("GEOCODER_API", DEFAULT_GEOCODER_API)?.join("search")?;
+    url.query_pairs_mut()
+        .append_pair("q", place)
+        .append_pair("format", "jsonv2")
+        .append_pair("limit", "1");
+    let found: Vec<GeocodeResp> = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let found = found
+        .into_iter()
+        .next()
+        .with_context(|| format!("cannot find `{}`, try an address or coordinates", place))?;
+    Ok(Point {
+        name: found.display_name,
+        lat: found.lat.parse()?,
+        lng: found.lon.parse()?,
+    })
+}
+
+fn describe(step: &Step) -> String {
+    let mut text = step.maneuver.kind.clone();
+    if let Some(modifier) = &step.maneuver.modifier {
+        text.push(' ');
+        text.push_str(modifier);
+    }
+    if !step.name.is_empty() {
+        text.push_str(" onto ");
+        text.push_str(&step.name);
+    }
+    format!("{} ({} m)", text, step.distance.round())
+}
+
+impl Tool for Directions {
+    type Input = DirectionsInput;
+    type Output = DirectionsOutput;
+
+    const NAME: &str = "directions";
+    const DESCRIPTION: &str = "get the travel time, distance and route between two places, given as `latitude,longitude` or an address";
+    const PROMPT: &str = "use `directions` when the user asks how long it takes to get somewhere or how to get there, e.g. to compare the places found by `nearbyplace`";
+
+    async fn call(&mut self, input: Self::Input, _ctx: &ToolCtx) -> anyhow::Result<Self::Output> {
+        let client = Client::builder()
+            .user_agent("llumen")
+            .timeout(Duration::from_secs(DIRECTIONS_TIMEOUT))
+            .build()?;
+        let mode = input.mode.unwrap_or_default();
+        let from = locate(&client, &input.from).await?;
+        let to = locate(&client, &input.to).await?;
+
+        let mut url = base("DIRECTIONS_API", DEFAULT_DIRECTIONS_API)?.join(&format!(
+            "route/v1/{}/{},{};{},{}",
+            mode.profile(),
+            from.lng,
+            from.lat,
+            to.lng,
+            to.lat
+        ))?;
+        url.query_pairs_mut()
+            .append_pair("overview", "false")
+            .append_pair("steps", "true");
+        let resp: RouteResp = client.get(url).send().await?.json().await?;
+        let route = match resp.code.as_str() {
+            "Ok" => resp.routes.into_iter().next(),
+            _ => None,
+        }
+        .with_context(|| {
+            format!(
+                "no route found: {}",
+                resp.message.unwrap_or(resp.code.clone())
+            )
+        })?;
+
+        let summary = route
+            .legs
+            .iter()
+            .map(|x| x.summary.as_str())
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let steps = route
+            .legs
+            .iter()
+            .flat_map(|x| &x.steps)
+            .take(DIRECTIONS_MAX_STEPS)
+            .map(describe)
+            .collect();
+        Ok(DirectionsOutput {
+            from: from.name,
+            to: to.name,
+            mode,
+            duration_minutes: (route.duration / 60.0).ceil() as u32,
+            distance_km: (route.distance / 100.0).round() / 10.0,
+            summary,
+            steps,
+        })
+    }
+}
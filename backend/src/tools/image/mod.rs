@@ -0,0 +1,79 @@
+use anyhow::{Context, bail};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    files::{self, HEAD_BYTES, sniff},
+    tools::{Tool, ToolCtx},
+};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct GenerateImage;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GenerateImageInput {
+    /// a detailed description of the image in English: subject, style,
+    /// composition, colors
+    prompt: String,
+    #[serde(default)]
+    shape: GenerateImageShape,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GenerateImageShape {
+    #[default]
+    Square,
+    Portrait,
+    Landscape,
+}
+
+impl GenerateImageShape {
+    fn size(&self) -> &'static str {
+        match self {
+            GenerateImageShape::Square => "1024x1024",
+            GenerateImageShape::Portrait => "1024x1536",
+            GenerateImageShape::Landscape => "1536x1024",
+        }
+    }
+}
+
+impl Tool for GenerateImage {
+    type Input = GenerateImageInput;
+    type Output = String;
+
+    const NAME: &str = "generateimage";
+    const DESCRIPTION: &str =
+        "generate an image from a description, it is shown to the user in the chat";
+    const PROMPT: &str = "use `generateimage` when the user asks you to draw, paint or create a picture; do not describe the image again or link it, the user already sees it";
+
+    async fn call(&mut self, input: Self::Input, ctx: &ToolCtx) -> anyhow::Result<Self::Output> {
+        let image = ctx
+            .app
+            .image
+            .as_ref()
+            .context("image generation is not configured on this instance")?;
+        if input.prompt.trim().is_empty() {
+            bail!("the prompt is empty");
+        }
+        let data = image
+            .generate(input.prompt.trim(), input.shape.size())
+            .await?;
+        let extension = match sniff(&data[..data.len().min(HEAD_BYTES)]) {
+            "image/png" => "png",
+            "image/jpeg" => "jpg",
+            "image/webp" => "webp",
+            _ => bail!("the provider returned something else than an image"),
+        };
+        let file = files::create(
+            &ctx.app.conn,
+            &ctx.app.files,
+            ctx.user_id,
+            format!("image.{}", extension),
+            data,
+        )
+        .await?;
+        ctx.attach(file);
+        Ok("the image is generated and shown to the user".to_owned())
+    }
+}
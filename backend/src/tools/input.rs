@@ -0,0 +1,61 @@
+//! Questions asked to the user in the middle of a tool call
+
+use std::{collections::HashMap, fmt, sync::Mutex};
+
+use serde_json::Value;
+use tokio::sync::oneshot;
+
+/// Error of a tool call that cannot go on without the user, e.g. which of two
+/// addresses a mail should go to
+///
+/// The call is made again with the same arguments once the user answer, the
+/// answer is in [`super::ToolCtx::answer`]
+#[derive(Debug)]
+pub struct NeedsInput {
+    pub question: String,
+    /// JSON schema of the answer
+    pub schema: Value,
+}
+
+impl fmt::Display for NeedsInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Need an answer from the user: {}", self.question)
+    }
+}
+
+impl std::error::Error for NeedsInput {}
+
+/// Tool calls waiting for the user, by chat and tool call id
+#[derive(Debug, Default)]
+pub struct PendingInputs {
+    map: Mutex<HashMap<(i32, String), oneshot::Sender<Value>>>,
+}
+
+impl PendingInputs {
+    /// Replace an earlier question of the same call
+    pub fn wait(&self, chat_id: i32, call_id: &str) -> oneshot::Receiver<Value> {
+        let (tx, rx) = oneshot::channel();
+        self.map
+            .lock()
+            .unwrap()
+            .insert((chat_id, call_id.to_owned()), tx);
+        rx
+    }
+
+    /// false if the call is not waiting, e.g. it was halted
+    pub fn answer(&self, chat_id: i32, call_id: &str, answer: Value) -> bool {
+        let tx = self
+            .map
+            .lock()
+            .unwrap()
+            .remove(&(chat_id, call_id.to_owned()));
+        tx.is_some_and(|tx| tx.send(answer).is_ok())
+    }
+
+    pub fn cancel(&self, chat_id: i32, call_id: &str) {
+        self.map
+            .lock()
+            .unwrap()
+            .remove(&(chat_id, call_id.to_owned()));
+    }
+}
@@ -1,12 +1,12 @@
 use base64::{Engine as _, engine::general_purpose};
 use reqwest::header::{ACCEPT, AUTHORIZATION};
-use serde_json::Value;
+use serde_json::{Value, json};
 
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize, de};
+use serde::{Deserialize, Serialize};
 
-use crate::tools::Tool;
-use dotenv::var;
+use crate::tools::{NeedsInput, Tool, ToolCtx, contacts};
+use entity::LinkKind;
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct RecentMail;
@@ -22,8 +22,8 @@ pub struct GetMailContent;
 
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct ReplyMailInput {
-    thread_id: String,
-    recipient_email: String,
+    thread_id: Option<String>, // default to the thread of the last mail read
+    recipient_email: Option<String>, // default to the sender of the last mail read
     subject: String,
     body: String,
 }
@@ -32,6 +32,7 @@ pub struct ReplyMailInput {
 pub struct RecentMailInput {
     max_results: Option<u32>, // max number of results to return, default to 10, max 20
     q: Option<String>,        // query string to filter results, same as Gmail search box
+    mailbox: Option<String>,  // label to look in, remembered for later calls, default to inbox
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -46,6 +47,47 @@ pub struct GetMailContentInput {
     mail_id: String,
 }
 
+/// `given`, or the chat variable `key` set by an earlier call
+async fn remembered(ctx: &ToolCtx, given: Option<String>, key: &str) -> anyhow::Result<String> {
+    if let Some(given) = given {
+        return Ok(given);
+    }
+    ctx.var(key)
+        .await?
+        .and_then(|x| x.as_str().map(str::to_owned))
+        .ok_or_else(|| anyhow::anyhow!("{} is required, no mail was read in this chat", key))
+}
+
+/// Address of a recipient given by address or by name, see `contacts`
+///
+/// A name matching one contact is mailed at their address, otherwise the user
+/// is asked: one of two Alices, or someone not in the address book
+async fn recipient(ctx: &ToolCtx, to: String) -> anyhow::Result<String> {
+    if let Some(Value::String(answer)) = ctx.answer() {
+        return Ok(answer);
+    }
+    if to.contains('@') {
+        return Ok(to);
+    }
+    let found = contacts::resolve(&ctx.app.conn, ctx.user_id, &to).await?;
+    if let [contact] = found.as_slice() {
+        return Ok(contact.email.clone());
+    }
+    let schema = match found.is_empty() {
+        true => json!({ "type": "string", "format": "email" }),
+        false => json!({
+            "type": "string",
+            "format": "email",
+            "examples": found.iter().map(|x| &x.email).collect::<Vec<_>>(),
+        }),
+    };
+    Err(NeedsInput {
+        question: format!("Which address should the mail to {} go to?", to),
+        schema,
+    }
+    .into())
+}
+
 /// Refreshes a Google OAuth 2.0 access token.
 /// Returns Ok(access_token) if successful, or Err(error_message) otherwise.
 async fn refresh_google_access_token(
@@ -98,6 +140,7 @@ async fn fetch_latest_gmail_messages_as_string(
     access_token: &str,
     max_results: i32,
     q: &str,
+    ctx: &ToolCtx,
 ) -> anyhow::Result<String> {
     let api_list_url = "https://gmail.googleapis.com/gmail/v1/users/me/messages";
     let client = reqwest::Client::new();
@@ -181,22 +224,19 @@ async fn fetch_latest_gmail_messages_as_string(
                 {
                     let mut found = None;
                     for part in parts {
-                        if part["mimeType"] == "text/plain" {
-                            if let Some(data) = part["body"].get("data").and_then(|d| d.as_str()) {
-                                found = Some(data);
-                                break;
-                            }
+                        if part["mimeType"] == "text/plain"
+                            && let Some(data) = part["body"].get("data").and_then(|d| d.as_str())
+                        {
+                            found = Some(data);
+                            break;
                         }
                     }
                     found
-                } else if let Some(data) = payload
-                    .get("body")
-                    .and_then(|b| b.get("data"))
-                    .and_then(|d| d.as_str())
-                {
-                    Some(data)
                 } else {
-                    None
+                    payload
+                        .get("body")
+                        .and_then(|b| b.get("data"))
+                        .and_then(|d| d.as_str())
                 };
 
                 let body_text = if let Some(data) = body_content {
@@ -211,6 +251,13 @@ async fn fetch_latest_gmail_messages_as_string(
                     "Unable to parse the content.".to_string()
                 };
 
+                ctx.link(
+                    LinkKind::Mail,
+                    message_id,
+                    format!("{} ({})", subject, sender),
+                );
+                ctx.link(LinkKind::MailThread, thread_id, subject);
+
                 result.push_str(&format!("----- Mail {} -----\n", i + 1));
                 result.push_str(&format!(
                     "mail_id: {}, thread_id: {}\n",
@@ -240,15 +287,16 @@ impl Tool for RecentMail {
     mail_id can be used to fetch the full content of the mail using other tools.
     thread_id can be used to reply to the mail using other tools.
     max_results is optional, if not provided, it will default to 10.
-    q is optional, if not provided, it will be label:inbox. You can use the same query syntax as in Gmail search box.
+    q is optional. You can use the same query syntax as in Gmail search box.
+    mailbox is optional, the label to look in. It is remembered, later calls without it look in the same label, inbox at first.
     You can ask the user which mail they want to know about, and use getmailcontent or replymail tool to get the content or reply to the mail.
     ";
     const PROMPT: &str = "use `recentmail` to get recent mail";
 
-    async fn call(&mut self, input: Self::Input) -> anyhow::Result<Self::Output> {
-        let client_id = var("CLIENT_ID").unwrap_or("".to_owned());
-        let client_secret = var("CLIENT_SECRET").unwrap_or("".to_owned());
-        let refresh_token = var("REFRESH_TOKEN").unwrap_or("".to_owned());
+    async fn call(&mut self, input: Self::Input, ctx: &ToolCtx) -> anyhow::Result<Self::Output> {
+        let client_id = ctx.credential("CLIENT_ID").await?.unwrap_or_default();
+        let client_secret = ctx.credential("CLIENT_SECRET").await?.unwrap_or_default();
+        let refresh_token = ctx.credential("REFRESH_TOKEN").await?.unwrap_or_default();
         tracing::debug!(
             "client_id: {}, client_secret: {}, refresh_token: {}",
             client_id,
@@ -259,8 +307,20 @@ impl Tool for RecentMail {
             refresh_google_access_token(&client_id, &client_secret, &refresh_token).await?;
         tracing::debug!("access_token: {}", access_token);
         let max_results = std::cmp::min(input.max_results.unwrap_or(10), 20) as i32;
-        let q = input.q.unwrap_or("label:inbox".to_owned());
-        let result = fetch_latest_gmail_messages_as_string(&access_token, max_results, &q).await?;
+        let mailbox = match input.mailbox {
+            Some(mailbox) => {
+                ctx.set_var("mailbox", &mailbox).await?;
+                mailbox
+            }
+            None => ctx
+                .var("mailbox")
+                .await?
+                .and_then(|x| x.as_str().map(str::to_owned))
+                .unwrap_or("inbox".to_owned()),
+        };
+        let q = format!("label:{} {}", mailbox, input.q.unwrap_or_default());
+        let result =
+            fetch_latest_gmail_messages_as_string(&access_token, max_results, &q, ctx).await?;
         Ok(result)
     }
 }
@@ -272,16 +332,20 @@ impl Tool for ReplyMail {
     const NAME: &str = "replymail";
     const DESCRIPTION: &str = "reply to a mail using the mail_id obtained from recentmail tool.
     thread_id is the thread_id of the mail to reply to. this id should be first obtained from recentmail tool.
-    recipient_email is the email address of the recipient.
+    recipient_email is the email address of the recipient, or their name in the address book of the user.
+    both default to the last mail read with getmailcontent, omit them when replying to it.
     subject is the subject of the reply mail.
     body is the content of the reply mail.
     ";
     const PROMPT: &str = "use `replymail` to reply a mail";
 
-    async fn call(&mut self, input: Self::Input) -> anyhow::Result<Self::Output> {
-        let client_id = var("CLIENT_ID").unwrap_or("".to_owned());
-        let client_secret = var("CLIENT_SECRET").unwrap_or("".to_owned());
-        let refresh_token = var("REFRESH_TOKEN").unwrap_or("".to_owned());
+    async fn call(&mut self, input: Self::Input, ctx: &ToolCtx) -> anyhow::Result<Self::Output> {
+        let thread_id = remembered(ctx, input.thread_id, "thread_id").await?;
+        let recipient_email = remembered(ctx, input.recipient_email, "reply_to").await?;
+        let recipient_email = recipient(ctx, recipient_email).await?;
+        let client_id = ctx.credential("CLIENT_ID").await?.unwrap_or_default();
+        let client_secret = ctx.credential("CLIENT_SECRET").await?.unwrap_or_default();
+        let refresh_token = ctx.credential("REFRESH_TOKEN").await?.unwrap_or_default();
         let access_token =
             refresh_google_access_token(&client_id, &client_secret, &refresh_token).await?;
         let api_send_url = "https://gmail.googleapis.com/gmail/v1/users/me/messages/send";
@@ -295,13 +359,13 @@ impl Tool for ReplyMail {
 
         let email_content = format!(
             "Subject: {}\r\nContent-Type: text/plain; charset=\"UTF-8\"\r\nTo: {}\r\nIn-Reply-To: {}\r\nReferences: {}\r\n\r\n{}",
-            subject_encoded, input.recipient_email, input.thread_id, input.thread_id, input.body
+            subject_encoded, recipient_email, thread_id, thread_id, input.body
         );
         let encoded_email = general_purpose::STANDARD.encode(email_content);
 
         let body = serde_json::json!({
             "raw": encoded_email,
-            "threadId": input.thread_id,
+            "threadId": thread_id,
         });
 
         let response = client
@@ -335,16 +399,21 @@ impl Tool for SendMail {
 
     const NAME: &str = "sendmail";
     const DESCRIPTION: &str = "send a mail to a recipient.
-    to is the recipient's email address.
+    to is the recipient's email address, or their name in the address book of the user.
     subject is the subject of the mail.
     body is the content of the mail.
     ";
     const PROMPT: &str = "use `sendmail` to send a mail";
 
-    async fn call(&mut self, input: Self::Input) -> anyhow::Result<Self::Output> {
-        let client_id = var("CLIENT_ID").unwrap_or("".to_owned());
-        let client_secret = var("CLIENT_SECRET").unwrap_or("".to_owned());
-        let refresh_token = var("REFRESH_TOKEN").unwrap_or("".to_owned());
+    async fn call(
+        &mut self,
+        mut input: Self::Input,
+        ctx: &ToolCtx,
+    ) -> anyhow::Result<Self::Output> {
+        input.to = recipient(ctx, input.to).await?;
+        let client_id = ctx.credential("CLIENT_ID").await?.unwrap_or_default();
+        let client_secret = ctx.credential("CLIENT_SECRET").await?.unwrap_or_default();
+        let refresh_token = ctx.credential("REFRESH_TOKEN").await?.unwrap_or_default();
         let access_token =
             refresh_google_access_token(&client_id, &client_secret, &refresh_token).await?;
         let api_send_url = "https://gmail.googleapis.com/gmail/v1/users/me/messages/send";
@@ -402,10 +471,10 @@ impl Tool for GetMailContent {
     ";
     const PROMPT: &str = "use `getmailcontent` to get the full content of a mail";
 
-    async fn call(&mut self, input: Self::Input) -> anyhow::Result<Self::Output> {
-        let client_id = var("CLIENT_ID").unwrap_or("".to_owned());
-        let client_secret = var("CLIENT_SECRET").unwrap_or("".to_owned());
-        let refresh_token = var("REFRESH_TOKEN").unwrap_or("".to_owned());
+    async fn call(&mut self, input: Self::Input, ctx: &ToolCtx) -> anyhow::Result<Self::Output> {
+        let client_id = ctx.credential("CLIENT_ID").await?.unwrap_or_default();
+        let client_secret = ctx.credential("CLIENT_SECRET").await?.unwrap_or_default();
+        let refresh_token = ctx.credential("REFRESH_TOKEN").await?.unwrap_or_default();
         let access_token =
             refresh_google_access_token(&client_id, &client_secret, &refresh_token).await?;
         let api_get_url = format!(
@@ -452,26 +521,34 @@ impl Tool for GetMailContent {
             .find(|h| h["name"] == "Date")
             .and_then(|h| h["value"].as_str())
             .unwrap_or("Unknown Date");
+        ctx.link(
+            LinkKind::Mail,
+            &input.mail_id,
+            format!("{} ({})", subject, sender),
+        );
+        if let Some(thread_id) = message_full["threadId"].as_str() {
+            ctx.link(LinkKind::MailThread, thread_id, subject);
+            ctx.set_var("thread_id", thread_id).await?;
+        }
+        ctx.set_var("mail_id", &input.mail_id).await?;
+        ctx.set_var("reply_to", sender).await?;
         // Get body
         let body_content = if let Some(parts) = payload.get("parts").and_then(|p| p.as_array()) {
             let mut found = None;
             for part in parts {
-                if part["mimeType"] == "text/plain" {
-                    if let Some(data) = part["body"].get("data").and_then(|d| d.as_str()) {
-                        found = Some(data);
-                        break;
-                    }
+                if part["mimeType"] == "text/plain"
+                    && let Some(data) = part["body"].get("data").and_then(|d| d.as_str())
+                {
+                    found = Some(data);
+                    break;
                 }
             }
             found
-        } else if let Some(data) = payload
-            .get("body")
-            .and_then(|b| b.get("data"))
-            .and_then(|d| d.as_str())
-        {
-            Some(data)
         } else {
-            None
+            payload
+                .get("body")
+                .and_then(|b| b.get("data"))
+                .and_then(|d| d.as_str())
         };
         let body_text = if let Some(data) = body_content {
             match general_purpose::URL_SAFE.decode(data) {
@@ -0,0 +1,69 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::MEMORY_RECALL_LIMIT,
+    memory::{self, Saved},
+    tools::{Tool, ToolCtx},
+};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RememberFact;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RecallFacts;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RememberFactInput {
+    /// the fact as a short self-contained sentence about the user,
+    /// e.g. `The user is vegetarian`
+    fact: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RecallFactsInput {
+    /// words the facts should contain, empty for the latest facts
+    query: String,
+}
+
+impl Tool for RememberFact {
+    type Input = RememberFactInput;
+    type Output = String;
+
+    const NAME: &str = "rememberfact";
+    const DESCRIPTION: &str = "save a lasting fact about the user, such as a preference, their job or a name they use, so it is known in their later chats";
+    const PROMPT: &str = "use `rememberfact` when the user shares a lasting fact about themselves or asks you to remember something";
+
+    async fn call(&mut self, input: Self::Input, ctx: &ToolCtx) -> anyhow::Result<Self::Output> {
+        let saved =
+            memory::save(&ctx.app.conn, ctx.user_id, Some(ctx.chat_id), &input.fact).await?;
+        Ok(match saved {
+            Saved::New(_) => "saved".to_owned(),
+            Saved::Known => "already known".to_owned(),
+            Saved::Full => {
+                "the memory is full, the user has to remove facts in their settings first"
+                    .to_owned()
+            }
+        })
+    }
+}
+
+impl Tool for RecallFacts {
+    type Input = RecallFactsInput;
+    type Output = Vec<String>;
+
+    const NAME: &str = "recallfacts";
+    const DESCRIPTION: &str = "look up facts saved about the user in earlier chats, latest first";
+    const PROMPT: &str = "use `recallfacts` when something the user told you before may matter and it is not in the facts already given";
+
+    async fn call(&mut self, input: Self::Input, ctx: &ToolCtx) -> anyhow::Result<Self::Output> {
+        let facts = memory::search(
+            &ctx.app.conn,
+            ctx.user_id,
+            &input.query,
+            MEMORY_RECALL_LIMIT,
+        )
+        .await?;
+        Ok(facts.into_iter().map(|x| x.content).collect())
+    }
+}
@@ -1,19 +1,46 @@
+mod input;
 mod set;
 mod store;
 mod tool;
+mod validate;
 
+pub use input::*;
 pub use set::*;
 pub use store::*;
 pub use tool::*;
+pub use validate::*;
 
 use crate::tool_set;
 
-pub mod wttr;
-pub mod nearbyplace;
+pub mod agent;
+pub mod contacts;
+pub mod declared;
+pub mod directions;
+pub mod image;
 pub mod mail;
+pub mod memory;
+pub mod nearbyplace;
 pub mod rss;
+pub mod wttr;
 
 pub const NORMAL: ToolSet = tool_set![];
-pub const SEARCH: ToolSet = tool_set![wttr::Wttr];
-pub const AGENT: ToolSet = tool_set![wttr::Wttr, nearbyplace::NearByPlace, mail::RecentMail, mail::ReplyMail, mail::SendMail, mail::GetMailContent, rss::RssSearch];
+pub const SEARCH: ToolSet = tool_set![wttr::Wttr, memory::RememberFact, memory::RecallFacts];
+pub const AGENT: ToolSet = tool_set![
+    wttr::Wttr,
+    nearbyplace::NearByPlace,
+    directions::Directions,
+    mail::RecentMail,
+    mail::ReplyMail,
+    mail::SendMail,
+    mail::GetMailContent,
+    contacts::AddContact,
+    contacts::FindContact,
+    contacts::ListContacts,
+    rss::RssSearch,
+    memory::RememberFact,
+    memory::RecallFacts,
+    image::GenerateImage,
+    agent::Delegate
+]
+.with_declared();
 pub const RESEARCH: ToolSet = tool_set![];
@@ -1,8 +1,9 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::tools::{Tool, ToolCtx};
 use dotenv::var;
-use crate::tools::Tool;
+use entity::LinkKind;
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct NearByPlace;
@@ -24,10 +25,18 @@ impl Tool for NearByPlace {
     radius is in meters, default to 1000 meters, max 50000 meters
     ";
     const PROMPT: &str = "use `nearbyplace` to get nearby place info when user request";
-    
-    async fn call(&mut self, input: Self::Input) -> anyhow::Result<Self::Output> {
+
+    async fn call(&mut self, input: Self::Input, ctx: &ToolCtx) -> anyhow::Result<Self::Output> {
         let url = "https://places.googleapis.com/v1/places:searchNearby";
-        let api_key = var("GOOGLE_MAP_API_KEY").unwrap_or("".to_owned());
+        let workspace_id = ctx.workspace_id().await?;
+        let api_key = ctx
+            .app
+            .tools
+            .credential(workspace_id, "GOOGLE_MAP_API_KEY")
+            .await?
+            .or_else(|| ctx.app.settings.current().google_map_api_key)
+            .or_else(|| var("GOOGLE_MAP_API_KEY").ok())
+            .unwrap_or_default();
         let body = serde_json::json!({
             "includedTypes": [input.keyword],
             "maxResultCount": 5,
@@ -47,13 +56,23 @@ impl Tool for NearByPlace {
             .post(url)
             .header("Content-Type", "application/json")
             .header("X-Goog-Api-Key", api_key)
-            .header("X-Goog-FieldMask", "places.displayName,places.formattedAddress,places.priceLevel,places.rating,places.location")
+            .header("X-Goog-FieldMask", "places.id,places.displayName,places.formattedAddress,places.priceLevel,places.rating,places.location")
             .json(&body)
             .send()
             .await?
             .text()
             .await?;
 
+        if let Ok(json) = serde_json::from_str::<serde_json::Value>(&resp) {
+            for place in json["places"].as_array().into_iter().flatten() {
+                if let (Some(id), Some(name)) =
+                    (place["id"].as_str(), place["displayName"]["text"].as_str())
+                {
+                    ctx.link(LinkKind::Place, id, name);
+                }
+            }
+        }
+
         Ok(resp)
     }
 }
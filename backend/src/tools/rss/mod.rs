@@ -1,10 +1,7 @@
-use reqwest::Url;
-use regex::Regex;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::tools::Tool;
-use tokio::fs;
+use crate::tools::{Tool, ToolCtx};
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct RssSearch;
@@ -19,15 +16,13 @@ impl Tool for RssSearch {
     type Output = String;
 
     const NAME: &str = "rsssearch";
-    const DESCRIPTION: &str = "get rss feed subscribed and filter by keywords, return in xml format";
+    const DESCRIPTION: &str =
+        "get rss feed subscribed and filter by keywords, return in xml format";
     const PROMPT: &str = "use `rsssearch` to get rss feed";
 
-    async fn call(&mut self, input: Self::Input) -> anyhow::Result<Self::Output> {
+    async fn call(&mut self, input: Self::Input, _ctx: &ToolCtx) -> anyhow::Result<Self::Output> {
         let mut xml_list = Vec::new();
-        let dir = concat!(
-            env!("CARGO_MANIFEST_DIR"),
-            "/../rssfeed"
-        );
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../rssfeed");
         let mut entries = tokio::fs::read_dir(dir).await?;
         while let Some(entry) = entries.next_entry().await? {
             let path = entry.path();
@@ -61,23 +56,24 @@ impl Tool for RssSearch {
                     let item_end = item_start + item_end + "</item>".len();
                     let item_block = &xml[item_start..item_end];
                     // Try to extract keywords from the <keyword> tag inside item_block
-                    if let Some(kw_start) = item_block.find("<keyword>") {
-                        if let Some(kw_end) = item_block[kw_start..].find("</keyword>") {
-                            let kw_start = kw_start + "<keyword>".len();
-                            let kw_end = kw_start + kw_end - "<keyword>".len();
-                            let keywords_str = &item_block[kw_start..kw_end];
-                            let xml_keywords: Vec<&str> = keywords_str.split(',').map(|s| s.trim()).collect();
-                            let mut match_count = 0;
-                            for input_kw in &input.keywords {
-                                for xml_kw in &xml_keywords {
-                                    if xml_kw.to_lowercase().contains(&input_kw.to_lowercase()) {
-                                        match_count += 1;
-                                    }
+                    if let Some(kw_start) = item_block.find("<keyword>")
+                        && let Some(kw_end) = item_block[kw_start..].find("</keyword>")
+                    {
+                        let kw_start = kw_start + "<keyword>".len();
+                        let kw_end = kw_start + kw_end - "<keyword>".len();
+                        let keywords_str = &item_block[kw_start..kw_end];
+                        let xml_keywords: Vec<&str> =
+                            keywords_str.split(',').map(|s| s.trim()).collect();
+                        let mut match_count = 0;
+                        for input_kw in &input.keywords {
+                            for xml_kw in &xml_keywords {
+                                if xml_kw.to_lowercase().contains(&input_kw.to_lowercase()) {
+                                    match_count += 1;
                                 }
                             }
-                            if match_count > 0 {
-                                filtered_items_xml.push(item_block.to_string());
-                            }
+                        }
+                        if match_count > 0 {
+                            filtered_items_xml.push(item_block.to_string());
                         }
                     }
                     start = item_end;
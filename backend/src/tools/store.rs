@@ -1,24 +1,74 @@
-use std::{collections::HashMap, marker::PhantomData};
+use std::{
+    collections::{BTreeMap, HashMap},
+    marker::PhantomData,
+    path::Path,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
 
-use anyhow::{Context, Result};
-use entity::tool;
+use anyhow::{Context, Result, bail};
+use entity::{config, prelude::Config, tool};
 use schemars::schema_for;
 use sea_orm::ActiveValue::Set;
 use sea_orm::sea_query::OnConflict;
 use sea_orm::{DbConn, EntityTrait};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use typeshare::typeshare;
 
 use crate::{
+    config::DECLARED_TOOL_TIMEOUT,
+    middlewares::metrics::METRICS,
     openrouter,
-    tools::{Tool, ToolSet, UntypedTool},
+    tools::{Tool, ToolSet, UntypedTool, declared},
+    utils::{secrets::Secrets, workspace},
 };
 
+/// Where a tool comes from, each source can be disabled on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[typeshare]
+#[serde(rename_all = "snake_case")]
+pub enum ToolSource {
+    /// Compiled in, their ids are their bare names
+    Builtin,
+    /// From `TOOLS_DIR` or imported from OpenAPI
+    Declared,
+}
+
+impl ToolSource {
+    /// Namespaced id of a tool called `name`, e.g. `http__exchange_rate`
+    pub fn id(self, name: &str) -> String {
+        match self {
+            ToolSource::Builtin => name.to_owned(),
+            ToolSource::Declared => format!("http__{}", name),
+        }
+    }
+}
+
 pub struct ToolStore {
-    tools: HashMap<&'static str, ToolStoreInner>,
+    /// By id, locked because declared tools can be imported while serving
+    tools: RwLock<HashMap<&'static str, Arc<ToolStoreInner>>>,
+    /// Old or bare names to ids, so prompts and saved chats mentioning them keep working
+    aliases: RwLock<HashMap<&'static str, &'static str>>,
     conn: DbConn,
+    /// Opens the credentials of workspaces
+    secrets: Secrets,
+    /// Kill switch, no tool is listed or grabbed while set
+    disabled: AtomicBool,
+    disabled_sources: RwLock<Vec<ToolSource>>,
+    /// Ids of the tools from `TOOLS_DIR`, joining tool sets that want them
+    declared: RwLock<Vec<&'static str>>,
+    http_client: reqwest::Client,
 }
 
+const DISABLED_KEY: &str = "tools_disabled";
+const DISABLED_SOURCES_KEY: &str = "tools_disabled_sources";
+
 pub struct ToolStoreInner {
+    source: ToolSource,
     constructor: Box<dyn ToolConstructor + Send + Sync>,
     description: &'static str,
     prompt: &'static str,
@@ -27,69 +77,295 @@ pub struct ToolStoreInner {
 
 pub struct ToolBox {
     pub tools: HashMap<&'static str, Box<dyn UntypedTool>>,
+    /// Schemas of the arguments of the tools in the box
+    schemas: HashMap<&'static str, Value>,
+    /// Aliases of the tools in the box
+    aliases: HashMap<&'static str, &'static str>,
     chat_id: i32,
 }
 
 impl ToolStore {
-    pub fn new(conn: DbConn) -> Self {
+    pub fn new(conn: DbConn, secrets: Secrets) -> Self {
         Self {
             tools: Default::default(),
+            aliases: Default::default(),
             conn,
+            secrets,
+            disabled: AtomicBool::new(false),
+            disabled_sources: Default::default(),
+            declared: Default::default(),
+            http_client: reqwest::Client::builder()
+                .user_agent("llumen")
+                .timeout(Duration::from_secs(DECLARED_TOOL_TIMEOUT))
+                .build()
+                .expect("Cannot build http client"),
+        }
+    }
+
+    /// Restore the switches saved by [`ToolStore::set_disabled`] and
+    /// [`ToolStore::set_disabled_sources`]
+    pub async fn load_disabled(&self) -> Result<()> {
+        let disabled = Config::find_by_id(DISABLED_KEY)
+            .one(&self.conn)
+            .await?
+            .is_some_and(|x| x.value == [1]);
+        self.disabled.store(disabled, Ordering::Relaxed);
+
+        if let Some(x) = Config::find_by_id(DISABLED_SOURCES_KEY)
+            .one(&self.conn)
+            .await?
+        {
+            *self.disabled_sources.write().unwrap() = serde_json::from_slice(&x.value)?;
         }
+        Ok(())
+    }
+
+    pub fn disabled(&self) -> bool {
+        self.disabled.load(Ordering::Relaxed)
+    }
+
+    /// A credential the admins set for the workspace, opened
+    pub async fn credential(&self, workspace_id: i32, name: &str) -> Result<Option<String>> {
+        workspace::credential(&self.conn, workspace_id, name)
+            .await?
+            .map(|x| self.secrets.open(&x))
+            .transpose()
+    }
+
+    /// Every credential of the workspace by name, opened
+    pub async fn credentials(&self, workspace_id: i32) -> Result<BTreeMap<String, String>> {
+        workspace::credentials(&self.conn, workspace_id)
+            .await?
+            .into_iter()
+            .map(|(name, value)| Ok((name, self.secrets.open(&value)?)))
+            .collect()
+    }
+
+    /// Tool boxes already grabbed are not affected
+    pub async fn set_disabled(&self, disabled: bool) -> Result<()> {
+        self.save(DISABLED_KEY, vec![disabled as u8]).await?;
+        self.disabled.store(disabled, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn disabled_sources(&self) -> Vec<ToolSource> {
+        self.disabled_sources.read().unwrap().clone()
+    }
+
+    /// Tool boxes already grabbed are not affected
+    pub async fn set_disabled_sources(&self, sources: Vec<ToolSource>) -> Result<()> {
+        self.save(DISABLED_SOURCES_KEY, serde_json::to_vec(&sources)?)
+            .await?;
+        *self.disabled_sources.write().unwrap() = sources;
+        Ok(())
+    }
+
+    async fn save(&self, key: &str, value: Vec<u8>) -> Result<()> {
+        Config::insert(config::ActiveModel {
+            key: Set(key.to_owned()),
+            value: Set(value),
+        })
+        .on_conflict(
+            OnConflict::column(config::Column::Key)
+                .update_column(config::Column::Value)
+                .to_owned(),
+        )
+        .exec(&self.conn)
+        .await?;
+        Ok(())
     }
 
     pub fn add_tool<T: Tool>(&mut self) -> Result<()> {
-        self.tools.insert(
-            T::NAME,
-            ToolStoreInner {
-                constructor: Eraser::<T>::new(),
-                description: T::DESCRIPTION,
-                prompt: T::PROMPT,
-                schema: serde_json::to_value(schema_for!(T::Input))
-                    .context("Cannot generate schema for tool")?,
-            },
-        );
+        let inner = ToolStoreInner {
+            source: ToolSource::Builtin,
+            constructor: Eraser::<T>::boxed(),
+            description: T::DESCRIPTION,
+            prompt: T::PROMPT,
+            schema: serde_json::to_value(schema_for!(T::Input))
+                .context("Cannot generate schema for tool")?,
+        };
+        self.register(T::NAME, inner)
+    }
+
+    /// Fail if `id` is already taken, by a tool or an alias
+    fn register(&self, id: &'static str, inner: ToolStoreInner) -> Result<()> {
+        let mut tools = self.tools.write().unwrap();
+        if tools.contains_key(id) || self.aliases.read().unwrap().contains_key(id) {
+            bail!("`{}` is already a tool", id);
+        }
+        tools.insert(id, Arc::new(inner));
+        Ok(())
+    }
+
+    /// Let `alias` stand for the tool `id`, e.g. after a rename
+    pub fn alias(&self, alias: &'static str, id: &'static str) -> Result<()> {
+        let tools = self.tools.read().unwrap();
+        if !tools.contains_key(id) {
+            bail!("`{}` is not a tool", id);
+        }
+        let mut aliases = self.aliases.write().unwrap();
+        if tools.contains_key(alias) || aliases.contains_key(alias) {
+            bail!("`{}` is already a tool", alias);
+        }
+        aliases.insert(alias, id);
+        Ok(())
+    }
+
+    /// Register the tools declared in `dir`, return how many were added
+    pub fn load_declared(&self, dir: &Path) -> usize {
+        let mut count = 0;
+        for spec in declared::load_dir(dir) {
+            match self.add_declared(spec) {
+                Ok(()) => count += 1,
+                Err(err) => tracing::warn!("skip declared tool: {}", err),
+            }
+        }
+        count
+    }
+
+    /// Register a declared tool, available to new tool boxes right away
+    ///
+    /// Its bare name become an alias unless another tool has it. Its id,
+    /// description and prompt are leaked, this only happens on start and on
+    /// imports by the administrator
+    pub fn add_declared(&self, spec: declared::Spec) -> Result<()> {
+        let id: &'static str = ToolSource::Declared.id(&spec.name).leak();
+        let name = spec.name.clone();
+        let inner = ToolStoreInner {
+            source: ToolSource::Declared,
+            description: spec.description.clone().leak(),
+            prompt: spec.prompt.clone().leak(),
+            schema: spec.schema.clone(),
+            constructor: Box::new(declared::HttpTool::new(spec, self.http_client.clone())),
+        };
+        self.register(id, inner)?;
+        self.declared.write().unwrap().push(id);
+
+        if !self.has(&name) {
+            self.alias(name.leak(), id)?;
+        }
         Ok(())
     }
 
+    /// Whether `name` is the id or an alias of a tool
+    pub fn has(&self, name: &str) -> bool {
+        self.tools.read().unwrap().contains_key(name)
+            || self.aliases.read().unwrap().contains_key(name)
+    }
+
+    /// Tools of the set, declared ones included if it has them
+    pub fn names(&self, tool_set: ToolSet) -> Vec<&'static str> {
+        let mut names: Vec<_> = tool_set.toold().collect();
+        if tool_set.declared() {
+            names.extend(self.declared.read().unwrap().iter().copied());
+        }
+        names
+    }
+
+    /// Resolve aliases and drop unknown tools or tools of disabled sources,
+    /// each tool at most once
+    fn resolve<'a>(
+        &self,
+        names: impl Iterator<Item = &'a str>,
+    ) -> Vec<(&'static str, Arc<ToolStoreInner>)> {
+        let tools = self.tools.read().unwrap();
+        let aliases = self.aliases.read().unwrap();
+        let disabled_sources = self.disabled_sources.read().unwrap();
+
+        let mut resolved: Vec<(&'static str, Arc<ToolStoreInner>)> = vec![];
+        for name in names {
+            let id = aliases.get(name).copied().unwrap_or(name);
+            let Some((&id, inner)) = tools.get_key_value(id) else {
+                continue;
+            };
+            if disabled_sources.contains(&inner.source) || resolved.iter().any(|x| x.0 == id) {
+                continue;
+            }
+            resolved.push((id, inner.clone()));
+        }
+        resolved
+    }
+
     pub fn list(&self, tool_set: ToolSet) -> (Vec<&'static str>, Vec<openrouter::Tool>) {
-        tool_set
-            .toold()
-            .filter_map(|name| {
-                self.tools.get(name).map(|tool| {
-                    (
-                        tool.prompt,
-                        openrouter::Tool {
-                            name: name.to_string(),
-                            description: tool.description.to_owned(),
-                            schema: tool.schema.clone(),
-                        },
-                    )
-                })
+        self.list_by_name(self.names(tool_set).into_iter())
+    }
+
+    /// Same as [`ToolStore::list`], but for an arbitrary subset of tools
+    pub fn list_by_name<'a>(
+        &self,
+        names: impl Iterator<Item = &'a str>,
+    ) -> (Vec<&'static str>, Vec<openrouter::Tool>) {
+        if self.disabled() {
+            return Default::default();
+        }
+        self.resolve(names)
+            .into_iter()
+            .map(|(id, tool)| {
+                (
+                    tool.prompt,
+                    openrouter::Tool {
+                        name: id.to_string(),
+                        description: tool.description.to_owned(),
+                        schema: tool.schema.clone(),
+                    },
+                )
             })
             .collect()
     }
 
     /// Grab a tool box
     pub async fn grab(&self, chat_id: i32, tool_set: ToolSet) -> Result<ToolBox> {
-        let iter = tool_set
-            .toold()
-            .filter_map(|name| self.tools.get(name).map(|tool| (name, tool)));
+        self.grab_by_name(chat_id, self.names(tool_set).into_iter())
+            .await
+    }
+
+    /// Same as [`ToolStore::grab`], but for an arbitrary subset of tools
+    pub async fn grab_by_name<'a>(
+        &self,
+        chat_id: i32,
+        names: impl Iterator<Item = &'a str>,
+    ) -> Result<ToolBox> {
+        if self.disabled() {
+            return Ok(ToolBox {
+                tools: HashMap::new(),
+                schemas: HashMap::new(),
+                aliases: HashMap::new(),
+                chat_id,
+            });
+        }
+        // not held across the queries below
+        let inners = self.resolve(names);
 
         let mut tools = HashMap::new();
+        let mut schemas = HashMap::new();
 
-        for (name, inner) in iter {
-            let dyn_tool = tool::Entity::find_by_id((chat_id, name.to_owned()))
+        for (id, inner) in inners {
+            let dyn_tool = tool::Entity::find_by_id((chat_id, id.to_owned()))
                 .one(&self.conn)
                 .await?
-                .map(|model| inner.constructor.new(&model.state))
+                .map(|model| inner.constructor.construct(&model.state))
                 .transpose()?
                 .unwrap_or(inner.constructor.default());
 
-            tools.insert(name, dyn_tool);
+            tools.insert(id, dyn_tool);
+            schemas.insert(id, inner.schema.clone());
         }
 
-        Ok(ToolBox { tools, chat_id })
+        let aliases = self
+            .aliases
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, id)| tools.contains_key(*id))
+            .map(|(&alias, &id)| (alias, id))
+            .collect();
+
+        Ok(ToolBox {
+            tools,
+            schemas,
+            aliases,
+            chat_id,
+        })
     }
 
     /// Put tool box back
@@ -114,7 +390,7 @@ impl ToolStore {
 }
 
 trait ToolConstructor {
-    fn new(&self, v: &str) -> Result<Box<dyn UntypedTool>>;
+    fn construct(&self, v: &str) -> Result<Box<dyn UntypedTool>>;
     fn default(&self) -> Box<dyn UntypedTool>;
 }
 
@@ -127,7 +403,7 @@ impl<T> Eraser<T>
 where
     T: Tool,
 {
-    fn new() -> Box<dyn ToolConstructor + Send + Sync> {
+    fn boxed() -> Box<dyn ToolConstructor + Send + Sync> {
         Box::new(Self {
             _marker: PhantomData,
         })
@@ -138,7 +414,7 @@ impl<T> ToolConstructor for Eraser<T>
 where
     T: Tool,
 {
-    fn new(&self, v: &str) -> Result<Box<dyn UntypedTool>> {
+    fn construct(&self, v: &str) -> Result<Box<dyn UntypedTool>> {
         let tool: T = serde_json::from_str(v).context("Cannot deserialize tool state")?;
         Ok(Box::new(tool))
     }
@@ -148,14 +424,37 @@ where
     }
 }
 
+/// Declared tools are stateless, the saved state is ignored
+impl ToolConstructor for declared::HttpTool {
+    fn construct(&self, _: &str) -> Result<Box<dyn UntypedTool>> {
+        Ok(Box::new(self.clone()))
+    }
+
+    fn default(&self) -> Box<dyn UntypedTool> {
+        Box::new(self.clone())
+    }
+}
+
 impl ToolBox {
+    /// Problems of the arguments against the schema of the tool, see
+    /// `tools::validate`; none for a tool not in the box
+    pub fn check(&self, name: &str, args: &str) -> Vec<String> {
+        let name = self.aliases.get(name).copied().unwrap_or(name);
+        match self.schemas.get(name) {
+            Some(schema) => super::check(schema, args),
+            None => vec![],
+        }
+    }
+
+    /// By id or alias, the id is returned
     pub fn get(&mut self, name: &str) -> Option<(&'static str, &mut Box<dyn UntypedTool>)> {
-        let Some((name, _)) = self.tools.get_key_value(name) else {
-            return None;
-        };
+        let name = self.aliases.get(name).copied().unwrap_or(name);
+        let (name, _) = self.tools.get_key_value(name)?;
         let name = *name;
 
         let tool = self.tools.get_mut(name).unwrap();
+        // only looked up to be called
+        METRICS.tool_call(name);
         Some((name, tool))
     }
 }
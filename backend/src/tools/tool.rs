@@ -1,9 +1,125 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
 use anyhow::{Context, Result};
+use entity::{LinkKind, file, prelude::*};
 use futures_util::{FutureExt, future::BoxFuture};
 use schemars::JsonSchema;
+use sea_orm::EntityTrait;
 use serde::{Serialize, de::DeserializeOwned};
 use serde_json::Value;
 
+use crate::{AppState, utils::chat_variable};
+
+/// What a tool can reach during a call
+pub struct ToolCtx {
+    pub app: Arc<AppState>,
+    pub chat_id: i32,
+    /// Who sent the message replied to
+    pub user_id: i32,
+    links: Mutex<Vec<EntityLink>>,
+    /// Files made by calls, attached to the assistant message
+    files: Mutex<Vec<file::Model>>,
+    answer: Mutex<Option<Value>>,
+}
+
+/// An entity referenced by a tool result, e.g. a mail id
+#[derive(Debug, Clone)]
+pub struct EntityLink {
+    pub kind: LinkKind,
+    pub id: String,
+    pub label: String,
+}
+
+impl ToolCtx {
+    pub fn new(app: Arc<AppState>, chat_id: i32, user_id: i32) -> Self {
+        Self {
+            app,
+            chat_id,
+            user_id,
+            links: Default::default(),
+            files: Default::default(),
+            answer: Default::default(),
+        }
+    }
+
+    /// What the user answered to the [`super::NeedsInput`] of the previous
+    /// attempt, None on the first attempt
+    pub fn answer(&self) -> Option<Value> {
+        self.answer.lock().unwrap().take()
+    }
+
+    pub fn set_answer(&self, answer: Option<Value>) {
+        *self.answer.lock().unwrap() = answer;
+    }
+
+    /// Variable of the chat set by an earlier call, of any tool
+    pub async fn var(&self, key: &str) -> Result<Option<Value>> {
+        chat_variable::get(&self.app.conn, self.chat_id, key).await
+    }
+
+    pub async fn vars(&self) -> Result<BTreeMap<String, Value>> {
+        chat_variable::all(&self.app.conn, self.chat_id).await
+    }
+
+    /// Persisted right away, also listed to the model as `chat.vars` in
+    /// prompts. Null removes the variable
+    pub async fn set_var(&self, key: &str, value: impl Serialize) -> Result<()> {
+        chat_variable::set(
+            &self.app.conn,
+            self.chat_id,
+            key,
+            serde_json::to_value(value)?,
+        )
+        .await
+    }
+
+    /// Workspace of the chat, see `utils::workspace`
+    pub async fn workspace_id(&self) -> Result<i32> {
+        let chat = Chat::find_by_id(self.chat_id)
+            .one(&self.app.conn)
+            .await?
+            .context("Cannot find chat")?;
+        Ok(chat.workspace_id)
+    }
+
+    /// A credential the admins set for the workspace of the chat, otherwise
+    /// the env of the same name
+    pub async fn credential(&self, name: &str) -> Result<Option<String>> {
+        let workspace_id = self.workspace_id().await?;
+        match self.app.tools.credential(workspace_id, name).await? {
+            Some(x) => Ok(Some(x)),
+            None => Ok(dotenv::var(name).ok()),
+        }
+    }
+
+    /// Record an entity, it would be linked to the assistant message
+    /// so follow-up requests can refer to it by id
+    pub fn link(&self, kind: LinkKind, id: impl Into<String>, label: impl Into<String>) {
+        self.links.lock().unwrap().push(EntityLink {
+            kind,
+            id: id.into(),
+            label: label.into(),
+        });
+    }
+
+    pub fn take_links(&self) -> Vec<EntityLink> {
+        std::mem::take(&mut self.links.lock().unwrap())
+    }
+
+    /// Record a file stored by the call, e.g. a generated image, it would be
+    /// attached to the assistant message and shown in the chat
+    pub fn attach(&self, file: file::Model) {
+        self.files.lock().unwrap().push(file);
+    }
+
+    pub fn take_files(&self) -> Vec<file::Model> {
+        std::mem::take(&mut self.files.lock().unwrap())
+    }
+}
+
 pub trait Tool: Serialize + DeserializeOwned + Default + Send + 'static {
     type Input: JsonSchema + DeserializeOwned + Send;
     type Output: Serialize;
@@ -12,11 +128,15 @@ pub trait Tool: Serialize + DeserializeOwned + Default + Send + 'static {
     const DESCRIPTION: &str;
     const PROMPT: &str;
 
-    fn call(&mut self, input: Self::Input) -> impl Future<Output = Result<Self::Output>> + Send;
+    fn call(
+        &mut self,
+        input: Self::Input,
+        ctx: &ToolCtx,
+    ) -> impl Future<Output = Result<Self::Output>> + Send;
 }
 
 pub trait UntypedTool: Send {
-    fn call<'a>(&'a mut self, input: &'a str) -> BoxFuture<'a, Result<Value>>;
+    fn call<'a>(&'a mut self, input: &'a str, ctx: &'a ToolCtx) -> BoxFuture<'a, Result<Value>>;
     fn se(&self) -> Result<String>;
 }
 
@@ -24,11 +144,11 @@ impl<T> UntypedTool for T
 where
     T: Tool,
 {
-    fn call<'a>(&'a mut self, input: &'a str) -> BoxFuture<'a, Result<Value>> {
+    fn call<'a>(&'a mut self, input: &'a str, ctx: &'a ToolCtx) -> BoxFuture<'a, Result<Value>> {
         async {
-            Ok(Tool::call(self, serde_json::from_str(input)?)
+            Ok(Tool::call(self, serde_json::from_str(input)?, ctx)
                 .await
-                .map(|output| serde_json::to_value(output))??)
+                .map(serde_json::to_value)??)
         }
         .boxed()
     }
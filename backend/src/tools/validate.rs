@@ -0,0 +1,329 @@
+//! Arguments of tool calls checked against the schema of their tool
+//!
+//! Models write arguments as text, and sometimes miss a field, quote a number
+//! or make up an option. The call is then not made: the model gets an
+//! [`InvalidArgs`] listing what is wrong instead, and can call again with the
+//! arguments fixed. Only the keywords the schemas of schemars and of declared
+//! tools use are checked, others such as `format` or `pattern` are ignored
+
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::errors::ErrorKind;
+
+/// Mismatches listed, the rest is left for the next attempt
+const MAX_PROBLEMS: usize = 8;
+/// `$ref` followed in a row, recursive schemas are cut there
+const MAX_DEPTH: usize = 32;
+
+/// Result of a call whose arguments do not match, sent to the model in place
+/// of the output of the tool
+#[derive(Debug, Clone, Serialize)]
+pub struct InvalidArgs {
+    /// Always [`ErrorKind::ToolArgsInvalid`]
+    pub error: ErrorKind,
+    /// What to do next, with the problems
+    pub reason: String,
+    /// Each mismatch, at a JSON pointer into the arguments
+    pub problems: Vec<String>,
+}
+
+impl InvalidArgs {
+    /// `retry` tells the model to call again with fixed arguments, otherwise
+    /// to answer without the tool
+    pub fn new(problems: Vec<String>, retry: bool) -> Self {
+        let next = match retry {
+            true => "fix them and call the tool again",
+            false => "do not call the tool again, answer without it",
+        };
+        Self {
+            error: ErrorKind::ToolArgsInvalid,
+            reason: format!(
+                "the arguments do not match the schema of the tool ({}), {}",
+                problems.join("; "),
+                next
+            ),
+            problems,
+        }
+    }
+}
+
+impl fmt::Display for InvalidArgs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.reason)
+    }
+}
+
+impl std::error::Error for InvalidArgs {}
+
+/// Problems of `args` against `schema`, empty if it matches
+pub fn check(schema: &Value, args: &str) -> Vec<String> {
+    let value: Value = match serde_json::from_str(args) {
+        Ok(x) => x,
+        Err(err) => return vec![format!("the arguments are not valid JSON: {}", err)],
+    };
+    let mut problems = vec![];
+    walk(schema, schema, &value, "", 0, &mut problems);
+    problems.truncate(MAX_PROBLEMS);
+    problems
+}
+
+fn walk(
+    root: &Value,
+    schema: &Value,
+    value: &Value,
+    path: &str,
+    depth: usize,
+    problems: &mut Vec<String>,
+) {
+    if depth > MAX_DEPTH || problems.len() >= MAX_PROBLEMS {
+        return;
+    }
+    let schema = match schema {
+        Value::Bool(true) => return,
+        Value::Bool(false) => {
+            problems.push(format!("{}: not allowed", at(path)));
+            return;
+        }
+        Value::Object(x) => x,
+        _ => return,
+    };
+    if let Some(target) = schema.get("$ref").and_then(Value::as_str) {
+        if let Some(target) = resolve(root, target) {
+            walk(root, target, value, path, depth + 1, problems);
+        }
+        // siblings of a `$ref` are only annotations for schemars
+        return;
+    }
+    // OpenAPI 3.0, imported as is in declared tools
+    if value.is_null() && schema.get("nullable") == Some(&Value::Bool(true)) {
+        return;
+    }
+
+    if let Some(types) = schema.get("type") {
+        let types: Vec<&str> = match types {
+            Value::String(x) => vec![x.as_str()],
+            Value::Array(x) => x.iter().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        };
+        if !types.is_empty() && !types.iter().any(|x| is_type(value, x)) {
+            problems.push(format!(
+                "{}: expected {}, got {}",
+                at(path),
+                types.join(" or "),
+                type_of(value)
+            ));
+            return;
+        }
+    }
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array)
+        && !allowed.contains(value)
+    {
+        problems.push(format!(
+            "{}: expected one of {}, got {}",
+            at(path),
+            list(allowed),
+            value
+        ));
+        return;
+    }
+    if let Some(expected) = schema.get("const")
+        && expected != value
+    {
+        problems.push(format!(
+            "{}: expected {}, got {}",
+            at(path),
+            expected,
+            value
+        ));
+        return;
+    }
+
+    for key in ["anyOf", "oneOf"] {
+        let Some(branches) = schema.get(key).and_then(Value::as_array) else {
+            continue;
+        };
+        // oneOf is taken as anyOf, schemars uses it for enums that do not
+        // overlap anyway
+        let matched = branches.iter().any(|branch| {
+            let mut x = vec![];
+            walk(root, branch, value, path, depth + 1, &mut x);
+            x.is_empty()
+        });
+        if !matched {
+            problems.push(format!(
+                "{}: {} matches none of the shapes allowed",
+                at(path),
+                value
+            ));
+            return;
+        }
+    }
+    if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+        for branch in all {
+            walk(root, branch, value, path, depth + 1, problems);
+        }
+    }
+
+    match value {
+        Value::Object(fields) => object(root, schema, fields, path, depth, problems),
+        Value::Array(items) => {
+            let len = items.len() as f64;
+            if let Some(min) = schema.get("minItems").and_then(Value::as_f64)
+                && len < min
+            {
+                problems.push(format!("{}: expected at least {} items", at(path), min));
+            }
+            if let Some(max) = schema.get("maxItems").and_then(Value::as_f64)
+                && len > max
+            {
+                problems.push(format!("{}: expected at most {} items", at(path), max));
+            }
+            if let Some(item) = schema.get("items") {
+                for (idx, x) in items.iter().enumerate() {
+                    walk(
+                        root,
+                        item,
+                        x,
+                        &format!("{}/{}", path, idx),
+                        depth + 1,
+                        problems,
+                    );
+                }
+            }
+        }
+        Value::String(x) => {
+            let len = x.chars().count() as f64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_f64)
+                && len < min
+            {
+                problems.push(format!(
+                    "{}: expected at least {} characters",
+                    at(path),
+                    min
+                ));
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_f64)
+                && len > max
+            {
+                problems.push(format!("{}: expected at most {} characters", at(path), max));
+            }
+        }
+        Value::Number(x) => {
+            let x = x.as_f64().unwrap_or_default();
+            let bound = |key| schema.get(key).and_then(Value::as_f64);
+            if let Some(min) = bound("minimum")
+                && x < min
+            {
+                problems.push(format!("{}: expected at least {}", at(path), min));
+            }
+            if let Some(max) = bound("maximum")
+                && x > max
+            {
+                problems.push(format!("{}: expected at most {}", at(path), max));
+            }
+            if let Some(min) = bound("exclusiveMinimum")
+                && x <= min
+            {
+                problems.push(format!("{}: expected more than {}", at(path), min));
+            }
+            if let Some(max) = bound("exclusiveMaximum")
+                && x >= max
+            {
+                problems.push(format!("{}: expected less than {}", at(path), max));
+            }
+        }
+        _ => {}
+    }
+}
+
+fn object(
+    root: &Value,
+    schema: &Map<String, Value>,
+    fields: &Map<String, Value>,
+    path: &str,
+    depth: usize,
+    problems: &mut Vec<String>,
+) {
+    let properties = schema.get("properties").and_then(Value::as_object);
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for key in required.iter().filter_map(Value::as_str) {
+            if !fields.contains_key(key) {
+                problems.push(format!("{}/{}: missing", path, key));
+            }
+        }
+    }
+    for (key, field) in fields {
+        let field_path = format!("{}/{}", path, key);
+        match properties.and_then(|x| x.get(key)) {
+            Some(property) => walk(root, property, field, &field_path, depth + 1, problems),
+            None => match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) => {
+                    let known = properties.map(|x| x.keys().cloned().collect::<Vec<_>>());
+                    problems.push(format!(
+                        "{}: unknown field, expected one of {}",
+                        field_path,
+                        known.unwrap_or_default().join(", ")
+                    ));
+                }
+                Some(additional) => walk(root, additional, field, &field_path, depth + 1, problems),
+                None => {}
+            },
+        }
+    }
+}
+
+/// `#`, `#/$defs/..` and `#/definitions/..`, None for a remote or a broken
+/// reference
+fn resolve<'a>(root: &'a Value, target: &str) -> Option<&'a Value> {
+    let pointer = target.strip_prefix('#')?;
+    root.pointer(pointer)
+}
+
+fn is_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => match value {
+            Value::Number(x) => {
+                x.is_i64() || x.is_u64() || x.as_f64().is_some_and(|x| x.fract() == 0.0)
+            }
+            _ => false,
+        },
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        // unknown types are not ours to refuse
+        _ => true,
+    }
+}
+
+fn type_of(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn list(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(Value::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `/` for the arguments themselves
+fn at(path: &str) -> &str {
+    match path {
+        "" => "/",
+        x => x,
+    }
+}
@@ -2,7 +2,7 @@ use reqwest::Url;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::tools::Tool;
+use crate::tools::{Tool, ToolCtx};
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Wttr;
@@ -21,7 +21,7 @@ impl Tool for Wttr {
     const DESCRIPTION: &str = "get weather info such as humidity, wind speed, temperature, etc from wttr.in in json format";
     const PROMPT: &str = "use `wttr` to get weather info whem user request";
 
-    async fn call(&mut self, input: Self::Input) -> anyhow::Result<Self::Output> {
+    async fn call(&mut self, input: Self::Input, _ctx: &ToolCtx) -> anyhow::Result<Self::Output> {
         let url: Url = "https://wttr.in/".parse()?;
         let mut url = url.join(input.location.trim().replace(" ", "+").as_str())?;
         url.set_query(Some("format=j1"));
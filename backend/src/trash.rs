@@ -0,0 +1,87 @@
+//! Deleted chats and messages, purged after [`TRASH_DAYS`]
+//!
+//! A deletion removes the rows at once and keeps them here as JSON, restoring
+//! insert them back with their ids, which are never reused. Chats deleted by
+//! the retention policy or with their account skip the trash
+
+use anyhow::Result;
+use entity::{prelude::*, trash};
+use sea_orm::{ActiveValue::Set, ConnectionTrait, DbConn, prelude::*};
+
+use crate::{
+    config::TRASH_DAYS,
+    undo::{self, Snapshot},
+};
+
+pub enum Restore {
+    /// Id of the chat the rows belong to
+    Restored(i32),
+    NotFound,
+    /// The chat of a message was deleted too, it has to be restored first
+    ChatGone,
+}
+
+/// Move the snapshots of a deletion to the trash, return their ids
+pub async fn put(
+    conn: &impl ConnectionTrait,
+    user_id: i32,
+    snapshots: Vec<Snapshot>,
+) -> Result<Vec<i32>> {
+    let now = now();
+    let mut ids = Vec::with_capacity(snapshots.len());
+    for snapshot in snapshots {
+        let res = Trash::insert(trash::ActiveModel {
+            owner_id: Set(user_id),
+            chat_id: Set(snapshot.chat_id()),
+            message_id: Set(snapshot.message_id()),
+            title: Set(snapshot.title()),
+            deleted_at: Set(now),
+            snapshot: Set(serde_json::to_string(&snapshot)?),
+            ..Default::default()
+        })
+        .exec(conn)
+        .await?;
+        ids.push(res.last_insert_id);
+    }
+    Ok(ids)
+}
+
+/// Insert the rows of an entry of the user back and remove it from the trash
+pub async fn restore(conn: &impl ConnectionTrait, user_id: i32, id: i32) -> Result<Restore> {
+    let Some(entry) = Trash::find_by_id(id)
+        .filter(trash::Column::OwnerId.eq(user_id))
+        .one(conn)
+        .await?
+    else {
+        return Ok(Restore::NotFound);
+    };
+    if entry.message_id.is_some() && Chat::find_by_id(entry.chat_id).one(conn).await?.is_none() {
+        return Ok(Restore::ChatGone);
+    }
+    let snapshot: Snapshot = serde_json::from_str(&entry.snapshot)?;
+    undo::restore(conn, snapshot).await?;
+    Trash::delete_by_id(id).exec(conn).await?;
+    Ok(Restore::Restored(entry.chat_id))
+}
+
+/// When an entry deleted at `deleted_at` is purged
+pub fn purge_at(deleted_at: i64) -> i64 {
+    deleted_at + TRASH_DAYS * 24 * 3600
+}
+
+/// Purge the entries past [`TRASH_DAYS`], run every `TRASH_PURGE_INTERVAL`
+/// by `jobs`
+pub async fn purge_due(conn: &DbConn) -> Result<()> {
+    let res = Trash::delete_many()
+        .filter(trash::Column::DeletedAt.lt(now() - TRASH_DAYS * 24 * 3600))
+        .exec(conn)
+        .await?;
+    if res.rows_affected > 0 {
+        tracing::info!("purged {} entries of the trash", res.rows_affected);
+    }
+    Ok(())
+}
+
+fn now() -> i64 {
+    time::UtcDateTime::now().unix_timestamp()
+}
@@ -0,0 +1,102 @@
+//! Speech synthesis of replies, see `TTS_PROVIDER` env
+
+use std::sync::LazyLock;
+
+use anyhow::{Result, bail};
+use dotenv::var;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::config::TTS_MAX_CHARS;
+
+/// `/audio/speech` of an OpenAI-compatible provider, e.g. OpenAI or a local
+/// Kokoro server
+pub struct Tts {
+    endpoint: String,
+    api_key: Option<String>,
+    model: String,
+    voice: String,
+}
+
+#[derive(Serialize)]
+struct SpeechReq<'a> {
+    model: &'a str,
+    input: &'a str,
+    voice: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    speed: Option<f32>,
+    response_format: &'static str,
+}
+
+impl Tts {
+    /// None unless `TTS_PROVIDER` is `openai`
+    pub fn from_env() -> Option<Self> {
+        match var("TTS_PROVIDER").ok()?.as_str() {
+            "openai" => Some(Self {
+                endpoint: format!(
+                    "{}/audio/speech",
+                    var("TTS_API_BASE")
+                        .unwrap_or("https://api.openai.com/v1".to_owned())
+                        .trim_end_matches('/')
+                ),
+                api_key: var("TTS_API_KEY").ok(),
+                model: var("TTS_MODEL").unwrap_or("tts-1".to_owned()),
+                voice: var("TTS_VOICE").unwrap_or("alloy".to_owned()),
+            }),
+            x => {
+                tracing::warn!("unknown TTS_PROVIDER {}, expect openai, speech disabled", x);
+                None
+            }
+        }
+    }
+
+    /// MP3 of `text` as the provider stream it, `voice` default to `TTS_VOICE`
+    /// and `speed` to the provider's
+    pub async fn synthesize(
+        &self,
+        text: &str,
+        voice: Option<&str>,
+        speed: Option<f32>,
+    ) -> Result<reqwest::Response> {
+        let req = reqwest::Client::new()
+            .post(&self.endpoint)
+            .json(&SpeechReq {
+                model: &self.model,
+                input: text,
+                voice: voice.unwrap_or(&self.voice),
+                speed: speed.map(|x| x.clamp(0.25, 4.0)),
+                response_format: "mp3",
+            });
+        let req = match &self.api_key {
+            Some(api_key) => req.bearer_auth(api_key),
+            None => req,
+        };
+        let res = req.send().await?;
+        if !res.status().is_success() {
+            bail!(
+                "speech synthesis failed with {}: {}",
+                res.status(),
+                res.text().await.unwrap_or_default()
+            );
+        }
+        Ok(res)
+    }
+}
+
+static CODE_BLOCK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)```.*?(```|$)").unwrap());
+static IMAGE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"!\[[^\]]*\]\([^)]*\)").unwrap());
+static LINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[([^\]]*)\]\([^)]*\)").unwrap());
+static LINE_MARK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s*(#+|>+|[-*+]|\d+\.|\|)\s*").unwrap());
+static INLINE_MARK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[*`~|]+").unwrap());
+
+/// Markdown of a reply as it should be read, code blocks and link targets
+/// are skipped, cut to [`TTS_MAX_CHARS`]
+pub fn speakable(markdown: &str) -> String {
+    let text = CODE_BLOCK.replace_all(markdown, "");
+    let text = IMAGE.replace_all(&text, "");
+    let text = LINK.replace_all(&text, "$1");
+    let text = LINE_MARK.replace_all(&text, "");
+    let text = INLINE_MARK.replace_all(&text, "");
+    text.trim().chars().take(TTS_MAX_CHARS).collect()
+}
@@ -0,0 +1,607 @@
+//! Undo window of destructive actions, see `UNDO_WINDOW` env
+//!
+//! Deleted rows are removed at once and moved to the trash, so every read
+//! already skip them. A token point to the trash entries of one deletion, and
+//! `POST /api/undo/{token}` restore them with their ids, the order of messages
+//! is their ids
+//!
+//! Tokens do not survive a restart, the entries stay in the trash
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use entity::{
+    ChatSentiment, ChatTask, ChunkKind, MessageKind, attachment, chat, chat_collection, chat_label,
+    chat_member, chat_variable, chunk, collection, file, label, link, message, prelude::*, user,
+};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, QueryFilter,
+    QueryOrder, QuerySelect,
+    sea_query::{Expr, OnConflict},
+};
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::{
+    AppState,
+    config::{TRASH_TITLE_CHARS, UNDO_WINDOW_SECS},
+    utils::{branch, workspace::DEFAULT_WORKSPACE},
+};
+
+pub struct Undo {
+    window: Duration,
+    map: Mutex<HashMap<String, Entry>>,
+}
+
+struct Entry {
+    user_id: i32,
+    /// Ids of `trash`
+    trash: Vec<i32>,
+    at: Instant,
+}
+
+/// Rows removed by one deletion, with what cascaded from them
+#[derive(Serialize, Deserialize)]
+pub enum Snapshot {
+    Chat {
+        chat: ChatRow,
+        variables: Vec<chat_variable::Model>,
+        labels: Vec<chat_label::Model>,
+        #[serde(default)]
+        members: Vec<chat_member::Model>,
+        #[serde(default)]
+        collections: Vec<chat_collection::Model>,
+        messages: Vec<MessageSnapshot>,
+    },
+    Message(MessageSnapshot),
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MessageSnapshot {
+    message: MessageRow,
+    chunks: Vec<chunk::Model>,
+    links: Vec<link::Model>,
+    /// Kept by the sweep of `files` while the entry is in the trash, put back
+    /// in case they were not
+    #[serde(default)]
+    attachments: Vec<attachment::Model>,
+    /// Messages handed to its parent by the deletion, see `branch::detach`
+    children: Vec<i32>,
+    /// Whether it was the head of its chat
+    head: bool,
+}
+
+/// A row of `chat` as kept in the trash
+///
+/// Entries outlive migrations: a column added since the trash existed needs
+/// a default here for the entries written before it
+#[derive(Serialize, Deserialize)]
+pub struct ChatRow {
+    id: i32,
+    owner_id: i32,
+    model_id: i32,
+    title: Option<String>,
+    reproducible: bool,
+    head_id: Option<i32>,
+    topic: Option<String>,
+    sentiment: Option<ChatSentiment>,
+    task: Option<ChatTask>,
+    tagged_at: Option<i64>,
+    retention_notice_at: Option<i64>,
+    share_token: Option<String>,
+    folder_id: Option<i32>,
+    pinned: bool,
+    archived_at: Option<i64>,
+    #[serde(default)]
+    prompt_variant_id: Option<i32>,
+    #[serde(default)]
+    system_prompt: Option<String>,
+    #[serde(default)]
+    system_prompt_replace: bool,
+    #[serde(default)]
+    summary: Option<String>,
+    #[serde(default)]
+    summary_until: Option<i32>,
+    #[serde(default)]
+    persona_id: Option<i32>,
+    #[serde(default = "default_workspace")]
+    workspace_id: i32,
+}
+
+fn default_workspace() -> i32 {
+    DEFAULT_WORKSPACE
+}
+
+impl From<chat::Model> for ChatRow {
+    fn from(x: chat::Model) -> Self {
+        Self {
+            id: x.id,
+            owner_id: x.owner_id,
+            model_id: x.model_id,
+            title: x.title,
+            reproducible: x.reproducible,
+            head_id: x.head_id,
+            topic: x.topic,
+            sentiment: x.sentiment,
+            task: x.task,
+            tagged_at: x.tagged_at,
+            retention_notice_at: x.retention_notice_at,
+            share_token: x.share_token,
+            folder_id: x.folder_id,
+            pinned: x.pinned,
+            archived_at: x.archived_at,
+            prompt_variant_id: x.prompt_variant_id,
+            system_prompt: x.system_prompt,
+            system_prompt_replace: x.system_prompt_replace,
+            summary: x.summary,
+            summary_until: x.summary_until,
+            persona_id: x.persona_id,
+            workspace_id: x.workspace_id,
+        }
+    }
+}
+
+impl From<ChatRow> for chat::Model {
+    fn from(x: ChatRow) -> Self {
+        Self {
+            id: x.id,
+            owner_id: x.owner_id,
+            model_id: x.model_id,
+            title: x.title,
+            reproducible: x.reproducible,
+            head_id: x.head_id,
+            topic: x.topic,
+            sentiment: x.sentiment,
+            task: x.task,
+            tagged_at: x.tagged_at,
+            retention_notice_at: x.retention_notice_at,
+            share_token: x.share_token,
+            folder_id: x.folder_id,
+            pinned: x.pinned,
+            archived_at: x.archived_at,
+            prompt_variant_id: x.prompt_variant_id,
+            system_prompt: x.system_prompt,
+            system_prompt_replace: x.system_prompt_replace,
+            summary: x.summary,
+            summary_until: x.summary_until,
+            persona_id: x.persona_id,
+            workspace_id: x.workspace_id,
+        }
+    }
+}
+
+/// A row of `message` as kept in the trash, see [`ChatRow`]
+#[derive(Serialize, Deserialize)]
+pub struct MessageRow {
+    id: i32,
+    chat_id: i32,
+    kind: MessageKind,
+    generation: Option<String>,
+    truncated: bool,
+    private: bool,
+    created_at: i64,
+    tokens: i64,
+    parent_id: Option<i32>,
+    #[serde(default)]
+    prompt_variant_id: Option<i32>,
+    #[serde(default)]
+    author_id: Option<i32>,
+    #[serde(default)]
+    generating: bool,
+    #[serde(default)]
+    interrupted: bool,
+    #[serde(default)]
+    trace: Option<String>,
+}
+
+impl From<message::Model> for MessageRow {
+    fn from(x: message::Model) -> Self {
+        Self {
+            id: x.id,
+            chat_id: x.chat_id,
+            kind: x.kind,
+            generation: x.generation,
+            truncated: x.truncated,
+            private: x.private,
+            created_at: x.created_at,
+            tokens: x.tokens,
+            parent_id: x.parent_id,
+            prompt_variant_id: x.prompt_variant_id,
+            author_id: x.author_id,
+            generating: x.generating,
+            interrupted: x.interrupted,
+            trace: x.trace,
+        }
+    }
+}
+
+impl From<MessageRow> for message::Model {
+    fn from(x: MessageRow) -> Self {
+        Self {
+            id: x.id,
+            chat_id: x.chat_id,
+            kind: x.kind,
+            generation: x.generation,
+            truncated: x.truncated,
+            private: x.private,
+            created_at: x.created_at,
+            tokens: x.tokens,
+            parent_id: x.parent_id,
+            prompt_variant_id: x.prompt_variant_id,
+            author_id: x.author_id,
+            generating: x.generating,
+            interrupted: x.interrupted,
+            trace: x.trace,
+        }
+    }
+}
+
+impl Snapshot {
+    pub fn chat_id(&self) -> i32 {
+        match self {
+            Snapshot::Chat { chat, .. } => chat.id,
+            Snapshot::Message(x) => x.message.chat_id,
+        }
+    }
+
+    /// None when the whole chat was deleted
+    pub fn message_id(&self) -> Option<i32> {
+        match self {
+            Snapshot::Chat { .. } => None,
+            Snapshot::Message(x) => Some(x.message.id),
+        }
+    }
+
+    /// Title of the chat, or the start of the text of the message
+    pub fn title(&self) -> Option<String> {
+        match self {
+            Snapshot::Chat { chat, .. } => chat.title.clone(),
+            Snapshot::Message(x) => x
+                .chunks
+                .iter()
+                .find(|x| x.kind == ChunkKind::Text)
+                .map(|x| x.content.chars().take(TRASH_TITLE_CHARS).collect()),
+        }
+    }
+}
+
+impl Undo {
+    /// `UNDO_WINDOW` in seconds, default to [`UNDO_WINDOW_SECS`], 0 disable undo
+    pub fn from_env() -> Self {
+        let secs = dotenv::var("UNDO_WINDOW")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(UNDO_WINDOW_SECS);
+        Self {
+            window: Duration::from_secs(secs),
+            map: Default::default(),
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// None if undo is disabled or there is nothing to undo
+    pub fn stage(&self, user_id: i32, trash: Vec<i32>) -> Result<Option<String>> {
+        if self.window.is_zero() || trash.is_empty() {
+            return Ok(None);
+        }
+        let mut bytes = [0u8; 16];
+        getrandom::fill(&mut bytes).map_err(|e| anyhow::anyhow!("Cannot generate token: {}", e))?;
+        let token: String = bytes.iter().map(|x| format!("{:02x}", x)).collect();
+
+        self.map.lock().unwrap().insert(
+            token.clone(),
+            Entry {
+                user_id,
+                trash,
+                at: Instant::now(),
+            },
+        );
+        Ok(Some(token))
+    }
+
+    /// Trash entries of the token if it belongs to the user and is still in its window
+    pub fn take(&self, user_id: i32, token: &str) -> Option<Vec<i32>> {
+        let mut map = self.map.lock().unwrap();
+        let entry = map.get(token)?;
+        if entry.user_id != user_id || entry.at.elapsed() >= self.window {
+            return None;
+        }
+        map.remove(token).map(|x| x.trash)
+    }
+
+    /// Drop the tokens past their window, the trash still restore their rows
+    pub fn spawn_expire(app: Arc<AppState>) {
+        if app.undo.window.is_zero() {
+            return;
+        }
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(app.undo.window);
+            loop {
+                interval.tick().await;
+                let window = app.undo.window;
+                app.undo
+                    .map
+                    .lock()
+                    .unwrap()
+                    .retain(|_, x| x.at.elapsed() < window);
+            }
+        });
+    }
+}
+
+/// Rows of a chat of the user, None if there is no such chat
+pub async fn snapshot_chat(
+    conn: &impl ConnectionTrait,
+    user_id: i32,
+    chat_id: i32,
+) -> Result<Option<Snapshot>> {
+    let Some(chat) = Chat::find_by_id(chat_id)
+        .filter(chat::Column::OwnerId.eq(user_id))
+        .one(conn)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let variables = ChatVariable::find()
+        .filter(chat_variable::Column::ChatId.eq(chat_id))
+        .all(conn)
+        .await?;
+    let labels = ChatLabel::find()
+        .filter(chat_label::Column::ChatId.eq(chat_id))
+        .all(conn)
+        .await?;
+    let members = ChatMember::find()
+        .filter(chat_member::Column::ChatId.eq(chat_id))
+        .all(conn)
+        .await?;
+    let collections = ChatCollection::find()
+        .filter(chat_collection::Column::ChatId.eq(chat_id))
+        .all(conn)
+        .await?;
+    let messages = Message::find()
+        .filter(message::Column::ChatId.eq(chat_id))
+        .order_by_asc(message::Column::Id)
+        .all(conn)
+        .await?;
+    let messages = snapshot_messages(conn, messages).await?;
+    Ok(Some(Snapshot::Chat {
+        chat: chat.into(),
+        variables,
+        labels,
+        members,
+        collections,
+        messages,
+    }))
+}
+
+/// Rows of a message in a chat of the user, None if there is no such message
+pub async fn snapshot_message(
+    conn: &impl ConnectionTrait,
+    user_id: i32,
+    message_id: i32,
+) -> Result<Option<Snapshot>> {
+    let Some((message, Some(chat))) = Message::find_by_id(message_id)
+        .find_also_related(Chat)
+        .one(conn)
+        .await?
+    else {
+        return Ok(None);
+    };
+    if chat.owner_id != user_id {
+        return Ok(None);
+    }
+    let children = Message::find()
+        .select_only()
+        .column(message::Column::Id)
+        .filter(message::Column::ParentId.eq(message.id))
+        .into_tuple()
+        .all(conn)
+        .await?;
+    let head = chat.head_id == Some(message.id);
+    Ok(snapshot_messages(conn, vec![message])
+        .await?
+        .pop()
+        .map(|x| {
+            Snapshot::Message(MessageSnapshot {
+                children,
+                head,
+                ..x
+            })
+        }))
+}
+
+async fn snapshot_messages(
+    conn: &impl ConnectionTrait,
+    messages: Vec<message::Model>,
+) -> Result<Vec<MessageSnapshot>> {
+    let ids: Vec<_> = messages.iter().map(|x| x.id).collect();
+    let mut chunks = Chunk::find()
+        .filter(chunk::Column::MessageId.is_in(ids.clone()))
+        .order_by_asc(chunk::Column::Id)
+        .all(conn)
+        .await?;
+    let mut links = Link::find()
+        .filter(link::Column::MessageId.is_in(ids.clone()))
+        .order_by_asc(link::Column::Id)
+        .all(conn)
+        .await?;
+    let mut attachments = Attachment::find()
+        .filter(attachment::Column::MessageId.is_in(ids))
+        .order_by_asc(attachment::Column::FileId)
+        .all(conn)
+        .await?;
+
+    Ok(messages
+        .into_iter()
+        .map(|message| MessageSnapshot {
+            chunks: chunks
+                .extract_if(.., |x| x.message_id == message.id)
+                .collect(),
+            links: links
+                .extract_if(.., |x| x.message_id == message.id)
+                .collect(),
+            attachments: attachments
+                .extract_if(.., |x| x.message_id == message.id)
+                .collect(),
+            message: message.into(),
+            children: vec![],
+            head: false,
+        })
+        .collect())
+}
+
+/// Insert the rows back, fail if one of their ids was taken since
+pub async fn restore(conn: &impl ConnectionTrait, snapshot: Snapshot) -> Result<()> {
+    match snapshot {
+        Snapshot::Chat {
+            chat,
+            variables,
+            labels,
+            members,
+            collections,
+            messages,
+        } => {
+            let mut chat = chat::Model::from(chat);
+            if let Some(folder_id) = chat.folder_id
+                && Folder::find_by_id(folder_id).one(conn).await?.is_none()
+            {
+                chat.folder_id = None;
+            }
+            if Workspace::find_by_id(chat.workspace_id)
+                .one(conn)
+                .await?
+                .is_none()
+            {
+                chat.workspace_id = DEFAULT_WORKSPACE;
+            }
+            Chat::insert(chat.into_active_model().reset_all())
+                .exec(conn)
+                .await?;
+            for variable in variables {
+                ChatVariable::insert(variable.into_active_model().reset_all())
+                    .exec(conn)
+                    .await?;
+            }
+            // labels deleted since are not brought back
+            let kept: Vec<i32> = Label::find()
+                .select_only()
+                .column(label::Column::Id)
+                .filter(label::Column::Id.is_in(labels.iter().map(|x| x.label_id)))
+                .into_tuple()
+                .all(conn)
+                .await?;
+            for label in labels.into_iter().filter(|x| kept.contains(&x.label_id)) {
+                ChatLabel::insert(label.into_active_model().reset_all())
+                    .exec(conn)
+                    .await?;
+            }
+            // nor members whose account was deleted since
+            let kept: Vec<i32> = User::find()
+                .select_only()
+                .column(user::Column::Id)
+                .filter(user::Column::Id.is_in(members.iter().map(|x| x.user_id)))
+                .into_tuple()
+                .all(conn)
+                .await?;
+            for member in members.into_iter().filter(|x| kept.contains(&x.user_id)) {
+                ChatMember::insert(member.into_active_model().reset_all())
+                    .exec(conn)
+                    .await?;
+            }
+            let kept: Vec<i32> = Collection::find()
+                .select_only()
+                .column(collection::Column::Id)
+                .filter(collection::Column::Id.is_in(collections.iter().map(|x| x.collection_id)))
+                .into_tuple()
+                .all(conn)
+                .await?;
+            for collection in collections
+                .into_iter()
+                .filter(|x| kept.contains(&x.collection_id))
+            {
+                ChatCollection::insert(collection.into_active_model().reset_all())
+                    .exec(conn)
+                    .await?;
+            }
+            for message in messages {
+                restore_message(conn, message).await?;
+            }
+        }
+        Snapshot::Message(message) => restore_message(conn, message).await?,
+    }
+    Ok(())
+}
+
+async fn restore_message(conn: &impl ConnectionTrait, snapshot: MessageSnapshot) -> Result<()> {
+    let (message_id, chat_id) = (snapshot.message.id, snapshot.message.chat_id);
+    Message::insert(
+        message::Model::from(snapshot.message)
+            .into_active_model()
+            .reset_all(),
+    )
+    .exec(conn)
+    .await?;
+    if !snapshot.children.is_empty() {
+        Message::update_many()
+            .col_expr(message::Column::ParentId, Expr::value(message_id))
+            .filter(message::Column::Id.is_in(snapshot.children))
+            .exec(conn)
+            .await?;
+    }
+    if snapshot.head {
+        branch::checkout(conn, chat_id, Some(message_id)).await?;
+    }
+    if !snapshot.chunks.is_empty() {
+        Chunk::insert_many(
+            snapshot
+                .chunks
+                .into_iter()
+                .map(|x| x.into_active_model().reset_all()),
+        )
+        .exec(conn)
+        .await?;
+    }
+    if !snapshot.links.is_empty() {
+        Link::insert_many(
+            snapshot
+                .links
+                .into_iter()
+                .map(|x| x.into_active_model().reset_all()),
+        )
+        .exec(conn)
+        .await?;
+    }
+    // files purged since are gone for good
+    let kept: Vec<i32> = File::find()
+        .select_only()
+        .column(file::Column::Id)
+        .filter(file::Column::Id.is_in(snapshot.attachments.iter().map(|x| x.file_id)))
+        .into_tuple()
+        .all(conn)
+        .await?;
+    let attachments: Vec<_> = snapshot
+        .attachments
+        .into_iter()
+        .filter(|x| kept.contains(&x.file_id))
+        .map(|x| x.into_active_model().reset_all())
+        .collect();
+    if !attachments.is_empty() {
+        Attachment::insert_many(attachments)
+            .on_conflict(
+                OnConflict::columns([attachment::Column::MessageId, attachment::Column::FileId])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .do_nothing()
+            .exec(conn)
+            .await?;
+    }
+    Ok(())
+}
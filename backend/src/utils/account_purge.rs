@@ -0,0 +1,124 @@
+//! Accounts deleted by their owner, after a grace period
+//!
+//! Deleting a user cascade to its chats, messages, files, sessions and keys,
+//! tool states, login failures and sync changes have no foreign key and are
+//! removed here
+
+use std::time::Duration;
+
+use anyhow::Result;
+use entity::{api_key, chat, prelude::*, sync_change, tool, user};
+use pasetors::claims::{Claims, ClaimsValidationRules};
+use sea_orm::{ActiveValue::Set, ConnectionTrait, QueryFilter, QuerySelect, prelude::*};
+
+use crate::{
+    config::{ACCOUNT_PURGE_CONFIRM_SECS, ACCOUNT_PURGE_GRACE_SECS},
+    utils::{keyring::Keyring, login_throttle, session},
+};
+
+/// Proof the user confirmed recently, not an access token since it has no "uid"
+pub fn confirmation(keyring: &Keyring, user_id: i32) -> Result<String> {
+    let mut claim = Claims::new_expires_in(&Duration::from_secs(ACCOUNT_PURGE_CONFIRM_SECS))?;
+    // safety:
+    // "purge" is not reserve
+    claim.add_additional("purge", user_id).unwrap();
+    keyring.encrypt(&claim)
+}
+
+pub fn confirmed(keyring: &Keyring, token: &str, user_id: i32) -> bool {
+    keyring
+        .decrypt(token, &ClaimsValidationRules::new())
+        .ok()
+        .and_then(|x| x.payload_claims()?.get_claim("purge")?.as_i64())
+        .is_some_and(|x| x == user_id as i64)
+}
+
+/// Sign the user out everywhere and revoke its API keys, return when the
+/// account is purged
+pub async fn schedule(conn: &impl ConnectionTrait, user_id: i32) -> Result<i64> {
+    let purge_at = now() + ACCOUNT_PURGE_GRACE_SECS;
+    User::update(user::ActiveModel {
+        id: Set(user_id),
+        purge_at: Set(Some(purge_at)),
+        ..Default::default()
+    })
+    .exec(conn)
+    .await?;
+    session::revoke_all(conn, user_id).await?;
+    ApiKey::delete_many()
+        .filter(api_key::Column::UserId.eq(user_id))
+        .exec(conn)
+        .await?;
+    Ok(purge_at)
+}
+
+/// Signing in again within the grace period keep the account
+pub async fn cancel(conn: &impl ConnectionTrait, user_id: i32) -> Result<()> {
+    let res = User::update_many()
+        .col_expr(user::Column::PurgeAt, Expr::value(Option::<i64>::None))
+        .filter(user::Column::Id.eq(user_id))
+        .filter(user::Column::PurgeAt.is_not_null())
+        .exec(conn)
+        .await?;
+    if res.rows_affected > 0 {
+        tracing::info!("user {} signed in, deletion canceled", user_id);
+    }
+    Ok(())
+}
+
+/// Delete users and everything they own
+pub async fn purge(conn: &impl ConnectionTrait, ids: &[i32]) -> Result<()> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+    let chats = Chat::find()
+        .select_only()
+        .column(chat::Column::Id)
+        .filter(chat::Column::OwnerId.is_in(ids.to_vec()))
+        .into_tuple::<i32>()
+        .all(conn)
+        .await?;
+    Tool::delete_many()
+        .filter(tool::Column::ChatId.is_in(chats))
+        .exec(conn)
+        .await?;
+
+    let users = User::find()
+        .filter(user::Column::Id.is_in(ids.to_vec()))
+        .all(conn)
+        .await?;
+    for user in &users {
+        login_throttle::succeed(conn, &user.name).await?;
+    }
+    User::delete_many()
+        .filter(user::Column::Id.is_in(ids.to_vec()))
+        .exec(conn)
+        .await?;
+    // written by the triggers of the cascade above
+    SyncChange::delete_many()
+        .filter(sync_change::Column::UserId.is_in(ids.to_vec()))
+        .exec(conn)
+        .await?;
+    Ok(())
+}
+
+/// Purge accounts past their grace period, run every
+/// `ACCOUNT_PURGE_INTERVAL` by `jobs`
+pub async fn purge_due(conn: &DbConn) -> Result<()> {
+    let ids = User::find()
+        .select_only()
+        .column(user::Column::Id)
+        .filter(user::Column::PurgeAt.lt(now()))
+        .into_tuple::<i32>()
+        .all(conn)
+        .await?;
+    purge(conn, &ids).await?;
+    if !ids.is_empty() {
+        tracing::info!("purged {} deleted accounts", ids.len());
+    }
+    Ok(())
+}
+
+fn now() -> i64 {
+    time::UtcDateTime::now().unix_timestamp()
+}
@@ -0,0 +1,78 @@
+//! Long-lived keys for scripts, stored hashed in the DB
+
+use anyhow::Result;
+use entity::{ApiKeyScopes, api_key, prelude::*};
+use sea_orm::{ActiveValue::Set, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+use sha2::{Digest, Sha256};
+
+use crate::config::API_KEY_TOUCH_SECS;
+
+/// Tell keys apart from PASETO tokens in the authorization header
+pub const PREFIX: &str = "llumen_";
+
+/// Return the key in plain text, it cannot be recovered later
+pub async fn create(
+    conn: &impl ConnectionTrait,
+    user_id: i32,
+    workspace_id: i32,
+    name: String,
+    scopes: ApiKeyScopes,
+) -> Result<(i32, String)> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|e| anyhow::anyhow!("Cannot generate key: {}", e))?;
+    let hex: String = bytes.iter().map(|x| format!("{:02x}", x)).collect();
+    let key = format!("{}{}", PREFIX, hex);
+
+    let res = ApiKey::insert(api_key::ActiveModel {
+        user_id: Set(user_id),
+        workspace_id: Set(Some(workspace_id)),
+        name: Set(name),
+        // enough to recognize it in a list
+        prefix: Set(key[..PREFIX.len() + 6].to_owned()),
+        key_hash: Set(hash(&key)),
+        scopes: Set(scopes),
+        created_at: Set(now()),
+        ..Default::default()
+    })
+    .exec(conn)
+    .await?;
+    Ok((res.last_insert_id, key))
+}
+
+/// Return the user, the workspace and the scopes of a key, None if unknown or
+/// revoked
+pub async fn find(
+    conn: &impl ConnectionTrait,
+    key: &str,
+) -> Result<Option<(i32, Option<i32>, ApiKeyScopes)>> {
+    let Some(model) = ApiKey::find()
+        .filter(api_key::Column::KeyHash.eq(hash(key)))
+        .one(conn)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let now = now();
+    if model
+        .last_used_at
+        .is_none_or(|x| x + API_KEY_TOUCH_SECS < now)
+    {
+        ApiKey::update(api_key::ActiveModel {
+            id: Set(model.id),
+            last_used_at: Set(Some(now)),
+            ..Default::default()
+        })
+        .exec(conn)
+        .await?;
+    }
+    Ok(Some((model.user_id, model.workspace_id, model.scopes)))
+}
+
+fn hash(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+fn now() -> i64 {
+    time::UtcDateTime::now().unix_timestamp()
+}
@@ -0,0 +1,179 @@
+//! Messages form a tree through `parent_id`, editing or regenerating a message
+//! start a sibling of it
+//!
+//! A chat show one branch, from a root to `chat.head_id`. A head that is unset
+//! or no longer exist stand for the latest message
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use entity::{chat, message, prelude::*};
+use sea_orm::{ActiveValue::Set, Condition, ConnectionTrait, QueryOrder, QuerySelect, prelude::*};
+
+/// (id, parent_id) of every message of the chat, in order of ids
+async fn tree(conn: &impl ConnectionTrait, chat_id: i32) -> Result<Vec<(i32, Option<i32>)>> {
+    Ok(Message::find()
+        .select_only()
+        .columns([message::Column::Id, message::Column::ParentId])
+        .filter(message::Column::ChatId.eq(chat_id))
+        .order_by_asc(message::Column::Id)
+        .into_tuple()
+        .all(conn)
+        .await?)
+}
+
+async fn head_id(conn: &impl ConnectionTrait, chat_id: i32) -> Result<Option<i32>> {
+    Ok(Chat::find_by_id(chat_id)
+        .one(conn)
+        .await?
+        .and_then(|x| x.head_id))
+}
+
+/// Ids of the active branch, root first
+pub async fn active(conn: &impl ConnectionTrait, chat_id: i32) -> Result<Vec<i32>> {
+    let head_id = head_id(conn, chat_id).await?;
+    let parents: HashMap<i32, Option<i32>> = tree(conn, chat_id).await?.into_iter().collect();
+
+    let mut cursor = match head_id.filter(|x| parents.contains_key(x)) {
+        Some(head) => Some(head),
+        None => parents.keys().max().copied(),
+    };
+    let mut ids = vec![];
+    while let Some(id) = cursor {
+        ids.push(id);
+        // a child always come after its parent, this also stop a cycle
+        cursor = parents.get(&id).copied().flatten().filter(|x| *x < id);
+    }
+    ids.reverse();
+    Ok(ids)
+}
+
+/// Last message of the active branch, None for an empty chat
+pub async fn head(conn: &impl ConnectionTrait, chat_id: i32) -> Result<Option<i32>> {
+    if let Some(head) = head_id(conn, chat_id).await? {
+        let exists = Message::find_by_id(head)
+            .filter(message::Column::ChatId.eq(chat_id))
+            .one(conn)
+            .await?
+            .is_some();
+        if exists {
+            return Ok(Some(head));
+        }
+    }
+    Ok(Message::find()
+        .filter(message::Column::ChatId.eq(chat_id))
+        .order_by_desc(message::Column::Id)
+        .one(conn)
+        .await?
+        .map(|x| x.id))
+}
+
+/// Make `head` the last message of the active branch, None start a new root
+pub async fn checkout(conn: &impl ConnectionTrait, chat_id: i32, head: Option<i32>) -> Result<()> {
+    Chat::update_many()
+        .col_expr(chat::Column::HeadId, Expr::value(head))
+        .filter(chat::Column::Id.eq(chat_id))
+        .exec(conn)
+        .await?;
+    Ok(())
+}
+
+/// Insert a message at the end of the active branch and move the head to it
+pub async fn append(
+    conn: &impl ConnectionTrait,
+    chat_id: i32,
+    mut message: message::ActiveModel,
+) -> Result<i32> {
+    message.chat_id = Set(chat_id);
+    message.parent_id = Set(head(conn, chat_id).await?);
+    let message_id = Message::insert(message).exec(conn).await?.last_insert_id;
+    checkout(conn, chat_id, Some(message_id)).await?;
+    Ok(message_id)
+}
+
+/// Latest message under `message_id` (itself included), where switching to
+/// its branch lands; None if the chat has no such message
+pub async fn latest_leaf(
+    conn: &impl ConnectionTrait,
+    chat_id: i32,
+    message_id: i32,
+) -> Result<Option<i32>> {
+    let tree = tree(conn, chat_id).await?;
+    if !tree.iter().any(|(id, _)| *id == message_id) {
+        return Ok(None);
+    }
+    let mut under = HashSet::from([message_id]);
+    for (id, parent) in tree {
+        if parent.is_some_and(|x| under.contains(&x)) {
+            under.insert(id);
+        }
+    }
+    Ok(under.into_iter().max())
+}
+
+/// Hand the children of a message about to be deleted to its parent, so the
+/// messages after it stay on their branch
+pub async fn detach(conn: &impl ConnectionTrait, message: &message::Model) -> Result<()> {
+    Message::update_many()
+        .col_expr(message::Column::ParentId, Expr::value(message.parent_id))
+        .filter(message::Column::ParentId.eq(message.id))
+        .exec(conn)
+        .await?;
+    Chat::update_many()
+        .col_expr(chat::Column::HeadId, Expr::value(message.parent_id))
+        .filter(chat::Column::HeadId.eq(message.id))
+        .exec(conn)
+        .await?;
+    Ok(())
+}
+
+/// Ids of the messages sharing the parent of each message, itself included
+///
+/// Only messages with alternatives are in the map
+pub async fn siblings(
+    conn: &impl ConnectionTrait,
+    messages: &[message::Model],
+) -> Result<HashMap<i32, Vec<i32>>> {
+    let parents: HashSet<_> = messages.iter().filter_map(|x| x.parent_id).collect();
+    let roots: HashSet<_> = messages
+        .iter()
+        .filter(|x| x.parent_id.is_none())
+        .map(|x| x.chat_id)
+        .collect();
+    if parents.is_empty() && roots.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let candidates: Vec<(i32, i32, Option<i32>)> = Message::find()
+        .select_only()
+        .columns([
+            message::Column::Id,
+            message::Column::ChatId,
+            message::Column::ParentId,
+        ])
+        .filter(
+            Condition::any()
+                .add(message::Column::ParentId.is_in(parents))
+                .add(
+                    Condition::all()
+                        .add(message::Column::ParentId.is_null())
+                        .add(message::Column::ChatId.is_in(roots)),
+                ),
+        )
+        .order_by_asc(message::Column::Id)
+        .into_tuple()
+        .all(conn)
+        .await?;
+
+    let mut groups: HashMap<(i32, Option<i32>), Vec<i32>> = HashMap::new();
+    for (id, chat_id, parent_id) in candidates {
+        groups.entry((chat_id, parent_id)).or_default().push(id);
+    }
+    Ok(messages
+        .iter()
+        .filter_map(|x| {
+            let group = groups.get(&(x.chat_id, x.parent_id))?;
+            (group.len() > 1).then(|| (x.id, group.clone()))
+        })
+        .collect())
+}
@@ -0,0 +1,80 @@
+//! Per-chat key-value store written by tools
+//!
+//! Identifiers like a thread id survive across turns without the model
+//! repeating them, tools read them back and prompts list them as `chat.vars`
+
+use std::collections::BTreeMap;
+
+use anyhow::{Result, bail};
+use entity::{chat_variable, prelude::*};
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, ConnectionTrait, EntityTrait, PaginatorTrait, QueryFilter,
+    sea_query::OnConflict,
+};
+use serde_json::Value;
+
+use crate::config::{CHAT_VARIABLE_MAX_BYTES, CHAT_VARIABLE_MAX_KEYS};
+
+pub async fn get(conn: &impl ConnectionTrait, chat_id: i32, key: &str) -> Result<Option<Value>> {
+    let model = ChatVariable::find_by_id((chat_id, key.to_owned()))
+        .one(conn)
+        .await?;
+    // a value that no longer parse is as good as unset
+    Ok(model.and_then(|x| serde_json::from_str(&x.value).ok()))
+}
+
+pub async fn all(conn: &impl ConnectionTrait, chat_id: i32) -> Result<BTreeMap<String, Value>> {
+    let models = ChatVariable::find()
+        .filter(chat_variable::Column::ChatId.eq(chat_id))
+        .all(conn)
+        .await?;
+    Ok(models
+        .into_iter()
+        .filter_map(|x| Some((x.key, serde_json::from_str(&x.value).ok()?)))
+        .collect())
+}
+
+/// `Value::Null` removes the variable
+pub async fn set(conn: &impl ConnectionTrait, chat_id: i32, key: &str, value: Value) -> Result<()> {
+    if value.is_null() {
+        ChatVariable::delete_by_id((chat_id, key.to_owned()))
+            .exec(conn)
+            .await?;
+        return Ok(());
+    }
+
+    let value = serde_json::to_string(&value)?;
+    if value.len() > CHAT_VARIABLE_MAX_BYTES {
+        bail!("`{}` is larger than {} bytes", key, CHAT_VARIABLE_MAX_BYTES);
+    }
+    let exists = ChatVariable::find_by_id((chat_id, key.to_owned()))
+        .one(conn)
+        .await?
+        .is_some();
+    if !exists
+        && ChatVariable::find()
+            .filter(chat_variable::Column::ChatId.eq(chat_id))
+            .count(conn)
+            .await?
+            >= CHAT_VARIABLE_MAX_KEYS
+    {
+        bail!(
+            "The chat already holds {} variables",
+            CHAT_VARIABLE_MAX_KEYS
+        );
+    }
+
+    ChatVariable::insert(chat_variable::ActiveModel {
+        chat_id: Set(chat_id),
+        key: Set(key.to_owned()),
+        value: Set(value),
+    })
+    .on_conflict(
+        OnConflict::columns([chat_variable::Column::ChatId, chat_variable::Column::Key])
+            .update_column(chat_variable::Column::Value)
+            .to_owned(),
+    )
+    .exec(conn)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,58 @@
+//! What can be told about the client of a request
+
+use std::net::{IpAddr, SocketAddr};
+
+use dotenv::var;
+use http::{HeaderMap, header};
+
+/// Peer address, or the last `X-Forwarded-For` hop behind a trusted proxy (`TRUST_PROXY`)
+///
+/// The last hop is the one the proxy appended, the ones before it come from the client
+pub fn ip(headers: &HeaderMap, addr: SocketAddr) -> IpAddr {
+    let trust_proxy = var("TRUST_PROXY").is_ok_and(|x| x == "1" || x == "true");
+    let forwarded = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .next_back()
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.split(',').next_back())
+        .and_then(|x| x.trim().parse().ok());
+    match (trust_proxy, forwarded) {
+        (true, Some(ip)) => ip,
+        _ => addr.ip(),
+    }
+}
+
+/// Browser and OS from the `User-Agent`, e.g. "Firefox on Linux"
+pub fn label(headers: &HeaderMap) -> Option<String> {
+    let agent = headers.get(header::USER_AGENT)?.to_str().ok()?;
+    // order matters, every browser claim to be Mozilla and most to be Safari
+    let browser = [
+        ("Edg/", "Edge"),
+        ("OPR/", "Opera"),
+        ("Firefox/", "Firefox"),
+        ("Chrome/", "Chrome"),
+        ("Safari/", "Safari"),
+    ]
+    .into_iter()
+    .find(|(x, _)| agent.contains(x))
+    .map(|(_, x)| x);
+    let os = [
+        ("Android", "Android"),
+        ("iPhone", "iOS"),
+        ("iPad", "iPadOS"),
+        ("Windows", "Windows"),
+        ("Mac OS X", "macOS"),
+        ("Linux", "Linux"),
+    ]
+    .into_iter()
+    .find(|(x, _)| agent.contains(x))
+    .map(|(_, x)| x);
+
+    Some(match (browser, os) {
+        (Some(browser), Some(os)) => format!("{} on {}", browser, os),
+        (Some(x), None) | (None, Some(x)) => x.to_owned(),
+        // scripts, e.g. "curl/8.5.0"
+        (None, None) => agent.chars().take(64).collect(),
+    })
+}
@@ -0,0 +1,77 @@
+//! Context size of the completions of each chat
+//!
+//! The whole history is sent on every message, a long chat end up filling the
+//! context of the model and the provider compress or refuse it. The stats tell
+//! "the bot forgot everything" apart from a bad answer
+
+use anyhow::Result;
+use entity::{context_stat, prelude::*};
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, DbConn, EntityTrait, IntoActiveModel};
+
+use crate::config::{CONTEXT_ALERT_STREAK, CONTEXT_CEILING_RATIO};
+
+/// One completion of the tool loop
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    /// None if the provider does not report usage
+    pub prompt_tokens: Option<usize>,
+    /// None until the model list is synced
+    pub context_length: Option<usize>,
+    pub truncated: bool,
+}
+
+impl Sample {
+    fn at_ceiling(&self) -> bool {
+        match (self.prompt_tokens, self.context_length) {
+            (Some(prompt), Some(context)) => {
+                prompt as f64 >= context as f64 * CONTEXT_CEILING_RATIO
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Record a completion, true when the chat just reached [`CONTEXT_ALERT_STREAK`]
+/// completions in a row at the ceiling
+pub async fn record(conn: &DbConn, chat_id: i32, sample: Sample) -> Result<bool> {
+    let now = time::UtcDateTime::now().unix_timestamp();
+    let prompt_tokens = sample.prompt_tokens.unwrap_or_default() as i64;
+    let streak_inc = sample.at_ceiling() as i32;
+
+    let Some(stat) = ContextStat::find_by_id(chat_id).one(conn).await? else {
+        ContextStat::insert(context_stat::ActiveModel {
+            chat_id: Set(chat_id),
+            completions: Set(1),
+            truncations: Set(sample.truncated as i32),
+            last_prompt_tokens: Set(prompt_tokens),
+            max_prompt_tokens: Set(prompt_tokens),
+            context_length: Set(sample.context_length.map(|x| x as i64)),
+            streak: Set(streak_inc),
+            updated_at: Set(now),
+        })
+        .exec(conn)
+        .await?;
+        return Ok(streak_inc == CONTEXT_ALERT_STREAK);
+    };
+
+    let streak = match sample.at_ceiling() {
+        true => stat.streak + 1,
+        false => 0,
+    };
+    let mut model = stat.clone().into_active_model();
+    model.completions = Set(stat.completions + 1);
+    model.truncations = Set(stat.truncations + sample.truncated as i32);
+    if sample.prompt_tokens.is_some() {
+        model.last_prompt_tokens = Set(prompt_tokens);
+        model.max_prompt_tokens = Set(stat.max_prompt_tokens.max(prompt_tokens));
+    }
+    if sample.context_length.is_some() {
+        model.context_length = Set(sample.context_length.map(|x| x as i64));
+    }
+    model.streak = Set(streak);
+    model.updated_at = Set(now);
+    model.update(conn).await?;
+
+    // once per streak, the user does not need a warning on every message
+    Ok(streak == CONTEXT_ALERT_STREAK)
+}
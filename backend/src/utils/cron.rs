@@ -0,0 +1,268 @@
+//! Five field cron expressions, `minute hour day-of-month month day-of-week`
+//!
+//! Fields take `*`, numbers, ranges `a-b`, steps `*/n` or `a-b/n` and comma
+//! separated lists of those; a day of week is 0 to 7, both 0 and 7 being
+//! Sunday. As in cron, a day matches either of the day fields when both are
+//! restricted
+
+use anyhow::{Context, Result, bail};
+use time::{Duration, OffsetDateTime, UtcOffset};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    /// day of month and day of week are `*`
+    any_day: bool,
+    any_weekday: bool,
+}
+
+/// Bits `min..=max` the field set, and whether it is `*`
+fn field(x: &str, min: u32, max: u32) -> Result<(u64, bool)> {
+    let mut bits = 0u64;
+    for part in x.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().context("malformed step")?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            bail!("step cannot be 0");
+        }
+        let (from, to) = match range {
+            "*" => (min, max),
+            x => match x.split_once('-') {
+                Some((a, b)) => (a.parse()?, b.parse()?),
+                // `5/15` run from 5 to the end, like `5-59/15`
+                None if step > 1 => (x.parse()?, max),
+                None => {
+                    let x = x.parse()?;
+                    (x, x)
+                }
+            },
+        };
+        if from < min || to > max || from > to {
+            bail!("{} is out of {}-{}", part, min, max);
+        }
+        for i in (from..=to).step_by(step as usize) {
+            bits |= 1 << i;
+        }
+    }
+    Ok((bits, x == "*"))
+}
+
+impl Cron {
+    pub fn parse(x: &str) -> Result<Self> {
+        let fields: Vec<&str> = x.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            bail!("expect 5 fields: minute hour day-of-month month day-of-week");
+        };
+        let (minutes, _) = field(minute, 0, 59).context("minute")?;
+        let (hours, _) = field(hour, 0, 23).context("hour")?;
+        let (days, any_day) = field(day, 1, 31).context("day of month")?;
+        let (months, _) = field(month, 1, 12).context("month")?;
+        let (weekdays, any_weekday) = field(weekday, 0, 7).context("day of week")?;
+        // 7 is another Sunday
+        let weekdays = (weekdays | weekdays >> 7) & 0x7f;
+        Ok(Self {
+            minutes,
+            hours: hours as u32,
+            days: days as u32,
+            months: months as u16,
+            weekdays: weekdays as u8,
+            any_day,
+            any_weekday,
+        })
+    }
+
+    fn day_matches(&self, t: OffsetDateTime) -> bool {
+        let day = self.days & (1 << t.day()) != 0;
+        let weekday = self.weekdays & (1 << t.weekday().number_days_from_sunday()) != 0;
+        let month = self.months & (1 << u8::from(t.month())) != 0;
+        month
+            && match (self.any_day, self.any_weekday) {
+                (false, false) => day || weekday,
+                _ => day && weekday,
+            }
+    }
+
+    /// First minute strictly after `after` the expression match, in the time
+    /// zone `offset` minutes ahead of UTC, unix seconds
+    ///
+    /// None if nothing match within 5 years, e.g. `0 0 31 2 *`
+    pub fn next(&self, after: i64, offset: i32) -> Option<i64> {
+        let offset = UtcOffset::from_whole_seconds(offset * 60).ok()?;
+        let start = OffsetDateTime::from_unix_timestamp(after)
+            .ok()?
+            .to_offset(offset);
+        let mut t = start.replace_second(0).ok()?.replace_nanosecond(0).ok()? + Duration::MINUTE;
+        let end = start + Duration::days(5 * 366);
+        while t < end {
+            if !self.day_matches(t) {
+                t = t.replace_time(time::Time::MIDNIGHT) + Duration::DAY;
+            } else if self.hours & (1 << t.hour()) == 0 {
+                t = t.replace_minute(0).ok()? + Duration::HOUR;
+            } else if self.minutes & (1 << t.minute()) == 0 {
+                t += Duration::MINUTE;
+            } else {
+                return Some(t.unix_timestamp());
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::{PrimitiveDateTime, macros::datetime};
+
+    use super::*;
+
+    fn unix(at: PrimitiveDateTime) -> i64 {
+        at.assume_utc().unix_timestamp()
+    }
+
+    fn next(cron: &str, after: PrimitiveDateTime) -> Option<i64> {
+        Cron::parse(cron).unwrap().next(unix(after), 0)
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        for x in [
+            "",
+            "* * * *",
+            "* * * * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * 32 * *",
+            "* * * 0 *",
+            "* * * 13 *",
+            "* * * * 8",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "1,,2 * * * *",
+            "a * * * *",
+            "-1 * * * *",
+        ] {
+            assert!(Cron::parse(x).is_err(), "{}", x);
+        }
+    }
+
+    #[test]
+    fn next_is_strictly_after() {
+        let at = datetime!(2026-10-14 08:00:30);
+        assert_eq!(
+            next("* * * * *", at),
+            Some(unix(datetime!(2026-10-14 08:01)))
+        );
+        let at = datetime!(2026-10-14 08:00);
+        assert_eq!(
+            next("0 8 * * *", at),
+            Some(unix(datetime!(2026-10-15 08:00)))
+        );
+    }
+
+    #[test]
+    fn steps_ranges_and_lists() {
+        let at = datetime!(2026-10-14 08:01);
+        assert_eq!(
+            next("*/15 * * * *", at),
+            Some(unix(datetime!(2026-10-14 08:15)))
+        );
+        let at = datetime!(2026-10-14 08:06);
+        assert_eq!(
+            next("5/15 * * * *", at),
+            Some(unix(datetime!(2026-10-14 08:20)))
+        );
+        assert_eq!(
+            next("0,30 * * * *", at),
+            Some(unix(datetime!(2026-10-14 08:30)))
+        );
+        let at = datetime!(2026-10-14 10:00);
+        assert_eq!(
+            next("0 9-17/4 * * *", at),
+            Some(unix(datetime!(2026-10-14 13:00)))
+        );
+        let at = datetime!(2026-10-14 17:00);
+        assert_eq!(
+            next("0 9-17/4 * * *", at),
+            Some(unix(datetime!(2026-10-15 09:00)))
+        );
+    }
+
+    #[test]
+    fn crosses_months_and_years() {
+        let at = datetime!(2026-10-14 08:00);
+        assert_eq!(
+            next("0 0 1 * *", at),
+            Some(unix(datetime!(2026-11-01 00:00)))
+        );
+        assert_eq!(
+            next("0 0 1 1 *", at),
+            Some(unix(datetime!(2027-01-01 00:00)))
+        );
+        assert_eq!(
+            next("0 0 29 2 *", at),
+            Some(unix(datetime!(2028-02-29 00:00)))
+        );
+    }
+
+    #[test]
+    fn seven_is_sunday() {
+        // a Wednesday, the next Sunday is the 18th
+        let at = datetime!(2026-10-14 08:00);
+        let sunday = Some(unix(datetime!(2026-10-18 00:00)));
+        assert_eq!(next("0 0 * * 0", at), sunday);
+        assert_eq!(next("0 0 * * 7", at), sunday);
+        assert_eq!(
+            next("0 0 * * 6-7", at),
+            Some(unix(datetime!(2026-10-17 00:00)))
+        );
+        assert_eq!(
+            Cron::parse("0 0 * * 0").unwrap(),
+            Cron::parse("0 0 * * 7").unwrap()
+        );
+    }
+
+    #[test]
+    fn either_day_field_matches_when_both_are_restricted() {
+        let at = datetime!(2026-10-14 08:00);
+        assert_eq!(
+            next("0 0 15 * 0", at),
+            Some(unix(datetime!(2026-10-15 00:00)))
+        );
+        let at = datetime!(2026-10-15 00:00);
+        assert_eq!(
+            next("0 0 15 * 0", at),
+            Some(unix(datetime!(2026-10-18 00:00)))
+        );
+        // a restricted field and `*` must both match
+        assert_eq!(
+            next("0 0 * 11 0", at),
+            Some(unix(datetime!(2026-11-01 00:00)))
+        );
+    }
+
+    #[test]
+    fn impossible_dates_never_run() {
+        assert_eq!(next("0 0 31 2 *", datetime!(2026-10-14 08:00)), None);
+        assert_eq!(next("0 0 30 2 *", datetime!(2026-10-14 08:00)), None);
+    }
+
+    #[test]
+    fn runs_in_the_time_zone_of_the_offset() {
+        let cron = Cron::parse("0 8 * * *").unwrap();
+        // 08:00 in UTC+8 is midnight UTC
+        let at = unix(datetime!(2026-10-14 00:00));
+        assert_eq!(cron.next(at, 480), Some(unix(datetime!(2026-10-15 00:00))));
+        // and 13:00 UTC in UTC-5
+        assert_eq!(cron.next(at, -300), Some(unix(datetime!(2026-10-14 13:00))));
+        // the day of a weekday is the one of the offset: Sunday 00:30 in
+        // UTC+8 is still Saturday in UTC
+        let cron = Cron::parse("30 0 * * 0").unwrap();
+        assert_eq!(cron.next(at, 480), Some(unix(datetime!(2026-10-17 16:30))));
+    }
+}
@@ -0,0 +1,76 @@
+//! One-time tokens mailed to prove an address belong to the user, stored hashed
+//!
+//! Users with an unverified address cannot send messages while mails are
+//! configured, otherwise nothing can be verified and the check is skipped
+
+use anyhow::Result;
+use entity::{email_verification, prelude::*};
+use sea_orm::{ActiveValue::Set, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+use sha2::{Digest, Sha256};
+
+use crate::{AppState, config::EMAIL_VERIFICATION_SECS, notify::mailer::Mail};
+
+/// Replace pending tokens of the user, only the latest mail work
+pub async fn create(conn: &impl ConnectionTrait, user_id: i32, email: &str) -> Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|e| anyhow::anyhow!("Cannot generate token: {}", e))?;
+    let token: String = bytes.iter().map(|x| format!("{:02x}", x)).collect();
+
+    EmailVerification::delete_many()
+        .filter(email_verification::Column::UserId.eq(user_id))
+        .exec(conn)
+        .await?;
+    EmailVerification::insert(email_verification::ActiveModel {
+        user_id: Set(user_id),
+        token_hash: Set(hash(&token)),
+        email: Set(email.to_owned()),
+        expires_at: Set(now() + EMAIL_VERIFICATION_SECS),
+        ..Default::default()
+    })
+    .exec(conn)
+    .await?;
+    Ok(token)
+}
+
+/// Return the user and the address the token was mailed to, None if unknown
+/// or expired
+///
+/// The token is deleted either way
+pub async fn consume(conn: &impl ConnectionTrait, token: &str) -> Result<Option<(i32, String)>> {
+    let Some(model) = EmailVerification::find()
+        .filter(email_verification::Column::TokenHash.eq(hash(token)))
+        .one(conn)
+        .await?
+    else {
+        return Ok(None);
+    };
+    EmailVerification::delete_by_id(model.id).exec(conn).await?;
+    Ok((model.expires_at >= now()).then_some((model.user_id, model.email)))
+}
+
+/// Queue the mail of the link, the caller should commit `token` first
+pub async fn send(app: &AppState, user_id: i32, email: String, token: String) {
+    let Some(mailer) = app.mailer.as_ref() else {
+        return;
+    };
+    let mail = Mail::new("Verify your email")
+        .text(format!(
+            "Confirm this address for your llumen account by opening the link below \
+             within {} hours.",
+            EMAIL_VERIFICATION_SECS / 3600
+        ))
+        .link("Verify email", mailer.link(&format!("/verify#{}", token)))
+        .text("If you did not expect this mail, ignore it.");
+    let mail = mailer.task(email, &mail);
+    if let Err(err) = app.jobs.push(&app.conn, mail).await {
+        tracing::warn!("cannot mail verification link to user {}: {}", user_id, err);
+    }
+}
+
+fn hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+fn now() -> i64 {
+    time::UtcDateTime::now().unix_timestamp()
+}
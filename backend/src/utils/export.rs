@@ -0,0 +1,306 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+use entity::{ChunkKind, Generation, MessageKind, chat, prelude::*};
+use sea_orm::{ColumnTrait, DbConn, QueryFilter, QueryOrder};
+use serde::{Serialize, Serializer};
+use time::{UtcDateTime, format_description::well_known::Rfc3339};
+
+use crate::utils::{
+    branch,
+    markdown::{self, escape},
+    message::visible_messages,
+};
+
+/// Where an exported document came from
+#[derive(Debug, Clone, Serialize)]
+pub struct Provenance {
+    pub instance_id: String,
+    pub chat_id: i32,
+    pub title: Option<String>,
+    /// unix seconds
+    pub exported_at: u32,
+    /// distinct models that generated the transcript, in order of first use
+    pub models: Vec<String>,
+    pub prompt_versions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Entry {
+    #[serde(rename = "role", serialize_with = "serialize_role")]
+    pub kind: MessageKind,
+    /// unix seconds, 0 for messages sent before it was recorded
+    pub created_at: i64,
+    /// text and tool calls in the order they were streamed
+    pub parts: Vec<Part>,
+    pub generation: Option<Generation>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Part {
+    Text {
+        content: String,
+    },
+    ToolCall {
+        name: String,
+        args: String,
+        result: String,
+    },
+}
+
+/// The active branch of a chat as seen by its viewer, ready to be rendered
+/// into a document
+#[derive(Debug, Clone, Serialize)]
+pub struct Transcript {
+    pub provenance: Provenance,
+    #[serde(rename = "messages")]
+    pub entries: Vec<Entry>,
+}
+
+impl Transcript {
+    pub async fn load(
+        conn: &DbConn,
+        instance_id: String,
+        chat: &chat::Model,
+        viewer_id: i32,
+    ) -> Result<Self> {
+        let ids = branch::active(conn, chat.id).await?;
+        let messages = visible_messages(chat.id, viewer_id)
+            .filter(entity::message::Column::Id.is_in(ids))
+            .order_by_asc(entity::message::Column::Id)
+            .find_with_related(Chunk)
+            .all(conn)
+            .await?;
+
+        let mut entries = Vec::with_capacity(messages.len());
+        for (message, mut chunks) in messages {
+            if message.kind == MessageKind::Hidden {
+                continue;
+            }
+            chunks.sort_by_key(|x| x.id);
+            let mut parts = Vec::with_capacity(chunks.len());
+            for chunk in chunks {
+                match chunk.kind {
+                    ChunkKind::Text => parts.push(Part::Text {
+                        content: chunk.content,
+                    }),
+                    ChunkKind::Reasoning => {}
+                    ChunkKind::ToolCall => {
+                        let tool_call = chunk.as_tool_call()?;
+                        parts.push(Part::ToolCall {
+                            name: tool_call.name,
+                            args: tool_call.args,
+                            result: tool_call.content,
+                        });
+                    }
+                }
+            }
+            entries.push(Entry {
+                kind: message.kind,
+                created_at: message.created_at,
+                parts,
+                generation: message.get_generation(),
+            });
+        }
+
+        let mut models = Vec::new();
+        let mut prompt_versions = Vec::new();
+        for generation in entries.iter().filter_map(|x| x.generation.as_ref()) {
+            if !models.contains(&generation.model_id) {
+                models.push(generation.model_id.clone());
+            }
+            if !prompt_versions.contains(&generation.prompt_version) {
+                prompt_versions.push(generation.prompt_version.clone());
+            }
+        }
+
+        Ok(Self {
+            provenance: Provenance {
+                instance_id,
+                chat_id: chat.id,
+                title: chat.title.clone(),
+                exported_at: UtcDateTime::now().unix_timestamp() as u32,
+                models,
+                prompt_versions,
+            },
+            entries,
+        })
+    }
+
+    /// Markdown with the provenance as YAML front matter
+    pub fn markdown(&self) -> String {
+        let p = &self.provenance;
+        let mut res = String::new();
+
+        // JSON strings and arrays are valid YAML
+        writeln!(res, "---").ok();
+        if let Some(title) = &p.title {
+            writeln!(res, "title: {}", json(title)).ok();
+        }
+        writeln!(res, "chat_id: {}", p.chat_id).ok();
+        writeln!(res, "instance_id: {}", json(&p.instance_id)).ok();
+        writeln!(res, "exported_at: {}", p.exported_at).ok();
+        writeln!(res, "models: {}", json(&p.models)).ok();
+        writeln!(res, "prompt_versions: {}", json(&p.prompt_versions)).ok();
+        writeln!(res, "---").ok();
+
+        for entry in &self.entries {
+            match datetime(entry.created_at) {
+                Some(at) => writeln!(res, "\n## {} ({})\n", role(entry.kind), at).ok(),
+                None => writeln!(res, "\n## {}\n", role(entry.kind)).ok(),
+            };
+            if let Some(generation) = &entry.generation {
+                writeln!(
+                    res,
+                    "<!-- model: {}, prompt_version: {}, generated_at: {} -->\n",
+                    generation.model_id, generation.prompt_version, generation.created_at
+                )
+                .ok();
+            }
+            for part in &entry.parts {
+                match part {
+                    Part::Text { content } => writeln!(res, "{}\n", content).ok(),
+                    Part::ToolCall { name, args, result } => writeln!(
+                        res,
+                        "**Tool call `{}`**\n\n{}\n\n{}\n",
+                        name,
+                        fence("json", args),
+                        fence("", result)
+                    )
+                    .ok(),
+                };
+            }
+        }
+        res
+    }
+
+    /// The transcript and its provenance as pretty-printed JSON
+    pub fn json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Standalone HTML with the provenance in `<meta>` tags
+    pub fn html(&self) -> String {
+        let p = &self.provenance;
+        let mut res = String::new();
+
+        writeln!(
+            res,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">"
+        )
+        .ok();
+        if let Some(title) = &p.title {
+            writeln!(res, "<title>{}</title>", escape(title)).ok();
+        }
+        let metas = [
+            ("chat-id", p.chat_id.to_string()),
+            ("instance-id", p.instance_id.clone()),
+            ("exported-at", p.exported_at.to_string()),
+            ("models", p.models.join(",")),
+            ("prompt-versions", p.prompt_versions.join(",")),
+        ];
+        for (name, content) in metas {
+            writeln!(
+                res,
+                "<meta name=\"provenance:{}\" content=\"{}\">",
+                name,
+                escape(&content)
+            )
+            .ok();
+        }
+        writeln!(res, "<style>{}</style>\n</head>\n<body>", STYLE).ok();
+
+        for entry in &self.entries {
+            write!(
+                res,
+                "<article data-role=\"{}\" data-created-at=\"{}\"",
+                role(entry.kind),
+                entry.created_at
+            )
+            .ok();
+            if let Some(generation) = &entry.generation {
+                write!(
+                    res,
+                    " data-model=\"{}\" data-prompt-version=\"{}\" data-generated-at=\"{}\"",
+                    escape(&generation.model_id),
+                    escape(&generation.prompt_version),
+                    generation.created_at
+                )
+                .ok();
+            }
+            writeln!(res, ">\n<h2>{}</h2>", role(entry.kind)).ok();
+            for part in &entry.parts {
+                match part {
+                    Part::Text { content } => write!(res, "{}", markdown::to_html(content)).ok(),
+                    Part::ToolCall { name, args, result } => writeln!(
+                        res,
+                        "<details>\n<summary>Tool call <code>{}</code></summary>\n<pre>{}</pre>\n<pre>{}</pre>\n</details>",
+                        escape(name),
+                        escape(args),
+                        escape(result)
+                    )
+                    .ok(),
+                };
+            }
+            writeln!(res, "</article>").ok();
+        }
+        writeln!(
+            res,
+            "<footer>Exported from instance {} at {}</footer>\n</body>\n</html>",
+            escape(&p.instance_id),
+            p.exported_at
+        )
+        .ok();
+        res
+    }
+}
+
+const STYLE: &str = "\
+body { font-family: sans-serif; max-width: 48rem; margin: auto; line-height: 1.5; }
+article { border-bottom: 1px solid #ddd; padding: 0.5rem 0; }
+article[data-role=User] h2 { color: #2563eb; }
+h2 { font-size: 1rem; }
+pre { background: #f5f5f5; padding: 0.75rem; overflow-x: auto; white-space: pre-wrap; }
+code { font-family: monospace; background: #f5f5f5; }
+img { max-width: 100%; }
+footer { color: #888; font-size: 0.75rem; margin-top: 1rem; }
+";
+
+fn role(kind: MessageKind) -> &'static str {
+    match kind {
+        MessageKind::User => "User",
+        MessageKind::Assistant => "Assistant",
+        MessageKind::Marker | MessageKind::Hidden => "System",
+    }
+}
+
+fn serialize_role<S: Serializer>(kind: &MessageKind, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(role(*kind))
+}
+
+/// None when unknown
+fn datetime(unix: i64) -> Option<String> {
+    match unix {
+        0 => None,
+        unix => UtcDateTime::from_unix_timestamp(unix)
+            .ok()?
+            .format(&Rfc3339)
+            .ok(),
+    }
+}
+
+/// Code block whose fence outlasts every run of backticks in `content`
+fn fence(lang: &str, content: &str) -> String {
+    let longest = content
+        .split(|x| x != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, lang, content, fence)
+}
+
+fn json(x: &impl Serialize) -> String {
+    serde_json::to_string(x).unwrap_or_default()
+}
@@ -0,0 +1,58 @@
+//! Paragraphs of the main part of a DOCX file, a section per heading
+
+use anyhow::{Context, Result};
+use xml::reader::{EventReader, XmlEvent};
+
+use super::{Section, zip};
+
+pub fn extract(data: &[u8], limit: usize) -> Result<Vec<Section>> {
+    let xml = zip::entry(data, "word/document.xml", limit)?.context("not a DOCX file")?;
+
+    let mut sections = vec![Section::default()];
+    let mut paragraph = String::new();
+    let mut heading = false;
+    let mut in_text = false;
+    for event in EventReader::new(&xml[..]) {
+        match event? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } if name.prefix.as_deref() == Some("w") => match name.local_name.as_str() {
+                "p" => {
+                    paragraph.clear();
+                    heading = false;
+                }
+                "pStyle" => {
+                    heading = attributes.iter().any(|x| {
+                        x.name.local_name == "val"
+                            && (x.value.starts_with("Heading") || x.value == "Title")
+                    });
+                }
+                "t" => in_text = true,
+                "tab" => paragraph.push('\t'),
+                "br" | "cr" => paragraph.push('\n'),
+                _ => {}
+            },
+            XmlEvent::EndElement { name } if name.prefix.as_deref() == Some("w") => {
+                match name.local_name.as_str() {
+                    "t" => in_text = false,
+                    "p" => {
+                        let text = paragraph.trim();
+                        if heading && !text.is_empty() {
+                            sections.push(Section {
+                                label: Some(text.to_owned()),
+                                text: String::new(),
+                            });
+                        }
+                        let section = sections.last_mut().unwrap();
+                        section.text.push_str(text);
+                        section.text.push('\n');
+                    }
+                    _ => {}
+                }
+            }
+            XmlEvent::Characters(x) | XmlEvent::Whitespace(x) if in_text => paragraph.push_str(&x),
+            _ => {}
+        }
+    }
+    Ok(sections)
+}
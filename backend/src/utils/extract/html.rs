@@ -0,0 +1,163 @@
+//! Visible text of an HTML page, a section per `h1` to `h3`
+
+use super::Section;
+
+/// Elements whose content is not text
+const SKIPPED: [&str; 6] = ["script", "style", "head", "noscript", "template", "svg"];
+/// Elements that start a line
+const BLOCKS: [&str; 24] = [
+    "p",
+    "div",
+    "br",
+    "li",
+    "tr",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "section",
+    "article",
+    "header",
+    "footer",
+    "table",
+    "ul",
+    "ol",
+    "pre",
+    "blockquote",
+    "hr",
+    "dt",
+    "dd",
+    "figcaption",
+];
+
+pub fn extract(html: &str) -> Vec<Section> {
+    let mut sections = vec![Section::default()];
+    // text of the heading being read, a section starts at its end
+    let mut heading: Option<String> = None;
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(open) = rest.find('<') else {
+            push_text(&mut sections, &mut heading, rest);
+            break;
+        };
+        push_text(&mut sections, &mut heading, &rest[..open]);
+        rest = &rest[open..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |x| &comment[x + 3..]);
+            continue;
+        }
+        let Some(close) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[1..close];
+        rest = &rest[close + 1..];
+        let closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|x| x.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+
+        if !closing && SKIPPED.contains(&name.as_str()) && !tag.ends_with('/') {
+            let end = format!("</{}", name);
+            rest = find_ignore_case(rest, &end).map_or("", |x| {
+                rest[x..].find('>').map_or("", |y| &rest[x + y + 1..])
+            });
+            continue;
+        }
+        match name.as_str() {
+            "h1" | "h2" | "h3" if !closing => heading = Some(String::new()),
+            "h1" | "h2" | "h3" => {
+                if let Some(text) = heading.take() {
+                    let text = collapse(&text);
+                    sections.push(Section {
+                        label: Some(text.clone()).filter(|x| !x.is_empty()),
+                        text: format!("{}\n", text),
+                    });
+                }
+            }
+            "td" | "th" => push_text(&mut sections, &mut heading, " "),
+            x if BLOCKS.contains(&x) => push_text(&mut sections, &mut heading, "\n"),
+            _ => {}
+        }
+    }
+    for section in &mut sections {
+        section.text = section
+            .text
+            .lines()
+            .map(collapse)
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+    sections
+}
+
+fn push_text(sections: &mut [Section], heading: &mut Option<String>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    let text = decode_entities(text);
+    match heading {
+        Some(heading) => heading.push_str(&text),
+        None => sections.last_mut().unwrap().text.push_str(&text),
+    }
+}
+
+/// Runs of blanks as one space
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .to_ascii_lowercase()
+        .find(&needle.to_ascii_lowercase())
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_owned();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('&') {
+        out.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let decoded = rest[1..].find(';').filter(|x| *x <= 10).and_then(|end| {
+            let entity = &rest[1..end + 1];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => match entity.strip_prefix('#') {
+                    Some(x) => match x.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => x.parse().ok(),
+                    }
+                    .and_then(char::from_u32),
+                    None => None,
+                },
+            };
+            c.map(|c| (c, end + 2))
+        });
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
@@ -0,0 +1,86 @@
+//! DEFLATE streams of PDF files and of the entries of DOCX files
+
+use anyhow::{Result, bail};
+use miniz_oxide::inflate::{self, TINFLStatus};
+
+/// A zlib stream, as the `FlateDecode` filter of PDF; the checksum is not
+/// checked
+pub fn zlib(data: &[u8], limit: usize) -> Result<Vec<u8>> {
+    if data.len() < 2 || (u16::from(data[0]) << 8 | u16::from(data[1])) % 31 != 0 {
+        bail!("not a zlib stream");
+    }
+    inflate(&data[2..], limit)
+}
+
+/// A raw DEFLATE stream, bytes after its last block are ignored; fails past
+/// `limit` bytes of output
+pub fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>> {
+    match inflate::decompress_to_vec_with_limit(data, limit) {
+        Ok(out) => Ok(out),
+        Err(err) if err.status == TINFLStatus::HasMoreOutput => {
+            bail!("inflated past {} bytes", limit)
+        }
+        Err(err) => bail!("malformed stream: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{
+        Compression,
+        write::{DeflateEncoder, ZlibEncoder},
+    };
+
+    use super::*;
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn round_trips() {
+        let data = b"BT /F1 12 Tf (hello) Tj ET\n".repeat(5000);
+        assert_eq!(inflate(&deflate(&data), data.len()).unwrap(), data);
+        assert_eq!(inflate(&deflate(b""), 0).unwrap(), b"");
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&data).unwrap();
+        assert_eq!(zlib(&encoder.finish().unwrap(), data.len()).unwrap(), data);
+    }
+
+    #[test]
+    fn trailing_bytes_are_ignored() {
+        let mut stream = deflate(b"hello");
+        stream.extend(b"\r\nendstream");
+        assert_eq!(inflate(&stream, 100).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn truncated_streams_fail() {
+        let stream = deflate(&b"0123456789abcdef".repeat(1000));
+        for len in 0..stream.len() {
+            assert!(inflate(&stream[..len], usize::MAX).is_err(), "{}", len);
+        }
+    }
+
+    #[test]
+    fn garbage_fails() {
+        assert!(inflate(&[0xff; 64], 1 << 20).is_err());
+        assert!(zlib(b"", 1 << 20).is_err());
+        assert!(zlib(b"\x78", 1 << 20).is_err());
+        assert!(zlib(b"not zlib at all", 1 << 20).is_err());
+    }
+
+    #[test]
+    fn output_past_the_limit_fails() {
+        // 64 MiB of zeroes in a few KiB
+        let bomb = deflate(&vec![0; 64 << 20]);
+        assert!(bomb.len() < 128 << 10);
+        let err = inflate(&bomb, 1 << 20).unwrap_err();
+        assert!(err.to_string().contains("inflated past"));
+    }
+}
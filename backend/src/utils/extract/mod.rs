@@ -0,0 +1,127 @@
+//! Text of uploaded documents, for the knowledge base and the files attached
+//! to a message
+//!
+//! PDF, DOCX and HTML files are read by hand, the parsers here cover the
+//! common files of office suites and browsers rather than the whole formats.
+//! Their text comes split in sections: the pages of a PDF, the headings of a
+//! DOCX or HTML file. Parsing runs on the blocking pool, a large file does
+//! not hold up the requests being served
+
+use anyhow::{Context, Result, bail};
+
+use crate::config::EXTRACT_MAX_INFLATED_BYTES;
+
+mod docx;
+mod html;
+mod inflate;
+mod pdf;
+mod zip;
+
+/// Part of a document, with the page or heading it is under
+#[derive(Debug, Default, Clone)]
+pub struct Section {
+    pub label: Option<String>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Html,
+    Pdf,
+    Docx,
+}
+
+/// Format of a file, None if its text cannot be read
+///
+/// The content type comes from the browser, the name settles the ambiguous
+/// ones
+pub fn format(content_type: &str, name: &str) -> Option<Format> {
+    let name = name.to_lowercase();
+    let content_type = content_type.split(';').next().unwrap_or_default().trim();
+    match content_type {
+        "text/html" | "application/xhtml+xml" => Some(Format::Html),
+        "application/pdf" => Some(Format::Pdf),
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document" => {
+            Some(Format::Docx)
+        }
+        "application/zip" | "application/octet-stream" if name.ends_with(".docx") => {
+            Some(Format::Docx)
+        }
+        "application/octet-stream" if name.ends_with(".pdf") => Some(Format::Pdf),
+        _ if name.ends_with(".html") || name.ends_with(".htm") => Some(Format::Html),
+        x if x.starts_with("text/") => Some(Format::Text),
+        _ => None,
+    }
+}
+
+/// Sections of the file, on the blocking pool
+pub async fn extract(format: Format, data: Vec<u8>) -> Result<Vec<Section>> {
+    tokio::task::spawn_blocking(move || extract_blocking(format, &data))
+        .await
+        .context("the parser panicked")?
+}
+
+fn extract_blocking(format: Format, data: &[u8]) -> Result<Vec<Section>> {
+    let limit = EXTRACT_MAX_INFLATED_BYTES;
+    let sections = match format {
+        Format::Text => {
+            let text = String::from_utf8_lossy(data);
+            // a page saved as text/plain
+            let head = text.trim_start().get(..15).unwrap_or_default();
+            if head.eq_ignore_ascii_case("<!doctype html>") || head.starts_with("<html") {
+                html::extract(&text)
+            } else {
+                vec![Section {
+                    label: None,
+                    text: text.into_owned(),
+                }]
+            }
+        }
+        Format::Html => html::extract(&String::from_utf8_lossy(data)),
+        Format::Pdf => pdf::extract(data, limit)?,
+        Format::Docx => docx::extract(data, limit)?,
+    };
+    let sections: Vec<_> = sections
+        .into_iter()
+        .map(|x| Section {
+            label: x.label,
+            text: normalize(&x.text),
+        })
+        .filter(|x| !x.text.is_empty())
+        .collect();
+    if sections.is_empty() {
+        bail!("no text found in the file");
+    }
+    Ok(sections)
+}
+
+/// Lines trimmed of trailing blanks, at most one empty line in a row
+fn normalize(text: &str) -> String {
+    let mut out = String::new();
+    let mut blank = 0;
+    for line in text.lines().map(str::trim_end) {
+        if line.is_empty() {
+            blank += 1;
+            continue;
+        }
+        if !out.is_empty() {
+            out.push_str(if blank > 0 { "\n\n" } else { "\n" });
+        }
+        out.push_str(line);
+        blank = 0;
+    }
+    out
+}
+
+/// The whole text, labels as lines of their own
+pub fn join(sections: &[Section]) -> String {
+    sections
+        .iter()
+        .map(|x| match &x.label {
+            Some(label) => format!("[{}]\n{}", label, x.text),
+            None => x.text.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
@@ -0,0 +1,914 @@
+//! Text of the pages of a PDF file, a section per page
+//!
+//! Objects are read wherever they are in the file, object streams included,
+//! the cross-reference table is not used. Text is decoded by the `ToUnicode`
+//! map of its font when it has one, as Latin-1 otherwise. Encrypted files and
+//! filters other than `FlateDecode` are not supported, scanned pages have no
+//! text
+
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    sync::LazyLock,
+};
+
+use anyhow::{Result, bail};
+use regex::bytes::Regex;
+
+use super::{Section, inflate};
+
+static OBJ: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(\d+)\s+\d+\s+obj\b").unwrap());
+
+/// Nesting of arrays and dictionaries read at most
+const MAX_DEPTH: usize = 32;
+/// Codes the ranges of a `ToUnicode` map define at most, together
+const MAX_CODES: usize = 1 << 18;
+
+#[derive(Debug, Clone)]
+enum Object {
+    Null,
+    Bool,
+    Number(f64),
+    Name(String),
+    Str(Vec<u8>),
+    Array(Vec<Object>),
+    Dict(Dict),
+    Ref(u32),
+    /// An operator of a content stream, or a keyword
+    Op(Vec<u8>),
+}
+
+type Dict = HashMap<String, Object>;
+
+impl Object {
+    fn dict(&self) -> Option<&Dict> {
+        match self {
+            Object::Dict(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> Option<&str> {
+        match self {
+            Object::Name(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn number(&self) -> Option<f64> {
+        match self {
+            Object::Number(x) => Some(*x),
+            _ => None,
+        }
+    }
+}
+
+struct Pdf<'a> {
+    /// Dictionary or value of each object, with the raw bytes of its stream
+    objects: HashMap<u32, (Object, Option<&'a [u8]>)>,
+    /// Objects read from object streams, they have no stream of their own
+    packed: HashMap<u32, Object>,
+    /// Bytes the streams decode to at most, together since a stream can be
+    /// referenced many times
+    limit: usize,
+    decoded: Cell<usize>,
+}
+
+pub fn extract(data: &[u8], limit: usize) -> Result<Vec<Section>> {
+    if data.windows(8).any(|x| x == b"/Encrypt") {
+        bail!("encrypted PDF files are not supported");
+    }
+    let pdf = Pdf::parse(data, limit);
+    let pages = pdf.pages();
+    if pages.is_empty() {
+        bail!("no page found");
+    }
+    Ok(pages
+        .into_iter()
+        .enumerate()
+        .map(|(i, (page, resources))| Section {
+            label: Some(format!("page {}", i + 1)),
+            text: pdf.page_text(page, resources),
+        })
+        .collect())
+}
+
+impl<'a> Pdf<'a> {
+    fn parse(data: &'a [u8], limit: usize) -> Self {
+        let mut objects = HashMap::new();
+        for cap in OBJ.captures_iter(data) {
+            let Some(id) = std::str::from_utf8(&cap[1])
+                .ok()
+                .and_then(|x| x.parse().ok())
+            else {
+                continue;
+            };
+            let mut lexer = Lexer {
+                data,
+                pos: cap.get(0).unwrap().end(),
+            };
+            let Some(object) = lexer.object(0) else {
+                continue;
+            };
+            lexer.skip();
+            let stream = lexer.stream(&object);
+            // later objects are updates of earlier ones
+            objects.insert(id, (object, stream));
+        }
+        let mut pdf = Self {
+            objects,
+            packed: HashMap::new(),
+            limit,
+            decoded: Cell::new(0),
+        };
+        pdf.unpack();
+        pdf
+    }
+
+    /// Read the objects of the object streams
+    fn unpack(&mut self) {
+        let mut packed = HashMap::new();
+        for (object, stream) in self.objects.values() {
+            let Some(dict) = object.dict() else {
+                continue;
+            };
+            if dict.get("Type").and_then(Object::name) != Some("ObjStm") {
+                continue;
+            }
+            let Some(data) = stream.and_then(|x| self.decode(dict, x)) else {
+                continue;
+            };
+            let count = dict.get("N").and_then(Object::number).unwrap_or(0.0) as usize;
+            let first = dict.get("First").and_then(Object::number).unwrap_or(0.0) as usize;
+            let mut header = Lexer {
+                data: &data,
+                pos: 0,
+            };
+            let mut entries = vec![];
+            for _ in 0..count {
+                let (Some(Object::Number(id)), Some(Object::Number(offset))) =
+                    (header.object(0), header.object(0))
+                else {
+                    break;
+                };
+                entries.push((id as u32, first.saturating_add(offset as usize)));
+            }
+            for (id, offset) in entries {
+                let mut lexer = Lexer {
+                    data: &data,
+                    pos: offset,
+                };
+                if let Some(object) = lexer.object(0) {
+                    packed.insert(id, object);
+                }
+            }
+        }
+        self.packed = packed;
+    }
+
+    fn get(&self, id: u32) -> Option<&Object> {
+        match self.objects.get(&id) {
+            Some((object, _)) => Some(object),
+            None => self.packed.get(&id),
+        }
+    }
+
+    /// The object a reference points to, the object itself otherwise
+    fn resolve<'b>(&'b self, mut object: &'b Object) -> &'b Object {
+        for _ in 0..MAX_DEPTH {
+            match object {
+                Object::Ref(id) => match self.get(*id) {
+                    Some(x) => object = x,
+                    None => return &Object::Null,
+                },
+                _ => return object,
+            }
+        }
+        &Object::Null
+    }
+
+    fn dict<'b>(&'b self, object: Option<&'b Object>) -> Option<&'b Dict> {
+        object.map(|x| self.resolve(x)).and_then(Object::dict)
+    }
+
+    /// Decoded stream of a referenced object
+    fn stream(&self, object: &Object) -> Option<Vec<u8>> {
+        let Object::Ref(id) = object else {
+            return None;
+        };
+        let (object, stream) = self.objects.get(id)?;
+        self.decode(object.dict()?, stream.as_ref()?)
+    }
+
+    /// Apply the filters of the stream, None if one is not supported
+    fn decode(&self, dict: &Dict, raw: &[u8]) -> Option<Vec<u8>> {
+        let filters = match dict.get("Filter").map(|x| self.resolve(x)) {
+            None | Some(Object::Null) => vec![],
+            Some(Object::Name(x)) => vec![x.as_str()],
+            Some(Object::Array(x)) => x.iter().filter_map(Object::name).collect(),
+            Some(_) => return None,
+        };
+        let budget = self.limit.saturating_sub(self.decoded.get());
+        if raw.len() > budget {
+            return None;
+        }
+        let mut data = raw.to_vec();
+        for filter in filters {
+            match filter {
+                "FlateDecode" | "Fl" => data = inflate::zlib(&data, budget).ok()?,
+                _ => return None,
+            }
+        }
+        self.decoded.set(self.decoded.get() + data.len());
+        Some(data)
+    }
+
+    /// Pages in order with their resources, inherited from the page tree
+    fn pages(&self) -> Vec<(&Dict, Option<&Dict>)> {
+        let catalog = self
+            .objects
+            .values()
+            .map(|(x, _)| x)
+            .chain(self.packed.values())
+            .filter_map(Object::dict)
+            .find(|x| x.get("Type").and_then(Object::name) == Some("Catalog"));
+        let mut pages = vec![];
+        if let Some(root) = self.dict(catalog.and_then(|x| x.get("Pages"))) {
+            self.walk(root, None, &mut pages, &mut HashSet::new(), 0);
+        }
+        if pages.is_empty() {
+            // no usable page tree, pages in the order of their ids
+            let mut ids: Vec<u32> = self
+                .objects
+                .keys()
+                .chain(self.packed.keys())
+                .copied()
+                .collect();
+            ids.sort_unstable();
+            ids.dedup();
+            for id in ids {
+                let Some(page) = self.get(id).and_then(Object::dict) else {
+                    continue;
+                };
+                if page.get("Type").and_then(Object::name) == Some("Page") {
+                    pages.push((page, self.dict(page.get("Resources"))));
+                }
+            }
+        }
+        pages
+    }
+
+    fn walk<'b>(
+        &'b self,
+        node: &'b Dict,
+        resources: Option<&'b Dict>,
+        pages: &mut Vec<(&'b Dict, Option<&'b Dict>)>,
+        seen: &mut HashSet<*const Dict>,
+        depth: usize,
+    ) {
+        if depth > MAX_DEPTH || !seen.insert(node as *const Dict) {
+            return;
+        }
+        let resources = self.dict(node.get("Resources")).or(resources);
+        match node.get("Kids").map(|x| self.resolve(x)) {
+            Some(Object::Array(kids)) => {
+                for kid in kids {
+                    if let Some(kid) = self.dict(Some(kid)) {
+                        self.walk(kid, resources, pages, seen, depth + 1);
+                    }
+                }
+            }
+            _ => pages.push((node, resources)),
+        }
+    }
+
+    fn page_text(&self, page: &Dict, resources: Option<&Dict>) -> String {
+        let mut fonts = HashMap::new();
+        if let Some(list) = self.dict(resources.and_then(|x| x.get("Font"))) {
+            for (name, font) in list {
+                let cmap = self
+                    .dict(Some(font))
+                    .and_then(|x| x.get("ToUnicode"))
+                    .and_then(|x| self.stream(x))
+                    .map(|x| CMap::parse(&x));
+                if let Some(cmap) = cmap {
+                    fonts.insert(name.as_str(), cmap);
+                }
+            }
+        }
+
+        let mut content = vec![];
+        let parts = match page.get("Contents") {
+            Some(Object::Array(x)) => x.iter().collect(),
+            Some(x) => match self.resolve(x) {
+                Object::Array(x) => x.iter().collect(),
+                _ => vec![x],
+            },
+            None => vec![],
+        };
+        for part in parts {
+            if let Some(data) = self.stream(part) {
+                content.extend(data);
+                content.push(b'\n');
+            }
+        }
+        interpret(&content, &fonts)
+    }
+}
+
+/// Text shown by the operators of a content stream
+fn interpret(content: &[u8], fonts: &HashMap<&str, CMap>) -> String {
+    let mut lexer = Lexer {
+        data: content,
+        pos: 0,
+    };
+    let mut out = String::new();
+    let mut operands = vec![];
+    let mut font = None;
+    let mut last_y = None;
+    while let Some(object) = lexer.object(0) {
+        let Object::Op(op) = object else {
+            operands.push(object);
+            continue;
+        };
+        let number = |i: usize| operands.get(i).and_then(Object::number).unwrap_or(0.0);
+        match op.as_slice() {
+            b"Tf" => {
+                font = operands
+                    .first()
+                    .and_then(Object::name)
+                    .and_then(|x| fonts.get(x))
+            }
+            b"Tj" => show(&mut out, operands.last(), font),
+            b"'" | b"\"" => {
+                newline(&mut out);
+                show(&mut out, operands.last(), font);
+            }
+            b"TJ" => {
+                if let Some(Object::Array(items)) = operands.last() {
+                    for x in items {
+                        match x {
+                            // a gap wider than a fifth of the font size
+                            Object::Number(x) if *x < -200.0 => space(&mut out),
+                            x => show(&mut out, Some(x), font),
+                        }
+                    }
+                }
+            }
+            b"Td" | b"TD" => {
+                if number(1) != 0.0 {
+                    newline(&mut out);
+                } else if number(0) > 0.0 {
+                    space(&mut out);
+                }
+            }
+            b"Tm" => {
+                let y = number(5);
+                match last_y {
+                    Some(last) if f64::abs(last - y) > 0.1 => newline(&mut out),
+                    _ => space(&mut out),
+                }
+                last_y = Some(y);
+            }
+            b"T*" => newline(&mut out),
+            b"ET" => space(&mut out),
+            // the data of an inline image is not made of objects
+            b"BI" => match content[lexer.pos..]
+                .windows(3)
+                .position(|x| x[0].is_ascii_whitespace() && &x[1..] == b"EI")
+            {
+                Some(x) => lexer.pos += x + 3,
+                None => break,
+            },
+            _ => {}
+        }
+        operands.clear();
+    }
+    out
+}
+
+fn show(out: &mut String, object: Option<&Object>, font: Option<&CMap>) {
+    let Some(Object::Str(bytes)) = object else {
+        return;
+    };
+    let text = match font {
+        Some(cmap) => cmap.decode(bytes),
+        None => bytes.iter().map(|&x| x as char).collect(),
+    };
+    out.extend(text.chars().filter(|x| !x.is_control() || *x == '\t'));
+}
+
+fn newline(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+fn space(out: &mut String) {
+    if !out.is_empty() && !out.ends_with(char::is_whitespace) {
+        out.push(' ');
+    }
+}
+
+/// Text of the character codes of a font, from its `ToUnicode` stream
+#[derive(Default)]
+struct CMap {
+    /// Bytes of a code
+    width: usize,
+    map: HashMap<u32, String>,
+}
+
+impl CMap {
+    fn parse(data: &[u8]) -> Self {
+        let mut cmap = CMap::default();
+        let mut lexer = Lexer { data, pos: 0 };
+        let mut budget = MAX_CODES;
+        while let Some(object) = lexer.object(0) {
+            let Object::Op(op) = object else {
+                continue;
+            };
+            match op.as_slice() {
+                b"begincodespacerange" => {
+                    if let Some(Object::Str(x)) = lexer.object(0) {
+                        cmap.width = x.len();
+                    }
+                }
+                b"beginbfchar" => {
+                    while let (Some(Object::Str(src)), Some(Object::Str(dst))) =
+                        (lexer.object(0), lexer.object(0))
+                    {
+                        cmap.width = cmap.width.max(src.len());
+                        cmap.map.insert(code(&src), utf16(&dst));
+                    }
+                }
+                b"beginbfrange" => {
+                    while let (Some(Object::Str(lo)), Some(Object::Str(hi)), Some(dst)) =
+                        (lexer.object(0), lexer.object(0), lexer.object(0))
+                    {
+                        cmap.width = cmap.width.max(lo.len());
+                        let (lo, hi) = (code(&lo), code(&hi));
+                        let hi = hi.min(lo.saturating_add(0xffff));
+                        let count = (hi.saturating_sub(lo) as usize + 1).min(budget);
+                        budget -= count;
+                        match dst {
+                            Object::Str(dst) => {
+                                let mut units: Vec<u16> = dst
+                                    .chunks(2)
+                                    .map(|x| u16::from_be_bytes([x[0], *x.get(1).unwrap_or(&0)]))
+                                    .collect();
+                                for x in (lo..=hi).take(count) {
+                                    cmap.map.insert(x, String::from_utf16_lossy(&units));
+                                    if let Some(last) = units.last_mut() {
+                                        *last = last.wrapping_add(1);
+                                    }
+                                }
+                            }
+                            Object::Array(list) => {
+                                for (x, dst) in (lo..=hi).take(count).zip(list) {
+                                    if let Object::Str(dst) = dst {
+                                        cmap.map.insert(x, utf16(&dst));
+                                    }
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        cmap.width = cmap.width.clamp(1, 4);
+        cmap
+    }
+
+    fn decode(&self, bytes: &[u8]) -> String {
+        bytes
+            .chunks(self.width)
+            .filter_map(|x| match self.map.get(&code(x)) {
+                Some(text) => Some(text.clone()),
+                None if self.width == 1 => Some((x[0] as char).to_string()),
+                None => None,
+            })
+            .collect()
+    }
+}
+
+fn code(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |code, &x| code << 8 | x as u32)
+}
+
+fn utf16(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes
+        .chunks(2)
+        .map(|x| u16::from_be_bytes([x[0], *x.get(1).unwrap_or(&0)]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+struct Lexer<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+fn is_white(x: u8) -> bool {
+    matches!(x, b' ' | b'\t' | b'\r' | b'\n' | b'\x0c' | b'\0')
+}
+
+fn is_delimiter(x: u8) -> bool {
+    matches!(
+        x,
+        b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+    )
+}
+
+impl<'a> Lexer<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    /// Skip blanks and comments
+    fn skip(&mut self) {
+        while let Some(x) = self.peek() {
+            if is_white(x) {
+                self.pos += 1;
+            } else if x == b'%' {
+                while self.peek().is_some_and(|x| x != b'\n' && x != b'\r') {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Next object, None at the end of the data
+    fn object(&mut self, depth: usize) -> Option<Object> {
+        self.skip();
+        let x = self.peek()?;
+        if depth > MAX_DEPTH {
+            self.pos = self.data.len();
+            return None;
+        }
+        Some(match x {
+            b'/' => {
+                self.pos += 1;
+                Object::Name(self.name())
+            }
+            b'(' => Object::Str(self.literal()),
+            b'<' if self.data.get(self.pos + 1) == Some(&b'<') => {
+                self.pos += 2;
+                let mut dict = Dict::new();
+                loop {
+                    self.skip();
+                    if self.data[self.pos..].starts_with(b">>") {
+                        self.pos += 2;
+                        break;
+                    }
+                    let Some(key) = self.object(depth + 1) else {
+                        break;
+                    };
+                    let Object::Name(key) = key else {
+                        continue;
+                    };
+                    let Some(value) = self.object(depth + 1) else {
+                        break;
+                    };
+                    dict.insert(key, value);
+                }
+                Object::Dict(dict)
+            }
+            b'<' => Object::Str(self.hex()),
+            b'[' => {
+                self.pos += 1;
+                let mut list = vec![];
+                loop {
+                    self.skip();
+                    if self.peek() == Some(b']') {
+                        self.pos += 1;
+                        break;
+                    }
+                    let Some(x) = self.object(depth + 1) else {
+                        break;
+                    };
+                    list.push(x);
+                }
+                Object::Array(list)
+            }
+            b'0'..=b'9' | b'+' | b'-' | b'.' => self.number(),
+            x if is_delimiter(x) => {
+                // stray, e.g. the `>>` of a malformed dictionary
+                self.pos += 1;
+                Object::Op(vec![x])
+            }
+            _ => {
+                let start = self.pos;
+                while self
+                    .peek()
+                    .is_some_and(|x| !is_white(x) && !is_delimiter(x))
+                {
+                    self.pos += 1;
+                }
+                match &self.data[start..self.pos] {
+                    b"true" | b"false" => Object::Bool,
+                    b"null" => Object::Null,
+                    x => Object::Op(x.to_vec()),
+                }
+            }
+        })
+    }
+
+    /// A number, or a reference if followed by a generation and `R`
+    fn number(&mut self) -> Object {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|x| x.is_ascii_digit() || matches!(x, b'+' | b'-' | b'.'))
+        {
+            self.pos += 1;
+        }
+        let text = std::str::from_utf8(&self.data[start..self.pos]).unwrap_or_default();
+        let value: f64 = text.parse().unwrap_or(0.0);
+        if !text.bytes().all(|x| x.is_ascii_digit()) {
+            return Object::Number(value);
+        }
+
+        let after = self.pos;
+        self.skip();
+        let generation = self.pos;
+        while self.peek().is_some_and(|x| x.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if self.pos > generation {
+            self.skip();
+            let end = self.data.get(self.pos + 1).copied();
+            if self.peek() == Some(b'R') && end.is_none_or(|x| is_white(x) || is_delimiter(x)) {
+                self.pos += 1;
+                return Object::Ref(value as u32);
+            }
+        }
+        self.pos = after;
+        Object::Number(value)
+    }
+
+    fn name(&mut self) -> String {
+        let mut name = vec![];
+        while let Some(x) = self.peek().filter(|x| !is_white(*x) && !is_delimiter(*x)) {
+            self.pos += 1;
+            let hex = self.data.get(self.pos..self.pos + 2);
+            match hex.and_then(|x| std::str::from_utf8(x).ok()) {
+                Some(hex) if x == b'#' => match u8::from_str_radix(hex, 16) {
+                    Ok(x) => {
+                        name.push(x);
+                        self.pos += 2;
+                    }
+                    Err(_) => name.push(x),
+                },
+                _ => name.push(x),
+            }
+        }
+        String::from_utf8_lossy(&name).into_owned()
+    }
+
+    fn literal(&mut self) -> Vec<u8> {
+        self.pos += 1;
+        let mut out = vec![];
+        let mut depth = 0;
+        while let Some(x) = self.peek() {
+            self.pos += 1;
+            match x {
+                b'(' => {
+                    depth += 1;
+                    out.push(x);
+                }
+                b')' if depth == 0 => break,
+                b')' => {
+                    depth -= 1;
+                    out.push(x);
+                }
+                b'\\' => {
+                    let Some(x) = self.peek() else {
+                        break;
+                    };
+                    self.pos += 1;
+                    match x {
+                        b'n' => out.push(b'\n'),
+                        b'r' => out.push(b'\r'),
+                        b't' => out.push(b'\t'),
+                        b'b' => out.push(8),
+                        b'f' => out.push(12),
+                        b'0'..=b'7' => {
+                            let mut value = (x - b'0') as u32;
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(x @ b'0'..=b'7') => {
+                                        value = value * 8 + (x - b'0') as u32;
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            out.push(value as u8);
+                        }
+                        // a line continued
+                        b'\r' => {
+                            if self.peek() == Some(b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => {}
+                        x => out.push(x),
+                    }
+                }
+                x => out.push(x),
+            }
+        }
+        out
+    }
+
+    fn hex(&mut self) -> Vec<u8> {
+        self.pos += 1;
+        let mut digits = vec![];
+        while let Some(x) = self.peek() {
+            self.pos += 1;
+            match x {
+                b'>' => break,
+                x if x.is_ascii_hexdigit() => digits.push((x as char).to_digit(16).unwrap() as u8),
+                _ => {}
+            }
+        }
+        if digits.len() % 2 == 1 {
+            digits.push(0);
+        }
+        digits.chunks(2).map(|x| x[0] << 4 | x[1]).collect()
+    }
+
+    /// Raw bytes of the stream following a dictionary, if any
+    fn stream(&mut self, object: &Object) -> Option<&'a [u8]> {
+        let dict = object.dict()?;
+        if !self.data[self.pos..].starts_with(b"stream") {
+            return None;
+        }
+        self.pos += 6;
+        if self.data[self.pos..].starts_with(b"\r\n") {
+            self.pos += 2;
+        } else if self.peek().is_some_and(|x| x == b'\n' || x == b'\r') {
+            self.pos += 1;
+        }
+        let start = self.pos;
+        // an indirect length is not known yet, the stream ends at `endstream`
+        let end = dict
+            .get("Length")
+            .and_then(Object::number)
+            .map(|x| start.saturating_add(x as usize))
+            .filter(|end| {
+                self.data
+                    .get(*end..)
+                    .is_some_and(|x| x.trim_ascii_start().starts_with(b"endstream"))
+            })
+            .or_else(|| {
+                self.data[start..]
+                    .windows(9)
+                    .position(|x| x == b"endstream")
+                    .map(|x| start + x)
+            })?;
+        self.pos = end;
+        Some(&self.data[start..end])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{Compression, write::ZlibEncoder};
+
+    use super::*;
+
+    const LIMIT: usize = 1 << 20;
+
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// A PDF of one page showing `text`, its content compressed
+    fn sample(text: &str) -> Vec<u8> {
+        let content = zlib(format!("BT /F1 12 Tf ({}) Tj ET", text).as_bytes());
+        let mut pdf = b"%PDF-1.4\n\
+            1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj\n\
+            2 0 obj << /Type /Pages /Kids [3 0 R] /Count 1 >> endobj\n\
+            3 0 obj << /Type /Page /Parent 2 0 R /Contents 4 0 R >> endobj\n"
+            .to_vec();
+        pdf.extend(
+            format!(
+                "4 0 obj << /Length {} /Filter /FlateDecode >>\nstream\n",
+                content.len()
+            )
+            .bytes(),
+        );
+        pdf.extend(content);
+        pdf.extend(b"\nendstream\nendobj\n%%EOF\n");
+        pdf
+    }
+
+    #[test]
+    fn reads_the_text_of_a_page() {
+        let sections = extract(&sample("hello world"), LIMIT).unwrap();
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].label.as_deref(), Some("page 1"));
+        assert!(sections[0].text.contains("hello world"));
+    }
+
+    #[test]
+    fn truncated_files_do_not_panic() {
+        let pdf = sample("hello world");
+        for len in 0..pdf.len() {
+            let _ = extract(&pdf[..len], LIMIT);
+        }
+    }
+
+    #[test]
+    fn garbage_is_an_error() {
+        assert!(extract(b"", LIMIT).is_err());
+        assert!(extract(b"%PDF-1.4\n1 0 obj << /Type /Catalog", LIMIT).is_err());
+        assert!(extract(&[0xff; 4096], LIMIT).is_err());
+    }
+
+    #[test]
+    fn malformed_streams_yield_no_text() {
+        let pdf = b"%PDF-1.4\n\
+            1 0 obj << /Type /Page /Contents 2 0 R >> endobj\n\
+            2 0 obj << /Length 8 /Filter /FlateDecode >>\nstream\nx\x9cgarbag\nendstream endobj\n";
+        let sections = extract(pdf, LIMIT).unwrap();
+        assert_eq!(sections[0].text, "");
+    }
+
+    #[test]
+    fn oversized_lengths_fall_back_to_endstream() {
+        for length in ["99999999999999999999", "-5", "1e308", "4294967296"] {
+            let pdf = format!(
+                "%PDF-1.4\n\
+                1 0 obj << /Type /Page /Contents 2 0 R >> endobj\n\
+                2 0 obj << /Length {} >>\nstream\nBT (hi) Tj ET\nendstream endobj\n",
+                length
+            );
+            let sections = extract(pdf.as_bytes(), LIMIT).unwrap();
+            assert!(sections[0].text.contains("hi"), "{}", length);
+        }
+    }
+
+    #[test]
+    fn oversized_object_stream_offsets_are_skipped() {
+        let packed = zlib(b"1 5 << /Type /Page >>");
+        let mut pdf = format!(
+            "%PDF-1.4\n5 0 obj << /Type /ObjStm /N 4000000000 /First 18446744073709551615 /Length {} /Filter /FlateDecode >>\nstream\n",
+            packed.len()
+        )
+        .into_bytes();
+        pdf.extend(packed);
+        pdf.extend(b"\nendstream endobj\n");
+        assert!(extract(&pdf, LIMIT).is_err());
+    }
+
+    #[test]
+    fn repeated_streams_share_the_limit() {
+        // a stream of 1 MiB of zeroes referenced by many pages
+        let bomb = zlib(&vec![b' '; LIMIT / 2]);
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        pdf.extend(
+            format!(
+                "1 0 obj << /Length {} /Filter /FlateDecode >>\nstream\n",
+                bomb.len()
+            )
+            .bytes(),
+        );
+        pdf.extend(bomb);
+        pdf.extend(b"\nendstream endobj\n");
+        for id in 2..1000 {
+            pdf.extend(
+                format!(
+                    "{} 0 obj << /Type /Page /Contents [1 0 R 1 0 R] >> endobj\n",
+                    id
+                )
+                .bytes(),
+            );
+        }
+        let pdf = Pdf::parse(&pdf, LIMIT);
+        for (page, resources) in pdf.pages() {
+            pdf.page_text(page, resources);
+        }
+        assert!(pdf.decoded.get() <= LIMIT);
+    }
+
+    #[test]
+    fn huge_unicode_ranges_are_capped() {
+        let ranges = "<00000000> <ffffffff> <0041>\n".repeat(10_000);
+        let data = format!(
+            "begincodespacerange <00000000> <ffffffff> endcodespacerange\nbeginbfrange\n{}endbfrange",
+            ranges
+        );
+        let cmap = CMap::parse(data.as_bytes());
+        assert!(cmap.map.len() <= MAX_CODES);
+    }
+}
@@ -0,0 +1,176 @@
+//! Entries of a zip archive by their name, as the parts of a DOCX file
+
+use anyhow::{Context, Result, bail};
+
+use super::inflate;
+
+/// Content of the entry named `name`, None if the archive has none; fails
+/// past `limit` bytes, stored or deflated
+pub fn entry(data: &[u8], name: &str, limit: usize) -> Result<Option<Vec<u8>>> {
+    // the end of central directory record, before a comment of 64 KiB at most
+    let end = (0..data.len().saturating_sub(21))
+        .rev()
+        .take(65536 + 22)
+        .find(|&i| data[i..].starts_with(b"PK\x05\x06"))
+        .context("not a zip archive")?;
+    let count = u16_at(data, end + 10)? as usize;
+    let mut at = u32_at(data, end + 16)? as usize;
+
+    for _ in 0..count {
+        if !data.get(at..).is_some_and(|x| x.starts_with(b"PK\x01\x02")) {
+            bail!("malformed central directory");
+        }
+        let method = u16_at(data, at + 10)?;
+        let compressed = u32_at(data, at + 20)? as usize;
+        let name_len = u16_at(data, at + 28)? as usize;
+        let extra_len = u16_at(data, at + 30)? as usize;
+        let comment_len = u16_at(data, at + 32)? as usize;
+        let offset = u32_at(data, at + 42)? as usize;
+        let entry_name = data
+            .get(at + 46..at + 46 + name_len)
+            .context("truncated central directory")?;
+        at += 46 + name_len + extra_len + comment_len;
+        if entry_name != name.as_bytes() {
+            continue;
+        }
+
+        // the local header has its own name and extra field
+        let start =
+            offset + 30 + u16_at(data, offset + 26)? as usize + u16_at(data, offset + 28)? as usize;
+        let raw = data
+            .get(start..start + compressed)
+            .context("truncated entry")?;
+        return match method {
+            0 if raw.len() > limit => bail!("{} is past {} bytes", name, limit),
+            0 => Ok(Some(raw.to_vec())),
+            8 => Ok(Some(inflate::inflate(raw, limit)?)),
+            x => bail!("unsupported compression method {}", x),
+        };
+    }
+    Ok(None)
+}
+
+fn u16_at(data: &[u8], at: usize) -> Result<u16> {
+    let x = data.get(at..at + 2).context("truncated archive")?;
+    Ok(u16::from_le_bytes([x[0], x[1]]))
+}
+
+fn u32_at(data: &[u8], at: usize) -> Result<u32> {
+    let x = data.get(at..at + 4).context("truncated archive")?;
+    Ok(u32::from_le_bytes([x[0], x[1], x[2], x[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use time::UtcDateTime;
+
+    use super::*;
+    use crate::utils::zip::Writer;
+
+    fn archive(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = Writer::new(UtcDateTime::now());
+        for (name, data) in entries {
+            zip.append(name, data).unwrap();
+        }
+        zip.finish().unwrap()
+    }
+
+    fn noise(len: usize) -> Vec<u8> {
+        let mut data = vec![0; len];
+        fastrand::Rng::with_seed(7).fill(&mut data);
+        data
+    }
+
+    fn set_u32(data: &mut [u8], at: usize, value: u32) {
+        data[at..at + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    /// Offset of the central directory, from the end record
+    fn central(data: &[u8]) -> usize {
+        u32_at(data, data.len() - 6).unwrap() as usize
+    }
+
+    #[test]
+    fn finds_entries_by_name() {
+        let zip = archive(&[
+            ("[Content_Types].xml", b"<Types/>"),
+            ("word/document.xml", &b"<w:t>hello</w:t>".repeat(100)),
+        ]);
+        let document = entry(&zip, "word/document.xml", 1 << 20).unwrap();
+        assert_eq!(document.unwrap(), b"<w:t>hello</w:t>".repeat(100));
+        assert_eq!(entry(&zip, "word/missing.xml", 1 << 20).unwrap(), None);
+    }
+
+    #[test]
+    fn reads_what_the_takeout_writer_writes() {
+        let image = noise(4096);
+        let zip = archive(&[
+            ("a.md", "今天\n".repeat(500).as_bytes()),
+            ("cat.png", &image),
+            ("empty", b""),
+        ]);
+        assert_eq!(
+            entry(&zip, "a.md", 1 << 20).unwrap().unwrap(),
+            "今天\n".repeat(500).as_bytes()
+        );
+        assert_eq!(entry(&zip, "cat.png", 1 << 20).unwrap().unwrap(), image);
+        assert_eq!(entry(&zip, "empty", 1 << 20).unwrap().unwrap(), b"");
+    }
+
+    #[test]
+    fn a_trailing_comment_is_skipped() {
+        let mut zip = archive(&[("a", b"a")]);
+        let len = zip.len();
+        zip[len - 2..].copy_from_slice(&7u16.to_le_bytes());
+        zip.extend(b"comment");
+        assert_eq!(entry(&zip, "a", 1 << 20).unwrap().unwrap(), b"a");
+    }
+
+    #[test]
+    fn truncated_archives_fail() {
+        let zip = archive(&[("a", &b"abc".repeat(100)), ("b", b"b")]);
+        for len in 0..zip.len() {
+            assert!(entry(&zip[..len], "b", 1 << 20).is_err(), "{}", len);
+        }
+        assert!(entry(b"not a zip", "a", 1 << 20).is_err());
+    }
+
+    #[test]
+    fn offsets_out_of_the_archive_fail() {
+        let zip = archive(&[("a", &b"abc".repeat(100))]);
+        let at = central(&zip);
+
+        let mut bad = zip.clone();
+        set_u32(&mut bad, zip.len() - 6, u32::MAX);
+        assert!(entry(&bad, "a", 1 << 20).is_err());
+        // compressed size, then offset of the local header
+        for field in [20, 42] {
+            let mut bad = zip.clone();
+            set_u32(&mut bad, at + field, u32::MAX);
+            assert!(entry(&bad, "a", 1 << 20).is_err(), "{}", field);
+        }
+    }
+
+    #[test]
+    fn unknown_methods_fail() {
+        let mut zip = archive(&[("a", b"a")]);
+        let at = central(&zip);
+        // LZMA
+        zip[at + 10] = 14;
+        let err = entry(&zip, "a", 1 << 20).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("unsupported compression method 14")
+        );
+    }
+
+    #[test]
+    fn entries_past_the_limit_fail() {
+        let bomb = vec![0; 1 << 20];
+        let noise = noise(4096);
+        let zip = archive(&[("bomb", &bomb), ("noise", &noise)]);
+        assert!(entry(&zip, "bomb", 1 << 10).is_err());
+        assert!(entry(&zip, "noise", 1 << 10).is_err());
+        assert_eq!(entry(&zip, "noise", 4096).unwrap().unwrap(), noise);
+    }
+}
@@ -0,0 +1,42 @@
+//! Folders and labels users sort their chats with
+//!
+//! A chat is in at most one folder and can have several labels. Labels are
+//! named apart from the tags, which the tagger guess on its own
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use axum::Json;
+use entity::{chat_label, prelude::*};
+use sea_orm::{ConnectionTrait, QueryOrder, prelude::*};
+
+use crate::{config::FOLDER_NAME_MAX_CHARS, errors::*};
+
+/// Trimmed name of a folder or label, refused if empty or too long
+pub fn name(name: &str) -> Result<String, Json<Error>> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > FOLDER_NAME_MAX_CHARS {
+        return Err(Json(Error {
+            error: ErrorKind::MalformedRequest,
+            reason: format!("Names must be 1 to {} characters", FOLDER_NAME_MAX_CHARS),
+        }));
+    }
+    Ok(name.to_owned())
+}
+
+/// Label ids of each chat, chats without label are missing
+pub async fn labels(
+    conn: &impl ConnectionTrait,
+    chat_ids: Vec<i32>,
+) -> Result<HashMap<i32, Vec<i32>>> {
+    let mut map: HashMap<i32, Vec<i32>> = HashMap::new();
+    for x in ChatLabel::find()
+        .filter(chat_label::Column::ChatId.is_in(chat_ids))
+        .order_by_asc(chat_label::Column::LabelId)
+        .all(conn)
+        .await?
+    {
+        map.entry(x.chat_id).or_default().push(x.label_id);
+    }
+    Ok(map)
+}
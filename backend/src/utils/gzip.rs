@@ -0,0 +1,83 @@
+//! gzip (RFC 1952) and bare DEFLATE, for the responses of
+//! `middlewares::compression` and the entries of `utils::zip`
+
+use std::io::Write;
+
+use flate2::{
+    Compression, Crc,
+    write::{DeflateEncoder, GzEncoder},
+};
+
+pub fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    // writing to a Vec cannot fail
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// The bare DEFLATE stream, as zip entries hold it
+pub fn deflate_raw(data: &[u8]) -> Vec<u8> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::{DeflateDecoder, GzDecoder};
+
+    use super::*;
+
+    fn gunzip(data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        GzDecoder::new(data).read_to_end(&mut out).unwrap();
+        out
+    }
+
+    fn inflate(data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        DeflateDecoder::new(data).read_to_end(&mut out).unwrap();
+        out
+    }
+
+    fn samples() -> Vec<Vec<u8>> {
+        let json = br#"{"id":1,"title":"hello","tags":["a","b"]},"#.repeat(2000);
+        let noise: Vec<u8> = (0..100_000u32)
+            .map(|x| (x.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect();
+        vec![vec![], b"a".to_vec(), vec![0; 70_000], json, noise]
+    }
+
+    #[test]
+    fn gzip_round_trips() {
+        for data in samples() {
+            assert_eq!(gunzip(&gzip(&data)), data);
+        }
+    }
+
+    #[test]
+    fn deflate_round_trips() {
+        for data in samples() {
+            assert_eq!(inflate(&deflate_raw(&data)), data);
+        }
+    }
+
+    #[test]
+    fn long_runs_shrink() {
+        assert!(gzip(&[b'x'; 40_000]).len() < 1_000);
+    }
+
+    #[test]
+    fn crc32_matches_reference() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+}
@@ -0,0 +1,19 @@
+use anyhow::Result;
+use entity::{config, prelude::*};
+use sea_orm::{ActiveValue::Set, DbConn, EntityTrait};
+
+/// Identify this deployment in exported documents, generated on first start
+pub async fn instance_id(conn: &DbConn) -> Result<String> {
+    if let Some(x) = Config::find_by_id("instance_id").one(conn).await? {
+        return Ok(String::from_utf8(x.value)?);
+    }
+
+    let id = format!("{:016x}", fastrand::u64(..));
+    Config::insert(config::ActiveModel {
+        key: Set("instance_id".to_owned()),
+        value: Set(id.clone().into_bytes()),
+    })
+    .exec(conn)
+    .await?;
+    Ok(id)
+}
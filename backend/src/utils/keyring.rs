@@ -0,0 +1,153 @@
+//! Keys of the PASETO tokens of the instance, kept in `config`
+//!
+//! New tokens are encrypted with the newest key and checked against every key,
+//! so a rotation signs nobody out: the keys it retires are kept
+//! [`PASETO_KEY_RETAIN_SECS`], until the tokens they encrypted expired, then
+//! dropped at the next rotation or start
+
+use std::sync::RwLock;
+
+use anyhow::{Context, Result};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use entity::{config, prelude::*};
+use pasetors::{
+    Local,
+    claims::{Claims, ClaimsValidationRules},
+    keys::{Generate, SymmetricKey},
+    local,
+    token::{TrustedToken, UntrustedToken},
+    version4::V4,
+};
+use sea_orm::{
+    ActiveValue::Set, ConnectionTrait, DbConn, EntityTrait, TransactionTrait, sea_query::OnConflict,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::config::PASETO_KEY_RETAIN_SECS;
+
+/// The key new tokens are encrypted with
+const KEY: &str = "paseto_key";
+const RETIRED_KEY: &str = "paseto_retired_keys";
+
+/// A key replaced by a rotation, as stored
+#[derive(Serialize, Deserialize)]
+struct Retired {
+    /// 32 bytes in base64
+    key: String,
+    /// unix seconds
+    retired_at: i64,
+}
+
+struct Keys {
+    current: SymmetricKey<V4>,
+    /// Newest first
+    retired: Vec<(SymmetricKey<V4>, i64)>,
+}
+
+pub struct Keyring {
+    conn: DbConn,
+    keys: RwLock<Keys>,
+}
+
+impl Keyring {
+    pub async fn load(conn: DbConn) -> Result<Self> {
+        let keys = Keys::load(&conn).await?;
+        Ok(Self {
+            conn,
+            keys: RwLock::new(keys),
+        })
+    }
+
+    /// Encrypt with the newest key
+    pub fn encrypt(&self, claims: &Claims) -> Result<String> {
+        let keys = self.keys.read().unwrap();
+        Ok(local::encrypt(&keys.current, claims, None, None)?)
+    }
+
+    /// Decrypt with whichever key the token was encrypted with
+    pub fn decrypt(&self, token: &str, rules: &ClaimsValidationRules) -> Result<TrustedToken> {
+        let token = UntrustedToken::<Local, V4>::try_from(token)?;
+        let keys = self.keys.read().unwrap();
+        std::iter::once(&keys.current)
+            .chain(keys.retired.iter().map(|(key, _)| key))
+            .find_map(|key| local::decrypt(key, &token, rules, None, None).ok())
+            .context("Cannot decrypt token with any key")
+    }
+
+    /// Encrypt new tokens with a new key, return how many older keys are still
+    /// accepted
+    ///
+    /// The keys are read again from the database, so a rotation from the
+    /// command line since the start is kept
+    pub async fn rotate(&self) -> Result<usize> {
+        let txn = self.conn.begin().await?;
+        let mut keys = Keys::load(&txn).await?;
+        let old = std::mem::replace(&mut keys.current, SymmetricKey::generate()?);
+        keys.retired.insert(0, (old, now()));
+        keys.save(&txn).await?;
+        txn.commit().await?;
+
+        let retired = keys.retired.len();
+        *self.keys.write().unwrap() = keys;
+        Ok(retired)
+    }
+}
+
+impl Keys {
+    /// Retired keys past [`PASETO_KEY_RETAIN_SECS`] are left out
+    async fn load(conn: &impl ConnectionTrait) -> Result<Self> {
+        let current = Config::find_by_id(KEY)
+            .one(conn)
+            .await?
+            .context("Cannot find paseto key")?;
+        let current = SymmetricKey::from(&current.value)?;
+
+        let retired: Vec<Retired> = match Config::find_by_id(RETIRED_KEY).one(conn).await? {
+            Some(x) => serde_json::from_slice(&x.value)?,
+            None => Vec::new(),
+        };
+        let mut keys = Vec::with_capacity(retired.len());
+        for x in retired {
+            if x.retired_at + PASETO_KEY_RETAIN_SECS < now() {
+                continue;
+            }
+            keys.push((SymmetricKey::from(&STANDARD.decode(x.key)?)?, x.retired_at));
+        }
+        Ok(Self {
+            current,
+            retired: keys,
+        })
+    }
+
+    async fn save(&self, conn: &impl ConnectionTrait) -> Result<()> {
+        let retired: Vec<Retired> = self
+            .retired
+            .iter()
+            .map(|(key, retired_at)| Retired {
+                key: STANDARD.encode(key.as_bytes()),
+                retired_at: *retired_at,
+            })
+            .collect();
+        for (key, value) in [
+            (KEY, self.current.as_bytes().to_vec()),
+            (RETIRED_KEY, serde_json::to_vec(&retired)?),
+        ] {
+            Config::insert(config::ActiveModel {
+                key: Set(key.to_owned()),
+                value: Set(value),
+            })
+            .on_conflict(
+                OnConflict::column(config::Column::Key)
+                    .update_column(config::Column::Value)
+                    .to_owned(),
+            )
+            .exec(conn)
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+fn now() -> i64 {
+    time::UtcDateTime::now().unix_timestamp()
+}
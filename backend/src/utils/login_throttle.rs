@@ -0,0 +1,106 @@
+//! Lock out accounts and IPs after repeated failed logins
+//!
+//! Kept in the DB so a restart does not hand out fresh attempts
+
+use std::net::IpAddr;
+
+use anyhow::Result;
+use entity::{login_throttle, prelude::*};
+use sea_orm::{ActiveValue::Set, ConnectionTrait, EntityTrait, sea_query::OnConflict};
+
+use crate::config::{
+    LOGIN_FAILURE_WINDOW_SECS, LOGIN_FREE_FAILURES_ACCOUNT, LOGIN_FREE_FAILURES_IP,
+    LOGIN_LOCKOUT_MAX_SECS, LOGIN_LOCKOUT_SECS,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Key<'a> {
+    /// The submitted username, whether it exists or not
+    Account(&'a str),
+    Ip(IpAddr),
+}
+
+impl Key<'_> {
+    fn id(&self) -> String {
+        match self {
+            Key::Account(name) => format!("account:{}", name),
+            Key::Ip(ip) => format!("ip:{}", ip),
+        }
+    }
+
+    fn free_failures(&self) -> i32 {
+        match self {
+            Key::Account(_) => LOGIN_FREE_FAILURES_ACCOUNT,
+            Key::Ip(_) => LOGIN_FREE_FAILURES_IP,
+        }
+    }
+}
+
+/// Seconds until every key is unlocked, None if none is locked
+pub async fn locked(conn: &impl ConnectionTrait, keys: &[Key<'_>]) -> Result<Option<i64>> {
+    let now = now();
+    let mut wait = None;
+    for key in keys {
+        if let Some(x) = LoginThrottle::find_by_id(key.id()).one(conn).await?
+            && x.locked_until > now
+        {
+            wait = wait.max(Some(x.locked_until - now));
+        }
+    }
+    Ok(wait)
+}
+
+/// Count a failure, locking keys out of their free attempts for twice as
+/// long as the last time
+pub async fn fail(conn: &impl ConnectionTrait, keys: &[Key<'_>]) -> Result<()> {
+    let now = now();
+    for key in keys {
+        let failures = match LoginThrottle::find_by_id(key.id()).one(conn).await? {
+            Some(x) if now - x.last_failure_at < LOGIN_FAILURE_WINDOW_SECS => x.failures + 1,
+            _ => 1,
+        };
+        let locked_until = match failures - key.free_failures() {
+            over if over > 0 => {
+                let secs = LOGIN_LOCKOUT_SECS.saturating_mul(1 << (over - 1).min(32));
+                now + secs.min(LOGIN_LOCKOUT_MAX_SECS)
+            }
+            _ => 0,
+        };
+        if locked_until > 0 {
+            tracing::warn!("login locked for {} after {} failures", key.id(), failures);
+        }
+
+        LoginThrottle::insert(login_throttle::ActiveModel {
+            key: Set(key.id()),
+            failures: Set(failures),
+            last_failure_at: Set(now),
+            locked_until: Set(locked_until),
+        })
+        .on_conflict(
+            OnConflict::column(login_throttle::Column::Key)
+                .update_columns([
+                    login_throttle::Column::Failures,
+                    login_throttle::Column::LastFailureAt,
+                    login_throttle::Column::LockedUntil,
+                ])
+                .to_owned(),
+        )
+        .exec(conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Forget the failures of an account
+///
+/// IPs are not forgiven, an attacker with an account of its own could reset them
+pub async fn succeed(conn: &impl ConnectionTrait, name: &str) -> Result<()> {
+    LoginThrottle::delete_by_id(Key::Account(name).id())
+        .exec(conn)
+        .await?;
+    Ok(())
+}
+
+fn now() -> i64 {
+    time::UtcDateTime::now().unix_timestamp()
+}
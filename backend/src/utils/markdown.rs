@@ -0,0 +1,186 @@
+//! Just enough markdown to HTML for exported transcripts
+//!
+//! Cover what models usually write: headings, lists, quotes, fenced code,
+//! inline code, emphasis, links and images
+
+use std::fmt::Write;
+
+pub fn to_html(src: &str) -> String {
+    let mut res = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut list: Option<&'static str> = None;
+    let mut lines = src.lines();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+
+        if let Some(lang) = trimmed
+            .strip_prefix("```")
+            .or_else(|| trimmed.strip_prefix("~~~"))
+        {
+            flush(&mut res, &mut paragraph, &mut list);
+            let fence = &trimmed[..3];
+            let lang = lang.trim();
+            match lang.is_empty() {
+                true => res.push_str("<pre><code>"),
+                false => write!(res, "<pre><code class=\"language-{}\">", escape(lang)).unwrap(),
+            }
+            for line in lines.by_ref() {
+                if line.trim_start().starts_with(fence) {
+                    break;
+                }
+                res.push_str(&escape(line));
+                res.push('\n');
+            }
+            res.push_str("</code></pre>\n");
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            flush(&mut res, &mut paragraph, &mut list);
+            continue;
+        }
+
+        if let Some((level, text)) = heading(trimmed) {
+            flush(&mut res, &mut paragraph, &mut list);
+            writeln!(res, "<h{0}>{1}</h{0}>", level, inline(text)).unwrap();
+            continue;
+        }
+
+        if matches!(trimmed, "---" | "***" | "___") {
+            flush(&mut res, &mut paragraph, &mut list);
+            res.push_str("<hr>\n");
+            continue;
+        }
+
+        if let Some(text) = trimmed.strip_prefix("> ").or(trimmed.strip_prefix(">")) {
+            flush(&mut res, &mut paragraph, &mut list);
+            writeln!(res, "<blockquote>{}</blockquote>", inline(text)).unwrap();
+            continue;
+        }
+
+        if let Some((tag, text)) = list_item(trimmed) {
+            if !paragraph.is_empty() || list != Some(tag) {
+                flush(&mut res, &mut paragraph, &mut list);
+                writeln!(res, "<{}>", tag).unwrap();
+                list = Some(tag);
+            }
+            writeln!(res, "<li>{}</li>", inline(text)).unwrap();
+            continue;
+        }
+
+        if list.is_some() {
+            flush(&mut res, &mut paragraph, &mut list);
+        }
+        paragraph.push(trimmed);
+    }
+    flush(&mut res, &mut paragraph, &mut list);
+    res
+}
+
+fn flush(res: &mut String, paragraph: &mut Vec<&str>, list: &mut Option<&'static str>) {
+    if let Some(tag) = list.take() {
+        writeln!(res, "</{}>", tag).unwrap();
+    }
+    if !paragraph.is_empty() {
+        let text = paragraph
+            .iter()
+            .map(|x| inline(x))
+            .collect::<Vec<_>>()
+            .join("<br>\n");
+        writeln!(res, "<p>{}</p>", text).unwrap();
+        paragraph.clear();
+    }
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    line[level..].strip_prefix(' ').map(|x| (level, x.trim()))
+}
+
+fn list_item(line: &str) -> Option<(&'static str, &str)> {
+    if let Some(text) = ["- ", "* ", "+ "].iter().find_map(|x| line.strip_prefix(x)) {
+        return Some(("ul", text));
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 {
+        return None;
+    }
+    line[digits..].strip_prefix(". ").map(|x| ("ol", x))
+}
+
+fn inline(src: &str) -> String {
+    let mut res = String::new();
+    let mut rest = src;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '`'
+            && let Some(end) = rest[1..].find('`')
+        {
+            write!(res, "<code>{}</code>", escape(&rest[1..end + 1])).unwrap();
+            rest = &rest[end + 2..];
+            continue;
+        }
+        if c == '!'
+            && let Some((alt, url, len)) = link(&rest[1..])
+        {
+            write!(res, "<img alt=\"{}\" src=\"{}\">", escape(alt), escape(url)).unwrap();
+            rest = &rest[len + 1..];
+            continue;
+        }
+        if c == '['
+            && let Some((text, url, len)) = link(rest)
+        {
+            write!(res, "<a href=\"{}\">{}</a>", escape(url), inline(text)).unwrap();
+            rest = &rest[len..];
+            continue;
+        }
+        if let Some(inner) = rest.strip_prefix("**")
+            && let Some(end) = inner.find("**")
+        {
+            write!(res, "<strong>{}</strong>", inline(&inner[..end])).unwrap();
+            rest = &inner[end + 2..];
+            continue;
+        }
+        if c == '*'
+            && let Some(end) = rest[1..].find('*').filter(|&x| x != 0)
+        {
+            write!(res, "<em>{}</em>", inline(&rest[1..end + 1])).unwrap();
+            rest = &rest[end + 2..];
+            continue;
+        }
+
+        res.push_str(&escape(&rest[..c.len_utf8()]));
+        rest = &rest[c.len_utf8()..];
+    }
+    res
+}
+
+/// Parse `[text](url)`, return text, url and the length consumed
+///
+/// Only web and inline image urls are accepted
+fn link(src: &str) -> Option<(&str, &str, usize)> {
+    let src_inner = src.strip_prefix('[')?;
+    let text_end = src_inner.find("](")?;
+    let after = &src_inner[text_end + 2..];
+    let url_end = after.find(')')?;
+    let url = after[..url_end].trim();
+
+    let safe = ["http://", "https://", "data:image/", "/"]
+        .iter()
+        .any(|x| url.starts_with(x));
+    if !safe {
+        return None;
+    }
+    Some((&src_inner[..text_end], url, 1 + text_end + 2 + url_end + 1))
+}
+
+pub fn escape(x: &str) -> String {
+    x.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
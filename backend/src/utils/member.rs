@@ -0,0 +1,82 @@
+//! Accounts taking part in a chat besides its creator
+//!
+//! `chat.owner_id` stays the creator, who alone delete, archive, pin and file
+//! the chat. Members listed in `chat_member` read it, send messages and follow
+//! its stream, the owners among them also manage the members and the settings
+//!
+//! A chat belongs to the workspace it was created in and is only reached from
+//! there, see `utils::workspace`
+
+use axum::Json;
+use entity::{ChatMemberRole, chat, chat_member, prelude::*};
+use sea_orm::{Condition, ConnectionTrait, prelude::*, sea_query::Query};
+
+use crate::errors::*;
+
+/// Role of the user in the chat, None if they do not take part in it
+pub async fn role(
+    conn: &impl ConnectionTrait,
+    chat: &chat::Model,
+    user_id: i32,
+) -> Result<Option<ChatMemberRole>, DbErr> {
+    if chat.owner_id == user_id {
+        return Ok(Some(ChatMemberRole::Owner));
+    }
+    let member = ChatMember::find_by_id((chat.id, user_id)).one(conn).await?;
+    Ok(member.map(|x| x.role))
+}
+
+/// A chat of the workspace the user takes part in, with their role
+pub async fn find(
+    conn: &impl ConnectionTrait,
+    chat_id: i32,
+    user_id: i32,
+    workspace_id: i32,
+) -> Result<(chat::Model, ChatMemberRole), Json<Error>> {
+    let chat = Chat::find_by_id(chat_id)
+        .one(conn)
+        .await
+        .kind(ErrorKind::Internal)?
+        .filter(|x| x.workspace_id == workspace_id)
+        .ok_or("")
+        .kind(ErrorKind::ResourceNotFound)?;
+    let role = role(conn, &chat, user_id)
+        .await
+        .kind(ErrorKind::Internal)?
+        .ok_or("")
+        .kind(ErrorKind::ResourceNotFound)?;
+    Ok((chat, role))
+}
+
+/// A chat the user is an owner of, members are refused
+pub async fn owned(
+    conn: &impl ConnectionTrait,
+    chat_id: i32,
+    user_id: i32,
+    workspace_id: i32,
+) -> Result<chat::Model, Json<Error>> {
+    match find(conn, chat_id, user_id, workspace_id).await? {
+        (chat, ChatMemberRole::Owner) => Ok(chat),
+        (_, ChatMemberRole::Member) => Err(Json(Error {
+            error: ErrorKind::Unauthorized,
+            reason: "Only owners of the chat can do this".to_owned(),
+        })),
+    }
+}
+
+/// Chats of the workspace the user created or joined
+pub fn joined(user_id: i32, workspace_id: i32) -> Condition {
+    Condition::all()
+        .add(chat::Column::WorkspaceId.eq(workspace_id))
+        .add(
+            Condition::any().add(chat::Column::OwnerId.eq(user_id)).add(
+                chat::Column::Id.in_subquery(
+                    Query::select()
+                        .column(chat_member::Column::ChatId)
+                        .from(ChatMember)
+                        .and_where(chat_member::Column::UserId.eq(user_id))
+                        .to_owned(),
+                ),
+            ),
+        )
+}
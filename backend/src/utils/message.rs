@@ -0,0 +1,25 @@
+use entity::{chat, message, prelude::*};
+use sea_orm::{Condition, JoinType, QuerySelect, RelationTrait, Select, prelude::*};
+
+/// Messages of a chat as seen by `viewer_id`
+///
+/// Private messages are only visible to their author, the chat owner for
+/// messages without one
+pub fn visible_messages(chat_id: i32, viewer_id: i32) -> Select<Message> {
+    Message::find()
+        .join(JoinType::InnerJoin, message::Relation::Chat.def())
+        .filter(message::Column::ChatId.eq(chat_id))
+        .filter(visible_to(viewer_id))
+}
+
+/// Messages `viewer_id` can see, for a query joined with `chat`
+pub fn visible_to(viewer_id: i32) -> Condition {
+    Condition::any()
+        .add(message::Column::Private.eq(false))
+        .add(message::Column::AuthorId.eq(viewer_id))
+        .add(
+            Condition::all()
+                .add(message::Column::AuthorId.is_null())
+                .add(chat::Column::OwnerId.eq(viewer_id)),
+        )
+}
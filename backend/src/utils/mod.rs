@@ -1,3 +1,35 @@
-pub mod blob;
+pub mod account_purge;
+pub mod api_key;
+pub mod branch;
+pub mod chat_variable;
+pub mod client;
+pub mod context_stat;
+pub mod cron;
+pub mod email_verification;
+pub mod export;
+pub mod extract;
+pub mod folder;
+pub mod gzip;
+pub mod instance;
+pub mod keyring;
+pub mod login_throttle;
+pub mod markdown;
+pub mod member;
+pub mod message;
 pub mod model;
+pub mod openapi;
 pub mod password_hash;
+pub mod password_reset;
+#[cfg(feature = "pdf")]
+pub mod pdf;
+pub mod preference;
+pub mod search_term;
+pub mod secrets;
+pub mod session;
+pub mod sql;
+pub mod tagger;
+pub mod tar;
+pub mod totp;
+pub mod websocket;
+pub mod workspace;
+pub mod zip;
@@ -1,6 +1,20 @@
-use entity;
+use std::sync::Arc;
 
-use crate::openrouter;
+use entity::{self, model};
+use sea_orm::{DbErr, EntityTrait};
+
+use crate::{AppState, openrouter};
+
+/// Every row of `model`, cached for `CACHE_TTL`, see `cache`
+pub async fn all(app: &AppState) -> Result<Arc<Vec<model::Model>>, DbErr> {
+    let load = async { Ok(Arc::new(model::Entity::find().all(&app.conn).await?)) };
+    app.cache.models.get_or_load((), load).await
+}
+
+/// A row of `model`, from [`all`]
+pub async fn find(app: &AppState, id: i32) -> Result<Option<model::Model>, DbErr> {
+    Ok(all(app).await?.iter().find(|x| x.id == id).cloned())
+}
 
 impl From<entity::ModelConfig> for openrouter::Model {
     fn from(value: entity::ModelConfig) -> Self {
@@ -10,7 +24,9 @@ impl From<entity::ModelConfig> for openrouter::Model {
             repeat_penalty: value.parameter.repeat_penalty,
             top_k: value.parameter.top_k,
             top_p: value.parameter.top_p,
+            seed: value.parameter.seed,
             online: false,
+            reasoning: value.capability.reasoning,
         }
     }
 }
@@ -0,0 +1,198 @@
+//! OpenAPI 3.0 documents of routes, with schemas derived by `schemars`
+//!
+//! Routes answer errors with status 200 and an `Error` body, so every JSON
+//! response is documented as either. Path parameters named `id` are integers,
+//! other ones strings
+
+use schemars::{JsonSchema, SchemaGenerator, generate::SchemaSettings};
+use serde_json::{Map, Value, json};
+
+use crate::errors::Error;
+
+const SECURITY: &str = "token";
+
+pub struct Builder {
+    generator: SchemaGenerator,
+    paths: Map<String, Value>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self {
+            generator: SchemaSettings::openapi3().into_generator(),
+            paths: Map::new(),
+        }
+    }
+
+    /// An operation under `/api`, authenticated unless made [`Op::public`]
+    pub fn op(&mut self, method: &str, path: &str, summary: &str) -> Op<'_> {
+        let parameters: Vec<Value> = path
+            .split('/')
+            .filter_map(|x| x.strip_prefix('{')?.strip_suffix('}'))
+            .map(|name| {
+                let kind = if name == "id" { "integer" } else { "string" };
+                json!({ "name": name, "in": "path", "required": true, "schema": { "type": kind } })
+            })
+            .collect();
+        let mut operation = Map::new();
+        operation.insert("summary".to_owned(), summary.into());
+        if !parameters.is_empty() {
+            operation.insert("parameters".to_owned(), parameters.into());
+        }
+        Op {
+            builder: self,
+            method: method.to_lowercase(),
+            path: path.to_owned(),
+            operation,
+        }
+    }
+
+    pub fn build(mut self, title: &str, version: &str) -> Value {
+        self.generator.subschema_for::<Error>();
+        json!({
+            "openapi": "3.0.3",
+            "info": { "title": title, "version": version },
+            "servers": [{ "url": "/api" }],
+            "paths": self.paths,
+            "components": {
+                "schemas": self.generator.take_definitions(true),
+                "securitySchemes": {
+                    SECURITY: {
+                        "type": "apiKey",
+                        "in": "header",
+                        "name": "Authorization",
+                        "description": "access token of `auth/login` or an API key, without a scheme"
+                    }
+                }
+            },
+            "security": [{ SECURITY: [] }]
+        })
+    }
+
+    fn schema<T: JsonSchema>(&mut self) -> Value {
+        self.generator.subschema_for::<T>().to_value()
+    }
+}
+
+/// An operation being described, added by the method setting its response
+pub struct Op<'a> {
+    builder: &'a mut Builder,
+    method: String,
+    path: String,
+    operation: Map<String, Value>,
+}
+
+impl Op<'_> {
+    /// Served without a token
+    pub fn public(mut self) -> Self {
+        self.operation.insert("security".to_owned(), json!([]));
+        self
+    }
+
+    pub fn body<T: JsonSchema>(mut self) -> Self {
+        let schema = self.builder.schema::<T>();
+        self.operation.insert(
+            "requestBody".to_owned(),
+            json!({ "required": true, "content": { "application/json": { "schema": schema } } }),
+        );
+        self
+    }
+
+    /// A multipart form, `fields` naming the parts and what they hold
+    pub fn form(mut self, fields: &[(&str, &str)]) -> Self {
+        let properties: Map<String, Value> = fields
+            .iter()
+            .map(|(name, description)| {
+                let schema = match *name {
+                    "file" => json!({ "type": "string", "format": "binary" }),
+                    _ => json!({ "type": "string" }),
+                };
+                let mut schema = schema;
+                schema["description"] = (*description).into();
+                (name.to_string(), schema)
+            })
+            .collect();
+        self.operation.insert(
+            "requestBody".to_owned(),
+            json!({
+                "required": true,
+                "content": {
+                    "multipart/form-data": {
+                        "schema": { "type": "object", "properties": properties }
+                    }
+                }
+            }),
+        );
+        self
+    }
+
+    /// The fields of `T` as query parameters
+    pub fn query<T: JsonSchema>(mut self) -> Self {
+        let schema = SchemaSettings::openapi3()
+            .with(|x| x.inline_subschemas = true)
+            .into_generator()
+            .into_root_schema_for::<T>();
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|x| x.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        let mut parameters: Vec<Value> = self
+            .operation
+            .remove("parameters")
+            .and_then(|x| x.as_array().cloned())
+            .unwrap_or_default();
+        if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+            for (name, schema) in properties {
+                let mut parameter = json!({
+                    "name": name,
+                    "in": "query",
+                    "required": required.contains(&name.as_str()),
+                    "schema": schema,
+                });
+                if let Some(description) = schema.get("description") {
+                    parameter["description"] = description.clone();
+                }
+                parameters.push(parameter);
+            }
+        }
+        self.operation
+            .insert("parameters".to_owned(), parameters.into());
+        self
+    }
+
+    /// Answer `T` as JSON
+    pub fn json<T: JsonSchema>(self) {
+        let schema = self.builder.schema::<T>();
+        let error = self.builder.schema::<Error>();
+        let content = json!({ "application/json": { "schema": { "oneOf": [schema, error] } } });
+        self.respond("the response, or an `Error` with its reason", content);
+    }
+
+    /// Answer a stream of server-sent events, each a `T` as JSON
+    pub fn events<T: JsonSchema>(self) {
+        let schema = self.builder.schema::<T>();
+        let content = json!({ "text/event-stream": { "schema": schema } });
+        self.respond("server-sent events, the data of each is JSON", content);
+    }
+
+    /// Answer a file of `content_type`
+    pub fn file(self, content_type: &str) {
+        let content =
+            json!({ content_type: { "schema": { "type": "string", "format": "binary" } } });
+        self.respond("the file", content);
+    }
+
+    fn respond(mut self, description: &str, content: Value) {
+        self.operation.insert(
+            "responses".to_owned(),
+            json!({ "200": { "description": description, "content": content } }),
+        );
+        let item = self
+            .builder
+            .paths
+            .entry(self.path)
+            .or_insert_with(|| Value::Object(Map::new()));
+        item[self.method.as_str()] = Value::Object(self.operation);
+    }
+}
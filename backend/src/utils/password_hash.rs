@@ -2,24 +2,34 @@ use argon2;
 
 const SALT_LEN: usize = 16;
 
+/// Argon2 takes tens of milliseconds of CPU, so it runs on the blocking pool
+/// instead of stalling a worker
 #[derive(Default)]
 pub struct Hasher {
     config: argon2::Config<'static>,
 }
 
 impl Hasher {
-    pub fn verify_password(&self, hash: &str, password: &str) -> bool {
-        return argon2::verify_encoded(&hash, password.as_bytes()).unwrap();
+    pub async fn verify_password(&self, hash: &str, password: &str) -> bool {
+        let (hash, password) = (hash.to_owned(), password.to_owned());
+        tokio::task::spawn_blocking(move || {
+            argon2::verify_encoded(&hash, password.as_bytes()).unwrap()
+        })
+        .await
+        .unwrap()
     }
-    pub fn hash_password(&self, password: &str) -> String {
+    pub async fn hash_password(&self, password: &str) -> String {
         let salt = {
             (0..SALT_LEN)
                 .map(|_| fastrand::u8(0..u8::MAX))
                 .collect::<Vec<u8>>()
         };
 
-        let hash = argon2::hash_encoded(password.as_bytes(), &salt, &self.config).unwrap();
-
-        return hash;
+        let (config, password) = (self.config.clone(), password.to_owned());
+        tokio::task::spawn_blocking(move || {
+            argon2::hash_encoded(password.as_bytes(), &salt, &config).unwrap()
+        })
+        .await
+        .unwrap()
     }
 }
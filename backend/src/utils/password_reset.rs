@@ -0,0 +1,52 @@
+//! One-time tokens mailed to reset a forgotten password, stored hashed
+
+use anyhow::Result;
+use entity::{password_reset, prelude::*};
+use sea_orm::{ActiveValue::Set, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+use sha2::{Digest, Sha256};
+
+use crate::config::PASSWORD_RESET_SECS;
+
+/// Replace pending tokens of the user, only the latest mail work
+pub async fn create(conn: &impl ConnectionTrait, user_id: i32) -> Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|e| anyhow::anyhow!("Cannot generate token: {}", e))?;
+    let token: String = bytes.iter().map(|x| format!("{:02x}", x)).collect();
+
+    PasswordReset::delete_many()
+        .filter(password_reset::Column::UserId.eq(user_id))
+        .exec(conn)
+        .await?;
+    PasswordReset::insert(password_reset::ActiveModel {
+        user_id: Set(user_id),
+        token_hash: Set(hash(&token)),
+        expires_at: Set(now() + PASSWORD_RESET_SECS),
+        ..Default::default()
+    })
+    .exec(conn)
+    .await?;
+    Ok(token)
+}
+
+/// Return the user the token belong to, None if unknown or expired
+///
+/// The token is deleted either way
+pub async fn consume(conn: &impl ConnectionTrait, token: &str) -> Result<Option<i32>> {
+    let Some(model) = PasswordReset::find()
+        .filter(password_reset::Column::TokenHash.eq(hash(token)))
+        .one(conn)
+        .await?
+    else {
+        return Ok(None);
+    };
+    PasswordReset::delete_by_id(model.id).exec(conn).await?;
+    Ok((model.expires_at >= now()).then_some(model.user_id))
+}
+
+fn hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+fn now() -> i64 {
+    time::UtcDateTime::now().unix_timestamp()
+}
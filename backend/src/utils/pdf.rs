@@ -0,0 +1,44 @@
+use std::{fs, path::Path, process::Command};
+
+use anyhow::{Result, bail};
+use dotenv::var;
+
+/// Print HTML with a headless chromium, see `CHROMIUM_PATH` env
+pub async fn html_to_pdf(html: String) -> Result<Vec<u8>> {
+    tokio::task::spawn_blocking(move || {
+        let dir = std::env::temp_dir().join(format!("chat-export-{:016x}", fastrand::u64(..)));
+        fs::create_dir(&dir)?;
+        let res = print(&dir, &html);
+        fs::remove_dir_all(&dir).ok();
+        res
+    })
+    .await?
+}
+
+fn print(dir: &Path, html: &str) -> Result<Vec<u8>> {
+    let input = dir.join("transcript.html");
+    let output = dir.join("transcript.pdf");
+    fs::write(&input, html)?;
+
+    let chromium = var("CHROMIUM_PATH").unwrap_or("chromium".to_owned());
+    let res = Command::new(chromium)
+        .args([
+            "--headless",
+            "--disable-gpu",
+            "--no-sandbox",
+            "--no-pdf-header-footer",
+        ])
+        .arg(format!("--user-data-dir={}", dir.join("profile").display()))
+        .arg(format!("--print-to-pdf={}", output.display()))
+        .arg(format!("file://{}", input.display()))
+        .output()?;
+    if !res.status.success() {
+        bail!(
+            "chromium exited with {}: {}",
+            res.status,
+            String::from_utf8_lossy(&res.stderr)
+        );
+    }
+
+    Ok(fs::read(output)?)
+}
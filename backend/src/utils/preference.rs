@@ -0,0 +1,148 @@
+//! Preferences of a user the server acts on, written at `user/preferences`
+//!
+//! Every write raises `user.preference_version`, a write based on an older
+//! version is refused so two devices cannot silently undo each other. Keys
+//! are the fields of [`UserPreference`], checked against its schema with
+//! `tools::validate` and then by [`problems`]
+
+use std::sync::LazyLock;
+
+use anyhow::Result;
+use entity::{UserPreference, prelude::*, user};
+use schemars::schema_for;
+use sea_orm::{ConnectionTrait, prelude::*, sea_query::Expr};
+use serde_json::Value;
+use time::UtcOffset;
+
+use crate::{middlewares::locale::Locale, retention, tools};
+
+static SCHEMA: LazyLock<Value> = LazyLock::new(|| {
+    let mut schema = serde_json::to_value(schema_for!(UserPreference)).unwrap();
+    schema["additionalProperties"] = Value::Bool(false);
+    schema
+});
+
+/// Names of the keys a preference can have
+pub fn keys() -> Vec<&'static str> {
+    SCHEMA["properties"]
+        .as_object()
+        .map(|x| x.keys().map(String::as_str).collect())
+        .unwrap_or_default()
+}
+
+/// `preference` with the keys of `set` replaced and those of `unset` removed,
+/// or what is wrong with the result
+pub fn apply(
+    preference: &UserPreference,
+    set: &UserPreference,
+    unset: &[String],
+) -> Result<UserPreference, Vec<String>> {
+    let keys = keys();
+    let unknown: Vec<String> = unset
+        .iter()
+        .filter(|x| !keys.contains(&x.as_str()))
+        .map(|x| format!("/{}: unknown key, expected one of {}", x, keys.join(", ")))
+        .collect();
+    if !unknown.is_empty() {
+        return Err(unknown);
+    }
+    let mut merged = serde_json::to_value(preference).map_err(|e| vec![e.to_string()])?;
+    let set = serde_json::to_value(set).map_err(|e| vec![e.to_string()])?;
+    let fields = merged.as_object_mut().unwrap();
+    for key in unset {
+        fields.remove(key);
+    }
+    fields.extend(
+        set.as_object()
+            .into_iter()
+            .flatten()
+            .map(|(k, v)| (k.clone(), v.clone())),
+    );
+
+    let mut found = tools::check(&SCHEMA, &merged.to_string());
+    if !found.is_empty() {
+        return Err(found);
+    }
+    let merged: UserPreference = serde_json::from_value(merged).map_err(|e| vec![e.to_string()])?;
+    found = problems(&merged);
+    match found.is_empty() {
+        true => Ok(merged),
+        false => Err(found),
+    }
+}
+
+/// What the schema cannot tell about the values of a preference
+pub fn problems(preference: &UserPreference) -> Vec<String> {
+    let mut problems = vec![];
+    if let Some(locale) = &preference.locale
+        && Locale::parse(locale).is_none()
+    {
+        problems.push(format!("/locale: unsupported locale {}", locale));
+    }
+    if let Some(x) = &preference.submit_on_enter
+        && x != "true"
+        && x != "false"
+    {
+        problems.push("/submit_on_enter: expected \"true\" or \"false\"".to_owned());
+    }
+    if let Some(speed) = &preference.voice_speed
+        && !speed
+            .parse::<f32>()
+            .is_ok_and(|x| (0.25..=4.0).contains(&x))
+    {
+        problems.push("/voice_speed: expected a number from 0.25 to 4".to_owned());
+    }
+    if let Some(days) = &preference.retention_days
+        && !days.is_empty()
+        && retention::user_days(days).is_none()
+    {
+        problems.push("/retention_days: expected a number of days, at least one".to_owned());
+    }
+    if let Some(timezone) = &preference.timezone
+        && !is_timezone(timezone)
+    {
+        problems.push(format!(
+            "/timezone: expected an IANA time zone such as Asia/Taipei, got {}",
+            timezone
+        ));
+    }
+    problems
+}
+
+/// Shaped like `Area/City` or `UTC`, the names themselves are not known to
+/// the server
+fn is_timezone(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.split('/').all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "_-+".contains(c))
+        })
+}
+
+/// Offset of the time zone of the user, None if they never set one
+pub fn utc_offset(preference: &UserPreference) -> Option<UtcOffset> {
+    UtcOffset::from_whole_seconds(preference.utc_offset? * 60).ok()
+}
+
+/// Replace the preference if it is still at `version`, return the new version,
+/// None if it was written since
+pub async fn write(
+    conn: &impl ConnectionTrait,
+    user_id: i32,
+    version: i32,
+    preference: UserPreference,
+) -> Result<Option<i32>> {
+    let res = User::update_many()
+        .col_expr(user::Column::Preference, Expr::value(preference))
+        .col_expr(
+            user::Column::PreferenceVersion,
+            Expr::col(user::Column::PreferenceVersion).add(1),
+        )
+        .filter(user::Column::Id.eq(user_id))
+        .filter(user::Column::PreferenceVersion.eq(version))
+        .exec(conn)
+        .await?;
+    Ok((res.rows_affected > 0).then_some(version + 1))
+}
@@ -0,0 +1,73 @@
+//! Terms a user tune their search with, see `SearchTermKind`
+//!
+//! Ignored terms are applied by the triggers of `message_fts` through the
+//! `search_document` view, changing them reindex every message of the user
+
+use anyhow::Result;
+use entity::{SearchTermKind, prelude::*, search_term};
+use sea_orm::{ActiveValue::Set, ConnectionTrait, QueryOrder, prelude::*};
+
+use super::sql;
+
+/// Terms of a kind in the order they were written
+pub async fn load(
+    conn: &impl ConnectionTrait,
+    user_id: i32,
+    kind: SearchTermKind,
+) -> Result<Vec<String>> {
+    Ok(SearchTerm::find()
+        .filter(search_term::Column::UserId.eq(user_id))
+        .filter(search_term::Column::Kind.eq(kind))
+        .order_by_asc(search_term::Column::Id)
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|x| x.term)
+        .collect())
+}
+
+/// Replace every term of the user and rebuild its index
+pub async fn replace(
+    conn: &impl ConnectionTrait,
+    user_id: i32,
+    boost: Vec<String>,
+    ignore: Vec<String>,
+) -> Result<()> {
+    SearchTerm::delete_many()
+        .filter(search_term::Column::UserId.eq(user_id))
+        .exec(conn)
+        .await?;
+    let terms: Vec<_> = boost
+        .into_iter()
+        .map(|x| (SearchTermKind::Boost, x))
+        .chain(ignore.into_iter().map(|x| (SearchTermKind::Ignore, x)))
+        .map(|(kind, term)| search_term::ActiveModel {
+            user_id: Set(user_id),
+            kind: Set(kind),
+            term: Set(term),
+            ..Default::default()
+        })
+        .collect();
+    if !terms.is_empty() {
+        SearchTerm::insert_many(terms).exec(conn).await?;
+    }
+    reindex(conn, user_id).await
+}
+
+async fn reindex(conn: &impl ConnectionTrait, user_id: i32) -> Result<()> {
+    let backend = conn.get_database_backend();
+    conn.execute(sql::statement(
+        backend,
+        "DELETE FROM message_fts WHERE rowid IN (SELECT id FROM search_document WHERE owner_id = ?)",
+        [user_id.into()],
+    ))
+    .await?;
+    conn.execute(sql::statement(
+        backend,
+        "INSERT INTO message_fts (rowid, content, message_id)
+        SELECT id, content, message_id FROM search_document WHERE owner_id = ?",
+        [user_id.into()],
+    ))
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,210 @@
+//! Encryption of the credentials kept in the database, such as the API keys of
+//! tools in `workspace_credential` and of the upstream in `admin/config`
+//!
+//! Envelope encryption: values are sealed with AES-256-GCM under a data key
+//! made once and kept in `config`, itself sealed under the master key, which
+//! never touches the database. A dump or a backup of the database alone thus
+//! reads no credential. The master key is, first found:
+//!
+//! - `SECRETS_KEY` env, 32 bytes in base64
+//! - the file at `SECRETS_KEY_FILE` env, made on the first start
+//! - `secrets_key` of the systemd credentials of the service, see
+//!   `LoadCredentialEncrypted=`, so it can be sealed by the TPM of the host
+//! - `secrets.key` in the working directory, made on the first start
+//!
+//! Values written before are sealed on start, see [`seal_existing`]; a value
+//! without the [`PREFIX`] is still read as is
+
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::{Context, Result, anyhow};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use entity::{config, prelude::*};
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ConnectionTrait, EntityTrait, IntoActiveModel,
+    sea_query::OnConflict,
+};
+
+/// Where the sealed data key is kept in `config`
+const DATA_KEY: &str = "secrets_data_key";
+/// Marks a sealed value, followed by the nonce and the ciphertext in base64
+const PREFIX: &str = "enc:v1:";
+/// Made on the first start when no other key is given
+const DEFAULT_KEY_FILE: &str = "secrets.key";
+const KEY_LEN: usize = 32;
+
+/// Seals and opens the credentials of the database
+#[derive(Clone)]
+pub struct Secrets {
+    key: Arc<[u8; KEY_LEN]>,
+}
+
+impl Secrets {
+    /// Open the data key with the master key, making both on the first start
+    pub async fn load(conn: &impl ConnectionTrait) -> Result<Self> {
+        let master = master_key()?;
+        let sealed = match Config::find_by_id(DATA_KEY).one(conn).await? {
+            Some(x) => x.value,
+            None => {
+                let sealed = seal(&master, &random_key()?)?;
+                Config::insert(config::ActiveModel {
+                    key: Set(DATA_KEY.to_owned()),
+                    value: Set(sealed),
+                })
+                // another instance on the same database may have made one
+                .on_conflict(
+                    OnConflict::column(config::Column::Key)
+                        .do_nothing()
+                        .to_owned(),
+                )
+                .do_nothing()
+                .exec(conn)
+                .await?;
+                Config::find_by_id(DATA_KEY)
+                    .one(conn)
+                    .await?
+                    .context("Cannot find secrets data key")?
+                    .value
+            }
+        };
+        let key = open(&master, &sealed).context(
+            "Cannot open the secrets data key, is the master key the one it was made with?",
+        )?;
+        let key: [u8; KEY_LEN] = key
+            .try_into()
+            .map_err(|_| anyhow!("Malformed secrets data key"))?;
+        Ok(Self { key: Arc::new(key) })
+    }
+
+    /// Seal a value to store
+    pub fn seal(&self, value: &str) -> Result<String> {
+        let sealed = seal(&self.key, value.as_bytes())?;
+        Ok(format!("{}{}", PREFIX, STANDARD.encode(sealed)))
+    }
+
+    /// Open a stored value, one stored before encryption is returned as is
+    pub fn open(&self, stored: &str) -> Result<String> {
+        let Some(sealed) = stored.strip_prefix(PREFIX) else {
+            return Ok(stored.to_owned());
+        };
+        let value = open(&self.key, &STANDARD.decode(sealed)?)?;
+        Ok(String::from_utf8(value)?)
+    }
+
+    /// Whether a stored value is sealed
+    pub fn is_sealed(stored: &str) -> bool {
+        stored.starts_with(PREFIX)
+    }
+}
+
+/// Seal the credentials of workspaces stored before encryption, return how
+/// many were
+pub async fn seal_existing(conn: &impl ConnectionTrait, secrets: &Secrets) -> Result<usize> {
+    let plain: Vec<_> = WorkspaceCredential::find()
+        .all(conn)
+        .await?
+        .into_iter()
+        .filter(|x| !Secrets::is_sealed(&x.value))
+        .collect();
+    let count = plain.len();
+    for credential in plain {
+        let value = secrets.seal(&credential.value)?;
+        let mut credential = credential.into_active_model();
+        credential.value = Set(value);
+        credential.update(conn).await?;
+    }
+    Ok(count)
+}
+
+fn master_key() -> Result<[u8; KEY_LEN]> {
+    if let Ok(key) = dotenv::var("SECRETS_KEY") {
+        return decode_key(&key).context("Malformed SECRETS_KEY");
+    }
+    let systemd = dotenv::var("CREDENTIALS_DIRECTORY")
+        .map(|dir| PathBuf::from(dir).join("secrets_key"))
+        .ok()
+        .filter(|x| x.exists());
+    // the credentials of systemd are read-only
+    let (path, make) = match (dotenv::var("SECRETS_KEY_FILE"), systemd) {
+        (Ok(path), _) => (PathBuf::from(path), true),
+        (_, Some(path)) => (path, false),
+        _ => (PathBuf::from(DEFAULT_KEY_FILE), true),
+    };
+    if make && !path.exists() {
+        let key = random_key()?;
+        write_key_file(&path, &STANDARD.encode(key))
+            .with_context(|| format!("Cannot write {}", path.display()))?;
+        tracing::warn!(
+            "made a secrets key in {}, back it up: the credentials cannot be read without it",
+            path.display()
+        );
+        return Ok(key);
+    }
+    let key = std::fs::read_to_string(&path)
+        .with_context(|| format!("Cannot read secrets key {}", path.display()))?;
+    decode_key(&key).with_context(|| format!("Malformed secrets key {}", path.display()))
+}
+
+fn decode_key(key: &str) -> Result<[u8; KEY_LEN]> {
+    STANDARD
+        .decode(key.trim())?
+        .try_into()
+        .map_err(|_| anyhow!("expected {} bytes", KEY_LEN))
+}
+
+#[cfg(unix)]
+fn write_key_file(path: &std::path::Path, key: &str) -> std::io::Result<()> {
+    use std::{io::Write, os::unix::fs::OpenOptionsExt};
+
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?
+        .write_all(key.as_bytes())
+}
+
+#[cfg(not(unix))]
+fn write_key_file(path: &std::path::Path, key: &str) -> std::io::Result<()> {
+    std::fs::write(path, key)
+}
+
+fn random_key() -> Result<[u8; KEY_LEN]> {
+    let mut key = [0u8; KEY_LEN];
+    getrandom::fill(&mut key).map_err(|e| anyhow!("Cannot generate key: {}", e))?;
+    Ok(key)
+}
+
+fn cipher(key: &[u8; KEY_LEN]) -> Result<LessSafeKey> {
+    let key = UnboundKey::new(&AES_256_GCM, key).map_err(|_| anyhow!("Malformed key"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+/// Nonce followed by the ciphertext and its tag
+fn seal(key: &[u8; KEY_LEN], plain: &[u8]) -> Result<Vec<u8>> {
+    let mut nonce = [0u8; NONCE_LEN];
+    getrandom::fill(&mut nonce).map_err(|e| anyhow!("Cannot generate nonce: {}", e))?;
+    let mut sealed = plain.to_vec();
+    cipher(key)?
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::empty(),
+            &mut sealed,
+        )
+        .map_err(|_| anyhow!("Cannot seal value"))?;
+    Ok([nonce.as_slice(), &sealed].concat())
+}
+
+fn open(key: &[u8; KEY_LEN], sealed: &[u8]) -> Result<Vec<u8>> {
+    if sealed.len() < NONCE_LEN {
+        return Err(anyhow!("Malformed sealed value"));
+    }
+    let (nonce, sealed) = sealed.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow!("Malformed nonce"))?;
+    let mut sealed = sealed.to_vec();
+    let plain = cipher(key)?
+        .open_in_place(nonce, Aad::empty(), &mut sealed)
+        .map_err(|_| anyhow!("Cannot open sealed value"))?;
+    Ok(plain.to_vec())
+}
@@ -0,0 +1,212 @@
+//! Short-lived access tokens, renewed with refresh tokens stored hashed in the DB
+//!
+//! A session is a signed in device, its refresh token rotate but its id stay
+
+use std::{net::SocketAddr, time::Duration};
+
+use anyhow::Result;
+use entity::{prelude::*, session};
+use http::HeaderMap;
+use pasetors::claims::Claims;
+use sea_orm::{ActiveValue::Set, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::{ACCESS_TOKEN_SECS, REFRESH_TOKEN_SECS},
+    utils::{client, keyring::Keyring, workspace},
+};
+
+/// Shown in the list of sessions
+#[derive(Debug, Clone)]
+pub struct Device {
+    pub label: Option<String>,
+    pub ip: String,
+}
+
+impl Device {
+    pub fn new(headers: &HeaderMap, addr: SocketAddr) -> Self {
+        Self {
+            label: client::label(headers),
+            ip: client::ip(headers, addr).to_string(),
+        }
+    }
+}
+
+/// Return the token and its expiry in RFC 3339
+///
+/// The token stop working once the session is revoked. It is for the workspace
+/// selected in the session, or the first one of the user if they left it, see
+/// `utils::workspace`
+pub async fn access_token(
+    conn: &impl ConnectionTrait,
+    keyring: &Keyring,
+    user_id: i32,
+    session_id: i32,
+) -> Result<(String, String)> {
+    let selected = Session::find_by_id(session_id)
+        .one(conn)
+        .await?
+        .and_then(|x| x.workspace_id);
+    let workspace_id = workspace::resolve(conn, user_id, selected).await?;
+
+    let mut claim = Claims::new_expires_in(&Duration::from_secs(ACCESS_TOKEN_SECS))?;
+
+    // safety:
+    // "uid", "sid" and "wid" are not reserve
+    claim.add_additional("uid", user_id).unwrap();
+    claim.add_additional("sid", session_id).unwrap();
+    if let Some(workspace_id) = workspace_id {
+        claim.add_additional("wid", workspace_id).unwrap();
+    }
+
+    // safety:
+    // "exp" must exists
+    let exp = claim.get_claim("exp").unwrap().as_str().unwrap().to_owned();
+
+    Ok((keyring.encrypt(&claim)?, exp))
+}
+
+/// Start a session, return its id and refresh token
+pub async fn create(
+    conn: &impl ConnectionTrait,
+    user_id: i32,
+    device: Device,
+) -> Result<(i32, String)> {
+    let token = random_token()?;
+    let now = now();
+    // sessions nobody refreshed in time
+    Session::delete_many()
+        .filter(session::Column::UserId.eq(user_id))
+        .filter(session::Column::ExpiresAt.lt(now))
+        .exec(conn)
+        .await?;
+    let id = Session::insert(session::ActiveModel {
+        user_id: Set(user_id),
+        token_hash: Set(hash(&token)),
+        created_at: Set(now),
+        expires_at: Set(now + REFRESH_TOKEN_SECS),
+        label: Set(device.label),
+        ip: Set(Some(device.ip)),
+        last_seen_at: Set(Some(now)),
+        ..Default::default()
+    })
+    .exec(conn)
+    .await?
+    .last_insert_id;
+    Ok((id, token))
+}
+
+/// Exchange a refresh token for a new one, the old one stop working
+///
+/// Return the user, the session and the new token, or None if the token is
+/// unknown, revoked or expired
+pub async fn rotate(
+    conn: &impl ConnectionTrait,
+    token: &str,
+    device: Device,
+) -> Result<Option<(i32, i32, String)>> {
+    let Some(session) = find(conn, token).await? else {
+        return Ok(None);
+    };
+    let now = now();
+    if session.expires_at < now {
+        Session::delete_by_id(session.id).exec(conn).await?;
+        return Ok(None);
+    }
+
+    let token = random_token()?;
+    Session::update(session::ActiveModel {
+        id: Set(session.id),
+        token_hash: Set(hash(&token)),
+        expires_at: Set(now + REFRESH_TOKEN_SECS),
+        ip: Set(Some(device.ip)),
+        last_seen_at: Set(Some(now)),
+        ..Default::default()
+    })
+    .exec(conn)
+    .await?;
+    Ok(Some((session.user_id, session.id, token)))
+}
+
+/// Work in another workspace from now on, the caller check the membership
+pub async fn select_workspace(
+    conn: &impl ConnectionTrait,
+    session_id: i32,
+    workspace_id: i32,
+) -> Result<()> {
+    Session::update(session::ActiveModel {
+        id: Set(session_id),
+        workspace_id: Set(Some(workspace_id)),
+        ..Default::default()
+    })
+    .exec(conn)
+    .await?;
+    Ok(())
+}
+
+/// User and expiry of a session, None once it is revoked
+pub async fn expiry(conn: &impl ConnectionTrait, session_id: i32) -> Result<Option<(i32, i64)>> {
+    Ok(Session::find_by_id(session_id)
+        .one(conn)
+        .await?
+        .map(|x| (x.user_id, x.expires_at)))
+}
+
+/// Whether tokens of a session of [`expiry`] are still accepted
+pub fn alive(expiry: Option<(i32, i64)>) -> bool {
+    expiry.is_some_and(|(_, expires_at)| expires_at >= now())
+}
+
+/// Sign out a device of the user, return false if there is no such session
+pub async fn revoke_by_id(
+    conn: &impl ConnectionTrait,
+    user_id: i32,
+    session_id: i32,
+) -> Result<bool> {
+    let res = Session::delete_many()
+        .filter(session::Column::Id.eq(session_id))
+        .filter(session::Column::UserId.eq(user_id))
+        .exec(conn)
+        .await?;
+    Ok(res.rows_affected > 0)
+}
+
+/// Return the id of the revoked session, None if the token was not valid anyway
+pub async fn revoke(conn: &impl ConnectionTrait, token: &str) -> Result<Option<i32>> {
+    let Some(session) = find(conn, token).await? else {
+        return Ok(None);
+    };
+    let res = Session::delete_by_id(session.id).exec(conn).await?;
+    Ok((res.rows_affected > 0).then_some(session.id))
+}
+
+/// Sign a user out of every device
+pub async fn revoke_all(conn: &impl ConnectionTrait, user_id: i32) -> Result<u64> {
+    let res = Session::delete_many()
+        .filter(session::Column::UserId.eq(user_id))
+        .exec(conn)
+        .await?;
+    Ok(res.rows_affected)
+}
+
+async fn find(conn: &impl ConnectionTrait, token: &str) -> Result<Option<session::Model>> {
+    Ok(Session::find()
+        .filter(session::Column::TokenHash.eq(hash(token)))
+        .one(conn)
+        .await?)
+}
+
+fn random_token() -> Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|e| anyhow::anyhow!("Cannot generate token: {}", e))?;
+    Ok(bytes.iter().map(|x| format!("{:02x}", x)).collect())
+}
+
+/// Refresh tokens are random, a plain hash is enough
+fn hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+fn now() -> i64 {
+    time::UtcDateTime::now().unix_timestamp()
+}
@@ -0,0 +1,56 @@
+//! Raw SQL written once for SQLite and PostgreSQL, see `DATABASE_URL`
+//!
+//! Queries are written in the SQL both understand, with `?` placeholders;
+//! the few functions they spell differently are picked here by backend
+
+use sea_orm::{DbBackend, Statement, Value};
+
+/// A statement of `sql`, whose `?` are numbered `$1`, `$2`… for PostgreSQL
+pub fn statement(
+    backend: DbBackend,
+    sql: impl AsRef<str>,
+    values: impl IntoIterator<Item = Value>,
+) -> Statement {
+    let sql = sql.as_ref();
+    let sql = match backend {
+        DbBackend::Postgres => numbered(sql),
+        _ => sql.to_owned(),
+    };
+    Statement::from_sql_and_values(backend, sql, values)
+}
+
+/// `?` outside of string literals replaced by `$n`
+fn numbered(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let (mut n, mut quoted) = (0, false);
+    for c in sql.chars() {
+        match c {
+            '\'' => {
+                quoted = !quoted;
+                out.push(c);
+            }
+            '?' if !quoted => {
+                n += 1;
+                out.push_str(&format!("${}", n));
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Text of `key` in the JSON object of the text column `column`
+pub fn json_text(backend: DbBackend, column: &str, key: &str) -> String {
+    match backend {
+        DbBackend::Postgres => format!("({}::jsonb ->> '{}')", column, key),
+        _ => format!("json_extract({}, '$.{}')", column, key),
+    }
+}
+
+/// Position of `needle` in `haystack` from 1, 0 when it is not in it
+pub fn position(backend: DbBackend, haystack: &str, needle: &str) -> String {
+    match backend {
+        DbBackend::Postgres => format!("strpos({}, {})", haystack, needle),
+        _ => format!("instr({}, {})", haystack, needle),
+    }
+}
@@ -0,0 +1,179 @@
+//! Topic, sentiment and task of chats, guessed by a cheap model when
+//! `TAG_MODEL` is set
+//!
+//! A chat is tagged once it has been idle for [`TAG_IDLE_SECS`], and again
+//! after new messages went idle. Demo chats are never tagged
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{Context, Result};
+use dotenv::var;
+use entity::{ChatSentiment, ChatTask, MessageKind, chat, prelude::*};
+use sea_orm::{ConnectionTrait, prelude::*};
+use serde::{Deserialize, Deserializer, de::DeserializeOwned};
+use serde_json::Value;
+
+use crate::{
+    AppState,
+    config::{TAG_BATCH, TAG_IDLE_SECS, TAG_INTERVAL, TAG_MAX_CHARS},
+    openrouter,
+    utils::{
+        export::{Part, Transcript},
+        sql,
+    },
+};
+
+const SYSTEM_PROMPT: &str = r#"You label conversations between a user and an assistant.
+Reply with a single JSON object and nothing else:
+{"topic": "...", "sentiment": "...", "task": "..."}
+- topic: the subject in one to three lowercase english words, e.g. "rust", "travel", "tax"
+- sentiment: how the user felt by the end, one of "negative", "neutral", "positive"
+- task: what the user wanted, one of "question", "coding", "writing", "analysis", "planning", "chitchat", "other""#;
+
+/// Longest topic kept, a longer one is a sentence rather than a label
+const MAX_TOPIC_CHARS: usize = 40;
+
+#[derive(Debug, Default, Deserialize)]
+struct Tags {
+    #[serde(default)]
+    topic: Option<String>,
+    #[serde(default, deserialize_with = "lenient")]
+    sentiment: Option<ChatSentiment>,
+    #[serde(default, deserialize_with = "lenient")]
+    task: Option<ChatTask>,
+}
+
+/// Case insensitive, an unknown label is None instead of an error
+fn lenient<'de, D: Deserializer<'de>, T: DeserializeOwned>(
+    deserializer: D,
+) -> Result<Option<T>, D::Error> {
+    let value = match Value::deserialize(deserializer)? {
+        Value::String(x) => Value::String(x.trim().to_lowercase()),
+        x => x,
+    };
+    Ok(serde_json::from_value(value).ok())
+}
+
+/// Periodically tag the idle chats, do nothing without `TAG_MODEL`
+pub fn spawn(app: Arc<AppState>) {
+    let Ok(model) = var("TAG_MODEL") else {
+        return;
+    };
+    let model = openrouter::Model {
+        id: model,
+        temperature: Some(0.0),
+        repeat_penalty: None,
+        top_k: None,
+        top_p: None,
+        seed: None,
+        online: false,
+        reasoning: false,
+    };
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(TAG_INTERVAL));
+        loop {
+            interval.tick().await;
+            match tag_idle(&app, &model).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("tagged {} chats", count),
+                Err(err) => tracing::warn!("cannot tag chats: {}", err),
+            }
+        }
+    });
+}
+
+async fn tag_idle(app: &Arc<AppState>, model: &openrouter::Model) -> Result<usize> {
+    let now = time::UtcDateTime::now().unix_timestamp();
+    let chats = Chat::find()
+        .from_raw_sql(sql::statement(
+            app.conn.get_database_backend(),
+            "SELECT chat.* FROM chat
+            JOIN \"user\" ON \"user\".id = chat.owner_id
+            JOIN (SELECT chat_id, MAX(created_at) AS last_at FROM message GROUP BY chat_id) latest
+                ON latest.chat_id = chat.id
+            WHERE \"user\".demo_expires_at IS NULL AND latest.last_at < ?
+                AND (chat.tagged_at IS NULL OR chat.tagged_at < latest.last_at)
+            ORDER BY chat.id LIMIT ?",
+            [(now - TAG_IDLE_SECS).into(), TAG_BATCH.into()],
+        ))
+        .all(&app.conn)
+        .await?;
+
+    let count = chats.len();
+    for chat in chats {
+        // a chat that cannot be tagged is not retried until it has new messages
+        let tags = match tag(app, &chat, model).await {
+            Ok(tags) => tags,
+            Err(err) => {
+                tracing::warn!("cannot tag chat {}: {}", chat.id, err);
+                Tags::default()
+            }
+        };
+        Chat::update_many()
+            .col_expr(chat::Column::Topic, Expr::value(tags.topic))
+            .col_expr(chat::Column::Sentiment, Expr::value(tags.sentiment))
+            .col_expr(chat::Column::Task, Expr::value(tags.task))
+            .col_expr(chat::Column::TaggedAt, Expr::value(now))
+            .filter(chat::Column::Id.eq(chat.id))
+            .exec(&app.conn)
+            .await?;
+    }
+    Ok(count)
+}
+
+async fn tag(app: &Arc<AppState>, chat: &chat::Model, model: &openrouter::Model) -> Result<Tags> {
+    let transcript =
+        Transcript::load(&app.conn, app.instance_id.clone(), chat, chat.owner_id).await?;
+
+    let mut text = String::new();
+    for entry in &transcript.entries {
+        let role = match entry.kind {
+            MessageKind::User => "User",
+            MessageKind::Assistant => "Assistant",
+            _ => continue,
+        };
+        for part in &entry.parts {
+            if let Part::Text { content } = part {
+                text.push_str(role);
+                text.push_str(": ");
+                text.push_str(content);
+                text.push_str("\n\n");
+            }
+        }
+    }
+    if text.is_empty() {
+        return Ok(Tags::default());
+    }
+    let text: String = text.chars().take(TAG_MAX_CHARS).collect();
+
+    let response = app
+        .openrouter
+        .complete(
+            vec![
+                openrouter::Message::System(SYSTEM_PROMPT.to_owned()),
+                openrouter::Message::User(text),
+            ],
+            model.clone(),
+        )
+        .await?
+        .response;
+
+    // models like to wrap JSON in a code block
+    let json = response
+        .find('{')
+        .zip(response.rfind('}'))
+        .and_then(|(start, end)| response.get(start..=end))
+        .context("No JSON object in the response")?;
+    let mut tags: Tags = serde_json::from_str(json)?;
+    tags.topic = tags
+        .topic
+        .map(|x| {
+            x.trim()
+                .to_lowercase()
+                .chars()
+                .take(MAX_TOPIC_CHARS)
+                .collect::<String>()
+        })
+        .filter(|x| !x.is_empty());
+    Ok(tags)
+}
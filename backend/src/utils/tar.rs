@@ -0,0 +1,338 @@
+//! ustar archives of regular files, for the backups of `backup`
+//!
+//! Only what `tar` needs to list and extract them is written: a path of at
+//! most 100 bytes, the size and a mode. Reading skips entries other than
+//! regular files, e.g. the pax headers GNU tar adds when repacking
+
+use anyhow::{Result, bail};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const BLOCK: usize = 512;
+/// Largest size the 11 octal digits of the header hold
+const MAX_SIZE: u64 = 0o77777777777;
+
+pub struct Writer<W> {
+    out: W,
+}
+
+impl<W: AsyncWrite + Unpin> Writer<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+
+    /// An entry of `size` bytes read from `data`, which must hold that many
+    pub async fn append(
+        &mut self,
+        path: &str,
+        size: u64,
+        mtime: i64,
+        data: impl AsyncRead + Unpin,
+    ) -> Result<()> {
+        self.out.write_all(&header(path, size, mtime)?).await?;
+        let copied = tokio::io::copy(&mut data.take(size), &mut self.out).await?;
+        if copied != size {
+            bail!("{} ended after {} of {} bytes", path, copied, size);
+        }
+        self.pad(size).await
+    }
+
+    pub async fn append_bytes(&mut self, path: &str, mtime: i64, data: &[u8]) -> Result<()> {
+        self.out
+            .write_all(&header(path, data.len() as u64, mtime)?)
+            .await?;
+        self.out.write_all(data).await?;
+        self.pad(data.len() as u64).await
+    }
+
+    /// End the archive with its two empty blocks
+    pub async fn finish(mut self) -> Result<W> {
+        self.out.write_all(&[0; BLOCK * 2]).await?;
+        self.out.flush().await?;
+        Ok(self.out)
+    }
+
+    async fn pad(&mut self, size: u64) -> Result<()> {
+        let rest = padding(size);
+        self.out.write_all(&[0; BLOCK][..rest]).await?;
+        Ok(())
+    }
+}
+
+fn header(path: &str, size: u64, mtime: i64) -> Result<[u8; BLOCK]> {
+    if path.is_empty() || path.len() > 100 {
+        bail!("cannot archive {}, paths are 1 to 100 bytes", path);
+    }
+    if size > MAX_SIZE {
+        bail!("cannot archive {}, larger than {} bytes", path, MAX_SIZE);
+    }
+    let mut header = [0u8; BLOCK];
+    header[..path.len()].copy_from_slice(path.as_bytes());
+    octal(&mut header[100..108], 0o644);
+    octal(&mut header[108..116], 0);
+    octal(&mut header[116..124], 0);
+    octal(&mut header[124..136], size);
+    octal(&mut header[136..148], mtime.max(0) as u64);
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // summed with its own field as spaces
+    header[148..156].fill(b' ');
+    let sum: u32 = header.iter().map(|x| u32::from(*x)).sum();
+    octal(&mut header[148..155], sum.into());
+    Ok(header)
+}
+
+/// Zero padded octal digits and a NUL
+fn octal(field: &mut [u8], value: u64) {
+    let digits = format!("{:0width$o}", value, width = field.len() - 1);
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+    field[digits.len()] = 0;
+}
+
+fn padding(size: u64) -> usize {
+    (BLOCK - (size % BLOCK as u64) as usize) % BLOCK
+}
+
+pub struct Entry {
+    pub path: String,
+    pub size: u64,
+}
+
+pub struct Reader<R> {
+    input: R,
+    /// Bytes of the current entry not read yet
+    data: u64,
+    /// Zeros after the data of the current entry, up to the next block
+    pad: u64,
+}
+
+impl<R: AsyncRead + Unpin> Reader<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            input,
+            data: 0,
+            pad: 0,
+        }
+    }
+
+    /// The next regular file, None at the end of the archive
+    pub async fn next(&mut self) -> Result<Option<Entry>> {
+        loop {
+            self.skip(self.data + self.pad).await?;
+            let mut header = [0u8; BLOCK];
+            self.input.read_exact(&mut header).await?;
+            if header.iter().all(|x| *x == 0) {
+                return Ok(None);
+            }
+
+            let mut sum = header;
+            sum[148..156].fill(b' ');
+            let sum: u32 = sum.iter().map(|x| u32::from(*x)).sum();
+            if parse_octal(&header[148..156]).ok() != Some(u64::from(sum)) {
+                bail!("not a tar archive, or a corrupted one");
+            }
+
+            let size = parse_octal(&header[124..136])?;
+            (self.data, self.pad) = (size, padding(size) as u64);
+            if !matches!(header[156], b'0' | 0) {
+                continue;
+            }
+
+            let name = text(&header[..100]);
+            let prefix = match &header[257..263] == b"ustar\0" {
+                true => text(&header[345..500]),
+                false => String::new(),
+            };
+            let path = match prefix.is_empty() {
+                true => name,
+                false => format!("{}/{}", prefix, name),
+            };
+            return Ok(Some(Entry { path, size }));
+        }
+    }
+
+    /// Copy the data of the current entry to `out`
+    pub async fn copy_to(&mut self, out: &mut (impl AsyncWrite + Unpin)) -> Result<()> {
+        let copied = tokio::io::copy(&mut (&mut self.input).take(self.data), out).await?;
+        if copied != self.data {
+            bail!("the archive ends within an entry");
+        }
+        self.data = 0;
+        Ok(())
+    }
+
+    pub async fn bytes(&mut self) -> Result<Vec<u8>> {
+        // the size of a header is not trusted before the data is there
+        let mut data = Vec::with_capacity(self.data.min(1 << 20) as usize);
+        self.copy_to(&mut data).await?;
+        Ok(data)
+    }
+
+    async fn skip(&mut self, len: u64) -> Result<()> {
+        let skipped =
+            tokio::io::copy(&mut (&mut self.input).take(len), &mut tokio::io::sink()).await?;
+        if skipped != len {
+            bail!("the archive ends within an entry");
+        }
+        (self.data, self.pad) = (0, 0);
+        Ok(())
+    }
+}
+
+fn text(field: &[u8]) -> String {
+    let end = field.iter().position(|x| *x == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+fn parse_octal(field: &[u8]) -> Result<u64> {
+    let digits = text(field);
+    let digits = digits.trim_matches(|x: char| x == ' ' || x == '\0');
+    match u64::from_str_radix(digits, 8) {
+        Ok(x) => Ok(x),
+        Err(_) => bail!("malformed number in a tar header"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn archive(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut tar = Writer::new(Vec::new());
+        for (path, data) in entries {
+            tar.append_bytes(path, 1_791_964_800, data).await.unwrap();
+        }
+        tar.finish().await.unwrap()
+    }
+
+    async fn read_all(data: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+        let mut tar = Reader::new(data);
+        let mut entries = vec![];
+        while let Some(entry) = tar.next().await? {
+            let data = tar.bytes().await?;
+            assert_eq!(entry.size, data.len() as u64);
+            entries.push((entry.path, data));
+        }
+        Ok(entries)
+    }
+
+    /// A header with `typeflag`, its checksum redone
+    fn with_type(header: [u8; BLOCK], typeflag: u8) -> [u8; BLOCK] {
+        let mut header = header;
+        header[156] = typeflag;
+        header[148..156].fill(b' ');
+        let sum: u32 = header.iter().map(|x| u32::from(*x)).sum();
+        octal(&mut header[148..155], sum.into());
+        header
+    }
+
+    #[tokio::test]
+    async fn entries_read_back() {
+        let entries: Vec<(String, Vec<u8>)> = [
+            ("db.sqlite", vec![7; 5000]),
+            ("files/empty", vec![]),
+            ("files/block", vec![1; BLOCK]),
+            ("files/block+1", vec![2; BLOCK + 1]),
+        ]
+        .into_iter()
+        .map(|(path, data)| (path.to_owned(), data))
+        .collect();
+        let refs: Vec<(&str, &[u8])> = entries
+            .iter()
+            .map(|(path, data)| (path.as_str(), data.as_slice()))
+            .collect();
+        let tar = archive(&refs).await;
+        assert_eq!(tar.len() % BLOCK, 0);
+        assert_eq!(read_all(&tar).await.unwrap(), entries);
+    }
+
+    #[tokio::test]
+    async fn unread_entries_are_skipped() {
+        let tar = archive(&[("a", &[1; 700]), ("b", b"b")]).await;
+        let mut reader = Reader::new(tar.as_slice());
+        assert_eq!(reader.next().await.unwrap().unwrap().path, "a");
+        assert_eq!(reader.next().await.unwrap().unwrap().path, "b");
+        assert_eq!(reader.bytes().await.unwrap(), b"b");
+        assert!(reader.next().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn headers_are_ustar() {
+        let tar = archive(&[("files/a", b"hello")]).await;
+        assert_eq!(&tar[..8], b"files/a\0");
+        assert_eq!(&tar[124..136], b"00000000005\0");
+        assert_eq!(&tar[136..148], b"15263633200\0");
+        assert_eq!(&tar[257..265], b"ustar\x0000");
+        assert_eq!(&tar[BLOCK..BLOCK + 5], b"hello");
+    }
+
+    #[tokio::test]
+    async fn paths_must_fit_the_header() {
+        let mut tar = Writer::new(Vec::new());
+        assert!(tar.append_bytes("", 0, b"").await.is_err());
+        assert!(tar.append_bytes(&"a".repeat(101), 0, b"").await.is_err());
+        assert!(tar.append_bytes(&"a".repeat(100), 0, b"").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn short_data_fails() {
+        let mut tar = Writer::new(Vec::new());
+        let err = tar.append("a", 10, 0, &b"12345"[..]).await.unwrap_err();
+        assert!(err.to_string().contains("ended after 5 of 10 bytes"));
+    }
+
+    #[tokio::test]
+    async fn other_entries_are_skipped() {
+        // a pax header as GNU tar writes before a file, then the file
+        let pax = b"30 mtime=1791964800.123456789\n";
+        let mut tar =
+            with_type(header("PaxHeaders/a", pax.len() as u64, 0).unwrap(), b'x').to_vec();
+        tar.extend(pax);
+        tar.resize(BLOCK * 2, 0);
+        tar.extend(with_type(header("dir/", 0, 0).unwrap(), b'5'));
+        tar.extend(archive(&[("dir/a", b"a")]).await);
+        assert_eq!(
+            read_all(&tar).await.unwrap(),
+            vec![("dir/a".to_owned(), b"a".to_vec())]
+        );
+    }
+
+    #[tokio::test]
+    async fn long_paths_join_the_prefix() {
+        let mut tar = archive(&[("name", b"x")]).await;
+        tar[345..351].copy_from_slice(b"prefix");
+        let tar = [
+            &with_type(tar[..BLOCK].try_into().unwrap(), b'0')[..],
+            &tar[BLOCK..],
+        ]
+        .concat();
+        assert_eq!(read_all(&tar).await.unwrap()[0].0, "prefix/name");
+    }
+
+    #[tokio::test]
+    async fn corrupted_archives_fail() {
+        let tar = archive(&[("a", &[1; 700])]).await;
+
+        let mut bad = tar.clone();
+        bad[0] = b'b';
+        let err = read_all(&bad).await.unwrap_err();
+        assert!(err.to_string().contains("not a tar archive"));
+
+        // within the header, the data or the first end block, the second is
+        // not read
+        for len in [100, BLOCK + 10, tar.len() - BLOCK - 10] {
+            assert!(read_all(&tar[..len]).await.is_err(), "{}", len);
+        }
+        assert!(read_all(b"not a tar archive").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn a_huge_size_fails_at_the_data() {
+        let mut tar = with_type(header("a", MAX_SIZE, 0).unwrap(), b'0').to_vec();
+        tar.extend([0; BLOCK * 2]);
+        let mut reader = Reader::new(tar.as_slice());
+        assert_eq!(reader.next().await.unwrap().unwrap().size, MAX_SIZE);
+        assert!(reader.bytes().await.is_err());
+    }
+}
@@ -0,0 +1,265 @@
+//! Time-based one-time passwords (RFC 6238) and hashed recovery codes
+
+use anyhow::Result;
+use entity::{prelude::*, recovery_code, totp};
+use hmac::{Hmac, Mac};
+use sea_orm::{ActiveValue::Set, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use url::form_urlencoded;
+
+use crate::config::{TOTP_RECOVERY_CODES, TOTP_SKEW};
+
+const ISSUER: &str = "llumen";
+const STEP: i64 = 30;
+const DIGITS: u32 = 6;
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// A new secret in base32
+pub fn secret() -> Result<String> {
+    Ok(base32(&random(20)?))
+}
+
+/// Scanned by authenticator apps
+pub fn uri(secret: &str, username: &str) -> String {
+    let query = form_urlencoded::Serializer::new(String::new())
+        .extend_pairs([
+            ("secret", secret),
+            ("issuer", ISSUER),
+            ("algorithm", "SHA1"),
+            ("digits", "6"),
+            ("period", "30"),
+        ])
+        .finish();
+    let label: String =
+        form_urlencoded::byte_serialize(format!("{}:{}", ISSUER, username).as_bytes()).collect();
+    format!("otpauth://totp/{}?{}", label, query)
+}
+
+/// Return the time step the code belong to, None if it is wrong
+///
+/// Steps up to `last_step` are rejected, so each code only work once
+pub fn check_code(secret: &str, code: &str, last_step: Option<i64>) -> Option<i64> {
+    let now = time::UtcDateTime::now().unix_timestamp();
+    check_code_at(secret, code, last_step, now)
+}
+
+/// [`check_code`] at the unix time `at`
+fn check_code_at(secret: &str, code: &str, last_step: Option<i64>, at: i64) -> Option<i64> {
+    let key = decode_base32(secret)?;
+    let code: u32 = code.trim().parse().ok()?;
+    let now = at / STEP;
+
+    (now - TOTP_SKEW..=now + TOTP_SKEW)
+        .filter(|step| last_step.is_none_or(|last| *step > last))
+        .find(|step| hotp(&key, *step as u64) == code)
+}
+
+/// Check a TOTP or a recovery code of an enabled user, a recovery code is consumed
+///
+/// Return true if the user has no TOTP enabled
+pub async fn verify(conn: &impl ConnectionTrait, user_id: i32, code: Option<&str>) -> Result<bool> {
+    let Some(model) = Totp::find_by_id(user_id)
+        .one(conn)
+        .await?
+        .filter(|x| x.enabled_at.is_some())
+    else {
+        return Ok(true);
+    };
+    let Some(code) = code else {
+        return Ok(false);
+    };
+
+    if let Some(step) = check_code(&model.secret, code, model.last_step) {
+        Totp::update(totp::ActiveModel {
+            user_id: Set(user_id),
+            last_step: Set(Some(step)),
+            ..Default::default()
+        })
+        .exec(conn)
+        .await?;
+        return Ok(true);
+    }
+
+    let res = RecoveryCode::delete_many()
+        .filter(recovery_code::Column::UserId.eq(user_id))
+        .filter(recovery_code::Column::CodeHash.eq(hash(code)))
+        .exec(conn)
+        .await?;
+    Ok(res.rows_affected > 0)
+}
+
+/// Replace the recovery codes of a user, return them in plain text
+pub async fn recovery_codes(conn: &impl ConnectionTrait, user_id: i32) -> Result<Vec<String>> {
+    RecoveryCode::delete_many()
+        .filter(recovery_code::Column::UserId.eq(user_id))
+        .exec(conn)
+        .await?;
+
+    let codes = (0..TOTP_RECOVERY_CODES)
+        .map(|_| random(5).map(|x| base32(&x).to_lowercase()))
+        .collect::<Result<Vec<_>>>()?;
+    RecoveryCode::insert_many(codes.iter().map(|code| recovery_code::ActiveModel {
+        user_id: Set(user_id),
+        code_hash: Set(hash(code)),
+        ..Default::default()
+    }))
+    .exec(conn)
+    .await?;
+    Ok(codes)
+}
+
+fn hotp(key: &[u8], counter: u64) -> u32 {
+    // safety:
+    // HMAC accept keys of any length
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).unwrap();
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[19] & 0xf) as usize;
+    let bin = u32::from_be_bytes([
+        hash[offset],
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    (bin & 0x7fff_ffff) % 10u32.pow(DIGITS)
+}
+
+/// Recovery codes are random, a plain hash is enough
+fn hash(code: &str) -> String {
+    let code = code.trim().to_lowercase();
+    format!("{:x}", Sha256::digest(code.as_bytes()))
+}
+
+fn random(len: usize) -> Result<Vec<u8>> {
+    let mut bytes = vec![0u8; len];
+    getrandom::fill(&mut bytes).map_err(|e| anyhow::anyhow!("Cannot generate secret: {}", e))?;
+    Ok(bytes)
+}
+
+fn base32(bytes: &[u8]) -> String {
+    let mut res = String::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            res.push(BASE32[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        res.push(BASE32[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    res
+}
+
+fn decode_base32(s: &str) -> Option<Vec<u8>> {
+    let mut res = vec![];
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in s.trim_end_matches('=').bytes() {
+        let value = BASE32.iter().position(|x| *x == c.to_ascii_uppercase())? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            res.push((buffer >> bits) as u8);
+        }
+    }
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// "12345678901234567890", the key of the RFC test vectors
+    const SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn hotp_matches_rfc_4226() {
+        let codes = [
+            755224, 287082, 359152, 969429, 338314, 254676, 287922, 162583, 399871, 520489,
+        ];
+        for (counter, code) in codes.into_iter().enumerate() {
+            assert_eq!(hotp(b"12345678901234567890", counter as u64), code);
+        }
+    }
+
+    #[test]
+    fn codes_match_rfc_6238() {
+        // the last 6 of the 8 digits of the SHA-1 vectors
+        for (at, code) in [
+            (59, "287082"),
+            (1_111_111_109, "081804"),
+            (1_111_111_111, "050471"),
+            (1_234_567_890, "005924"),
+            (2_000_000_000, "279037"),
+        ] {
+            assert_eq!(check_code_at(SECRET, code, None, at), Some(at / STEP));
+        }
+    }
+
+    #[test]
+    fn codes_of_adjacent_steps_are_accepted() {
+        let at = 1_234_567_890;
+        let step = at / STEP;
+        let code = |step: i64| format!("{:06}", hotp(&decode_base32(SECRET).unwrap(), step as u64));
+        for skew in -TOTP_SKEW..=TOTP_SKEW {
+            assert_eq!(
+                check_code_at(SECRET, &code(step + skew), None, at),
+                Some(step + skew)
+            );
+        }
+        for far in [step - TOTP_SKEW - 1, step + TOTP_SKEW + 1] {
+            assert_eq!(check_code_at(SECRET, &code(far), None, at), None);
+        }
+    }
+
+    #[test]
+    fn a_code_only_works_once() {
+        let at = 1_234_567_890;
+        let step = check_code_at(SECRET, "005924", None, at).unwrap();
+        assert_eq!(check_code_at(SECRET, "005924", Some(step), at), None);
+        assert_eq!(check_code_at(SECRET, "005924", Some(step + 1), at), None);
+        assert_eq!(
+            check_code_at(SECRET, "005924", Some(step - 1), at),
+            Some(step)
+        );
+    }
+
+    #[test]
+    fn malformed_codes_and_secrets_fail() {
+        let at = 1_234_567_890;
+        assert_eq!(
+            check_code_at(SECRET, " 005924\n", None, at),
+            Some(at / STEP)
+        );
+        for code in ["", "abcdef", "005925", "-5924", "0059 24"] {
+            assert_eq!(check_code_at(SECRET, code, None, at), None, "{}", code);
+        }
+        assert_eq!(check_code_at("not base32!", "005924", None, at), None);
+    }
+
+    #[test]
+    fn base32_round_trips() {
+        assert_eq!(base32(b"12345678901234567890"), SECRET);
+        for len in 0..24 {
+            let bytes: Vec<u8> = (0..len).map(|x: u8| x.wrapping_mul(37) ^ 0x5a).collect();
+            assert_eq!(decode_base32(&base32(&bytes)).unwrap(), bytes);
+        }
+        // authenticator apps show secrets in lower case, some padded
+        assert_eq!(
+            decode_base32(&format!("{}====", SECRET.to_lowercase())).unwrap(),
+            b"12345678901234567890"
+        );
+        assert_eq!(decode_base32("AB1C"), None);
+    }
+
+    #[test]
+    fn recovery_codes_are_hashed_regardless_of_case() {
+        assert_eq!(hash(" Abcde-FGH "), hash("abcde-fgh"));
+        assert_ne!(hash("abcde"), hash("abcdf"));
+    }
+}
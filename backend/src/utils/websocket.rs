@@ -0,0 +1,198 @@
+//! Minimal RFC 6455 server side, text messages only
+//!
+//! axum's `ws` feature pull in tungstenite, this is all we need of it
+
+use anyhow::{Result, bail};
+use axum::{
+    extract::Request,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::Response,
+};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use hyper::upgrade::Upgraded;
+use hyper_util::rt::TokioIo;
+use sha1::{Digest, Sha1};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf};
+
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// Larger messages from the client close the connection
+const MAX_MESSAGE: usize = 1 << 20;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+pub type Io = TokioIo<Upgraded>;
+
+/// Answer the handshake, the connection is handed to `f` once upgraded
+pub fn upgrade<F, Fut>(mut req: Request, f: F) -> Result<Response, StatusCode>
+where
+    F: FnOnce(Io) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let key = handshake_key(req.headers()).ok_or(StatusCode::BAD_REQUEST)?;
+    let on_upgrade = hyper::upgrade::on(&mut req);
+
+    tokio::spawn(async move {
+        match on_upgrade.await {
+            Ok(upgraded) => f(TokioIo::new(upgraded)).await,
+            Err(err) => tracing::warn!("websocket upgrade failed: {}", err),
+        }
+    });
+
+    let mut accept = Sha1::new();
+    accept.update(key.as_bytes());
+    accept.update(GUID.as_bytes());
+    let accept = BASE64_STANDARD.encode(accept.finalize());
+
+    Response::builder()
+        .status(StatusCode::SWITCHING_PROTOCOLS)
+        .header(header::UPGRADE, HeaderValue::from_static("websocket"))
+        .header(header::CONNECTION, HeaderValue::from_static("upgrade"))
+        .header(header::SEC_WEBSOCKET_ACCEPT, accept)
+        .body(Default::default())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn handshake_key(headers: &HeaderMap) -> Option<String> {
+    let is_upgrade = headers
+        .get(header::UPGRADE)
+        .and_then(|x| x.to_str().ok())
+        .is_some_and(|x| x.eq_ignore_ascii_case("websocket"));
+    let version = headers
+        .get(header::SEC_WEBSOCKET_VERSION)
+        .is_some_and(|x| x == "13");
+    if !is_upgrade || !version {
+        return None;
+    }
+    Some(
+        headers
+            .get(header::SEC_WEBSOCKET_KEY)?
+            .to_str()
+            .ok()?
+            .to_owned(),
+    )
+}
+
+pub fn split(io: Io) -> (Reader<Io>, Writer<Io>) {
+    let (read, write) = tokio::io::split(io);
+    (
+        Reader {
+            io: read,
+            partial: Vec::new(),
+        },
+        Writer { io: write },
+    )
+}
+
+/// What the client sent
+#[derive(Debug)]
+pub enum Frame {
+    Text(String),
+    /// should be answered with a pong carrying the same payload
+    Ping(Vec<u8>),
+    Close,
+}
+
+pub struct Reader<T> {
+    io: ReadHalf<T>,
+    /// fragments of a message, control frames can come in between
+    partial: Vec<u8>,
+}
+
+impl<T: AsyncRead> Reader<T> {
+    /// Not cancel safe, run it in its own task
+    pub async fn recv(&mut self) -> Result<Frame> {
+        loop {
+            let (fin, opcode, payload) = self.frame().await?;
+            match opcode {
+                OP_TEXT | OP_BINARY | OP_CONTINUATION => {
+                    self.partial.extend_from_slice(&payload);
+                    if self.partial.len() > MAX_MESSAGE {
+                        bail!("message too large");
+                    }
+                    if fin {
+                        let message = std::mem::take(&mut self.partial);
+                        return Ok(Frame::Text(String::from_utf8(message)?));
+                    }
+                }
+                OP_PING => return Ok(Frame::Ping(payload)),
+                OP_PONG => {}
+                OP_CLOSE => return Ok(Frame::Close),
+                _ => bail!("unknown opcode {}", opcode),
+            }
+        }
+    }
+
+    async fn frame(&mut self) -> Result<(bool, u8, Vec<u8>)> {
+        let mut head = [0u8; 2];
+        self.io.read_exact(&mut head).await?;
+        let fin = head[0] & 0x80 != 0;
+        let opcode = head[0] & 0x0F;
+        if head[1] & 0x80 == 0 {
+            bail!("client frames must be masked");
+        }
+
+        let len = match head[1] & 0x7F {
+            126 => self.io.read_u16().await? as usize,
+            127 => self.io.read_u64().await? as usize,
+            len => len as usize,
+        };
+        if len > MAX_MESSAGE {
+            bail!("frame too large");
+        }
+
+        let mut mask = [0u8; 4];
+        self.io.read_exact(&mut mask).await?;
+        let mut payload = vec![0u8; len];
+        self.io.read_exact(&mut payload).await?;
+        for (i, x) in payload.iter_mut().enumerate() {
+            *x ^= mask[i % 4];
+        }
+        Ok((fin, opcode, payload))
+    }
+}
+
+pub struct Writer<T> {
+    io: WriteHalf<T>,
+}
+
+impl<T: AsyncWrite> Writer<T> {
+    pub async fn text(&mut self, text: &str) -> Result<()> {
+        self.frame(OP_TEXT, text.as_bytes()).await
+    }
+
+    pub async fn pong(&mut self, payload: &[u8]) -> Result<()> {
+        self.frame(OP_PONG, payload).await
+    }
+
+    pub async fn ping(&mut self) -> Result<()> {
+        self.frame(OP_PING, &[]).await
+    }
+
+    pub async fn close(&mut self) -> Result<()> {
+        self.frame(OP_CLOSE, &[]).await
+    }
+
+    async fn frame(&mut self, opcode: u8, payload: &[u8]) -> Result<()> {
+        let mut head = vec![0x80 | opcode];
+        match payload.len() {
+            len @ 0..=125 => head.push(len as u8),
+            len @ 126..=0xFFFF => {
+                head.push(126);
+                head.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                head.push(127);
+                head.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        self.io.write_all(&head).await?;
+        self.io.write_all(payload).await?;
+        self.io.flush().await?;
+        Ok(())
+    }
+}
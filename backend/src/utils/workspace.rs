@@ -0,0 +1,116 @@
+//! Workspaces split an instance between teams sharing it
+//!
+//! A user takes part in the workspaces listed in `workspace_member` and works
+//! in one at a time, the `wid` claim of their access token (see
+//! `utils::session`) checked by `middlewares::auth` on every request. Chats,
+//! prompt templates, tool credentials and the models offered belong to a
+//! workspace; folders, labels, memories and other personal items follow the
+//! user everywhere. Everyone joins [`DEFAULT_WORKSPACE`] unless an admin says
+//! otherwise
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use entity::{prelude::*, workspace_credential, workspace_member, workspace_model};
+use sea_orm::{ActiveValue::Set, ConnectionTrait, QueryOrder, prelude::*, sea_query::OnConflict};
+
+/// Created by the migration, where chats written before workspaces are
+pub const DEFAULT_WORKSPACE: i32 = 1;
+
+pub async fn is_member(
+    conn: &impl ConnectionTrait,
+    workspace_id: i32,
+    user_id: i32,
+) -> Result<bool> {
+    Ok(WorkspaceMember::find_by_id((workspace_id, user_id))
+        .one(conn)
+        .await?
+        .is_some())
+}
+
+/// `selected` if the user is still a member, otherwise the first workspace
+/// they joined; None for a user of no workspace
+pub async fn resolve(
+    conn: &impl ConnectionTrait,
+    user_id: i32,
+    selected: Option<i32>,
+) -> Result<Option<i32>> {
+    if let Some(id) = selected
+        && is_member(conn, id, user_id).await?
+    {
+        return Ok(Some(id));
+    }
+    let first = WorkspaceMember::find()
+        .filter(workspace_member::Column::UserId.eq(user_id))
+        .order_by_asc(workspace_member::Column::WorkspaceId)
+        .one(conn)
+        .await?;
+    Ok(first.map(|x| x.workspace_id))
+}
+
+/// Add the user to the workspace, nothing if they are in it
+pub async fn join(conn: &impl ConnectionTrait, workspace_id: i32, user_id: i32) -> Result<()> {
+    WorkspaceMember::insert(workspace_member::ActiveModel {
+        workspace_id: Set(workspace_id),
+        user_id: Set(user_id),
+    })
+    .on_conflict(
+        OnConflict::columns([
+            workspace_member::Column::WorkspaceId,
+            workspace_member::Column::UserId,
+        ])
+        .do_nothing()
+        .to_owned(),
+    )
+    .do_nothing()
+    .exec(conn)
+    .await?;
+    Ok(())
+}
+
+/// Models offered in the workspace, None for every model
+pub async fn models(conn: &impl ConnectionTrait, workspace_id: i32) -> Result<Option<Vec<i32>>> {
+    let models: Vec<i32> = WorkspaceModel::find()
+        .filter(workspace_model::Column::WorkspaceId.eq(workspace_id))
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|x| x.model_id)
+        .collect();
+    Ok((!models.is_empty()).then_some(models))
+}
+
+pub async fn allows_model(
+    conn: &impl ConnectionTrait,
+    workspace_id: i32,
+    model_id: i32,
+) -> Result<bool> {
+    Ok(models(conn, workspace_id)
+        .await?
+        .is_none_or(|x| x.contains(&model_id)))
+}
+
+/// A credential of the workspace for tools as stored, sealed by
+/// `utils::secrets`, see `ToolStore::credential`
+pub async fn credential(
+    conn: &impl ConnectionTrait,
+    workspace_id: i32,
+    name: &str,
+) -> Result<Option<String>> {
+    let credential = WorkspaceCredential::find_by_id((workspace_id, name.to_owned()))
+        .one(conn)
+        .await?;
+    Ok(credential.map(|x| x.value))
+}
+
+/// Every credential of the workspace by name as stored
+pub async fn credentials(
+    conn: &impl ConnectionTrait,
+    workspace_id: i32,
+) -> Result<BTreeMap<String, String>> {
+    let credentials = WorkspaceCredential::find()
+        .filter(workspace_credential::Column::WorkspaceId.eq(workspace_id))
+        .all(conn)
+        .await?;
+    Ok(credentials.into_iter().map(|x| (x.name, x.value)).collect())
+}
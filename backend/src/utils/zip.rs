@@ -0,0 +1,227 @@
+//! zip archives built in memory, for the takeouts of `takeout`
+//!
+//! Entries are deflated with the encoder of `utils::gzip`, or stored when that
+//! does not make them smaller, as with images. No zip64: an archive or an
+//! entry past 4 GiB is refused
+
+use anyhow::{Result, bail};
+use time::UtcDateTime;
+
+use crate::utils::gzip::{crc32, deflate_raw};
+
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+/// 2.0, the first version with deflate
+const VERSION: u16 = 20;
+/// Names are UTF-8
+const FLAG_UTF8: u16 = 1 << 11;
+
+pub struct Writer {
+    out: Vec<u8>,
+    central: Vec<u8>,
+    count: u16,
+    /// MS-DOS time and date of every entry
+    time: u16,
+    date: u16,
+}
+
+impl Writer {
+    /// Entries are dated `at`
+    pub fn new(at: UtcDateTime) -> Self {
+        let time =
+            ((at.hour() as u16) << 11) | ((at.minute() as u16) << 5) | (at.second() as u16 / 2);
+        let date = (((at.year() - 1980).max(0) as u16) << 9)
+            | ((at.month() as u16) << 5)
+            | at.day() as u16;
+        Self {
+            out: Vec::new(),
+            central: Vec::new(),
+            count: 0,
+            time,
+            date,
+        }
+    }
+
+    pub fn append(&mut self, path: &str, data: &[u8]) -> Result<()> {
+        if self.count == u16::MAX {
+            bail!("too many entries for a zip archive");
+        }
+        let deflated = deflate_raw(data);
+        let (method, body) = match deflated.len() < data.len() {
+            true => (DEFLATED, deflated.as_slice()),
+            false => (STORED, data),
+        };
+        let (Ok(size), Ok(compressed), Ok(offset), Ok(name_len)) = (
+            u32::try_from(data.len()),
+            u32::try_from(body.len()),
+            u32::try_from(self.out.len()),
+            u16::try_from(path.len()),
+        ) else {
+            bail!("{} is too large for a zip archive", path);
+        };
+        let crc = crc32(data);
+
+        let out = &mut self.out;
+        out.extend(b"PK\x03\x04");
+        out.extend(VERSION.to_le_bytes());
+        out.extend(FLAG_UTF8.to_le_bytes());
+        out.extend(method.to_le_bytes());
+        out.extend(self.time.to_le_bytes());
+        out.extend(self.date.to_le_bytes());
+        out.extend(crc.to_le_bytes());
+        out.extend(compressed.to_le_bytes());
+        out.extend(size.to_le_bytes());
+        out.extend(name_len.to_le_bytes());
+        // no extra field
+        out.extend(0u16.to_le_bytes());
+        out.extend(path.as_bytes());
+        out.extend(body);
+
+        let central = &mut self.central;
+        central.extend(b"PK\x01\x02");
+        // made by: version, unix
+        central.extend(((3u16 << 8) | VERSION).to_le_bytes());
+        central.extend(VERSION.to_le_bytes());
+        central.extend(FLAG_UTF8.to_le_bytes());
+        central.extend(method.to_le_bytes());
+        central.extend(self.time.to_le_bytes());
+        central.extend(self.date.to_le_bytes());
+        central.extend(crc.to_le_bytes());
+        central.extend(compressed.to_le_bytes());
+        central.extend(size.to_le_bytes());
+        central.extend(name_len.to_le_bytes());
+        // extra field, comment, disk, internal attributes
+        central.extend([0; 8]);
+        // external attributes: a regular file readable by everyone
+        central.extend((0o100644u32 << 16).to_le_bytes());
+        central.extend(offset.to_le_bytes());
+        central.extend(path.as_bytes());
+
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<Vec<u8>> {
+        let (Ok(offset), Ok(size)) = (
+            u32::try_from(self.out.len()),
+            u32::try_from(self.central.len()),
+        ) else {
+            bail!("too large for a zip archive");
+        };
+        self.out.append(&mut self.central);
+        self.out.extend(b"PK\x05\x06");
+        // this disk, the disk of the central directory
+        self.out.extend([0; 4]);
+        self.out.extend(self.count.to_le_bytes());
+        self.out.extend(self.count.to_le_bytes());
+        self.out.extend(size.to_le_bytes());
+        self.out.extend(offset.to_le_bytes());
+        // no comment
+        self.out.extend(0u16.to_le_bytes());
+        Ok(self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::DeflateDecoder;
+    use time::macros::datetime;
+
+    use super::*;
+
+    fn u16_at(data: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([data[at], data[at + 1]])
+    }
+
+    fn u32_at(data: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+    }
+
+    fn noise(len: usize) -> Vec<u8> {
+        let mut data = vec![0; len];
+        fastrand::Rng::with_seed(7).fill(&mut data);
+        data
+    }
+
+    /// Names and contents of the local headers, checked against their sizes
+    /// and CRC
+    fn entries(zip: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let mut entries = vec![];
+        let mut at = 0;
+        while zip[at..].starts_with(b"PK\x03\x04") {
+            let (method, crc) = (u16_at(zip, at + 8), u32_at(zip, at + 14));
+            let (compressed, size) = (u32_at(zip, at + 18), u32_at(zip, at + 22));
+            let name_len = u16_at(zip, at + 26) as usize;
+            let name = String::from_utf8(zip[at + 30..at + 30 + name_len].to_vec()).unwrap();
+            let start = at + 30 + name_len;
+            let body = &zip[start..start + compressed as usize];
+            let data = match method {
+                DEFLATED => {
+                    let mut out = Vec::new();
+                    DeflateDecoder::new(body).read_to_end(&mut out).unwrap();
+                    out
+                }
+                _ => body.to_vec(),
+            };
+            assert_eq!((data.len() as u32, crc32(&data)), (size, crc), "{}", name);
+            entries.push((name, data));
+            at = start + compressed as usize;
+        }
+        assert!(zip[at..].starts_with(b"PK\x01\x02"));
+        entries
+    }
+
+    #[test]
+    fn entries_read_back() {
+        let text = "# Notes\n".repeat(1000).into_bytes();
+        let image = noise(4096);
+        let mut zip = Writer::new(UtcDateTime::now());
+        zip.append("chats/1.md", &text).unwrap();
+        zip.append("files/cat.png", &image).unwrap();
+        zip.append("筆記/日記.md", "今天".as_bytes()).unwrap();
+        zip.append("empty", b"").unwrap();
+        let zip = zip.finish().unwrap();
+
+        assert_eq!(
+            entries(&zip),
+            vec![
+                ("chats/1.md".to_owned(), text),
+                ("files/cat.png".to_owned(), image),
+                ("筆記/日記.md".to_owned(), "今天".as_bytes().to_vec()),
+                ("empty".to_owned(), vec![]),
+            ]
+        );
+        // the end of central directory record counts them
+        assert_eq!(u16_at(&zip, zip.len() - 12), 4);
+    }
+
+    #[test]
+    fn incompressible_entries_are_stored() {
+        let mut zip = Writer::new(UtcDateTime::now());
+        zip.append("a", &[b'a'; 1000]).unwrap();
+        let deflated = zip.out.len();
+        zip.append("b", &noise(1000)).unwrap();
+        let out = zip.finish().unwrap();
+        // the method follows the signature, version and flags
+        assert_eq!(u16_at(&out, 8), DEFLATED);
+        assert_eq!(u16_at(&out, deflated + 8), STORED);
+    }
+
+    #[test]
+    fn entries_carry_the_dos_time() {
+        let mut zip = Writer::new(datetime!(2026-10-14 08:30:31).as_utc());
+        zip.append("a", b"a").unwrap();
+        let out = zip.finish().unwrap();
+        // seconds are halved, years count from 1980
+        assert_eq!(u16_at(&out, 10), (8 << 11) | (30 << 5) | 15);
+        assert_eq!(u16_at(&out, 12), (46 << 9) | (10 << 5) | 14);
+    }
+
+    #[test]
+    fn names_past_64_kib_are_refused() {
+        let mut zip = Writer::new(UtcDateTime::now());
+        assert!(zip.append(&"a".repeat(70_000), b"").is_err());
+    }
+}
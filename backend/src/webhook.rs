@@ -0,0 +1,373 @@
+//! Signed POSTs to URLs users register, on events of their chats and tasks
+//!
+//! An event queues a job of `jobs` per webhook listening to it, so a delivery
+//! survives restarts and is retried with the backoff of the queue; any answer
+//! but a 2xx is a failure. The body is a [`Delivery`] in JSON, signed with the
+//! secret of the webhook: `X-Llumen-Signature` is `sha256=` and the hex
+//! HMAC-SHA256 of `X-Llumen-Timestamp`, a dot and the body, so receivers can
+//! refuse old deliveries replayed. The timestamp is the one of the attempt
+//!
+//! The host is resolved again on every attempt and the request sent to the
+//! address checked, without following redirects: loopback and link-local
+//! addresses, cloud metadata among them, are never reached, those of private
+//! networks only if admins allow them
+
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, bail};
+use entity::{ChunkKind, WebhookEvent, chunk, prelude::*, webhook};
+use hmac::{Hmac, Mac};
+use sea_orm::{QueryOrder, prelude::*};
+use serde::Serialize;
+use sha2::Sha256;
+use url::{Host, Url};
+
+use crate::{
+    AppState, config::WEBHOOK_TIMEOUT, jobs::Task, notify::NotificationScheduleRun, sse::EndKind,
+};
+
+/// Body of a delivery
+#[derive(Debug, Serialize)]
+pub struct Delivery {
+    #[serde(flatten)]
+    pub payload: Payload,
+    /// unix seconds of the event
+    pub at: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum Payload {
+    MessageCompleted(MessageCompleted),
+    ScheduleRun(NotificationScheduleRun),
+}
+
+#[derive(Debug, Serialize)]
+pub struct MessageCompleted {
+    pub chat_id: i32,
+    pub message_id: i32,
+    /// `complete`, `halt`, `error` or `truncated`
+    pub end: &'static str,
+    /// Text of the reply, without reasoning and tool calls
+    pub text: String,
+}
+
+impl Payload {
+    fn event(&self) -> WebhookEvent {
+        match self {
+            Payload::MessageCompleted(_) => WebhookEvent::MessageCompleted,
+            Payload::ScheduleRun(_) => WebhookEvent::ScheduleRun,
+        }
+    }
+}
+
+/// Queue the reply `message_id` that ended as `kind` to the webhooks of the
+/// user who sent the message
+pub async fn message_completed(
+    app: &AppState,
+    user_id: i32,
+    chat_id: i32,
+    message_id: i32,
+    kind: EndKind,
+) -> Result<()> {
+    let hooks = listening(app, user_id, WebhookEvent::MessageCompleted).await?;
+    if hooks.is_empty() {
+        return Ok(());
+    }
+    let text = Chunk::find()
+        .filter(chunk::Column::MessageId.eq(message_id))
+        .filter(chunk::Column::Kind.eq(ChunkKind::Text))
+        .order_by_asc(chunk::Column::Id)
+        .all(&app.conn)
+        .await?
+        .into_iter()
+        .map(|x| x.content)
+        .collect();
+    let end = match kind {
+        EndKind::Complete => "complete",
+        EndKind::Halt => "halt",
+        EndKind::Error => "error",
+        EndKind::Truncated => "truncated",
+    };
+    let payload = Payload::MessageCompleted(MessageCompleted {
+        chat_id,
+        message_id,
+        end,
+        text,
+    });
+    queue(app, hooks, payload).await
+}
+
+pub async fn schedule_run(
+    app: &AppState,
+    user_id: i32,
+    run: NotificationScheduleRun,
+) -> Result<()> {
+    let hooks = listening(app, user_id, WebhookEvent::ScheduleRun).await?;
+    queue(app, hooks, Payload::ScheduleRun(run)).await
+}
+
+async fn listening(app: &AppState, user_id: i32, event: WebhookEvent) -> Result<Vec<i32>> {
+    let hooks = Webhook::find()
+        .filter(webhook::Column::UserId.eq(user_id))
+        .all(&app.conn)
+        .await?;
+    Ok(hooks
+        .into_iter()
+        .filter(|x| x.events.has(event))
+        .map(|x| x.id)
+        .collect())
+}
+
+async fn queue(app: &AppState, hooks: Vec<i32>, payload: Payload) -> Result<()> {
+    if hooks.is_empty() {
+        return Ok(());
+    }
+    let event = payload.event();
+    let body = serde_json::to_string(&Delivery { payload, at: now() })?;
+    for webhook_id in hooks {
+        let task = Task::Webhook {
+            webhook_id,
+            event,
+            body: body.clone(),
+        };
+        app.jobs.push(&app.conn, task).await?;
+    }
+    Ok(())
+}
+
+/// Run as a job of `jobs`, dropped if the webhook was deleted or stopped
+/// listening to `event` since; the outcome is recorded on the webhook
+pub async fn deliver(
+    app: &AppState,
+    webhook_id: i32,
+    event: WebhookEvent,
+    body: String,
+) -> Result<()> {
+    let Some(hook) = Webhook::find_by_id(webhook_id).one(&app.conn).await? else {
+        return Ok(());
+    };
+    if !hook.events.has(event) {
+        return Ok(());
+    }
+    let at = now();
+    let private = app.settings.current().webhook_private_networks;
+    let res = post(&hook, event, body, at, private).await;
+    Webhook::update_many()
+        .col_expr(webhook::Column::LastDeliveryAt, at.into())
+        .col_expr(
+            webhook::Column::LastError,
+            res.as_ref().err().map(|x| x.to_string()).into(),
+        )
+        .filter(webhook::Column::Id.eq(webhook_id))
+        .exec(&app.conn)
+        .await?;
+    res
+}
+
+async fn post(
+    hook: &webhook::Model,
+    event: WebhookEvent,
+    body: String,
+    at: i64,
+    private: bool,
+) -> Result<()> {
+    let url = Url::parse(&hook.url)?;
+    let addr = resolve(&url, private).await?;
+    let mut client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(Duration::from_secs(WEBHOOK_TIMEOUT));
+    if let Some(Host::Domain(domain)) = url.host() {
+        // connect to the address checked, not to what a second lookup says
+        client = client.resolve(domain, addr);
+    }
+
+    let signature = sign(&hook.secret, at, &body);
+    let event = match event {
+        WebhookEvent::MessageCompleted => "message_completed",
+        WebhookEvent::ScheduleRun => "schedule_run",
+    };
+    let res = client
+        .build()?
+        .post(url)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header("X-Llumen-Event", event)
+        .header("X-Llumen-Timestamp", at)
+        .header("X-Llumen-Signature", format!("sha256={}", signature))
+        .body(body)
+        .send()
+        .await?;
+    if !res.status().is_success() {
+        bail!("answered {}", res.status());
+    }
+    Ok(())
+}
+
+/// Address a delivery to `url` goes to, refused if its host resolves to one
+/// webhooks may not reach
+pub async fn resolve(url: &Url, private: bool) -> Result<SocketAddr> {
+    let port = url
+        .port_or_known_default()
+        .context("webhook URL without a port")?;
+    let addrs: Vec<SocketAddr> = match url.host().context("webhook URL without a host")? {
+        Host::Ipv4(ip) => vec![(ip, port).into()],
+        Host::Ipv6(ip) => vec![(ip, port).into()],
+        Host::Domain(domain) => tokio::net::lookup_host((domain, port))
+            .await
+            .with_context(|| format!("cannot resolve {}", domain))?
+            .collect(),
+    };
+    // every address is checked, a client may connect to any of them
+    if let Some(addr) = addrs.iter().find(|x| !reachable(x.ip(), private)) {
+        bail!(
+            "{} is {}, webhooks cannot reach {}",
+            url.host_str().unwrap_or_default(),
+            addr.ip(),
+            match reachable(addr.ip(), true) {
+                true => "private networks unless admins allow them",
+                false => "loopback nor link-local addresses",
+            }
+        );
+    }
+    addrs.into_iter().next().context("the host has no address")
+}
+
+/// Whether a webhook may be delivered to `ip`, addresses of private networks
+/// only if `private`
+fn reachable(ip: IpAddr, private: bool) -> bool {
+    match ip.to_canonical() {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            let local = a == 0
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_multicast();
+            // shared address space of carrier-grade NAT as well
+            let lan = ip.is_private() || (a == 100 && (64..128).contains(&b));
+            !local && (private || !lan)
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            // NAT64 reaches the IPv4 address in the last 32 bits
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let v4 = Ipv4Addr::from_bits(ip.to_bits() as u32);
+                return reachable(IpAddr::V4(v4), private);
+            }
+            let local = ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                || segments[0] & 0xffc0 == 0xfe80;
+            let lan = segments[0] & 0xfe00 == 0xfc00;
+            !local && (private || !lan)
+        }
+    }
+}
+
+fn sign(secret: &str, at: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(format!("{}.", at).as_bytes());
+    mac.update(body.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|x| format!("{:02x}", x))
+        .collect()
+}
+
+/// A secret for a new webhook, shown to the user once
+pub fn secret() -> Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|e| anyhow::anyhow!("Cannot generate secret: {}", e))?;
+    Ok(format!(
+        "whsec_{}",
+        bytes
+            .iter()
+            .map(|x| format!("{:02x}", x))
+            .collect::<String>()
+    ))
+}
+
+fn now() -> i64 {
+    time::UtcDateTime::now().unix_timestamp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(x: &str) -> IpAddr {
+        x.parse().unwrap()
+    }
+
+    #[test]
+    fn public_addresses_are_reachable() {
+        for x in [
+            "93.184.216.34",
+            "1.1.1.1",
+            "2606:4700::1111",
+            "64:ff9b::808:808",
+        ] {
+            assert!(reachable(ip(x), false), "{}", x);
+        }
+    }
+
+    #[test]
+    fn the_server_and_link_local_are_never_reachable() {
+        for x in [
+            "127.0.0.1",
+            "127.8.9.10",
+            "0.0.0.0",
+            "0.1.2.3",
+            "169.254.169.254",
+            "255.255.255.255",
+            "224.0.0.1",
+            "::1",
+            "::",
+            "fe80::1",
+            "ff02::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+            "64:ff9b::7f00:1",
+        ] {
+            assert!(!reachable(ip(x), false), "{}", x);
+            assert!(!reachable(ip(x), true), "{}", x);
+        }
+    }
+
+    #[test]
+    fn private_networks_only_if_allowed() {
+        for x in [
+            "10.0.0.1",
+            "172.16.5.4",
+            "192.168.1.10",
+            "100.64.0.1",
+            "fd00::1",
+            "::ffff:192.168.1.10",
+            "64:ff9b::a00:1",
+        ] {
+            assert!(!reachable(ip(x), false), "{}", x);
+            assert!(reachable(ip(x), true), "{}", x);
+        }
+    }
+
+    #[tokio::test]
+    async fn resolve_checks_literals() {
+        let url = |x| Url::parse(x).unwrap();
+        assert!(
+            resolve(&url("http://127.0.0.1:8123/hook"), true)
+                .await
+                .is_err()
+        );
+        assert!(resolve(&url("http://[::1]/hook"), true).await.is_err());
+        assert!(resolve(&url("http://localhost/hook"), true).await.is_err());
+        assert!(resolve(&url("http://192.168.1.2/"), false).await.is_err());
+        let addr = resolve(&url("http://192.168.1.2/"), true).await.unwrap();
+        assert_eq!(addr, "192.168.1.2:80".parse().unwrap());
+        let addr = resolve(&url("https://1.1.1.1/"), false).await.unwrap();
+        assert_eq!(addr.port(), 443);
+    }
+}
@@ -0,0 +1,338 @@
+import {
+	CreateMutation,
+	CreateQuery,
+	SetQueryData,
+	type CreateMutationResult,
+	type QueryResult
+} from './state';
+import { get } from 'svelte/store';
+import { dispatchError } from '$lib/error';
+import { token } from '$lib/store';
+import { APIFetch, RawAPIFetch, apiBase, getError } from './state/errorHandle';
+
+import type {
+	AdminConfigReadReq,
+	AdminConfigReadResp,
+	AdminConfigWriteReq,
+	AdminConfigWriteResp,
+	AdminStatsRange,
+	AdminStatsReq,
+	AdminStatsResp,
+	AdminWorkspaceCreateReq,
+	AdminWorkspaceCreateResp,
+	AdminWorkspaceDeleteReq,
+	AdminWorkspaceDeleteResp,
+	AdminWorkspaceListReq,
+	AdminWorkspaceListResp,
+	AdminWorkspaceMemberReq,
+	AdminWorkspaceMemberResp,
+	AdminWorkspaceWriteReq,
+	AdminWorkspaceWriteResp,
+	AuditReq,
+	AuditResp,
+	BackupCreateReq,
+	BackupRestoreResp,
+	OpenApiImportReq,
+	OpenApiImportResp,
+	OpenApiPreviewReq,
+	OpenApiPreviewResp,
+	ChatTagsResp,
+	FeedbackReq,
+	FeedbackResp,
+	JobDeleteReq,
+	JobDeleteResp,
+	JobListReq,
+	JobListResp,
+	JobRetryReq,
+	JobRetryResp,
+	KeysRotateReq,
+	KeysRotateResp,
+	ModelListResp,
+	QuotaReadReq,
+	QuotaReadResp,
+	QuotaWriteReq,
+	QuotaWriteResp,
+	SpendReadReq,
+	SpendReadResp,
+	SpendResumeReq,
+	SpendResumeResp,
+	SystemReq,
+	SystemResp,
+	TagsReq
+} from './types';
+
+export function useSystem(): QueryResult<SystemResp> {
+	return CreateQuery<SystemReq, SystemResp>({
+		key: ['admin', 'system'],
+		path: 'admin/system',
+		body: {},
+		staleTime: 0
+	});
+}
+
+export function useStats(range: AdminStatsRange): QueryResult<AdminStatsResp> {
+	return CreateQuery<AdminStatsReq, AdminStatsResp>({
+		key: ['admin', 'stats', range],
+		path: 'admin/stats',
+		body: { range },
+		staleTime: 0
+	});
+}
+
+export function useAdminConfig(): QueryResult<AdminConfigReadResp> {
+	return CreateQuery<AdminConfigReadReq, AdminConfigReadResp>({
+		key: ['admin', 'config'],
+		path: 'admin/config/read',
+		body: {},
+		staleTime: 0
+	});
+}
+
+export function WriteAdminConfig(): CreateMutationResult<
+	AdminConfigWriteReq,
+	AdminConfigWriteResp
+> {
+	return CreateMutation({
+		path: 'admin/config/write',
+		onSuccess(_, param) {
+			// a key sent empty is cleared, one left out stays as it was
+			SetQueryData<AdminConfigReadResp>({
+				key: ['admin', 'config'],
+				updater: (data) =>
+					data
+						? {
+								default_model_id: param.default_model_id,
+								rate_limit: param.rate_limit,
+								api_base: param.api_base || undefined,
+								api_key_set: param.api_key != undefined ? param.api_key != '' : data.api_key_set,
+								google_map_api_key_set:
+									param.google_map_api_key != undefined
+										? param.google_map_api_key != ''
+										: data.google_map_api_key_set
+							}
+						: data
+			});
+			SetQueryData<ModelListResp>({
+				key: ['models'],
+				updater: (data) => (data ? { ...data, default_id: param.default_model_id } : data)
+			});
+		}
+	});
+}
+
+export function RotateKeys(): CreateMutationResult<KeysRotateReq, KeysRotateResp> {
+	return CreateMutation({ path: 'admin/keys/rotate' });
+}
+
+export function useAdminWorkspaces(): QueryResult<AdminWorkspaceListResp> {
+	return CreateQuery<AdminWorkspaceListReq, AdminWorkspaceListResp>({
+		key: ['admin', 'workspaces'],
+		path: 'admin/workspace/list',
+		body: {},
+		staleTime: 0
+	});
+}
+
+function updateWorkspaces(updater: (x: AdminWorkspaceListResp) => void) {
+	SetQueryData<AdminWorkspaceListResp>({
+		key: ['admin', 'workspaces'],
+		updater: (x) => {
+			if (x != undefined) updater(x);
+			return x;
+		}
+	});
+}
+
+export function CreateWorkspace(): CreateMutationResult<
+	AdminWorkspaceCreateReq,
+	AdminWorkspaceCreateResp
+> {
+	return CreateMutation({
+		path: 'admin/workspace/create',
+		onSuccess: (data, param) =>
+			updateWorkspaces((x) =>
+				x.list.push({
+					id: data.id,
+					name: param.name.trim(),
+					member_ids: [],
+					model_ids: [],
+					credentials: []
+				})
+			)
+	});
+}
+
+export function WriteWorkspace(): CreateMutationResult<
+	AdminWorkspaceWriteReq,
+	AdminWorkspaceWriteResp
+> {
+	return CreateMutation({
+		path: 'admin/workspace/write',
+		onSuccess: (_, param) =>
+			updateWorkspaces((x) => {
+				const workspace = x.list.find((w) => w.id == param.id);
+				if (workspace == undefined) return;
+				workspace.name = param.name.trim();
+				workspace.model_ids = param.model_ids;
+				for (const [name, value] of Object.entries(param.credentials)) {
+					workspace.credentials = workspace.credentials.filter((c) => c != name);
+					if (value.trim() != '') workspace.credentials.push(name);
+				}
+				workspace.credentials.sort();
+			})
+	});
+}
+
+export function DeleteWorkspace(): CreateMutationResult<
+	AdminWorkspaceDeleteReq,
+	AdminWorkspaceDeleteResp
+> {
+	return CreateMutation({
+		path: 'admin/workspace/delete',
+		onSuccess: (_, param) =>
+			updateWorkspaces((x) => (x.list = x.list.filter((w) => w.id != param.id)))
+	});
+}
+
+export function WorkspaceMember(): CreateMutationResult<
+	AdminWorkspaceMemberReq,
+	AdminWorkspaceMemberResp
+> {
+	return CreateMutation({
+		path: 'admin/workspace/member',
+		onSuccess: (_, param) =>
+			updateWorkspaces((x) => {
+				const workspace = x.list.find((w) => w.id == param.workspace_id);
+				if (workspace == undefined) return;
+				workspace.member_ids = workspace.member_ids.filter((id) => id != param.user_id);
+				if (param.member) workspace.member_ids.push(param.user_id);
+			})
+	});
+}
+
+export function useFeedback(): QueryResult<FeedbackResp> {
+	return CreateQuery<FeedbackReq, FeedbackResp>({
+		key: ['admin', 'feedback'],
+		path: 'admin/feedback',
+		body: {}
+	});
+}
+
+export function useTags(): QueryResult<ChatTagsResp> {
+	return CreateQuery<TagsReq, ChatTagsResp>({
+		key: ['admin', 'tags'],
+		path: 'admin/tags',
+		body: {}
+	});
+}
+
+export function PreviewOpenApi(): CreateMutationResult<OpenApiPreviewReq, OpenApiPreviewResp> {
+	return CreateMutation({ path: 'admin/openapi/preview' });
+}
+
+export function ImportOpenApi(): CreateMutationResult<OpenApiImportReq, OpenApiImportResp> {
+	return CreateMutation({ path: 'admin/openapi/import' });
+}
+
+export function useSpend(): QueryResult<SpendReadResp> {
+	return CreateQuery<SpendReadReq, SpendReadResp>({
+		key: ['admin', 'spend'],
+		path: 'admin/spend/read',
+		body: {},
+		staleTime: 0
+	});
+}
+
+export function ResumeSpend(): CreateMutationResult<SpendResumeReq, SpendResumeResp> {
+	return CreateMutation({
+		path: 'admin/spend/resume',
+		onSuccess: () =>
+			SetQueryData<SpendReadResp>({
+				key: ['admin', 'spend'],
+				updater: (data) => (data ? { ...data, paused: undefined } : data)
+			})
+	});
+}
+
+export function useJobs(): QueryResult<JobListResp> {
+	return CreateQuery<JobListReq, JobListResp>({
+		key: ['admin', 'jobs'],
+		path: 'admin/job/list',
+		body: {},
+		staleTime: 0
+	});
+}
+
+export function RetryJob(): CreateMutationResult<JobRetryReq, JobRetryResp> {
+	return CreateMutation({
+		path: 'admin/job/retry',
+		onSuccess: (data, param) =>
+			SetQueryData<JobListResp>({
+				key: ['admin', 'jobs'],
+				updater: (x) =>
+					x && data.retried ? { list: x.list.filter((job) => job.id != param.id) } : x
+			})
+	});
+}
+
+export function DeleteJob(): CreateMutationResult<JobDeleteReq, JobDeleteResp> {
+	return CreateMutation({
+		path: 'admin/job/delete',
+		onSuccess: (_, param) =>
+			SetQueryData<JobListResp>({
+				key: ['admin', 'jobs'],
+				updater: (x) => (x ? { list: x.list.filter((job) => job.id != param.id) } : x)
+			})
+	});
+}
+
+export function useQuota(userId: number): QueryResult<QuotaReadResp> {
+	return CreateQuery<QuotaReadReq, QuotaReadResp>({
+		key: ['admin', 'quota', userId.toString()],
+		path: 'admin/quota/read',
+		body: { user_id: userId },
+		staleTime: 0
+	});
+}
+
+export function WriteQuota(): CreateMutationResult<QuotaWriteReq, QuotaWriteResp> {
+	return CreateMutation({
+		path: 'admin/quota/write',
+		onSuccess: (_, param) =>
+			SetQueryData<QuotaReadResp>({
+				key: ['admin', 'quota', param.user_id.toString()],
+				updater: (data) => (data ? { ...data, limits: param.limits } : data)
+			})
+	});
+}
+
+/** A page of the audit log, newest first */
+export async function fetchAudit(req: AuditReq): Promise<AuditResp | undefined> {
+	return APIFetch<AuditResp, AuditReq>('admin/audit', req);
+}
+
+/** Tar archive of the database and the files, see `backup` in the backend */
+export async function fetchBackup(): Promise<Blob | undefined> {
+	const res = await RawAPIFetch<BackupCreateReq>('admin/backup', {});
+	if (res.headers.get('content-type')?.startsWith('application/x-tar')) return res.blob();
+
+	const error = getError(await res.json().catch(() => undefined));
+	dispatchError(error?.error ?? 'API(typeshare)', error?.reason, error?.message);
+}
+
+/** Restore a fresh instance from an archive of `fetchBackup`, sent as is */
+export async function restoreBackup(archive: File): Promise<BackupRestoreResp | undefined> {
+	const tokenVal = get(token)?.value;
+	const headers: Record<string, string> = { 'Content-Type': 'application/x-tar' };
+	if (tokenVal) headers['Authorization'] = tokenVal;
+	const res = await fetch(apiBase + 'admin/backup/restore', {
+		method: 'POST',
+		headers,
+		body: archive
+	});
+
+	const data = await res.json().catch(() => undefined);
+	const error = getError(data);
+	if (error == undefined && res.ok) return data as BackupRestoreResp;
+	dispatchError(error?.error ?? 'API(typeshare)', error?.reason, error?.message);
+}
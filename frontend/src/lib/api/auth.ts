@@ -1,10 +1,42 @@
 import { token } from '$lib/store';
+import { get } from 'svelte/store';
 import { page } from '$app/state';
 import { goto } from '$app/navigation';
-import { CreateMutation, type CreateMutationResult } from './state';
+import {
+	CreateMutation,
+	CreateQuery,
+	SetQueryData,
+	type CreateMutationResult,
+	type QueryResult
+} from './state';
 import { APIFetch } from './state/errorHandle';
 
-import type { LoginReq, LoginResp, RenewResp, RenewReq } from './types';
+import type {
+	ForgotReq,
+	ForgotResp,
+	LoginReq,
+	LoginResp,
+	LogoutReq,
+	LogoutResp,
+	OauthProvidersReq,
+	OauthProvidersResp,
+	OauthStartReq,
+	OauthStartResp,
+	RefreshReq,
+	RefreshResp,
+	ResetReq,
+	ResetResp,
+	TotpDisableReq,
+	TotpDisableResp,
+	TotpEnableReq,
+	TotpEnableResp,
+	TotpSetupReq,
+	TotpSetupResp,
+	TotpStatusReq,
+	TotpStatusResp,
+	VerifyReq,
+	VerifyResp
+} from './types';
 import { onDestroy } from 'svelte';
 
 export interface User {
@@ -22,15 +54,100 @@ export function Login(): CreateMutationResult<LoginReq, LoginResp> {
 			token.set({
 				value: data.token,
 				expireAt: expireAt.toString(),
-				renewAt: renewAt.toString()
+				renewAt: renewAt.toString(),
+				refreshToken: data.refresh_token
 			});
 		}
 	});
 }
 
-export async function RenewToken(originalToken: string) {
+export function useOauthProviders(): QueryResult<OauthProvidersResp> {
+	return CreateQuery<OauthProvidersReq, OauthProvidersResp>({
+		key: ['oauthProviders'],
+		path: 'auth/oauth/providers',
+		body: {},
+		staleTime: Infinity
+	});
+}
+
+/** Leave for the provider, the identity is linked to the current user if logged in */
+export async function OauthLogin(provider: string) {
+	const res = await APIFetch<OauthStartResp, OauthStartReq>(`auth/oauth/${provider}`, {});
+	if (res) window.location.href = res.url;
+}
+
+/**
+ * Take the result of a social login from the url fragment
+ *
+ * Return the error if it failed
+ */
+export function consumeOauthResult(): string | undefined {
+	const params = new URLSearchParams(window.location.hash.slice(1));
+	history.replaceState(null, '', window.location.pathname + window.location.search);
+
+	const value = params.get('token');
+	const exp = params.get('exp');
+	const refreshToken = params.get('refresh_token');
+	if (value && exp && refreshToken) {
+		const now = new Date();
+		const expireAt = new Date(exp);
+		const renewAt = new Date(now.getTime() + (expireAt.getTime() - now.getTime()) / 2);
+
+		token.set({
+			value,
+			expireAt: expireAt.toString(),
+			renewAt: renewAt.toString(),
+			refreshToken
+		});
+		return;
+	}
+	return params.get('error') ?? undefined;
+}
+
+/** Mail a reset link, answer the same whether the user exists or not */
+export function Forgot(): CreateMutationResult<ForgotReq, ForgotResp> {
+	return CreateMutation({ path: 'auth/forgot' });
+}
+
+export function Reset(): CreateMutationResult<ResetReq, ResetResp> {
+	return CreateMutation({ path: 'auth/reset' });
+}
+
+/** Open a mailed verification link, no login needed */
+export function Verify(): CreateMutationResult<VerifyReq, VerifyResp> {
+	return CreateMutation({ path: 'auth/verify' });
+}
+
+export function useTotpStatus(): QueryResult<TotpStatusResp> {
+	return CreateQuery<TotpStatusReq, TotpStatusResp>({
+		key: ['totpStatus'],
+		path: 'auth/totp/status',
+		body: {},
+		staleTime: 0
+	});
+}
+
+function setTotpStatus(enabled: boolean) {
+	SetQueryData<TotpStatusResp>({ key: ['totpStatus'], updater: () => ({ enabled }) });
+}
+
+export function TotpSetup(): CreateMutationResult<TotpSetupReq, TotpSetupResp> {
+	return CreateMutation({ path: 'auth/totp/setup' });
+}
+
+export function TotpEnable(): CreateMutationResult<TotpEnableReq, TotpEnableResp> {
+	return CreateMutation({ path: 'auth/totp/enable', onSuccess: () => setTotpStatus(true) });
+}
+
+export function TotpDisable(): CreateMutationResult<TotpDisableReq, TotpDisableResp> {
+	return CreateMutation({ path: 'auth/totp/disable', onSuccess: () => setTotpStatus(false) });
+}
+
+export async function RenewToken(refreshToken: string) {
 	console.log('renew');
-	const res = await APIFetch<RenewResp, RenewReq>('auth/renew', { token: originalToken });
+	const res = await APIFetch<RefreshResp, RefreshReq>('auth/refresh', {
+		refresh_token: refreshToken
+	});
 
 	if (res) {
 		const now = new Date();
@@ -40,11 +157,19 @@ export async function RenewToken(originalToken: string) {
 		token.set({
 			value: res.token,
 			expireAt: expireAt.toString(),
-			renewAt: renewAt.toString()
+			renewAt: renewAt.toString(),
+			refreshToken: res.refresh_token
 		});
 	}
 }
 
+export async function Logout() {
+	const data = get(token);
+	token.set(undefined);
+	if (data?.refreshToken)
+		await APIFetch<LogoutResp, LogoutReq>('auth/logout', { refresh_token: data.refreshToken });
+}
+
 export function initAuth() {
 	const guardPrefix = ['/chat', '/setting'];
 
@@ -66,13 +191,14 @@ export function initAuth() {
 				const renewAt = new Date(data.renewAt);
 				const now = new Date();
 				const timeout = renewAt.getTime() - now.getTime();
-				if (expireAt < now) {
-					token.set(undefined);
+				if (!data.refreshToken) {
+					// issued before refresh tokens, or by demo login
+					if (expireAt < now) token.set(undefined);
 				} else if (timeout > 0) {
-					const timeoutId = setTimeout(() => RenewToken(data.value), timeout);
+					const timeoutId = setTimeout(() => RenewToken(data.refreshToken), timeout);
 					return () => clearTimeout(timeoutId);
 				} else {
-					RenewToken(data.value);
+					RenewToken(data.refreshToken);
 				}
 			}
 		})
@@ -14,22 +14,48 @@ import {
 	type ChatReadReq,
 	type ChatDeleteReq,
 	type ChatDeleteResp,
+	type ChatDraftResp,
+	type ChatDraftWriteReq,
 	MessageCreateReqMode,
 	type ChatUpdateReq,
-	type ChatUpdateResp
+	type ChatUpdateResp,
+	type ChatToolInputReq,
+	type ChatToolInputResp,
+	type ChatSettingsResp,
+	type ChatSettingsWriteReq,
+	type ChatMemberListResp,
+	type ChatMemberAddReq,
+	type ChatMemberAddResp,
+	type ChatMemberRemoveReq,
+	type ChatMemberRemoveResp,
+	ChatMemberRole,
+	type ChatShareResp,
+	type ChatUnshareResp,
+	type ChatPinReq,
+	type ChatPinResp,
+	type ChatArchiveReq,
+	type ChatArchiveResp,
+	type ChatTrashListReq,
+	type ChatTrashListResp,
+	type ChatTrashRestoreReq,
+	type ChatTrashRestoreResp,
+	type UndoResp
 } from './types';
 import {
 	CreateInfiniteQuery,
 	CreateMutation,
 	CreateQuery,
 	CreateRawMutation,
+	RevalidateInfiniteQueryData,
 	SetInfiniteQueryData,
+	SetQueryData,
 	type Fetcher,
 	type InfiniteQueryResult,
 	type QueryResult,
 	type RawMutationResult
 } from './state';
 import { APIFetch } from './state/errorHandle';
+import { uploadFiles } from './file';
 import { once } from './state/helper';
 import { onDestroy } from 'svelte';
 import type { MutationResult } from './state/mutate';
@@ -46,6 +72,9 @@ export interface CreateRoomRequest {
 export function createRoom(): RawMutationResult<CreateRoomRequest, ChatCreateResp> {
 	return CreateRawMutation({
 		mutator: async (param) => {
+			const fileIds = await uploadFiles(param.files);
+			if (!fileIds) return;
+
 			let chatRes = await APIFetch<ChatCreateResp, ChatCreateReq>('chat/create', {
 				model_id: param.modelId
 			});
@@ -55,14 +84,17 @@ export function createRoom(): RawMutationResult<CreateRoomRequest, ChatCreateRes
 			const res = await APIFetch<MessageCreateResp, MessageCreateReq>('message/create', {
 				chat_id: chatRes.id,
 				text: param.message,
-				mode: param.mode
+				mode: param.mode,
+				files: fileIds
 			});
 
 			SetInfiniteQueryData<ChatPaginateRespList>({
 				key: ['chatPaginate'],
 				data: {
 					id: chatRes.id,
-					model_id: param.modelId
+					model_id: param.modelId,
+					label_ids: [],
+					pinned: false
 				}
 			});
 
@@ -136,6 +168,24 @@ export function useRooms(): InfiniteQueryResult<ChatPaginateRespList> {
 	});
 }
 
+export interface ToolInputRequest {
+	chatId: number;
+	callId: string;
+	/** JSON */
+	answer: string;
+}
+
+/** Answer the question of a tool call waiting for the user */
+export function answerToolInput(): RawMutationResult<ToolInputRequest, ChatToolInputResp> {
+	return CreateRawMutation({
+		mutator: ({ chatId, callId, answer }) =>
+			APIFetch<ChatToolInputResp, ChatToolInputReq>(
+				`chat/${chatId}/tool/${encodeURIComponent(callId)}/input`,
+				{ answer }
+			)
+	});
+}
+
 export function haltCompletion() {
 	return CreateMutation({
 		path: 'chat/halt',
@@ -161,12 +211,150 @@ export function deleteRoom(): MutationResult<ChatDeleteReq, ChatDeleteResp> {
 	});
 }
 
+/** Restore what a deletion removed, then refetch the chat list */
+export async function undoDelete(token: string) {
+	const res = await APIFetch<UndoResp>(`undo/${token}`);
+	if (res) RevalidateInfiniteQueryData<ChatPaginateRespList>({ key: ['chatPaginate'] });
+	return res;
+}
+
+export function useTrash(): QueryResult<ChatTrashListResp> {
+	return CreateQuery<ChatTrashListReq, ChatTrashListResp>({
+		key: ['chatTrash'],
+		path: 'chat/trash/list',
+		body: {},
+		staleTime: 0
+	});
+}
+
+/** Put a deleted chat or message back, then refetch the chat list */
+export function restoreTrash(): MutationResult<ChatTrashRestoreReq, ChatTrashRestoreResp> {
+	return CreateMutation({
+		path: 'chat/trash/restore',
+		onSuccess(_, param) {
+			SetQueryData<ChatTrashListResp>({
+				key: ['chatTrash'],
+				updater: (x) => {
+					if (x != undefined) x.list = x.list.filter((e) => e.id !== param.id);
+					return x;
+				}
+			});
+			RevalidateInfiniteQueryData<ChatPaginateRespList>({ key: ['chatPaginate'] });
+		}
+	});
+}
+
 export function useRoomStreamingState(id: number): Writable<boolean> {
 	return globalCache.getOr(['chat', 'stream', id.toString()], false);
 }
 
+/** Draft of the chat as saved last, by this device or another one */
+export function useRoomDraft(id: number): Writable<string | undefined> {
+	return globalCache.get<string>(['chat', 'draft', id.toString()]);
+}
+
+export async function readRoomDraft(id: number) {
+	const res = await APIFetch<ChatDraftResp>(`chat/${id}/draft`, null, 'GET');
+	if (res) useRoomDraft(id).set(res.content);
+	return res;
+}
+
+/** An empty text removes the draft, sending the message does it too */
+export function writeRoomDraft(id: number, content: string) {
+	useRoomDraft(id).set(content);
+	return APIFetch<ChatDraftResp, ChatDraftWriteReq>(`chat/${id}/draft`, { content }, 'PUT');
+}
+
 export function updateRoom(): MutationResult<ChatUpdateReq, ChatUpdateResp> {
 	return CreateMutation({
 		path: 'chat/write'
 	});
 }
+
+export function useRoomSettings(id: number): QueryResult<ChatSettingsResp> {
+	return CreateQuery<null, ChatSettingsResp>({
+		key: ['chatSettings', id.toString()],
+		path: `chat/${id}/settings`,
+		method: 'GET'
+	});
+}
+
+/** Apply to the next reply of the chat */
+export async function writeRoomSettings(id: number, req: ChatSettingsWriteReq) {
+	const res = await APIFetch<ChatSettingsResp, ChatSettingsWriteReq>(`chat/${id}/settings`, req);
+	if (res)
+		SetQueryData<ChatSettingsResp>({
+			key: ['chatSettings', id.toString()],
+			updater: () => res
+		});
+	return res;
+}
+
+/** Everyone taking part in the chat, the creator first */
+export function useRoomMembers(id: number): QueryResult<ChatMemberListResp> {
+	return CreateQuery<null, ChatMemberListResp>({
+		key: ['chatMembers', id.toString()],
+		path: `chat/${id}/member`,
+		method: 'GET'
+	});
+}
+
+async function reloadMembers(id: number) {
+	const res = await APIFetch<ChatMemberListResp>(`chat/${id}/member`, null, 'GET');
+	if (res)
+		SetQueryData<ChatMemberListResp>({
+			key: ['chatMembers', id.toString()],
+			updater: () => res
+		});
+}
+
+/** Add an account by its username, or change the role of a member */
+export async function addRoomMember(id: number, name: string, role: ChatMemberRole) {
+	const res = await APIFetch<ChatMemberAddResp, ChatMemberAddReq>(`chat/${id}/member`, {
+		name,
+		role
+	});
+	if (res) await reloadMembers(id);
+	return res;
+}
+
+/** Owners remove anyone but the creator, members only leave */
+export async function removeRoomMember(id: number, user_id: number) {
+	const res = await APIFetch<ChatMemberRemoveResp, ChatMemberRemoveReq>(
+		`chat/${id}/member`,
+		{ user_id },
+		'DELETE'
+	);
+	if (res) await reloadMembers(id);
+	return res;
+}
+
+/** Public url of a read-only copy of the chat, the same until revoked */
+export async function shareRoom(id: number): Promise<string | undefined> {
+	const res = await APIFetch<ChatShareResp>(`chat/${id}/share`);
+	if (!res) return;
+	SetQueryData<ChatReadResp>({
+		key: ['chatRead', id.toString()],
+		updater: (data) => data && { ...data, share_token: res.token }
+	});
+	return `${location.origin}/share/${res.token}`;
+}
+
+export async function unshareRoom(id: number) {
+	const res = await APIFetch<ChatUnshareResp>(`chat/${id}/share`, null, 'DELETE');
+	SetQueryData<ChatReadResp>({
+		key: ['chatRead', id.toString()],
+		updater: (data) => data && { ...data, share_token: undefined }
+	});
+	return res;
+}
+
+/** Pinned chats are marked in the chat list */
+export function pinRoom(id: number, pinned: boolean) {
+	return APIFetch<ChatPinResp, ChatPinReq>('chat/pin', { chat_id: id, pinned });
+}
+
+/** Archived chats leave the chat list, they are kept otherwise */
+export function archiveRoom(id: number, archived: boolean) {
+	return APIFetch<ChatArchiveResp, ChatArchiveReq>('chat/archive', { chat_id: id, archived });
+}
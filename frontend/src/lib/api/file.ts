@@ -0,0 +1,35 @@
+import { APIMultipartFetch, RawAPIFetch } from './state/errorHandle';
+import type { FileUploadResp } from './types';
+
+export async function uploadFile(file: File): Promise<FileUploadResp | undefined> {
+	const body = new FormData();
+	body.append('file', file, file.name);
+	return APIMultipartFetch<FileUploadResp>('file/upload', body);
+}
+
+/** Whether the text of the file can be read for a summary, see `message/summarize` */
+export function summarizable(name: string): boolean {
+	return /\.(pdf|docx|html?|txt|md|csv)$/i.test(name);
+}
+
+/** Ids of the uploaded files, undefined once one of them fail */
+export async function uploadFiles(files: File[]): Promise<number[] | undefined> {
+	const ids = [];
+	for (const file of files) {
+		const res = await uploadFile(file);
+		if (!res) return;
+		ids.push(res.id);
+	}
+	return ids;
+}
+
+/** Save a file of the user, downloads need the token so it cannot be linked */
+export async function downloadFile(id: number, name: string) {
+	const res = await RawAPIFetch(`file/${id}`, null, 'GET');
+	if (!res.ok) return;
+	const link = document.createElement('a');
+	link.href = URL.createObjectURL(await res.blob());
+	link.download = name;
+	link.click();
+	URL.revokeObjectURL(link.href);
+}
@@ -0,0 +1,87 @@
+import {
+	CreateMutation,
+	CreateQuery,
+	RevalidateInfiniteQueryData,
+	type CreateMutationResult,
+	type QueryResult
+} from './state';
+import { globalCache } from './state/cache';
+
+import type {
+	ChatPaginateRespList,
+	FolderAssignReq,
+	FolderAssignResp,
+	FolderCreateReq,
+	FolderCreateResp,
+	FolderDeleteReq,
+	FolderDeleteResp,
+	FolderListReq,
+	FolderListResp,
+	FolderWriteReq,
+	FolderWriteResp,
+	LabelAssignReq,
+	LabelAssignResp,
+	LabelCreateReq,
+	LabelCreateResp,
+	LabelDeleteReq,
+	LabelDeleteResp,
+	LabelListReq,
+	LabelListResp,
+	LabelWriteReq,
+	LabelWriteResp
+} from './types';
+
+export function useFolders(): QueryResult<FolderListResp> {
+	return CreateQuery<FolderListReq, FolderListResp>({
+		key: ['folderList'],
+		path: 'folder/list',
+		body: {}
+	});
+}
+
+export function useLabels(): QueryResult<LabelListResp> {
+	return CreateQuery<LabelListReq, LabelListResp>({
+		key: ['labelList'],
+		path: 'label/list',
+		body: {}
+	});
+}
+
+/** Counts of the lists and the folder or labels of chats have changed */
+function revalidate() {
+	globalCache.delete(['folderList']);
+	globalCache.delete(['labelList']);
+	RevalidateInfiniteQueryData<ChatPaginateRespList>({ key: ['chatPaginate'] });
+}
+
+export function CreateFolder(): CreateMutationResult<FolderCreateReq, FolderCreateResp> {
+	return CreateMutation({ path: 'folder/create', onSuccess: revalidate });
+}
+
+export function WriteFolder(): CreateMutationResult<FolderWriteReq, FolderWriteResp> {
+	return CreateMutation({ path: 'folder/write', onSuccess: revalidate });
+}
+
+export function DeleteFolder(): CreateMutationResult<FolderDeleteReq, FolderDeleteResp> {
+	return CreateMutation({ path: 'folder/delete', onSuccess: revalidate });
+}
+
+export function AssignFolder(): CreateMutationResult<FolderAssignReq, FolderAssignResp> {
+	return CreateMutation({ path: 'folder/assign', onSuccess: revalidate });
+}
+
+export function CreateLabel(): CreateMutationResult<LabelCreateReq, LabelCreateResp> {
+	return CreateMutation({ path: 'label/create', onSuccess: revalidate });
+}
+
+export function WriteLabel(): CreateMutationResult<LabelWriteReq, LabelWriteResp> {
+	return CreateMutation({ path: 'label/write', onSuccess: revalidate });
+}
+
+export function DeleteLabel(): CreateMutationResult<LabelDeleteReq, LabelDeleteResp> {
+	return CreateMutation({ path: 'label/delete', onSuccess: revalidate });
+}
+
+export function AssignLabels(): CreateMutationResult<LabelAssignReq, LabelAssignResp> {
+	return CreateMutation({ path: 'label/assign', onSuccess: revalidate });
+}
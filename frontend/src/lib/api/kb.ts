@@ -0,0 +1,128 @@
+import { CreateQuery, SetQueryData, type QueryResult } from './state';
+import { APIFetch } from './state/errorHandle';
+import { uploadFile } from './file';
+import type {
+	ChatReadResp,
+	KbAssignReq,
+	KbAssignResp,
+	KbCollectionCreateReq,
+	KbCollectionCreateResp,
+	KbCollectionDeleteReq,
+	KbCollectionDeleteResp,
+	KbCollectionListReq,
+	KbCollectionListResp,
+	KbCreateReq,
+	KbCreateResp,
+	KbDeleteReq,
+	KbDeleteResp,
+	KbListReq,
+	KbListResp,
+	KbMoveReq,
+	KbMoveResp,
+	KbReindexReq,
+	KbReindexResp
+} from './types';
+
+export function useDocuments(): QueryResult<KbListResp> {
+	return CreateQuery<KbListReq, KbListResp>({
+		key: ['documents'],
+		path: 'kb/list',
+		body: {}
+	});
+}
+
+export async function reloadDocuments() {
+	const res = await APIFetch<KbListResp, KbListReq>('kb/list', {});
+	if (res)
+		SetQueryData<KbListResp>({
+			key: ['documents'],
+			updater: () => res
+		});
+}
+
+/** Upload the file and add it, it is pending until ingested */
+export async function addDocument(file: File, collectionId?: number) {
+	const uploaded = await uploadFile(file);
+	if (!uploaded) return;
+	const res = await APIFetch<KbCreateResp, KbCreateReq>('kb/create', {
+		file_id: uploaded.id,
+		collection_id: collectionId
+	});
+	if (res) await Promise.all([reloadDocuments(), reloadCollections()]);
+	return res;
+}
+
+export async function deleteDocument(id: number) {
+	const res = await APIFetch<KbDeleteResp, KbDeleteReq>('kb/delete', { id });
+	if (res) await Promise.all([reloadDocuments(), reloadCollections()]);
+	return res;
+}
+
+/** Read the file again, the document is pending until done */
+export async function reindexDocument(id: number) {
+	const res = await APIFetch<KbReindexResp, KbReindexReq>('kb/reindex', { id });
+	if (res) await reloadDocuments();
+	return res;
+}
+
+export async function moveDocument(id: number, collectionId: number | undefined) {
+	const res = await APIFetch<KbMoveResp, KbMoveReq>('kb/move', {
+		id,
+		collection_id: collectionId
+	});
+	if (res) await Promise.all([reloadDocuments(), reloadCollections()]);
+	return res;
+}
+
+export function useCollections(): QueryResult<KbCollectionListResp> {
+	return CreateQuery<KbCollectionListReq, KbCollectionListResp>({
+		key: ['collections'],
+		path: 'kb/collection/list',
+		body: {}
+	});
+}
+
+async function reloadCollections() {
+	const res = await APIFetch<KbCollectionListResp, KbCollectionListReq>(
+		'kb/collection/list',
+		{}
+	);
+	if (res)
+		SetQueryData<KbCollectionListResp>({
+			key: ['collections'],
+			updater: () => res
+		});
+}
+
+export async function createCollection(name: string) {
+	const res = await APIFetch<KbCollectionCreateResp, KbCollectionCreateReq>(
+		'kb/collection/create',
+		{ name }
+	);
+	if (res) await reloadCollections();
+	return res;
+}
+
+/** Its documents stay, outside of any collection */
+export async function deleteCollection(id: number) {
+	const res = await APIFetch<KbCollectionDeleteResp, KbCollectionDeleteReq>(
+		'kb/collection/delete',
+		{ id }
+	);
+	if (res) await Promise.all([reloadDocuments(), reloadCollections()]);
+	return res;
+}
+
+/** Search only the documents in the collections for the replies of the chat */
+export async function assignCollections(chatId: number, collectionIds: number[]) {
+	const res = await APIFetch<KbAssignResp, KbAssignReq>('kb/assign', {
+		chat_id: chatId,
+		collection_ids: collectionIds
+	});
+	if (res)
+		SetQueryData<ChatReadResp>({
+			key: ['chatRead', chatId.toString()],
+			updater: (x) => x && { ...x, collection_ids: collectionIds }
+		});
+	return res;
+}
@@ -0,0 +1,39 @@
+import { CreateQuery, SetQueryData, type QueryResult } from './state';
+import { APIFetch } from './state/errorHandle';
+import type {
+	MemoryCreateReq,
+	MemoryCreateResp,
+	MemoryDeleteReq,
+	MemoryDeleteResp,
+	MemoryListReq,
+	MemoryListResp
+} from './types';
+
+export function useMemories(): QueryResult<MemoryListResp> {
+	return CreateQuery<MemoryListReq, MemoryListResp>({
+		key: ['memories'],
+		path: 'memory/list',
+		body: {}
+	});
+}
+
+async function reloadMemories() {
+	const res = await APIFetch<MemoryListResp, MemoryListReq>('memory/list', {});
+	if (res)
+		SetQueryData<MemoryListResp>({
+			key: ['memories'],
+			updater: () => res
+		});
+}
+
+export async function createMemory(content: string) {
+	const res = await APIFetch<MemoryCreateResp, MemoryCreateReq>('memory/create', { content });
+	if (res) await reloadMemories();
+	return res;
+}
+
+export async function deleteMemory(id: number) {
+	const res = await APIFetch<MemoryDeleteResp, MemoryDeleteReq>('memory/delete', { id });
+	if (res) await reloadMemories();
+	return res;
+}
@@ -2,27 +2,52 @@ import type { TokensList } from 'marked';
 import {
 	CreateEventQuery,
 	CreateInfiniteQuery,
-	CreateMutation,
+	CreateRawMutation,
+	RevalidateInfiniteQueryData,
 	SetInfiniteQueryData,
 	type Fetcher,
 	type InfiniteQueryResult
 } from './state';
-import { APIFetch } from './state/errorHandle';
+import { APIFetch, APIMultipartFetch, getError, RawAPIFetch } from './state/errorHandle';
 import type { MutationResult } from './state/mutate';
 import {
+	MessageCreateReqMode,
 	MessagePaginateReqOrder,
 	MessagePaginateRespRole,
+	type ChatBranchReq,
+	type FeedbackRating,
+	type ChatBranchResp,
+	type ChatContinueReq,
+	type ChatContinueResp,
 	type MessageCreateReq,
 	type MessageCreateResp,
+	type MessageDraftReq,
+	type MessageDraftResp,
+	type MessageEditReq,
+	type MessageEditResp,
+	type MessageFeedbackReq,
+	type MessageFeedbackResp,
 	type MessagePaginateReq,
 	type MessagePaginateResp,
 	type MessagePaginateRespList,
+	type MessageSummarizeReq,
+	type MessageSummarizeResp,
+	type MessageRegenerateReq,
+	type MessageRegenerateResp,
+	type MessageSearchResp,
+	type SseEvent,
 	type SseReq,
-	type SseResp
+	type SseResp,
+	type ChatVoiceResp
 } from './types';
 import { globalCache } from './state/cache';
 import { onDestroy } from 'svelte';
+import type { Writable } from 'svelte/store';
 import { dev } from '$app/environment';
+import { dispatchError } from '$lib/error';
+
+/** Event schema this client understands, see `SSE_VERSION` in the backend */
+const SSE_VERSION = 2;
 
 class MessageFetcher implements Fetcher<MessagePaginateRespList> {
 	chatId: number;
@@ -79,9 +104,34 @@ export function useMessage(chat_id: number): InfiniteQueryResult<MessagePaginate
 	});
 }
 
+/** Network failures of a send are retried this many times */
+const SEND_RETRIES = 3;
+
+/** Retried on network failures with the same `Idempotency-Key`, the server answer it once */
+async function sendMessage(param: MessageCreateReq): Promise<MessageCreateResp | undefined> {
+	const key = crypto.randomUUID();
+	for (let attempt = 0; ; attempt++) {
+		let res: Response;
+		try {
+			res = await RawAPIFetch('message/create', param, 'POST', undefined, {
+				'Idempotency-Key': key
+			});
+		} catch (err) {
+			if (attempt >= SEND_RETRIES) throw err;
+			await new Promise((resolve) => setTimeout(resolve, 1000 * 2 ** attempt));
+			continue;
+		}
+		const resJson = await res.json();
+		const error = getError(resJson);
+		if (!error) return resJson as MessageCreateResp;
+		dispatchError(error.error, error.reason, error.message);
+		return;
+	}
+}
+
 export function createMessage(): MutationResult<MessageCreateReq, MessageCreateResp> {
-	return CreateMutation({
-		path: 'message/create',
+	return CreateRawMutation({
+		mutator: sendMessage,
 		onSuccess: (data, param) => {
 			const roomStreamingState = globalCache.getOr(
 				['chat', 'stream', param.chat_id.toString()],
@@ -101,41 +151,183 @@ export function createMessage(): MutationResult<MessageCreateReq, MessageCreateR
 	});
 }
 
+/** Speech of a reply in the voice settings of the user */
+export async function fetchSpeech(id: number): Promise<Blob | undefined> {
+	const res = await RawAPIFetch(`message/${id}/audio`, null, 'GET');
+	if (res.headers.get('content-type')?.startsWith('audio/')) return res.blob();
+
+	const error = getError(await res.json().catch(() => undefined));
+	dispatchError(error?.error ?? 'API(typeshare)', error?.reason, error?.message);
+}
+
+/** Rate a reply, `undefined` take the rating back */
+export async function rateMessage(
+	chatId: number,
+	id: number,
+	rating?: FeedbackRating,
+	comment?: string
+) {
+	const res = await APIFetch<MessageFeedbackResp, MessageFeedbackReq>(`message/${id}/feedback`, {
+		rating,
+		comment
+	});
+	if (res)
+		RevalidateInfiniteQueryData<MessagePaginateRespList>({
+			key: ['messagePaginate', chatId.toString()],
+			predicate: (x) => x.id == id
+		});
+	return res;
+}
+
+/** Send a recording, it is transcribed and answered like a typed message */
+export async function sendVoice(chatId: number, audio: Blob, mode: MessageCreateReqMode) {
+	const body = new FormData();
+	body.append('file', audio, 'voice');
+	body.append('mode', mode);
+	const res = await APIMultipartFetch<ChatVoiceResp>(`chat/${chatId}/voice`, body);
+	if (!res) return;
+
+	setStreaming(chatId);
+	SetInfiniteQueryData<MessagePaginateRespList>({
+		key: ['messagePaginate', chatId.toString()],
+		data: {
+			id: res.id,
+			role: MessagePaginateRespRole.User,
+			chunks: [{ id: res.id, kind: { t: 'text', c: { context: res.text } } }]
+		}
+	});
+	return res;
+}
+
+/** Bumped whenever the active branch of the chat change, the message list is rebuilt on it */
+export function useBranchVersion(chatId: number): Writable<number> {
+	return globalCache.getOr(['chat', 'branch', chatId.toString()], 0);
+}
+
+function reloadBranch(chatId: number) {
+	globalCache.delete(['messagePaginate', chatId.toString()]);
+	useBranchVersion(chatId).update((x) => x + 1);
+}
+
+function setStreaming(chatId: number) {
+	globalCache.getOr(['chat', 'stream', chatId.toString()], false).set(true);
+}
+
+/**
+ * Edit a user message, with `rerun` it is sent again on a new branch and the
+ * original stays on its own, otherwise only its text is fixed
+ */
+export async function editMessage(chatId: number, id: number, text: string, rerun: boolean) {
+	const res = await APIFetch<MessageEditResp, MessageEditReq>(
+		`message/${id}`,
+		{ text, rerun, mode: MessageCreateReqMode.Normal },
+		'PATCH'
+	);
+	if (!res) return;
+	if (rerun) setStreaming(chatId);
+	reloadBranch(chatId);
+}
+
+/** Answer again the message before an assistant message, on a new branch */
+export async function regenerateMessage(chatId: number, id: number) {
+	const res = await APIFetch<MessageRegenerateResp, MessageRegenerateReq>('message/regenerate', {
+		id,
+		mode: MessageCreateReqMode.Normal
+	});
+	if (!res) return;
+	setStreaming(chatId);
+	reloadBranch(chatId);
+}
+
+/** Finish a reply cut off by a restart of the server, the rest streams after it */
+export async function continueMessage(chatId: number, id: number) {
+	const res = await APIFetch<ChatContinueResp, ChatContinueReq>(`chat/${chatId}/continue`, {
+		message_id: id,
+		mode: MessageCreateReqMode.Normal
+	});
+	if (!res) return;
+	setStreaming(chatId);
+	reloadBranch(chatId);
+}
+
+/** Ask for a summary of an uploaded document, it streams like a sent message */
+export async function summarizeFile(chatId: number, fileId: number) {
+	const res = await APIFetch<MessageSummarizeResp, MessageSummarizeReq>('message/summarize', {
+		chat_id: chatId,
+		file_id: fileId
+	});
+	if (!res) return;
+	setStreaming(chatId);
+	reloadBranch(chatId);
+}
+
+/** Show the branch `messageId` is on */
+export async function switchBranch(chatId: number, messageId: number) {
+	const res = await APIFetch<ChatBranchResp, ChatBranchReq>('chat/branch', {
+		chat_id: chatId,
+		message_id: messageId
+	});
+	if (res) reloadBranch(chatId);
+}
+
 let SSEHandlers: {
-	[key in SseResp['t']]: Array<(data: Extract<SseResp, { t: key }>['c']) => void>;
+	[key in SseResp['type']]: Array<(data: Extract<SseResp, { type: key }>['data']) => void>;
 } = {
 	last_message: [],
-	token: [],
-	reasoning_token: [],
+	token_delta: [],
+	reasoning_delta: [],
 	chunk_end: [],
+	tool_call_delta: [],
 	tool_call: [],
-	tool_call_end: [],
+	tool_input: [],
+	tool_result: [],
+	attachment: [],
 	message_end: [],
 	user_message: [],
-	change_title: []
+	chat_title: [],
+	chat_status: [],
+	plan: [],
+	progress: [],
+	meta: [],
+	context_warning: [],
+	lagged: [],
+	error: []
 } satisfies {
-	[key in SseResp['t']]: Array<(data: Extract<SseResp, { t: key }>['c']) => void>;
+	[key in SseResp['type']]: Array<(data: Extract<SseResp, { type: key }>['data']) => void>;
 };
 
+/** Let the backend prepare the chat history while the user is typing */
+export function draftMessage(chatId: number) {
+	return APIFetch<MessageDraftResp, MessageDraftReq>('message/draft', { chat_id: chatId });
+}
+
+/** Messages of every chat of the user containing all words of `q` */
+export function searchMessages(q: string) {
+	return APIFetch<MessageSearchResp>(`message/search?q=${encodeURIComponent(q)}`, null, 'GET');
+}
+
 export function startSSE(chatId: number) {
-	CreateEventQuery<SseResp, SseReq>({
+	CreateEventQuery<SseEvent, SseReq>({
 		path: 'chat/sse',
 		key: ['messageEvent', chatId.toString()],
 		body: {
 			id: chatId
 		},
-		onEvent: (res: SseResp) => {
+		onEvent: (res: SseEvent) => {
 			console.log('SSE Event:', res);
 			console.log(SSEHandlers);
 
-			SSEHandlers[res.t].forEach((handler) => handler(res.c as any));
+			if (res.v != SSE_VERSION) console.warn('unexpected SSE event version', res.v);
+			if (res.type == 'error') dispatchError(res.data.error, res.data.reason);
+
+			SSEHandlers[res.type].forEach((handler) => handler(res.data as any));
 		}
 	});
 }
 
-export function addSSEHandler<T extends SseResp['t']>(
+export function addSSEHandler<T extends SseResp['type']>(
 	event: T,
-	handler: (data: Extract<SseResp, { t: T }>['c']) => void
+	handler: (data: Extract<SseResp, { type: T }>['data']) => void
 ) {
 	console.log('add SSE handler', event, handler);
 
@@ -18,7 +18,15 @@ import type {
 	ModelCreateReq,
 	ModelCreateResp,
 	ModelWriteReq,
-	ModelWriteResp
+	ModelWriteResp,
+	ModelVariantListReq,
+	ModelVariantListResp,
+	ModelVariantCreateReq,
+	ModelVariantCreateResp,
+	ModelVariantWriteReq,
+	ModelVariantWriteResp,
+	ModelVariantDeleteReq,
+	ModelVariantDeleteResp
 } from './types';
 
 export enum Mode {
@@ -102,6 +110,73 @@ export function updateModel(): CreateMutationResult<ModelWriteReq, ModelWriteRes
 	});
 }
 
+export function useVariants(model_id: number): QueryResult<ModelVariantListResp> {
+	return CreateQuery<ModelVariantListReq, ModelVariantListResp>({
+		key: ['models', model_id.toString(), 'variants'],
+		path: 'model/variant/list',
+		body: { model_id },
+		staleTime: 0
+	});
+}
+
+export function createVariant(): CreateMutationResult<
+	ModelVariantCreateReq,
+	ModelVariantCreateResp
+> {
+	return CreateMutation({
+		path: 'model/variant/create',
+		onSuccess(data, param) {
+			SetQueryData<ModelVariantListResp>({
+				key: ['models', param.model_id.toString(), 'variants'],
+				updater: (x) => {
+					x?.list.push({
+						...param,
+						id: data.id,
+						created_at: Date.now() / 1000,
+						stats: { chats: 0, replies: 0, regenerated: 0, truncated: 0, tokens: 0 }
+					});
+					return x;
+				}
+			});
+		}
+	});
+}
+
+export function writeVariant(
+	model_id: number
+): CreateMutationResult<ModelVariantWriteReq, ModelVariantWriteResp> {
+	return CreateMutation({
+		path: 'model/variant/write',
+		onSuccess(data, param) {
+			SetQueryData<ModelVariantListResp>({
+				key: ['models', model_id.toString(), 'variants'],
+				updater: (x) => {
+					const variant = x?.list.find((u) => u.id === param.id);
+					if (variant) Object.assign(variant, param);
+					return x;
+				}
+			});
+		}
+	});
+}
+
+export function deleteVariant(
+	model_id: number
+): CreateMutationResult<ModelVariantDeleteReq, ModelVariantDeleteResp> {
+	return CreateMutation({
+		path: 'model/variant/delete',
+		onSuccess(data, param) {
+			SetQueryData<ModelVariantListResp>({
+				key: ['models', model_id.toString(), 'variants'],
+				updater: (x) => {
+					if (x != undefined) x.list = x.list.filter((u) => u.id !== param.id);
+					return x;
+				}
+			});
+		}
+	});
+}
+
 export const defaultModelConfig = [
 	'display_name="GPT-OSS 20B"',
 	'# From https://openrouter.ai/models',
@@ -114,5 +189,11 @@ export const defaultModelConfig = [
 	'image = false',
 	'audio = false',
 	'# available option: Native, Text, Mistral, Disabled',
-	'ocr = "Native"'
+	'ocr = "Native"',
+	'# request reasoning tokens, shown as a collapsible "thinking" section',
+	'reasoning = false',
+	'',
+	'[parameter]',
+	'# fixed sampling seed, only honored by some providers',
+	'# seed = 42'
 ].join('\n');
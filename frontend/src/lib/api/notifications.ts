@@ -0,0 +1,50 @@
+import { CreateQuery, SetQueryData, type QueryResult } from './state';
+import { APIFetch } from './state/errorHandle';
+import type {
+	NotificationInbox,
+	NotificationListResp,
+	NotificationReadReq,
+	NotificationReadResp
+} from './types';
+
+/** Latest messages of the inbox, background features post there */
+export function useNotifications(): QueryResult<NotificationListResp> {
+	return CreateQuery<null, NotificationListResp>({
+		key: ['notifications'],
+		path: 'notifications',
+		method: 'GET'
+	});
+}
+
+/** Put a message posted while the page is open on top of the inbox */
+export function pushNotification(x: NotificationInbox) {
+	SetQueryData<NotificationListResp>({
+		key: ['notifications'],
+		updater: (data) =>
+			data && {
+				...data,
+				list: [{ ...x, read: false }, ...data.list],
+				unread: data.unread + 1
+			}
+	});
+}
+
+/** Mark messages as read, the whole inbox without `ids` */
+export async function readNotifications(ids?: number[]) {
+	const res = await APIFetch<NotificationReadResp, NotificationReadReq>('notifications/read', {
+		ids
+	});
+	if (res)
+		SetQueryData<NotificationListResp>({
+			key: ['notifications'],
+			updater: (data) =>
+				data && {
+					...data,
+					list: data.list.map((x) =>
+						ids == undefined || ids.includes(x.id) ? { ...x, read: true } : x
+					),
+					unread: res.unread
+				}
+		});
+	return res;
+}
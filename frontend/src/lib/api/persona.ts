@@ -0,0 +1,65 @@
+import { CreateQuery, SetQueryData, type QueryResult } from './state';
+import { APIFetch } from './state/errorHandle';
+import type {
+	ChatReadResp,
+	PersonaAssignReq,
+	PersonaAssignResp,
+	PersonaCreateReq,
+	PersonaCreateResp,
+	PersonaDeleteReq,
+	PersonaDeleteResp,
+	PersonaListReq,
+	PersonaListResp,
+	PersonaWriteReq,
+	PersonaWriteResp
+} from './types';
+
+export function usePersonas(): QueryResult<PersonaListResp> {
+	return CreateQuery<PersonaListReq, PersonaListResp>({
+		key: ['personas'],
+		path: 'persona/list',
+		body: {}
+	});
+}
+
+async function reloadPersonas() {
+	const res = await APIFetch<PersonaListResp, PersonaListReq>('persona/list', {});
+	if (res)
+		SetQueryData<PersonaListResp>({
+			key: ['personas'],
+			updater: () => res
+		});
+}
+
+export async function createPersona(req: PersonaCreateReq) {
+	const res = await APIFetch<PersonaCreateResp, PersonaCreateReq>('persona/create', req);
+	if (res) await reloadPersonas();
+	return res;
+}
+
+export async function writePersona(req: PersonaWriteReq) {
+	const res = await APIFetch<PersonaWriteResp, PersonaWriteReq>('persona/write', req);
+	if (res) await reloadPersonas();
+	return res;
+}
+
+/** Its chats go back to the built-in prompt */
+export async function deletePersona(id: number) {
+	const res = await APIFetch<PersonaDeleteResp, PersonaDeleteReq>('persona/delete', { id });
+	if (res) await reloadPersonas();
+	return res;
+}
+
+/** Talk as the persona from the next reply, on its model if it has one */
+export async function assignPersona(chatId: number, personaId: number | undefined) {
+	const res = await APIFetch<PersonaAssignResp, PersonaAssignReq>('persona/assign', {
+		chat_id: chatId,
+		persona_id: personaId
+	});
+	if (res)
+		SetQueryData<ChatReadResp>({
+			key: ['chatRead', chatId.toString()],
+			updater: (x) => x && { ...x, persona_id: personaId, model_id: res.model_id }
+		});
+	return res;
+}
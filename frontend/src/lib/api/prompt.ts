@@ -0,0 +1,69 @@
+import { CreateQuery, SetQueryData, type QueryResult } from './state';
+import { APIFetch } from './state/errorHandle';
+import type {
+	PromptDeleteReq,
+	PromptDeleteResp,
+	PromptListReq,
+	PromptListResp,
+	PromptPinReq,
+	PromptPinResp,
+	PromptPreviewReq,
+	PromptPreviewResp,
+	PromptReadReq,
+	PromptReadResp,
+	PromptVersionsReq,
+	PromptVersionsResp,
+	PromptWriteReq,
+	PromptWriteResp
+} from './types';
+
+export function usePrompts(): QueryResult<PromptListResp> {
+	return CreateQuery<PromptListReq, PromptListResp>({
+		key: ['prompts'],
+		path: 'prompt/list',
+		body: {}
+	});
+}
+
+async function reloadPrompts() {
+	const res = await APIFetch<PromptListResp, PromptListReq>('prompt/list', {});
+	if (res)
+		SetQueryData<PromptListResp>({
+			key: ['prompts'],
+			updater: () => res
+		});
+}
+
+/** The template of the name and locale, and the built-in prompt it replaces */
+export function readPrompt(req: PromptReadReq) {
+	return APIFetch<PromptReadResp, PromptReadReq>('prompt/read', req);
+}
+
+export async function writePrompt(req: PromptWriteReq) {
+	const res = await APIFetch<PromptWriteResp, PromptWriteReq>('prompt/write', req);
+	if (res) await reloadPrompts();
+	return res;
+}
+
+/** The built-in prompt is used again */
+export async function deletePrompt(id: number) {
+	const res = await APIFetch<PromptDeleteResp, PromptDeleteReq>('prompt/delete', { id });
+	if (res) await reloadPrompts();
+	return res;
+}
+
+export function previewPrompt(req: PromptPreviewReq) {
+	return APIFetch<PromptPreviewResp, PromptPreviewReq>('prompt/preview', req);
+}
+
+/** Every version of a template, newest first */
+export function promptVersions(id: number) {
+	return APIFetch<PromptVersionsResp, PromptVersionsReq>('prompt/versions', { id });
+}
+
+/** Use an earlier (or later) version of the template */
+export async function pinPrompt(req: PromptPinReq) {
+	const res = await APIFetch<PromptPinResp, PromptPinReq>('prompt/pin', req);
+	if (res) await reloadPrompts();
+	return res;
+}
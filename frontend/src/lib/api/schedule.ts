@@ -0,0 +1,72 @@
+import { CreateEventQuery, CreateQuery, SetQueryData, type QueryResult } from './state';
+import { APIFetch } from './state/errorHandle';
+import type {
+	Notification,
+	ScheduleCreateReq,
+	ScheduleCreateResp,
+	ScheduleDeleteReq,
+	ScheduleDeleteResp,
+	ScheduleListReq,
+	ScheduleListResp,
+	ScheduleRunReq,
+	ScheduleRunResp,
+	ScheduleWriteReq,
+	ScheduleWriteResp
+} from './types';
+
+export function useSchedules(): QueryResult<ScheduleListResp> {
+	return CreateQuery<ScheduleListReq, ScheduleListResp>({
+		key: ['schedules'],
+		path: 'schedule/list',
+		body: {},
+		staleTime: 0
+	});
+}
+
+// next runs and chats are decided by the server, reload rather than guess
+async function reloadSchedules() {
+	const res = await APIFetch<ScheduleListResp, ScheduleListReq>('schedule/list', {});
+	if (res)
+		SetQueryData<ScheduleListResp>({
+			key: ['schedules'],
+			updater: () => res
+		});
+}
+
+/** `utc_offset` defaults to the time zone of the browser */
+export async function createSchedule(req: ScheduleCreateReq) {
+	const res = await APIFetch<ScheduleCreateResp, ScheduleCreateReq>('schedule/create', {
+		utc_offset: -new Date().getTimezoneOffset(),
+		...req
+	});
+	if (res) await reloadSchedules();
+	return res;
+}
+
+export async function writeSchedule(req: ScheduleWriteReq) {
+	const res = await APIFetch<ScheduleWriteResp, ScheduleWriteReq>('schedule/write', req);
+	if (res) await reloadSchedules();
+	return res;
+}
+
+export async function deleteSchedule(id: number) {
+	const res = await APIFetch<ScheduleDeleteResp, ScheduleDeleteReq>('schedule/delete', { id });
+	if (res) await reloadSchedules();
+	return res;
+}
+
+/** Run now, the next run is unchanged */
+export async function runSchedule(id: number) {
+	const res = await APIFetch<ScheduleRunResp, ScheduleRunReq>('schedule/run', { id });
+	if (res) await reloadSchedules();
+	return res;
+}
+
+/** Follow the notifications of the user until the component is destroyed */
+export function startNotifications(onEvent: (data: Notification) => void) {
+	CreateEventQuery<Notification>({
+		path: 'user/notifications',
+		method: 'GET',
+		onEvent
+	});
+}
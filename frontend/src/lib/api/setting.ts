@@ -0,0 +1,43 @@
+import {
+	CreateQuery,
+	type QueryResult,
+	CreateMutation,
+	type CreateMutationResult,
+	SetQueryData
+} from './state';
+
+import type {
+	SettingReadReq,
+	SettingReadResp,
+	SettingWriteReq,
+	SettingWriteResp
+} from './types';
+
+export function useSetting(): QueryResult<SettingReadResp> {
+	return CreateQuery<SettingReadReq, SettingReadResp>({
+		key: ['setting'],
+		path: 'setting/read',
+		body: {},
+		staleTime: 0
+	});
+}
+
+export function WriteSetting(): CreateMutationResult<SettingWriteReq, SettingWriteResp> {
+	return CreateMutation({
+		path: 'setting/write',
+		onSuccess(_, param) {
+			SetQueryData<SettingReadResp>({
+				key: ['setting'],
+				updater: (data) => {
+					if (data != undefined && param.tools_disabled != undefined)
+						data.tools_disabled = param.tools_disabled;
+					if (data != undefined && param.disabled_sources != undefined)
+						data.disabled_sources = param.disabled_sources;
+					if (data != undefined && param.retention != undefined)
+						data.retention = param.retention;
+					return data;
+				}
+			});
+		}
+	});
+}
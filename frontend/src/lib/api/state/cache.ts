@@ -59,6 +59,11 @@ export class WritableCache {
 		return entry.store as Writable<T | undefined>;
 	}
 
+	// drop an entry, the next get start from scratch
+	delete(key: string[]): void {
+		this.map.delete(this.serialize(key));
+	}
+
 	getOr<T>(key: string[], val: T): Writable<T> {
 		const hasKey = this.hasEntry(key);
 		const entry = this.getEntry(key);
@@ -1,8 +1,9 @@
 import { dispatchError } from '$lib/error';
 import { get } from 'svelte/store';
-import type { Error as APIError } from '../types';
+import type { LocalizedError as APIError } from '../types';
 import { token } from '$lib/store';
 import { dev } from '$app/environment';
+import { locale } from 'svelte-i18n';
 
 export const apiBase = dev ? 'http://localhost:8001/api/' : '/api/';
 
@@ -15,16 +16,20 @@ export function getError(data: any): APIError | undefined {
 export async function RawAPIFetch<P = any>(
 	path: string,
 	body: P | null = null,
-	method: 'POST' | 'GET' | 'PUT' | 'UPDATE' = 'POST',
-	signal?: AbortSignal
+	method: 'POST' | 'GET' | 'PUT' | 'PATCH' | 'UPDATE' | 'DELETE' = 'POST',
+	signal?: AbortSignal,
+	extraHeaders: Record<string, string> = {}
 ): Promise<Response> {
 	let tokenVal = get(token)?.value;
 
 	if (path.startsWith('/')) throw new Error('Invalid path');
 
-	const headers: Record<string, string> = {};
+	const headers: Record<string, string> = { ...extraHeaders };
 	headers['Content-Type'] = 'application/json';
 	if (tokenVal) headers['Authorization'] = tokenVal;
+	// errors and replies follow the locale chosen in the settings
+	const localeVal = get(locale);
+	if (localeVal) headers['Accept-Language'] = localeVal;
 
 	return fetch(apiBase + path, {
 		method,
@@ -37,14 +42,34 @@ export async function RawAPIFetch<P = any>(
 export async function APIFetch<D, P = any>(
 	path: string,
 	body: P | null = null,
-	method: 'POST' | 'GET' | 'PUT' | 'UPDATE' = 'POST'
+	method: 'POST' | 'GET' | 'PUT' | 'PATCH' | 'UPDATE' | 'DELETE' = 'POST'
 ): Promise<D | undefined> {
 	const res = await RawAPIFetch(path, body, method);
 
 	try {
 		const resJson: D | APIError = await res.json();
 		const error = getError(resJson);
-		if (error) dispatchError(error.error, error.reason);
+		if (error) dispatchError(error.error, error.reason, error.message);
+		else return resJson as D;
+	} catch (_) {
+		dispatchError('API(typeshare)');
+	}
+}
+
+/** Multipart, the browser set the content type with the boundary */
+export async function APIMultipartFetch<D>(path: string, body: FormData): Promise<D | undefined> {
+	let tokenVal = get(token)?.value;
+
+	const headers: Record<string, string> = {};
+	if (tokenVal) headers['Authorization'] = tokenVal;
+	const localeVal = get(locale);
+	if (localeVal) headers['Accept-Language'] = localeVal;
+
+	try {
+		const res = await fetch(apiBase + path, { method: 'POST', headers, body });
+		const resJson: D | APIError = await res.json();
+		const error = getError(resJson);
+		if (error) dispatchError(error.error, error.reason, error.message);
 		else return resJson as D;
 	} catch (_) {
 		dispatchError('API(typeshare)');
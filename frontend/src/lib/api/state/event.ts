@@ -5,10 +5,12 @@ import { globalCache } from './cache';
 import { writable, type Readable } from 'svelte/store';
 import { dispatchError } from '$lib/error';
 
+const RECONNECT_DELAY = 1000;
+
 export interface EventQueryOption<D, P> {
 	path: string;
 	body?: P;
-	method?: 'POST' | 'GET' | 'PUT' | 'UPDATE';
+	method?: 'POST' | 'GET' | 'PUT' | 'PATCH' | 'UPDATE' | 'DELETE';
 	onEvent: (data: D) => void;
 	key?: string[];
 }
@@ -29,22 +31,31 @@ export function CreateEventQuery<D, P = null>(option: EventQueryOption<D, P>): E
 
 	(async () => {
 		// TODO: respect visibilityStateChange
-		const res = await RawAPIFetch<P>(path, body, method, controller.signal);
-		status.set(true);
-		let stream = events(res, controller.signal);
-		try {
-			for await (let event of stream) {
-				const data = event.data;
-
-				if (data != undefined && data.trim() != ':') {
-					const resJson = JSON.parse(data);
-					const error = getError(resJson);
-					if (error) dispatchError(error.error, error.reason);
-					else onEvent(resJson);
+		let lastEventId: string | undefined;
+		while (!controller.signal.aborted) {
+			try {
+				const headers: Record<string, string> = lastEventId ? { 'Last-Event-ID': lastEventId } : {};
+				const res = await RawAPIFetch<P>(path, body, method, controller.signal, headers);
+				status.set(true);
+				let stream = events(res, controller.signal);
+				for await (let event of stream) {
+					if (event.id) lastEventId = event.id;
+					const data = event.data;
+
+					if (data != undefined && data.trim() != ':') {
+						const resJson = JSON.parse(data);
+						const error = getError(resJson);
+						if (error) dispatchError(error.error, error.reason, error.message);
+						else onEvent(resJson);
+					}
 				}
+			} catch (err) {
+				if (err instanceof DOMException && err.name == 'AbortError') return;
+				console.warn('event stream dropped', err);
 			}
-		} catch (err) {
-			if (!(err instanceof DOMException && err.name == 'AbortError')) throw err;
+			// reconnect and resume from the last event
+			status.set(false);
+			await new Promise((resolve) => setTimeout(resolve, RECONNECT_DELAY));
 		}
 	})();
 
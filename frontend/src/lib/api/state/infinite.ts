@@ -141,7 +141,9 @@ class Pages<D extends { id: number }> {
 		if (pages.length == 0) return;
 
 		pages[0].data.update((x) => {
-			if (data.id != x[0]?.id) x.unshift(data);
+			// a refetch may already hold the row, e.g. a reply still streaming
+			if (data.id == x[0]?.id) x[0] = data;
+			else x.unshift(data);
 			return x;
 		});
 	}
@@ -68,7 +68,7 @@ export interface MutationResult<P, D> {
 export interface CreateMutateOption<P, D> {
 	onSuccess?: (data: D, param: P) => void;
 	path: string | (() => string);
-	method?: 'POST' | 'GET' | 'PUT' | 'UPDATE';
+	method?: 'POST' | 'GET' | 'PUT' | 'PATCH' | 'UPDATE' | 'DELETE';
 }
 
 export function CreateMutation<P, D>(option: CreateMutateOption<P, D>): MutationResult<P, D> {
@@ -2,8 +2,309 @@
  Generated by typeshare 1.13.3
 */
 
+/**
+ * Limits of the tool loop admins set at `admin/config`, read when a reply
+ * starts
+ */
+export interface AgentLoopPolicy {
+	/** Rounds of tool calls of a reply, the model answers without tools after */
+	max_depth: number;
+	/** Tool calls of a reply outside of agent mode */
+	max_tool_calls: number;
+	/** Tool calls of a reply in agent mode */
+	agent_max_tool_calls: number;
+	/** Seconds a reply in agent mode runs before it writes its final report */
+	agent_max_secs: number;
+}
+
+export interface AdminConfigReadReq {}
+
+export interface AdminConfigReadResp {
+	default_model_id?: number;
+	rate_limit: RateLimitPolicy;
+	/** Set here, otherwise `API_BASE` env is used */
+	api_base?: string;
+	/** Keys are never sent back, only whether they are set here */
+	api_key_set: boolean;
+	google_map_api_key_set: boolean;
+	moderation: ModerationPolicy;
+	/**
+	 * Whether `MODERATION_PROVIDER` is configured, `moderation.provider`
+	 * does nothing otherwise
+	 */
+	moderation_provider: boolean;
+	agent_loop: AgentLoopPolicy;
+	webhook_private_networks: boolean;
+}
+
+export interface AdminConfigWriteReq {
+	default_model_id?: number;
+	rate_limit: RateLimitPolicy;
+	/** Empty or missing follow `API_BASE` env */
+	api_base?: string;
+	/** Missing keep the key set, empty clear it to follow `API_KEY` env */
+	api_key?: string;
+	/**
+	 * Missing keep the key set, empty clear it to follow `GOOGLE_MAP_API_KEY`
+	 * env
+	 */
+	google_map_api_key?: string;
+	/** Missing keep the policy set */
+	moderation?: ModerationPolicy;
+	/** Missing keep the limits set */
+	agent_loop?: AgentLoopPolicy;
+	/** Missing keep the setting */
+	webhook_private_networks?: boolean;
+}
+
+export interface AdminConfigWriteResp {
+	wrote: boolean;
+}
+
+export interface AdminStatsReq {
+	/** default to `week` */
+	range?: AdminStatsRange;
+}
+
+export interface AdminStatsRespDay {
+	/** unix seconds, the start of the UTC day */
+	day: number;
+	/** Replies asked for, regenerations included */
+	messages: number;
+	tokens: number;
+	active_users: number;
+}
+
+export interface AdminStatsRespModel {
+	model_id: string;
+	replies: number;
+	failed: number;
+	/** Output tokens */
+	tokens: number;
+	cost: number;
+	ttft_p95_ms: number;
+}
+
+export interface AdminStatsRespTool {
+	name: string;
+	calls: number;
+}
+
+export interface AdminStatsResp {
+	/** unix seconds the range starts at, the start of a UTC day */
+	since: number;
+	users: number;
+	/** Users who asked for a reply in the range */
+	active_users: number;
+	/** Every day of the range, oldest first */
+	daily: AdminStatsRespDay[];
+	replies: number;
+	/** Replies that ended in an error */
+	failed: number;
+	/** `failed` over `replies`, 0 without replies */
+	error_rate: number;
+	/** Milliseconds until the first token, for 95% of the replies that got one */
+	ttft_p95_ms: number;
+	/** USD, of the models with a known price */
+	cost: number;
+	/** Most replies first, at most `STATS_TOP` */
+	models: AdminStatsRespModel[];
+	/** Most calls first, at most `STATS_TOP` */
+	tools: AdminStatsRespTool[];
+}
+
+export interface AdminWorkspace {
+	id: number;
+	name: string;
+	member_ids: number[];
+	/** Models offered, empty for every model */
+	model_ids: number[];
+	/** Names of the credentials set, values are never sent back */
+	credentials: string[];
+}
+
+export interface AdminWorkspaceCreateReq {
+	name: string;
+}
+
+export interface AdminWorkspaceCreateResp {
+	id: number;
+}
+
+export interface AdminWorkspaceDeleteReq {
+	id: number;
+}
+
+export interface AdminWorkspaceDeleteResp {}
+
+export interface AdminWorkspaceListReq {}
+
+export interface AdminWorkspaceListResp {
+	list: AdminWorkspace[];
+}
+
+export interface AdminWorkspaceMemberReq {
+	workspace_id: number;
+	user_id: number;
+	/** false to remove the user */
+	member: boolean;
+}
+
+export interface AdminWorkspaceMemberResp {}
+
+export interface AdminWorkspaceWriteReq {
+	id: number;
+	name: string;
+	/** Replace the models offered, empty for every model */
+	model_ids: number[];
+	/** Set by name, an empty value removes one, names left out are kept */
+	credentials: Record<string, string>;
+}
+
+export interface AdminWorkspaceWriteResp {}
+
+export interface ApiKeyCreateReq {
+	/** Shown in the list, e.g. what script use it */
+	name: string;
+	scopes: ApiKeyScope[];
+}
+
+export interface ApiKeyCreateResp {
+	id: number;
+	/** Only shown here, send it as the authorization header */
+	key: string;
+}
+
+export interface ApiKeyListReq {}
+
+export interface ApiKeyListRespItem {
+	id: number;
+	name: string;
+	/** First characters of the key */
+	prefix: string;
+	scopes: ApiKeyScope[];
+	created_at: number;
+	/** Updated at most once a minute */
+	last_used_at?: number;
+}
+
+export interface ApiKeyListResp {
+	list: ApiKeyListRespItem[];
+}
+
+export interface ApiKeyRevokeReq {
+	id: number;
+}
+
+export interface ApiKeyRevokeResp {
+	/** false if the key does not exist or belong to someone else */
+	revoked: boolean;
+}
+
+export interface AuditReq {
+	kind?: AuditKind;
+	user_id?: number;
+	/** id of the last entry of the previous page */
+	before?: number;
+}
+
+export interface AuditRespEntry {
+	id: number;
+	kind: AuditKind;
+	user_id?: number;
+	/** None if the user was deleted since */
+	username?: string;
+	ip?: string;
+	/** `x-request-id` of the request, to find its logs */
+	request_id?: string;
+	detail: string;
+	/** unix seconds */
+	created_at: number;
+}
+
+export interface AuditResp {
+	/** newest first, a full page if more are left */
+	list: AuditRespEntry[];
+}
+
+export interface BackupCreateReq {}
+
+export interface BackupRestoreResp {
+	users: number;
+	chats: number;
+	files: number;
+}
+
+export interface CaptureReq {
+	/** Page the user is on */
+	url: string;
+	title?: string;
+	/** Text selected on the page */
+	selection?: string;
+	/**
+	 * PNG or JPEG in base64, a data url such as the one of
+	 * `chrome.tabs.captureVisibleTab` is accepted
+	 */
+	screenshot?: string;
+	/** Without it the model is asked about the page alone */
+	question?: string;
+	/** Append to this chat, a new one is created otherwise */
+	chat_id?: number;
+	/** Model of a new chat, default to the one of the last chat of the user */
+	model_id?: number;
+	/** default to normal */
+	mode?: MessageCreateReqMode;
+}
+
+export interface CaptureResp {
+	chat_id: number;
+	/** The user message with the page, the reply stream on `/api/chat/sse` */
+	id: number;
+	/** Where to open the chat, absolute when `PUBLIC_URL` is set */
+	link: string;
+}
+
+export interface ChatArchiveReq {
+	chat_id: number;
+	/** false to bring it back */
+	archived: boolean;
+}
+
+export interface ChatArchiveResp {
+	wrote: boolean;
+}
+
+export interface ChatBranchReq {
+	chat_id: number;
+	/** any message of the branch, e.g. one of `siblings` */
+	message_id: number;
+}
+
+export interface ChatBranchResp {
+	/** last message of the branch now shown */
+	head_id: number;
+}
+
+export interface ChatContinueReq {
+	/** id of the interrupted assistant message */
+	message_id: number;
+	mode: MessageCreateReqMode;
+}
+
+export interface ChatContinueResp {
+	/** the message the new reply goes on from */
+	parent_id: number;
+}
+
 export interface ChatCreateReq {
 	model_id: number;
+	/** pin model, params and seed on every message, default to false */
+	reproducible?: boolean;
+	/**
+	 * Persona of the user the chat talks as, its model if it has one stands
+	 * in for `model_id`
+	 */
+	persona_id?: number;
 }
 
 export interface ChatCreateResp {
@@ -16,13 +317,95 @@ export interface ChatDeleteReq {
 
 export interface ChatDeleteResp {
 	deleted: boolean;
+	/** POST to `/api/undo/{token}` to bring the chat back, absent if undo is disabled */
+	undo_token?: string;
+}
+
+export interface ChatDraftResp {
+	/** Empty without a draft */
+	content: string;
+	/** None without a draft */
+	updated_at?: number;
+}
+
+export interface ChatDraftWriteReq {
+	/** An empty text removes the draft */
+	content: string;
+}
+
+/** UTC days up to the current one */
+export enum AdminStatsRange {
+	Day = 'day',
+	Week = 'week',
+	Month = 'month',
+	/** The 90 days of `STATS_DAYS` */
+	Quarter = 'quarter'
+}
+
+/** What an API key can do, on top of identifying its user */
+export enum ApiKeyScope {
+	/** Read chats, messages and models */
+	Read = 'read',
+	/** Create and write chats and messages */
+	Chat = 'chat',
+	/** Let the model call tools in messages sent with the key */
+	Tools = 'tools',
+	/** Only counts and usage, never the content of chats, for dashboards */
+	Stats = 'stats'
+}
+
+export enum ChatExportReqFormat {
+	Md = 'md',
+	Html = 'html',
+	/** with the tool calls, for programs */
+	Json = 'json',
+	/** only with the `pdf` feature */
+	Pdf = 'pdf'
+}
+
+export interface ChatExportReq {
+	format: ChatExportReqFormat;
 }
 
 export interface ChatHaltReq {
 	id: number;
 }
 
-export interface ChatHaltResp {}
+export interface ChatHaltResp {
+	/** false if the chat is not generating */
+	halted: boolean;
+}
+
+export enum ChatImportReqFormat {
+	/** `json` export of a chat, or the export of every chat */
+	Llumen = 'llumen',
+	/** `conversations.json` of a ChatGPT data export */
+	Chatgpt = 'chatgpt'
+}
+
+export interface ChatImportReq {
+	/** model of the imported chats */
+	model_id: number;
+	format: ChatImportReqFormat;
+	/** content of the exported file */
+	data: string;
+}
+
+export interface ChatImportResp {
+	/** in the order of the file */
+	ids: number[];
+}
+
+export interface ChatMergeReq {
+	first_id: number;
+	second_id: number;
+	/** default to be generated after the next message */
+	title?: string;
+}
+
+export interface ChatMergeResp {
+	id: number;
+}
 
 export enum ChatPaginateReqOrder {
 	/** greater than */
@@ -40,6 +423,7 @@ export interface ChatPaginateReqLimit {
 	id?: number;
 	order: ChatPaginateReqOrder;
 	limit?: number;
+	filter?: ChatPaginateReqFilter;
 }
 
 /**
@@ -49,18 +433,52 @@ export interface ChatPaginateReqLimit {
 export interface ChatPaginateReqRange {
 	upper: number;
 	lower: number;
+	filter?: ChatPaginateReqFilter;
+}
+
+/**
+ * Only chats with all the given tags, see `utils::tagger`, and in the given
+ * folder and label, see `utils::folder`
+ */
+export interface ChatPaginateReqFilter {
+	topic?: string;
+	sentiment?: ChatSentiment;
+	task?: ChatTask;
+	folder_id?: number;
+	label_id?: number;
+	pinned?: boolean;
+	/** true for only the archived chats, they are left out otherwise */
+	archived?: boolean;
 }
 
 export interface ChatPaginateRespList {
 	id: number;
 	model_id: number;
 	title?: string;
+	topic?: string;
+	sentiment?: ChatSentiment;
+	task?: ChatTask;
+	/** When the retention policy delete it, unless a message is sent before */
+	delete_at?: number;
+	folder_id?: number;
+	label_ids: number[];
+	pinned: boolean;
+	archived_at?: number;
 }
 
 export interface ChatPaginateResp {
 	list: ChatPaginateRespList[];
 }
 
+export interface ChatPinReq {
+	chat_id: number;
+	pinned: boolean;
+}
+
+export interface ChatPinResp {
+	wrote: boolean;
+}
+
 export interface ChatReadReq {
 	id: number;
 }
@@ -68,276 +486,2200 @@ export interface ChatReadReq {
 export interface ChatReadResp {
 	model_id?: number;
 	title?: string;
+	reproducible: boolean;
+	/** Set while the chat is shared at `/share/{token}` */
+	share_token?: string;
+	pinned: boolean;
+	archived_at?: number;
+	/** Role of the user, see `chat/{id}/member` */
+	role: ChatMemberRole;
+	/** Persona the chat talks as, see `persona/assign` */
+	persona_id?: number;
+	/**
+	 * Collections the documents searched are in, empty for all, see
+	 * `kb/assign`
+	 */
+	collection_ids: number[];
 }
 
-export interface ChatUpdateReq {
-	chat_id: number;
-	title?: string;
+/** Mood of the user over a chat, guessed by the tagger */
+export enum ChatSentiment {
+	Negative = 'negative',
+	Neutral = 'neutral',
+	Positive = 'positive'
 }
 
-export interface ChatUpdateResp {
-	wrote: boolean;
+/** Part of a member in a shared chat, the creator of the chat is always an owner */
+export enum ChatMemberRole {
+	/** manage the members and the settings of the chat */
+	Owner = 'owner',
+	/** read and send messages */
+	Member = 'member'
 }
 
-export enum ErrorKind {
-	Unauthorized = 'unauthorized',
-	MalformedToken = 'malformed_token',
-	MalformedRequest = 'malformed_request',
-	Internal = 'internal',
+/** Rating of a reply by its user */
+export enum FeedbackRating {
+	Down = 'down',
+	Up = 'up'
+}
+
+/** What the user wanted from a chat, guessed by the tagger */
+export enum ChatTask {
+	Other = 'other',
+	Question = 'question',
+	Coding = 'coding',
+	Writing = 'writing',
+	Analysis = 'analysis',
+	Planning = 'planning',
+	Chitchat = 'chitchat'
+}
+
+/** Where the ingestion of a document is at */
+export enum DocumentStatus {
+	Pending = 'pending',
+	Ready = 'ready',
+	Failed = 'failed'
+}
+
+/** Security relevant event of `audit_log` */
+export enum AuditKind {
+	Login = 'login',
+	/** wrong password or code, the user is the account tried if it exists */
 	LoginFail = 'login_fail',
-	ResourceNotFound = 'resource_not_found',
-	ApiFail = 'api_fail',
-	ToolCallFail = 'tool_call_fail'
+	/** an access token renewed with a refresh token */
+	Refresh = 'refresh',
+	ToolCall = 'tool_call',
+	/** settings, models, prompts, quotas or tools changed by an admin */
+	Config = 'config',
+	/** a user created or deleted by an admin */
+	User = 'user',
+	/** a message or reply flagged or blocked, see `moderation` */
+	Moderation = 'moderation'
+}
+
+/** What a row of `job` does, its payload tells on what */
+export enum JobKind {
+	Title = 'title',
+	/** chunk and embed a document of the knowledge base */
+	Ingest = 'ingest',
+	/** a run of a scheduled task */
+	Schedule = 'schedule',
+	Mail = 'mail',
+	Retention = 'retention',
+	TrashPurge = 'trash_purge',
+	AccountPurge = 'account_purge',
+	StatsPurge = 'stats_purge',
+	/** a delivery to a webhook of a user */
+	Webhook = 'webhook',
+	/** a Web Push message to a browser of a user */
+	Push = 'push',
+	/** a zip of everything kept about a user, see `user/takeout` */
+	Takeout = 'takeout'
+}
+
+/** Where a row of `job` is at, done jobs are deleted */
+export enum JobStatus {
+	/** waiting for its time, either new or retried after a failure */
+	Pending = 'pending',
+	Running = 'running',
+	/** gave up after the last attempt, kept for an admin to retry */
+	Failed = 'failed'
+}
+
+/** Background feature an `inbox_message` comes from, see `notify::inbox` */
+export enum InboxSource {
+	/** a run of a scheduled task */
+	Schedule = 'schedule',
+	/** spending went past a monthly cap or most of it */
+	SpendCap = 'spend_cap'
+}
+
+export interface ChatMemberAddReq {
+	/** username of the account to add */
+	name: string;
+	/** adding a member again change their role */
+	role: ChatMemberRole;
 }
 
-export interface Error {
-	error: ErrorKind;
-	reason: string;
+export interface ChatMemberAddResp {
+	user_id: number;
 }
 
-export interface LoginReq {
-	username: string;
-	password: string;
+export interface ChatMemberListRespItem {
+	user_id: number;
+	name: string;
+	role: ChatMemberRole;
+	/** the creator cannot be removed */
+	creator: boolean;
 }
 
-export interface LoginResp {
+export interface ChatMemberListResp {
+	/** the creator first, then by the time they joined */
+	list: ChatMemberListRespItem[];
+}
+
+export interface ChatMemberRemoveReq {
+	user_id: number;
+}
+
+export interface ChatMemberRemoveResp {
+	/** false if the user was not a member */
+	removed: boolean;
+}
+
+export interface ChatSettingsResp {
+	/**
+	 * Instructions for this chat, a template with the variables of the
+	 * built-in prompt, e.g. `{{ user.name }}`
+	 */
+	system_prompt?: string;
+	/**
+	 * Whether `system_prompt` stands in for the built-in prompt of the
+	 * normal mode, otherwise it follows the prompt of every mode
+	 */
+	system_prompt_replace: boolean;
+}
+
+/** Missing fields are left unchanged */
+export interface ChatSettingsWriteReq {
+	/** An empty prompt removes it */
+	system_prompt?: string;
+	system_prompt_replace?: boolean;
+}
+
+export interface ChatShareResp {
+	/** Anyone can read the chat at `/share/{token}` */
 	token: string;
-	exp: string;
 }
 
-export enum MessageCreateReqMode {
-	Normal = 'normal',
-	Search = 'search',
-	Agent = 'agent',
-	Research = 'research'
+export interface ChatTagsReq {}
+
+export interface ChatTagsRespTopic {
+	topic: string;
+	count: number;
 }
 
-export interface MessageCreateReq {
+export interface ChatTagsRespSentiment {
+	sentiment: ChatSentiment;
+	count: number;
+}
+
+export interface ChatTagsRespTask {
+	task: ChatTask;
+	count: number;
+}
+
+export interface ChatTagsResp {
+	/** Most frequent first, at most [`TAG_TOP_TOPICS`] */
+	topics: ChatTagsRespTopic[];
+	sentiments: ChatTagsRespSentiment[];
+	tasks: ChatTagsRespTask[];
+	/** Chats the tagger has not read yet */
+	untagged: number;
+}
+
+export interface ChatToolInputReq {
+	/** JSON matching the schema of the question */
+	answer: string;
+}
+
+export interface ChatToolInputResp {
+	/** false if the call no longer wait, e.g. it timed out or was halted */
+	accepted: boolean;
+}
+
+export interface ChatTrashListReq {}
+
+export interface ChatTrashListRespItem {
+	id: number;
 	chat_id: number;
-	mode: MessageCreateReqMode;
-	text: string;
+	/** Absent when the whole chat was deleted */
+	message_id?: number;
+	/** Title of the chat, or the start of the text of the message */
+	title?: string;
+	/** unix seconds */
+	deleted_at: number;
+	/** unix seconds */
+	purge_at: number;
 }
 
-export interface MessageCreateResp {
+export interface ChatTrashListResp {
+	/** Latest deletion first */
+	list: ChatTrashListRespItem[];
+}
+
+export interface ChatTrashRestoreReq {
 	id: number;
 }
 
-export enum MessagePaginateReqOrder {
-	/** greater than */
-	Gt = 'gt',
-	/** less than */
-	Lt = 'lt'
+export interface ChatTrashRestoreResp {
+	/** Chat the restored rows belong to, refetch it */
+	chat_id: number;
 }
 
-export interface MessagePaginateReqLimit {
+export interface ChatUnshareResp {
+	/** false if the chat was not shared */
+	revoked: boolean;
+}
+
+export interface ChatUpdateReq {
 	chat_id: number;
-	/** default to i32::MAX */
-	id?: number;
-	order: MessagePaginateReqOrder;
-	limit?: number;
+	title?: string;
 }
 
-/**
- * Does not include upper & lower
- * lower [... return items ... ] upper
- */
-export interface MessagePaginateReqRange {
+export interface ChatUpdateResp {
+	wrote: boolean;
+}
+
+export interface ChatVoiceResp {
+	/** The user message, the reply stream on `/api/chat/sse` as usual */
+	id: number;
+	/** What was heard */
+	text: string;
+}
+
+export interface ContextReq {}
+
+export interface ContextRespList {
 	chat_id: number;
-	upper: number;
-	lower: number;
+	owner_id: number;
+	completions: number;
+	/** completions cut by the output limit of the provider */
+	truncations: number;
+	last_prompt_tokens: number;
+	max_prompt_tokens: number;
+	/** None if the model is not in the synced model list */
+	context_length?: number;
+	/** completions in a row at the ceiling, see `CONTEXT_CEILING_RATIO` */
+	streak: number;
+	/** unix seconds */
+	updated_at: number;
+}
+
+export interface ContextResp {
+	list: ContextRespList[];
+}
+
+export interface DemoLoginReq {
+	captcha_id: number;
+	answer: number;
+}
+
+export interface DemoLoginResp {
+	token: string;
+	exp: string;
+}
+
+export interface DemoStatusResp {
+	/** a fresh captcha, needed to start a session */
+	captcha_id: number;
+	question: string;
+	/** left for this IP in the current hour */
+	sessions_left: number;
+	messages_left: number;
+}
+
+export enum ErrorKind {
+	Unauthorized = 'unauthorized',
+	MalformedToken = 'malformed_token',
+	MalformedRequest = 'malformed_request',
+	Internal = 'internal',
+	LoginFail = 'login_fail',
+	/** The password is right, but a TOTP or recovery code is needed */
+	TotpRequired = 'totp_required',
+	ResourceNotFound = 'resource_not_found',
+	ApiFail = 'api_fail',
+	ToolCallFail = 'tool_call_fail',
+	/** Generations are paused by the spend guard until an admin resume them */
+	Paused = 'paused',
+	/** A daily or monthly quota of the user is used up, see `user/usage` */
+	QuotaExceeded = 'quota_exceeded',
+	/** Too many requests in a short time, see `middlewares::rate_limit` */
+	RateLimited = 'rate_limited',
+	/** The request body is over its limit, see `middlewares::body_limit` */
+	PayloadTooLarge = 'payload_too_large',
+	/**
+	 * The message or reply was blocked by the moderation policy, see
+	 * `moderation`
+	 */
+	Moderated = 'moderated',
+	/**
+	 * A monthly spending cap of the user or of the model is reached, see
+	 * `spend::cap`
+	 */
+	SpendCapped = 'spend_capped',
+	/**
+	 * Arguments written by the model do not match the schema of the tool,
+	 * see `tools::validate`
+	 */
+	ToolArgsInvalid = 'tool_args_invalid',
+	/** Changed since the version the request was based on, read it again */
+	Conflict = 'conflict'
+}
+
+export interface FederationModelsResp {
+	/** upstream model ids, use them as `peer/{id}` on the home instance */
+	list: string[];
+}
+
+export interface FeedbackReq {}
+
+export interface FeedbackRespModel {
+	model_id: number;
+	/** None if the model config is malformed */
+	display_name?: string;
+	up: number;
+	down: number;
+}
+
+export interface FeedbackRespComment {
+	message_id: number;
+	chat_id: number;
+	rating: FeedbackRating;
+	comment: string;
+	/** hash of the prompt the reply was written with, see `Generation` */
+	prompt_version?: string;
+	/** version of the admin template it was built on, see `prompt/versions` */
+	template_version?: number;
+	/** unix seconds */
+	created_at: number;
+}
+
+export interface FeedbackResp {
+	up: number;
+	down: number;
+	/** by the model of the chat, the most rated first */
+	models: FeedbackRespModel[];
+	/** latest ratings with a comment */
+	comments: FeedbackRespComment[];
+}
+
+export interface FileDeleteReq {
+	id: number;
+}
+
+export interface FileDeleteResp {
+	deleted: boolean;
+}
+
+export interface FileUploadResp {
+	id: number;
+	name: string;
+	/** Sniffed from the content */
+	content_type: string;
+	size: number;
+}
+
+export interface FolderAssignReq {
+	chat_id: number;
+	/** None to move the chat out of its folder */
+	folder_id?: number;
+}
+
+export interface FolderAssignResp {
+	wrote: boolean;
+}
+
+export interface FolderCreateReq {
+	name: string;
+}
+
+export interface FolderCreateResp {
+	id: number;
+}
+
+export interface FolderDeleteReq {
+	id: number;
+}
+
+export interface FolderDeleteResp {
+	deleted: boolean;
+}
+
+export interface FolderListReq {}
+
+export interface FolderListRespItem {
+	id: number;
+	name: string;
+	chats: number;
+}
+
+export interface FolderListResp {
+	list: FolderListRespItem[];
+}
+
+export interface FolderWriteReq {
+	id: number;
+	name: string;
+}
+
+export interface FolderWriteResp {
+	/** false if the folder does not exist or the name is taken */
+	wrote: boolean;
+}
+
+export interface ForgotReq {
+	username: string;
+}
+
+export interface ForgotResp {}
+
+export interface JobDeleteReq {
+	id: number;
+}
+
+export interface JobDeleteResp {
+	deleted: boolean;
+}
+
+export interface JobListReq {}
+
+export interface JobListRespEntry {
+	id: number;
+	kind: JobKind;
+	status: JobStatus;
+	/** what the job is about, e.g. the chat to title or the recipient of a mail */
+	target: string;
+	attempts: number;
+	error: string;
+	/** unix seconds of the next attempt of a pending job */
+	run_at: number;
+	/** unix seconds */
+	created_at: number;
+	/** unix seconds of the latest failure */
+	updated_at: number;
+}
+
+export interface JobListResp {
+	/** failed jobs and the ones waiting for a retry, latest failure first */
+	list: JobListRespEntry[];
+}
+
+export interface JobRetryReq {
+	id: number;
+}
+
+export interface JobRetryResp {
+	/** false if the job is not failed, e.g. retried already */
+	retried: boolean;
+}
+
+export interface KbAssignReq {
+	chat_id: number;
+	/** Replace the scope of the chat, empty to search every document */
+	collection_ids: number[];
+}
+
+export interface KbAssignResp {
+	wrote: boolean;
+}
+
+export interface KbCollectionCreateReq {
+	name: string;
+}
+
+export interface KbCollectionCreateResp {
+	id: number;
+}
+
+export interface KbCollectionDeleteReq {
+	id: number;
+}
+
+export interface KbCollectionDeleteResp {
+	deleted: boolean;
+}
+
+export interface KbCollectionListReq {}
+
+export interface KbCollectionListRespItem {
+	id: number;
+	name: string;
+	documents: number;
+}
+
+export interface KbCollectionListResp {
+	/** By name */
+	list: KbCollectionListRespItem[];
+}
+
+export interface KbCreateReq {
+	/** Uploaded with `/api/file/upload` */
+	file_id: number;
+	collection_id?: number;
+}
+
+export interface KbCreateResp {
+	id: number;
+}
+
+export interface KbDeleteReq {
+	id: number;
+}
+
+export interface KbDeleteResp {
+	/** false if the document does not exist */
+	deleted: boolean;
+}
+
+export interface KbListReq {
+	/** Only the documents of the collection */
+	collection_id?: number;
+}
+
+export interface KbListRespItem {
+	id: number;
+	name: string;
+	status: DocumentStatus;
+	/** Why the ingestion failed */
+	error?: string;
+	chunks: number;
+	created_at: number;
+	collection_id?: number;
+}
+
+export interface KbListResp {
+	/** Latest first */
+	list: KbListRespItem[];
+}
+
+export interface KbMoveReq {
+	/** id of the document */
+	id: number;
+	/** None to take it out of its collection */
+	collection_id?: number;
+}
+
+export interface KbMoveResp {
+	wrote: boolean;
+}
+
+export interface KbReindexReq {
+	id: number;
+}
+
+export interface KbReindexResp {
+	/** false if the document is being read already */
+	reindexed: boolean;
+}
+
+export interface KeysRotateReq {}
+
+export interface KeysRotateResp {
+	/** Older keys still accepted until the tokens they encrypted expire */
+	retired: number;
+}
+
+export interface LabelAssignReq {
+	chat_id: number;
+	/** Replace the labels of the chat, empty to remove them all */
+	label_ids: number[];
+}
+
+export interface LabelAssignResp {
+	wrote: boolean;
+}
+
+export interface LabelCreateReq {
+	name: string;
+}
+
+export interface LabelCreateResp {
+	id: number;
+}
+
+export interface LabelDeleteReq {
+	id: number;
+}
+
+export interface LabelDeleteResp {
+	deleted: boolean;
+}
+
+export interface LabelListReq {}
+
+export interface LabelListRespItem {
+	id: number;
+	name: string;
+	chats: number;
+}
+
+export interface LabelListResp {
+	list: LabelListRespItem[];
+}
+
+export interface LabelWriteReq {
+	id: number;
+	name: string;
+}
+
+export interface LabelWriteResp {
+	/** false if the label does not exist or the name is taken */
+	wrote: boolean;
+}
+
+export interface MessagePaginateRespFile {
+	id: number;
+	name: string;
+	content_type: string;
+	size: number;
+}
+
+export interface ModelParameter {
+	temperature?: number;
+	repeat_penalty?: number;
+	top_k?: number;
+	top_p?: number;
+	/** sampling seed, only honored by some providers */
+	seed?: number;
+}
+
+/** What produced an assistant message, pinned in reproducible chats */
+export interface Generation {
+	model_id: string;
+	parameter: ModelParameter;
+	prompt_version: string;
+	/**
+	 * Version of the admin template the prompt was built on, see
+	 * `prompt_template_version`, None for the built-in prompt
+	 */
+	template_version?: number;
+	/** unix seconds, 0 for messages generated before it was recorded */
+	created_at?: number;
+}
+
+export enum TraceStatus {
+	Done = 'done',
+	Failed = 'failed',
+	/** Not called, the limits were reached or the tool is unknown */
+	Skipped = 'skipped',
+	/** The reply was halted during the call */
+	Halted = 'halted'
+}
+
+export interface TraceTool {
+	name: string;
+	/** Milliseconds from the start of the reply */
+	start_ms: number;
+	/** Waiting for the user to answer included */
+	duration_ms: number;
+	status: TraceStatus;
+}
+
+/** A completion of the model, then the tools it called */
+export interface TraceRound {
+	/** As reported by the provider */
+	model: string;
+	/** Milliseconds from the start of the reply */
+	start_ms: number;
+	duration_ms: number;
+	tokens: number;
+	/** In USD */
+	cost: number;
+	finish_reason?: string;
+	tools: TraceTool[];
+}
+
+/** How an assistant message ran the tool loop, kept for `message/{id}/trace` */
+export interface Trace {
+	rounds: TraceRound[];
+	/** Milliseconds from the start of the reply to its end */
+	duration_ms: number;
+	/** The limit the loop ran into, the model then answered without tools */
+	stopped?: string;
+}
+
+/** An [`Error`] as answered */
+export interface LocalizedError {
+	error: ErrorKind;
+	/** What went wrong in detail, in English */
+	reason: string;
+	/**
+	 * What `error` means for the user, in the locale of the request, see
+	 * `middlewares::locale`
+	 */
+	message: string;
+}
+
+export interface LoginReq {
+	username: string;
+	password: string;
+	/** TOTP or recovery code, required once TOTP is enabled */
+	totp?: string;
+}
+
+export interface LoginResp {
+	token: string;
+	exp: string;
+	refresh_token: string;
+}
+
+export interface LogoutReq {
+	refresh_token: string;
+}
+
+export interface LogoutResp {
+	/** false if the token was already revoked or unknown */
+	revoked: boolean;
+}
+
+export enum MessageCreateReqMode {
+	Normal = 'normal',
+	Search = 'search',
+	Agent = 'agent',
+	Research = 'research'
+}
+
+export interface MemoryCreateReq {
+	/** A short sentence about the user, e.g. `I am vegetarian` */
+	content: string;
+}
+
+export interface MemoryCreateResp {
+	/** None if the same fact is already kept */
+	id?: number;
+}
+
+export interface MemoryDeleteReq {
+	id: number;
+}
+
+export interface MemoryDeleteResp {
+	/** false if the fact does not exist */
+	deleted: boolean;
+}
+
+export interface MemoryListReq {}
+
+export interface MemoryListRespItem {
+	id: number;
+	content: string;
+	/** Chat the model saved the fact in, None if the user wrote it */
+	chat_id?: number;
+	created_at: number;
+}
+
+export interface MemoryListResp {
+	/** Latest first */
+	list: MemoryListRespItem[];
+}
+
+export interface MessageCreateReq {
+	chat_id: number;
+	mode: MessageCreateReqMode;
+	text: string;
+	/** Uploaded with `/api/file/upload`, sent to the model along the text */
+	files?: number[];
+}
+
+export interface MessageCreateResp {
+	id: number;
+}
+
+export interface MessageDeleteReq {
+	id: number;
+}
+
+export interface MessageDeleteResp {
+	deleted: boolean;
+	/** POST to `/api/undo/{token}` to bring the message back, absent if undo is disabled */
+	undo_token?: string;
+}
+
+export interface MessageDraftReq {
+	chat_id: number;
+}
+
+export interface MessageDraftResp {
+	/** false while a completion of the chat is in-flight */
+	prefetched: boolean;
+}
+
+export interface MessageEditReq {
+	text: string;
+	/**
+	 * Answer the edited message again on a new branch, like
+	 * `/api/message/write`; default to false, which fix the text in place and
+	 * keep the replies
+	 */
+	rerun?: boolean;
+	/** Only used with `rerun`, default to normal */
+	mode?: MessageCreateReqMode;
+}
+
+export interface MessageEditResp {
+	/** The edited message, a new sibling of it with `rerun` */
+	id: number;
+}
+
+export interface MessageFeedbackReq {
+	/** None take the rating back */
+	rating?: FeedbackRating;
+	/** What was wrong or right with the reply */
+	comment?: string;
+}
+
+export interface MessageFeedbackResp {
+	rating?: FeedbackRating;
+}
+
+export enum MessagePaginateReqOrder {
+	/** greater than */
+	Gt = 'gt',
+	/** less than */
+	Lt = 'lt'
+}
+
+export interface MessagePaginateReqLimit {
+	chat_id: number;
+	/**
+	 * Cursor, exclusive. Default to the latest message for `Lt`, the
+	 * first for `Gt`
+	 */
+	id?: number;
+	order: MessagePaginateReqOrder;
+	/** default to and capped at 100 */
+	limit?: number;
+}
+
+/**
+ * Does not include upper & lower
+ * lower [... return items ... ] upper
+ */
+export interface MessagePaginateReqRange {
+	chat_id: number;
+	upper: number;
+	lower: number;
+}
+
+export enum MessagePaginateRespRole {
+	User = 'user',
+	Assistant = 'assistant',
+	Marker = 'marker'
+}
+
+export type MessagePaginateRespChunkKind =
+	| { t: 'text'; c: MessagePaginateRespChunkKindText }
+	| { t: 'reasoning'; c: MessagePaginateRespChunkKindReasoning }
+	| { t: 'tool_call'; c: MessagePaginateRespChunkKindToolCall };
+
+export interface MessagePaginateRespChunk {
+	id: number;
+	kind: MessagePaginateRespChunkKind;
+}
+
+export enum MessagePaginateRespLinkKind {
+	Mail = 'mail',
+	MailThread = 'mail_thread',
+	Place = 'place',
+	Document = 'document'
+}
+
+export interface MessagePaginateRespLink {
+	kind: MessagePaginateRespLinkKind;
+	id: string;
+	label: string;
+}
+
+export interface MessagePaginateRespList {
+	id: number;
+	role: MessagePaginateRespRole;
+	chunks: MessagePaginateRespChunk[];
+	/** the upstream timed out and the reply is partial */
+	truncated: boolean;
+	/** the server stopped while the reply streamed, see `chat/{id}/continue` */
+	interrupted: boolean;
+	/** only visible to its author */
+	private: boolean;
+	/**
+	 * member who sent the message or asked for the reply, None for the owner
+	 * in older messages, see `chat/{id}/member`
+	 */
+	author_id?: number;
+	/** only on assistant messages */
+	generation?: Generation;
+	/** entities referenced by tool results */
+	links: MessagePaginateRespLink[];
+	/** sent along a user message, download with `/api/file/{id}` */
+	files?: MessagePaginateRespFile[];
+	/** rating of the user, only on assistant messages */
+	feedback?: FeedbackRating;
+	/** previous message of its branch, None for the first message */
+	parent_id?: number;
+	/**
+	 * ids of the edits or regenerations of this message, itself included,
+	 * only if there are some
+	 */
+	siblings?: number[];
+}
+
+export interface MessagePaginateResp {
+	list: MessagePaginateRespList[];
+	/**
+	 * `id` of the next page in the same order, None once the history is
+	 * exhausted or for ranges
+	 */
+	next?: number;
+}
+
+export interface MessagePaginateRespChunkKindReasoning {
+	context: string;
+}
+
+export interface MessagePaginateRespChunkKindText {
+	context: string;
+}
+
+export interface MessagePaginateRespChunkKindToolCall {
+	name: string;
+	args: string;
+	context: string;
+}
+
+export interface MessageRegenerateReq {
+	/** id of the assistant message to replace */
+	id: number;
+	mode: MessageCreateReqMode;
+}
+
+export interface MessageRegenerateResp {
+	/** the message the new reply answer */
+	parent_id?: number;
+}
+
+export interface MessageSearchReq {
+	/** Words separated by spaces, all of them must appear */
+	q: string;
+	/** Only in this chat */
+	chat_id?: number;
+	/** default to and capped at 50 */
+	limit?: number;
+}
+
+export interface MessageSearchRespPart {
+	text: string;
+	/** One of the searched words */
+	hit: boolean;
+}
+
+export interface MessageSearchRespItem {
+	chat_id: number;
+	chat_title?: string;
+	message_id: number;
+	role: MessagePaginateRespRole;
+	/** Text around the first hit, split where the words start and end */
+	snippet: MessageSearchRespPart[];
+}
+
+export interface MessageSearchResp {
+	/** Best match first */
+	list: MessageSearchRespItem[];
+}
+
+export interface MessageTraceResp {
+	/** None for replies written before they were traced */
+	trace?: Trace;
+}
+
+export interface MessageSummarizeReq {
+	chat_id: number;
+	/** Uploaded with `/api/file/upload`, a text, HTML, PDF or DOCX file */
+	file_id: number;
+}
+
+export interface MessageSummarizeResp {
+	id: number;
+}
+
+export interface MessageVisibilityReq {
+	/** message id */
+	id: number;
+	/** hide from other members and shared exports, still in the author's context */
+	private: boolean;
+}
+
+export interface MessageVisibilityResp {
+	wrote: boolean;
+}
+
+export interface MessageWriteReq {
+	/** id of the user message to edit */
+	id: number;
+	text: string;
+	mode: MessageCreateReqMode;
+}
+
+export interface MessageWriteResp {
+	/** the edited message, a sibling of `id` */
+	id: number;
+}
+
+/** What a match does */
+export enum ModerationAction {
+	/** Nothing is screened */
+	Off = 'off',
+	/** Written to the audit log, the text goes through */
+	Flag = 'flag',
+	/** Written to the audit log and refused */
+	Block = 'block'
+}
+
+export interface ModerationPolicy {
+	action: ModerationAction;
+	/** Case-insensitive regexes, a plain word matches anywhere */
+	rules: string[];
+	/** Also ask `MODERATION_PROVIDER`, ignored without it */
+	provider: boolean;
+	/** Screen replies too, not only the messages of users */
+	output: boolean;
+}
+
+export interface OauthProvidersReq {}
+
+export interface OauthProvidersResp {
+	/** `google`, `github` or `oidc`, empty if social login is disabled */
+	list: string[];
+}
+
+export interface OauthStartReq {}
+
+export interface OauthStartResp {
+	/** send the browser here, it come back to `/login` with the token in the fragment */
+	url: string;
+}
+
+export enum OcrEngine {
+	Native = 'Native',
+	Text = 'Text',
+	Mistral = 'Mistral',
+	Disabled = 'Disabled'
+}
+
+export interface ModelCapability {
+	image?: boolean;
+	audio?: boolean;
+	ocr?: OcrEngine;
+	reasoning?: boolean;
+}
+
+export interface ModelCheckReq {
+	config: string;
+}
+
+export interface ModelCheckResp {
+	reason?: string;
+}
+
+export interface ModelConfig {
+	display_name: string;
+	model_id: string;
+	capability?: ModelCapability;
+	parameter?: ModelParameter;
+}
+
+export interface ModelCreateReq {
+	config: string;
+}
+
+export interface ModelCreateResp {
+	id: number;
+	display_name: string;
+}
+
+export interface ModelDeleteReq {
+	id: number;
+}
+
+export interface ModelDeleteResp {
+	deleted: boolean;
+}
+
+export interface ModelList {
+	id: number;
+	display_name: string;
+}
+
+export interface ModelListReq {}
+
+export interface ModelListResp {
+	list: ModelList[];
+	/**
+	 * Model new chats start with, the user's `default_model_id` else the
+	 * one admins set
+	 */
+	default_id?: number;
+}
+
+export interface ModelReadReq {
+	id: number;
+}
+
+export interface ModelReadResp {
+	raw: string;
+}
+
+export interface ModelVariantCreateReq {
+	model_id: number;
+	name: string;
+	/** None for the built-in prompt, the control of the experiment */
+	template?: string;
+	weight: number;
+}
+
+export interface ModelVariantCreateResp {
+	id: number;
+}
+
+/** Chats of a deleted variant are sampled again on their next message */
+export interface ModelVariantDeleteReq {
+	id: number;
+}
+
+export interface ModelVariantDeleteResp {
+	deleted: boolean;
+}
+
+export interface ModelVariantListReq {
+	model_id: number;
+}
+
+/** Quality of the replies written with a variant */
+export interface ModelVariantStats {
+	/** chats with a reply of the variant */
+	chats: number;
+	replies: number;
+	/** replies the user asked to write again */
+	regenerated: number;
+	/** replies cut by the output limit of the provider */
+	truncated: number;
+	/** output tokens of the replies */
+	tokens: number;
+	/** replies rated by their user, see `message/{id}/feedback` */
+	up: number;
+	down: number;
+}
+
+export interface ModelVariantListRespItem {
+	id: number;
+	name: string;
+	/** None for the built-in prompt */
+	template?: string;
+	weight: number;
+	/** unix seconds */
+	created_at: number;
+	stats: ModelVariantStats;
+}
+
+export interface ModelVariantListResp {
+	list: ModelVariantListRespItem[];
+}
+
+/** Replace a variant, chats already sampled into it keep it */
+export interface ModelVariantWriteReq {
+	id: number;
+	name: string;
+	template?: string;
+	/** 0 stop sampling new chats into it */
+	weight: number;
+}
+
+export interface ModelVariantWriteResp {
+	wrote: boolean;
+}
+
+export interface ModelWriteReq {
+	id: number;
+	config: string;
+}
+
+export interface ModelWriteResp {
+	display_name: string;
+	wrote: boolean;
+}
+
+export interface PersonaAssignReq {
+	chat_id: number;
+	/** None to go back to the built-in prompt */
+	persona_id?: number;
+}
+
+export interface PersonaAssignResp {
+	/** Model of the chat, the one of the persona if it has one */
+	model_id: number;
+}
+
+export interface PersonaCreateReq {
+	name: string;
+	/**
+	 * Follow the built-in prompt, a template with its variables, e.g.
+	 * `{{ user.name }}`
+	 */
+	prompt: string;
+	/** Model chats created with the persona start on */
+	model_id?: number;
+	/** Tools offered in its chats, None for every tool of the mode */
+	tools?: string[];
+}
+
+export interface PersonaCreateResp {
+	id: number;
+}
+
+export interface PersonaDeleteReq {
+	id: number;
+}
+
+export interface PersonaDeleteResp {
+	/** false if the persona does not exist */
+	deleted: boolean;
+}
+
+export interface PersonaListReq {}
+
+export interface PersonaListRespItem {
+	id: number;
+	name: string;
+	prompt: string;
+	/** None once the model is deleted */
+	model_id?: number;
+	/** None for every tool of the mode */
+	tools?: string[];
+}
+
+export interface PersonaListResp {
+	/** Oldest first */
+	list: PersonaListRespItem[];
+	/** Tools a persona can keep, those of the agent mode */
+	available_tools: string[];
+}
+
+export interface PersonaWriteReq {
+	id: number;
+	name?: string;
+	prompt?: string;
+	model_id?: number;
+	/** An empty list is no tool, see `tools_all` for every tool of the mode */
+	tools?: string[];
+	/** Offer every tool of the mode again */
+	tools_all?: boolean;
+}
+
+export interface PersonaWriteResp {}
+
+export interface PolicyHistoryReq {
+	limit?: number;
+}
+
+export interface PolicyHistoryRespList {
+	version: number;
+	content: string;
+	/** missing if the author was deleted */
+	author?: string;
+	/** unix timestamp in seconds */
+	created_at: number;
+}
+
+export interface PolicyHistoryResp {
+	/** newest first */
+	list: PolicyHistoryRespList[];
+}
+
+export interface PolicyReadReq {}
+
+export interface PolicyReadResp {
+	/** missing if no policy was ever written */
+	version?: number;
+	content: string;
+}
+
+export interface PolicyWriteReq {
+	/** empty to disable the policy */
+	content: string;
+}
+
+export interface PolicyWriteResp {
+	version: number;
+}
+
+export interface PreferencesReadResp {
+	preference: UserPreference;
+	/** Give it back when writing, see `PreferencesWriteReq` */
+	version: number;
+}
+
+export interface PreferencesWriteReq {
+	/** `version` of `PreferencesReadResp` the change is based on */
+	version: number;
+	/** Keys to set, the others are kept */
+	set?: UserPreference;
+	/** Keys to remove, back to their default */
+	unset?: string[];
+}
+
+export interface PreferencesWriteResp {
+	preference: UserPreference;
+	version: number;
+}
+
+export interface PricingHistoryReq {
+	model_id: string;
+	limit?: number;
+}
+
+export interface PricingHistoryRespList {
+	/** USD per token */
+	prompt: number;
+	completion: number;
+	/** unix timestamp in seconds, effective until the next entry */
+	effective_at: number;
+}
+
+export interface PricingHistoryResp {
+	/** newest first */
+	list: PricingHistoryRespList[];
+}
+
+export interface PricingListReq {
+	/** only these upstream model ids, every synced model if missing */
+	model_ids?: string[];
+}
+
+export interface PricingListRespList {
+	model_id: string;
+	/** USD per token */
+	prompt: number;
+	completion: number;
+	/** unix timestamp in seconds */
+	effective_at: number;
+}
+
+export interface PricingListResp {
+	list: PricingListRespList[];
+}
+
+export interface PromptDeleteReq {
+	id: number;
+}
+
+export interface PromptDeleteResp {
+	deleted: boolean;
+}
+
+export interface PromptListReq {}
+
+export interface PromptVariable {
+	/** path in the template, e.g. `user.name` */
+	name: string;
+	kind: string;
+	description: string;
+}
+
+export interface PromptListRespItem {
+	id: number;
+	name: string;
+	/** None for every locale */
+	locale?: string;
+	/** None for every workspace */
+	workspace_id?: number;
+	/** missing if the author was deleted */
+	author?: string;
+	/** unix seconds */
+	updated_at: number;
+}
+
+export interface PromptListResp {
+	/** Built-in prompts a template can replace */
+	names: string[];
+	locales: string[];
+	/** Variables a template can use */
+	variables: PromptVariable[];
+	list: PromptListRespItem[];
+}
+
+export interface PromptPinReq {
+	/** id of the template */
+	id: number;
+	/** One of its versions, e.g. an earlier one to roll back to */
+	version_id: number;
+}
+
+export interface PromptPinResp {}
+
+export interface PromptPreviewReq {
+	/** decide the tools whose hints are listed */
+	name: string;
+	content: string;
+}
+
+export interface PromptPreviewResp {
+	rendered: string;
+}
+
+export interface PromptReadReq {
+	name: string;
+	/** None for every locale */
+	locale?: string;
+	/** None for every workspace */
+	workspace_id?: number;
+}
+
+export interface PromptReadRespTemplate {
+	id: number;
+	/** Content of the version in use */
+	content: string;
+	version_id?: number;
+}
+
+export interface PromptReadResp {
+	/** The prompt shipped with the server, in the locale or the default one */
+	builtin: string;
+	/** missing if no template replace it */
+	template?: PromptReadRespTemplate;
+}
+
+export interface PromptVersionsReq {
+	/** id of the template */
+	id: number;
+}
+
+export interface PromptVersionsRespItem {
+	id: number;
+	content: string;
+	/** missing if the author was deleted */
+	author?: string;
+	/** unix seconds */
+	created_at: number;
+	/** Whether the template uses this version */
+	active: boolean;
+}
+
+export interface PromptVersionsResp {
+	/** Newest first */
+	list: PromptVersionsRespItem[];
+}
+
+export interface PromptWriteReq {
+	name: string;
+	/** None for every locale without a template of its own */
+	locale?: string;
+	/** minijinja template, see `variables` of `prompt/list` */
+	content: string;
+	/** None for every workspace without a template of its own */
+	workspace_id?: number;
+}
+
+export interface PromptWriteResp {
+	id: number;
+	/** The new version, now in use */
+	version_id: number;
+}
+
+export interface PushKeyReq {}
+
+export interface PushKeyResp {
+	/** `applicationServerKey` of `PushManager.subscribe`, in base64url */
+	public_key: string;
+}
+
+/** The fields of `PushSubscription.toJSON()`, flattened */
+export interface PushSubscribeReq {
+	endpoint: string;
+	/** `keys.p256dh`, base64url */
+	p256dh: string;
+	/** `keys.auth`, base64url */
+	auth: string;
+}
+
+export interface PushSubscribeResp {
+	id: number;
+}
+
+export interface PushUnsubscribeReq {
+	endpoint: string;
+}
+
+export interface PushUnsubscribeResp {
+	/** false if the browser was not subscribed by the user */
+	deleted: boolean;
+}
+
+export interface RateLimitPolicy {
+	/** 0 turns the limit off */
+	per_minute: number;
+	burst: number;
+}
+
+export interface RefreshReq {
+	refresh_token: string;
+}
+
+export interface RefreshResp {
+	token: string;
+	exp: string;
+	refresh_token: string;
+}
+
+export interface ResetReq {
+	/** From the mailed link */
+	token: string;
+	password: string;
+}
+
+export interface ResetResp {
+	/** To sign in with the new password */
+	username: string;
+}
+
+export interface RuntimeConfig {
+	/** Model new chats start with until the user picks one */
+	default_model_id?: number;
+	/** Requests per user or IP, see `middlewares::rate_limit` */
+	rate_limit: RateLimitPolicy;
+	/** Override `API_BASE` env */
+	api_base?: string;
+	/** Override `API_KEY` env, never sent back */
+	api_key?: string;
+	/**
+	 * Override `GOOGLE_MAP_API_KEY` env of the nearby place tool, never sent
+	 * back
+	 */
+	google_map_api_key?: string;
+	/** Screening of messages and replies, see `moderation` */
+	moderation: ModerationPolicy;
+	/** Limits of the tool loop of replies, see `routes::message::budget` */
+	agent_loop: AgentLoopPolicy;
+	/**
+	 * Deliver webhooks to addresses of private networks, e.g. Home Assistant;
+	 * loopback and link-local ones never are, see `webhook`
+	 */
+	webhook_private_networks: boolean;
+}
+
+export interface SearchTermsReadReq {}
+
+export interface SearchTermsReadResp {
+	/** Messages containing one of them rank higher, ignoring case */
+	boost: string[];
+	/**
+	 * Removed from messages and tool results before they are indexed, exact
+	 * text such as a mail signature
+	 */
+	ignore: string[];
+}
+
+export interface SearchTermsWriteReq {
+	/** Replace the boosted terms, see `SearchTermsReadResp` */
+	boost: string[];
+	/** Replace the ignored terms */
+	ignore: string[];
+}
+
+export interface SearchTermsWriteResp {}
+
+export interface RoutingReadReq {}
+
+export interface RoutingRule {
+	condition: RoutingCondition;
+	/** Model a matching message is answered by */
+	model_id: number;
+}
+
+export interface RoutingReadResp {
+	/** Tried in order, the first that matches is used */
+	rules: RoutingRule[];
+}
+
+export interface RoutingWriteReq {
+	/** Replace the rules, see `RoutingReadResp` */
+	rules: RoutingRule[];
+}
+
+export interface RoutingWriteResp {}
+
+export interface ScheduleCreateReq {
+	name: string;
+	prompt: string;
+	cron: string;
+	/** minutes ahead of UTC the expression is read in */
+	utc_offset?: number;
+	model_id: number;
+}
+
+export interface ScheduleCreateResp {
+	id: number;
+	/** unix seconds */
+	next_run_at: number;
+}
+
+export interface ScheduleDeleteReq {
+	id: number;
+}
+
+export interface ScheduleDeleteResp {
+	/** false if the task does not exist */
+	deleted: boolean;
+}
+
+export interface ScheduleListReq {}
+
+export interface ScheduleListRespItem {
+	id: number;
+	name: string;
+	prompt: string;
+	cron: string;
+	utc_offset: number;
+	model_id: number;
+	/** None until the first run */
+	chat_id?: number;
+	enabled: boolean;
+	next_run_at?: number;
+	last_run_at?: number;
+	/** Why the last run could not start */
+	last_error?: string;
+}
+
+export interface ScheduleListResp {
+	list: ScheduleListRespItem[];
+}
+
+export interface ScheduleRunReq {
+	id: number;
+}
+
+export interface ScheduleRunResp {
+	/** Chat the prompt was sent in, None if the run could not start */
+	chat_id?: number;
+	/** Why the run could not start */
+	error?: string;
+}
+
+export interface ScheduleWriteReq {
+	id: number;
+	name?: string;
+	prompt?: string;
+	cron?: string;
+	utc_offset?: number;
+	model_id?: number;
+	/** Enabling a task schedule its next run from now */
+	enabled?: boolean;
+}
+
+export interface ScheduleWriteResp {
+	/** unix seconds, None while disabled */
+	next_run_at?: number;
+}
+
+export interface SessionListReq {}
+
+export interface SessionListRespItem {
+	id: number;
+	/** Browser and OS, e.g. "Firefox on Linux" */
+	label?: string;
+	ip?: string;
+	created_at: number;
+	/** Updated when the device refresh its token, about every 15 minutes */
+	last_seen_at?: number;
+	/** The session of this request */
+	current: boolean;
+}
+
+export interface SessionListResp {
+	list: SessionListRespItem[];
+}
+
+export interface SessionRevokeReq {
+	id: number;
+}
+
+export interface SessionRevokeResp {
+	/** false if the session does not exist or belong to someone else */
+	revoked: boolean;
+}
+
+export interface SettingReadReq {}
+
+export interface SettingReadResp {
+	tools_disabled: boolean;
+	disabled_sources: ToolSource[];
+	retention: RetentionPolicy;
+	/** Days of `RETENTION_DAYS`, what the default policy keep */
+	retention_default_days?: number;
+	/**
+	 * Days idle chats are kept under the policy, none for forever; users may
+	 * choose fewer for theirs
+	 */
+	retention_days?: number;
+}
+
+export interface SettingWriteReq {
+	/** Stop advertising tools to every model, for incident response */
+	tools_disabled?: boolean;
+	/** Stop advertising the tools of these sources, the others stay */
+	disabled_sources?: ToolSource[];
+	/** Owners of idle chats are mailed before they are deleted */
+	retention?: RetentionPolicy;
+}
+
+export interface SettingWriteResp {
+	wrote: boolean;
+}
+
+/** Why generations are paused */
+export interface SpendPause {
+	/** unix seconds */
+	at: number;
+	/** USD spent in the hour that tripped the guard, when it did */
+	hour_cost: number;
+	/** USD per hour over the trailing hours */
+	average: number;
+}
+
+/** Limits of one period, None for no limit */
+export interface QuotaLimit {
+	messages?: number;
+	tokens?: number;
+	tool_calls?: number;
+}
+
+export interface QuotaLimits {
+	daily: QuotaLimit;
+	monthly: QuotaLimit;
+}
+
+/** Counts of one period */
+export interface QuotaUsed {
+	messages: number;
+	tokens: number;
+	tool_calls: number;
+}
+
+export interface QuotaReadReq {
+	user_id: number;
+}
+
+export interface QuotaReadResp {
+	/** `QUOTA_DAILY_*` and `QUOTA_MONTHLY_*` */
+	defaults: QuotaLimits;
+	/** Limits set for the user, None follow the defaults */
+	limits: QuotaLimits;
+	daily: QuotaUsed;
+	monthly: QuotaUsed;
+}
+
+export interface QuotaWriteReq {
+	user_id: number;
+	/** Replace the limits set for the user, None follow the defaults */
+	limits: QuotaLimits;
+}
+
+export interface QuotaWriteResp {}
+
+export interface SpendCapReadReq {}
+
+export interface SpendCapReadRespItem {
+	target: SpendCapTarget;
+	/** USD per UTC month */
+	monthly: number;
+	/** USD spent in the current month */
+	spent: number;
+}
+
+export interface SpendCapReadResp {
+	/** `SPEND_CAP_MONTHLY`, the cap of users without their own */
+	default?: number;
+	/** Caps set by admins */
+	list: SpendCapReadRespItem[];
+}
+
+export interface SpendCapWriteReq {
+	target: SpendCapTarget;
+	/** Replace the cap, None removes it and a user follows the default */
+	monthly?: number;
+}
+
+export interface SpendCapWriteResp {}
+
+export interface SpendReadReq {}
+
+export interface SpendReadResp {
+	/** Set while generations of users other than admins are paused */
+	paused?: SpendPause;
+	/** `SPEND_GUARD_MULTIPLE`, the guard is off without it */
+	multiple?: number;
+	/** USD spent on the upstream in the current hour */
+	hour_cost: number;
+	/** USD per hour over the trailing hours */
+	average: number;
+}
+
+export interface SpendResumeReq {}
+
+export interface SpendResumeResp {
+	/** false if generations were not paused */
+	resumed: boolean;
+}
+
+export interface SseReq {
+	/**
+	 * chat to follow, every chat has its own stream so other chats of the
+	 * user are never delivered here
+	 */
+	id: number;
+}
+
+export interface SseRespAttachment {
+	message_id: number;
+	/** download with `/api/file/{id}` */
+	file_id: number;
+	name: string;
+	content_type: string;
+}
+
+export interface SseRespChatStatus {
+	pinned: boolean;
+	archived_at?: number;
+}
+
+export interface SseRespChatTitle {
+	title: string;
+}
+
+export enum SseRespEndKind {
+	Complete = 'complete',
+	Halt = 'halt',
+	Error = 'error',
+	Truncated = 'truncated'
+}
+
+export interface SseRespChunkEnd {
+	id: number;
+	kind: SseRespEndKind;
+}
+
+export interface SseRespContextWarning {
+	prompt_tokens: number;
+	context_length: number;
+}
+
+export interface SseRespLastMessage {
+	id: number;
+	version: number;
+}
+
+export interface SseRespMessageEnd {
+	id: number;
+	kind: SseRespEndKind;
+}
+
+export interface SseRespMeta {
+	id: number;
+	tokens_per_sec: number;
+	/** time to first token */
+	ttft_ms: number;
+	model: string;
+	finish_reason: string;
 }
 
-export enum MessagePaginateRespRole {
-	User = 'user',
-	Assistant = 'assistant'
+export enum SseRespPlanStatus {
+	Pending = 'pending',
+	Running = 'running',
+	Done = 'done',
+	Failed = 'failed',
+	Skipped = 'skipped'
 }
 
-export type MessagePaginateRespChunkKind =
-	| { t: 'text'; c: MessagePaginateRespChunkKindText }
-	| { t: 'reasoning'; c: MessagePaginateRespChunkKindReasoning }
-	| { t: 'tool_call'; c: MessagePaginateRespChunkKindToolCall };
+export interface SseRespPlanStep {
+	name: string;
+	status: SseRespPlanStatus;
+}
 
-export interface MessagePaginateRespChunk {
-	id: number;
-	kind: MessagePaginateRespChunkKind;
+export interface SseRespPlan {
+	steps: SseRespPlanStep[];
+	/** index of the step being updated */
+	current: number;
 }
 
-export interface MessagePaginateRespList {
-	id: number;
-	role: MessagePaginateRespRole;
-	chunks: MessagePaginateRespChunk[];
+export interface SseRespProgress {
+	steps: number;
+	max_steps: number;
+	cost: number;
+	elapsed_ms: number;
 }
 
-export interface MessagePaginateResp {
-	list: MessagePaginateRespList[];
+export interface SseRespDelta {
+	content: string;
 }
 
-export interface MessagePaginateRespChunkKindReasoning {
-	context: string;
+export interface SseRespToolCall {
+	name: string;
+	args: string;
 }
 
-export interface MessagePaginateRespChunkKindText {
-	context: string;
+export interface SseRespToolCallDelta {
+	name: string;
+	/** append to the args received so far */
+	args: string;
 }
 
-export interface MessagePaginateRespChunkKindToolCall {
+export interface SseRespToolInput {
+	call_id: string;
+	name: string;
+	question: string;
+	/** JSON schema of the answer */
+	schema: string;
+}
+
+export interface SseRespToolResult {
+	chunk_id: number;
 	name: string;
 	args: string;
-	context: string;
+	content: string;
 }
 
-export interface MessageWriteReq {
-	/** message id */
-	id: number;
-	text: string;
+export interface SseRespUserMessage {
+	message_id: number;
+	chunk_id: number;
+	/** member who sent it, see `chat/{id}/member` */
+	author_id: number;
+	content: string;
 }
 
-export enum OcrEngine {
-	Native = 'Native',
-	Text = 'Text',
-	Mistral = 'Mistral',
-	Disabled = 'Disabled'
+export interface OpenApiImportReq {
+	url: string;
+	/** Ids from the preview */
+	operations: string[];
+	/**
+	 * Environment variable holding the API credential, filled into the
+	 * security scheme of the operations
+	 */
+	credential?: string;
 }
 
-export interface ModelCapability {
-	image?: boolean;
-	audio?: boolean;
-	ocr?: OcrEngine;
+export interface OpenApiImportResp {
+	/** Ids of the tools added, offered in agent mode */
+	tools: string[];
 }
 
-export interface ModelCheckReq {
-	config: string;
+export interface OpenApiOperation {
+	/** Name of the tool it would become */
+	id: string;
+	method: string;
+	path: string;
+	summary: string;
+	/** A tool with the same name exists, importing it would fail */
+	exists: boolean;
 }
 
-export interface ModelCheckResp {
-	reason?: string;
+export interface OpenApiPreviewReq {
+	/** Url of a JSON OpenAPI 3 document */
+	url: string;
 }
 
-export interface ModelParameter {
-	temperature?: number;
-	repeat_penalty?: number;
-	top_k?: number;
-	top_p?: number;
+export interface OpenApiPreviewResp {
+	title: string;
+	operations: OpenApiOperation[];
 }
 
-export interface ModelConfig {
-	display_name: string;
-	model_id: string;
-	capability?: ModelCapability;
-	parameter?: ModelParameter;
+export enum SyncEntityKind {
+	Chat = 'chat',
+	Message = 'message'
 }
 
-export interface ModelCreateReq {
-	config: string;
+export interface SyncReadReq {
+	/** `cursor` of the previous sync, default to everything */
+	since?: number;
 }
 
-export interface ModelCreateResp {
+export interface SyncReadRespChat {
 	id: number;
-	display_name: string;
+	model_id: number;
+	title?: string;
+	reproducible: boolean;
+	/** last message of the active branch, None for the latest message */
+	head_id?: number;
+	pinned: boolean;
+	archived_at?: number;
 }
 
-export interface ModelDeleteReq {
+export interface SyncReadRespMessage {
+	chat_id: number;
+	message: MessagePaginateRespList;
+}
+
+export interface SyncReadRespDeleted {
+	entity: SyncEntityKind;
 	id: number;
 }
 
-export interface ModelDeleteResp {
-	deleted: boolean;
+export interface SyncReadResp {
+	/** `since` of the next sync and `base` of writes made on top of this one */
+	cursor: number;
+	/** Changes past `cursor` were left out, sync again right away */
+	more: boolean;
+	/** Created or changed since `since` */
+	chats: SyncReadRespChat[];
+	messages: SyncReadRespMessage[];
+	/** Only when changed */
+	preference?: UserPreference;
+	/** Tombstones, messages of a deleted chat are not listed */
+	deleted: SyncReadRespDeleted[];
 }
 
-export interface ModelList {
+export interface SyncWriteReqChatTitle {
 	id: number;
-	display_name: string;
+	title: string;
 }
 
-export interface ModelListReq {}
+export interface SyncWriteReqChatDelete {
+	id: number;
+}
 
-export interface ModelListResp {
-	list: ModelList[];
+export interface SyncWriteReq {
+	/** `cursor` of the last sync before the client went offline */
+	base: number;
+	/** Applied in order */
+	ops: SyncWriteReqOp[];
 }
 
-export interface ModelReadReq {
-	id: number;
+export enum SyncWriteRespResult {
+	Applied = 'applied',
+	/**
+	 * Changed on the server after `base`, the server copy is kept and
+	 * arrives with the next sync
+	 */
+	Conflict = 'conflict',
+	NotFound = 'not_found',
+	/** The merged value is not valid, nothing is written */
+	Rejected = 'rejected'
 }
 
-export interface ModelReadResp {
-	raw: string;
+export interface SyncWriteResp {
+	/** One per op, in order */
+	results: SyncWriteRespResult[];
+	/** Undo every chat deleted by the batch, absent if none was deleted */
+	undo_token?: string;
 }
 
-export interface ModelWriteReq {
-	id: number;
-	config: string;
+export interface SystemReq {}
+
+export interface SystemResp {
+	/** Resident memory of this process in KiB */
+	rss_kb: number;
+	/** Memory limit of the container in KiB, None outside a cgroup */
+	memory_limit_kb?: number;
+	/** None on platforms other than linux */
+	open_files?: number;
+	open_files_limit?: number;
+	/** Tasks alive in the tokio runtime, including the reaper and the syncs */
+	tasks: number;
+	/** Size of the database in KiB, including free pages */
+	db_size_kb: number;
+	uptime_secs: number;
+	/** Resources close to their limit, in english */
+	warnings: string[];
 }
 
-export interface ModelWriteResp {
-	display_name: string;
-	wrote: boolean;
+export interface TagsReq {}
+
+/** Where a tool comes from, each source can be disabled on its own */
+export enum ToolSource {
+	/** Compiled in, their ids are their bare names */
+	Builtin = 'builtin',
+	/** From `TOOLS_DIR` or imported from OpenAPI */
+	Declared = 'declared'
 }
 
-export interface RenewReq {
-	token: string;
+export interface TotpDisableReq {
+	/** a TOTP or recovery code */
+	code: string;
 }
 
-export interface RenewResp {
-	token: string;
-	exp: string;
+export interface TotpDisableResp {
+	/** false if TOTP was not enabled */
+	disabled: boolean;
 }
 
-export interface Resp {}
+export interface TotpEnableReq {
+	/** from the authenticator app, proving the secret was saved */
+	code: string;
+}
 
-export interface SseReq {
-	id: number;
+export interface TotpEnableResp {
+	/** each works once in place of a code, only shown here */
+	recovery_codes: string[];
 }
 
-export enum SseRespEndKind {
-	Complete = 'complete',
-	Halt = 'halt',
-	Error = 'error'
+export interface TotpSetupReq {}
+
+export interface TotpSetupResp {
+	/** `otpauth://` uri for authenticator apps, usually shown as a QR code */
+	uri: string;
+	/** base32, for entering by hand */
+	secret: string;
 }
 
-export interface SseRespChunkEnd {
-	id: number;
-	kind: SseRespEndKind;
+export interface TotpStatusReq {}
+
+export interface TotpStatusResp {
+	enabled: boolean;
 }
 
-export interface SseRespLastMessage {
-	id: number;
-	version: number;
+export interface UndoResp {
+	/** Chats the restored rows belong to, refetch them */
+	chat_ids: number[];
 }
 
-export interface SseRespMessageEnd {
-	id: number;
-	kind: SseRespEndKind;
+export enum UserActivityReqGranularity {
+	/** The last year */
+	Day = 'day',
+	/** The last 14 days */
+	Hour = 'hour'
 }
 
-export interface SseRespToken {
-	content: string;
+export interface UserActivityReq {
+	/** default to `day` */
+	granularity?: UserActivityReqGranularity;
+	/** Minutes ahead of UTC buckets start at, e.g. 480 in Taipei, default to 0 */
+	offset?: number;
 }
 
-export interface SseRespToolCall {
-	name: string;
-	args: string;
+export interface UserActivityResp {
+	/** Start of the first bucket, unix seconds */
+	from: number;
+	/** Seconds a bucket covers */
+	width: number;
+	/** Oldest first, buckets without messages are left out */
+	buckets: UserActivityRespBucket[];
 }
 
-export interface SseRespToolCallEnd {
-	chunk_id: number;
-	name: string;
-	args: string;
-	content: string;
+export interface UserActivityRespBucket {
+	/** unix seconds */
+	start: number;
+	/** Sent by the user */
+	messages: number;
+	/** Output tokens of the replies, 0 for replies before tokens were recorded */
+	tokens: number;
 }
 
-export interface SseRespUserMessage {
-	message_id: number;
-	chunk_id: number;
-	content: string;
+export interface UserCalendarResp {
+	/** Calendar apps subscribe to `/calendar/{token}` without logging in */
+	token: string;
 }
 
-export interface SseRespUserTitle {
-	title: string;
+export interface UserCalendarRevokeResp {
+	/** false if the user had no feed */
+	revoked: boolean;
 }
 
 export interface UserCreateReq {
 	username: string;
 	password: string;
+	/** Default to [`UserRole::User`] */
+	role?: UserRole;
+	/** Mailed a verification link, the user cannot send messages until it is opened */
+	email?: string;
+	/** Workspace the user joins, default to the default one */
+	workspace_id?: number;
 }
 
 export interface UserCreateResp {
@@ -355,6 +2697,7 @@ export interface UserDeleteResp {
 export interface UserList {
 	id: number;
 	name: string;
+	role: UserRole;
 }
 
 export interface UserListReq {}
@@ -367,17 +2710,120 @@ export interface UserPreference {
 	theme?: string;
 	locale?: string;
 	submit_on_enter?: string;
+	/** Voice replies are read aloud with, one of the TTS provider's */
+	voice?: string;
+	/** Speed of the voice, e.g. `1.25` */
+	voice_speed?: string;
+	/**
+	 * Days idle chats are kept, e.g. `30`, only when sooner than the policy
+	 * of the instance; empty to follow it
+	 */
+	retention_days?: string;
+	/** IANA name of the time zone of the user, e.g. `Asia/Taipei` */
+	timezone?: string;
+	/** Minutes the time zone is ahead of UTC now, as the browser reports it */
+	utc_offset?: number;
+	/** Model new chats start with, before the default of the instance */
+	default_model_id?: number;
+	/** Push a notification when a reply ends while away, true when unset */
+	notify_replies?: boolean;
+	/** Push a notification when a scheduled task runs, true when unset */
+	notify_schedules?: boolean;
+}
+
+export interface UserPurgeReq {
+	/** From `/api/user/purge_token` */
+	token: string;
+}
+
+export interface UserPurgeResp {
+	/** Unix seconds, until then signing in cancel the deletion */
+	purge_at: number;
+}
+
+export interface UserPurgeTokenReq {
+	/** Not needed by users who only sign in with a provider */
+	password?: string;
+}
+
+export interface UserPurgeTokenResp {
+	/** Give it to `DELETE /api/user` within `expires_in_secs` */
+	token: string;
+	expires_in_secs: number;
+	/** How long the account is kept once deleted, signing in cancel the deletion */
+	grace_secs: number;
 }
 
 export interface UserReadReq {
-	/** If omit will use the current user instead */
+	/** If omit will use the current user instead, only admins can read others */
 	user_id?: number;
 }
 
+export interface UserUsageRespPeriod {
+	used: QuotaUsed;
+	/** None for no limit */
+	limit: QuotaLimit;
+	/** Left before the limit, None for no limit */
+	remaining: QuotaLimit;
+	/** unix seconds */
+	resets_at: number;
+}
+
+export interface UserUsageRespSpend {
+	spent: number;
+	/** None for no cap, models can still have their own */
+	cap?: number;
+}
+
+export interface UserTakeoutResp {
+	/** false if a takeout of the user is already being built */
+	queued: boolean;
+}
+
+export interface UserUsageResp {
+	/** Admins are never limited, their usage is still counted */
+	exempt: boolean;
+	/** The current UTC day */
+	daily: UserUsageRespPeriod;
+	/** The current UTC month */
+	monthly: UserUsageRespPeriod;
+	/** USD spent this month against the spending cap */
+	spend: UserUsageRespSpend;
+}
+
 export interface UserReadResp {
 	user_id: number;
 	username: string;
 	preference: UserPreference;
+	email?: string;
+	/** False until the link mailed to `email` is opened, messages cannot be sent */
+	email_verified: boolean;
+	role: UserRole;
+}
+
+/** Admins manage models, settings and other users */
+export enum UserRole {
+	User = 'user',
+	Admin = 'admin'
+}
+
+export interface UserStatsReq {}
+
+export interface UserStatsResp {
+	chats: number;
+	user_messages: number;
+	assistant_messages: number;
+	/** Completions of the tool loops, several per assistant message with tools */
+	completions: number;
+	/** Completions cut by the upstream timeouts or the output limit */
+	truncations: number;
+	/** Most used first */
+	models: UserStatsRespModel[];
+}
+
+export interface UserStatsRespModel {
+	model_id: string;
+	messages: number;
 }
 
 export interface UserUpdateReq {
@@ -385,12 +2831,245 @@ export interface UserUpdateReq {
 	user_id?: number;
 	preference?: UserPreference;
 	password?: string;
+	/**
+	 * Where password reset links are mailed, empty to remove it
+	 *
+	 * A new address must be verified with a mailed link before sending messages
+	 */
+	email?: string;
 }
 
 export interface UserUpdateResp {
 	user_id: number;
 }
 
+export interface VerifyReq {
+	/** From the mailed link */
+	token: string;
+}
+
+export interface VerifyResp {
+	username: string;
+	email: string;
+}
+
+export interface WebhookCreateReq {
+	/**
+	 * http or https, never to loopback nor link-local addresses; those of
+	 * private networks, e.g. Home Assistant, only if admins allow them
+	 */
+	url: string;
+	events: WebhookEvent[];
+}
+
+export interface WebhookCreateResp {
+	id: number;
+	/** Only shown here, key of the HMAC-SHA256 in `X-Llumen-Signature` */
+	secret: string;
+}
+
+export interface WebhookDeleteReq {
+	id: number;
+}
+
+export interface WebhookDeleteResp {
+	/** false if the webhook does not exist or belong to someone else */
+	deleted: boolean;
+}
+
+/** What a webhook is called on */
+export enum WebhookEvent {
+	/** A reply of the assistant ended, in a chat of the user */
+	MessageCompleted = 'message_completed',
+	/** A scheduled task of the user sent its prompt, or could not */
+	ScheduleRun = 'schedule_run'
+}
+
+export interface WebhookListReq {}
+
+export interface WebhookListResp {
+	list: WebhookListRespItem[];
+}
+
+export interface WebhookListRespItem {
+	id: number;
+	url: string;
+	events: WebhookEvent[];
+	created_at: number;
+	/** Latest attempt to deliver an event, successful or not */
+	last_delivery_at?: number;
+	/** Why the latest attempt failed, it is retried with a backoff */
+	last_error?: string;
+}
+
+export interface WorkspaceListReq {}
+
+export interface WorkspaceListResp {
+	list: WorkspaceListRespItem[];
+	/** The workspace of the token */
+	current_id: number;
+}
+
+export interface WorkspaceListRespItem {
+	id: number;
+	name: string;
+}
+
+export interface WorkspaceSelectReq {
+	id: number;
+}
+
+export interface WorkspaceSelectResp {
+	/**
+	 * Access token of the workspace, the one of the request still works in
+	 * the previous workspace until it expires
+	 */
+	token: string;
+	exp: string;
+}
+
+export interface WsReqAuth {
+	token: string;
+}
+
+export interface WsReqSubscribe {
+	chat_id: number;
+	/** same as the `Last-Event-ID` header of `/api/chat/sse` */
+	last_event_id?: string;
+}
+
+export type SseResp =
+	| { type: 'last_message'; data: SseRespLastMessage }
+	| { type: 'token_delta'; data: SseRespDelta }
+	| { type: 'reasoning_delta'; data: SseRespDelta }
+	| { type: 'chunk_end'; data: SseRespChunkEnd }
+	/** part of the arguments of a tool call still being written by the model */
+	| { type: 'tool_call_delta'; data: SseRespToolCallDelta }
+	| { type: 'tool_call'; data: SseRespToolCall }
+	/** the tool call waits for the user, answer with `/api/chat/{id}/tool/{call_id}/input` */
+	| { type: 'tool_input'; data: SseRespToolInput }
+	| { type: 'tool_result'; data: SseRespToolResult }
+	/** a file a tool made was attached to the message, e.g. a generated image */
+	| { type: 'attachment'; data: SseRespAttachment }
+	| { type: 'message_end'; data: SseRespMessageEnd }
+	| { type: 'user_message'; data: SseRespUserMessage }
+	| { type: 'chat_title'; data: SseRespChatTitle }
+	/** the chat was pinned or archived, from this tab or another */
+	| { type: 'chat_status'; data: SseRespChatStatus }
+	| { type: 'plan'; data: SseRespPlan }
+	| { type: 'progress'; data: SseRespProgress }
+	| { type: 'meta'; data: SseRespMeta }
+	/** the chat keeps filling the context of the model, its beginning may get lost */
+	| { type: 'context_warning'; data: SseRespContextWarning }
+	/** the connection fell behind and is closed, refetch and subscribe again */
+	| { type: 'lagged'; data?: undefined }
+	| { type: 'error'; data: Error };
+
+/**
+ * Every event sent on a chat stream, over SSE or WebSocket
+ *
+ * Bump `SSE_VERSION` when an existing kind changes shape, adding a kind is not breaking
+ */
+export type SseEvent = { v: number } & SseResp;
+
+export interface WsRespEvent {
+	chat_id: number;
+	id?: string;
+	event: SseEvent;
+}
+
+export interface NotificationScheduleRun {
+	schedule_id: number;
+	name: string;
+	/** None if the run could not start */
+	chat_id?: number;
+	/** Why the run could not start */
+	error?: string;
+}
+
+export interface NotificationSpendCap {
+	/** Display name of the model capped, None for the cap of the user */
+	model?: string;
+	/** USD this month */
+	spent: number;
+	cap: number;
+	/** false while it is only the warning */
+	reached: boolean;
+}
+
+export interface NotificationTakeout {
+	/** Downloaded from `file/{id}`, deleted after `FILE_ORPHAN_SECS` */
+	file_id: number;
+	name: string;
+	/** Bytes */
+	size: number;
+}
+
+export interface NotificationDraftUpdated {
+	chat_id: number;
+	/** Empty once the draft is sent or cleared */
+	content: string;
+	updated_at: number;
+}
+
+export interface NotificationInbox {
+	id: number;
+	source: InboxSource;
+	/** Markdown */
+	content: string;
+	/** Chat the message is about */
+	chat_id?: number;
+	created_at: number;
+}
+
+export interface NotificationListReq {
+	/** Cursor, exclusive, `next` of the previous page */
+	before?: number;
+}
+
+export interface NotificationListRespItem {
+	id: number;
+	source: InboxSource;
+	/** Markdown */
+	content: string;
+	/** Chat the message is about */
+	chat_id?: number;
+	created_at: number;
+	read: boolean;
+}
+
+export interface NotificationListResp {
+	/** Latest first */
+	list: NotificationListRespItem[];
+	/** Unread messages of the whole inbox */
+	unread: number;
+	/** `before` of the next page, None once the inbox is exhausted */
+	next?: number;
+}
+
+export interface NotificationReadReq {
+	/** None to mark the whole inbox */
+	ids?: number[];
+}
+
+export interface NotificationReadResp {
+	/** Unread messages left */
+	unread: number;
+}
+
+/** Notifications of a user outside of any chat */
+export type Notification =
+	/** A scheduled task sent its prompt, the reply streams in the chat */
+	| { type: 'schedule_run'; data: NotificationScheduleRun }
+	/** Spending went past a monthly cap or most of it, see `spend::cap` */
+	| { type: 'spend_cap'; data: NotificationSpendCap }
+	/** The takeout asked for at `user/takeout` is ready to download */
+	| { type: 'takeout'; data: NotificationTakeout }
+	/** A draft was saved or cleared, on this device or another one */
+	| { type: 'draft_updated'; data: NotificationDraftUpdated }
+	/** A message was posted to the inbox, see `inbox` */
+	| { type: 'inbox'; data: NotificationInbox };
+
 export type ChatPaginateReq =
 	| { t: 'limit'; c: ChatPaginateReqLimit }
 	| { t: 'range'; c: ChatPaginateReqRange };
@@ -399,13 +3078,48 @@ export type MessagePaginateReq =
 	| { t: 'limit'; c: MessagePaginateReqLimit }
 	| { t: 'range'; c: MessagePaginateReqRange };
 
-export type SseResp =
-	| { t: 'last_message'; c: SseRespLastMessage }
-	| { t: 'token'; c: SseRespToken }
-	| { t: 'reasoning_token'; c: SseRespToken }
-	| { t: 'chunk_end'; c: SseRespChunkEnd }
-	| { t: 'tool_call'; c: SseRespToolCall }
-	| { t: 'tool_call_end'; c: SseRespToolCallEnd }
-	| { t: 'message_end'; c: SseRespMessageEnd }
-	| { t: 'user_message'; c: SseRespUserMessage }
-	| { t: 'change_title'; c: SseRespUserTitle };
+/** What a cap applies to */
+export type SpendCapTarget =
+	/** Every model the user chats with */
+	| { t: 'user'; c: number }
+	/** Every user chatting with the model */
+	| { t: 'model'; c: number };
+
+/** How long idle chats are kept */
+export type RetentionPolicy =
+	/** Follow `RETENTION_DAYS`, forever if unset */
+	| { t: 'default'; c?: undefined }
+	| { t: 'forever'; c?: undefined }
+	/** Delete chats without new messages for this many days */
+	| { t: 'days'; c: number };
+
+export type SyncWriteReqOp =
+	| { t: 'chat_title'; c: SyncWriteReqChatTitle }
+	| { t: 'chat_delete'; c: SyncWriteReqChatDelete }
+	/** Unset fields are kept */
+	| { t: 'preference'; c: UserPreference };
+
+/** Client message, `auth` must come first */
+export type WsReq =
+	| { t: 'auth'; c: WsReqAuth }
+	/** replace the current subscription */
+	| { t: 'subscribe'; c: WsReqSubscribe }
+	| { t: 'send'; c: MessageCreateReq }
+	| { t: 'halt'; c: ChatHaltReq };
+
+export type WsResp =
+	| { t: 'event'; c: WsRespEvent }
+	| { t: 'created'; c: MessageCreateResp }
+	| { t: 'halted'; c: ChatHaltResp }
+	| { t: 'error'; c: Error };
+
+/** When a routing rule of a user applies to a message */
+export type RoutingCondition =
+	/** The message has a fenced code block or lines of code */
+	| { t: 'code'; c?: undefined }
+	/** The prompt, the history included, is estimated past this many tokens */
+	| { t: 'tokens'; c: number }
+	/** The last reply of the chat called at least this many tools */
+	| { t: 'tool_calls'; c: number }
+	/** The message contains this text, ignoring case */
+	| { t: 'keyword'; c: string };
@@ -8,6 +8,32 @@ import {
 } from './state';
 
 import type {
+	ApiKeyCreateReq,
+	ApiKeyCreateResp,
+	ApiKeyListReq,
+	ApiKeyListResp,
+	ApiKeyRevokeReq,
+	ApiKeyRevokeResp,
+	PushKeyReq,
+	PushKeyResp,
+	PushSubscribeReq,
+	PushSubscribeResp,
+	PushUnsubscribeReq,
+	PushUnsubscribeResp,
+	RoutingReadReq,
+	RoutingReadResp,
+	RoutingWriteReq,
+	RoutingWriteResp,
+	SearchTermsReadReq,
+	SearchTermsReadResp,
+	SearchTermsWriteReq,
+	SearchTermsWriteResp,
+	SessionListReq,
+	SessionListResp,
+	SessionRevokeReq,
+	SessionRevokeResp,
+	UserActivityReqGranularity,
+	UserActivityResp,
 	UserCreateReq,
 	UserCreateResp,
 	UserReadResp,
@@ -15,8 +41,22 @@ import type {
 	UserUpdateReq,
 	UserUpdateResp,
 	UserListResp,
-	UserDeleteReq
+	UserDeleteReq,
+	UserPurgeReq,
+	UserPurgeResp,
+	UserPurgeTokenReq,
+	UserPurgeTokenResp,
+	UserTakeoutResp,
+	UserUsageResp,
+	WebhookCreateReq,
+	WebhookCreateResp,
+	WebhookDeleteReq,
+	WebhookDeleteResp,
+	WebhookListReq,
+	WebhookListResp
 } from './types';
+import { UserRole } from './types';
+import { APIFetch } from './state/errorHandle';
 
 export interface User {
 	username: string;
@@ -39,7 +79,8 @@ export function CreateUser(): CreateMutationResult<UserCreateReq, UserCreateResp
 					if (list != undefined)
 						list.list.unshift({
 							id: data.user_id,
-							name: param.username
+							name: param.username,
+							role: param.role ?? UserRole.User
 						});
 					return list;
 				}
@@ -63,6 +104,14 @@ export function UpdateUser(): CreateMutationResult<UserUpdateReq, UserUpdateResp
 		path: 'user/update',
 		onSuccess(data, param) {
 			if (param.preference) updatePreference(param.preference);
+			if (param.email != undefined)
+				SetQueryData<UserReadResp>({
+					key: ['currentUser'],
+					updater: (data) => {
+						if (data != undefined) data.email = param.email || undefined;
+						return data;
+					}
+				});
 		}
 	});
 }
@@ -81,3 +130,208 @@ export function DeleteUser(): CreateMutationResult<UserDeleteReq, UserReadResp>
 		}
 	});
 }
+
+export function useApiKeys(): QueryResult<ApiKeyListResp> {
+	return CreateQuery<ApiKeyListReq, ApiKeyListResp>({
+		key: ['apiKeys'],
+		path: 'user/keys/list',
+		body: {},
+		staleTime: 0
+	});
+}
+
+export function CreateApiKey(): CreateMutationResult<ApiKeyCreateReq, ApiKeyCreateResp> {
+	return CreateMutation({
+		path: 'user/keys/create',
+		onSuccess(data, param) {
+			SetQueryData<ApiKeyListResp>({
+				key: ['apiKeys'],
+				updater: (x) => {
+					x?.list.unshift({
+						id: data.id,
+						name: param.name,
+						// same length as the server keep
+						prefix: data.key.slice(0, 13),
+						scopes: param.scopes,
+						created_at: Math.floor(Date.now() / 1000)
+					});
+					return x;
+				}
+			});
+		}
+	});
+}
+
+export function RevokeApiKey(): CreateMutationResult<ApiKeyRevokeReq, ApiKeyRevokeResp> {
+	return CreateMutation({
+		path: 'user/keys/revoke',
+		onSuccess(_, param) {
+			SetQueryData<ApiKeyListResp>({
+				key: ['apiKeys'],
+				updater: (x) => {
+					if (x != undefined) x.list = x.list.filter((k) => k.id !== param.id);
+					return x;
+				}
+			});
+		}
+	});
+}
+
+export function useWebhooks(): QueryResult<WebhookListResp> {
+	return CreateQuery<WebhookListReq, WebhookListResp>({
+		key: ['webhooks'],
+		path: 'user/webhooks/list',
+		body: {},
+		staleTime: 0
+	});
+}
+
+export function CreateWebhook(): CreateMutationResult<WebhookCreateReq, WebhookCreateResp> {
+	return CreateMutation({
+		path: 'user/webhooks/create',
+		onSuccess(data, param) {
+			SetQueryData<WebhookListResp>({
+				key: ['webhooks'],
+				updater: (x) => {
+					x?.list.unshift({
+						id: data.id,
+						url: param.url.trim(),
+						events: param.events,
+						created_at: Math.floor(Date.now() / 1000)
+					});
+					return x;
+				}
+			});
+		}
+	});
+}
+
+export function DeleteWebhook(): CreateMutationResult<WebhookDeleteReq, WebhookDeleteResp> {
+	return CreateMutation({
+		path: 'user/webhooks/delete',
+		onSuccess(_, param) {
+			SetQueryData<WebhookListResp>({
+				key: ['webhooks'],
+				updater: (x) => {
+					if (x != undefined) x.list = x.list.filter((k) => k.id !== param.id);
+					return x;
+				}
+			});
+		}
+	});
+}
+
+/** VAPID public key to subscribe the browser with */
+export function fetchPushKey() {
+	return APIFetch<PushKeyResp, PushKeyReq>('user/push/key', {});
+}
+
+/** Receive replies and scheduled runs in this browser while the app is closed */
+export function SubscribePush(): CreateMutationResult<PushSubscribeReq, PushSubscribeResp> {
+	return CreateMutation({ path: 'user/push/subscribe' });
+}
+
+export function UnsubscribePush(): CreateMutationResult<PushUnsubscribeReq, PushUnsubscribeResp> {
+	return CreateMutation({ path: 'user/push/unsubscribe' });
+}
+
+export function useSessions(): QueryResult<SessionListResp> {
+	return CreateQuery<SessionListReq, SessionListResp>({
+		key: ['sessions'],
+		path: 'user/sessions/list',
+		body: {},
+		staleTime: 0
+	});
+}
+
+export function RevokeSession(): CreateMutationResult<SessionRevokeReq, SessionRevokeResp> {
+	return CreateMutation({
+		path: 'user/sessions/revoke',
+		onSuccess(_, param) {
+			SetQueryData<SessionListResp>({
+				key: ['sessions'],
+				updater: (x) => {
+					if (x != undefined) x.list = x.list.filter((s) => s.id !== param.id);
+					return x;
+				}
+			});
+		}
+	});
+}
+
+/** Counts of today and this month against the quotas */
+export function useUsage(): QueryResult<UserUsageResp> {
+	return CreateQuery<null, UserUsageResp>({
+		key: ['usage'],
+		path: 'user/usage',
+		method: 'GET',
+		staleTime: 0
+	});
+}
+
+export function useSearchTerms(): QueryResult<SearchTermsReadResp> {
+	return CreateQuery<SearchTermsReadReq, SearchTermsReadResp>({
+		key: ['searchTerms'],
+		path: 'user/search_terms/read',
+		body: {}
+	});
+}
+
+/** Replace the terms, every message of the user is reindexed */
+export function WriteSearchTerms(): CreateMutationResult<SearchTermsWriteReq, SearchTermsWriteResp> {
+	return CreateMutation({
+		path: 'user/search_terms/write',
+		onSuccess(_, param) {
+			SetQueryData<SearchTermsReadResp>({
+				key: ['searchTerms'],
+				updater: () => param
+			});
+		}
+	});
+}
+
+export function useRouting(): QueryResult<RoutingReadResp> {
+	return CreateQuery<RoutingReadReq, RoutingReadResp>({
+		key: ['routing'],
+		path: 'user/routing/read',
+		body: {}
+	});
+}
+
+/** Replace the rules, used from the next message on */
+export function WriteRouting(): CreateMutationResult<RoutingWriteReq, RoutingWriteResp> {
+	return CreateMutation({
+		path: 'user/routing/write',
+		onSuccess(_, param) {
+			SetQueryData<RoutingReadResp>({
+				key: ['routing'],
+				updater: () => param
+			});
+		}
+	});
+}
+
+/** Check the password before deleting the own account */
+export function PurgeToken(): CreateMutationResult<UserPurgeTokenReq, UserPurgeTokenResp> {
+	return CreateMutation({ path: 'user/purge_token' });
+}
+
+/** Delete the own account, every session is signed out */
+export function PurgeUser(): CreateMutationResult<UserPurgeReq, UserPurgeResp> {
+	return CreateMutation({ path: 'user', method: 'DELETE' });
+}
+
+/** Messages and tokens of the user per day or hour, in the local time zone */
+export function fetchActivity(granularity: UserActivityReqGranularity) {
+	const offset = -new Date().getTimezoneOffset();
+	return APIFetch<UserActivityResp>(
+		`user/activity?granularity=${granularity}&offset=${offset}`,
+		null,
+		'GET'
+	);
+}
+
+/** Queue a zip of everything kept about the user, a `takeout` notification tells when it is ready */
+export function requestTakeout() {
+	return APIFetch<UserTakeoutResp>('user/takeout', null, 'GET');
+}
@@ -0,0 +1,46 @@
+import { token } from '$lib/store';
+import {
+	clearCache,
+	CreateMutation,
+	CreateQuery,
+	type CreateMutationResult,
+	type QueryResult
+} from './state';
+
+import type {
+	WorkspaceListReq,
+	WorkspaceListResp,
+	WorkspaceSelectReq,
+	WorkspaceSelectResp
+} from './types';
+
+export function useWorkspaces(): QueryResult<WorkspaceListResp> {
+	return CreateQuery<WorkspaceListReq, WorkspaceListResp>({
+		key: ['workspaces'],
+		path: 'workspace/list',
+		body: {}
+	});
+}
+
+/** Swap the token for one of the workspace, everything cached belongs to the old one */
+export function SelectWorkspace(): CreateMutationResult<WorkspaceSelectReq, WorkspaceSelectResp> {
+	return CreateMutation({
+		path: 'workspace/select',
+		onSuccess: (data) => {
+			const now = new Date();
+			const expireAt = new Date(data.exp);
+			const renewAt = new Date(now.getTime() + (expireAt.getTime() - now.getTime()) / 2);
+
+			token.update(
+				(x) =>
+					x && {
+						...x,
+						value: data.token,
+						expireAt: expireAt.toString(),
+						renewAt: renewAt.toString()
+					}
+			);
+			clearCache();
+		}
+	});
+}
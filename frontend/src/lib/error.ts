@@ -6,16 +6,19 @@ let latestError = writable<{
 	id: number;
 	error: string;
 	reason?: string;
+	/** What the error means, localized by the server */
+	message?: string;
 } | null>(null);
 
-export function dispatchError(errorMsg: string, reason?: string) {
+export function dispatchError(errorMsg: string, reason?: string, message?: string) {
 	latestError.update((prev) => {
 		const lastId = prev ? prev.id : 0;
 
 		return {
 			id: lastId + 1,
 			error: errorMsg,
-			reason
+			reason,
+			message
 		};
 	});
 }
@@ -1,11 +1,13 @@
 import { derived, get } from 'svelte/store';
-import { APIFetch } from '../api/state/errorHandle';
+import { APIFetch, RawAPIFetch } from '../api/state/errorHandle';
 import type {
 	UserReadReq,
 	UserPreference,
 	UserReadResp,
 	UserUpdateResp,
-	UserUpdateReq
+	UserUpdateReq,
+	PreferencesReadResp,
+	PreferencesWriteReq
 } from '../api/types';
 import { setLocale } from './i18n';
 import { localState, token } from '../store';
@@ -28,7 +30,10 @@ function defaultPreference(): Required<UserPreference> {
 			window.matchMedia && window.matchMedia('(prefers-color-scheme: dark)').matches
 				? 'dark'
 				: 'light',
-		submit_on_enter: 'false'
+		submit_on_enter: 'false',
+		// the default voice of the server
+		voice: '',
+		voice_speed: '1'
 	};
 }
 
@@ -45,6 +50,20 @@ async function initWithRemote() {
 	updatePreference(remote.preference);
 
 	await setRemotePreference(get(preference));
+	await syncTimezone();
+}
+
+/** Tell the server the time zone of this browser, for times in replies and schedules */
+async function syncTimezone() {
+	const timezone = Intl.DateTimeFormat().resolvedOptions().timeZone;
+	const utc_offset = -new Date().getTimezoneOffset();
+	const remote = await APIFetch<PreferencesReadResp>('user/preferences', null, 'GET');
+	if (remote == undefined) return;
+	const { preference: current, version } = remote;
+	if (current.timezone == timezone && current.utc_offset == utc_offset) return;
+	// a conflict means another device wrote just now, this one retries on its next start
+	const body: PreferencesWriteReq = { version, set: { timezone, utc_offset } };
+	await RawAPIFetch('user/preferences', body, 'PATCH');
 }
 
 export async function init() {
@@ -62,6 +81,10 @@ export async function init() {
 
 export const submitOnEnter = derived(preference, (x) => x.submit_on_enter);
 
+export const voice = derived(preference, (x) => x.voice);
+
+export const voiceSpeed = derived(preference, (x) => x.voice_speed);
+
 export const theme = derived(preference, (x) => x.theme);
 
 export const locale = derived(preference, (x) => x.locale);
@@ -35,7 +35,6 @@ export function localState<T>(
 	return tokenStore;
 }
 
-export const token = localState<undefined | { value: string; expireAt: string; renewAt: string }>(
-	'token',
-	undefined
-);
+export const token = localState<
+	undefined | { value: string; expireAt: string; renewAt: string; refreshToken: string }
+>('token', undefined);
@@ -0,0 +1,21 @@
+import { writable } from 'svelte/store';
+
+/** Latest deletion that can be undone, the server forgets it after `UNDO_WINDOW` */
+let pendingUndo = writable<{
+	id: number;
+	token: string;
+	/** i18n key of the deleted thing */
+	label: string;
+} | null>(null);
+
+export function offerUndo(token: string, label: string) {
+	pendingUndo.update((prev) => ({ id: (prev?.id ?? 0) + 1, token, label }));
+}
+
+export function useUndo() {
+	return pendingUndo;
+}
+
+export function dismissUndo() {
+	pendingUndo.set(null);
+}